@@ -56,6 +56,24 @@ pub enum Command {
     Simplify(SimplifyArgs),
     Copy(CopyArgs),
     Verify(VerifyArgs),
+    /// Report style filter support and per-source-layer zoom coverage
+    /// without reading tiles.
+    StyleCheck(StyleCheckArgs),
+    /// Write every tile as `out_dir/z/x/y.mvt` for inspection with external tools.
+    Explode(ExplodeArgs),
+    /// Rebuild an MBTiles file from a `z/x/y.mvt` tree written by explode.
+    Implode(ImplodeArgs),
+    /// Write one tile as a GeoJSON FeatureCollection for GIS tools.
+    Export(ExportArgs),
+    /// Write the bytes of one tile to stdout or a file for debugging.
+    Get(GetArgs),
+    /// Copy a tileset, replacing one tile with the features of a GeoJSON file.
+    ImportTile(ImportTileArgs),
+    /// Serve a tileset with a MapLibre viewer on localhost for visual QA.
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+    /// Generate tiles above the max zoom from their ancestors at the max zoom.
+    Overzoom(OverzoomArgs),
 }
 
 #[derive(Debug, Args)]
@@ -63,6 +81,10 @@ pub struct InspectArgs {
     /// Path to an MBTiles or PMTiles file to inspect.
     pub input: PathBuf,
 
+    /// Input format (mbtiles/pmtiles) when the extension does not identify it.
+    #[arg(long)]
+    pub input_format: Option<String>,
+
     /// Threshold in bytes for size warnings in histogram averages.
     #[arg(long, default_value_t = 1_280_000)]
     pub max_tile_bytes: u64,
@@ -75,13 +97,41 @@ pub struct InspectArgs {
     #[arg(long)]
     pub topn: Option<u32>,
 
-    /// Sampling strategy: ratio (e.g. 0.1) or count (e.g. 1000).
+    /// Sampling strategy: ratio (e.g. 0.1), count (e.g. 1000), or a ratio
+    /// applied within each zoom (e.g. z:0.1).
     #[arg(long)]
     pub sample: Option<String>,
 
-    /// Output format (text/json/ndjson).
-    #[arg(long = "report-format", value_enum, default_value_t = ReportFormat::Text)]
-    pub output: ReportFormat,
+    /// Seed for --sample selection; a fixed default keeps repeated runs on the same tiles.
+    #[arg(long)]
+    pub sample_seed: Option<u64>,
+
+    /// Output format (text/json/ndjson/csv, and parquet when built with the
+    /// `parquet` feature).
+    #[arg(long = "report-format", value_enum, default_value_t = InspectReportFormat::Text)]
+    pub output: InspectReportFormat,
+
+    /// JSON: print the report as one minified line instead of indented.
+    #[arg(long, default_value_t = false, overrides_with = "pretty")]
+    pub compact: bool,
+
+    /// JSON: print the report indented (the default; overrides an earlier --compact).
+    #[arg(long, default_value_t = false, overrides_with = "compact")]
+    pub pretty: bool,
+
+    /// Text: print per-zoom total bytes as a sparkline under the zoom table.
+    #[arg(long, default_value_t = false)]
+    pub sparkline: bool,
+
+    /// CSV: emit only this table, without the leading section column
+    /// (requires --report-format csv).
+    #[arg(long, value_enum)]
+    pub csv_section: Option<CsvSection>,
+
+    /// Parquet: directory receiving tiles.parquet (the --list-tiles
+    /// listing) and layers.parquet (the layer list) [default: .].
+    #[arg(long, value_name = "DIR")]
+    pub parquet_dir: Option<PathBuf>,
 
     /// Limit output sections (comma-separated). See error output for allowed values.
     #[arg(long, num_args = 0..=1, default_missing_value = "")]
@@ -107,14 +157,22 @@ pub struct InspectArgs {
     #[arg(long)]
     pub bucket: Option<usize>,
 
-    /// Target tile in z/x/y form (requires --summary).
-    #[arg(long)]
-    pub tile: Option<String>,
+    /// Target tile in z/x/y form, or x/y ranges like 14/8700-8705/5450-5455 (repeatable; requires --summary).
+    #[arg(long, value_name = "Z/X/Y")]
+    pub tile: Vec<String>,
 
     /// Emit a tile summary (requires --tile).
     #[arg(long, default_value_t = false)]
     pub summary: bool,
 
+    /// Summarize every z/x/y listed in FILE (one per line), writing one JSON object per tile.
+    #[arg(long, value_name = "FILE", conflicts_with = "tile")]
+    pub tiles_from: Option<PathBuf>,
+
+    /// Viewer URL for listed tiles, e.g. https://example.com/{z}/{x}/{y}.pbf ({-y} = TMS row).
+    #[arg(long, value_name = "TEMPLATE")]
+    pub tile_url_template: Option<String>,
+
     /// Filter output to specific layers (comma-separated).
     #[arg(long, value_delimiter = ',', num_args = 1..)]
     pub layers: Vec<String>,
@@ -127,15 +185,34 @@ pub struct InspectArgs {
     #[arg(long, default_value_t = false)]
     pub recommend: bool,
 
+    /// Also recommend layers averaging more bytes than this per top tile (requires --recommend).
+    #[arg(long)]
+    pub recommend_max_layer_bytes: Option<u64>,
+
+    /// Also recommend layers averaging more features than this per top tile (requires --recommend).
+    #[arg(long)]
+    pub recommend_max_layer_features: Option<u64>,
+
     /// Fast defaults: sample=0.1, topn=5, histogram-buckets=10.
     #[arg(long, default_value_t = false)]
     pub fast: bool,
 
-    /// List tiles in the selected bucket (requires --bucket).
+    /// List tiles in the selected size range (--min-bytes/--max-bytes) or
+    /// histogram bucket (--bucket). With --report-format ndjson, tiles are
+    /// streamed as they are scanned and every tile is listed when neither is
+    /// given.
     #[arg(long, default_value_t = false)]
     pub list_tiles: bool,
 
-    /// Limit the number of tiles listed per bucket.
+    /// List only tiles of at least this many bytes (requires --list-tiles).
+    #[arg(long, conflicts_with = "bucket")]
+    pub min_bytes: Option<u64>,
+
+    /// List only tiles of at most this many bytes (requires --list-tiles).
+    #[arg(long, conflicts_with = "bucket")]
+    pub max_bytes: Option<u64>,
+
+    /// Limit the number of tiles listed per bucket (0 = unlimited when streaming).
     #[arg(long, default_value_t = 100)]
     pub limit: usize,
 
@@ -155,6 +232,127 @@ pub struct InspectArgs {
     #[arg(long, default_value_t = false)]
     pub include_layer_list: bool,
 
+    /// Count invalid geometries (ring orientation, vertex count, closure, self-intersection) per layer.
+    #[arg(long, default_value_t = false)]
+    pub check_geometry: bool,
+
+    /// Split each layer's bytes into geometry and attribute overhead by
+    /// re-encoding it without properties (slow; honors --sample).
+    #[arg(long, default_value_t = false)]
+    pub attributes_size: bool,
+
+    /// Attribute decompressed tile bytes to each layer (sum of its protobuf
+    /// message lengths), per zoom and as a share of the total (honors --sample).
+    #[arg(long, default_value_t = false)]
+    pub layer_bytes: bool,
+
+    /// Report p50/p90/p95/p99/p99.9 tile sizes per zoom (approximate when sampling).
+    #[arg(long, alias = "zoom-range-stats", default_value_t = false)]
+    pub percentiles: bool,
+
+    /// Report duplicate tile contents and bytes reclaimable by deduplication.
+    #[arg(long, default_value_t = false)]
+    pub dedup_stats: bool,
+
+    /// Count tiles per media type (mvt/png/jpeg/webp/avif) from their magic bytes.
+    #[arg(long, default_value_t = false)]
+    pub media_types: bool,
+
+    /// Cross-tabulate feature counts per layer and zoom (reads every scanned tile).
+    #[arg(long, default_value_t = false)]
+    pub layer_zoom_matrix: bool,
+
+    /// Style JSON to preview: mark each layer kept, dropped or kept only at
+    /// some zooms, with the bytes and features the style would remove
+    /// (implies --include-layer-list, --layer-bytes and --layer-zoom-matrix).
+    #[arg(long, value_name = "PATH", conflicts_with = "layers_only")]
+    pub style: Option<PathBuf>,
+
+    /// Style vector source whose layers are previewed; required when the
+    /// style has more than one vector source.
+    #[arg(long, requires = "style")]
+    pub source: Option<String>,
+
+    /// Report the most frequent values of this property key per layer (needs --include-layer-list).
+    #[arg(long, value_name = "KEY")]
+    pub property_histogram: Option<String>,
+
+    /// Flag property keys whose values are nearly all distinct (for example
+    /// IDs written into a name field) with their estimated bytes (needs --include-layer-list).
+    #[arg(long, default_value_t = false)]
+    pub detect_cardinality: bool,
+
+    /// Distinct-value ratio (distinct values / features with the key) above which
+    /// --detect-cardinality reports a key [default: 0.9].
+    #[arg(long, value_name = "RATIO")]
+    pub cardinality_threshold: Option<f64>,
+
+    /// Distinct values tracked exactly per layer and key by --detect-cardinality
+    /// (8 bytes each); larger counts are estimated [default: 4096].
+    #[arg(long, value_name = "N")]
+    pub cardinality_cap: Option<usize>,
+
+    /// Size basis for statistics: stored bytes or decoded (uncompressed) MVT bytes.
+    #[arg(long, value_enum, default_value_t = SizeMeasureArg::Stored)]
+    pub measure: SizeMeasureArg,
+
+    /// Digest decompressed tiles for --list-tiles output and a whole-tileset content hash.
+    #[arg(long, value_enum, default_value_t = HashArg::None)]
+    pub hash: HashArg,
+
+    /// Strictly decode every scanned tile and list the ones that fail (unknown geometry commands, truncated data); exits with an error if any tile fails or any row has an invalid tile address.
+    #[arg(long, alias = "strict", default_value_t = false)]
+    pub validate_mvt: bool,
+
+    /// Count tiles of at most N bytes as empty.
+    #[arg(long, value_name = "N", default_value_t = crate::mbtiles::EMPTY_TILE_MAX_BYTES)]
+    pub empty_threshold: u64,
+
+    /// Decode the tiles of at most --empty-threshold bytes and count only those without features as empty; the others are reported as small tiles.
+    #[arg(long, default_value_t = false)]
+    pub empty_exact: bool,
+
+    /// Leave tiles counted as empty (see --empty-threshold) out of the histograms so they do not crowd the first bucket; the empty tile count is still reported.
+    #[arg(long, default_value_t = false)]
+    pub exclude_empty: bool,
+
+    /// Build histograms from the sizes seen by the stats scan when the tileset has at most N tiles (8 bytes of memory per tile); larger tilesets are rescanned.
+    #[arg(long, value_name = "N", default_value_t = crate::mbtiles::DEFAULT_HISTOGRAM_CACHE_MAX_TILES)]
+    pub histogram_cache_max_tiles: u64,
+
+    /// Only list layers: skip the tile size scan, histograms and top tiles
+    /// (implies --include-layer-list; also used for `--stats layers`).
+    #[arg(long, default_value_t = false)]
+    pub layers_only: bool,
+
+    /// Print only the number of stored tiles (of the --zoom level when given) from a count query or the PMTiles header, without scanning tiles.
+    #[arg(long, default_value_t = false)]
+    pub count_only: bool,
+
+    /// Open the MBTiles input with SQLite's immutable=1: no locks are taken and the WAL is ignored, so a database locked by a live writer can still be read. Only safe for files that are not being written.
+    #[arg(long, default_value_t = false)]
+    pub immutable: bool,
+
+    /// Read MBTiles tiles from this table instead of `tiles` (with --zoom-column etc. for nonstandard column names).
+    #[arg(long, value_name = "TABLE")]
+    pub tile_table: Option<String>,
+
+    /// Zoom column of the MBTiles tile table (default: zoom_level).
+    #[arg(long, value_name = "COLUMN")]
+    pub zoom_column: Option<String>,
+
+    /// Tile column (x) column of the MBTiles tile table (default: tile_column).
+    #[arg(long, value_name = "COLUMN")]
+    pub x_column: Option<String>,
+
+    /// Tile row (TMS y) column of the MBTiles tile table (default: tile_row).
+    #[arg(long, value_name = "COLUMN")]
+    pub y_column: Option<String>,
+
+    /// Tile data column of the MBTiles tile table (default: tile_data).
+    #[arg(long, value_name = "COLUMN")]
+    pub data_column: Option<String>,
+
     /// Tile summary detail level (full or compact).
     #[arg(long, value_enum, default_value_t = TileInfoFormat::Full)]
     pub tile_info_format: TileInfoFormat,
@@ -177,8 +375,32 @@ pub struct OptimizeArgs {
     #[arg(long = "report-format", value_enum, default_value_t = ReportFormat::Text)]
     pub report_format: ReportFormat,
 
+    /// JSON: print the report as one minified line instead of indented.
+    #[arg(long, default_value_t = false, overrides_with = "pretty")]
+    pub compact: bool,
+
+    /// JSON: print the report indented (the default; overrides an earlier --compact).
+    #[arg(long, default_value_t = false, overrides_with = "compact")]
+    pub pretty: bool,
+
+    /// Style JSON path; repeat to keep the union of what every style needs.
     #[arg(long)]
-    pub style: Option<PathBuf>,
+    pub style: Vec<PathBuf>,
+
+    /// Style vector source whose layers are used; required when the style
+    /// has more than one vector source.
+    #[arg(long)]
+    pub source: Option<String>,
+
+    /// Keep only this source layer, at every zoom, instead of following a
+    /// style; repeatable.
+    #[arg(long = "keep-layer", value_name = "LAYER", conflicts_with = "style")]
+    pub keep_layer: Vec<String>,
+
+    /// Drop this source layer and keep every other one, instead of following
+    /// a style; repeatable.
+    #[arg(long = "drop-layer", value_name = "LAYER", conflicts_with = "style")]
+    pub drop_layer: Vec<String>,
 
     #[arg(long, value_enum, default_value_t = StyleMode::LayerFilter)]
     pub style_mode: StyleMode,
@@ -207,11 +429,115 @@ pub struct OptimizeArgs {
     #[arg(long, default_value_t = false)]
     pub drop_empty_tiles: bool,
 
+    /// Also drop tiles whose output is at most N bytes (implies --drop-empty-tiles).
+    #[arg(long, value_name = "N")]
+    pub drop_empty_threshold: Option<u64>,
+
+    /// Insert MBTiles rows in z/x/y or Hilbert tile id order so identical
+    /// runs write identical files; tiles are staged in a temp-dir SQLite
+    /// table of about the output size first (with --checkpoint, the tiles
+    /// table is reordered once the run completes instead).
+    #[arg(long, value_enum, default_value_t = OutputOrderArg::None)]
+    pub output_order: OutputOrderArg,
+
+    /// Stop with an error when a tile row has an invalid address (negative or
+    /// non-integer values, zoom beyond 30) instead of skipping it.
+    #[arg(long, default_value_t = false)]
+    pub strict: bool,
+
+    /// Reverse polygon rings whose orientation violates the MVT spec.
+    #[arg(long, default_value_t = false)]
+    pub fix_winding: bool,
+
+    /// Gzip level (0-9) for re-encoded gzip tiles.
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_GZIP_LEVEL)]
+    pub gzip_level: u32,
+
+    /// Output tile compression regardless of input: none, gzip[:0-9], or brotli[:0-11] (PMTiles only).
+    #[arg(long, value_name = "CODEC[:LEVEL]", conflicts_with = "gzip_level")]
+    pub recompress: Option<String>,
+
+    /// Brotli quality (0-11) for PMTiles directories, metadata, and brotli tiles
+    /// re-encoded in the input codec; higher is smaller but slower.
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_BROTLI_QUALITY)]
+    pub brotli_quality: u32,
+
+    /// Brotli window bits (10-24) for everything written with brotli to PMTiles.
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_BROTLI_WINDOW)]
+    pub brotli_window: u32,
+
+    /// Drop polygon features smaller than this area (square pixels of a 256px tile).
+    #[arg(long)]
+    pub drop_tiny_features: Option<f64>,
+
+    /// Merge point features closer than this distance (pixels of a 256px tile).
+    #[arg(long)]
+    pub merge_nearby_points: Option<f64>,
+
+    /// Source layers excluded from --drop-tiny-features/--merge-nearby-points
+    /// (style layers with "interactive": true are always excluded).
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    pub cleanup_exclude_layers: Vec<String>,
+
+    /// Feature ids in the output: keep input ids, drop them, or reindex each layer from 1.
+    #[arg(long, value_enum, default_value_t = FeatureIdsArg::Keep)]
+    pub feature_ids: FeatureIdsArg,
+
+    /// Store identical output tiles once (MBTiles map/images schema).
+    #[arg(long, default_value_t = false)]
+    pub dedup: bool,
+
     #[arg(long)]
     pub checkpoint: Option<PathBuf>,
 
     #[arg(long, default_value_t = false)]
     pub resume: bool,
+
+    /// Write a JSON run report (sizes, per-zoom counts, prune stats) to this path.
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+
+    /// Disable the progress bar.
+    #[arg(long, default_value_t = false)]
+    pub no_progress: bool,
+
+    /// Skip tiles at zooms outside every style layer's minzoom/maxzoom range.
+    #[arg(long, default_value_t = false)]
+    pub clamp_zoom: bool,
+
+    /// Add tiles to an existing output MBTiles, replacing tiles at the same
+    /// coordinates, instead of creating a new file.
+    #[arg(long, default_value_t = false)]
+    pub append: bool,
+
+    /// Drop feature properties that no style layer of the source layer reads
+    /// (filter, layout and paint expressions, `{key}` tokens).
+    #[arg(long, default_value_t = false)]
+    pub prune_attributes: bool,
+
+    /// Write source layer OLD as NEW; repeatable. Layers renamed to the same
+    /// name are merged into one output layer.
+    #[arg(long = "rename-layer", value_name = "OLD=NEW")]
+    pub rename_layer: Vec<String>,
+
+    /// Keep the input's minzoom/maxzoom/bounds/center instead of fitting
+    /// them to the tiles written.
+    #[arg(long, default_value_t = false)]
+    pub keep_original_metadata: bool,
+
+    /// Prune the input (or a --sample of it) without writing any output and
+    /// report the projected removed features and output size.
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Tiles pruned by --dry-run: ratio (0-1], count per zoom, or z:ratio
+    /// (at least one tile per zoom).
+    #[arg(long)]
+    pub sample: Option<String>,
+
+    /// Seed for --sample selection (default: a fixed seed).
+    #[arg(long)]
+    pub sample_seed: Option<u64>,
 }
 
 #[derive(Debug, Args)]
@@ -221,20 +547,55 @@ pub struct SimplifyArgs {
     #[arg(long)]
     pub output: Option<PathBuf>,
 
+    /// Input format (mbtiles/pmtiles) when the extension does not identify it.
     #[arg(long)]
-    pub z: u8,
+    pub input_format: Option<String>,
+
+    /// Zoom level. With --x/--y rewrites that single tile; alone, simplifies
+    /// every tile at this zoom.
+    #[arg(long, visible_alias = "zoom")]
+    pub z: Option<u8>,
 
     #[arg(long)]
-    pub x: u32,
+    pub x: Option<u32>,
 
     #[arg(long)]
-    pub y: u32,
+    pub y: Option<u32>,
 
     #[arg(long)]
     pub layer: Vec<String>,
 
+    /// Simplify tolerance in tile units at extent 4096 (scaled to each layer's extent).
     #[arg(long)]
     pub tolerance: Option<f64>,
+
+    /// Clamp simplified coordinates to the extent plus this buffer (units at extent 4096).
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_SIMPLIFY_BUFFER)]
+    pub buffer: u32,
+
+    /// Keep a ring's original coordinates when simplification would make it
+    /// self-intersect.
+    #[arg(long)]
+    pub preserve_topology: bool,
+
+    /// Batch: simplify matching tiles at every zoom (the default when
+    /// neither --zoom nor --over-limit-only is given).
+    #[arg(long)]
+    pub all_zooms: bool,
+
+    /// Batch: only simplify tiles larger than --max-tile-bytes.
+    #[arg(long)]
+    pub over_limit_only: bool,
+
+    #[arg(long, default_value_t = 1_280_000)]
+    pub max_tile_bytes: u64,
+
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// Batch: write a JSON run report (sizes, per-zoom counts, simplify stats) to this path.
+    #[arg(long)]
+    pub report: Option<PathBuf>,
 }
 
 #[derive(Debug, Args)]
@@ -249,6 +610,26 @@ pub struct CopyArgs {
 
     #[arg(long)]
     pub output_format: Option<String>,
+
+    /// Compression for PMTiles directories and metadata when writing PMTiles.
+    #[arg(long, value_enum, default_value_t = InternalCompressionArg::Brotli)]
+    pub internal_compression: InternalCompressionArg,
+
+    /// Re-encode tiles: none, gzip[:0-9], or brotli[:0-11] (PMTiles only).
+    #[arg(long, value_name = "CODEC[:LEVEL]")]
+    pub recompress: Option<String>,
+
+    /// Brotli quality (0-11) for PMTiles directories and metadata.
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_BROTLI_QUALITY)]
+    pub brotli_quality: u32,
+
+    /// Brotli window bits (10-24) for everything written with brotli to PMTiles.
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_BROTLI_WINDOW)]
+    pub brotli_window: u32,
+
+    /// Row convention of the MBTiles side when converting to or from PMTiles.
+    #[arg(long, value_enum, default_value_t = TileSchemeArg::Tms)]
+    pub scheme: TileSchemeArg,
 }
 
 #[derive(Debug, Args)]
@@ -256,6 +637,183 @@ pub struct VerifyArgs {
     pub input: PathBuf,
 }
 
+#[derive(Debug, Args)]
+pub struct ExplodeArgs {
+    pub input: PathBuf,
+
+    #[arg(long = "output-dir")]
+    pub out_dir: PathBuf,
+
+    #[arg(long)]
+    pub input_format: Option<String>,
+
+    /// Keep tiles gzip-compressed and write them as `y.mvt.gz`.
+    #[arg(long, default_value_t = false)]
+    pub gzip: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ImplodeArgs {
+    pub in_dir: PathBuf,
+
+    #[arg(long)]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    pub input: PathBuf,
+
+    /// Tile to export in z/x/y (XYZ) form.
+    #[arg(long, value_name = "Z/X/Y")]
+    pub tile: String,
+
+    /// Only export these layers (comma-separated).
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    pub layers: Vec<String>,
+
+    /// GeoJSON file to write.
+    #[arg(long)]
+    pub output: PathBuf,
+
+    #[arg(long)]
+    pub input_format: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct GetArgs {
+    pub input: PathBuf,
+
+    /// Tile to read in z/x/y (XYZ) form.
+    #[arg(value_name = "Z/X/Y")]
+    pub tile: String,
+
+    /// Write the bytes as stored, without decompressing them.
+    #[arg(long)]
+    pub raw: bool,
+
+    /// File to write instead of stdout.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    #[arg(long)]
+    pub input_format: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportTileArgs {
+    pub input: PathBuf,
+
+    /// Tile to replace in z/x/y (XYZ) form.
+    #[arg(long, value_name = "Z/X/Y")]
+    pub tile: String,
+
+    /// GeoJSON FeatureCollection to encode (WGS84).
+    #[arg(long)]
+    pub geojson: PathBuf,
+
+    /// Tileset to write; every other tile is copied unchanged.
+    #[arg(long)]
+    pub output: PathBuf,
+
+    /// Layer of features without a `layer` property.
+    #[arg(long)]
+    pub layer: Option<String>,
+
+    /// Extent of the imported layers (default: the replaced tile's layer
+    /// extent, else 4096).
+    #[arg(long)]
+    pub extent: Option<u32>,
+
+    /// Buffer around the tile that features may reach into (units at extent 4096).
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_SIMPLIFY_BUFFER)]
+    pub buffer: u32,
+
+    /// Clamp features reaching beyond the buffer, or fail on them.
+    #[arg(long, value_enum, default_value_t = OutsideTileArg::Clip)]
+    pub outside: OutsideTileArg,
+
+    #[arg(long)]
+    pub input_format: Option<String>,
+}
+
+#[cfg(feature = "serve")]
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    pub input: PathBuf,
+
+    /// Port to listen on (127.0.0.1).
+    #[arg(long, default_value_t = crate::serve::DEFAULT_SERVE_PORT)]
+    pub port: u16,
+
+    /// Style JSON to view the tiles with; its vector source is pointed at the
+    /// served tiles. Without it every layer is drawn with a debug style.
+    #[arg(long)]
+    pub style: Option<PathBuf>,
+
+    /// Style vector source to replace; required when the style has more
+    /// than one vector source.
+    #[arg(long)]
+    pub source: Option<String>,
+
+    #[arg(long)]
+    pub input_format: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct OverzoomArgs {
+    pub input: PathBuf,
+
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Highest zoom to generate; every zoom above the tileset max zoom up
+    /// to this one is filled.
+    #[arg(long)]
+    pub target_zoom: u8,
+
+    /// Clip generated geometries to the tile plus this buffer (units at extent 4096).
+    #[arg(long, default_value_t = crate::mbtiles::DEFAULT_SIMPLIFY_BUFFER)]
+    pub buffer: u32,
+
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// Hide the progress bar.
+    #[arg(long, default_value_t = false)]
+    pub no_progress: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct StyleCheckArgs {
+    #[arg(long)]
+    pub style: PathBuf,
+
+    /// Style vector source to check; required when the style has more than
+    /// one vector source.
+    #[arg(long)]
+    pub source: Option<String>,
+
+    /// Exit with an error when any filter uses unsupported expressions.
+    #[arg(long, default_value_t = false)]
+    pub strict: bool,
+
+    /// Highest zoom checked for source-layer coverage.
+    #[arg(long, default_value_t = 22)]
+    pub max_zoom: u8,
+
+    #[arg(long = "report-format", value_enum, default_value_t = ReportFormat::Text)]
+    pub report_format: ReportFormat,
+
+    /// JSON: print the report as one minified line instead of indented.
+    #[arg(long, default_value_t = false, overrides_with = "pretty")]
+    pub compact: bool,
+
+    /// JSON: print the report indented (the default; overrides an earlier --compact).
+    #[arg(long, default_value_t = false, overrides_with = "compact")]
+    pub pretty: bool,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum StyleMode {
     Layer,
@@ -271,6 +829,7 @@ pub enum TileInfoFormat {
     Compact,
 }
 
+/// `--report-format` of `optimize` and `style-check`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum ReportFormat {
     Text,
@@ -278,14 +837,82 @@ pub enum ReportFormat {
     Ndjson,
 }
 
+/// `--report-format` of `inspect`, which can also write CSV sections and
+/// Parquet files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InspectReportFormat {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    // Tile and layer lists as Parquet files.
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CsvSection {
+    Zoom,
+    Histogram,
+    Layers,
+    #[value(name = "top_tiles")]
+    TopTiles,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InternalCompressionArg {
+    None,
+    Gzip,
+    Brotli,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TileSchemeArg {
+    Xyz,
+    Tms,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum TileSortArg {
     Size,
     Zxy,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputOrderArg {
+    None,
+    Zxy,
+    Hilbert,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HashArg {
+    None,
+    Sha256,
+    Xxh3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SizeMeasureArg {
+    Stored,
+    Uncompressed,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum UnknownFilterMode {
     Keep,
     Drop,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutsideTileArg {
+    Clip,
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FeatureIdsArg {
+    Keep,
+    Drop,
+    Reindex,
+}
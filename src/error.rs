@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::mbtiles::TileCoord;
+
+/// Error returned by the public inspect, prune and simplify functions so
+/// library callers can match on the failure kind. The rest of the crate works
+/// with `anyhow`; typed failures raised inside it are recovered when the error
+/// crosses the library boundary (see the `From<anyhow::Error>` impl).
+#[derive(Debug)]
+pub enum VtError {
+    /// The path or file is not an MBTiles database.
+    NotMbtiles { reason: String },
+    /// The path or file is not a PMTiles archive (wrong extension or magic).
+    NotPmtiles { reason: String },
+    /// The requested tile is not stored. `y` is the row as stored in the
+    /// container (TMS for MBTiles).
+    TileNotFound(TileCoord),
+    /// A stored tile could not be decoded or rewritten.
+    DecodeTile { coord: TileCoord, message: String },
+    /// SQLite failure; `context` says what was being done (empty when
+    /// unknown).
+    Sqlite {
+        context: String,
+        source: rusqlite::Error,
+    },
+    /// I/O failure; `context` says what was being done (empty when unknown).
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    /// Any other failure, with its context chain.
+    Other(anyhow::Error),
+}
+
+impl VtError {
+    pub(crate) fn decode_tile(coord: TileCoord, err: impl Into<anyhow::Error>) -> Self {
+        Self::DecodeTile {
+            coord,
+            message: format!("{:#}", err.into()),
+        }
+    }
+}
+
+impl fmt::Display for VtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotMbtiles { reason } | Self::NotPmtiles { reason } => f.write_str(reason),
+            Self::TileNotFound(coord) => write!(
+                f,
+                "tile not found: z={} x={} y={}",
+                coord.zoom, coord.x, coord.y
+            ),
+            Self::DecodeTile { coord, message } => write!(
+                f,
+                "failed to decode tile {}/{}/{}: {message}",
+                coord.zoom, coord.x, coord.y
+            ),
+            Self::Sqlite { context, source } if context.is_empty() => write!(f, "{source}"),
+            Self::Io { context, source } if context.is_empty() => write!(f, "{source}"),
+            Self::Sqlite { context, .. } | Self::Io { context, .. } => f.write_str(context),
+            Self::Other(err) if f.alternate() => write!(f, "{err:#}"),
+            Self::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for VtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Sqlite { context, source } if !context.is_empty() => Some(source),
+            Self::Io { context, source } if !context.is_empty() => Some(source),
+            Self::Other(err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Context messages added before the first error of type `E` in the chain,
+/// or `None` when the chain holds no `E`.
+fn context_before<E: std::error::Error + 'static>(err: &anyhow::Error) -> Option<String> {
+    let position = err.chain().position(|cause| cause.is::<E>())?;
+    Some(
+        err.chain()
+            .take(position)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(": "),
+    )
+}
+
+impl From<anyhow::Error> for VtError {
+    fn from(err: anyhow::Error) -> Self {
+        let mut err = match err.downcast::<VtError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        if let Some(context) = context_before::<rusqlite::Error>(&err) {
+            match err.downcast::<rusqlite::Error>() {
+                Ok(source) => return Self::Sqlite { context, source },
+                Err(other) => err = other,
+            }
+        }
+        if let Some(context) = context_before::<std::io::Error>(&err) {
+            match err.downcast::<std::io::Error>() {
+                Ok(source) => return Self::Io { context, source },
+                Err(other) => err = other,
+            }
+        }
+        Self::Other(err)
+    }
+}
+
+impl From<rusqlite::Error> for VtError {
+    fn from(source: rusqlite::Error) -> Self {
+        Self::Sqlite {
+            context: String::new(),
+            source,
+        }
+    }
+}
+
+impl From<std::io::Error> for VtError {
+    fn from(source: std::io::Error) -> Self {
+        Self::Io {
+            context: String::new(),
+            source,
+        }
+    }
+}
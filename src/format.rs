@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{Result, bail};
+use serde::Serialize;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TileFormat {
@@ -10,7 +11,16 @@ pub enum TileFormat {
 
 impl TileFormat {
     pub fn from_extension(path: &Path) -> Option<Self> {
-        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        let ext = match remote_url(path) {
+            Some(url) => {
+                let url_path = url.split(['?', '#']).next().unwrap_or(url);
+                Path::new(url_path)
+                    .extension()?
+                    .to_str()?
+                    .to_ascii_lowercase()
+            }
+            None => path.extension()?.to_str()?.to_ascii_lowercase(),
+        };
         match ext.as_str() {
             "mbtiles" => Some(TileFormat::Mbtiles),
             "pmtiles" => Some(TileFormat::Pmtiles),
@@ -35,24 +45,174 @@ impl TileFormat {
     }
 }
 
+/// Image format of a raster tileset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RasterFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+}
+
+impl RasterFormat {
+    /// Parse the MBTiles metadata `format` value (`png`, `jpg`, `webp`, `avif`).
+    pub fn from_metadata(format: &str) -> Option<Self> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "png" => Some(RasterFormat::Png),
+            "jpg" | "jpeg" => Some(RasterFormat::Jpeg),
+            "webp" => Some(RasterFormat::Webp),
+            "avif" => Some(RasterFormat::Avif),
+            _ => None,
+        }
+    }
+
+    /// Detect the format from the magic bytes at the start of a tile.
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(RasterFormat::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(RasterFormat::Jpeg)
+        } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+            Some(RasterFormat::Webp)
+        } else if bytes.len() >= 12
+            && &bytes[4..8] == b"ftyp"
+            && matches!(&bytes[8..12], b"avif" | b"avis")
+        {
+            Some(RasterFormat::Avif)
+        } else {
+            None
+        }
+    }
+
+    /// Map a PMTiles header `tile_type` (2 = png, 3 = jpeg, 4 = webp, 5 = avif).
+    pub fn from_pmtiles_tile_type(tile_type: u8) -> Option<Self> {
+        match tile_type {
+            2 => Some(RasterFormat::Png),
+            3 => Some(RasterFormat::Jpeg),
+            4 => Some(RasterFormat::Webp),
+            5 => Some(RasterFormat::Avif),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RasterFormat::Png => "png",
+            RasterFormat::Jpeg => "jpeg",
+            RasterFormat::Webp => "webp",
+            RasterFormat::Avif => "avif",
+        }
+    }
+}
+
+/// Content of a single tile, told apart by its leading bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TileMediaType {
+    Mvt,
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+    Unknown,
+}
+
+impl TileMediaType {
+    /// Map a PMTiles header `tile_type`, which applies to every tile.
+    pub fn from_pmtiles_tile_type(tile_type: u8) -> Self {
+        match tile_type {
+            1 => TileMediaType::Mvt,
+            tile_type => RasterFormat::from_pmtiles_tile_type(tile_type)
+                .map_or(TileMediaType::Unknown, TileMediaType::from),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TileMediaType::Mvt => "mvt",
+            TileMediaType::Png => "png",
+            TileMediaType::Jpeg => "jpeg",
+            TileMediaType::Webp => "webp",
+            TileMediaType::Avif => "avif",
+            TileMediaType::Unknown => "unknown",
+        }
+    }
+}
+
+impl From<RasterFormat> for TileMediaType {
+    fn from(format: RasterFormat) -> Self {
+        match format {
+            RasterFormat::Png => TileMediaType::Png,
+            RasterFormat::Jpeg => TileMediaType::Jpeg,
+            RasterFormat::Webp => TileMediaType::Webp,
+            RasterFormat::Avif => TileMediaType::Avif,
+        }
+    }
+}
+
+/// Detect what a stored tile holds from its magic bytes. Gzip data and
+/// protobuf starting with a `layers` field (tag `0x1a`) count as MVT, as does
+/// an empty tile (a vector tile without layers); other codecs such as brotli
+/// carry no magic and are `Unknown` until decompressed.
+pub fn detect_tile_media_type(bytes: &[u8]) -> TileMediaType {
+    if let Some(format) = RasterFormat::from_magic(bytes) {
+        return format.into();
+    }
+    if bytes.is_empty() || bytes.starts_with(&[0x1f, 0x8b]) || bytes[0] == 0x1a {
+        TileMediaType::Mvt
+    } else {
+        TileMediaType::Unknown
+    }
+}
+
+/// Returns the URL when the input refers to an `http(s)://` resource.
+pub fn remote_url(path: &Path) -> Option<&str> {
+    let text = path.to_str()?;
+    let lower = text.get(..8)?.to_ascii_lowercase();
+    if lower.starts_with("http://") || lower == "https://" {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+pub fn is_remote_input(path: &Path) -> bool {
+    remote_url(path).is_some()
+}
+
+fn ensure_local_input(input_path: &Path) -> Result<()> {
+    if is_remote_input(input_path) {
+        bail!("remote inputs are only supported by inspect (PMTiles)");
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FormatDecision {
     pub input: TileFormat,
     pub output: TileFormat,
 }
 
+/// Format of an input path: `input_format` when given, otherwise inferred
+/// from the extension.
+pub fn resolve_input_format(input_path: &Path, input_format: Option<&str>) -> Result<TileFormat> {
+    if let Some(name) = input_format {
+        TileFormat::from_str(name).ok_or_else(|| anyhow::anyhow!("unknown input format: {name}"))
+    } else {
+        TileFormat::from_extension(input_path).ok_or_else(|| {
+            anyhow::anyhow!("cannot infer input format from path (use --input-format)")
+        })
+    }
+}
+
 pub fn decide_formats(
     input_path: &Path,
     output_path: Option<&Path>,
     input_format: Option<&str>,
     output_format: Option<&str>,
 ) -> Result<FormatDecision> {
-    let input = if let Some(name) = input_format {
-        TileFormat::from_str(name).ok_or_else(|| anyhow::anyhow!("unknown input format: {name}"))?
-    } else {
-        TileFormat::from_extension(input_path)
-            .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?
-    };
+    let input = resolve_input_format(input_path, input_format)?;
 
     let output = if let Some(name) = output_format {
         TileFormat::from_str(name)
@@ -101,6 +261,7 @@ pub fn plan_copy(
     input_format: Option<&str>,
     output_format: Option<&str>,
 ) -> Result<FormatDecision> {
+    ensure_local_input(input_path)?;
     validate_output_format_matches_path(output_path, output_format)?;
     decide_formats(input_path, output_path, input_format, output_format)
 }
@@ -111,6 +272,7 @@ pub fn plan_optimize(
     input_format: Option<&str>,
     output_format: Option<&str>,
 ) -> Result<FormatDecision> {
+    ensure_local_input(input_path)?;
     validate_output_format_matches_path(output_path, output_format)?;
     decide_formats(input_path, output_path, input_format, output_format)
 }
@@ -0,0 +1,557 @@
+use anyhow::{Context, Result};
+use geo_types::{Coord, Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Polygon};
+use mvt::Tile;
+use mvt_reader::Reader;
+use mvt_reader::feature::Value as MvtValue;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use crate::mbtiles::{
+    DEFAULT_GZIP_LEVEL, DEFAULT_SIMPLIFY_BUFFER, LayerEncoder, MbtilesFile,
+    SIMPLIFY_REFERENCE_EXTENT, TileCoord, clamp_geometry, encode_geometry, encode_tile_payload,
+    ensure_vector_tileset, flip_y, replace_mbtiles_tile, ring_signed_area,
+};
+use crate::pmtiles::{PmtilesFile, encode_tile_payload_pmtiles, replace_pmtiles_tile};
+use crate::source::TileSource;
+
+/// Extent of imported layers that the replaced tile does not have.
+pub const DEFAULT_IMPORT_EXTENT: u32 = 4096;
+
+/// What importing does with features reaching beyond the tile buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutsideTileMode {
+    /// Clamp line and polygon coordinates to the buffer; drop points beyond it.
+    #[default]
+    Clip,
+    /// Fail on the first such feature.
+    Reject,
+}
+
+/// Options for importing a GeoJSON FeatureCollection into one tile.
+#[derive(Debug, Clone)]
+pub struct GeojsonImportOptions {
+    /// Layer of features without a `layer` property.
+    pub layer: Option<String>,
+    /// Extent of every imported layer. By default each layer keeps the extent
+    /// it has in the replaced tile, and new layers use 4096.
+    pub extent: Option<u32>,
+    /// Buffer around the tile in units at extent 4096.
+    pub buffer: u32,
+    pub outside: OutsideTileMode,
+}
+
+impl Default for GeojsonImportOptions {
+    fn default() -> Self {
+        Self {
+            layer: None,
+            extent: None,
+            buffer: DEFAULT_SIMPLIFY_BUFFER,
+            outside: OutsideTileMode::default(),
+        }
+    }
+}
+
+/// Write tile `coord` (XYZ) of an MBTiles file to `output` as a GeoJSON
+/// FeatureCollection. Returns the number of features written.
+pub fn export_mbtiles_geojson(
+    input: &Path,
+    coord: TileCoord,
+    layers: &[String],
+    output: &Path,
+) -> Result<u64> {
+    let source = MbtilesFile::open(input)?;
+    // MBTiles rows are TMS
+    let row = TileCoord {
+        y: flip_y(coord.zoom, coord.y),
+        ..coord
+    };
+    export_tile_source_geojson(&source, coord, row, layers, output)
+}
+
+/// PMTiles counterpart of [`export_mbtiles_geojson`].
+pub fn export_pmtiles_geojson(
+    input: &Path,
+    coord: TileCoord,
+    layers: &[String],
+    output: &Path,
+) -> Result<u64> {
+    let source = PmtilesFile::open(input)?;
+    export_tile_source_geojson(&source, coord, coord, layers, output)
+}
+
+fn export_tile_source_geojson<S: TileSource + ?Sized>(
+    source: &S,
+    coord: TileCoord,
+    stored: TileCoord,
+    layers: &[String],
+    output: &Path,
+) -> Result<u64> {
+    let data = source
+        .tile_data(stored)?
+        .with_context(|| format!("tile {}/{}/{} not found", coord.zoom, coord.x, coord.y))?;
+    let payload = source.decode_tile(&data)?;
+    let collection = tile_to_geojson(&payload, coord, layers)?;
+    let features = collection["features"].as_array().map_or(0, Vec::len) as u64;
+    let json = serde_json::to_vec_pretty(&collection).context("serialize geojson")?;
+    fs::write(output, json)
+        .with_context(|| format!("failed to write geojson: {}", output.display()))?;
+    Ok(features)
+}
+
+/// Copy an MBTiles file to `output`, replacing tile `coord` (XYZ) with the
+/// features of a GeoJSON file (see [`geojson_to_tile`]). The new tile keeps
+/// the gzip compression of the one it replaces. Returns the number of
+/// features written.
+pub fn import_mbtiles_geojson(
+    input: &Path,
+    coord: TileCoord,
+    geojson: &Path,
+    output: &Path,
+    options: &GeojsonImportOptions,
+) -> Result<u64> {
+    let source = MbtilesFile::open(input)?;
+    ensure_vector_tileset(&source)?;
+    // MBTiles rows are TMS
+    let row = TileCoord {
+        y: flip_y(coord.zoom, coord.y),
+        ..coord
+    };
+    let data = stored_tile(&source, coord, row)?;
+    let (payload, features) = import_tile_payload(&source, &data, coord, geojson, options)?;
+    let gzip = data.starts_with(&[0x1f, 0x8b]);
+    let data = encode_tile_payload(&payload, gzip, DEFAULT_GZIP_LEVEL)?;
+    replace_mbtiles_tile(input, output, row, data)?;
+    Ok(features)
+}
+
+/// PMTiles counterpart of [`import_mbtiles_geojson`]; the tile is encoded
+/// with the archive's tile compression.
+pub fn import_pmtiles_geojson(
+    input: &Path,
+    coord: TileCoord,
+    geojson: &Path,
+    output: &Path,
+    options: &GeojsonImportOptions,
+) -> Result<u64> {
+    let source = PmtilesFile::open(input)?;
+    ensure_vector_tileset(&source)?;
+    let data = stored_tile(&source, coord, coord)?;
+    let (payload, features) = import_tile_payload(&source, &data, coord, geojson, options)?;
+    let data = encode_tile_payload_pmtiles(
+        &payload,
+        source.header().tile_compression,
+        DEFAULT_GZIP_LEVEL,
+    )?;
+    replace_pmtiles_tile(input, output, coord, &data)?;
+    Ok(features)
+}
+
+fn stored_tile<S: TileSource + ?Sized>(
+    source: &S,
+    coord: TileCoord,
+    stored: TileCoord,
+) -> Result<Vec<u8>> {
+    source
+        .tile_data(stored)?
+        .with_context(|| format!("tile {}/{}/{} not found", coord.zoom, coord.x, coord.y))
+}
+
+fn import_tile_payload<S: TileSource + ?Sized>(
+    source: &S,
+    data: &[u8],
+    coord: TileCoord,
+    geojson: &Path,
+    options: &GeojsonImportOptions,
+) -> Result<(Vec<u8>, u64)> {
+    let reader = Reader::new(source.decode_tile(data)?)
+        .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+    let layer_extents = reader
+        .get_layer_metadata()
+        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?
+        .into_iter()
+        .map(|layer| (layer.name, layer.extent))
+        .collect();
+    let text = fs::read(geojson)
+        .with_context(|| format!("failed to read geojson: {}", geojson.display()))?;
+    let collection: Value = serde_json::from_slice(&text)
+        .with_context(|| format!("failed to parse geojson: {}", geojson.display()))?;
+    geojson_to_tile(&collection, coord, &layer_extents, options)
+}
+
+/// Encode a GeoJSON FeatureCollection in WGS84 as the raw MVT payload of
+/// tile `coord` (XYZ). Returns the payload and the number of features in it.
+///
+/// Features go to the layer named by their `layer` property (which is not
+/// kept as a tag), else to `options.layer`; layers are written in order of
+/// first appearance with the extent from `layer_extents` unless
+/// `options.extent` is set. Coordinates are rounded to the extent grid and
+/// polygon rings are oriented as MVT requires. Numeric `id`s are kept, and
+/// array or object properties are stored as JSON strings.
+pub fn geojson_to_tile(
+    collection: &Value,
+    coord: TileCoord,
+    layer_extents: &HashMap<String, u32>,
+    options: &GeojsonImportOptions,
+) -> Result<(Vec<u8>, u64)> {
+    if collection.get("type").and_then(Value::as_str) != Some("FeatureCollection") {
+        anyhow::bail!("expected a GeoJSON FeatureCollection");
+    }
+    let features = collection
+        .get("features")
+        .and_then(Value::as_array)
+        .context("FeatureCollection has no features array")?;
+
+    let mut layers: Vec<(String, u32, Option<mvt::Layer>)> = Vec::new();
+    let mut written = 0u64;
+    for (index, feature) in features.iter().enumerate() {
+        let properties = feature.get("properties").and_then(Value::as_object);
+        let Some(name) = properties
+            .and_then(|properties| properties.get("layer"))
+            .and_then(Value::as_str)
+            .or(options.layer.as_deref())
+        else {
+            anyhow::bail!("feature {index}: no `layer` property and no default layer");
+        };
+        let extent = options
+            .extent
+            .or_else(|| layer_extents.get(name).copied())
+            .unwrap_or(DEFAULT_IMPORT_EXTENT);
+        let geometry = feature
+            .get("geometry")
+            .filter(|geometry| !geometry.is_null())
+            .with_context(|| format!("feature {index}: missing geometry"))?;
+        let geometry = TileProjection::new(coord, extent)
+            .geometry(geometry)
+            .with_context(|| format!("feature {index}"))?;
+        let Some(geometry) =
+            fit_to_tile(geometry, extent, options).with_context(|| format!("feature {index}"))?
+        else {
+            continue;
+        };
+
+        let slot = match layers.iter().position(|(layer, ..)| layer == name) {
+            Some(slot) => slot,
+            None => {
+                let layer = Tile::new(extent).create_layer(name);
+                layers.push((name.to_string(), extent, Some(layer)));
+                layers.len() - 1
+            }
+        };
+        let layer = layers[slot].2.take().expect("layer builder");
+        let mut builder = layer.into_feature(encode_geometry(&geometry)?);
+        if let Some(id) = feature.get("id").and_then(Value::as_u64) {
+            builder.set_id(id);
+        }
+        for (key, value) in properties.into_iter().flatten() {
+            if key == "layer" {
+                continue;
+            }
+            match value {
+                Value::Null => {}
+                Value::Bool(val) => builder.add_tag_bool(key, *val),
+                Value::String(text) => builder.add_tag_string(key, text),
+                Value::Number(number) => {
+                    if let Some(val) = number.as_u64() {
+                        builder.add_tag_uint(key, val);
+                    } else if let Some(val) = number.as_i64() {
+                        builder.add_tag_sint(key, val);
+                    } else if let Some(val) = number.as_f64() {
+                        builder.add_tag_double(key, val);
+                    }
+                }
+                Value::Array(_) | Value::Object(_) => {
+                    builder.add_tag_string(key, &value.to_string());
+                }
+            }
+        }
+        layers[slot].2 = Some(builder.into_layer());
+        written += 1;
+    }
+
+    let mut encoder = LayerEncoder::default();
+    for (_, extent, layer) in layers {
+        encoder.add_layer(layer.expect("layer builder"), extent)?;
+    }
+    Ok((encoder.bytes, written))
+}
+
+/// Applies `options.outside` to a projected geometry; `None` drops it.
+fn fit_to_tile(
+    geometry: Geometry<f32>,
+    extent: u32,
+    options: &GeojsonImportOptions,
+) -> Result<Option<Geometry<f32>>> {
+    let buffer = (options.buffer as f32 * extent as f32 / SIMPLIFY_REFERENCE_EXTENT as f32).round();
+    let (min, max) = (-buffer, extent as f32 + buffer);
+    let clamped = clamp_geometry(&geometry, min, max);
+    if clamped == geometry {
+        return Ok(Some(geometry));
+    }
+    if options.outside == OutsideTileMode::Reject {
+        anyhow::bail!("geometry extends beyond the tile buffer");
+    }
+    let inside = |point: &geo_types::Point<f32>| {
+        (min..=max).contains(&point.x()) && (min..=max).contains(&point.y())
+    };
+    Ok(match geometry {
+        Geometry::Point(point) => inside(&point).then_some(Geometry::Point(point)),
+        Geometry::MultiPoint(points) => {
+            let points: Vec<_> = points.0.into_iter().filter(inside).collect();
+            (!points.is_empty()).then_some(Geometry::MultiPoint(MultiPoint(points)))
+        }
+        _ => Some(clamped),
+    })
+}
+
+/// Convert a raw MVT payload of tile `coord` (XYZ) into one GeoJSON
+/// FeatureCollection in WGS84.
+///
+/// Each feature carries its tile properties plus a `layer` property naming
+/// its source layer (replacing a tile property of the same name). Only
+/// `layers` are exported when it is not empty. Polygon rings follow
+/// RFC 7946: exteriors counterclockwise, holes clockwise.
+pub fn tile_to_geojson(payload: &[u8], coord: TileCoord, layers: &[String]) -> Result<Value> {
+    let reader = Reader::new(payload.to_vec())
+        .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+    let metadata = reader
+        .get_layer_metadata()
+        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+    let mut features = Vec::new();
+    for layer in metadata {
+        if !layers.is_empty() && !layers.contains(&layer.name) {
+            continue;
+        }
+        let project = TileProjection::new(coord, layer.extent);
+        let layer_features = reader
+            .get_features(layer.layer_index)
+            .map_err(|err| anyhow::anyhow!("layer {}: read features: {err}", layer.name))?;
+        for feature in layer_features {
+            let mut properties: Map<String, Value> = feature
+                .properties
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(key, value)| (key, property_value(value)))
+                .collect();
+            properties.insert("layer".to_string(), json!(layer.name));
+            let mut object = Map::new();
+            object.insert("type".to_string(), json!("Feature"));
+            if let Some(id) = feature.id {
+                object.insert("id".to_string(), json!(id));
+            }
+            object.insert(
+                "geometry".to_string(),
+                geometry_json(&feature.geometry, &project),
+            );
+            object.insert("properties".to_string(), Value::Object(properties));
+            features.push(Value::Object(object));
+        }
+    }
+    Ok(json!({
+        "type": "FeatureCollection",
+        "features": features,
+    }))
+}
+
+/// Maps tile-local coordinates to lon/lat through the tile's web mercator bounds.
+struct TileProjection {
+    x: f64,
+    y: f64,
+    tiles: f64,
+    extent: f64,
+}
+
+impl TileProjection {
+    fn new(coord: TileCoord, extent: u32) -> Self {
+        Self {
+            x: f64::from(coord.x),
+            y: f64::from(coord.y),
+            tiles: (1u64 << coord.zoom) as f64,
+            extent: f64::from(extent.max(1)),
+        }
+    }
+
+    /// Inverse of [`TileProjection::lon_lat`], rounded to the extent grid.
+    fn tile_coord(&self, position: &Value) -> Result<Coord<f32>> {
+        let values = position
+            .as_array()
+            .filter(|values| values.len() >= 2)
+            .context("position needs at least two numbers")?;
+        let (Some(lon), Some(lat)) = (values[0].as_f64(), values[1].as_f64()) else {
+            anyhow::bail!("position needs at least two numbers");
+        };
+        let x = ((lon + 180.0) / 360.0 * self.tiles - self.x) * self.extent;
+        let mercator = lat
+            .clamp(-85.051_128_78, 85.051_128_78)
+            .to_radians()
+            .tan()
+            .asinh();
+        let y = ((1.0 - mercator / PI) / 2.0 * self.tiles - self.y) * self.extent;
+        Ok(Coord {
+            x: x.round() as f32,
+            y: y.round() as f32,
+        })
+    }
+
+    fn tile_line(&self, positions: &Value) -> Result<LineString<f32>> {
+        positions
+            .as_array()
+            .context("expected an array of positions")?
+            .iter()
+            .map(|position| self.tile_coord(position))
+            .collect()
+    }
+
+    /// Exterior rings get positive area in tile coordinates, holes negative.
+    fn tile_polygon(&self, rings: &Value) -> Result<Polygon<f32>> {
+        let mut rings = rings
+            .as_array()
+            .context("expected an array of rings")?
+            .iter()
+            .enumerate()
+            .map(|(index, ring)| {
+                let mut ring = self.tile_line(ring)?;
+                ring.close();
+                if (ring_signed_area(&ring.0) > 0.0) != (index == 0) {
+                    ring.0.reverse();
+                }
+                Ok(ring)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if rings.is_empty() {
+            anyhow::bail!("polygon without rings");
+        }
+        let exterior = rings.remove(0);
+        Ok(Polygon::new(exterior, rings))
+    }
+
+    fn geometry(&self, geometry: &Value) -> Result<Geometry<f32>> {
+        let coordinates = geometry
+            .get("coordinates")
+            .context("geometry has no coordinates")?;
+        let members = || {
+            coordinates
+                .as_array()
+                .context("expected an array of coordinates")
+        };
+        Ok(match geometry.get("type").and_then(Value::as_str) {
+            Some("Point") => Geometry::Point(self.tile_coord(coordinates)?.into()),
+            Some("MultiPoint") => Geometry::MultiPoint(MultiPoint(
+                members()?
+                    .iter()
+                    .map(|position| self.tile_coord(position).map(Into::into))
+                    .collect::<Result<_>>()?,
+            )),
+            Some("LineString") => Geometry::LineString(self.tile_line(coordinates)?),
+            Some("MultiLineString") => Geometry::MultiLineString(MultiLineString(
+                members()?
+                    .iter()
+                    .map(|line| self.tile_line(line))
+                    .collect::<Result<_>>()?,
+            )),
+            Some("Polygon") => Geometry::Polygon(self.tile_polygon(coordinates)?),
+            Some("MultiPolygon") => Geometry::MultiPolygon(MultiPolygon(
+                members()?
+                    .iter()
+                    .map(|polygon| self.tile_polygon(polygon))
+                    .collect::<Result<_>>()?,
+            )),
+            Some(other) => anyhow::bail!("unsupported geometry type {other}"),
+            None => anyhow::bail!("geometry has no type"),
+        })
+    }
+
+    fn lon_lat(&self, x: f32, y: f32) -> [f64; 2] {
+        let lon = (self.x + f64::from(x) / self.extent) / self.tiles * 360.0 - 180.0;
+        let mercator = PI * (1.0 - 2.0 * (self.y + f64::from(y) / self.extent) / self.tiles);
+        [lon, mercator.sinh().atan().to_degrees()]
+    }
+
+    fn line(&self, line: &LineString<f32>) -> Vec<[f64; 2]> {
+        line.0
+            .iter()
+            .map(|coord| self.lon_lat(coord.x, coord.y))
+            .collect()
+    }
+
+    fn polygon(&self, polygon: &Polygon<f32>) -> Vec<Vec<[f64; 2]>> {
+        std::iter::once(self.ring(polygon.exterior(), true))
+            .chain(
+                polygon
+                    .interiors()
+                    .iter()
+                    .map(|ring| self.ring(ring, false)),
+            )
+            .collect()
+    }
+
+    fn ring(&self, ring: &LineString<f32>, exterior: bool) -> Vec<[f64; 2]> {
+        let mut coords = self.line(ring);
+        // Shoelace sum is positive for counterclockwise rings in lon/lat
+        let area: f64 = coords
+            .windows(2)
+            .map(|pair| pair[0][0] * pair[1][1] - pair[1][0] * pair[0][1])
+            .sum();
+        if (area > 0.0) != exterior {
+            coords.reverse();
+        }
+        coords
+    }
+}
+
+fn geometry_json(geometry: &Geometry<f32>, project: &TileProjection) -> Value {
+    match geometry {
+        Geometry::Point(point) => {
+            json!({"type": "Point", "coordinates": project.lon_lat(point.x(), point.y())})
+        }
+        // Point features decode as a one-point MultiPoint
+        Geometry::MultiPoint(points) if points.0.len() == 1 => {
+            let point = points.0[0];
+            json!({"type": "Point", "coordinates": project.lon_lat(point.x(), point.y())})
+        }
+        Geometry::MultiPoint(points) => json!({
+            "type": "MultiPoint",
+            "coordinates": points
+                .0
+                .iter()
+                .map(|point| project.lon_lat(point.x(), point.y()))
+                .collect::<Vec<_>>(),
+        }),
+        Geometry::LineString(line) => {
+            json!({"type": "LineString", "coordinates": project.line(line)})
+        }
+        Geometry::MultiLineString(lines) => json!({
+            "type": "MultiLineString",
+            "coordinates": lines.0.iter().map(|line| project.line(line)).collect::<Vec<_>>(),
+        }),
+        Geometry::Polygon(polygon) => {
+            json!({"type": "Polygon", "coordinates": project.polygon(polygon)})
+        }
+        Geometry::MultiPolygon(polygons) if polygons.0.len() == 1 => {
+            json!({"type": "Polygon", "coordinates": project.polygon(&polygons.0[0])})
+        }
+        Geometry::MultiPolygon(polygons) => json!({
+            "type": "MultiPolygon",
+            "coordinates": polygons
+                .0
+                .iter()
+                .map(|polygon| project.polygon(polygon))
+                .collect::<Vec<_>>(),
+        }),
+        _ => Value::Null,
+    }
+}
+
+fn property_value(value: MvtValue) -> Value {
+    match value {
+        MvtValue::String(text) => json!(text),
+        MvtValue::Float(val) => json!(val),
+        MvtValue::Double(val) => json!(val),
+        MvtValue::Int(val) | MvtValue::SInt(val) => json!(val),
+        MvtValue::UInt(val) => json!(val),
+        MvtValue::Bool(val) => json!(val),
+        MvtValue::Null => Value::Null,
+    }
+}
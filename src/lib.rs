@@ -1,6 +1,15 @@
 pub mod cli;
+pub mod error;
 pub mod format;
+pub mod geojson;
 pub mod mbtiles;
 pub mod output;
+#[cfg(feature = "parquet")]
+pub mod parquet_report;
 pub mod pmtiles;
+pub mod report;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod source;
 pub mod style;
+pub mod tree;
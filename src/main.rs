@@ -1,16 +1,30 @@
+use std::io::Write;
+use std::time::Instant;
 use std::{collections::BTreeMap, fs, thread};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
 use nu_ansi_term::{Color, Style};
 use serde::Serialize;
-use vt_optimizer::cli::{Cli, Command, ReportFormat, TileSortArg};
+use vt_optimizer::cli::{
+    Cli, Command, FeatureIdsArg, HashArg, InspectReportFormat, InternalCompressionArg,
+    OutputOrderArg, OutsideTileArg, ReportFormat, SizeMeasureArg, TileSchemeArg, TileSortArg,
+};
 use vt_optimizer::format::{plan_copy, plan_optimize, resolve_output_path};
+use vt_optimizer::geojson::{
+    GeojsonImportOptions, OutsideTileMode, export_mbtiles_geojson, export_pmtiles_geojson,
+    import_mbtiles_geojson, import_pmtiles_geojson,
+};
 use vt_optimizer::mbtiles::{
-    InspectOptions, PruneOptions, PruneStats, TileListOptions, TileSort, copy_mbtiles,
-    inspect_mbtiles_with_options, parse_sample_spec, parse_tile_spec, prune_mbtiles_layer_only,
-    simplify_mbtiles_tile,
+    CardinalityOptions, FeatureCleanup, FeatureIdMode, InspectOptions, MbtilesFile, OutputOrder,
+    OverzoomOptions, PruneEstimate, PruneOptions, PruneStats, RecommendThresholds, RecompressStats,
+    SimplifyOptions, TileCompression, TileHashAlgorithm, TileListOptions, TileScheme,
+    TileSizeRange, TileSort, ZoomTileCounts, copy_mbtiles_with_options, decode_tile_payload,
+    estimate_prune, inspect_mbtiles_with_options, overzoom_mbtiles, parse_layer_renames,
+    parse_sample_spec, parse_tile_compression, parse_tile_range, parse_tile_spec,
+    prune_mbtiles_layer_only_with_checkpoint, read_mbtiles_tile, simplify_mbtiles_all,
+    simplify_mbtiles_batch, simplify_mbtiles_tile,
 };
 use vt_optimizer::output::{
     format_bytes, format_histogram_table, format_histograms_by_zoom_section,
@@ -18,10 +32,16 @@ use vt_optimizer::output::{
     pad_right, resolve_output_format,
 };
 use vt_optimizer::pmtiles::{
-    inspect_pmtiles_with_options, mbtiles_to_pmtiles, pmtiles_to_mbtiles, prune_pmtiles_layer_only,
+    InternalCompression, PmtilesFile, PmtilesWriteOptions, inspect_pmtiles_with_options,
+    mbtiles_to_pmtiles_with_options, pmtiles_to_mbtiles_with_options,
+    prune_pmtiles_layer_only_with_threads, read_pmtiles_tile, simplify_pmtiles_batch,
     simplify_pmtiles_tile,
 };
-use vt_optimizer::style::read_style;
+#[cfg(feature = "serve")]
+use vt_optimizer::serve::{ServeOptions, serve_mbtiles, serve_pmtiles};
+use vt_optimizer::source::TileSource;
+use vt_optimizer::style::{LayerSelection, read_style_for_source, read_styles};
+use vt_optimizer::tree::{explode_mbtiles, explode_pmtiles, implode_to_mbtiles};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -35,54 +55,7 @@ fn main() -> Result<()> {
             run_optimize(args)?;
         }
         Some(Command::Simplify(args)) => {
-            let input_format = vt_optimizer::format::TileFormat::from_extension(&args.input)
-                .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
-            let coord = vt_optimizer::mbtiles::TileCoord {
-                zoom: args.z,
-                x: args.x,
-                y: args.y,
-            };
-            let (output, stats) = match input_format {
-                vt_optimizer::format::TileFormat::Mbtiles => {
-                    let output = args
-                        .output
-                        .clone()
-                        .unwrap_or_else(|| args.input.with_extension("simplified.mbtiles"));
-                    let stats = simplify_mbtiles_tile(
-                        &args.input,
-                        &output,
-                        coord,
-                        &args.layer,
-                        args.tolerance,
-                    )?;
-                    (output, stats)
-                }
-                vt_optimizer::format::TileFormat::Pmtiles => {
-                    let output = args
-                        .output
-                        .clone()
-                        .unwrap_or_else(|| args.input.with_extension("simplified.pmtiles"));
-                    let stats = simplify_pmtiles_tile(
-                        &args.input,
-                        &output,
-                        coord,
-                        &args.layer,
-                        args.tolerance,
-                    )?;
-                    (output, stats)
-                }
-            };
-            println!(
-                "simplify: input={} output={} z={} x={} y={} features={} vertices={}=>{}",
-                args.input.display(),
-                output.display(),
-                args.z,
-                args.x,
-                args.y,
-                stats.feature_count,
-                stats.vertices_before,
-                stats.vertices_after
-            );
+            run_simplify(args)?;
         }
         Some(Command::Copy(args)) => {
             let decision = plan_copy(
@@ -93,24 +66,46 @@ fn main() -> Result<()> {
             )?;
             let _output_path =
                 resolve_output_path(&args.input, args.output.as_deref(), decision.output);
-            match (decision.input, decision.output) {
+            let recompress = args
+                .recompress
+                .as_deref()
+                .map(parse_tile_compression)
+                .transpose()?;
+            let scheme = match args.scheme {
+                TileSchemeArg::Tms => TileScheme::Tms,
+                TileSchemeArg::Xyz => TileScheme::Xyz,
+            };
+            let recompressed = match (decision.input, decision.output) {
                 (
                     vt_optimizer::format::TileFormat::Mbtiles,
                     vt_optimizer::format::TileFormat::Mbtiles,
-                ) => {
-                    copy_mbtiles(&args.input, &_output_path)?;
-                }
+                ) => copy_mbtiles_with_options(&args.input, &_output_path, recompress)?,
                 (
                     vt_optimizer::format::TileFormat::Mbtiles,
                     vt_optimizer::format::TileFormat::Pmtiles,
                 ) => {
-                    mbtiles_to_pmtiles(&args.input, &_output_path)?;
+                    let internal_compression = match args.internal_compression {
+                        InternalCompressionArg::None => InternalCompression::None,
+                        InternalCompressionArg::Gzip => InternalCompression::Gzip,
+                        InternalCompressionArg::Brotli => InternalCompression::Brotli,
+                    };
+                    mbtiles_to_pmtiles_with_options(
+                        &args.input,
+                        &_output_path,
+                        internal_compression,
+                        recompress,
+                        scheme,
+                        PmtilesWriteOptions {
+                            brotli_quality: args.brotli_quality,
+                            brotli_window: args.brotli_window,
+                        },
+                    )?
                 }
                 (
                     vt_optimizer::format::TileFormat::Pmtiles,
                     vt_optimizer::format::TileFormat::Mbtiles,
                 ) => {
-                    pmtiles_to_mbtiles(&args.input, &_output_path)?;
+                    pmtiles_to_mbtiles_with_options(&args.input, &_output_path, recompress, scheme)?
                 }
                 (
                     vt_optimizer::format::TileFormat::Pmtiles,
@@ -118,12 +113,163 @@ fn main() -> Result<()> {
                 ) => {
                     anyhow::bail!("v0.0.3 does not support PMTiles to PMTiles copy");
                 }
+            };
+            if recompress.is_some() {
+                println!("{}", format_recompress_summary(&recompressed));
             }
             println!("copy: input={}", args.input.display());
         }
         Some(Command::Verify(args)) => {
             println!("verify: input={}", args.input.display());
         }
+        Some(Command::StyleCheck(args)) => {
+            run_style_check(args)?;
+        }
+        Some(Command::Explode(args)) => {
+            let stats = match vt_optimizer::format::resolve_input_format(
+                &args.input,
+                args.input_format.as_deref(),
+            )? {
+                vt_optimizer::format::TileFormat::Mbtiles => {
+                    explode_mbtiles(&args.input, &args.out_dir, args.gzip)?
+                }
+                vt_optimizer::format::TileFormat::Pmtiles => {
+                    explode_pmtiles(&args.input, &args.out_dir, args.gzip)?
+                }
+            };
+            println!(
+                "explode: {} tiles -> {}",
+                stats.tiles,
+                args.out_dir.display()
+            );
+        }
+        Some(Command::Implode(args)) => {
+            let stats = implode_to_mbtiles(&args.in_dir, &args.output)?;
+            println!(
+                "implode: {} tiles (z{}-z{}) -> {}",
+                stats.tiles,
+                stats.min_zoom.unwrap_or(0),
+                stats.max_zoom.unwrap_or(0),
+                args.output.display()
+            );
+        }
+        Some(Command::Export(args)) => {
+            let coord = parse_tile_spec(&args.tile)?;
+            let features = match vt_optimizer::format::resolve_input_format(
+                &args.input,
+                args.input_format.as_deref(),
+            )? {
+                vt_optimizer::format::TileFormat::Mbtiles => {
+                    export_mbtiles_geojson(&args.input, coord, &args.layers, &args.output)?
+                }
+                vt_optimizer::format::TileFormat::Pmtiles => {
+                    export_pmtiles_geojson(&args.input, coord, &args.layers, &args.output)?
+                }
+            };
+            println!("export: {} features -> {}", features, args.output.display());
+        }
+        Some(Command::Get(args)) => {
+            let coord = parse_tile_spec(&args.tile)?;
+            let format = vt_optimizer::format::resolve_input_format(
+                &args.input,
+                args.input_format.as_deref(),
+            )?;
+            let data = match format {
+                vt_optimizer::format::TileFormat::Mbtiles => read_mbtiles_tile(&args.input, coord)?,
+                vt_optimizer::format::TileFormat::Pmtiles => read_pmtiles_tile(&args.input, coord)?,
+            }
+            .with_context(|| {
+                format!(
+                    "tile {}/{}/{} not found in {}",
+                    coord.zoom,
+                    coord.x,
+                    coord.y,
+                    args.input.display()
+                )
+            })?;
+            let data = if args.raw {
+                data
+            } else {
+                match format {
+                    vt_optimizer::format::TileFormat::Mbtiles => decode_tile_payload(&data)?,
+                    vt_optimizer::format::TileFormat::Pmtiles => {
+                        PmtilesFile::open(&args.input)?.decode_tile(&data)?
+                    }
+                }
+            };
+            match &args.output {
+                Some(path) => {
+                    fs::write(path, &data)
+                        .with_context(|| format!("failed to write {}", path.display()))?;
+                    println!("get: {} bytes -> {}", data.len(), path.display());
+                }
+                None => {
+                    let mut stdout = std::io::stdout().lock();
+                    stdout
+                        .write_all(&data)
+                        .context("failed to write tile to stdout")?;
+                    stdout.flush().context("failed to write tile to stdout")?;
+                }
+            }
+        }
+        Some(Command::ImportTile(args)) => {
+            let coord = parse_tile_spec(&args.tile)?;
+            let options = GeojsonImportOptions {
+                layer: args.layer,
+                extent: args.extent,
+                buffer: args.buffer,
+                outside: match args.outside {
+                    OutsideTileArg::Clip => OutsideTileMode::Clip,
+                    OutsideTileArg::Reject => OutsideTileMode::Reject,
+                },
+            };
+            let features = match vt_optimizer::format::resolve_input_format(
+                &args.input,
+                args.input_format.as_deref(),
+            )? {
+                vt_optimizer::format::TileFormat::Mbtiles => import_mbtiles_geojson(
+                    &args.input,
+                    coord,
+                    &args.geojson,
+                    &args.output,
+                    &options,
+                )?,
+                vt_optimizer::format::TileFormat::Pmtiles => import_pmtiles_geojson(
+                    &args.input,
+                    coord,
+                    &args.geojson,
+                    &args.output,
+                    &options,
+                )?,
+            };
+            println!(
+                "import-tile: {} features -> {} ({})",
+                features,
+                args.output.display(),
+                args.tile
+            );
+        }
+        #[cfg(feature = "serve")]
+        Some(Command::Serve(args)) => {
+            let options = ServeOptions {
+                style: args.style,
+                source: args.source,
+            };
+            match vt_optimizer::format::resolve_input_format(
+                &args.input,
+                args.input_format.as_deref(),
+            )? {
+                vt_optimizer::format::TileFormat::Mbtiles => {
+                    serve_mbtiles(&args.input, args.port, &options)?
+                }
+                vt_optimizer::format::TileFormat::Pmtiles => {
+                    serve_pmtiles(&args.input, args.port, &options)?
+                }
+            }
+        }
+        Some(Command::Overzoom(args)) => {
+            run_overzoom(args)?;
+        }
         None => {
             let Some(input) = cli.mbtiles.as_ref() else {
                 anyhow::bail!("no subcommand or --mbtiles provided");
@@ -135,7 +281,12 @@ fn main() -> Result<()> {
                     input_format: None,
                     output_format: None,
                     report_format: ReportFormat::Text,
-                    style: cli.style.clone(),
+                    compact: false,
+                    pretty: false,
+                    style: cli.style.clone().into_iter().collect(),
+                    source: None,
+                    keep_layer: Vec::new(),
+                    drop_layer: Vec::new(),
                     style_mode: vt_optimizer::cli::StyleMode::VtCompat,
                     unknown_filter: vt_optimizer::cli::UnknownFilterMode::Keep,
                     max_tile_bytes: 1_280_000,
@@ -145,8 +296,31 @@ fn main() -> Result<()> {
                     read_cache_mb: None,
                     write_cache_mb: None,
                     drop_empty_tiles: false,
+                    fix_winding: false,
+                    gzip_level: vt_optimizer::mbtiles::DEFAULT_GZIP_LEVEL,
+                    recompress: None,
+                    brotli_quality: vt_optimizer::mbtiles::DEFAULT_BROTLI_QUALITY,
+                    brotli_window: vt_optimizer::mbtiles::DEFAULT_BROTLI_WINDOW,
+                    drop_tiny_features: None,
+                    merge_nearby_points: None,
+                    cleanup_exclude_layers: Vec::new(),
+                    feature_ids: vt_optimizer::cli::FeatureIdsArg::Keep,
+                    dedup: false,
                     checkpoint: None,
                     resume: false,
+                    report: None,
+                    no_progress: false,
+                    clamp_zoom: false,
+                    append: false,
+                    prune_attributes: false,
+                    rename_layer: Vec::new(),
+                    keep_original_metadata: false,
+                    dry_run: false,
+                    sample: None,
+                    sample_seed: None,
+                    drop_empty_threshold: None,
+                    output_order: vt_optimizer::cli::OutputOrderArg::None,
+                    strict: false,
                 };
                 run_optimize(args)?;
                 return Ok(());
@@ -156,118 +330,184 @@ fn main() -> Result<()> {
                     let args = vt_optimizer::cli::SimplifyArgs {
                         input: input.clone(),
                         output: cli.output.clone(),
-                        z,
-                        x,
-                        y,
+                        input_format: None,
+                        z: Some(z),
+                        x: Some(x),
+                        y: Some(y),
                         layer: cli.layer.clone(),
                         tolerance: cli.tolerance,
+                        buffer: vt_optimizer::mbtiles::DEFAULT_SIMPLIFY_BUFFER,
+                        preserve_topology: false,
+                        all_zooms: false,
+                        over_limit_only: false,
+                        max_tile_bytes: 1_280_000,
+                        threads: None,
+                        report: None,
                     };
-                    let input_format = vt_optimizer::format::TileFormat::from_extension(
-                        &args.input,
-                    )
-                    .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
-                    let coord = vt_optimizer::mbtiles::TileCoord {
-                        zoom: args.z,
-                        x: args.x,
-                        y: args.y,
-                    };
-                    let (output, stats) =
-                        match input_format {
-                            vt_optimizer::format::TileFormat::Mbtiles => {
-                                let output = args.output.clone().unwrap_or_else(|| {
-                                    args.input.with_extension("simplified.mbtiles")
-                                });
-                                let stats = simplify_mbtiles_tile(
-                                    &args.input,
-                                    &output,
-                                    coord,
-                                    &args.layer,
-                                    args.tolerance,
-                                )?;
-                                (output, stats)
-                            }
-                            vt_optimizer::format::TileFormat::Pmtiles => {
-                                let output = args.output.clone().unwrap_or_else(|| {
-                                    args.input.with_extension("simplified.pmtiles")
-                                });
-                                let stats = simplify_pmtiles_tile(
-                                    &args.input,
-                                    &output,
-                                    coord,
-                                    &args.layer,
-                                    args.tolerance,
-                                )?;
-                                (output, stats)
-                            }
-                        };
-                    println!(
-                        "simplify: input={} output={} z={} x={} y={} features={} vertices={}=>{}",
-                        args.input.display(),
-                        output.display(),
-                        args.z,
-                        args.x,
-                        args.y,
-                        stats.feature_count,
-                        stats.vertices_before,
-                        stats.vertices_after
-                    );
+                    run_simplify(args)?;
                     return Ok(());
                 }
                 let args = vt_optimizer::cli::InspectArgs {
                     input: input.clone(),
+                    input_format: None,
                     max_tile_bytes: 1_280_000,
                     histogram_buckets: 0,
                     topn: None,
                     sample: None,
-                    output: vt_optimizer::cli::ReportFormat::Text,
+                    sample_seed: None,
+                    output: vt_optimizer::cli::InspectReportFormat::Text,
+                    compact: false,
+                    pretty: false,
+                    csv_section: None,
+                    parquet_dir: None,
+                    sparkline: false,
                     stats: Some("tile_summary".to_string()),
                     no_progress: false,
                     zoom: None,
                     x: None,
                     y: None,
                     bucket: None,
-                    tile: Some(format!("{}/{}/{}", z, x, y)),
+                    tile: vec![format!("{}/{}/{}", z, x, y)],
                     summary: true,
+                    tiles_from: None,
+                    tile_url_template: None,
                     layers: Vec::new(),
                     layer: Vec::new(),
                     recommend: false,
+                    recommend_max_layer_bytes: None,
+                    recommend_max_layer_features: None,
                     fast: false,
                     list_tiles: false,
+                    min_bytes: None,
+                    max_bytes: None,
                     limit: 100,
                     sort: vt_optimizer::cli::TileSortArg::Size,
                     ndjson_lite: false,
                     ndjson_compact: false,
                     include_layer_list: false,
+                    check_geometry: false,
+                    attributes_size: false,
+                    layer_bytes: false,
+                    percentiles: false,
+                    dedup_stats: false,
+                    layer_zoom_matrix: false,
+                    style: None,
+                    source: None,
+                    property_histogram: None,
+                    detect_cardinality: false,
+                    cardinality_threshold: None,
+                    cardinality_cap: None,
+                    measure: SizeMeasureArg::Stored,
+                    hash: HashArg::None,
+                    validate_mvt: false,
+                    empty_threshold: vt_optimizer::mbtiles::EMPTY_TILE_MAX_BYTES,
+                    empty_exact: false,
+                    exclude_empty: false,
+                    histogram_cache_max_tiles:
+                        vt_optimizer::mbtiles::DEFAULT_HISTOGRAM_CACHE_MAX_TILES,
+                    layers_only: false,
+                    count_only: false,
+                    immutable: false,
+                    tile_table: None,
+                    zoom_column: None,
+                    x_column: None,
+                    y_column: None,
+                    data_column: None,
+                    media_types: false,
                     tile_info_format: vt_optimizer::cli::TileInfoFormat::Full,
                 };
                 run_inspect(args)?;
                 return Ok(());
             }
+            if (cli.x, cli.y, cli.z) == (None, None, None)
+                && (!cli.layer.is_empty() || cli.tolerance.is_some())
+            {
+                let args = vt_optimizer::cli::SimplifyArgs {
+                    input: input.clone(),
+                    output: cli.output.clone(),
+                    input_format: None,
+                    z: None,
+                    x: None,
+                    y: None,
+                    layer: cli.layer.clone(),
+                    tolerance: cli.tolerance,
+                    buffer: vt_optimizer::mbtiles::DEFAULT_SIMPLIFY_BUFFER,
+                    preserve_topology: false,
+                    all_zooms: true,
+                    over_limit_only: false,
+                    max_tile_bytes: 1_280_000,
+                    threads: None,
+                    report: None,
+                };
+                run_simplify(args)?;
+                return Ok(());
+            }
             let args = vt_optimizer::cli::InspectArgs {
                 input: input.clone(),
+                input_format: None,
                 max_tile_bytes: 1_280_000,
                 histogram_buckets: 10,
                 topn: None,
                 sample: None,
-                output: vt_optimizer::cli::ReportFormat::Text,
+                sample_seed: None,
+                output: vt_optimizer::cli::InspectReportFormat::Text,
+                compact: false,
+                pretty: false,
+                csv_section: None,
+                parquet_dir: None,
+                sparkline: false,
                 stats: None,
                 no_progress: false,
                 zoom: None,
                 x: None,
                 y: None,
                 bucket: None,
-                tile: None,
+                tile: Vec::new(),
                 summary: false,
+                tiles_from: None,
+                tile_url_template: None,
                 layers: Vec::new(),
                 layer: Vec::new(),
                 recommend: false,
+                recommend_max_layer_bytes: None,
+                recommend_max_layer_features: None,
                 fast: false,
                 list_tiles: false,
+                min_bytes: None,
+                max_bytes: None,
                 limit: 100,
                 sort: vt_optimizer::cli::TileSortArg::Size,
                 ndjson_lite: false,
                 ndjson_compact: false,
                 include_layer_list: false,
+                check_geometry: false,
+                attributes_size: false,
+                layer_bytes: false,
+                percentiles: false,
+                dedup_stats: false,
+                layer_zoom_matrix: false,
+                style: None,
+                source: None,
+                property_histogram: None,
+                detect_cardinality: false,
+                cardinality_threshold: None,
+                cardinality_cap: None,
+                measure: SizeMeasureArg::Stored,
+                hash: HashArg::None,
+                validate_mvt: false,
+                empty_threshold: vt_optimizer::mbtiles::EMPTY_TILE_MAX_BYTES,
+                empty_exact: false,
+                exclude_empty: false,
+                histogram_cache_max_tiles: vt_optimizer::mbtiles::DEFAULT_HISTOGRAM_CACHE_MAX_TILES,
+                layers_only: false,
+                count_only: false,
+                immutable: false,
+                tile_table: None,
+                zoom_column: None,
+                x_column: None,
+                y_column: None,
+                data_column: None,
+                media_types: false,
                 tile_info_format: vt_optimizer::cli::TileInfoFormat::Full,
             };
             run_inspect(args)?;
@@ -283,35 +523,183 @@ fn init_tracing(level: &str) {
     tracing_subscriber::fmt().with_env_filter(filter).init();
 }
 
+#[cfg(feature = "http")]
+fn inspect_remote_pmtiles(
+    url: &str,
+    options: &InspectOptions,
+    sink: Option<vt_optimizer::mbtiles::TileSink<'_>>,
+) -> Result<vt_optimizer::mbtiles::MbtilesReport> {
+    match sink {
+        Some(sink) => vt_optimizer::pmtiles::inspect_pmtiles_url_streaming(url, options, sink),
+        None => vt_optimizer::pmtiles::inspect_pmtiles_url_with_options(url, options),
+    }
+}
+
+#[cfg(not(feature = "http"))]
+fn inspect_remote_pmtiles(
+    _url: &str,
+    _options: &InspectOptions,
+    _sink: Option<vt_optimizer::mbtiles::TileSink<'_>>,
+) -> Result<vt_optimizer::mbtiles::MbtilesReport> {
+    anyhow::bail!("remote PMTiles inputs require building with the `http` feature");
+}
+
+fn format_invalid_geometries(count: Option<u64>) -> String {
+    count
+        .map(|count| format!(" invalid_geometries={count}"))
+        .unwrap_or_default()
+}
+
+fn format_duplicate_features(count: u64) -> String {
+    if count > 0 {
+        format!(" duplicate_features={count}")
+    } else {
+        String::new()
+    }
+}
+
+/// `inspect --tiles-from`: one tile summary JSON object per line, flushed
+/// as each tile is summarized.
+fn run_inspect_tiles_from(
+    args: &vt_optimizer::cli::InspectArgs,
+    list_path: &std::path::Path,
+) -> Result<()> {
+    let text = fs::read_to_string(list_path)
+        .with_context(|| format!("failed to read tile list: {}", list_path.display()))?;
+    let coords = vt_optimizer::mbtiles::parse_tile_list(&text)?;
+    let mut layers = args.layers.clone();
+    layers.extend(args.layer.clone());
+    let input_format =
+        vt_optimizer::format::resolve_input_format(&args.input, args.input_format.as_deref())?;
+    if vt_optimizer::format::remote_url(&args.input).is_some() {
+        anyhow::bail!("--tiles-from requires a local input");
+    }
+    let mut stdout = std::io::stdout().lock();
+    let mut write_summary = |summary: vt_optimizer::mbtiles::TileSummary| -> Result<()> {
+        writeln!(stdout, "{}", serde_json::to_string(&summary)?)?;
+        stdout.flush()?;
+        Ok(())
+    };
+    match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => {
+            vt_optimizer::mbtiles::summarize_mbtiles_tiles(
+                &args.input,
+                &coords,
+                &layers,
+                args.check_geometry,
+                &mut write_summary,
+            )?
+        }
+        vt_optimizer::format::TileFormat::Pmtiles => {
+            vt_optimizer::pmtiles::summarize_pmtiles_tiles(
+                &args.input,
+                &coords,
+                &layers,
+                args.check_geometry,
+                &mut write_summary,
+            )?
+        }
+    };
+    Ok(())
+}
+
+/// `inspect --count-only`: the tile count alone, for shell pipelines.
+fn run_inspect_count_only(args: &vt_optimizer::cli::InspectArgs) -> Result<()> {
+    if vt_optimizer::format::remote_url(&args.input).is_some() {
+        anyhow::bail!("--count-only requires a local input");
+    }
+    let input_format =
+        vt_optimizer::format::resolve_input_format(&args.input, args.input_format.as_deref())?;
+    let count = match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => {
+            vt_optimizer::mbtiles::tile_count(&args.input, args.zoom)?
+        }
+        vt_optimizer::format::TileFormat::Pmtiles => {
+            vt_optimizer::pmtiles::tile_count_pmtiles(&args.input, args.zoom)?
+        }
+    };
+    println!("{count}");
+    Ok(())
+}
+
+/// `--tile-table` and the column overrides, or `None` for the standard names.
+fn tile_table_names(
+    args: &vt_optimizer::cli::InspectArgs,
+) -> Option<vt_optimizer::mbtiles::TileTableNames> {
+    let defaults = vt_optimizer::mbtiles::TileTableNames::default();
+    let names = vt_optimizer::mbtiles::TileTableNames {
+        table: args.tile_table.clone().unwrap_or(defaults.table),
+        zoom: args.zoom_column.clone().unwrap_or(defaults.zoom),
+        column: args.x_column.clone().unwrap_or(defaults.column),
+        row: args.y_column.clone().unwrap_or(defaults.row),
+        data: args.data_column.clone().unwrap_or(defaults.data),
+    };
+    (!names.is_standard()).then_some(names)
+}
+
 fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
+    let tile_table = tile_table_names(&args);
+    if (args.immutable || tile_table.is_some()) && (args.count_only || args.tiles_from.is_some()) {
+        anyhow::bail!(
+            "--immutable and --tile-table/--*-column cannot be combined with --count-only or --tiles-from"
+        );
+    }
+    if args.count_only {
+        return run_inspect_count_only(&args);
+    }
     let output = resolve_output_format(args.output, args.ndjson_compact);
-    let stats_filter = vt_optimizer::output::parse_stats_filter(args.stats.as_deref())?;
-    if args.ndjson_lite && output != ReportFormat::Ndjson {
+    let stats_filter = if args.layers_only && args.stats.is_none() {
+        vt_optimizer::output::parse_stats_filter(Some("metadata,layers"))?
+    } else {
+        vt_optimizer::output::parse_stats_filter(args.stats.as_deref())?
+    };
+    if args.ndjson_lite && output != InspectReportFormat::Ndjson {
         anyhow::bail!("--ndjson-lite requires --report-format ndjson");
     }
+    if args.csv_section.is_some() && output != InspectReportFormat::Csv {
+        anyhow::bail!("--csv-section requires --report-format csv");
+    }
+    #[cfg(feature = "parquet")]
+    let parquet = output == InspectReportFormat::Parquet;
+    #[cfg(not(feature = "parquet"))]
+    let parquet = false;
+    if args.parquet_dir.is_some() && !parquet {
+        anyhow::bail!("--parquet-dir requires --report-format parquet");
+    }
     let sample = match args.sample.as_deref() {
         Some(value) => Some(parse_sample_spec(value)?),
         None => None,
     };
-    let mut tile = match args.tile.as_deref() {
-        Some(value) => Some(parse_tile_spec(value)?),
-        None => None,
-    };
-    if tile.is_some() && (args.x.is_some() || args.y.is_some()) {
+    let tile_url_template = args
+        .tile_url_template
+        .as_deref()
+        .map(vt_optimizer::mbtiles::parse_tile_url_template)
+        .transpose()?;
+    let mut tiles = Vec::new();
+    for value in args.tile.iter() {
+        tiles.extend(parse_tile_range(value)?);
+    }
+    if !tiles.is_empty() && (args.x.is_some() || args.y.is_some()) {
         anyhow::bail!("--tile cannot be combined with -x/-y");
     }
-    if tile.is_none() {
+    if tiles.is_empty() {
         if let (Some(z), Some(x), Some(y)) = (args.zoom, args.x, args.y) {
-            tile = Some(vt_optimizer::mbtiles::TileCoord { zoom: z, x, y });
+            tiles.push(vt_optimizer::mbtiles::TileCoord { zoom: z, x, y });
         } else if args.x.is_some() || args.y.is_some() {
             anyhow::bail!("-x/-y require -z/--zoom");
         }
     }
-    let summary = args.summary || (tile.is_some() && args.tile.is_none() && args.x.is_some());
-    if summary && tile.is_none() {
+    if let Some(list_path) = args.tiles_from.as_deref() {
+        if !tiles.is_empty() {
+            anyhow::bail!("--tiles-from cannot be combined with -x/-y");
+        }
+        return run_inspect_tiles_from(&args, list_path);
+    }
+    let summary = args.summary || (!tiles.is_empty() && args.tile.is_empty() && args.x.is_some());
+    if summary && tiles.is_empty() {
         anyhow::bail!("--summary requires --tile z/x/y");
     }
-    if tile.is_some() && !summary {
+    if !tiles.is_empty() && !summary {
         anyhow::bail!("--tile requires --summary");
     }
     let mut layers = args.layers.clone();
@@ -319,14 +707,90 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
     if args.recommend && args.zoom.is_none() {
         anyhow::bail!("--recommend requires --zoom");
     }
+    let scans_tiles = summary
+        || args.recommend
+        || args.bucket.is_some()
+        || args.list_tiles
+        || args.sample.is_some()
+        || args.fast
+        || args.percentiles
+        || args.dedup_stats
+        || args.media_types
+        || args.layer_zoom_matrix
+        || args.style.is_some()
+        || args.hash != HashArg::None
+        || args.validate_mvt;
+    if args.layers_only && scans_tiles {
+        anyhow::bail!("--layers-only cannot be combined with options that need the tile size scan");
+    }
+    let layers_only = args.layers_only || (stats_filter.layers_only() && !scans_tiles);
+    let include_layer_list = args.include_layer_list || layers_only || args.style.is_some();
+    if parquet && !args.list_tiles && !include_layer_list {
+        anyhow::bail!("--report-format parquet requires --list-tiles or --include-layer-list");
+    }
+    if args.property_histogram.is_some() && !include_layer_list {
+        anyhow::bail!("--property-histogram requires --include-layer-list");
+    }
+    if args.detect_cardinality && !include_layer_list {
+        anyhow::bail!("--detect-cardinality requires --include-layer-list");
+    }
+    if !args.detect_cardinality
+        && (args.cardinality_threshold.is_some() || args.cardinality_cap.is_some())
+    {
+        anyhow::bail!("--cardinality-threshold/--cardinality-cap require --detect-cardinality");
+    }
+    let cardinality = if args.detect_cardinality {
+        let defaults = CardinalityOptions::default();
+        let min_distinct_ratio = args
+            .cardinality_threshold
+            .unwrap_or(defaults.min_distinct_ratio);
+        if !(0.0..1.0).contains(&min_distinct_ratio) {
+            anyhow::bail!("--cardinality-threshold must be at least 0 and below 1");
+        }
+        let max_tracked_values = args.cardinality_cap.unwrap_or(defaults.max_tracked_values);
+        if max_tracked_values < 2 {
+            anyhow::bail!("--cardinality-cap must be at least 2");
+        }
+        Some(CardinalityOptions {
+            min_distinct_ratio,
+            max_tracked_values,
+            ..defaults
+        })
+    } else {
+        None
+    };
+    if args.attributes_size && !include_layer_list {
+        anyhow::bail!("--attributes-size requires --include-layer-list");
+    }
+    if args.layer_bytes && !include_layer_list {
+        anyhow::bail!("--layer-bytes requires --include-layer-list");
+    }
     if args.recommend && args.histogram_buckets == 0 {
         anyhow::bail!("--recommend requires --histogram-buckets");
     }
+    if !args.recommend
+        && (args.recommend_max_layer_bytes.is_some() || args.recommend_max_layer_features.is_some())
+    {
+        anyhow::bail!(
+            "--recommend-max-layer-bytes/--recommend-max-layer-features require --recommend"
+        );
+    }
+    let size_range = if args.min_bytes.is_some() || args.max_bytes.is_some() {
+        if !args.list_tiles {
+            anyhow::bail!("--min-bytes/--max-bytes require --list-tiles");
+        }
+        Some(TileSizeRange::new(args.min_bytes, args.max_bytes)?)
+    } else {
+        None
+    };
     let topn = if args.recommend && args.topn.is_none() {
         Some(5)
     } else {
         args.topn
     };
+    if args.sample_seed.is_some() && sample.is_none() && !args.fast {
+        anyhow::bail!("--sample-seed requires --sample or --fast");
+    }
     let (sample, mut topn, histogram_buckets) = if args.fast {
         (
             Some(vt_optimizer::mbtiles::SampleSpec::Ratio(0.1)),
@@ -336,23 +800,51 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
     } else {
         (sample, topn, args.histogram_buckets as usize)
     };
-    if output == ReportFormat::Text && topn.unwrap_or(0) == 0 {
+    if output == InspectReportFormat::Text && topn.unwrap_or(0) == 0 {
         topn = Some(10);
     }
     let topn_value = topn.unwrap_or(0) as usize;
     let options = InspectOptions {
         sample,
+        sample_seed: args.sample_seed,
         topn: topn_value,
         histogram_buckets,
         no_progress: args.no_progress,
         max_tile_bytes: args.max_tile_bytes,
         zoom: args.zoom,
         bucket: args.bucket,
-        tile,
+        tiles,
         summary,
         layers,
         recommend: args.recommend,
-        include_layer_list: args.include_layer_list,
+        include_layer_list,
+        check_geometry: args.check_geometry,
+        attributes_size: args.attributes_size,
+        layer_bytes: args.layer_bytes || args.style.is_some(),
+        percentiles: args.percentiles,
+        dedup_stats: args.dedup_stats,
+        layer_zoom_matrix: args.layer_zoom_matrix || args.style.is_some(),
+        property_histogram: args.property_histogram.clone(),
+        cardinality,
+        uncompressed_sizes: args.measure == SizeMeasureArg::Uncompressed,
+        hash: match args.hash {
+            HashArg::None => None,
+            HashArg::Sha256 => Some(TileHashAlgorithm::Sha256),
+            HashArg::Xxh3 => Some(TileHashAlgorithm::Xxh3),
+        },
+        validate_mvt: args.validate_mvt,
+        empty_tile_max_bytes: args.empty_threshold,
+        histogram_cache_max_tiles: args.histogram_cache_max_tiles,
+        layers_only,
+        media_types: args.media_types,
+        histogram_exclude_empty: args.exclude_empty,
+        empty_exact: args.empty_exact,
+        strict: args.validate_mvt,
+        recommend_thresholds: RecommendThresholds {
+            max_layer_bytes: args.recommend_max_layer_bytes,
+            max_layer_features: args.recommend_max_layer_features,
+            ..RecommendThresholds::default()
+        },
         list_tiles: if args.list_tiles {
             Some(TileListOptions {
                 limit: args.limit,
@@ -360,23 +852,110 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                     TileSortArg::Size => TileSort::Size,
                     TileSortArg::Zxy => TileSort::Zxy,
                 },
+                size_range,
             })
         } else {
             None
         },
+        style: args.style.clone(),
+        style_source: args.source.clone(),
     };
-    let input_format = vt_optimizer::format::TileFormat::from_extension(&args.input)
-        .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
-    let report = match input_format {
-        vt_optimizer::format::TileFormat::Mbtiles => {
+    let input_format =
+        vt_optimizer::format::resolve_input_format(&args.input, args.input_format.as_deref())?;
+    let remote_url = vt_optimizer::format::remote_url(&args.input);
+    // Tile rows of MBTiles reports are TMS `tile_row`s; URLs and Parquet use XYZ.
+    let row_scheme = match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => TileScheme::Tms,
+        vt_optimizer::format::TileFormat::Pmtiles => TileScheme::Xyz,
+    };
+    // NDJSON tile listings are written as the scan visits each tile.
+    let stream_tiles = output == InspectReportFormat::Ndjson && args.list_tiles;
+    let write_tile = |record: vt_optimizer::mbtiles::TileRecord| -> Result<()> {
+        let url = tile_url_template
+            .as_ref()
+            .map(|template| template.expand_row(record.zoom, record.x, record.y, row_scheme));
+        let line = vt_optimizer::output::ndjson_tile_line_with_url(&record, url)?;
+        writeln!(std::io::stdout().lock(), "{}", line)?;
+        Ok(())
+    };
+    let sink: Option<vt_optimizer::mbtiles::TileSink<'_>> = stream_tiles.then_some(&write_tile);
+    #[cfg(feature = "parquet")]
+    let parquet_dir = args
+        .parquet_dir
+        .clone()
+        .unwrap_or_else(|| std::path::PathBuf::from("."));
+    #[cfg(feature = "parquet")]
+    let parquet_tiles = if parquet && args.list_tiles {
+        std::fs::create_dir_all(&parquet_dir)
+            .with_context(|| format!("failed to create parquet dir: {}", parquet_dir.display()))?;
+        Some(vt_optimizer::parquet_report::ParquetTileWriter::create(
+            &parquet_dir.join(vt_optimizer::parquet_report::TILES_FILE_NAME),
+        )?)
+    } else {
+        None
+    };
+    #[cfg(feature = "parquet")]
+    let write_parquet_tile = |mut record: vt_optimizer::mbtiles::TileRecord| -> Result<()> {
+        record.y = row_scheme.convert_row(record.zoom, record.y);
+        match parquet_tiles.as_ref() {
+            Some(writer) => writer.write(record),
+            None => Ok(()),
+        }
+    };
+    #[cfg(feature = "parquet")]
+    let sink = sink.or(parquet_tiles
+        .is_some()
+        .then_some(&write_parquet_tile as vt_optimizer::mbtiles::TileSink<'_>));
+    let report = match (input_format, remote_url, sink) {
+        (vt_optimizer::format::TileFormat::Pmtiles, Some(url), sink) => {
+            inspect_remote_pmtiles(url, &options, sink)?
+        }
+        (vt_optimizer::format::TileFormat::Mbtiles, Some(_), _) => {
+            anyhow::bail!("remote inputs are only supported for PMTiles");
+        }
+        (vt_optimizer::format::TileFormat::Mbtiles, None, sink)
+            if args.immutable || tile_table.is_some() =>
+        {
+            let source = MbtilesFile::open_with_options(
+                &args.input,
+                vt_optimizer::mbtiles::MbtilesReadOptions {
+                    immutable: args.immutable,
+                    tile_table,
+                },
+            )?;
+            match sink {
+                Some(sink) => {
+                    vt_optimizer::mbtiles::inspect_tile_source_streaming(&source, options, sink)?
+                }
+                None => vt_optimizer::mbtiles::inspect_tile_source_with_options(&source, options)?,
+            }
+        }
+        (vt_optimizer::format::TileFormat::Pmtiles, _, _)
+            if args.immutable || tile_table.is_some() =>
+        {
+            anyhow::bail!("--immutable and --tile-table/--*-column only apply to MBTiles input");
+        }
+        (vt_optimizer::format::TileFormat::Mbtiles, None, Some(sink)) => {
+            vt_optimizer::mbtiles::inspect_mbtiles_streaming(&args.input, options, sink)?
+        }
+        (vt_optimizer::format::TileFormat::Mbtiles, None, None) => {
             inspect_mbtiles_with_options(&args.input, options)?
         }
-        vt_optimizer::format::TileFormat::Pmtiles => {
+        (vt_optimizer::format::TileFormat::Pmtiles, None, Some(sink)) => {
+            vt_optimizer::pmtiles::inspect_pmtiles_streaming(&args.input, &options, sink)?
+        }
+        (vt_optimizer::format::TileFormat::Pmtiles, None, None) => {
             inspect_pmtiles_with_options(&args.input, &options)?
         }
     };
     let report = vt_optimizer::output::apply_tile_info_format(report, args.tile_info_format);
-    let summary_totals = if args.include_layer_list
+    let report = match tile_url_template.as_ref() {
+        Some(template) => {
+            vt_optimizer::output::apply_tile_url_template(report, template, row_scheme)
+        }
+        None => report,
+    };
+    let summary_totals = if include_layer_list
         && stats_filter.includes(vt_optimizer::output::StatsSection::Summary)
     {
         vt_optimizer::output::summarize_file_layers(&report.file_layers)
@@ -385,11 +964,37 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
     };
     let report = vt_optimizer::output::apply_stats_filter(report, &stats_filter);
     match output {
-        ReportFormat::Json => {
-            let json = serde_json::to_string_pretty(&report)?;
+        InspectReportFormat::Json => {
+            let report = vt_optimizer::output::VersionedReport::new(
+                &report,
+                vt_optimizer::output::ReportGenerator::current(),
+            );
+            let json = vt_optimizer::output::json_report_string(&report, args.compact)?;
             println!("{}", json);
         }
-        ReportFormat::Ndjson => {
+        InspectReportFormat::Csv => {
+            for line in vt_optimizer::output::csv_lines(&report, args.csv_section) {
+                println!("{}", line);
+            }
+        }
+        #[cfg(feature = "parquet")]
+        InspectReportFormat::Parquet => {
+            if let Some(writer) = parquet_tiles {
+                let path = parquet_dir.join(vt_optimizer::parquet_report::TILES_FILE_NAME);
+                let rows = writer.finish()?;
+                println!("parquet: {} tiles -> {}", rows, path.display());
+            }
+            if include_layer_list {
+                std::fs::create_dir_all(&parquet_dir).with_context(|| {
+                    format!("failed to create parquet dir: {}", parquet_dir.display())
+                })?;
+                let path = parquet_dir.join(vt_optimizer::parquet_report::LAYERS_FILE_NAME);
+                let rows =
+                    vt_optimizer::parquet_report::write_layers_parquet(&path, &report.file_layers)?;
+                println!("parquet: {} layers -> {}", rows, path.display());
+            }
+        }
+        InspectReportFormat::Ndjson => {
             let options = vt_optimizer::output::NdjsonOptions {
                 include_summary: !args.ndjson_lite
                     && !args.ndjson_compact
@@ -400,7 +1005,7 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                 println!("{}", line);
             }
         }
-        ReportFormat::Text => {
+        InspectReportFormat::Text => {
             println!();
             eprintln!();
             let include_metadata =
@@ -414,9 +1019,9 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
             let include_histogram_by_zoom = args.stats.is_some()
                 && stats_filter.includes(vt_optimizer::output::StatsSection::HistogramByZoom);
             let hide_tile_summary_sections = args.x.is_some() && args.y.is_some();
-            let include_layers = args.include_layer_list
+            let include_layers = include_layer_list
                 && stats_filter.includes(vt_optimizer::output::StatsSection::Layers);
-            let show_layers_tip = !args.include_layer_list
+            let show_layers_tip = !include_layer_list
                 && stats_filter.includes(vt_optimizer::output::StatsSection::Layers)
                 && !hide_tile_summary_sections;
             let include_recommendations =
@@ -437,26 +1042,104 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                 for line in format_metadata_section(&report.metadata) {
                     println!("{}", emphasize_section_heading(&line));
                 }
+                if !report.vector_layers.is_empty() {
+                    println!("- vector_layers:");
+                    let mut lines =
+                        vt_optimizer::output::format_vector_layers_table(&report.vector_layers)
+                            .into_iter();
+                    if let Some(header) = lines.next() {
+                        println!("{}", emphasize_table_header(&header));
+                    }
+                    for line in lines {
+                        println!("{}", line);
+                    }
+                }
+                for line in
+                    vt_optimizer::output::format_metadata_mismatches(&report.metadata_mismatches)
+                {
+                    println!("{}", line);
+                }
                 println!();
             }
             if include_summary && !hide_tile_summary_sections {
                 println!("{}", emphasize_section_heading("## Summary"));
+                if let Some(format) = report.raster_format {
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            "Tile type",
+                            format!("raster tileset ({})", format.as_str())
+                        )
+                    );
+                }
                 println!(
                     "{}",
                     format_summary_label("Number of tiles", report.overall.tile_count)
                 );
-                println!(
-                    "{}",
-                    format_summary_label("Total size", format_bytes(report.overall.total_bytes))
-                );
+                if report.raster_format.is_none() && report.raster_tiles > 0 {
+                    println!(
+                        "{}",
+                        format_summary_label("Raster tiles", report.raster_tiles)
+                    );
+                }
+                if !report.media_types.is_empty() {
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            "Media types",
+                            vt_optimizer::output::format_media_types(&report.media_types)
+                        )
+                    );
+                }
+                if let Some(stored_bytes) = report.stored_bytes {
+                    println!(
+                        "{}",
+                        format_summary_label("Total size (stored)", format_bytes(stored_bytes))
+                    );
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            "Total size (uncompressed)",
+                            format_bytes(report.overall.total_bytes)
+                        )
+                    );
+                } else {
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            "Total size",
+                            format_bytes(report.overall.total_bytes)
+                        )
+                    );
+                }
                 println!(
                     "{}",
                     format_summary_label("Max tile size", format_bytes(report.overall.max_bytes))
                 );
                 if args.max_tile_bytes > 0 {
+                    if let Some(exact) = report.over_limit_tiles_exact {
+                        println!(
+                            "{}",
+                            format_summary_label("Tiles over limit (exact)", exact)
+                        );
+                    }
+                    if let Some(sampled) = report.over_limit_tiles_sampled {
+                        println!(
+                            "{}",
+                            format_summary_label(
+                                "Tiles over limit (sampled estimate)",
+                                format!(
+                                    "{} ({} of {} sampled tiles)",
+                                    sampled.estimate, sampled.sample_count, sampled.sample_size
+                                )
+                            )
+                        );
+                    }
+                }
+                if report.corrupt_rows > 0 {
                     println!(
                         "{}",
-                        format_summary_label("Tiles over limit", report.over_limit_tiles)
+                        format_summary_label("Corrupt rows skipped", report.corrupt_rows)
                     );
                 }
                 println!(
@@ -466,23 +1149,105 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                         format_bytes(report.overall.avg_bytes)
                     )
                 );
-                println!(
-                    "{}",
-                    format_summary_label("Empty tiles", report.empty_tiles)
-                );
+                match report.small_tiles {
+                    Some(small_tiles) => {
+                        println!(
+                            "{}",
+                            format_summary_label(
+                                &format!(
+                                    "Empty tiles (no features, <= {} bytes)",
+                                    report.empty_tile_max_bytes
+                                ),
+                                report.empty_tiles
+                            )
+                        );
+                        println!(
+                            "{}",
+                            format_summary_label(
+                                &format!(
+                                    "Small tiles (with features, <= {} bytes)",
+                                    report.empty_tile_max_bytes
+                                ),
+                                small_tiles
+                            )
+                        );
+                    }
+                    None => println!(
+                        "{}",
+                        format_summary_label(
+                            &format!("Empty tiles (<= {} bytes)", report.empty_tile_max_bytes),
+                            report.empty_tiles
+                        )
+                    ),
+                }
                 println!(
                     "{}",
                     format_summary_label("Empty tile ratio", format!("{:.4}", report.empty_ratio))
                 );
+                if let Some(dedup) = report.dedup.as_ref() {
+                    let suffix = if dedup.estimated {
+                        " (estimated from sample)"
+                    } else {
+                        ""
+                    };
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            "Unique tiles",
+                            format!("{}{suffix}", dedup.overall.unique_tiles)
+                        )
+                    );
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            "Duplicate tiles",
+                            format!("{}{suffix}", dedup.overall.duplicate_tiles)
+                        )
+                    );
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            "Bytes reclaimable by dedup",
+                            format!("{}{suffix}", format_bytes(dedup.overall.reclaimable_bytes))
+                        )
+                    );
+                }
+                if let Some(content_hash) = report.content_hash.as_ref() {
+                    println!(
+                        "{}",
+                        format_summary_label(
+                            &format!("Content hash ({})", content_hash.algorithm),
+                            &content_hash.digest
+                        )
+                    );
+                }
+                if let Some(validation) = report.mvt_validation.as_ref() {
+                    println!(
+                        "{}",
+                        format_summary_label("Invalid MVT tiles", validation.invalid_tiles)
+                    );
+                    for error in validation.errors.iter() {
+                        println!(
+                            "  z={} x={} y={}: {}",
+                            error.zoom, error.x, error.y, error.error
+                        );
+                    }
+                }
                 if report.sampled {
                     println!(
                         "{}",
                         format_summary_label(
                             "sample",
-                            format!(
-                                "used={} total={}",
-                                report.sample_used_tiles, report.sample_total_tiles
-                            )
+                            match report.sample_seed {
+                                Some(seed) => format!(
+                                    "used={} total={} seed={}",
+                                    report.sample_used_tiles, report.sample_total_tiles, seed
+                                ),
+                                None => format!(
+                                    "used={} total={}",
+                                    report.sample_used_tiles, report.sample_total_tiles
+                                ),
+                            }
                         )
                     );
                 }
@@ -523,6 +1288,19 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                 ) {
                     println!("{}", emphasize_table_header(&line));
                 }
+                if args.sparkline {
+                    let min_zoom = report.by_zoom.iter().map(|item| item.zoom).min();
+                    let max_zoom = report.by_zoom.iter().map(|item| item.zoom).max();
+                    if let (Some(min_zoom), Some(max_zoom)) = (min_zoom, max_zoom) {
+                        println!();
+                        println!(
+                            "  bytes z{}-z{} {}",
+                            min_zoom,
+                            max_zoom,
+                            vt_optimizer::output::format_zoom_sparkline(&report.by_zoom)
+                        );
+                    }
+                }
                 if args.zoom.is_none() {
                     println!();
                     println!(
@@ -593,7 +1371,118 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                     .to_string()
                     .len()
                     .max("# of values".len());
-                let layers_header = format!(
+                let show_invalid = report
+                    .file_layers
+                    .iter()
+                    .any(|l| l.invalid_geometry_count.is_some());
+                let invalid_width = report
+                    .file_layers
+                    .iter()
+                    .filter_map(|l| l.invalid_geometry_count)
+                    .max()
+                    .unwrap_or(0)
+                    .to_string()
+                    .len()
+                    .max("# of invalid".len());
+                let show_attributes = report
+                    .file_layers
+                    .iter()
+                    .any(|l| l.attribute_bytes.is_some());
+                let attribute_columns = |layer: &vt_optimizer::mbtiles::FileLayerSummary| {
+                    let geometry = layer.geometry_bytes.unwrap_or(0);
+                    let attributes = layer.attribute_bytes.unwrap_or(0);
+                    let total = geometry + attributes;
+                    [
+                        format_bytes(geometry),
+                        format_bytes(attributes),
+                        format!(
+                            "{:.1}%",
+                            if total == 0 {
+                                0.0
+                            } else {
+                                attributes as f64 * 100.0 / total as f64
+                            }
+                        ),
+                    ]
+                };
+                let attribute_titles = ["geometry", "attributes", "attr %"];
+                let attribute_widths = report.file_layers.iter().fold(
+                    attribute_titles.map(str::len),
+                    |mut widths, layer| {
+                        for (width, value) in widths.iter_mut().zip(attribute_columns(layer)) {
+                            *width = (*width).max(value.len());
+                        }
+                        widths
+                    },
+                );
+                let show_layer_bytes = report
+                    .file_layers
+                    .iter()
+                    .any(|l| l.approx_bytes_field_length.is_some());
+                let layer_bytes_columns = |layer: &vt_optimizer::mbtiles::FileLayerSummary| {
+                    [
+                        format_bytes(layer.approx_bytes_field_length.unwrap_or(0)),
+                        format!("{:.1}%", layer.pct_of_total.unwrap_or(0.0)),
+                    ]
+                };
+                let layer_bytes_titles = ["approx bytes", "% of total"];
+                let layer_bytes_widths = report.file_layers.iter().fold(
+                    layer_bytes_titles.map(str::len),
+                    |mut widths, layer| {
+                        for (width, value) in widths.iter_mut().zip(layer_bytes_columns(layer)) {
+                            *width = (*width).max(value.len());
+                        }
+                        widths
+                    },
+                );
+                let style_status = |layer: &vt_optimizer::mbtiles::FileLayerSummary| {
+                    let preview = report.style_preview.as_ref()?;
+                    let layer = preview.layers.iter().find(|l| l.name == layer.name)?;
+                    Some(match layer.status {
+                        vt_optimizer::mbtiles::StyleLayerStatus::Kept => "kept".to_string(),
+                        vt_optimizer::mbtiles::StyleLayerStatus::Dropped => "dropped".to_string(),
+                        vt_optimizer::mbtiles::StyleLayerStatus::Partial => format!(
+                            "kept {}",
+                            vt_optimizer::output::format_zoom_ranges(&layer.kept_zooms)
+                        ),
+                    })
+                };
+                let show_style = report.style_preview.is_some();
+                let style_width = report
+                    .file_layers
+                    .iter()
+                    .filter_map(style_status)
+                    .fold("style".len(), |width, value| width.max(value.len()));
+                let show_per_tile = report
+                    .file_layers
+                    .iter()
+                    .any(|l| l.tiles_with_layer.is_some());
+                let per_tile_columns = |layer: &vt_optimizer::mbtiles::FileLayerSummary| {
+                    [
+                        layer.tiles_with_layer.unwrap_or(0).to_string(),
+                        layer.min_features_per_tile.unwrap_or(0).to_string(),
+                        layer.max_features_per_tile.unwrap_or(0).to_string(),
+                        format!("{:.1}", layer.avg_features_per_tile.unwrap_or(0.0)),
+                    ]
+                };
+                let per_tile_titles = [
+                    "# of tiles",
+                    "min feat/tile",
+                    "max feat/tile",
+                    "avg feat/tile",
+                ];
+                let per_tile_widths = per_tile_titles.map(str::len);
+                let per_tile_widths =
+                    report
+                        .file_layers
+                        .iter()
+                        .fold(per_tile_widths, |mut widths, layer| {
+                            for (width, value) in widths.iter_mut().zip(per_tile_columns(layer)) {
+                                *width = (*width).max(value.len());
+                            }
+                            widths
+                        });
+                let mut layers_header = format!(
                     "  {} {} {} {} {}",
                     pad_right("name", name_width),
                     pad_left("# of vertices", vertices_width),
@@ -601,9 +1490,36 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                     pad_left("# of keys", keys_width),
                     pad_left("# of values", values_width),
                 );
+                if show_invalid {
+                    layers_header.push(' ');
+                    layers_header.push_str(&pad_left("# of invalid", invalid_width));
+                }
+                if show_attributes {
+                    for (title, width) in attribute_titles.iter().zip(attribute_widths) {
+                        layers_header.push(' ');
+                        layers_header.push_str(&pad_left(title, width));
+                    }
+                }
+                if show_layer_bytes {
+                    for (title, width) in layer_bytes_titles.iter().zip(layer_bytes_widths) {
+                        layers_header.push(' ');
+                        layers_header.push_str(&pad_left(title, width));
+                    }
+                }
+                if show_style {
+                    layers_header.push(' ');
+                    layers_header.push_str(&pad_right("style", style_width));
+                }
+                if show_per_tile {
+                    for (title, width) in per_tile_titles.iter().zip(per_tile_widths) {
+                        layers_header.push(' ');
+                        layers_header.push_str(&pad_left(title, width));
+                    }
+                    layers_header.push_str(" top keys");
+                }
                 println!("{}", emphasize_table_header(&layers_header));
                 for layer in report.file_layers.iter() {
-                    println!(
+                    let mut row = format!(
                         "  {} {} {} {} {}",
                         pad_right(&layer.name, name_width),
                         pad_left(&layer.vertex_count.to_string(), vertices_width),
@@ -611,24 +1527,142 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                         pad_left(&layer.property_key_count.to_string(), keys_width),
                         pad_left(&layer.property_value_count.to_string(), values_width),
                     );
+                    if show_invalid {
+                        let invalid = layer.invalid_geometry_count.unwrap_or(0);
+                        row.push(' ');
+                        row.push_str(&pad_left(&invalid.to_string(), invalid_width));
+                    }
+                    if show_attributes {
+                        for (value, width) in attribute_columns(layer).iter().zip(attribute_widths)
+                        {
+                            row.push(' ');
+                            row.push_str(&pad_left(value, width));
+                        }
+                    }
+                    if show_layer_bytes {
+                        for (value, width) in
+                            layer_bytes_columns(layer).iter().zip(layer_bytes_widths)
+                        {
+                            row.push(' ');
+                            row.push_str(&pad_left(value, width));
+                        }
+                    }
+                    if show_style {
+                        row.push(' ');
+                        row.push_str(&pad_right(
+                            &style_status(layer).unwrap_or_default(),
+                            style_width,
+                        ));
+                    }
+                    if show_per_tile {
+                        for (value, width) in per_tile_columns(layer).iter().zip(per_tile_widths) {
+                            row.push(' ');
+                            row.push_str(&pad_left(value, width));
+                        }
+                        let top_keys = layer
+                            .top_keys
+                            .iter()
+                            .map(|(key, count)| format!("{key}({count})"))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        row.push(' ');
+                        row.push_str(&top_keys);
+                    }
+                    println!("{}", row);
+                }
+                if let Some(preview) = report.style_preview.as_ref() {
+                    let bytes = match (preview.removed_bytes, preview.removed_bytes_pct) {
+                        (Some(bytes), Some(pct)) => format!("{} ({pct:.1}%)", format_bytes(bytes)),
+                        (Some(bytes), None) => format_bytes(bytes),
+                        _ => "unknown bytes".to_string(),
+                    };
+                    let features = match (preview.removed_features, preview.removed_features_pct) {
+                        (Some(features), Some(pct)) => format!("{features} features ({pct:.1}%)"),
+                        (Some(features), None) => format!("{features} features"),
+                        _ => "unknown features".to_string(),
+                    };
+                    println!();
+                    println!("  Style preview: would remove {bytes} and {features}");
+                }
+            }
+            if !hide_tile_summary_sections && !report.layer_zoom_matrix.is_empty() {
+                println!();
+                println!("{}", emphasize_section_heading("## Layers by zoom"));
+                for line in
+                    vt_optimizer::output::format_layer_zoom_matrix(&report.layer_zoom_matrix)
+                {
+                    println!("{}", emphasize_table_header(&line));
+                }
+            }
+            if !hide_tile_summary_sections {
+                for histogram in report.property_histograms.iter() {
+                    println!();
+                    println!(
+                        "{}",
+                        emphasize_section_heading(&format!(
+                            "## Property values: {}.{}",
+                            histogram.layer, histogram.key
+                        ))
+                    );
+                    let mut lines =
+                        vt_optimizer::output::format_property_histogram(histogram).into_iter();
+                    if let Some(header) = lines.next() {
+                        println!("{}", emphasize_table_header(&header));
+                    }
+                    for line in lines {
+                        println!("{}", line);
+                    }
+                    if histogram.truncated {
+                        println!("  (rarer values dropped; counts are lower bounds)");
+                    }
+                }
+            }
+            if !hide_tile_summary_sections && !report.high_cardinality_keys.is_empty() {
+                println!();
+                println!(
+                    "{}",
+                    emphasize_section_heading("## Warning: high-cardinality property keys")
+                );
+                println!(
+                    "  These keys have nearly one distinct value per feature; check them for IDs or other unique data."
+                );
+                let mut lines = vt_optimizer::output::format_high_cardinality_keys(
+                    &report.high_cardinality_keys,
+                )
+                .into_iter();
+                if let Some(header) = lines.next() {
+                    println!("{}", emphasize_table_header(&header));
+                }
+                for line in lines {
+                    println!("{}", line);
                 }
             }
             if show_layers_tip && !include_summary {
                 println!();
                 println!("Tip: use --include-layer-list to include layer statistics.");
             }
-            if include_recommendations && !report.recommended_buckets.is_empty() {
+            if include_recommendations
+                && (!report.recommended_buckets.is_empty() || !report.recommendations.is_empty())
+            {
                 println!();
                 println!("{}", emphasize_section_heading("## Recommendations"));
-                println!(
-                    "- buckets: {}",
-                    report
-                        .recommended_buckets
-                        .iter()
-                        .map(|idx| idx.to_string())
-                        .collect::<Vec<_>>()
-                        .join(",")
-                );
+                if !report.recommended_buckets.is_empty() {
+                    println!(
+                        "- buckets: {}",
+                        report
+                            .recommended_buckets
+                            .iter()
+                            .map(|idx| idx.to_string())
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    );
+                }
+                for recommendation in report.recommendations.iter() {
+                    println!(
+                        "- {}",
+                        vt_optimizer::output::format_recommendation(recommendation)
+                    );
+                }
             }
             if include_bucket && let Some(count) = report.bucket_count {
                 println!();
@@ -639,9 +1673,19 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                 println!();
                 println!("{}", emphasize_section_heading("## Bucket Tiles"));
                 for tile in report.bucket_tiles.iter() {
+                    let hash = tile
+                        .hash
+                        .as_ref()
+                        .map(|hash| format!(" hash={hash}"))
+                        .unwrap_or_default();
+                    let url = tile
+                        .url
+                        .as_ref()
+                        .map(|url| format!(" {url}"))
+                        .unwrap_or_default();
                     println!(
-                        "- z={}: x={} y={} bytes={}",
-                        tile.zoom, tile.x, tile.y, tile.bytes
+                        "- z={}: x={} y={} bytes={}{}{}",
+                        tile.zoom, tile.x, tile.y, tile.bytes, hash, url
                     );
                 }
             }
@@ -672,18 +1716,24 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                     );
                     for layer in summary.layers.iter() {
                         println!(
-                            "  {}: {} features={} vertices={} property_keys={} values={}",
+                            "  {}: {} features={} vertices={} property_keys={} values={}{}{}",
                             Style::new().fg(Color::Blue).paint("layer"),
                             layer.name,
                             layer.feature_count,
                             layer.vertex_count,
                             layer.property_key_count,
-                            layer.property_value_count
+                            layer.property_value_count,
+                            format_invalid_geometries(layer.invalid_geometry_count),
+                            format_duplicate_features(layer.duplicate_feature_count)
                         );
                     }
                 }
             }
-            if include_tile_summary && let Some(summary) = report.tile_summary.as_ref() {
+            for summary in report
+                .tile_summaries
+                .iter()
+                .filter(|_| include_tile_summary)
+            {
                 println!();
                 println!("{}", emphasize_section_heading("## Tile Summary"));
                 for line in vt_optimizer::output::format_tile_summary_text(summary) {
@@ -691,13 +1741,15 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
                 }
                 for layer in summary.layers.iter() {
                     println!(
-                        "  {}: {} features={} vertices={} property_keys={} values={}",
+                        "  {}: {} features={} vertices={} property_keys={} values={}{}{}",
                         Style::new().fg(Color::Blue).paint("layer"),
                         layer.name,
                         layer.feature_count,
                         layer.vertex_count,
                         layer.property_key_count,
-                        layer.property_value_count
+                        layer.property_value_count,
+                        format_invalid_geometries(layer.invalid_geometry_count),
+                        format_duplicate_features(layer.duplicate_feature_count)
                     );
                     if !layer.property_keys.is_empty() {
                         println!(
@@ -710,10 +1762,231 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
             }
         }
     }
+    if let Some(validation) = report.mvt_validation.as_ref()
+        && validation.invalid_tiles > 0
+    {
+        anyhow::bail!("{} tiles failed MVT validation", validation.invalid_tiles);
+    }
+    Ok(())
+}
+
+fn run_simplify(args: vt_optimizer::cli::SimplifyArgs) -> Result<()> {
+    let started = Instant::now();
+    let input_format =
+        vt_optimizer::format::resolve_input_format(&args.input, args.input_format.as_deref())?;
+    let output = args.output.clone().unwrap_or_else(|| match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => {
+            args.input.with_extension("simplified.mbtiles")
+        }
+        vt_optimizer::format::TileFormat::Pmtiles => {
+            args.input.with_extension("simplified.pmtiles")
+        }
+    });
+
+    match (args.z, args.x, args.y) {
+        (Some(z), Some(x), Some(y)) => {
+            if args.all_zooms || args.over_limit_only {
+                anyhow::bail!("--x/--y select a single tile; drop --all-zooms/--over-limit-only");
+            }
+            if args.report.is_some() {
+                anyhow::bail!("--report is only supported for batch simplify");
+            }
+            let coord = vt_optimizer::mbtiles::TileCoord { zoom: z, x, y };
+            let stats = match input_format {
+                vt_optimizer::format::TileFormat::Mbtiles => simplify_mbtiles_tile(
+                    &args.input,
+                    &output,
+                    coord,
+                    &args.layer,
+                    args.tolerance,
+                    args.buffer,
+                    args.preserve_topology,
+                )?,
+                vt_optimizer::format::TileFormat::Pmtiles => simplify_pmtiles_tile(
+                    &args.input,
+                    &output,
+                    coord,
+                    &args.layer,
+                    args.tolerance,
+                    args.buffer,
+                    args.preserve_topology,
+                )?,
+            };
+            println!(
+                "simplify: input={} output={} z={} x={} y={} features={} vertices={}=>{}",
+                args.input.display(),
+                output.display(),
+                z,
+                x,
+                y,
+                stats.feature_count,
+                stats.vertices_before,
+                stats.vertices_after
+            );
+            print_simplify_ring_stats(&stats);
+            return Ok(());
+        }
+        (_, None, None) => {}
+        _ => anyhow::bail!("single-tile simplify requires --z, --x and --y together"),
+    }
+
+    if args.z.is_some() && args.all_zooms {
+        anyhow::bail!("--zoom and --all-zooms are mutually exclusive");
+    }
+    // Without a zoom or size selector every tile is simplified.
+    let whole_file = args.z.is_none() && !args.over_limit_only;
+    let threads = args.threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let options = SimplifyOptions {
+        layers: args.layer.clone(),
+        tolerance: args.tolerance,
+        buffer: args.buffer,
+        preserve_topology: args.preserve_topology,
+        zoom: args.z,
+        over_limit_only: args.over_limit_only,
+        max_tile_bytes: args.max_tile_bytes,
+        threads,
+        readers: threads,
+        io_batch: 1_000,
+    };
+    let stats = match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles if whole_file => {
+            simplify_mbtiles_all(&args.input, &output, &options)?
+        }
+        vt_optimizer::format::TileFormat::Mbtiles => {
+            simplify_mbtiles_batch(&args.input, &output, &options)?
+        }
+        vt_optimizer::format::TileFormat::Pmtiles => {
+            simplify_pmtiles_batch(&args.input, &output, &options)?
+        }
+    };
+    let totals = stats.totals();
+    println!(
+        "simplify: input={} output={} tiles={}/{} features={} vertices={}=>{}",
+        args.input.display(),
+        output.display(),
+        stats.tiles_simplified(),
+        stats.tiles_read,
+        totals.feature_count,
+        totals.vertices_before,
+        totals.vertices_after
+    );
+    print_simplify_ring_stats(&totals);
+    for (zoom, zoom_stats) in stats.by_zoom.iter() {
+        println!(
+            "  z{zoom}: tiles={} features={} vertices={}=>{}",
+            zoom_stats.tiles,
+            zoom_stats.stats.feature_count,
+            zoom_stats.stats.vertices_before,
+            zoom_stats.stats.vertices_after
+        );
+    }
+    let reduction = if totals.vertices_before == 0 {
+        0.0
+    } else {
+        (totals.vertices_before as f64 - totals.vertices_after as f64)
+            / totals.vertices_before as f64
+            * 100.0
+    };
+    println!(
+        "vertices {}=>{} ({reduction:.2}% reduction)",
+        totals.vertices_before, totals.vertices_after
+    );
+    if let Some(path) = args.report.as_ref() {
+        let report_options = serde_json::json!({
+            "layers": args.layer,
+            "tolerance": args.tolerance,
+            "buffer": args.buffer,
+            "preserve_topology": args.preserve_topology,
+            "zoom": args.z,
+            "all_zooms": args.all_zooms,
+            "over_limit_only": args.over_limit_only,
+            "max_tile_bytes": args.max_tile_bytes,
+            "threads": threads,
+        });
+        vt_optimizer::report::OptimizeReport::for_simplify(
+            &args.input,
+            &output,
+            started.elapsed(),
+            report_options,
+            stats,
+        )?
+        .write(path)?;
+    }
+    Ok(())
+}
+
+fn run_overzoom(args: vt_optimizer::cli::OverzoomArgs) -> Result<()> {
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.input.with_extension("overzoom.mbtiles"));
+    let threads = args.threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let options = OverzoomOptions {
+        target_zoom: args.target_zoom,
+        buffer: args.buffer,
+        threads,
+        readers: threads,
+        io_batch: 1_000,
+        no_progress: args.no_progress,
+    };
+    let stats = overzoom_mbtiles(&args.input, &output, &options)?;
+    println!(
+        "overzoom: input={} output={} parents={} generated={} empty_dropped={}",
+        args.input.display(),
+        output.display(),
+        stats.parent_tiles,
+        stats.generated_tiles(),
+        stats.empty_children
+    );
+    for (zoom, count) in stats.generated_by_zoom.iter() {
+        println!("  z{zoom}: tiles={count}");
+    }
+    Ok(())
+}
+
+fn print_simplify_ring_stats(stats: &vt_optimizer::mbtiles::SimplifyStats) {
+    if stats.dropped_polygons + stats.dropped_holes + stats.restored_rings == 0 {
+        return;
+    }
+    println!(
+        "  rings: dropped_polygons={} dropped_holes={} restored={}",
+        stats.dropped_polygons, stats.dropped_holes, stats.restored_rings
+    );
+}
+
+fn run_style_check(args: vt_optimizer::cli::StyleCheckArgs) -> Result<()> {
+    let style = read_style_for_source(&args.style, args.source.as_deref())?;
+    let report = style.check(args.max_zoom);
+    match args.report_format {
+        ReportFormat::Text => {
+            println!("style-check: style={}", args.style.display());
+            for line in vt_optimizer::output::format_style_check_lines(&report) {
+                println!("{line}");
+            }
+        }
+        ReportFormat::Json => println!(
+            "{}",
+            vt_optimizer::output::json_report_string(&report, args.compact)?
+        ),
+        ReportFormat::Ndjson => println!("{}", serde_json::to_string(&report)?),
+    }
+    let unsupported = report.unsupported_layers();
+    if args.strict && unsupported > 0 {
+        anyhow::bail!("{unsupported} style layer filter(s) use unsupported expressions");
+    }
     Ok(())
 }
 
 fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
+    let started = Instant::now();
     let report_format = args.report_format;
     let emit_logs = report_format == ReportFormat::Text;
     let decision = plan_optimize(
@@ -723,22 +1996,105 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
         args.output_format.as_deref(),
     )?;
     let output_path = resolve_output_path(&args.input, args.output.as_deref(), decision.output);
-    let style_path = args
-        .style
-        .as_ref()
-        .context("--style is required for optimize")?;
+    if args.resume && args.checkpoint.is_none() {
+        anyhow::bail!("--resume requires --checkpoint");
+    }
+    if args.gzip_level > 9 {
+        anyhow::bail!("--gzip-level must be between 0 and 9");
+    }
+    let recompress = args
+        .recompress
+        .as_deref()
+        .map(parse_tile_compression)
+        .transpose()?;
+    if args
+        .drop_tiny_features
+        .into_iter()
+        .chain(args.merge_nearby_points)
+        .any(|value| value < 0.0)
+    {
+        anyhow::bail!("--drop-tiny-features/--merge-nearby-points must not be negative");
+    }
+    let cleanup = FeatureCleanup {
+        drop_tiny_features: args.drop_tiny_features,
+        merge_nearby_points: args.merge_nearby_points,
+        exclude_layers: args.cleanup_exclude_layers.iter().cloned().collect(),
+        feature_ids: match args.feature_ids {
+            FeatureIdsArg::Keep => FeatureIdMode::Keep,
+            FeatureIdsArg::Drop => FeatureIdMode::Drop,
+            FeatureIdsArg::Reindex => FeatureIdMode::Reindex,
+        },
+    };
+    if !cleanup.exclude_layers.is_empty() && !cleanup.is_enabled() {
+        anyhow::bail!(
+            "--cleanup-exclude-layers requires --drop-tiny-features or --merge-nearby-points"
+        );
+    }
+    let checkpoint =
+        args.checkpoint
+            .as_ref()
+            .map(|path| vt_optimizer::mbtiles::CheckpointOptions {
+                path: path.clone(),
+                resume: args.resume,
+                interval: vt_optimizer::mbtiles::DEFAULT_CHECKPOINT_INTERVAL,
+            });
+    let explicit_layers = !args.keep_layer.is_empty() || !args.drop_layer.is_empty();
+    if args.style.is_empty() && !explicit_layers {
+        anyhow::bail!("--style (or --keep-layer/--drop-layer) is required for optimize");
+    }
+    if explicit_layers && args.prune_attributes {
+        anyhow::bail!("--prune-attributes requires --style");
+    }
+    if explicit_layers && args.source.is_some() {
+        anyhow::bail!("--source requires --style");
+    }
     if args.style_mode != vt_optimizer::cli::StyleMode::Layer
         && args.style_mode != vt_optimizer::cli::StyleMode::LayerFilter
         && args.style_mode != vt_optimizer::cli::StyleMode::VtCompat
     {
         anyhow::bail!("v0.0.55 only supports --style-mode layer, layer+filter, or vt-compat");
     }
-    let input_stats = collect_optimize_io_stats(&args.input, decision.input)?;
+    if args.sample.is_some() && !args.dry_run {
+        anyhow::bail!("--sample requires --dry-run");
+    }
     if emit_logs {
         println!("Prune steps");
-        println!("- Parsing style file");
     }
-    let style = read_style(style_path)?;
+    let selection = if explicit_layers {
+        if emit_logs {
+            println!("- Using explicit layer list (style zooms and filters do not apply)");
+        }
+        LayerSelection::ExplicitList {
+            keep: (!args.keep_layer.is_empty()).then(|| args.keep_layer.iter().cloned().collect()),
+            drop: args.drop_layer.iter().cloned().collect(),
+        }
+    } else {
+        if emit_logs {
+            println!("- Parsing style file");
+        }
+        let style = read_styles(&args.style, args.source.as_deref())?;
+        if emit_logs && !style.sources().is_empty() {
+            println!("- Using style source: {}", style.sources().join(", "));
+        }
+        LayerSelection::StyleDriven(style)
+    };
+    let keep_keys = selection
+        .style()
+        .filter(|_| args.prune_attributes)
+        .map(|style| style.referenced_property_keys());
+    let layer_renames = parse_layer_renames(&args.rename_layer)?;
+    if args.dry_run {
+        return run_optimize_dry_run(
+            &args,
+            decision.input,
+            &selection,
+            cleanup,
+            recompress,
+            keep_keys,
+            layer_renames,
+        );
+    }
+    let input_stats = collect_optimize_io_stats(&args.input, decision.input)?;
     let stats = match (decision.input, decision.output) {
         (vt_optimizer::format::TileFormat::Mbtiles, vt_optimizer::format::TileFormat::Mbtiles) => {
             let apply_filters = args.style_mode == vt_optimizer::cli::StyleMode::LayerFilter;
@@ -754,10 +2110,10 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
                     args.io_batch,
                 );
             }
-            let stats = prune_mbtiles_layer_only(
+            let stats = prune_mbtiles_layer_only_with_checkpoint(
                 &args.input,
                 &output_path,
-                &style,
+                selection.clone(),
                 apply_filters,
                 PruneOptions {
                     threads,
@@ -765,10 +2121,29 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
                     readers,
                     read_cache_mb: args.read_cache_mb,
                     write_cache_mb: args.write_cache_mb,
-                    drop_empty_tiles: args.drop_empty_tiles,
+                    drop_empty_tiles: args.drop_empty_tiles || args.drop_empty_threshold.is_some(),
                     keep_unknown_filters: args.unknown_filter
                         == vt_optimizer::cli::UnknownFilterMode::Keep,
+                    fix_winding: args.fix_winding,
+                    compression_level: args.gzip_level,
+                    recompress,
+                    cleanup,
+                    dedup: args.dedup,
+                    no_progress: args.no_progress,
+                    clamp_zoom: args.clamp_zoom,
+                    append: args.append,
+                    keep_keys,
+                    layer_renames,
+                    keep_original_metadata: args.keep_original_metadata,
+                    drop_empty_max_bytes: args.drop_empty_threshold,
+                    output_order: match args.output_order {
+                        OutputOrderArg::None => OutputOrder::None,
+                        OutputOrderArg::Zxy => OutputOrder::Zxy,
+                        OutputOrderArg::Hilbert => OutputOrder::Hilbert,
+                    },
+                    strict: args.strict,
                 },
+                checkpoint.as_ref(),
             )?;
             if emit_logs {
                 println!("- Writing output file to {}", output_path.display());
@@ -776,16 +2151,52 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
             stats
         }
         (vt_optimizer::format::TileFormat::Pmtiles, vt_optimizer::format::TileFormat::Pmtiles) => {
+            if checkpoint.is_some() {
+                anyhow::bail!("--checkpoint/--resume are only supported for MBTiles optimize");
+            }
+            if args.dedup {
+                anyhow::bail!("--dedup is only supported for MBTiles optimize");
+            }
+            if args.append {
+                anyhow::bail!("--append is only supported for MBTiles optimize");
+            }
+            if args.drop_empty_threshold.is_some() {
+                anyhow::bail!("--drop-empty-threshold is only supported for MBTiles optimize");
+            }
+            if args.output_order != OutputOrderArg::None {
+                anyhow::bail!(
+                    "--output-order is only supported for MBTiles optimize (PMTiles output is always in tile id order)"
+                );
+            }
             let apply_filters = args.style_mode == vt_optimizer::cli::StyleMode::LayerFilter;
+            let threads = args.threads.unwrap_or_else(|| {
+                thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            });
             if emit_logs {
-                println!("- Processing tiles");
+                println!("- Processing tiles (threads={threads})");
             }
-            let stats = prune_pmtiles_layer_only(
+            let stats = prune_pmtiles_layer_only_with_threads(
                 &args.input,
                 &output_path,
-                &style,
+                selection.clone(),
                 apply_filters,
                 args.unknown_filter == vt_optimizer::cli::UnknownFilterMode::Keep,
+                args.fix_winding,
+                args.gzip_level,
+                recompress,
+                &cleanup,
+                threads,
+                args.no_progress,
+                args.clamp_zoom,
+                keep_keys.as_ref(),
+                &layer_renames,
+                args.keep_original_metadata,
+                PmtilesWriteOptions {
+                    brotli_quality: args.brotli_quality,
+                    brotli_window: args.brotli_window,
+                },
             )?;
             if emit_logs {
                 println!("- Writing output file to {}", output_path.display());
@@ -798,7 +2209,60 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
     };
     let output_stats = collect_optimize_io_stats(&output_path, decision.output)?;
     let optimization = build_optimization_summary(&input_stats, &output_stats, &stats);
-    let details = build_optimize_details(&stats);
+    let mut details = build_optimize_details(&stats);
+    if let Some(path) = args.report.as_ref() {
+        let report_options = serde_json::json!({
+            "style": args.style,
+            "source": args.source,
+            "keep_layer": args.keep_layer,
+            "drop_layer": args.drop_layer,
+            "style_mode": value_name(args.style_mode),
+            "unknown_filter": value_name(args.unknown_filter),
+            "max_tile_bytes": args.max_tile_bytes,
+            "threads": args.threads,
+            "readers": args.readers,
+            "io_batch": args.io_batch,
+            "drop_empty_tiles": args.drop_empty_tiles,
+            "drop_empty_threshold": args.drop_empty_threshold,
+            "fix_winding": args.fix_winding,
+            "gzip_level": args.gzip_level,
+            "recompress": args.recompress,
+            "brotli_quality": args.brotli_quality,
+            "brotli_window": args.brotli_window,
+            "drop_tiny_features": args.drop_tiny_features,
+            "merge_nearby_points": args.merge_nearby_points,
+            "cleanup_exclude_layers": args.cleanup_exclude_layers,
+            "feature_ids": value_name(args.feature_ids),
+            "dedup": args.dedup,
+            "checkpoint": args.checkpoint,
+            "resume": args.resume,
+            "clamp_zoom": args.clamp_zoom,
+            "append": args.append,
+            "prune_attributes": args.prune_attributes,
+            "rename_layer": args.rename_layer,
+            "keep_original_metadata": args.keep_original_metadata,
+        });
+        vt_optimizer::report::OptimizeReport::for_prune(
+            &args.input,
+            &output_path,
+            started.elapsed(),
+            report_options,
+            stats,
+        )?
+        .write(path)?;
+    }
+    if explicit_layers {
+        details.dropped_layers = details.removed_layers_by_zoom.keys().cloned().collect();
+    }
+    if args.style.len() > 1
+        && let Some(style) = selection.style()
+    {
+        details.retained_by_style = style
+            .retaining_styles(STYLE_CHECK_MAX_ZOOM)
+            .into_iter()
+            .filter(|(_, styles)| !styles.is_empty())
+            .collect();
+    }
     if emit_logs {
         print_optimize_summary(&input_stats, &output_stats, &optimization, &details);
         println!(
@@ -816,7 +2280,10 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
         match report_format {
             ReportFormat::Text => {}
             ReportFormat::Json => {
-                println!("{}", serde_json::to_string_pretty(&report)?);
+                println!(
+                    "{}",
+                    vt_optimizer::output::json_report_string(&report, args.compact)?
+                );
             }
             ReportFormat::Ndjson => {
                 println!("{}", serde_json::to_string(&report)?);
@@ -826,6 +2293,162 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
     Ok(())
 }
 
+/// `optimize --dry-run`: prune the sampled tiles in memory and report the
+/// projection instead of writing an output.
+fn run_optimize_dry_run(
+    args: &vt_optimizer::cli::OptimizeArgs,
+    input_format: vt_optimizer::format::TileFormat,
+    selection: &LayerSelection,
+    cleanup: FeatureCleanup,
+    recompress: Option<TileCompression>,
+    keep_keys: Option<std::collections::HashMap<String, std::collections::HashSet<String>>>,
+    layer_renames: std::collections::HashMap<String, String>,
+) -> Result<()> {
+    let sample = args.sample.as_deref().map(parse_sample_spec).transpose()?;
+    let apply_filters = args.style_mode == vt_optimizer::cli::StyleMode::LayerFilter;
+    let mut options = PruneOptions {
+        threads: 1,
+        io_batch: args.io_batch,
+        readers: 1,
+        read_cache_mb: args.read_cache_mb,
+        write_cache_mb: None,
+        drop_empty_tiles: args.drop_empty_tiles || args.drop_empty_threshold.is_some(),
+        keep_unknown_filters: args.unknown_filter == vt_optimizer::cli::UnknownFilterMode::Keep,
+        fix_winding: args.fix_winding,
+        compression_level: args.gzip_level,
+        recompress,
+        cleanup,
+        dedup: false,
+        no_progress: args.no_progress,
+        clamp_zoom: args.clamp_zoom,
+        append: false,
+        keep_keys,
+        layer_renames,
+        keep_original_metadata: args.keep_original_metadata,
+        drop_empty_max_bytes: args.drop_empty_threshold,
+        output_order: OutputOrder::None,
+        strict: false,
+    };
+    let estimate = match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => estimate_prune(
+            &MbtilesFile::open(&args.input)?,
+            selection.clone(),
+            apply_filters,
+            &options,
+            None,
+            sample.as_ref(),
+            args.sample_seed,
+        )?,
+        vt_optimizer::format::TileFormat::Pmtiles => {
+            let source = PmtilesFile::open(&args.input)?;
+            // PMTiles optimize keeps empty tiles and the header's codec
+            options.drop_empty_tiles = false;
+            let keep = TileCompression::from_pmtiles_code(
+                source.header().tile_compression,
+                args.gzip_level,
+            )?;
+            estimate_prune(
+                &source,
+                selection.clone(),
+                apply_filters,
+                &options,
+                Some(keep),
+                sample.as_ref(),
+                args.sample_seed,
+            )?
+        }
+    };
+    let report = DryRunReport {
+        input: args.input.display().to_string(),
+        sampled_tiles: estimate.sampled_tiles(),
+        projected: estimate.projected_totals(),
+        projected_removed_features: estimate.projected_removed_features(),
+        estimate: &estimate,
+    };
+    match args.report_format {
+        ReportFormat::Text => print_dry_run_summary(&report),
+        ReportFormat::Json => println!(
+            "{}",
+            vt_optimizer::output::json_report_string(&report, args.compact)?
+        ),
+        ReportFormat::Ndjson => println!("{}", serde_json::to_string(&report)?),
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct DryRunReport<'a> {
+    input: String,
+    sampled_tiles: u64,
+    projected: ZoomTileCounts,
+    projected_removed_features: u64,
+    #[serde(flatten)]
+    estimate: &'a PruneEstimate,
+}
+
+fn print_dry_run_summary(report: &DryRunReport) {
+    let projected = &report.projected;
+    println!(
+        "{}",
+        emphasize_section_heading("## Dry run (no output written)")
+    );
+    println!(
+        "{}",
+        format_summary_label(
+            "Sampled tiles",
+            format!("{} of {}", report.sampled_tiles, projected.tiles_before)
+        )
+    );
+    println!(
+        "{}",
+        format_summary_label(
+            "Projected tiles",
+            format!(
+                "{} -> {} ({:.2}%)",
+                projected.tiles_before,
+                projected.tiles_after,
+                percent_change(projected.tiles_after, projected.tiles_before)
+            )
+        )
+    );
+    println!(
+        "{}",
+        format_summary_label(
+            "Projected total tile size",
+            format!(
+                "{} -> {} ({:.2}%)",
+                format_bytes(projected.bytes_before),
+                format_bytes(projected.bytes_after),
+                percent_change(projected.bytes_after, projected.bytes_before)
+            )
+        )
+    );
+    println!(
+        "{}",
+        format_summary_label(
+            "Projected removed features",
+            report.projected_removed_features
+        )
+    );
+    let removed_layers = &report.estimate.sample.removed_layers_by_zoom;
+    if removed_layers.is_empty() {
+        println!("{}", format_summary_label("Removed layers", "none"));
+    } else {
+        println!(
+            "- {}:",
+            Style::new().fg(Color::Blue).paint("Removed layers")
+        );
+        for (layer, zooms) in removed_layers.iter() {
+            let zoom_list = zooms
+                .iter()
+                .map(|z| z.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("  {} @ z{}", layer, zoom_list);
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct OptimizeReport {
     input: OptimizeIoStats,
@@ -869,6 +2492,48 @@ struct OptimizeDetails {
     removed_features_by_zoom: BTreeMap<u8, u64>,
     removed_layers_by_zoom: BTreeMap<String, Vec<u8>>,
     unknown_filters_by_layer: BTreeMap<String, u64>,
+    corrected_rings: u64,
+    /// Tiles skipped by `--clamp-zoom`.
+    clamped_tiles: u64,
+    raster_tiles: u64,
+    /// Vector tiles written with their input bytes because pruning changed nothing.
+    unchanged_tiles: u64,
+    /// Input rows skipped for an invalid tile address.
+    corrupt_rows: u64,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    tiny_features_by_layer: BTreeMap<String, BTreeMap<u8, u64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    merged_points_by_layer: BTreeMap<String, BTreeMap<u8, u64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    removed_keys_by_layer: BTreeMap<String, BTreeMap<String, u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dedup: Option<OptimizeDedup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recompress: Option<RecompressStats>,
+    /// Styles that keep each source layer, when several styles are given.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    retained_by_style: BTreeMap<String, Vec<String>>,
+    /// Layers removed by `--keep-layer`/`--drop-layer`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dropped_layers: Vec<String>,
+}
+
+/// Highest zoom checked when attributing retained layers to styles.
+const STYLE_CHECK_MAX_ZOOM: u8 = 22;
+
+#[derive(Serialize)]
+struct OptimizeDedup {
+    tiles: u64,
+    unique_blobs: u64,
+    ratio: f64,
+}
+
+/// The command-line spelling of a `ValueEnum` option.
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
 }
 
 fn emphasize_section_heading(line: &str) -> String {
@@ -947,6 +2612,22 @@ fn build_optimize_details(stats: &PruneStats) -> OptimizeDetails {
         removed_features_by_zoom: stats.removed_features_by_zoom.clone(),
         removed_layers_by_zoom,
         unknown_filters_by_layer: stats.unknown_filters_by_layer.clone(),
+        corrected_rings: stats.corrected_rings,
+        clamped_tiles: stats.clamped_tiles,
+        raster_tiles: stats.raster_tiles,
+        unchanged_tiles: stats.unchanged_tiles,
+        corrupt_rows: stats.corrupt_rows,
+        tiny_features_by_layer: stats.tiny_features_by_layer.clone(),
+        merged_points_by_layer: stats.merged_points_by_layer.clone(),
+        removed_keys_by_layer: stats.removed_keys_by_layer.clone(),
+        dedup: stats.dedup.map(|dedup| OptimizeDedup {
+            tiles: dedup.tiles,
+            unique_blobs: dedup.unique_blobs,
+            ratio: dedup.ratio(),
+        }),
+        recompress: stats.recompress,
+        retained_by_style: BTreeMap::new(),
+        dropped_layers: Vec::new(),
     }
 }
 
@@ -1168,6 +2849,109 @@ fn print_optimize_summary(
             println!("  {}: {}", layer, count);
         }
     }
+    if details.clamped_tiles > 0 {
+        println!(
+            "{}",
+            format_summary_label("Tiles outside style zooms skipped", details.clamped_tiles)
+        );
+    }
+    if details.raster_tiles > 0 {
+        println!(
+            "{}",
+            format_summary_label("Raster tiles copied unchanged", details.raster_tiles)
+        );
+    }
+    if details.unchanged_tiles > 0 {
+        println!(
+            "{}",
+            format_summary_label("Tiles passed through unchanged", details.unchanged_tiles)
+        );
+    }
+    if details.corrupt_rows > 0 {
+        println!(
+            "{}",
+            format_summary_label("Corrupt rows skipped", details.corrupt_rows)
+        );
+    }
+    if details.corrected_rings > 0 {
+        println!(
+            "{}",
+            format_summary_label("Corrected ring orientation", details.corrected_rings)
+        );
+    }
+    for (label, counts) in [
+        ("Dropped tiny features", &details.tiny_features_by_layer),
+        ("Merged nearby points", &details.merged_points_by_layer),
+    ] {
+        if counts.is_empty() {
+            continue;
+        }
+        println!("- {}:", Style::new().fg(Color::Blue).paint(label));
+        for (layer, zooms) in counts.iter() {
+            for (zoom, count) in zooms.iter() {
+                println!("  {} @ z{}: {}", layer, zoom, count);
+            }
+        }
+    }
+    if !details.removed_keys_by_layer.is_empty() {
+        println!(
+            "- {}:",
+            Style::new().fg(Color::Blue).paint("Removed attributes")
+        );
+        for (layer, keys) in details.removed_keys_by_layer.iter() {
+            for (key, count) in keys.iter() {
+                println!("  {}.{}: {}", layer, key, count);
+            }
+        }
+    }
+    if let Some(dedup) = details.dedup.as_ref() {
+        println!(
+            "{}",
+            format_summary_label(
+                "Deduplicated tiles",
+                format!(
+                    "{} tiles -> {} blobs ({:.2}x)",
+                    dedup.tiles, dedup.unique_blobs, dedup.ratio
+                )
+            )
+        );
+    }
+    if let Some(recompress) = details.recompress.as_ref() {
+        println!("{}", format_recompress_summary(recompress));
+    }
+    if !details.retained_by_style.is_empty() {
+        println!(
+            "- {}:",
+            Style::new()
+                .fg(Color::Blue)
+                .paint("Layers retained by style")
+        );
+        for (layer, styles) in details.retained_by_style.iter() {
+            println!("  {}: {}", layer, styles.join(", "));
+        }
+    }
+    if !details.dropped_layers.is_empty() {
+        println!(
+            "{}",
+            format_summary_label(
+                "Layers dropped by request",
+                details.dropped_layers.join(", ")
+            )
+        );
+    }
+}
+
+fn format_recompress_summary(stats: &RecompressStats) -> String {
+    format_summary_label(
+        "Recompressed tiles",
+        format!(
+            "{} tiles, {} -> {} ({:.2}%)",
+            stats.tiles,
+            format_bytes(stats.input_bytes),
+            format_bytes(stats.output_bytes),
+            percent_change(stats.output_bytes, stats.input_bytes)
+        ),
+    )
 }
 
 fn signed_count_diff(before: u64, after: u64) -> i64 {
@@ -3,10 +3,15 @@ use geo_types::{
     Coord, Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Polygon,
 };
 use mvt::{GeomData, GeomEncoder, GeomType};
+use std::collections::{BTreeMap, HashMap};
 
-use crate::mbtiles::stats::HistogramBucket;
-use crate::mbtiles::types::SampleSpec;
+use crate::mbtiles::stats::{
+    HistogramBucket, MbtilesZoomStats, RecommendReason, Recommendation, RecommendedAction,
+    SimplifyStats, TileSummary,
+};
+use crate::mbtiles::types::{DEFAULT_SAMPLE_SEED, RecommendThresholds, SampleSpec};
 
+/// Values below `min_len` (tiles left out of the histogram) have no bucket.
 pub fn histogram_bucket_index(
     value: u64,
     min_len: Option<u64>,
@@ -18,7 +23,7 @@ pub fn histogram_bucket_index(
     }
     let min_len = min_len?;
     let max_len = max_len?;
-    if min_len > max_len {
+    if min_len > max_len || value < min_len {
         return None;
     }
     let range = (max_len - min_len).max(1);
@@ -77,6 +82,147 @@ pub fn format_property_value(value: &mvt_reader::feature::Value) -> String {
     }
 }
 
+/// 128-bit hash of a feature's geometry and properties (in key order),
+/// used to count exact duplicates without keeping the features. Ids are
+/// left out, so copies that only differ in id count as duplicates.
+pub fn feature_content_hash(feature: &mvt_reader::feature::Feature) -> u128 {
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    hash_geometry(&mut hasher, &feature.geometry);
+    if let Some(props) = feature.properties.as_ref() {
+        let mut props = props.iter().collect::<Vec<_>>();
+        props.sort_by(|a, b| a.0.cmp(b.0));
+        hasher.update(&(props.len() as u64).to_le_bytes());
+        for (key, value) in props {
+            hasher.update(&(key.len() as u64).to_le_bytes());
+            hasher.update(key.as_bytes());
+            match value {
+                mvt_reader::feature::Value::String(text) => {
+                    hasher.update(&[0]);
+                    hasher.update(&(text.len() as u64).to_le_bytes());
+                    hasher.update(text.as_bytes());
+                }
+                mvt_reader::feature::Value::Float(val) => {
+                    hasher.update(&[1]);
+                    hasher.update(&val.to_le_bytes());
+                }
+                mvt_reader::feature::Value::Double(val) => {
+                    hasher.update(&[2]);
+                    hasher.update(&val.to_le_bytes());
+                }
+                mvt_reader::feature::Value::Int(val) => {
+                    hasher.update(&[3]);
+                    hasher.update(&val.to_le_bytes());
+                }
+                mvt_reader::feature::Value::UInt(val) => {
+                    hasher.update(&[4]);
+                    hasher.update(&val.to_le_bytes());
+                }
+                mvt_reader::feature::Value::SInt(val) => {
+                    hasher.update(&[5]);
+                    hasher.update(&val.to_le_bytes());
+                }
+                mvt_reader::feature::Value::Bool(val) => hasher.update(&[6, u8::from(*val)]),
+                mvt_reader::feature::Value::Null => hasher.update(&[7]),
+            }
+        }
+    }
+    hasher.digest128()
+}
+
+fn hash_geometry(hasher: &mut xxhash_rust::xxh3::Xxh3, geometry: &geo_types::Geometry<f32>) {
+    fn coords<'a>(
+        hasher: &mut xxhash_rust::xxh3::Xxh3,
+        coords: impl ExactSizeIterator<Item = &'a geo_types::Coord<f32>>,
+    ) {
+        hasher.update(&(coords.len() as u64).to_le_bytes());
+        for coord in coords {
+            hasher.update(&coord.x.to_le_bytes());
+            hasher.update(&coord.y.to_le_bytes());
+        }
+    }
+    fn polygon(hasher: &mut xxhash_rust::xxh3::Xxh3, polygon: &geo_types::Polygon<f32>) {
+        coords(hasher, polygon.exterior().0.iter());
+        hasher.update(&(polygon.interiors().len() as u64).to_le_bytes());
+        for ring in polygon.interiors() {
+            coords(hasher, ring.0.iter());
+        }
+    }
+    match geometry {
+        geo_types::Geometry::Point(point) => {
+            hasher.update(&[0]);
+            coords(hasher, std::iter::once(&point.0));
+        }
+        geo_types::Geometry::MultiPoint(points) => {
+            hasher.update(&[1]);
+            coords(hasher, points.0.iter().map(|point| &point.0));
+        }
+        geo_types::Geometry::LineString(line) => {
+            hasher.update(&[2]);
+            coords(hasher, line.0.iter());
+        }
+        geo_types::Geometry::MultiLineString(lines) => {
+            hasher.update(&[3]);
+            hasher.update(&(lines.0.len() as u64).to_le_bytes());
+            for line in lines {
+                coords(hasher, line.0.iter());
+            }
+        }
+        geo_types::Geometry::Polygon(shape) => {
+            hasher.update(&[4]);
+            polygon(hasher, shape);
+        }
+        geo_types::Geometry::MultiPolygon(polygons) => {
+            hasher.update(&[5]);
+            hasher.update(&(polygons.0.len() as u64).to_le_bytes());
+            for shape in polygons {
+                polygon(hasher, shape);
+            }
+        }
+        geo_types::Geometry::Line(line) => {
+            hasher.update(&[6]);
+            coords(hasher, [line.start, line.end].iter());
+        }
+        geo_types::Geometry::Rect(rect) => {
+            hasher.update(&[7]);
+            coords(hasher, [rect.min(), rect.max()].iter());
+        }
+        geo_types::Geometry::Triangle(triangle) => {
+            hasher.update(&[8]);
+            coords(hasher, triangle.to_array().iter());
+        }
+        geo_types::Geometry::GeometryCollection(collection) => {
+            hasher.update(&[9]);
+            hasher.update(&(collection.0.len() as u64).to_le_bytes());
+            for geometry in collection {
+                hash_geometry(hasher, geometry);
+            }
+        }
+    }
+}
+
+/// Bytes of the layer `values` entry that stores `value`: the field key,
+/// the message length and the typed value inside it.
+pub fn encoded_value_size(value: &mvt_reader::feature::Value) -> u64 {
+    fn varint_len(value: u64) -> u64 {
+        u64::from((64 - value.leading_zeros()).max(1).div_ceil(7))
+    }
+    let inner = match value {
+        mvt_reader::feature::Value::String(text) => {
+            1 + varint_len(text.len() as u64) + text.len() as u64
+        }
+        mvt_reader::feature::Value::Float(_) => 5,
+        mvt_reader::feature::Value::Double(_) => 9,
+        mvt_reader::feature::Value::Int(val) => 1 + varint_len(*val as u64),
+        mvt_reader::feature::Value::UInt(val) => 1 + varint_len(*val),
+        mvt_reader::feature::Value::SInt(val) => {
+            1 + varint_len(((*val << 1) ^ (*val >> 63)) as u64)
+        }
+        mvt_reader::feature::Value::Bool(_) => 2,
+        mvt_reader::feature::Value::Null => 0,
+    };
+    1 + varint_len(inner) + inner
+}
+
 pub fn encode_linestring(encoder: &mut GeomEncoder<f32>, line: &LineString<f32>) -> Result<()> {
     for coord in ring_coords(line) {
         encoder
@@ -214,15 +360,40 @@ pub fn encode_geometry(geometry: &Geometry<f32>) -> Result<GeomData> {
     }
 }
 
-pub fn simplify_geometry(geometry: &Geometry<f32>, tolerance: f32) -> Geometry<f32> {
+/// Extent at which simplify tolerances and buffers are expressed.
+pub const SIMPLIFY_REFERENCE_EXTENT: u32 = 4096;
+/// Default clamp buffer around the tile, in units at extent 4096 (5px at 256).
+pub const DEFAULT_SIMPLIFY_BUFFER: u32 = 80;
+
+/// Simplify a decoded geometry. `tolerance` and `buffer` are given in units
+/// at extent 4096 and scaled to the layer `extent`; coordinates are rounded
+/// and clamped to `[-buffer, extent + buffer]` before simplification.
+///
+/// Polygon rings that collapse (fewer than 3 distinct points or zero area)
+/// are removed: a collapsed exterior drops its polygon and a collapsed
+/// interior drops that hole. With `preserve_topology`, a ring that
+/// simplification made self-intersecting keeps its original coordinates.
+/// Returns `None` when every polygon of the feature collapsed.
+pub fn simplify_geometry(
+    geometry: &Geometry<f32>,
+    tolerance: f32,
+    extent: u32,
+    buffer: u32,
+    preserve_topology: bool,
+    stats: &mut SimplifyStats,
+) -> Option<Geometry<f32>> {
     if tolerance <= 0.0 {
-        return geometry.clone();
+        return Some(geometry.clone());
     }
+    let scale = extent as f32 / SIMPLIFY_REFERENCE_EXTENT as f32;
+    let tolerance = tolerance * scale;
+    let buffer = (buffer as f32 * scale).round();
+    let geometry = &clamp_geometry(geometry, -buffer, extent as f32 + buffer);
 
     match geometry {
         Geometry::LineString(line) => {
             let simplified = simplify_line(&line.0, tolerance);
-            Geometry::LineString(LineString::from(simplified))
+            Some(Geometry::LineString(LineString::from(simplified)))
         }
         Geometry::MultiLineString(lines) => {
             let simplified = lines
@@ -230,42 +401,333 @@ pub fn simplify_geometry(geometry: &Geometry<f32>, tolerance: f32) -> Geometry<f
                 .iter()
                 .map(|line| LineString::from(simplify_line(&line.0, tolerance)))
                 .collect::<Vec<_>>();
-            Geometry::MultiLineString(MultiLineString(simplified))
+            Some(Geometry::MultiLineString(MultiLineString(simplified)))
         }
         Geometry::Polygon(polygon) => {
-            let exterior = simplify_ring(&polygon.exterior().0, tolerance);
-            let interiors = polygon
-                .interiors()
-                .iter()
-                .map(|ring| simplify_ring(&ring.0, tolerance))
-                .map(LineString::from)
-                .collect::<Vec<_>>();
-            Geometry::Polygon(Polygon::new(LineString::from(exterior), interiors))
+            simplify_polygon(polygon, tolerance, preserve_topology, stats).map(Geometry::Polygon)
         }
         Geometry::MultiPolygon(polygons) => {
             let simplified = polygons
                 .0
                 .iter()
-                .map(|polygon| {
-                    let exterior = simplify_ring(&polygon.exterior().0, tolerance);
-                    let interiors = polygon
-                        .interiors()
-                        .iter()
-                        .map(|ring| simplify_ring(&ring.0, tolerance))
-                        .map(LineString::from)
-                        .collect::<Vec<_>>();
-                    Polygon::new(LineString::from(exterior), interiors)
+                .filter_map(|polygon| {
+                    simplify_polygon(polygon, tolerance, preserve_topology, stats)
                 })
                 .collect::<Vec<_>>();
-            Geometry::MultiPolygon(MultiPolygon(simplified))
+            if simplified.is_empty() {
+                return None;
+            }
+            Some(Geometry::MultiPolygon(MultiPolygon(simplified)))
+        }
+        _ => Some(geometry.clone()),
+    }
+}
+
+pub(crate) fn clamp_geometry(geometry: &Geometry<f32>, min: f32, max: f32) -> Geometry<f32> {
+    let clamp = |coord: &Coord<f32>| Coord {
+        x: coord.x.round().clamp(min, max),
+        y: coord.y.round().clamp(min, max),
+    };
+    let clamp_line = |line: &LineString<f32>| line.0.iter().map(clamp).collect::<LineString<f32>>();
+    let clamp_polygon = |polygon: &Polygon<f32>| {
+        Polygon::new(
+            clamp_line(polygon.exterior()),
+            polygon.interiors().iter().map(clamp_line).collect(),
+        )
+    };
+    match geometry {
+        Geometry::Point(point) => Geometry::Point(clamp(&point.0).into()),
+        Geometry::MultiPoint(points) => Geometry::MultiPoint(MultiPoint(
+            points
+                .0
+                .iter()
+                .map(|point| clamp(&point.0).into())
+                .collect(),
+        )),
+        Geometry::LineString(line) => Geometry::LineString(clamp_line(line)),
+        Geometry::MultiLineString(lines) => {
+            Geometry::MultiLineString(MultiLineString(lines.0.iter().map(clamp_line).collect()))
+        }
+        Geometry::Polygon(polygon) => Geometry::Polygon(clamp_polygon(polygon)),
+        Geometry::MultiPolygon(polygons) => {
+            Geometry::MultiPolygon(MultiPolygon(polygons.0.iter().map(clamp_polygon).collect()))
         }
         _ => geometry.clone(),
     }
 }
 
-fn simplify_ring(points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
+/// Scale a geometry into a descendant tile `scale` (2^zoom delta) times
+/// finer and clip it to `[min, max]`; `offset` is the descendant's corner in
+/// scaled parent units. Coordinates are rounded to whole tile units after
+/// clipping.
+///
+/// Points outside the square are removed, lines are cut into one part per
+/// stretch inside it and polygon rings are clipped against each side in
+/// turn. Rings that collapse are dropped like in simplify: a collapsed
+/// exterior drops its polygon, a collapsed interior drops that hole.
+/// Returns `None` when nothing is left inside the square.
+pub fn overzoom_geometry(
+    geometry: &Geometry<f32>,
+    scale: f32,
+    offset: Coord<f32>,
+    min: f32,
+    max: f32,
+) -> Option<Geometry<f32>> {
+    let transform = |coord: &Coord<f32>| Coord {
+        x: coord.x * scale - offset.x,
+        y: coord.y * scale - offset.y,
+    };
+    let inside =
+        |coord: &Coord<f32>| coord.x >= min && coord.x <= max && coord.y >= min && coord.y <= max;
+    match geometry {
+        Geometry::Point(point) => {
+            let coord = transform(&point.0);
+            inside(&coord).then(|| Geometry::Point(round_coord(coord).into()))
+        }
+        Geometry::MultiPoint(points) => {
+            let kept = points
+                .0
+                .iter()
+                .map(|point| transform(&point.0))
+                .filter(|coord| inside(coord))
+                .map(|coord| round_coord(coord).into())
+                .collect::<Vec<_>>();
+            (!kept.is_empty()).then_some(Geometry::MultiPoint(MultiPoint(kept)))
+        }
+        Geometry::LineString(line) => {
+            let coords = line.0.iter().map(transform).collect::<Vec<_>>();
+            lines_geometry(clip_line(&coords, min, max))
+        }
+        Geometry::MultiLineString(lines) => {
+            let parts = lines
+                .0
+                .iter()
+                .flat_map(|line| {
+                    let coords = line.0.iter().map(transform).collect::<Vec<_>>();
+                    clip_line(&coords, min, max)
+                })
+                .collect();
+            lines_geometry(parts)
+        }
+        Geometry::Polygon(polygon) => {
+            clip_polygon(polygon, &transform, min, max).map(Geometry::Polygon)
+        }
+        Geometry::MultiPolygon(polygons) => {
+            let clipped = polygons
+                .0
+                .iter()
+                .filter_map(|polygon| clip_polygon(polygon, &transform, min, max))
+                .collect::<Vec<_>>();
+            (!clipped.is_empty()).then_some(Geometry::MultiPolygon(MultiPolygon(clipped)))
+        }
+        _ => None,
+    }
+}
+
+fn round_coord(coord: Coord<f32>) -> Coord<f32> {
+    Coord {
+        x: coord.x.round(),
+        y: coord.y.round(),
+    }
+}
+
+fn lines_geometry(mut parts: Vec<LineString<f32>>) -> Option<Geometry<f32>> {
+    match parts.len() {
+        0 => None,
+        1 => parts.pop().map(Geometry::LineString),
+        _ => Some(Geometry::MultiLineString(MultiLineString(parts))),
+    }
+}
+
+/// Cut a line into the parts inside `[min, max]`, dropping parts that round
+/// to a single point.
+fn clip_line(coords: &[Coord<f32>], min: f32, max: f32) -> Vec<LineString<f32>> {
+    let mut parts = Vec::new();
+    let mut current: Vec<Coord<f32>> = Vec::new();
+    let mut finish = |current: &mut Vec<Coord<f32>>| {
+        let mut part = std::mem::take(current)
+            .into_iter()
+            .map(round_coord)
+            .collect::<Vec<_>>();
+        part.dedup();
+        if part.len() >= 2 {
+            parts.push(LineString::from(part));
+        }
+    };
+    for segment in coords.windows(2) {
+        match clip_segment(segment[0], segment[1], min, max) {
+            Some((start, end)) => {
+                if current.last() != Some(&start) {
+                    finish(&mut current);
+                    current.push(start);
+                }
+                current.push(end);
+            }
+            None => finish(&mut current),
+        }
+    }
+    finish(&mut current);
+    parts
+}
+
+/// Liang-Barsky clipping of the segment `a`-`b` to `[min, max]`.
+fn clip_segment(
+    a: Coord<f32>,
+    b: Coord<f32>,
+    min: f32,
+    max: f32,
+) -> Option<(Coord<f32>, Coord<f32>)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [
+        (-dx, a.x - min),
+        (dx, max - a.x),
+        (-dy, a.y - min),
+        (dy, max - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let r = q / p;
+        if p < 0.0 {
+            if r > t1 {
+                return None;
+            }
+            t0 = t0.max(r);
+        } else {
+            if r < t0 {
+                return None;
+            }
+            t1 = t1.min(r);
+        }
+    }
+    let at = |t: f32| {
+        if t == 0.0 {
+            a
+        } else if t == 1.0 {
+            b
+        } else {
+            Coord {
+                x: a.x + dx * t,
+                y: a.y + dy * t,
+            }
+        }
+    };
+    Some((at(t0), at(t1)))
+}
+
+fn clip_polygon(
+    polygon: &Polygon<f32>,
+    transform: &impl Fn(&Coord<f32>) -> Coord<f32>,
+    min: f32,
+    max: f32,
+) -> Option<Polygon<f32>> {
+    let clip = |ring: &LineString<f32>| {
+        let coords = ring_coords(ring).iter().map(transform).collect::<Vec<_>>();
+        clip_ring(coords, min, max)
+    };
+    let exterior = clip(polygon.exterior())?;
+    let interiors = polygon.interiors().iter().filter_map(clip).collect();
+    Some(Polygon::new(exterior, interiors))
+}
+
+/// Sutherland-Hodgman clipping of an open ring to `[min, max]`; returns the
+/// closed, rounded ring or `None` when it collapses.
+fn clip_ring(mut coords: Vec<Coord<f32>>, min: f32, max: f32) -> Option<LineString<f32>> {
+    for (along_x, bound, keep_above) in [
+        (true, min, true),
+        (true, max, false),
+        (false, min, true),
+        (false, max, false),
+    ] {
+        coords = clip_ring_side(&coords, along_x, bound, keep_above);
+    }
+    let mut ring = coords.into_iter().map(round_coord).collect::<Vec<_>>();
+    ring.dedup();
+    while ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    let first = *ring.first()?;
+    ring.push(first);
+    (!is_ring_collapsed(&ring)).then(|| LineString::from(ring))
+}
+
+fn clip_ring_side(
+    coords: &[Coord<f32>],
+    along_x: bool,
+    bound: f32,
+    keep_above: bool,
+) -> Vec<Coord<f32>> {
+    let value = |coord: &Coord<f32>| if along_x { coord.x } else { coord.y };
+    let inside = |coord: &Coord<f32>| {
+        if keep_above {
+            value(coord) >= bound
+        } else {
+            value(coord) <= bound
+        }
+    };
+    let crossing = |a: &Coord<f32>, b: &Coord<f32>| {
+        let t = (bound - value(a)) / (value(b) - value(a));
+        if along_x {
+            Coord {
+                x: bound,
+                y: a.y + (b.y - a.y) * t,
+            }
+        } else {
+            Coord {
+                x: a.x + (b.x - a.x) * t,
+                y: bound,
+            }
+        }
+    };
+    let mut out = Vec::with_capacity(coords.len() + 4);
+    for (idx, current) in coords.iter().enumerate() {
+        let prev = &coords[(idx + coords.len() - 1) % coords.len()];
+        match (inside(prev), inside(current)) {
+            (true, true) => out.push(*current),
+            (true, false) => out.push(crossing(prev, current)),
+            (false, true) => {
+                out.push(crossing(prev, current));
+                out.push(*current);
+            }
+            (false, false) => {}
+        }
+    }
+    out
+}
+
+fn simplify_polygon(
+    polygon: &Polygon<f32>,
+    tolerance: f32,
+    preserve_topology: bool,
+    stats: &mut SimplifyStats,
+) -> Option<Polygon<f32>> {
+    let Some(exterior) = simplify_ring(&polygon.exterior().0, tolerance, preserve_topology, stats)
+    else {
+        stats.dropped_polygons += 1;
+        return None;
+    };
+    let mut interiors = Vec::with_capacity(polygon.interiors().len());
+    for ring in polygon.interiors() {
+        match simplify_ring(&ring.0, tolerance, preserve_topology, stats) {
+            Some(ring) => interiors.push(LineString::from(ring)),
+            None => stats.dropped_holes += 1,
+        }
+    }
+    Some(Polygon::new(LineString::from(exterior), interiors))
+}
+
+fn simplify_ring(
+    points: &[Coord<f32>],
+    tolerance: f32,
+    preserve_topology: bool,
+    stats: &mut SimplifyStats,
+) -> Option<Vec<Coord<f32>>> {
     if points.len() <= 4 {
-        return points.to_vec();
+        return Some(points.to_vec());
     }
 
     let closed = points.first() == points.last();
@@ -274,15 +736,33 @@ fn simplify_ring(points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
     } else {
         points.to_vec()
     };
-    let simplified = simplify_line(&core, tolerance);
-    if simplified.len() < 3 {
-        return points.to_vec();
-    }
-    let mut out = simplified;
+    let mut out = simplify_line(&core, tolerance);
     if closed {
         out.push(out[0]);
     }
-    out
+    if is_ring_collapsed(&out) {
+        return None;
+    }
+    if preserve_topology && closed && ring_self_intersects(&out) && !ring_self_intersects(points) {
+        stats.restored_rings += 1;
+        return Some(points.to_vec());
+    }
+    Some(out)
+}
+
+/// A ring with fewer than 3 distinct points or no area renders as nothing
+/// (or as an artifact).
+fn is_ring_collapsed(coords: &[Coord<f32>]) -> bool {
+    let mut distinct: Vec<Coord<f32>> = Vec::with_capacity(3);
+    for coord in coords {
+        if !distinct.contains(coord) {
+            distinct.push(*coord);
+            if distinct.len() >= 3 {
+                break;
+            }
+        }
+    }
+    distinct.len() < 3 || ring_signed_area(coords) == 0.0
 }
 
 fn simplify_line(points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
@@ -379,10 +859,487 @@ fn get_sq_seg_dist(p: Coord<f32>, p1: Coord<f32>, p2: Coord<f32>) -> f32 {
     dx * dx + dy * dy
 }
 
-pub fn include_sample(index: u64, total: u64, spec: Option<&SampleSpec>) -> bool {
+/// Check a decoded MVT geometry for problems that renderers reject or draw
+/// incorrectly: too few vertices, unclosed rings, wrong ring orientation
+/// (exterior rings must have positive area in tile coordinates, interior
+/// rings negative) and self-intersecting rings.
+pub fn is_geometry_valid(geometry: &Geometry<f32>) -> bool {
+    match geometry {
+        Geometry::Point(_) | Geometry::MultiPoint(_) => true,
+        Geometry::Line(line) => line.start != line.end,
+        Geometry::LineString(line) => ring_coords(line).len() >= 2,
+        Geometry::MultiLineString(lines) => lines.iter().all(|line| ring_coords(line).len() >= 2),
+        Geometry::Polygon(polygon) => is_polygon_valid(polygon),
+        Geometry::MultiPolygon(polygons) => polygons.iter().all(is_polygon_valid),
+        Geometry::Rect(_) | Geometry::Triangle(_) => true,
+        Geometry::GeometryCollection(collection) => collection.iter().all(is_geometry_valid),
+    }
+}
+
+fn is_polygon_valid(polygon: &Polygon<f32>) -> bool {
+    is_ring_valid(polygon.exterior(), true)
+        && polygon
+            .interiors()
+            .iter()
+            .all(|ring| is_ring_valid(ring, false))
+}
+
+fn is_ring_valid(ring: &LineString<f32>, exterior: bool) -> bool {
+    let coords = ring.0.as_slice();
+    if coords.len() < 4 || coords.first() != coords.last() {
+        return false;
+    }
+    let area = ring_signed_area(coords);
+    let oriented = if exterior { area > 0.0 } else { area < 0.0 };
+    oriented && !ring_self_intersects(coords)
+}
+
+/// Normalize polygon ring orientation so exterior rings have positive area
+/// and interior rings negative area in tile coordinates, as the MVT spec
+/// requires. Rings are regrouped using the orientation of the first ring, so
+/// a feature written with the opposite convention decodes back into the
+/// intended exteriors and holes. Returns the fixed geometry and the number of
+/// reversed rings, or `None` when nothing needed fixing.
+pub fn fix_polygon_winding(geometry: &Geometry<f32>) -> Option<(Geometry<f32>, u64)> {
+    let rings: Vec<&LineString<f32>> = match geometry {
+        Geometry::Polygon(polygon) => polygon_rings(polygon).collect(),
+        Geometry::MultiPolygon(polygons) => polygons.iter().flat_map(polygon_rings).collect(),
+        _ => return None,
+    };
+    let areas: Vec<f64> = rings.iter().map(|ring| ring_signed_area(&ring.0)).collect();
+    let exterior_sign = areas
+        .iter()
+        .find(|area| **area != 0.0)
+        .map_or(1.0, |area| area.signum());
+
+    let mut corrected = 0u64;
+    let mut polygons: Vec<(LineString<f32>, Vec<LineString<f32>>)> = Vec::new();
+    for (index, (ring, area)) in rings.into_iter().zip(areas).enumerate() {
+        let exterior = index == 0 || area.signum() == exterior_sign;
+        let wrong = if exterior { area < 0.0 } else { area > 0.0 };
+        let mut ring = ring.clone();
+        if wrong {
+            ring.0.reverse();
+            corrected += 1;
+        }
+        match polygons.last_mut() {
+            Some((_, interiors)) if !exterior => interiors.push(ring),
+            _ => polygons.push((ring, Vec::new())),
+        }
+    }
+    if corrected == 0 {
+        return None;
+    }
+    let mut polygons = polygons
+        .into_iter()
+        .map(|(exterior, interiors)| Polygon::new(exterior, interiors))
+        .collect::<Vec<_>>();
+    let fixed = if polygons.len() == 1 && matches!(geometry, Geometry::Polygon(_)) {
+        Geometry::Polygon(polygons.remove(0))
+    } else {
+        Geometry::MultiPolygon(MultiPolygon(polygons))
+    };
+    Some((fixed, corrected))
+}
+
+/// Area of a polygonal geometry (exteriors minus holes) in tile units;
+/// `None` for points and lines.
+pub fn polygon_area(geometry: &Geometry<f32>) -> Option<f64> {
+    let area = |polygon: &Polygon<f32>| {
+        let holes: f64 = polygon
+            .interiors()
+            .iter()
+            .map(|ring| ring_signed_area(&ring.0).abs())
+            .sum();
+        (ring_signed_area(&polygon.exterior().0).abs() - holes).max(0.0)
+    };
+    match geometry {
+        Geometry::Polygon(polygon) => Some(area(polygon)),
+        Geometry::MultiPolygon(polygons) => Some(polygons.iter().map(area).sum()),
+        _ => None,
+    }
+}
+
+/// Keeps the first point of every cluster closer than `distance` (tile units).
+pub(crate) struct PointMerger {
+    distance: f32,
+    cells: HashMap<(i64, i64), Vec<Coord<f32>>>,
+}
+
+impl PointMerger {
+    pub(crate) fn new(distance: f32) -> Self {
+        Self {
+            distance,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Returns false when `point` lies within the distance of a kept point.
+    pub(crate) fn keep(&mut self, point: Coord<f32>) -> bool {
+        let cell = |value: f32| (value / self.distance).floor() as i64;
+        let (cx, cy) = (cell(point.x), cell(point.y));
+        let sq_distance = self.distance * self.distance;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if let Some(points) = self.cells.get(&(cx + dx, cy + dy))
+                    && points
+                        .iter()
+                        .any(|kept| get_sq_dist(*kept, point) <= sq_distance)
+                {
+                    return false;
+                }
+            }
+        }
+        self.cells.entry((cx, cy)).or_default().push(point);
+        true
+    }
+}
+
+fn polygon_rings(polygon: &Polygon<f32>) -> impl Iterator<Item = &LineString<f32>> {
+    std::iter::once(polygon.exterior()).chain(polygon.interiors())
+}
+
+pub(crate) fn ring_signed_area(coords: &[Coord<f32>]) -> f64 {
+    coords
+        .windows(2)
+        .map(|pair| {
+            (pair[0].x as f64) * (pair[1].y as f64) - (pair[1].x as f64) * (pair[0].y as f64)
+        })
+        .sum::<f64>()
+        * 0.5
+}
+
+/// `coords` is a closed ring; segments sharing an endpoint are not compared.
+/// Segments are swept in order of their left x so only pairs whose x ranges
+/// overlap are tested.
+fn ring_self_intersects(coords: &[Coord<f32>]) -> bool {
+    if coords.len() < 4 {
+        return false;
+    }
+    let segments = coords.len() - 1;
+    let x_range = |i: usize| {
+        let (a, b) = (coords[i].x, coords[i + 1].x);
+        (a.min(b), a.max(b))
+    };
+    let mut order: Vec<usize> = (0..segments).collect();
+    order.sort_by(|a, b| x_range(*a).0.total_cmp(&x_range(*b).0));
+
+    let mut active: Vec<usize> = Vec::new();
+    for i in order {
+        let (min_x, _) = x_range(i);
+        active.retain(|&j| x_range(j).1 >= min_x);
+        for &j in &active {
+            let (lo, hi) = (i.min(j), i.max(j));
+            if hi - lo == 1 || (lo == 0 && hi == segments - 1) {
+                continue;
+            }
+            if segments_intersect(coords[i], coords[i + 1], coords[j], coords[j + 1]) {
+                return true;
+            }
+        }
+        active.push(i);
+    }
+    false
+}
+
+fn segments_intersect(a1: Coord<f32>, a2: Coord<f32>, b1: Coord<f32>, b2: Coord<f32>) -> bool {
+    fn orientation(p: Coord<f32>, q: Coord<f32>, r: Coord<f32>) -> f64 {
+        (q.x as f64 - p.x as f64) * (r.y as f64 - p.y as f64)
+            - (q.y as f64 - p.y as f64) * (r.x as f64 - p.x as f64)
+    }
+    fn on_segment(p: Coord<f32>, q: Coord<f32>, r: Coord<f32>) -> bool {
+        r.x >= p.x.min(q.x) && r.x <= p.x.max(q.x) && r.y >= p.y.min(q.y) && r.y <= p.y.max(q.y)
+    }
+    let d1 = orientation(b1, b2, a1);
+    let d2 = orientation(b1, b2, a2);
+    let d3 = orientation(a1, a2, b1);
+    let d4 = orientation(a1, a2, b2);
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+    (d1 == 0.0 && on_segment(b1, b2, a1))
+        || (d2 == 0.0 && on_segment(b1, b2, a2))
+        || (d3 == 0.0 && on_segment(a1, a2, b1))
+        || (d4 == 0.0 && on_segment(a1, a2, b2))
+}
+
+const RECOMMEND_MAX_LAYERS_PER_ZOOM: usize = 3;
+/// Average vertices per feature from which simplification is suggested
+/// rather than dropping the layer at that zoom.
+const RECOMMEND_SIMPLIFY_MIN_VERTICES: f64 = 8.0;
+/// Average vertices per feature from which the coarser tolerance is used.
+const RECOMMEND_COARSE_SIMPLIFY_VERTICES: f64 = 64.0;
+/// Assumed fraction of a layer's bytes removed by simplification.
+const RECOMMEND_SIMPLIFY_SAVINGS_RATIO: f64 = 0.4;
+
+/// [`build_recommendations_with_thresholds`] with the default thresholds.
+pub fn build_recommendations(
+    by_zoom: &[MbtilesZoomStats],
+    summaries: &[TileSummary],
+) -> Vec<Recommendation> {
+    build_recommendations_with_thresholds(by_zoom, summaries, &RecommendThresholds::default())
+}
+
+/// Suggest per-layer actions for each zoom present in `summaries` (the
+/// summaries of the largest tiles). Layer shares measured on those tiles are
+/// applied to the zoom's total bytes from `by_zoom` to estimate savings.
+///
+/// Up to three layers per zoom are reported when they exceed one of the
+/// `thresholds`; the layer with the most vertices additionally gets a
+/// simplify suggestion when its features are detailed enough.
+pub fn build_recommendations_with_thresholds(
+    by_zoom: &[MbtilesZoomStats],
+    summaries: &[TileSummary],
+    thresholds: &RecommendThresholds,
+) -> Vec<Recommendation> {
+    #[derive(Default)]
+    struct LayerTotals {
+        bytes: u64,
+        vertices: u64,
+        features: u64,
+    }
+
+    let mut zooms = summaries.iter().map(|s| s.zoom).collect::<Vec<_>>();
+    zooms.sort_unstable();
+    zooms.dedup();
+
+    let mut recommendations = Vec::new();
+    for zoom in zooms {
+        let mut layers: Vec<(String, LayerTotals)> = Vec::new();
+        let mut tile_bytes = 0u64;
+        let mut tile_count = 0u64;
+        for summary in summaries.iter().filter(|s| s.zoom == zoom) {
+            tile_bytes += summary.tile_bytes;
+            tile_count += 1;
+            for layer in &summary.layers {
+                let idx = match layers.iter().position(|(name, _)| *name == layer.name) {
+                    Some(idx) => idx,
+                    None => {
+                        layers.push((layer.name.clone(), LayerTotals::default()));
+                        layers.len() - 1
+                    }
+                };
+                let totals = &mut layers[idx].1;
+                totals.bytes += layer.bytes;
+                totals.vertices += layer.vertex_count;
+                totals.features += layer.feature_count as u64;
+            }
+        }
+        let total_bytes: u64 = layers.iter().map(|(_, t)| t.bytes).sum();
+        let total_vertices: u64 = layers.iter().map(|(_, t)| t.vertices).sum();
+        if total_bytes == 0 {
+            continue;
+        }
+        let zoom_bytes = by_zoom
+            .iter()
+            .find(|item| item.zoom == zoom)
+            .map(|item| item.stats.total_bytes)
+            .unwrap_or(tile_bytes);
+
+        layers.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0)));
+        let most_vertices = layers
+            .iter()
+            .filter(|(_, totals)| totals.vertices > 0)
+            .max_by(|a, b| a.1.vertices.cmp(&b.1.vertices).then_with(|| b.0.cmp(&a.0)))
+            .map(|(name, _)| name.clone());
+        let mut reported = 0usize;
+        for (name, totals) in layers {
+            let bytes_share = totals.bytes as f64 / total_bytes as f64;
+            let avg_bytes = totals.bytes / tile_count;
+            let avg_features = totals.features / tile_count;
+            let vertices_per_feature = totals.vertices as f64 / totals.features.max(1) as f64;
+            let reason = if reported >= RECOMMEND_MAX_LAYERS_PER_ZOOM {
+                None
+            } else if bytes_share >= thresholds.min_bytes_share {
+                Some(RecommendReason::BytesShare)
+            } else if thresholds
+                .max_layer_bytes
+                .is_some_and(|limit| avg_bytes > limit)
+            {
+                Some(RecommendReason::LayerBytes)
+            } else if thresholds
+                .max_layer_features
+                .is_some_and(|limit| avg_features > limit)
+            {
+                Some(RecommendReason::FeatureCount)
+            } else {
+                None
+            };
+            let reason = match reason {
+                Some(reason) => {
+                    reported += 1;
+                    reason
+                }
+                None if most_vertices.as_deref() == Some(name.as_str())
+                    && vertices_per_feature >= RECOMMEND_SIMPLIFY_MIN_VERTICES =>
+                {
+                    RecommendReason::MostVertices
+                }
+                None => continue,
+            };
+            let vertices_share = if total_vertices == 0 {
+                0.0
+            } else {
+                totals.vertices as f64 / total_vertices as f64
+            };
+            let layer_zoom_bytes = zoom_bytes as f64 * bytes_share;
+            let (action, savings) = if vertices_per_feature >= RECOMMEND_SIMPLIFY_MIN_VERTICES {
+                let tolerance = if vertices_per_feature >= RECOMMEND_COARSE_SIMPLIFY_VERTICES {
+                    2.0
+                } else {
+                    1.0
+                };
+                (
+                    RecommendedAction::Simplify { tolerance },
+                    layer_zoom_bytes * RECOMMEND_SIMPLIFY_SAVINGS_RATIO,
+                )
+            } else {
+                (
+                    RecommendedAction::RaiseMinzoom {
+                        minzoom: zoom.saturating_add(1),
+                    },
+                    layer_zoom_bytes,
+                )
+            };
+            recommendations.push(Recommendation {
+                zoom,
+                layer: name,
+                reason,
+                bytes_share,
+                vertices_share,
+                avg_bytes,
+                avg_features,
+                action,
+                estimated_savings_bytes: savings.round() as u64,
+            });
+        }
+    }
+    recommendations
+}
+
+fn read_proto_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data
+            .get(*pos)
+            .ok_or_else(|| anyhow::anyhow!("truncated varint in vector tile"))?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    anyhow::bail!("varint too long in vector tile")
+}
+
+/// Payloads of the length-delimited `field` entries of a protobuf message.
+fn proto_messages(data: &[u8], field: u64) -> Result<Vec<&[u8]>> {
+    let mut messages = Vec::new();
+    let mut pos = 0usize;
+    while pos < data.len() {
+        let key = read_proto_varint(data, &mut pos)?;
+        let skip = match key & 0x7 {
+            0 => {
+                read_proto_varint(data, &mut pos)?;
+                0
+            }
+            1 => 8,
+            2 => read_proto_varint(data, &mut pos)? as usize,
+            5 => 4,
+            wire => anyhow::bail!("unsupported wire type {wire} in vector tile"),
+        };
+        let end = pos
+            .checked_add(skip)
+            .filter(|end| *end <= data.len())
+            .ok_or_else(|| anyhow::anyhow!("truncated field in vector tile"))?;
+        if key & 0x7 == 2 && key >> 3 == field {
+            messages.push(&data[pos..end]);
+        }
+        pos = end;
+    }
+    Ok(messages)
+}
+
+/// Number of features over all layers of a decompressed vector tile, counted
+/// from the protobuf framing without decoding geometries (`--empty-exact`).
+pub fn tile_feature_count(payload: &[u8]) -> Result<u64> {
+    let mut count = 0u64;
+    // Tile.layers is field 3, Layer.features field 2.
+    for layer in proto_messages(payload, 3)? {
+        count += proto_messages(layer, 2)?.len() as u64;
+    }
+    Ok(count)
+}
+
+/// Encoded size in bytes of each layer message of a decompressed vector tile,
+/// in the order the layers appear (matching `mvt_reader` layer indices).
+pub fn layer_encoded_sizes(payload: &[u8]) -> Result<Vec<u64>> {
+    let mut sizes = Vec::new();
+    let mut pos = 0usize;
+    while pos < payload.len() {
+        let key = read_proto_varint(payload, &mut pos)?;
+        let skip = match key & 0x7 {
+            0 => {
+                read_proto_varint(payload, &mut pos)?;
+                0
+            }
+            1 => 8,
+            2 => {
+                let len = read_proto_varint(payload, &mut pos)?;
+                // Tile.layers is field 3.
+                if key >> 3 == 3 {
+                    sizes.push(len);
+                }
+                len as usize
+            }
+            5 => 4,
+            wire => anyhow::bail!("unsupported wire type {wire} in vector tile"),
+        };
+        pos = pos
+            .checked_add(skip)
+            .filter(|end| *end <= payload.len())
+            .ok_or_else(|| anyhow::anyhow!("truncated field in vector tile"))?;
+    }
+    Ok(sizes)
+}
+
+/// Decide whether the `index`-th tile (1-based) of a `total`-tile scan is
+/// part of the sample. `seed` defaults to [`DEFAULT_SAMPLE_SEED`], so the
+/// same tiles are picked on every run.
+///
+/// Ratio sampling hashes `index ^ seed`, so the subset does not depend on
+/// the tile count. Count sampling picks `count` tiles evenly spaced over the
+/// whole scan, starting at a seed-derived offset; scans run in zoom order, so
+/// every zoom gets its share instead of the first rows taking the lot. Pass
+/// positions from [`sample_zoom_offsets`] when each zoom is scanned on its own.
+/// Per-zoom sampling expects the position within one zoom and that zoom's
+/// tile count (see [`include_zoom_sample`]), and picks the ratio of it
+/// evenly spaced, at least one tile.
+pub fn include_sample(
+    index: u64,
+    total: u64,
+    spec: Option<&SampleSpec>,
+    seed: Option<u64>,
+) -> bool {
+    let seed = seed.unwrap_or(DEFAULT_SAMPLE_SEED);
     match spec {
         None => true,
-        Some(SampleSpec::Count(count)) => index <= *count,
+        Some(SampleSpec::Count(count)) => {
+            if total == 0 {
+                return index <= *count;
+            }
+            if *count >= total {
+                return index <= total;
+            }
+            if index == 0 || index > total {
+                return false;
+            }
+            let offset = (splitmix64(seed) % total) as u128;
+            let step = |position: u64| (position as u128 + offset) * *count as u128 / total as u128;
+            step(index) > step(index - 1)
+        }
         Some(SampleSpec::Ratio(ratio)) => {
             if *ratio >= 1.0 {
                 return true;
@@ -391,12 +1348,63 @@ pub fn include_sample(index: u64, total: u64, spec: Option<&SampleSpec>) -> bool
                 return false;
             }
             let threshold = (ratio * u64::MAX as f64) as u64;
-            let hash = splitmix64(index ^ total);
+            let hash = splitmix64(index ^ seed);
             hash <= threshold
         }
+        Some(SampleSpec::PerZoom(ratio)) => {
+            // Without the zoom's tile count there is nothing to space over
+            let spec = if total == 0 || *ratio >= 1.0 {
+                SampleSpec::Ratio(*ratio)
+            } else {
+                SampleSpec::Count(((ratio * total as f64).round() as u64).max(1))
+            };
+            include_sample(index, total, Some(&spec), Some(seed))
+        }
     }
 }
 
+/// [`include_sample`] for the `index`-th tile of a `total`-tile scan laid
+/// out by [`sample_zoom_offsets`], where the tile is at zoom `zoom`.
+/// Per-zoom sampling places it within its zoom, out of `zoom_counts[zoom]`.
+pub fn include_zoom_sample(
+    index: u64,
+    total: u64,
+    zoom: u8,
+    zoom_counts: &BTreeMap<u8, u64>,
+    offsets: &BTreeMap<u8, u64>,
+    spec: Option<&SampleSpec>,
+    seed: Option<u64>,
+) -> bool {
+    match spec {
+        Some(SampleSpec::PerZoom(_)) => {
+            let offset = offsets.get(&zoom).copied().unwrap_or(0);
+            let zoom_total = zoom_counts.get(&zoom).copied().unwrap_or(0);
+            include_sample(index.saturating_sub(offset), zoom_total, spec, seed)
+        }
+        _ => include_sample(index, total, spec, seed),
+    }
+}
+
+/// Tiles scanned before each zoom, plus the scan total, so per-zoom scans
+/// can pass `offset + index` and `total` to [`include_sample`]. With `zoom`
+/// set only that zoom is scanned.
+pub fn sample_zoom_offsets(
+    zoom_counts: &BTreeMap<u8, u64>,
+    zoom: Option<u8>,
+) -> (BTreeMap<u8, u64>, u64) {
+    let mut total = 0u64;
+    let offsets = zoom_counts
+        .iter()
+        .filter(|(z, _)| zoom.is_none_or(|target| **z == target))
+        .map(|(&z, &count)| {
+            let offset = total;
+            total = total.saturating_add(count);
+            (z, offset)
+        })
+        .collect();
+    (offsets, total)
+}
+
 fn splitmix64(mut x: u64) -> u64 {
     x = x.wrapping_add(0x9e3779b97f4a7c15);
     let mut z = x;
@@ -405,6 +1413,10 @@ fn splitmix64(mut x: u64) -> u64 {
     z ^ (z >> 31)
 }
 
+/// Bucket `tile_sizes` between `min_len` and `max_len`. Sizes at or below
+/// `floor` (`--exclude-empty`) are left out, and the totals used for the
+/// percentages are reduced by them.
+#[allow(clippy::too_many_arguments)]
 pub fn build_histogram_from_sizes(
     tile_sizes: &[u64],
     total_tiles_used: u64,
@@ -413,6 +1425,7 @@ pub fn build_histogram_from_sizes(
     min_len: u64,
     max_len: u64,
     max_tile_bytes: u64,
+    floor: Option<u64>,
 ) -> Vec<HistogramBucket> {
     if buckets == 0 || min_len > max_len {
         return Vec::new();
@@ -422,8 +1435,15 @@ pub fn build_histogram_from_sizes(
     let bucket_size = ((range as f64) / buckets as f64).ceil() as u64;
     let mut counts = vec![0u64; buckets];
     let mut bytes = vec![0u64; buckets];
+    let mut total_tiles_used = total_tiles_used;
+    let mut total_bytes_used = total_bytes_used;
 
     for &length in tile_sizes {
+        if floor.is_some_and(|floor| length <= floor) {
+            total_tiles_used = total_tiles_used.saturating_sub(1);
+            total_bytes_used = total_bytes_used.saturating_sub(length);
+            continue;
+        }
         let mut bucket = ((length.saturating_sub(min_len)) / bucket_size) as usize;
         if bucket >= buckets {
             bucket = buckets - 1;
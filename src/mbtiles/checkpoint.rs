@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CHECKPOINT_VERSION: u32 = 1;
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct CheckpointOptions {
+    pub path: PathBuf,
+    pub resume: bool,
+    /// Input tiles handled between output commits / checkpoint flushes.
+    pub interval: u64,
+}
+
+/// A reader rowid range; rows below `next_rowid` are committed to the output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointRange {
+    pub start_rowid: i64,
+    pub end_rowid: i64,
+    pub next_rowid: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruneCheckpoint {
+    pub version: u32,
+    pub input: String,
+    pub input_size: u64,
+    pub output: String,
+    pub ranges: Vec<CheckpointRange>,
+    pub tiles_done: u64,
+    pub complete: bool,
+}
+
+impl PruneCheckpoint {
+    pub fn new(input: &Path, output: &Path, ranges: &[(i64, i64)]) -> Result<Self> {
+        Ok(Self {
+            version: CHECKPOINT_VERSION,
+            input: input.display().to_string(),
+            input_size: file_size(input)?,
+            output: output.display().to_string(),
+            ranges: ranges
+                .iter()
+                .map(|&(start_rowid, end_rowid)| CheckpointRange {
+                    start_rowid,
+                    end_rowid,
+                    next_rowid: start_rowid,
+                })
+                .collect(),
+            tiles_done: 0,
+            complete: false,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read(path)
+            .with_context(|| format!("failed to read checkpoint: {}", path.display()))?;
+        let checkpoint: Self = serde_json::from_slice(&data)
+            .with_context(|| format!("failed to parse checkpoint: {}", path.display()))?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            anyhow::bail!(
+                "unsupported checkpoint version {} (expected {CHECKPOINT_VERSION})",
+                checkpoint.version
+            );
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes via a temporary file and rename so a kill never leaves a torn checkpoint.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        let json = serde_json::to_vec_pretty(self).context("serialize checkpoint")?;
+        fs::write(&tmp, json)
+            .with_context(|| format!("failed to write checkpoint: {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("failed to write checkpoint: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn ensure_matches(&self, input: &Path, output: &Path) -> Result<()> {
+        if self.input != input.display().to_string() || self.input_size != file_size(input)? {
+            anyhow::bail!(
+                "checkpoint was written for a different input: {}",
+                self.input
+            );
+        }
+        if self.output != output.display().to_string() {
+            anyhow::bail!(
+                "checkpoint was written for a different output: {}",
+                self.output
+            );
+        }
+        Ok(())
+    }
+
+    /// Rowid ranges still to read, index-aligned with `ranges`.
+    pub fn remaining_ranges(&self) -> Vec<(i64, i64)> {
+        self.ranges
+            .iter()
+            .map(|range| (range.next_rowid, range.end_rowid))
+            .collect()
+    }
+}
+
+fn file_size(path: &Path) -> Result<u64> {
+    Ok(fs::metadata(path)
+        .with_context(|| format!("failed to stat {}", path.display()))?
+        .len())
+}
+
+/// Releases per-range items in reader order so only a contiguous prefix of
+/// each range is ever written.
+pub(crate) struct RangeOrder<T> {
+    ranges: Vec<RangeState<T>>,
+}
+
+struct RangeState<T> {
+    next_seq: u64,
+    pending: BTreeMap<u64, (i64, Option<T>)>,
+}
+
+impl<T> RangeOrder<T> {
+    pub(crate) fn new(range_count: usize) -> Self {
+        Self {
+            ranges: (0..range_count)
+                .map(|_| RangeState {
+                    next_seq: 0,
+                    pending: BTreeMap::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Queues an item and returns the items that became releasable along with
+    /// the rowid of the last released position in this range.
+    pub(crate) fn push(
+        &mut self,
+        range: usize,
+        seq: u64,
+        rowid: i64,
+        item: Option<T>,
+    ) -> (Vec<T>, Option<i64>, u64) {
+        let state = &mut self.ranges[range];
+        state.pending.insert(seq, (rowid, item));
+        let mut ready = Vec::new();
+        let mut last_rowid = None;
+        let mut released = 0;
+        while let Some((rowid, item)) = state.pending.remove(&state.next_seq) {
+            state.next_seq += 1;
+            released += 1;
+            last_rowid = Some(rowid);
+            ready.extend(item);
+        }
+        (ready, last_rowid, released)
+    }
+}
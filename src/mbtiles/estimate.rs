@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::format::RasterFormat;
+use crate::mbtiles::{
+    PruneOptions, PruneStats, SampleSpec, TileCompression, ZoomTileCounts, compress_tile_payload,
+    include_sample, prune_tile_layers,
+};
+use crate::source::TileSource;
+use crate::style::LayerSelection;
+
+/// What an optimize run would do, from pruning a sample of the tiles
+/// without writing anything (`optimize --dry-run`).
+#[derive(Debug, Default, Serialize)]
+pub struct PruneEstimate {
+    /// Prune statistics of the sampled tiles only.
+    pub sample: PruneStats,
+    /// Tiles before/after and stored bytes, scaled per zoom from the sampled
+    /// tiles to every stored tile.
+    pub projected_by_zoom: BTreeMap<u8, ZoomTileCounts>,
+    /// Removed features, scaled per zoom like `projected_by_zoom`.
+    pub projected_removed_features_by_zoom: BTreeMap<u8, u64>,
+}
+
+impl PruneEstimate {
+    pub fn sampled_tiles(&self) -> u64 {
+        ZoomTileCounts::total(self.sample.tiles_by_zoom.values()).tiles_before
+    }
+
+    pub fn projected_totals(&self) -> ZoomTileCounts {
+        ZoomTileCounts::total(self.projected_by_zoom.values())
+    }
+
+    pub fn projected_removed_features(&self) -> u64 {
+        self.projected_removed_features_by_zoom.values().sum()
+    }
+}
+
+/// Run the optimize prune over the tiles of `source` picked by `sample`
+/// (every tile when `None`; counts apply per zoom) and project the result
+/// onto the whole tileset, zoom by zoom.
+///
+/// `keep_compression` is the codec output tiles get without
+/// `options.recompress`; `None` re-gzips tiles whose input was gzip (MBTiles).
+pub fn estimate_prune<S: TileSource + ?Sized>(
+    source: &S,
+    selection: impl Into<LayerSelection>,
+    apply_filters: bool,
+    options: &PruneOptions,
+    keep_compression: Option<TileCompression>,
+    sample: Option<&SampleSpec>,
+    sample_seed: Option<u64>,
+) -> Result<PruneEstimate> {
+    let zoom_counts = source.zoom_counts()?;
+    let selection = &selection
+        .into()
+        .with_tileset_max_zoom(zoom_counts.keys().next_back().copied());
+
+    let sample_stats = zoom_counts
+        .par_iter()
+        .map(|(&zoom, &total)| -> Result<PruneStats> {
+            let mut stats = PruneStats::default();
+            let mut index: u64 = 0;
+            let mut used: u64 = 0;
+            source.scan_tiles(zoom, None, true, &mut |tile| {
+                index += 1;
+                if !include_sample(index, total, sample, sample_seed) {
+                    return Ok(true);
+                }
+                used += 1;
+                let data = tile
+                    .data
+                    .context("prune estimate scan returned no tile data")?;
+                if options.clamp_zoom && !selection.covers_zoom(zoom) {
+                    stats.clamped_tiles += 1;
+                    stats.record_tile(zoom, data.len(), None);
+                } else if RasterFormat::from_magic(&data).is_some() {
+                    stats.raster_tiles += 1;
+                    stats.record_tile(zoom, data.len(), Some(data.len()));
+                } else {
+                    let output = prune_sampled_tile(
+                        source,
+                        &data,
+                        zoom,
+                        selection,
+                        apply_filters,
+                        options,
+                        keep_compression,
+                        &mut stats,
+                    )?;
+                    stats.record_tile(zoom, data.len(), output);
+                }
+                if let Some(SampleSpec::Count(limit)) = sample
+                    && used >= *limit
+                {
+                    return Ok(false);
+                }
+                Ok(true)
+            })?;
+            Ok(stats)
+        })
+        .try_reduce(PruneStats::default, |mut left, right| {
+            left.merge(right);
+            Ok(left)
+        })?;
+
+    let mut estimate = PruneEstimate::default();
+    for (&zoom, counts) in &sample_stats.tiles_by_zoom {
+        let total = zoom_counts.get(&zoom).copied().unwrap_or(0);
+        let factor = total as f64 / counts.tiles_before.max(1) as f64;
+        let scale = |value: u64| (value as f64 * factor).round() as u64;
+        estimate.projected_by_zoom.insert(
+            zoom,
+            ZoomTileCounts {
+                tiles_before: total,
+                tiles_after: scale(counts.tiles_after),
+                bytes_before: scale(counts.bytes_before),
+                bytes_after: scale(counts.bytes_after),
+            },
+        );
+        if let Some(&removed) = sample_stats.removed_features_by_zoom.get(&zoom) {
+            estimate
+                .projected_removed_features_by_zoom
+                .insert(zoom, scale(removed));
+        }
+    }
+    estimate.sample = sample_stats;
+    Ok(estimate)
+}
+
+/// Output size of one vector tile, or `None` when optimize would drop it.
+#[allow(clippy::too_many_arguments)]
+fn prune_sampled_tile<S: TileSource + ?Sized>(
+    source: &S,
+    data: &[u8],
+    zoom: u8,
+    selection: &LayerSelection,
+    apply_filters: bool,
+    options: &PruneOptions,
+    keep_compression: Option<TileCompression>,
+    stats: &mut PruneStats,
+) -> Result<Option<usize>> {
+    let payload = source.decode_tile(data)?;
+    let encoded = prune_tile_layers(
+        &payload,
+        zoom,
+        selection,
+        apply_filters,
+        options.keep_unknown_filters,
+        options.fix_winding,
+        &options.cleanup,
+        stats,
+        options.keep_keys.as_ref(),
+        &options.layer_renames,
+    )?;
+    if encoded.empty && options.drop_empty_tiles {
+        return Ok(None);
+    }
+    let compression = match (options.recompress, keep_compression) {
+        (Some(compression), _) | (None, Some(compression)) => compression,
+        (None, None) if data.starts_with(&[0x1f, 0x8b]) => {
+            TileCompression::Gzip(options.compression_level)
+        }
+        (None, None) => TileCompression::None,
+    };
+    let tile_data = compress_tile_payload(&encoded.bytes, compression)?;
+    if options.drop_empty_tiles
+        && options
+            .drop_empty_max_bytes
+            .is_some_and(|max| tile_data.len() as u64 <= max)
+    {
+        return Ok(None);
+    }
+    if options.recompress.is_some() {
+        stats
+            .recompress
+            .get_or_insert_default()
+            .record(data.len(), tile_data.len());
+    }
+    Ok(Some(tile_data.len()))
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+/// Min/max tile column and row (XYZ) written at each zoom.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TileExtents {
+    zooms: BTreeMap<u8, [u32; 4]>,
+}
+
+impl TileExtents {
+    pub fn add(&mut self, zoom: u8, x: u32, y: u32) {
+        self.add_range(zoom, x, x, y, y);
+    }
+
+    /// Record the tile block `[x_min, x_max] x [y_min, y_max]` at `zoom`.
+    pub fn add_range(&mut self, zoom: u8, x_min: u32, x_max: u32, y_min: u32, y_max: u32) {
+        let range = self
+            .zooms
+            .entry(zoom)
+            .or_insert([x_min, x_max, y_min, y_max]);
+        range[0] = range[0].min(x_min);
+        range[1] = range[1].max(x_max);
+        range[2] = range[2].min(y_min);
+        range[3] = range[3].max(y_max);
+    }
+
+    pub fn zoom_range(&self) -> Option<(u8, u8)> {
+        Some((*self.zooms.keys().next()?, *self.zooms.keys().next_back()?))
+    }
+
+    /// Union of the written tiles as `[west, south, east, north]` in degrees.
+    pub fn lon_lat_bounds(&self) -> Option<[f64; 4]> {
+        self.zooms
+            .iter()
+            .map(|(&zoom, &[x_min, x_max, y_min, y_max])| {
+                let tiles = (1u64 << zoom) as f64;
+                let lon = |x: u32| f64::from(x) / tiles * 360.0 - 180.0;
+                let lat = |y: u32| {
+                    (PI * (1.0 - 2.0 * f64::from(y) / tiles))
+                        .sinh()
+                        .atan()
+                        .to_degrees()
+                };
+                [lon(x_min), lat(y_max + 1), lon(x_max + 1), lat(y_min)]
+            })
+            .reduce(|a, b| {
+                [
+                    a[0].min(b[0]),
+                    a[1].min(b[1]),
+                    a[2].max(b[2]),
+                    a[3].max(b[3]),
+                ]
+            })
+    }
+
+    /// Intersect `bounds` with the written tiles; the tile bounds alone when
+    /// they do not overlap. `None` when no tile was written.
+    pub fn fit_bounds(&self, bounds: [f64; 4]) -> Option<[f64; 4]> {
+        let tiles = self.lon_lat_bounds()?;
+        let clipped = [
+            bounds[0].max(tiles[0]),
+            bounds[1].max(tiles[1]),
+            bounds[2].min(tiles[2]),
+            bounds[3].min(tiles[3]),
+        ];
+        Some(if clipped[0] >= clipped[2] || clipped[1] >= clipped[3] {
+            tiles
+        } else {
+            clipped
+        })
+    }
+
+    /// Keep `center` (lon, lat, zoom) when it lies inside `bounds`, else move
+    /// it to their middle; the zoom is clamped to the written range.
+    pub fn fit_center(&self, center: [f64; 3], bounds: [f64; 4]) -> [f64; 3] {
+        let zoom = match self.zoom_range() {
+            Some((minzoom, maxzoom)) => center[2].clamp(minzoom as f64, maxzoom as f64),
+            None => center[2],
+        };
+        if (bounds[0]..=bounds[2]).contains(&center[0])
+            && (bounds[1]..=bounds[3]).contains(&center[1])
+        {
+            [center[0], center[1], zoom]
+        } else {
+            [
+                (bounds[0] + bounds[2]) / 2.0,
+                (bounds[1] + bounds[3]) / 2.0,
+                zoom,
+            ]
+        }
+    }
+}
+
+/// Reconcile the `minzoom`, `maxzoom`, `bounds` and `center` entries present
+/// in `metadata` with the tiles actually written; missing entries are not
+/// added.
+///
+/// Zooms are replaced by the written range. Bounds shrink to their
+/// intersection with the written tiles, so precise bounds survive when no
+/// edge tile was dropped. The center is kept while it lies inside the new
+/// bounds and zoom range, and is otherwise moved to the middle of the bounds
+/// (keeping its zoom clamped to the range). Does nothing when no tile was
+/// written.
+pub fn refresh_extent_metadata(metadata: &mut BTreeMap<String, String>, extents: &TileExtents) {
+    let (Some((minzoom, maxzoom)), Some(tile_bounds)) =
+        (extents.zoom_range(), extents.lon_lat_bounds())
+    else {
+        return;
+    };
+    for (name, zoom) in [("minzoom", minzoom), ("maxzoom", maxzoom)] {
+        if let Some(value) = metadata.get_mut(name) {
+            *value = zoom.to_string();
+        }
+    }
+
+    let original = metadata
+        .get("bounds")
+        .and_then(|value| parse_numbers::<4>(value));
+    let bounds = original
+        .and_then(|original| extents.fit_bounds(original))
+        .unwrap_or(tile_bounds);
+    if original.is_some_and(|original| original != bounds) {
+        metadata.insert("bounds".to_string(), join_numbers(&bounds));
+    }
+
+    let Some(value) = metadata.get("center") else {
+        return;
+    };
+    // An unparsable center is replaced, starting from the lowest zoom
+    let center = parse_numbers::<3>(value);
+    let refreshed = extents.fit_center(center.unwrap_or([f64::NAN, f64::NAN, 0.0]), bounds);
+    if center != Some(refreshed) {
+        metadata.insert("center".to_string(), join_numbers(&refreshed));
+    }
+}
+
+fn parse_numbers<const N: usize>(value: &str) -> Option<[f64; N]> {
+    let numbers = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    numbers.try_into().ok()
+}
+
+fn join_numbers(values: &[f64]) -> String {
+    values
+        .iter()
+        .map(|value| ((value * 1e6).round() / 1e6).to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, bounded};
+use std::path::Path;
+use std::thread;
+
+use crate::mbtiles::{
+    TileCoord, apply_read_pragmas, ensure_mbtiles_path, open_readonly_mbtiles, tile_address,
+    tiles_schema_mode,
+};
+
+/// Tiles buffered between the reader thread and the consumer.
+const ITER_CHANNEL_CAPACITY: usize = 256;
+
+/// Streaming iterator over every tile of an MBTiles file, in storage order.
+///
+/// The read-only `Connection` and its prepared statement live on a reader
+/// thread feeding a bounded channel, so the iterator owns no borrowed state
+/// and only a small window of tiles is held in memory. Dropping the iterator
+/// stops the reader.
+pub struct MbtilesTiles {
+    rx: Receiver<Result<(TileCoord, Vec<u8>)>>,
+}
+
+impl Iterator for MbtilesTiles {
+    type Item = Result<(TileCoord, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+/// Iterate over all tiles of `path`, yielding raw (possibly gzip-compressed)
+/// tile bytes; decompress on demand with [`crate::mbtiles::decode_tile_payload`].
+/// `y` is the stored TMS `tile_row`.
+pub fn tiles_iter(path: &Path) -> Result<MbtilesTiles> {
+    ensure_mbtiles_path(path)?;
+    let conn = open_readonly_mbtiles(path)?;
+    apply_read_pragmas(&conn)?;
+    let mode = tiles_schema_mode(&conn)?;
+    let (zoom_col, x_col, y_col) = mode.coord_columns();
+    let query = format!(
+        "SELECT {zoom_col}, {x_col}, {y_col}, {} FROM {}",
+        mode.data_expr(),
+        mode.source()
+    );
+
+    let (tx, rx) = bounded(ITER_CHANNEL_CAPACITY);
+    thread::spawn(move || {
+        let result = (|| -> Result<()> {
+            let mut stmt = conn.prepare(&query).context("prepare tiles iter")?;
+            let mut rows = stmt.query([]).context("query tiles iter")?;
+            while let Some(row) = rows.next().context("read tile row")? {
+                // Rows without a valid tile address are skipped, as in inspect
+                let Some(coord) = tile_address(row, 0)? else {
+                    continue;
+                };
+                if tx.send(Ok((coord, row.get(3)?))).is_err() {
+                    // The iterator was dropped.
+                    return Ok(());
+                }
+            }
+            Ok(())
+        })();
+        if let Err(err) = result {
+            let _ = tx.send(Err(err));
+        }
+    });
+    Ok(MbtilesTiles { rx })
+}
@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use crossbeam_channel::{Receiver, Sender, bounded};
-use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use mvt_reader::Reader;
 use rayon::prelude::*;
 use rusqlite::{Connection, OpenFlags, params};
@@ -8,20 +8,38 @@ use std::cmp::Reverse;
 use std::collections::{BTreeMap, BinaryHeap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::thread;
 use std::time::Duration;
 use tracing::warn;
 
 pub mod algo;
+pub mod checkpoint;
+pub mod estimate;
+pub mod extent;
+pub mod iter;
 pub mod processing;
+pub mod source;
 pub mod stats;
 pub mod types;
+pub mod validate;
+mod writer;
 
 pub use self::algo::*;
+pub use self::checkpoint::*;
+pub use self::estimate::*;
+pub use self::extent::*;
+pub use self::iter::*;
 pub use self::processing::*;
+pub use self::source::*;
 pub use self::stats::*;
 pub use self::types::*;
+pub use self::validate::*;
+use self::writer::{MbtilesTileWriter, reorder_tiles_table};
+
+use crate::error::VtError;
+use crate::format::{RasterFormat, TileFormat, TileMediaType, detect_tile_media_type};
+use crate::source::{TileRow, TileSource};
 
 fn fetch_tile_data(conn: &Connection, coord: TileCoord) -> Result<Option<Vec<u8>>> {
     let query = select_tile_data_query(conn)?;
@@ -37,11 +55,31 @@ fn fetch_tile_data(conn: &Connection, coord: TileCoord) -> Result<Option<Vec<u8>
     }
 }
 
+/// Stored bytes of tile `coord` (XYZ; the row is flipped to TMS), still
+/// compressed as in the file, or `None` when the file has no such tile.
+pub fn read_mbtiles_tile(path: &Path, coord: TileCoord) -> Result<Option<Vec<u8>>> {
+    let conn = open_readonly_mbtiles(path)?;
+    fetch_tile_data(
+        &conn,
+        TileCoord {
+            y: flip_y(coord.zoom, coord.y),
+            ..coord
+        },
+    )
+}
+
 struct LayerAccum {
     feature_count: u64,
     vertex_count: u64,
     property_keys: HashSet<String>,
     property_values: HashSet<String>,
+    invalid_geometry_count: u64,
+    geometry_bytes: u64,
+    attribute_bytes: u64,
+    field_length_bytes_by_zoom: BTreeMap<u8, u64>,
+    top_values: TopValueCounter,
+    key_cardinality: KeyCardinality,
+    tile_stats: LayerTileStats,
 }
 
 impl LayerAccum {
@@ -51,164 +89,489 @@ impl LayerAccum {
             vertex_count: 0,
             property_keys: HashSet::new(),
             property_values: HashSet::new(),
+            invalid_geometry_count: 0,
+            geometry_bytes: 0,
+            attribute_bytes: 0,
+            field_length_bytes_by_zoom: BTreeMap::new(),
+            top_values: TopValueCounter::new(),
+            key_cardinality: KeyCardinality::new(),
+            tile_stats: LayerTileStats::default(),
+        }
+    }
+
+    fn add_property(
+        &mut self,
+        key: String,
+        value: &mvt_reader::feature::Value,
+        histogram_key: Option<&str>,
+        cardinality_cap: Option<usize>,
+    ) {
+        let value_text = format_property_value(value);
+        if let Some(capacity) = cardinality_cap {
+            self.key_cardinality
+                .add(&key, &value_text, encoded_value_size(value), 1, capacity);
+        }
+        let value = value_text;
+        if histogram_key == Some(key.as_str()) {
+            self.top_values.add(value.clone(), 1);
+        }
+        self.tile_stats.add_key(&key, 1);
+        self.property_keys.insert(key);
+        self.property_values.insert(value);
+    }
+
+    fn merge(&mut self, other: LayerAccum) {
+        self.feature_count += other.feature_count;
+        self.vertex_count += other.vertex_count;
+        self.property_keys.extend(other.property_keys);
+        self.property_values.extend(other.property_values);
+        self.invalid_geometry_count += other.invalid_geometry_count;
+        self.geometry_bytes += other.geometry_bytes;
+        self.attribute_bytes += other.attribute_bytes;
+        for (zoom, bytes) in other.field_length_bytes_by_zoom {
+            *self.field_length_bytes_by_zoom.entry(zoom).or_insert(0) += bytes;
+        }
+        self.top_values.merge(other.top_values);
+        self.key_cardinality.merge(other.key_cardinality);
+        self.tile_stats.merge(other.tile_stats);
+    }
+
+    /// Re-encodes the layer without and with properties to add its
+    /// geometry and attribute bytes.
+    fn add_attribute_sizes(
+        &mut self,
+        layer: &mvt_reader::layer::Layer,
+        features: &[mvt_reader::feature::Feature],
+    ) -> Result<()> {
+        let (geometry, attributes) = layer_attribute_sizes(&layer.name, layer.extent, features)?;
+        self.geometry_bytes += geometry;
+        self.attribute_bytes += attributes;
+        Ok(())
+    }
+
+    fn into_summary(
+        self,
+        name: String,
+        check_geometry: bool,
+        attributes_size: bool,
+        layer_bytes: bool,
+        per_tile: bool,
+    ) -> FileLayerSummary {
+        let mut summary = FileLayerSummary {
+            name,
+            vertex_count: self.vertex_count,
+            feature_count: self.feature_count,
+            property_key_count: self.property_keys.len(),
+            property_value_count: self.property_values.len(),
+            invalid_geometry_count: check_geometry.then_some(self.invalid_geometry_count),
+            tiles_with_layer: None,
+            min_features_per_tile: None,
+            max_features_per_tile: None,
+            avg_features_per_tile: None,
+            top_keys: Vec::new(),
+            geometry_bytes: attributes_size.then_some(self.geometry_bytes),
+            attribute_bytes: attributes_size.then_some(self.attribute_bytes),
+            approx_bytes_field_length: layer_bytes
+                .then(|| self.field_length_bytes_by_zoom.values().sum()),
+            approx_bytes_field_length_by_zoom: if layer_bytes {
+                self.field_length_bytes_by_zoom
+            } else {
+                BTreeMap::new()
+            },
+            pct_of_total: None,
+        };
+        if per_tile {
+            self.tile_stats.apply(&mut summary);
         }
+        summary
     }
 }
 
-fn build_file_layer_list(
-    conn: &Connection,
+/// Layer summaries sorted by name, plus the value histograms of
+/// `histogram_key` for layers that have it and the keys flagged by
+/// `cardinality`. `per_tile` adds the per-tile feature counts and top keys
+/// (layer lists for a single zoom).
+#[allow(clippy::too_many_arguments)]
+fn summarize_layer_accums(
+    accums: BTreeMap<String, LayerAccum>,
+    check_geometry: bool,
+    attributes_size: bool,
+    layer_bytes: bool,
+    histogram_key: Option<&str>,
+    cardinality: Option<&CardinalityOptions>,
+    per_tile: bool,
+) -> LayerListParts {
+    let mut layers = Vec::with_capacity(accums.len());
+    let mut histograms = Vec::new();
+    let mut high_cardinality = Vec::new();
+    for (name, mut accum) in accums {
+        let top_values = std::mem::take(&mut accum.top_values);
+        if let Some(key) = histogram_key
+            && !top_values.is_empty()
+        {
+            histograms.push(top_values.into_histogram(name.clone(), key.to_string()));
+        }
+        let key_cardinality = std::mem::take(&mut accum.key_cardinality);
+        if let Some(options) = cardinality {
+            high_cardinality.extend(key_cardinality.into_high_cardinality_keys(&name, options));
+        }
+        layers.push(accum.into_summary(
+            name,
+            check_geometry,
+            attributes_size,
+            layer_bytes,
+            per_tile,
+        ));
+    }
+    fill_layer_byte_shares(&mut layers);
+    sort_high_cardinality_keys(&mut high_cardinality);
+    (layers, histograms, high_cardinality)
+}
+
+/// Layer summaries, property histograms and high-cardinality keys of a
+/// layer list scan.
+type LayerListParts = (
+    Vec<FileLayerSummary>,
+    Vec<PropertyHistogram>,
+    Vec<HighCardinalityKey>,
+);
+
+/// Tile blobs buffered between the layer list readers and decoders; bounds
+/// the memory held by a layer list scan.
+const LAYER_LIST_QUEUE_CAPACITY: usize = 256;
+
+/// Decode one stored tile at `zoom` and add its layers to `accums`. Raster
+/// tiles are ignored.
+#[allow(clippy::too_many_arguments)]
+fn accumulate_layer_tile<S: TileSource + ?Sized>(
+    source: &S,
+    zoom: u8,
+    data: &[u8],
+    check_geometry: bool,
+    attributes_size: bool,
+    layer_bytes: bool,
+    histogram_key: Option<&str>,
+    cardinality_cap: Option<usize>,
+    accums: &mut BTreeMap<String, LayerAccum>,
+) -> Result<()> {
+    if RasterFormat::from_magic(data).is_some() {
+        return Ok(());
+    }
+    let payload = source.decode_tile(data)?;
+    let layer_sizes = if layer_bytes {
+        layer_encoded_sizes(&payload)?
+    } else {
+        Vec::new()
+    };
+    let reader =
+        Reader::new(payload).map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+    let layers = reader
+        .get_layer_metadata()
+        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+    for layer in layers {
+        let entry = accums
+            .entry(layer.name.clone())
+            .or_insert_with(LayerAccum::new);
+        entry.feature_count += layer.feature_count as u64;
+        entry.tile_stats.add_tiles(layer.feature_count as u64, 1);
+        if let Some(&bytes) = layer_sizes.get(layer.layer_index) {
+            *entry.field_length_bytes_by_zoom.entry(zoom).or_insert(0) += bytes;
+        }
+        let features = reader
+            .get_features(layer.layer_index)
+            .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
+        if attributes_size {
+            entry.add_attribute_sizes(&layer, &features)?;
+        }
+        for feature in features {
+            entry.vertex_count += count_vertices(&feature.geometry) as u64;
+            if check_geometry && !is_geometry_valid(&feature.geometry) {
+                entry.invalid_geometry_count += 1;
+            }
+            if let Some(props) = feature.properties {
+                for (key, value) in props {
+                    entry.add_property(key, &value, histogram_key, cardinality_cap);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Streams tile blobs from reader threads through a bounded channel to a
+/// pool of decoders, each accumulating its own layer map; the maps are merged
+/// once every tile is processed. Without sampling, high zooms are split into
+/// tile column chunks read concurrently, as in the histogram scan.
+#[allow(clippy::too_many_arguments)]
+fn build_file_layer_list<S: TileSource + ?Sized>(
+    source: &S,
     sample: Option<&SampleSpec>,
+    sample_seed: Option<u64>,
     total_tiles: u64,
     zoom: Option<u8>,
     no_progress: bool,
-) -> Result<Vec<FileLayerSummary>> {
-    let data_expr = tiles_data_expr(conn)?;
-    let source = tiles_source_clause(conn)?;
-    let zoom_col = if source == "tiles" {
-        "zoom_level"
+    check_geometry: bool,
+    attributes_size: bool,
+    layer_bytes: bool,
+    histogram_key: Option<&str>,
+    cardinality: Option<&CardinalityOptions>,
+) -> Result<LayerListParts> {
+    let cardinality_cap = cardinality.map(|options| options.max_tracked_values);
+    // Per-zoom sampling needs every zoom's tile count
+    let zoom_counts = if zoom.is_none() || matches!(sample, Some(SampleSpec::PerZoom(_))) {
+        source.zoom_counts()?
     } else {
-        "map.zoom_level"
+        BTreeMap::new()
+    };
+    let zooms = match zoom {
+        Some(target) => vec![target],
+        None => zoom_counts.keys().copied().collect::<Vec<_>>(),
     };
-    let query = format!("SELECT {zoom_col}, {data_expr} FROM {source}");
-    let mut stmt = conn.prepare(&query).context("prepare layer list scan")?;
-    let mut rows = stmt.query([]).context("query layer list scan")?;
+    let (sample_offsets, _) = sample_zoom_offsets(&zoom_counts, zoom);
 
-    let mut index: u64 = 0;
-    let mut tiles: Vec<Vec<u8>> = Vec::new();
-    let read_progress = if no_progress {
-        ProgressBar::hidden()
-    } else if total_tiles > 0 {
-        let bar = make_progress_bar(total_tiles);
-        bar.set_message("reading layers");
-        bar
+    let bars = MultiProgress::with_draw_target(if no_progress {
+        ProgressDrawTarget::hidden()
     } else {
-        let spinner = ProgressBar::new_spinner();
-        spinner.set_draw_target(ProgressDrawTarget::stderr_with_hz(20));
+        ProgressDrawTarget::stderr_with_hz(10)
+    });
+    let read_progress = if total_tiles > 0 {
+        bars.add(make_progress_bar(total_tiles))
+    } else {
+        let spinner = bars.add(ProgressBar::new_spinner());
         spinner.set_style(
             ProgressStyle::with_template("[{elapsed_precise}] {spinner:.cyan} {msg}")
                 .unwrap()
                 .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
         );
-        spinner.set_message("reading layers");
         spinner.enable_steady_tick(Duration::from_millis(80));
         spinner
     };
+    read_progress.set_message("reading layers");
+    let processing = bars.add(make_progress_bar(0));
+    processing.set_message("processing layers");
 
-    while let Some(row) = rows.next().context("read layer list row")? {
-        let row_zoom: u8 = row.get(0)?;
-        if let Some(target) = zoom
-            && row_zoom != target
-        {
-            continue;
-        }
-        index += 1;
-        if !include_sample(index, total_tiles, sample) {
-            continue;
-        }
-        let data: Vec<u8> = row.get(1)?;
-        tiles.push(data);
-
-        if let Some(SampleSpec::Count(limit)) = sample
-            && index >= *limit
-        {
-            break;
-        }
-
-        if index == 1 || index.is_multiple_of(100) {
-            read_progress.set_position(index);
+    // Sampling picks tiles by their position in a single ordered scan, so
+    // only unsampled scans are split between readers.
+    let mut tasks = Vec::new();
+    let chunked = sample.is_none() && source.supports_column_ranges();
+    let chunk_count = (rayon::current_num_threads() as u64)
+        .saturating_mul(4)
+        .max(1);
+    for zoom in &zooms {
+        match tile_column_chunks(*zoom, chunk_count) {
+            Some(ranges) if chunked && *zoom >= 12 => {
+                tasks.extend(ranges.into_iter().map(|range| (*zoom, Some(range))));
+            }
+            _ => tasks.push((*zoom, None)),
         }
     }
-
-    read_progress.set_position(index);
-    read_progress.finish();
-
-    let processing = if no_progress {
-        ProgressBar::hidden()
+    let reader_count = if chunked {
+        rayon::current_num_threads().clamp(1, tasks.len().max(1))
     } else {
-        let bar = make_progress_bar(tiles.len() as u64);
-        bar.set_message("processing layers");
-        bar
+        1
     };
-
-    let map = tiles
-        .into_par_iter()
-        .map(|data| {
-            let result = (|| -> Result<BTreeMap<String, LayerAccum>> {
-                let payload = decode_tile_payload(&data)?;
-                let reader = Reader::new(payload)
-                    .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
-                let layers = reader
-                    .get_layer_metadata()
-                    .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
-                let mut local = BTreeMap::new();
-                for layer in layers {
-                    let entry = local
-                        .entry(layer.name.clone())
-                        .or_insert_with(LayerAccum::new);
-                    entry.feature_count += layer.feature_count as u64;
-                    let features = reader
-                        .get_features(layer.layer_index)
-                        .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
-                    for feature in features {
-                        entry.vertex_count += count_vertices(&feature.geometry) as u64;
-                        if let Some(props) = feature.properties {
-                            for (key, value) in props {
-                                entry.property_keys.insert(key);
-                                entry.property_values.insert(format_property_value(&value));
+    let worker_count = rayon::current_num_threads().max(1);
+    let next_task = AtomicUsize::new(0);
+    let read = AtomicU64::new(0);
+    let (tx, rx) = bounded::<(u8, Vec<u8>)>(LAYER_LIST_QUEUE_CAPACITY);
+
+    let map = thread::scope(|scope| -> Result<BTreeMap<String, LayerAccum>> {
+        let workers = (0..worker_count)
+            .map(|_| {
+                let rx = rx.clone();
+                let processing = &processing;
+                scope.spawn(move || -> Result<BTreeMap<String, LayerAccum>> {
+                    let mut accums = BTreeMap::new();
+                    for (zoom, data) in rx {
+                        accumulate_layer_tile(
+                            source,
+                            zoom,
+                            &data,
+                            check_geometry,
+                            attributes_size,
+                            layer_bytes,
+                            histogram_key,
+                            cardinality_cap,
+                            &mut accums,
+                        )?;
+                        processing.inc(1);
+                    }
+                    Ok(accums)
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(rx);
+
+        let readers = (0..reader_count)
+            .map(|_| {
+                let tx = tx.clone();
+                let (tasks, next_task, read) = (&tasks, &next_task, &read);
+                let (read_progress, processing) = (&read_progress, &processing);
+                let (zoom_counts, sample_offsets) = (&zoom_counts, &sample_offsets);
+                scope.spawn(move || -> Result<()> {
+                    // Position in the ordered scan, only meaningful with a
+                    // single reader (sampled scans).
+                    let mut index: u64 = 0;
+                    let mut selected: u64 = 0;
+                    let mut done = false;
+                    while !done {
+                        let Some(&(zoom, range)) =
+                            tasks.get(next_task.fetch_add(1, Ordering::Relaxed))
+                        else {
+                            break;
+                        };
+                        source.scan_tiles(zoom, range, true, &mut |tile| {
+                            index += 1;
+                            let total = read.fetch_add(1, Ordering::Relaxed) + 1;
+                            if total == 1 || total.is_multiple_of(100) {
+                                read_progress.set_position(total);
                             }
-                        }
+                            if !include_zoom_sample(
+                                index,
+                                total_tiles,
+                                zoom,
+                                zoom_counts,
+                                sample_offsets,
+                                sample,
+                                sample_seed,
+                            ) {
+                                return Ok(true);
+                            }
+                            selected += 1;
+                            let data =
+                                tile.data.context("layer list scan returned no tile data")?;
+                            processing.inc_length(1);
+                            if tx.send((zoom, data)).is_err() {
+                                // Every decoder stopped on an error.
+                                done = true;
+                                return Ok(false);
+                            }
+                            if let Some(SampleSpec::Count(limit)) = sample
+                                && selected >= *limit
+                            {
+                                done = true;
+                                return Ok(false);
+                            }
+                            Ok(true)
+                        })?;
                     }
-                }
-                Ok(local)
-            })();
-            processing.inc(1);
-            result
-        })
-        .reduce(
-            || Ok(BTreeMap::new()),
-            |left, right| -> Result<BTreeMap<String, LayerAccum>> {
-                let mut left = left?;
-                let right = right?;
-                for (name, accum) in right {
-                    let entry = left.entry(name).or_insert_with(LayerAccum::new);
-                    entry.feature_count += accum.feature_count;
-                    entry.vertex_count += accum.vertex_count;
-                    entry.property_keys.extend(accum.property_keys);
-                    entry.property_values.extend(accum.property_values);
-                }
-                Ok(left)
-            },
-        )?;
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(tx);
+
+        let mut read_result = Ok(());
+        for reader in readers {
+            let result = reader
+                .join()
+                .map_err(|_| anyhow::anyhow!("layer list reader panicked"))?;
+            if read_result.is_ok() {
+                read_result = result;
+            }
+        }
+        read_progress.set_position(read.load(Ordering::Relaxed));
+        read_progress.finish();
+
+        let mut map = BTreeMap::new();
+        for worker in workers {
+            let accums = worker
+                .join()
+                .map_err(|_| anyhow::anyhow!("layer list worker panicked"))??;
+            for (name, accum) in accums {
+                map.entry(name).or_insert_with(LayerAccum::new).merge(accum);
+            }
+        }
+        read_result?;
+        Ok(map)
+    })?;
 
     processing.finish();
 
-    let mut result = map
-        .into_iter()
-        .map(|(name, accum)| FileLayerSummary {
-            name,
-            vertex_count: accum.vertex_count,
-            feature_count: accum.feature_count,
-            property_key_count: accum.property_keys.len(),
-            property_value_count: accum.property_values.len(),
-        })
-        .collect::<Vec<_>>();
-    result.sort_by(|a, b| a.name.cmp(&b.name));
-    Ok(result)
+    Ok(summarize_layer_accums(
+        map,
+        check_geometry,
+        attributes_size,
+        layer_bytes,
+        histogram_key,
+        cardinality,
+        zoom.is_some(),
+    ))
 }
 
-fn build_tile_summary(
-    conn: &Connection,
+fn build_tile_summary<S: TileSource + ?Sized>(
+    source: &S,
     coord: TileCoord,
     layers_filter: &[String],
+    check_geometry: bool,
 ) -> Result<TileSummary> {
-    let query = select_tile_data_query(conn)?;
-    let data: Vec<u8> = conn
-        .query_row(&query, params![coord.zoom, coord.x, coord.y], |row| {
-            row.get(0)
-        })
+    let data = source
+        .tile_data(coord)?
         .context("failed to read tile data")?;
     let tile_bytes = u64::try_from(data.len()).context("tile data size overflow")?;
-    let payload = decode_tile_payload(&data)?;
+    let payload = source.decode_tile(&data)?;
+    summarize_tile_payload(coord, tile_bytes, payload, layers_filter, check_geometry)
+}
+
+/// Summarize each tile of `coords` in order without scanning the tileset,
+/// passing every summary to `visit` as soon as it is built. Tiles that are
+/// not stored are skipped with a warning. Returns the number of summaries.
+pub fn summarize_tiles<S: TileSource + ?Sized>(
+    source: &S,
+    coords: &[TileCoord],
+    layers_filter: &[String],
+    check_geometry: bool,
+    visit: &mut dyn FnMut(TileSummary) -> Result<()>,
+) -> Result<u64> {
+    let mut count = 0;
+    for &coord in coords {
+        let Some(data) = source.tile_data(coord)? else {
+            warn!(
+                "tile {}/{}/{} not found; skipping",
+                coord.zoom, coord.x, coord.y
+            );
+            continue;
+        };
+        let tile_bytes = u64::try_from(data.len()).context("tile data size overflow")?;
+        let payload = source.decode_tile(&data)?;
+        visit(summarize_tile_payload(
+            coord,
+            tile_bytes,
+            payload,
+            layers_filter,
+            check_geometry,
+        )?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// [`summarize_tiles`] for an MBTiles file, reading every tile through one
+/// connection.
+pub fn summarize_mbtiles_tiles(
+    path: &Path,
+    coords: &[TileCoord],
+    layers_filter: &[String],
+    check_geometry: bool,
+    visit: &mut dyn FnMut(TileSummary) -> Result<()>,
+) -> Result<u64> {
+    ensure_mbtiles_path(path)?;
+    let conn = open_readonly_mbtiles(path)?;
+    apply_read_pragmas(&conn)?;
+    let source = MbtilesConnection::new(conn);
+    summarize_tiles(&source, coords, layers_filter, check_geometry, visit)
+}
+
+/// Build a [`TileSummary`] from a decompressed tile; `tile_bytes` is the
+/// stored (possibly compressed) size.
+pub(crate) fn summarize_tile_payload(
+    coord: TileCoord,
+    tile_bytes: u64,
+    payload: Vec<u8>,
+    layers_filter: &[String],
+    check_geometry: bool,
+) -> Result<TileSummary> {
+    let layer_sizes = layer_encoded_sizes(&payload)?;
     let reader =
         Reader::new(payload).map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
     let layers = reader
@@ -237,9 +600,18 @@ fn build_tile_summary(
         let mut values = HashSet::new();
         let mut vertex_count = 0u64;
         let mut feature_count = 0usize;
+        let mut invalid_geometry_count = 0u64;
+        let mut feature_hashes = HashSet::new();
+        let mut duplicate_feature_count = 0u64;
         for feature in features {
             feature_count += 1;
             vertex_count += count_vertices(&feature.geometry) as u64;
+            if check_geometry && !is_geometry_valid(&feature.geometry) {
+                invalid_geometry_count += 1;
+            }
+            if !feature_hashes.insert(feature_content_hash(&feature)) {
+                duplicate_feature_count += 1;
+            }
             if let Some(props) = feature.properties {
                 for (key, value) in props {
                     keys.insert(key.clone());
@@ -256,11 +628,14 @@ fn build_tile_summary(
         total_vertices += vertex_count;
         summaries.push(LayerSummary {
             name: layer.name,
+            bytes: layer_sizes.get(layer.layer_index).copied().unwrap_or(0),
             feature_count,
             vertex_count,
             property_key_count: key_list.len(),
             property_value_count: values.len(),
             property_keys: key_list,
+            invalid_geometry_count: check_geometry.then_some(invalid_geometry_count),
+            duplicate_feature_count,
         });
     }
     Ok(TileSummary {
@@ -277,10 +652,38 @@ fn build_tile_summary(
     })
 }
 
+/// Size counted by inspect statistics: the stored length, or the decoded
+/// payload length when measuring uncompressed sizes (the row must carry data).
+fn measured_length<S: TileSource + ?Sized>(
+    source: &S,
+    tile: &TileRow,
+    uncompressed: bool,
+) -> Result<u64> {
+    match tile.data.as_deref() {
+        Some(data) if uncompressed => Ok(source.decode_tile(data)?.len() as u64),
+        _ => Ok(tile.length),
+    }
+}
+
+/// `--empty-exact` check of a stored tile: true when it holds no features.
+/// Raster tiles and tiles that fail to decode are never empty.
+pub(crate) fn stored_tile_is_featureless(
+    data: &[u8],
+    decode: impl FnOnce(&[u8]) -> Result<Vec<u8>>,
+) -> bool {
+    if RasterFormat::from_magic(data).is_some() {
+        return false;
+    }
+    decode(data)
+        .and_then(|payload| tile_feature_count(&payload))
+        .is_ok_and(|count| count == 0)
+}
+
 #[allow(clippy::too_many_arguments)]
-fn build_histogram(
-    path: &Path,
+fn build_histogram<S: TileSource + ?Sized>(
+    source: &S,
     sample: Option<&SampleSpec>,
+    sample_seed: Option<u64>,
     total_tiles_db: u64,
     total_tiles_used: u64,
     total_bytes_used: u64,
@@ -289,13 +692,13 @@ fn build_histogram(
     max_len: u64,
     zoom: Option<u8>,
     max_tile_bytes: u64,
+    floor: Option<u64>,
+    uncompressed: bool,
     no_progress: bool,
 ) -> Result<Vec<HistogramBucket>> {
     if buckets == 0 || min_len > max_len {
         return Ok(Vec::new());
     }
-    let conn = open_readonly_mbtiles(path)?;
-    apply_read_pragmas(&conn)?;
     let progress = if no_progress {
         ProgressBar::hidden()
     } else {
@@ -305,20 +708,20 @@ fn build_histogram(
     };
     let range = (max_len - min_len).max(1);
     let bucket_size = ((range as f64) / buckets as f64).ceil() as u64;
-    let tile_source = tiles_source_clause(&conn)?;
-    let allow_column_chunk = sample.is_none() && tile_source == "tiles";
+    let allow_column_chunk = sample.is_none() && source.supports_column_ranges();
     let chunk_count = (rayon::current_num_threads() as u64)
         .saturating_mul(4)
         .max(1);
-    let query = select_zoom_length_by_zoom_query(&conn)?;
-    let query_with_column_range = select_zoom_length_by_zoom_and_column_range_query(&conn)?;
-    let zoom_counts = fetch_zoom_counts(&conn)?;
+    let zoom_counts = source.zoom_counts()?;
     let zooms = if let Some(target) = zoom {
         vec![target]
     } else {
         zoom_counts.keys().copied().collect::<Vec<_>>()
     };
+    let (sample_offsets, sample_total) = sample_zoom_offsets(&zoom_counts, zoom);
     let processed = Arc::new(AtomicU64::new(0));
+    let excluded_tiles = AtomicU64::new(0);
+    let excluded_bytes = AtomicU64::new(0);
     let progress = progress.clone();
 
     let mut tasks = Vec::new();
@@ -339,31 +742,13 @@ fn build_histogram(
     let (counts, bytes) = tasks
         .into_par_iter()
         .map(|(zoom, range)| -> Result<(Vec<u64>, Vec<u64>)> {
-            let conn = open_readonly_mbtiles(path)?;
-            apply_read_pragmas(&conn)?;
-            let mut stmt = if range.is_some() {
-                conn.prepare(&query_with_column_range)
-                    .context("prepare histogram scan (column range)")?
-            } else {
-                conn.prepare(&query).context("prepare histogram scan")?
-            };
-            let mut rows = if let Some((col_min, col_max)) = range {
-                stmt.query(params![zoom, col_min, col_max])
-                    .context("query histogram scan (column range)")?
-            } else {
-                stmt.query([zoom]).context("query histogram scan")?
-            };
-
-            let total_tiles_db = *zoom_counts.get(&zoom).unwrap_or(&0);
-            let mut index: u64 = 0;
+            let mut index = *sample_offsets.get(&zoom).unwrap_or(&0);
             let mut used: u64 = 0;
             let mut local_counts = vec![0u64; buckets];
             let mut local_bytes = vec![0u64; buckets];
             let mut batch: u64 = 0;
 
-            while let Some(row) = rows.next().context("read histogram row")? {
-                let length: i64 = row.get(0)?;
-                let length = u64::try_from(length).context("tile length must be non-negative")?;
+            source.scan_tiles(zoom, range, uncompressed, &mut |tile| {
                 index += 1;
                 batch += 1;
                 if batch >= 1000 {
@@ -372,23 +757,38 @@ fn build_histogram(
                     batch = 0;
                 }
 
-                if !include_sample(index, total_tiles_db, sample) {
-                    continue;
+                if !include_zoom_sample(
+                    index,
+                    sample_total,
+                    zoom,
+                    &zoom_counts,
+                    &sample_offsets,
+                    sample,
+                    sample_seed,
+                ) {
+                    return Ok(true);
                 }
+                let length = measured_length(source, &tile, uncompressed)?;
                 used += 1;
-                let mut bucket = ((length.saturating_sub(min_len)) / bucket_size) as usize;
-                if bucket >= buckets {
-                    bucket = buckets - 1;
+                if floor.is_some_and(|floor| length <= floor) {
+                    excluded_tiles.fetch_add(1, Ordering::Relaxed);
+                    excluded_bytes.fetch_add(length, Ordering::Relaxed);
+                } else {
+                    let mut bucket = ((length.saturating_sub(min_len)) / bucket_size) as usize;
+                    if bucket >= buckets {
+                        bucket = buckets - 1;
+                    }
+                    local_counts[bucket] += 1;
+                    local_bytes[bucket] += length;
                 }
-                local_counts[bucket] += 1;
-                local_bytes[bucket] += length;
 
                 if let Some(SampleSpec::Count(limit)) = sample
                     && used >= *limit
                 {
-                    break;
+                    return Ok(false);
                 }
-            }
+                Ok(true)
+            })?;
 
             if batch > 0 {
                 let total = processed.fetch_add(batch, Ordering::Relaxed) + batch;
@@ -412,6 +812,8 @@ fn build_histogram(
 
     progress.finish();
 
+    let total_tiles_used = total_tiles_used.saturating_sub(excluded_tiles.into_inner());
+    let total_bytes_used = total_bytes_used.saturating_sub(excluded_bytes.into_inner());
     let mut result = Vec::with_capacity(buckets);
     let mut accum_count = 0u64;
     let mut accum_bytes = 0u64;
@@ -471,21 +873,22 @@ fn build_histogram(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn build_zoom_histograms(
-    path: &Path,
+fn build_zoom_histograms<S: TileSource + ?Sized>(
+    source: &S,
     sample: Option<&SampleSpec>,
+    sample_seed: Option<u64>,
     zoom_counts: &BTreeMap<u8, u64>,
     zoom_minmax: &BTreeMap<u8, (u64, u64)>,
     buckets: usize,
     max_tile_bytes: u64,
+    floor: Option<u64>,
+    uncompressed: bool,
     no_progress: bool,
     total_tiles: u64,
 ) -> Result<Vec<ZoomHistogram>> {
     if buckets == 0 || zoom_minmax.is_empty() {
         return Ok(Vec::new());
     }
-    let conn = open_readonly_mbtiles(path)?;
-    apply_read_pragmas(&conn)?;
     let progress = if no_progress {
         ProgressBar::hidden()
     } else {
@@ -493,13 +896,10 @@ fn build_zoom_histograms(
         bar.set_message("building zoom histograms");
         bar
     };
-    let tile_source = tiles_source_clause(&conn)?;
-    let allow_column_chunk = sample.is_none() && tile_source == "tiles";
+    let allow_column_chunk = sample.is_none() && source.supports_column_ranges();
     let chunk_count = (rayon::current_num_threads() as u64)
         .saturating_mul(4)
         .max(1);
-    let query = select_zoom_length_by_zoom_query(&conn)?;
-    let query_with_column_range = select_zoom_length_by_zoom_and_column_range_query(&conn)?;
 
     #[derive(Clone, Copy)]
     struct ZoomConfig {
@@ -553,22 +953,6 @@ fn build_zoom_histograms(
     let accums = tasks
         .into_par_iter()
         .map(|(zoom, range)| -> Result<(u8, ZoomAccum)> {
-            let conn = open_readonly_mbtiles(path)?;
-            apply_read_pragmas(&conn)?;
-            let mut stmt = if range.is_some() {
-                conn.prepare(&query_with_column_range)
-                    .context("prepare zoom histogram scan (column range)")?
-            } else {
-                conn.prepare(&query)
-                    .context("prepare zoom histogram scan")?
-            };
-            let mut rows = if let Some((col_min, col_max)) = range {
-                stmt.query(params![zoom, col_min, col_max])
-                    .context("query zoom histogram scan (column range)")?
-            } else {
-                stmt.query([zoom]).context("query zoom histogram scan")?
-            };
-
             let config = configs.get(&zoom).expect("zoom histogram config missing");
             let mut accum = ZoomAccum {
                 min_len: config.min_len,
@@ -583,9 +967,7 @@ fn build_zoom_histograms(
             let mut index: u64 = 0;
             let mut batch: u64 = 0;
 
-            while let Some(row) = rows.next().context("read zoom histogram row")? {
-                let length: i64 = row.get(0)?;
-                let length = u64::try_from(length).context("tile length must be non-negative")?;
+            source.scan_tiles(zoom, range, uncompressed, &mut |tile| {
                 index += 1;
                 batch += 1;
                 if batch >= 1000 {
@@ -594,8 +976,12 @@ fn build_zoom_histograms(
                     batch = 0;
                 }
 
-                if !include_sample(index, total_tiles_db, sample) {
-                    continue;
+                if !include_sample(index, total_tiles_db, sample, sample_seed) {
+                    return Ok(true);
+                }
+                let length = measured_length(source, &tile, uncompressed)?;
+                if floor.is_some_and(|floor| length <= floor) {
+                    return Ok(true);
                 }
                 let mut bucket =
                     ((length.saturating_sub(accum.min_len)) / accum.bucket_size) as usize;
@@ -610,9 +996,10 @@ fn build_zoom_histograms(
                 if let Some(SampleSpec::Count(limit)) = sample
                     && accum.used_tiles >= *limit
                 {
-                    break;
+                    return Ok(false);
                 }
-            }
+                Ok(true)
+            })?;
 
             if batch > 0 {
                 let total = processed.fetch_add(batch, Ordering::Relaxed) + batch;
@@ -734,18 +1121,170 @@ fn build_zoom_histograms(
     Ok(result)
 }
 
-fn ensure_mbtiles_path(path: &Path) -> Result<()> {
-    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-    if ext.eq_ignore_ascii_case("mbtiles") {
-        Ok(())
-    } else {
-        anyhow::bail!("only .mbtiles paths are supported in v0.0.3");
+/// Rejects paths whose extension names another tile format; paths without a
+/// recognised extension are accepted (format chosen via `--input-format`).
+/// Fail early when simplify is pointed at a raster tileset.
+pub(crate) fn ensure_vector_tileset<S: TileSource + ?Sized>(source: &S) -> Result<()> {
+    if let Some(format) = source.raster_format()? {
+        anyhow::bail!(
+            "input is a raster tileset ({}); simplify only supports vector tiles",
+            format.as_str()
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn ensure_mbtiles_path(path: &Path) -> Result<()> {
+    match TileFormat::from_extension(path) {
+        Some(TileFormat::Mbtiles) | None => Ok(()),
+        Some(_) => Err(VtError::NotMbtiles {
+            reason: format!("only .mbtiles paths are supported: {}", path.display()),
+        }
+        .into()),
+    }
+}
+
+/// Waits before each retry of a read-only open that found the database
+/// locked; SQLite's busy handler already waits `OPEN_BUSY_TIMEOUT` per try.
+const OPEN_RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_millis(100),
+    Duration::from_millis(400),
+    Duration::from_millis(1600),
+];
+const OPEN_BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub(crate) fn open_readonly_mbtiles(path: &Path) -> Result<Connection> {
+    open_readonly_mbtiles_with(path, false)
+}
+
+/// Open `path` read-only, retrying while another process holds a lock on it
+/// (for example a server writing through WAL). `immutable` opens it with the
+/// `immutable=1` URI parameter: no locks are taken and no WAL is read, which
+/// is only safe for files nobody writes to.
+fn open_readonly_mbtiles_with(path: &Path, immutable: bool) -> Result<Connection> {
+    if !path.try_exists().unwrap_or(true) {
+        return Err(std::io::Error::from(std::io::ErrorKind::NotFound))
+            .with_context(|| format!("mbtiles not found: {}", path.display()));
+    }
+    let mut flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    if immutable {
+        flags |= OpenFlags::SQLITE_OPEN_URI;
+    }
+    let mut delays = OPEN_RETRY_DELAYS.iter();
+    loop {
+        let opened = if immutable {
+            Connection::open_with_flags(immutable_uri(path), flags)
+        } else {
+            Connection::open_with_flags(path, flags)
+        }
+        .and_then(|conn| {
+            conn.busy_timeout(OPEN_BUSY_TIMEOUT)?;
+            // Reading the schema takes the shared lock, so a locked
+            // database fails here rather than on the first query.
+            conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))?;
+            Ok(conn)
+        });
+        match opened {
+            Ok(conn) => return Ok(conn),
+            Err(err) if is_locked(&err) => match delays.next() {
+                Some(delay) => {
+                    warn!(path = %path.display(), "mbtiles is locked, retrying in {delay:?}");
+                    thread::sleep(*delay);
+                }
+                None => {
+                    return Err(err).with_context(|| {
+                        format!(
+                            "mbtiles is locked by another process: {} (retry later, or pass --immutable if the file is not being written)",
+                            path.display()
+                        )
+                    });
+                }
+            },
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to open mbtiles: {}", path.display()));
+            }
+        }
+    }
+}
+
+/// Expose a nonstandard tile table as a temporary `tiles` view with the
+/// standard column names, so every query builder reads it unchanged. The
+/// columns are checked with `PRAGMA table_info` first.
+fn create_tile_table_view(conn: &Connection, names: &TileTableNames) -> Result<()> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "PRAGMA main.table_info({})",
+            quote_identifier(&names.table)
+        ))
+        .context("prepare table_info")?;
+    let available = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .context("query table_info")?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("read table_info row")?;
+    if available.is_empty() {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type IN ('table', 'view') ORDER BY name")
+            .context("prepare table list")?;
+        let tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .context("query table list")?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("read table list")?;
+        anyhow::bail!(
+            "tile table {} not found; available tables: {}",
+            names.table,
+            tables.join(", ")
+        );
+    }
+    let missing = [&names.zoom, &names.column, &names.row, &names.data]
+        .into_iter()
+        .filter(|column| !available.contains(column))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "column(s) {} not found in tile table {}; available columns: {}",
+            missing.join(", "),
+            names.table,
+            available.join(", ")
+        );
     }
+    conn.execute_batch(&format!(
+        "CREATE TEMP VIEW tiles AS SELECT {} AS zoom_level, {} AS tile_column, {} AS tile_row, {} AS tile_data FROM main.{}",
+        quote_identifier(&names.zoom),
+        quote_identifier(&names.column),
+        quote_identifier(&names.row),
+        quote_identifier(&names.data),
+        quote_identifier(&names.table),
+    ))
+    .with_context(|| format!("failed to map tile table {}", names.table))
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
 }
 
-fn open_readonly_mbtiles(path: &Path) -> Result<Connection> {
-    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
-        .with_context(|| format!("failed to open mbtiles: {}", path.display()))
+fn is_locked(err: &rusqlite::Error) -> bool {
+    matches!(
+        err.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// `file:` URI for `path` with `immutable=1`; characters with a meaning in
+/// URIs are percent-encoded.
+fn immutable_uri(path: &Path) -> String {
+    let mut uri = String::from("file:");
+    for ch in path.to_string_lossy().chars() {
+        match ch {
+            '%' | '?' | '#' => uri.push_str(&format!("%{:02X}", ch as u32)),
+            _ => uri.push(ch),
+        }
+    }
+    uri.push_str("?immutable=1");
+    uri
 }
 
 fn apply_read_pragmas(conn: &Connection) -> Result<()> {
@@ -788,6 +1327,124 @@ fn supports_rowid(conn: &Connection, table: &str) -> Result<bool> {
     }
 }
 
+/// Read a tile coordinate column (`zoom_level`, `tile_column`, `tile_row`).
+/// Some legacy exports store them as TEXT or REAL, so any value holding a
+/// whole number is accepted; anything else is a conversion error.
+pub(crate) fn coord_column<T: TryFrom<i64>>(
+    row: &rusqlite::Row<'_>,
+    idx: usize,
+) -> rusqlite::Result<T> {
+    let value: rusqlite::types::Value = row.get(idx)?;
+    let whole = |v: f64| (v.fract() == 0.0 && v.abs() < i64::MAX as f64).then_some(v as i64);
+    let number = match &value {
+        rusqlite::types::Value::Integer(v) => Some(*v),
+        rusqlite::types::Value::Real(v) => whole(*v),
+        rusqlite::types::Value::Text(text) => {
+            let text = text.trim();
+            text.parse::<i64>()
+                .ok()
+                .or_else(|| text.parse::<f64>().ok().and_then(whole))
+        }
+        rusqlite::types::Value::Null | rusqlite::types::Value::Blob(_) => None,
+    };
+    number.and_then(|v| T::try_from(v).ok()).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            idx,
+            value.data_type(),
+            format!("not a tile coordinate: {value:?}").into(),
+        )
+    })
+}
+
+/// Like [`coord_column`], but a value that is not a coordinate of type `T`
+/// gives `None` instead of an error.
+fn optional_coord_column<T: TryFrom<i64>>(
+    row: &rusqlite::Row<'_>,
+    idx: usize,
+) -> rusqlite::Result<Option<T>> {
+    match coord_column(row, idx) {
+        Ok(value) => Ok(Some(value)),
+        Err(rusqlite::Error::FromSqlConversionFailure(..)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Zoom read from column `idx`, or `None` when it is not a zoom level up to
+/// [`MAX_TILE_ZOOM`].
+fn valid_zoom_column(row: &rusqlite::Row<'_>, idx: usize) -> rusqlite::Result<Option<u8>> {
+    Ok(optional_coord_column::<u8>(row, idx)?.filter(|zoom| *zoom <= MAX_TILE_ZOOM))
+}
+
+/// Tile address from the zoom, column and row at `idx..idx + 3`, or `None`
+/// for a corrupt row: a value that is not a non-negative integer, or a zoom
+/// beyond [`MAX_TILE_ZOOM`]. Scans skip such rows.
+pub(crate) fn tile_address(
+    row: &rusqlite::Row<'_>,
+    idx: usize,
+) -> rusqlite::Result<Option<TileCoord>> {
+    let Some(zoom) = valid_zoom_column(row, idx)? else {
+        return Ok(None);
+    };
+    let x = optional_coord_column::<u32>(row, idx + 1)?;
+    let y = optional_coord_column::<u32>(row, idx + 2)?;
+    Ok(x.zip(y).map(|(x, y)| TileCoord { zoom, x, y }))
+}
+
+/// Number of tile rows that [`tile_address`] rejects; with `strict` the
+/// first one is an error instead.
+pub(crate) fn count_corrupt_rows(conn: &Connection, strict: bool) -> Result<u64> {
+    let mode = tiles_schema_mode(conn)?;
+    let (zoom_col, x_col, y_col) = mode.coord_columns();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {zoom_col}, {x_col}, {y_col} FROM {}",
+            mode.coord_table()
+        ))
+        .context("prepare corrupt row scan")?;
+    let mut rows = stmt.query([]).context("query corrupt row scan")?;
+    let mut corrupt = 0u64;
+    while let Some(row) = rows.next().context("read tile address row")? {
+        if tile_address(row, 0)?.is_some() {
+            continue;
+        }
+        if strict {
+            let values: Vec<rusqlite::types::Value> = (0..3)
+                .map(|idx| row.get(idx))
+                .collect::<rusqlite::Result<_>>()?;
+            anyhow::bail!(
+                "invalid tile address zoom_level={:?} tile_column={:?} tile_row={:?} (run without --strict to skip such rows)",
+                values[0],
+                values[1],
+                values[2]
+            );
+        }
+        corrupt += 1;
+    }
+    Ok(corrupt)
+}
+
+/// Whether a tile coordinate column is declared with TEXT affinity, where
+/// range comparisons are lexicographic and column-range scans would miss rows.
+fn has_text_coord_columns(conn: &Connection, mode: TilesSchemaMode) -> Result<bool> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", mode.coord_table()))
+        .context("prepare table_info")?;
+    let mut rows = stmt.query([]).context("query table_info")?;
+    while let Some(row) = rows.next().context("read table_info row")? {
+        let name: String = row.get(1)?;
+        let declared = row.get::<_, String>(2)?.to_ascii_uppercase();
+        // SQLite affinity rules: INT wins over the text type names
+        let text = !declared.contains("INT")
+            && ["CHAR", "CLOB", "TEXT"]
+                .iter()
+                .any(|ty| declared.contains(ty));
+        if text && ["zoom_level", "tile_column", "tile_row"].contains(&name.as_str()) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 fn fetch_zoom_counts(conn: &Connection) -> Result<BTreeMap<u8, u64>> {
     let source = tiles_count_source_clause(conn)?;
     let zoom_col = if source == "map" {
@@ -800,7 +1457,10 @@ fn fetch_zoom_counts(conn: &Connection) -> Result<BTreeMap<u8, u64>> {
     let mut rows = stmt.query([]).context("query zoom counts")?;
     let mut counts = BTreeMap::new();
     while let Some(row) = rows.next().context("read zoom count row")? {
-        let zoom: u8 = row.get(0)?;
+        // Rows at an invalid zoom are corrupt and never scanned
+        let Some(zoom) = valid_zoom_column(row, 0)? else {
+            continue;
+        };
         let count: i64 = row.get(1)?;
         let count = u64::try_from(count).context("tile count must be non-negative")?;
         counts.insert(zoom, count);
@@ -819,16 +1479,133 @@ fn make_progress_bar(total: u64) -> ProgressBar {
     bar
 }
 
-pub fn inspect_mbtiles(path: &Path) -> Result<MbtilesReport> {
-    inspect_mbtiles_with_options(path, InspectOptions::default())
-}
-
-#[allow(clippy::unnecessary_unwrap)]
-pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Result<MbtilesReport> {
+/// Progress bar with throughput and ETA for long rewrites such as optimize.
+pub(crate) fn make_eta_progress_bar(total: u64, no_progress: bool) -> ProgressBar {
+    if no_progress {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stderr_with_hz(10));
+    bar.set_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {per_sec} ETA {eta}",
+        )
+        .unwrap()
+        .progress_chars("=>-"),
+    );
+    bar.enable_steady_tick(Duration::from_millis(200));
+    bar
+}
+
+/// Tile count and sizes of an MBTiles file (or of one zoom) from a single SQL
+/// aggregate; no tile is decoded and no histogram or layer list is built.
+pub fn quick_stats(path: &Path, zoom: Option<u8>) -> Result<MbtilesStats> {
     ensure_mbtiles_path(path)?;
     let conn = open_readonly_mbtiles(path)?;
-    apply_read_pragmas(&conn)?;
-    let metadata = read_metadata(&conn)?;
+    let mode = tiles_schema_mode(&conn)?;
+    let (zoom_column, _, _) = mode.coord_columns();
+    let data = mode.data_expr();
+    let query = format!(
+        "SELECT COUNT(*), COALESCE(SUM(LENGTH({data})), 0), COALESCE(MAX(LENGTH({data})), 0) FROM {} WHERE ?1 IS NULL OR {zoom_column} = ?1",
+        mode.source()
+    );
+    let (tile_count, total_bytes, max_bytes) = conn
+        .query_row(&query, (zoom.map(i64::from),), |row| {
+            Ok((
+                row.get::<_, i64>(0)? as u64,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })
+        .context("aggregate tile sizes")?;
+    Ok(MbtilesStats {
+        tile_count,
+        total_bytes,
+        max_bytes,
+        avg_bytes: total_bytes.checked_div(tile_count).unwrap_or(0),
+        percentiles: None,
+    })
+}
+
+/// Number of stored tiles (or of one zoom) from a `COUNT(*)` query; no tile
+/// row is read.
+pub fn tile_count(path: &Path, zoom: Option<u8>) -> Result<u64> {
+    ensure_mbtiles_path(path)?;
+    MbtilesFile::open(path)?.tile_count(zoom)
+}
+
+pub fn inspect_mbtiles(path: &Path) -> Result<MbtilesReport, VtError> {
+    inspect_mbtiles_with_options(path, InspectOptions::default())
+}
+
+pub fn inspect_mbtiles_with_options(
+    path: &Path,
+    options: InspectOptions,
+) -> Result<MbtilesReport, VtError> {
+    ensure_mbtiles_path(path)?;
+    let source = MbtilesFile::open(path)?;
+    Ok(inspect_tile_source_with_options(&source, options)?)
+}
+
+/// Like [`inspect_mbtiles_with_options`], but listed tiles are passed to
+/// `sink` as the scan visits them instead of being collected into
+/// `bucket_tiles`. Without `--bucket` every scanned tile is emitted; the
+/// `list_tiles` limit caps the number of records (`0` = unlimited) and its
+/// sort order is ignored.
+pub fn inspect_mbtiles_streaming(
+    path: &Path,
+    options: InspectOptions,
+    sink: TileSink<'_>,
+) -> Result<MbtilesReport> {
+    ensure_mbtiles_path(path)?;
+    let source = MbtilesFile::open(path)?;
+    inspect_tile_source_streaming(&source, options, sink)
+}
+
+/// Inspect any [`TileSource`]; the path-based inspect functions are thin
+/// wrappers around this.
+pub fn inspect_tile_source_with_options<S: TileSource + ?Sized>(
+    source: &S,
+    options: InspectOptions,
+) -> Result<MbtilesReport> {
+    inspect_tile_source_impl(source, options, None)
+}
+
+/// Streaming variant of [`inspect_tile_source_with_options`]; see
+/// [`inspect_mbtiles_streaming`].
+pub fn inspect_tile_source_streaming<S: TileSource + ?Sized>(
+    source: &S,
+    options: InspectOptions,
+    sink: TileSink<'_>,
+) -> Result<MbtilesReport> {
+    let limit = options.list_tiles.as_ref().map_or(0, |list| list.limit);
+    let stream = TileStream::new(sink, limit);
+    inspect_tile_source_impl(source, options, Some(&stream))
+}
+
+#[allow(clippy::unnecessary_unwrap)]
+/// Largest tiles as `(bytes, zoom, x, y, hash)`, smallest on top.
+pub(crate) type TopHeap = BinaryHeap<Reverse<(u64, u8, u32, u32, Option<String>)>>;
+
+fn inspect_tile_source_impl<S: TileSource + ?Sized>(
+    source: &S,
+    options: InspectOptions,
+    stream: Option<&TileStream<'_>>,
+) -> Result<MbtilesReport> {
+    let metadata = source.metadata()?;
+    let corrupt_rows = source.corrupt_rows(options.strict)?;
+    let raster_format = source.raster_format()?;
+    let options = match raster_format {
+        Some(format) => options.for_raster(format)?,
+        None => options,
+    };
+    let style = options.read_style()?;
+    if options.layers_only {
+        let mut report = inspect_layers_only(source, metadata, &options)?;
+        report.corrupt_rows = corrupt_rows;
+        report.check_vector_layers(&options);
+        report.preview_style(style.as_ref(), &options);
+        return Ok(report);
+    }
 
     // When sampling, avoid COUNT(*) and use per-zoom counts for sampling decisions.
     let (mut total_tiles, needs_counting) = if options.sample.is_some() {
@@ -838,7 +1615,7 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
     };
     let mut zoom_counts: Option<BTreeMap<u8, u64>> = None;
     if options.sample.is_some() {
-        let counts = fetch_zoom_counts(&conn)?;
+        let counts = source.zoom_counts()?;
         total_tiles = counts.values().sum();
         zoom_counts = Some(counts);
     }
@@ -859,16 +1636,7 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
     };
 
     let total_tiles: u64 = if needs_counting {
-        let query = select_tile_count_query(&conn, options.zoom.is_some())?;
-        let count = match options.zoom {
-            Some(z) => conn
-                .query_row(&query, [z], |row| row.get::<_, i64>(0))
-                .context("failed to read tile count (zoom)")?,
-            None => conn
-                .query_row(&query, [], |row| row.get::<_, i64>(0))
-                .context("failed to read tile count")?,
-        };
-        let count = u64::try_from(count).context("tile count must be non-negative")?;
+        let count = source.tile_count(options.zoom)?;
         if let Some(spinner) = spinner {
             spinner.finish_and_clear();
         }
@@ -877,12 +1645,26 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         total_tiles
     };
 
-    let tile_summary = if options.summary {
-        let coord = options.tile.context("--summary requires --tile z/x/y")?;
-        Some(build_tile_summary(&conn, coord, &options.layers)?)
-    } else {
-        None
-    };
+    let mut tile_summaries = Vec::new();
+    if options.summary {
+        if options.tiles.is_empty() {
+            anyhow::bail!("--summary requires --tile z/x/y");
+        }
+        summarize_tiles(
+            source,
+            &options.tiles,
+            &options.layers,
+            options.check_geometry,
+            &mut |summary| {
+                tile_summaries.push(summary);
+                Ok(())
+            },
+        )?;
+        if tile_summaries.is_empty() {
+            anyhow::bail!("failed to read tile data");
+        }
+    }
+    let tile_summary = tile_summaries.first().cloned();
 
     let progress = if options.no_progress {
         ProgressBar::hidden()
@@ -909,23 +1691,32 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         total_bytes: 0,
         max_bytes: 0,
         avg_bytes: 0,
+        percentiles: None,
     };
 
     let mut by_zoom: BTreeMap<u8, MbtilesStats> = BTreeMap::new();
     let mut zoom_minmax: BTreeMap<u8, (u64, u64)> = BTreeMap::new();
     let mut empty_tiles: u64 = 0;
+    let mut small_tiles: u64 = 0;
     let mut over_limit_tiles: u64 = 0;
     let mut used: u64 = 0;
+    let mut stored_bytes: u64 = 0;
 
     let mut min_len: Option<u64> = None;
     let mut max_len: Option<u64> = None;
 
-    let mut top_heap: BinaryHeap<Reverse<(u64, u8, u32, u32)>> = BinaryHeap::new();
+    let mut top_heap: TopHeap = BinaryHeap::new();
     let mut bucket_tiles: Vec<TopTile> = Vec::new();
+    let size_range = options.list_tiles.as_ref().and_then(|list| list.size_range);
     let topn = options.topn;
 
-    // Store tile sizes for histogram building (when sampling)
-    let should_collect_sizes = options.sample.is_some() && options.histogram_buckets > 0;
+    // Store tile sizes for histogram building: always when sampling, and for
+    // full scans small enough to build the histograms without rescanning
+    let cache_sizes = options.sample.is_none() && total_tiles <= options.histogram_cache_max_tiles;
+    let should_collect_sizes =
+        options.histogram_buckets > 0 && (options.sample.is_some() || cache_sizes);
+    let histogram_floor = options.histogram_floor();
+    let mut zoom_size_ranges: BTreeMap<u8, std::ops::Range<usize>> = BTreeMap::new();
     let mut tile_sizes: Vec<u64> = if should_collect_sizes {
         Vec::new()
     } else {
@@ -934,15 +1725,19 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
 
     // Collect layer information from sampled tiles
     let collect_layers = options.sample.is_some() && options.include_layer_list;
+    let histogram_key = options.property_histogram.as_deref();
+    let cardinality_cap = options
+        .cardinality
+        .as_ref()
+        .map(|cardinality| cardinality.max_tracked_values);
     let mut layer_accums: BTreeMap<String, LayerAccum> = BTreeMap::new();
 
     let zoom_counts_for_scan = if let Some(counts) = zoom_counts.as_ref() {
         counts.clone()
     } else {
-        fetch_zoom_counts(&conn)?
+        source.zoom_counts()?
     };
-    let tile_source = tiles_source_clause(&conn)?;
-    let allow_column_chunk = options.sample.is_none() && tile_source == "tiles";
+    let allow_column_chunk = options.sample.is_none() && source.supports_column_ranges();
     let chunk_count = (rayon::current_num_threads() as u64)
         .saturating_mul(4)
         .max(1);
@@ -955,24 +1750,39 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
     } else {
         zoom_counts_for_scan.keys().copied().collect::<Vec<_>>()
     };
+    let (sample_offsets, sample_total) = sample_zoom_offsets(&zoom_counts_for_scan, options.zoom);
     struct Pass1Accum {
         zoom: u8,
         stats: MbtilesStats,
         min_len: Option<u64>,
         max_len: Option<u64>,
         empty_tiles: u64,
+        small_tiles: u64,
         over_limit_tiles: u64,
-        top_heap: BinaryHeap<Reverse<(u64, u8, u32, u32)>>,
+        top_heap: TopHeap,
         tile_sizes: Vec<u64>,
+        quantiles: Option<SizeQuantiles>,
+        dedup: Option<DedupAccum>,
+        content_hash: Option<ContentHashAccum>,
         layer_accums: BTreeMap<String, LayerAccum>,
+        layer_zoom: LayerZoomCounts,
+        mvt_validation: Option<MvtValidation>,
+        raster_tiles: u64,
+        media_types: BTreeMap<TileMediaType, u64>,
         used: u64,
+        stored_bytes: u64,
     }
 
+    // Duplicate detection hashes payloads unless the source shares content ids
+    let hash_contents = options.dedup_stats && !source.content_ids();
     // When sampling and need layer list, fetch tile_data too for layer extraction
-    let need_tile_data = collect_layers;
-    let query = select_tiles_query_by_zoom(&conn, need_tile_data)?;
-    let query_with_column_range =
-        select_tiles_query_by_zoom_and_column_range(&conn, need_tile_data)?;
+    let need_tile_data = collect_layers
+        || hash_contents
+        || options.layer_zoom_matrix
+        || options.uncompressed_sizes
+        || options.hash.is_some()
+        || options.validate_mvt
+        || options.media_types;
     let processed = Arc::new(AtomicU64::new(0));
     let progress = progress.clone();
 
@@ -994,55 +1804,40 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
     let pass1 = pass1_tasks
         .into_par_iter()
         .map(|(zoom, range)| -> Result<Pass1Accum> {
-            let conn = open_readonly_mbtiles(path)?;
-            apply_read_pragmas(&conn)?;
-            let mut stmt = if range.is_some() {
-                conn.prepare(&query_with_column_range)
-                    .context("prepare tiles scan (column range)")?
-            } else {
-                conn.prepare(&query).context("prepare tiles scan")?
-            };
-            let mut rows = if let Some((col_min, col_max)) = range {
-                stmt.query(params![zoom, col_min, col_max])
-                    .context("query tiles scan (column range)")?
-            } else {
-                stmt.query([zoom]).context("query tiles scan")?
-            };
-
-            let total_tiles_db = *zoom_counts_for_scan.get(&zoom).unwrap_or(&0);
-            let mut index: u64 = 0;
+            let mut index = *sample_offsets.get(&zoom).unwrap_or(&0);
             let mut used: u64 = 0;
+            let mut stored_bytes: u64 = 0;
             let mut stats = MbtilesStats {
                 tile_count: 0,
                 total_bytes: 0,
                 max_bytes: 0,
                 avg_bytes: 0,
+                percentiles: None,
             };
             let mut local_min_len: Option<u64> = None;
             let mut local_max_len: Option<u64> = None;
             let mut empty_tiles: u64 = 0;
+            let mut small_tiles: u64 = 0;
             let mut over_limit_tiles: u64 = 0;
-            let mut top_heap: BinaryHeap<Reverse<(u64, u8, u32, u32)>> = BinaryHeap::new();
+            let mut top_heap: TopHeap = BinaryHeap::new();
             let mut tile_sizes: Vec<u64> = if should_collect_sizes {
                 Vec::new()
             } else {
                 Vec::with_capacity(0)
             };
+            let mut quantiles = options
+                .percentiles
+                .then(|| SizeQuantiles::new(options.sample.is_some()));
+            let mut dedup = options.dedup_stats.then(DedupAccum::new);
+            let mut content_hash = options.hash.map(ContentHashAccum::new);
             let mut layer_accums: BTreeMap<String, LayerAccum> = BTreeMap::new();
+            let mut layer_zoom = LayerZoomCounts::new();
+            let mut mvt_validation = options.validate_mvt.then(MvtValidation::default);
+            let mut raster_tiles: u64 = 0;
+            let mut media_types: BTreeMap<TileMediaType, u64> = BTreeMap::new();
             let mut batch: u64 = 0;
 
-            while let Some(row) = rows.next().context("read tile row")? {
-                let _zoom: u8 = row.get(0)?;
-                let x: u32 = row.get(1)?;
-                let y: u32 = row.get(2)?;
-                let length: i64 = row.get(3)?;
-                let length = u64::try_from(length).context("tile length must be non-negative")?;
-                let tile_data: Option<Vec<u8>> = if need_tile_data {
-                    Some(row.get(4)?)
-                } else {
-                    None
-                };
-
+            source.scan_tiles(zoom, range, need_tile_data, &mut |tile| {
                 index += 1;
                 batch += 1;
                 if batch >= 1000 {
@@ -1051,35 +1846,161 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                     batch = 0;
                 }
 
-                if options.max_tile_bytes > 0 && length > options.max_tile_bytes {
-                    over_limit_tiles += 1;
+                let included = options.sample.as_ref().is_none_or(|sample| {
+                    include_zoom_sample(
+                        index,
+                        sample_total,
+                        zoom,
+                        &zoom_counts_for_scan,
+                        &sample_offsets,
+                        Some(sample),
+                        options.sample_seed,
+                    )
+                });
+                if options.uncompressed_sizes && !included {
+                    return Ok(true);
+                }
+                let payload = match tile.data.as_deref() {
+                    Some(data)
+                        if included && (options.uncompressed_sizes || options.hash.is_some()) =>
+                    {
+                        Some(source.decode_tile(data)?)
+                    }
+                    _ => None,
+                };
+                let length = match payload.as_ref() {
+                    Some(payload) if options.uncompressed_sizes => payload.len() as u64,
+                    _ => tile.length,
+                };
+                let TileRow {
+                    x,
+                    y,
+                    length: stored_length,
+                    data: tile_data,
+                    content_id,
+                    ..
+                } = tile;
+
+                if !included {
+                    return Ok(true);
                 }
 
-                if let Some(sample) = options.sample.as_ref()
-                    && !include_sample(index, total_tiles_db, Some(sample))
-                {
-                    continue;
+                // Counts the sample; unsampled tiles are counted from their lengths below
+                if options.max_tile_bytes > 0 && length > options.max_tile_bytes {
+                    over_limit_tiles += 1;
                 }
 
                 used += 1;
+                stored_bytes += stored_length;
                 stats.tile_count += 1;
                 stats.total_bytes += length;
                 stats.max_bytes = stats.max_bytes.max(length);
 
-                if length <= EMPTY_TILE_MAX_BYTES {
-                    empty_tiles += 1;
+                if length <= options.empty_tile_max_bytes {
+                    let featureless = !options.empty_exact
+                        || match (payload.as_deref(), tile_data.as_deref()) {
+                            (Some(payload), _) => tile_feature_count(payload).is_ok_and(|n| n == 0),
+                            (None, Some(data)) => {
+                                stored_tile_is_featureless(data, |data| source.decode_tile(data))
+                            }
+                            (None, None) => source
+                                .tile_data(TileCoord { zoom, x, y })?
+                                .is_some_and(|data| {
+                                    stored_tile_is_featureless(&data, |data| {
+                                        source.decode_tile(data)
+                                    })
+                                }),
+                        };
+                    if featureless {
+                        empty_tiles += 1;
+                    } else {
+                        small_tiles += 1;
+                    }
                 }
 
-                local_min_len = Some(local_min_len.map_or(length, |v| v.min(length)));
-                local_max_len = Some(local_max_len.map_or(length, |v| v.max(length)));
+                // Histogram bounds only cover the tiles the histograms keep
+                if histogram_floor.is_none_or(|floor| length > floor) {
+                    local_min_len = Some(local_min_len.map_or(length, |v| v.min(length)));
+                    local_max_len = Some(local_max_len.map_or(length, |v| v.max(length)));
+                }
 
                 if should_collect_sizes {
                     tile_sizes.push(length);
                 }
+                if let Some(quantiles) = quantiles.as_mut() {
+                    quantiles.insert(length);
+                }
+                // Leaves use XYZ rows so an MBTiles file and its PMTiles copy agree.
+                let tile_hash = match (content_hash.as_mut(), payload.as_deref()) {
+                    (Some(accum), Some(payload)) => {
+                        Some(accum.add_payload(zoom, x, flip_y(zoom, y), payload))
+                    }
+                    _ => None,
+                };
+                if options.bucket.is_none()
+                    && size_range.is_none()
+                    && let Some(stream) = stream
+                {
+                    stream.emit(TileRecord {
+                        zoom,
+                        x,
+                        y,
+                        bytes: length,
+                        over_limit: options.max_tile_bytes > 0 && length > options.max_tile_bytes,
+                        hash: tile_hash.clone(),
+                    })?;
+                }
+                if let Some(dedup) = dedup.as_mut() {
+                    let key = match (content_id, tile_data.as_deref()) {
+                        (Some(id), _) => Some(ContentKey::Id(id)),
+                        (None, Some(data)) => Some(ContentKey::digest(data)),
+                        (None, None) => None,
+                    };
+                    if let Some(key) = key {
+                        dedup.insert(key, length);
+                    }
+                }
+                let raster_tile = tile_data
+                    .as_deref()
+                    .is_some_and(|data| RasterFormat::from_magic(data).is_some());
+                raster_tiles += u64::from(raster_tile);
+                if options.media_types
+                    && let Some(data) = tile_data.as_deref()
+                {
+                    // Codecs without magic bytes (brotli) are told apart once decoded
+                    let media_type = match detect_tile_media_type(data) {
+                        TileMediaType::Unknown => source
+                            .decode_tile(data)
+                            .map_or(TileMediaType::Unknown, |payload| {
+                                detect_tile_media_type(&payload)
+                            }),
+                        media_type => media_type,
+                    };
+                    *media_types.entry(media_type).or_default() += 1;
+                }
+                if let (Some(validation), Some(data)) =
+                    (mvt_validation.as_mut(), tile_data.as_deref())
+                    && !raster_tile
+                    && let Err(err) = source
+                        .decode_tile(data)
+                        .and_then(|payload| validate_mvt(&payload))
+                {
+                    validation.record(TileDecodeError {
+                        zoom,
+                        x,
+                        y,
+                        error: format!("{err:#}"),
+                    });
+                }
 
                 if collect_layers
                     && tile_data.is_some()
-                    && let Ok(payload) = decode_tile_payload(tile_data.as_ref().unwrap())
+                    && let Ok(payload) = source.decode_tile(tile_data.as_ref().unwrap())
+                    && let Ok(layer_sizes) = if options.layer_bytes {
+                        layer_encoded_sizes(&payload)
+                    } else {
+                        Ok(Vec::new())
+                    }
                     && let Ok(reader) = Reader::new(payload)
                     && let Ok(layers) = reader.get_layer_metadata()
                 {
@@ -1088,13 +2009,27 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                             .entry(layer.name.clone())
                             .or_insert_with(LayerAccum::new);
                         entry.feature_count += layer.feature_count as u64;
+                        entry.tile_stats.add_tiles(layer.feature_count as u64, 1);
+                        if let Some(&bytes) = layer_sizes.get(layer.layer_index) {
+                            *entry.field_length_bytes_by_zoom.entry(zoom).or_insert(0) += bytes;
+                        }
                         if let Ok(features) = reader.get_features(layer.layer_index) {
+                            if options.attributes_size {
+                                entry.add_attribute_sizes(&layer, &features)?;
+                            }
                             for feature in features {
                                 entry.vertex_count += count_vertices(&feature.geometry) as u64;
+                                if options.check_geometry && !is_geometry_valid(&feature.geometry) {
+                                    entry.invalid_geometry_count += 1;
+                                }
                                 if let Some(props) = feature.properties {
                                     for (key, value) in props {
-                                        entry.property_keys.insert(key.clone());
-                                        entry.property_values.insert(format_property_value(&value));
+                                        entry.add_property(
+                                            key,
+                                            &value,
+                                            histogram_key,
+                                            cardinality_cap,
+                                        );
                                     }
                                 }
                             }
@@ -1102,8 +2037,23 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                     }
                 }
 
+                if options.layer_zoom_matrix
+                    && !raster_tile
+                    && let Some(data) = tile_data.as_deref()
+                {
+                    let payload = source.decode_tile(data)?;
+                    let reader = Reader::new(payload)
+                        .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+                    let layers = reader
+                        .get_layer_metadata()
+                        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+                    for layer in layers {
+                        layer_zoom.add(&layer.name, zoom, layer.feature_count as u64);
+                    }
+                }
+
                 if topn > 0 {
-                    top_heap.push(Reverse((length, zoom, x, y)));
+                    top_heap.push(Reverse((length, zoom, x, y, tile_hash)));
                     if top_heap.len() > topn {
                         top_heap.pop();
                     }
@@ -1112,9 +2062,10 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                 if let Some(SampleSpec::Count(limit)) = options.sample
                     && used >= limit
                 {
-                    break;
+                    return Ok(false);
                 }
-            }
+                Ok(true)
+            })?;
 
             if batch > 0 {
                 let total = processed.fetch_add(batch, Ordering::Relaxed) + batch;
@@ -1127,11 +2078,20 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                 min_len: local_min_len,
                 max_len: local_max_len,
                 empty_tiles,
+                small_tiles,
                 over_limit_tiles,
                 top_heap,
                 tile_sizes,
+                quantiles,
+                dedup,
+                content_hash,
                 layer_accums,
+                layer_zoom,
+                mvt_validation,
+                raster_tiles,
+                media_types,
                 used,
+                stored_bytes,
             })
         })
         .collect::<Result<Vec<_>>>()?;
@@ -1147,10 +2107,12 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                     total_bytes: 0,
                     max_bytes: 0,
                     avg_bytes: 0,
+                    percentiles: None,
                 },
                 min_len: None,
                 max_len: None,
                 empty_tiles: 0,
+                small_tiles: 0,
                 over_limit_tiles: 0,
                 top_heap: BinaryHeap::new(),
                 tile_sizes: if should_collect_sizes {
@@ -1158,15 +2120,35 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                 } else {
                     Vec::with_capacity(0)
                 },
+                quantiles: options
+                    .percentiles
+                    .then(|| SizeQuantiles::new(options.sample.is_some())),
+                dedup: options.dedup_stats.then(DedupAccum::new),
+                content_hash: options.hash.map(ContentHashAccum::new),
                 layer_accums: BTreeMap::new(),
+                layer_zoom: LayerZoomCounts::new(),
+                mvt_validation: options.validate_mvt.then(MvtValidation::default),
+                raster_tiles: 0,
+                media_types: BTreeMap::new(),
                 used: 0,
+                stored_bytes: 0,
             });
 
         entry.used += accum.used;
+        entry.stored_bytes += accum.stored_bytes;
+        entry.raster_tiles += accum.raster_tiles;
+        merge_media_types(&mut entry.media_types, accum.media_types);
+        entry.layer_zoom.merge(accum.layer_zoom);
+        if let (Some(validation), Some(other)) =
+            (entry.mvt_validation.as_mut(), accum.mvt_validation)
+        {
+            validation.merge(other);
+        }
         entry.stats.tile_count += accum.stats.tile_count;
         entry.stats.total_bytes += accum.stats.total_bytes;
         entry.stats.max_bytes = entry.stats.max_bytes.max(accum.stats.max_bytes);
         entry.empty_tiles += accum.empty_tiles;
+        entry.small_tiles += accum.small_tiles;
         entry.over_limit_tiles += accum.over_limit_tiles;
         if let Some(min) = accum.min_len {
             entry.min_len = Some(entry.min_len.map_or(min, |v| v.min(min)));
@@ -1177,16 +2159,25 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         if should_collect_sizes {
             entry.tile_sizes.extend(accum.tile_sizes);
         }
+        if let (Some(quantiles), Some(other)) = (entry.quantiles.as_mut(), accum.quantiles.as_ref())
+        {
+            quantiles.merge(other);
+        }
+        if let (Some(dedup), Some(other)) = (entry.dedup.as_mut(), accum.dedup.as_ref()) {
+            dedup.merge(other);
+        }
+        if let (Some(hash), Some(other)) =
+            (entry.content_hash.as_mut(), accum.content_hash.as_ref())
+        {
+            hash.merge(other);
+        }
         if collect_layers {
             for (name, layer_accum) in accum.layer_accums {
-                let target = entry
+                entry
                     .layer_accums
                     .entry(name)
-                    .or_insert_with(LayerAccum::new);
-                target.feature_count += layer_accum.feature_count;
-                target.vertex_count += layer_accum.vertex_count;
-                target.property_keys.extend(layer_accum.property_keys);
-                target.property_values.extend(layer_accum.property_values);
+                    .or_insert_with(LayerAccum::new)
+                    .merge(layer_accum);
             }
         }
         if topn > 0 {
@@ -1199,14 +2190,58 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         }
     }
 
-    for accum in pass1_by_zoom.into_values() {
+    let mut overall_quantiles = options
+        .percentiles
+        .then(|| SizeQuantiles::new(options.sample.is_some()));
+    let mut overall_dedup = options.dedup_stats.then(DedupAccum::new);
+    let mut overall_hash = options.hash.map(ContentHashAccum::new);
+    let mut dedup_by_zoom = Vec::new();
+    let mut layer_zoom = LayerZoomCounts::new();
+    let mut mvt_validation = options.validate_mvt.then(MvtValidation::default);
+    let mut raster_tiles: u64 = 0;
+    let mut media_types = BTreeMap::new();
+    let mut over_limit_by_zoom = Vec::new();
+    for mut accum in pass1_by_zoom.into_values() {
         let zoom = accum.zoom;
+        over_limit_by_zoom.push((
+            accum.over_limit_tiles,
+            accum.used,
+            zoom_counts_for_scan.get(&zoom).copied().unwrap_or(0),
+        ));
         used += accum.used;
+        raster_tiles += accum.raster_tiles;
+        merge_media_types(&mut media_types, std::mem::take(&mut accum.media_types));
+        stored_bytes += accum.stored_bytes;
+        layer_zoom.merge(accum.layer_zoom);
+        if let (Some(validation), Some(other)) =
+            (mvt_validation.as_mut(), accum.mvt_validation.take())
+        {
+            validation.merge(other);
+        }
         overall.tile_count += accum.stats.tile_count;
         overall.total_bytes += accum.stats.total_bytes;
         overall.max_bytes = overall.max_bytes.max(accum.stats.max_bytes);
+        if let Some(quantiles) = accum.quantiles.as_mut() {
+            accum.stats.percentiles = quantiles.percentiles();
+            if let Some(overall_quantiles) = overall_quantiles.as_mut() {
+                overall_quantiles.merge(quantiles);
+            }
+        }
+        if let (Some(overall_hash), Some(hash)) =
+            (overall_hash.as_mut(), accum.content_hash.as_ref())
+        {
+            overall_hash.merge(hash);
+        }
+        if let (Some(overall_dedup), Some(dedup)) = (overall_dedup.as_mut(), accum.dedup.as_ref()) {
+            overall_dedup.merge(dedup);
+            dedup_by_zoom.push(ZoomDedupStats {
+                zoom,
+                stats: dedup.stats(),
+            });
+        }
         by_zoom.insert(zoom, accum.stats);
         empty_tiles += accum.empty_tiles;
+        small_tiles += accum.small_tiles;
         over_limit_tiles += accum.over_limit_tiles;
         if let (Some(min), Some(max)) = (accum.min_len, accum.max_len) {
             min_len = Some(min_len.map_or(min, |v| v.min(min)));
@@ -1214,15 +2249,16 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
             zoom_minmax.insert(zoom, (min, max));
         }
         if should_collect_sizes {
+            let start = tile_sizes.len();
             tile_sizes.extend(accum.tile_sizes);
+            zoom_size_ranges.insert(zoom, start..tile_sizes.len());
         }
         if collect_layers {
             for (name, layer_accum) in accum.layer_accums {
-                let entry = layer_accums.entry(name).or_insert_with(LayerAccum::new);
-                entry.feature_count += layer_accum.feature_count;
-                entry.vertex_count += layer_accum.vertex_count;
-                entry.property_keys.extend(layer_accum.property_keys);
-                entry.property_values.extend(layer_accum.property_values);
+                layer_accums
+                    .entry(name)
+                    .or_insert_with(LayerAccum::new)
+                    .merge(layer_accum);
             }
         }
         if topn > 0 {
@@ -1240,16 +2276,50 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         eprintln!();
     }
 
-    let bucketable = options.bucket.is_some()
-        && options.list_tiles.is_some()
+    let (over_limit_tiles_exact, over_limit_tiles_sampled) =
+        match (options.sample.as_ref(), options.max_tile_bytes) {
+            (None, _) | (Some(_), 0) => (Some(over_limit_tiles), None),
+            (Some(_), max_tile_bytes) => {
+                let exact = (!options.uncompressed_sizes)
+                    .then(|| count_over_limit_tiles(source, &zooms, max_tile_bytes))
+                    .transpose()?;
+                let estimate = if matches!(options.sample, Some(SampleSpec::PerZoom(_))) {
+                    // Zooms are sampled at different rates, so each is scaled on its own
+                    OverLimitEstimate::by_zoom(over_limit_by_zoom)
+                } else {
+                    OverLimitEstimate::new(over_limit_tiles, used, total_tiles)
+                };
+                (exact, Some(estimate))
+            }
+        };
+    let over_limit_tiles = over_limit_tiles_exact.unwrap_or_else(|| {
+        over_limit_tiles_sampled.map_or(over_limit_tiles, |sampled| sampled.estimate)
+    });
+
+    let listable = options.list_tiles.is_some() || stream.is_some();
+    let bucketable = listable
+        && options.bucket.is_some()
         && options.histogram_buckets > 0
         && min_len.is_some()
         && max_len.is_some();
-    if bucketable {
-        let bucket_target = options.bucket.expect("bucket target");
-        let list_options = options.list_tiles.expect("list options");
-        let query = select_tiles_query_by_zoom(&conn, false)?;
-        let query_with_column_range = select_tiles_query_by_zoom_and_column_range(&conn, false)?;
+    if bucketable || (listable && size_range.is_some()) {
+        let list_options = options.list_tiles.clone().unwrap_or(TileListOptions {
+            limit: 0,
+            sort: TileSort::Size,
+            size_range: None,
+        });
+        // A size range replaces the bucket and needs no histogram
+        let selects = |length: u64| match size_range {
+            Some(range) => range.contains(length),
+            None => {
+                histogram_bucket_index(length, min_len, max_len, options.histogram_buckets)
+                    == options.bucket
+            }
+        };
+        // Sampling counts every row, and decoded sizes are only known after
+        // reading the row, so those scans filter here instead of in the query
+        let pushdown =
+            size_range.filter(|_| options.sample.is_none() && !options.uncompressed_sizes);
         let zooms = if let Some(target) = options.zoom {
             vec![target]
         } else {
@@ -1274,67 +2344,81 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         let bucket_results = bucket_tasks
             .into_par_iter()
             .map(|(zoom, range)| -> Result<Vec<TopTile>> {
-                let conn = open_readonly_mbtiles(path)?;
-                apply_read_pragmas(&conn)?;
-                let mut stmt = if range.is_some() {
-                    conn.prepare(&query_with_column_range)
-                        .context("prepare bucket scan (column range)")?
-                } else {
-                    conn.prepare(&query).context("prepare bucket scan")?
-                };
-                let mut rows = if let Some((col_min, col_max)) = range {
-                    stmt.query(params![zoom, col_min, col_max])
-                        .context("query bucket scan (column range)")?
-                } else {
-                    stmt.query([zoom]).context("query bucket scan")?
-                };
-
-                let total_tiles_db = *zoom_counts_for_scan.get(&zoom).unwrap_or(&0);
-                let mut index: u64 = 0;
+                let mut index = *sample_offsets.get(&zoom).unwrap_or(&0);
                 let mut used: u64 = 0;
                 let mut tiles = Vec::new();
 
-                while let Some(row) = rows.next().context("read bucket row")? {
-                    let _zoom: u8 = row.get(0)?;
-                    let x: u32 = row.get(1)?;
-                    let y: u32 = row.get(2)?;
-                    let length: i64 = row.get(3)?;
-                    let length =
-                        u64::try_from(length).context("tile length must be non-negative")?;
+                let with_data = options.uncompressed_sizes || options.hash.is_some();
+                let mut visit = |tile: TileRow| -> Result<bool> {
                     index += 1;
 
                     if let Some(sample) = options.sample.as_ref()
-                        && !include_sample(index, total_tiles_db, Some(sample))
+                        && !include_zoom_sample(
+                            index,
+                            sample_total,
+                            zoom,
+                            &zoom_counts_for_scan,
+                            &sample_offsets,
+                            Some(sample),
+                            options.sample_seed,
+                        )
                     {
-                        continue;
+                        return Ok(true);
                     }
 
+                    let length = measured_length(source, &tile, options.uncompressed_sizes)?;
+                    let TileRow { x, y, data, .. } = tile;
                     used += 1;
-                    if let Some(bucket_idx) =
-                        histogram_bucket_index(length, min_len, max_len, options.histogram_buckets)
-                        && bucket_idx == bucket_target
-                    {
-                        tiles.push(TopTile {
-                            zoom,
-                            x,
-                            y,
-                            bytes: length,
-                        });
-                        if tiles.len() > list_options.limit {
-                            if list_options.sort == TileSort::Size {
-                                tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
-                            } else {
-                                tiles.sort_by(|a, b| (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y)));
+                    if selects(length) {
+                        let tile_hash = match (options.hash, data.as_deref()) {
+                            (Some(algorithm), Some(data)) => {
+                                Some(algorithm.hex_digest(&source.decode_tile(data)?))
+                            }
+                            _ => None,
+                        };
+                        if let Some(stream) = stream {
+                            stream.emit(TileRecord {
+                                zoom,
+                                x,
+                                y,
+                                bytes: length,
+                                over_limit: options.max_tile_bytes > 0
+                                    && length > options.max_tile_bytes,
+                                hash: tile_hash,
+                            })?;
+                        } else {
+                            tiles.push(TopTile {
+                                zoom,
+                                x,
+                                y,
+                                bytes: length,
+                                url: None,
+                                hash: tile_hash,
+                            });
+                            if tiles.len() > list_options.limit {
+                                if list_options.sort == TileSort::Size {
+                                    tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+                                } else {
+                                    tiles.sort_by(|a, b| {
+                                        (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y))
+                                    });
+                                }
+                                tiles.truncate(list_options.limit);
                             }
-                            tiles.truncate(list_options.limit);
                         }
                     }
 
                     if let Some(SampleSpec::Count(limit)) = options.sample
                         && used >= limit
                     {
-                        break;
+                        return Ok(false);
                     }
+                    Ok(true)
+                };
+                match pushdown {
+                    Some(lengths) => source
+                        .scan_tiles_in_size_range(zoom, range, with_data, lengths, &mut visit)?,
+                    None => source.scan_tiles(zoom, range, with_data, &mut visit)?,
                 }
 
                 Ok(tiles)
@@ -1353,34 +2437,40 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
     }
 
     // Build layer list from collected samples or full scan
-    let mut file_layers = if collect_layers && !layer_accums.is_empty() {
-        // Build from sampled tiles
-        let mut result = layer_accums
-            .into_iter()
-            .map(|(name, accum)| FileLayerSummary {
-                name,
-                vertex_count: accum.vertex_count,
-                feature_count: accum.feature_count,
-                property_key_count: accum.property_keys.len(),
-                property_value_count: accum.property_values.len(),
-            })
-            .collect::<Vec<_>>();
-        result.sort_by(|a, b| a.name.cmp(&b.name));
-        result
-    } else if options.include_layer_list && options.sample.is_none() {
-        build_file_layer_list(
-            &conn,
-            options.sample.as_ref(),
-            total_tiles,
-            options.zoom,
-            options.no_progress,
-        )?
-    } else {
-        Vec::new()
-    };
+    let (mut file_layers, mut property_histograms, mut high_cardinality_keys) =
+        if collect_layers && !layer_accums.is_empty() {
+            // Build from sampled tiles
+            summarize_layer_accums(
+                layer_accums,
+                options.check_geometry,
+                options.attributes_size,
+                options.layer_bytes,
+                histogram_key,
+                options.cardinality.as_ref(),
+                options.zoom.is_some(),
+            )
+        } else if options.include_layer_list && options.sample.is_none() {
+            build_file_layer_list(
+                source,
+                options.sample.as_ref(),
+                options.sample_seed,
+                total_tiles,
+                options.zoom,
+                options.no_progress,
+                options.check_geometry,
+                options.attributes_size,
+                options.layer_bytes,
+                histogram_key,
+                options.cardinality.as_ref(),
+            )?
+        } else {
+            (Vec::new(), Vec::new(), Vec::new())
+        };
     if !options.layers.is_empty() {
         let filter: HashSet<&str> = options.layers.iter().map(|s| s.as_str()).collect();
         file_layers.retain(|layer| filter.contains(layer.name.as_str()));
+        property_histograms.retain(|histogram| filter.contains(histogram.layer.as_str()));
+        high_cardinality_keys.retain(|key| filter.contains(key.layer.as_str()));
     }
 
     let by_zoom = by_zoom
@@ -1392,10 +2482,26 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         .collect::<Vec<_>>();
 
     finalize_stats(&mut overall);
+    overall.percentiles = overall_quantiles
+        .as_mut()
+        .and_then(SizeQuantiles::percentiles);
+
+    let dedup = overall_dedup.map(|dedup| DedupReport {
+        estimated: options.sample.is_some(),
+        overall: dedup.stats(),
+        by_zoom: dedup_by_zoom,
+    });
 
     let mut top_tiles = top_heap
         .into_iter()
-        .map(|Reverse((bytes, zoom, x, y))| TopTile { zoom, x, y, bytes })
+        .map(|Reverse((bytes, zoom, x, y, hash))| TopTile {
+            zoom,
+            x,
+            y,
+            bytes,
+            url: None,
+            hash,
+        })
         .collect::<Vec<_>>();
     top_tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
 
@@ -1405,11 +2511,17 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         empty_tiles as f64 / used as f64
     };
 
-    if zoom_counts.is_none() && options.histogram_buckets > 0 && options.zoom.is_none() {
-        zoom_counts = Some(fetch_zoom_counts(&conn)?);
+    if zoom_counts.is_none()
+        && options.histogram_buckets > 0
+        && options.zoom.is_none()
+        && !should_collect_sizes
+    {
+        zoom_counts = Some(source.zoom_counts()?);
     }
 
-    let histogram = if options.histogram_buckets > 0 && min_len.is_some() {
+    let histogram = if options.histogram_buckets > 0
+        && let (Some(min_len), Some(max_len)) = (min_len, max_len)
+    {
         let (level_tiles_used, level_bytes_used) = if let Some(target) = options.zoom {
             by_zoom
                 .iter()
@@ -1420,30 +2532,34 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
             (overall.tile_count, overall.total_bytes)
         };
 
-        // If sampling, build histogram from collected tile sizes (faster)
+        // Build from the sizes collected by the stats scan when available
         if !tile_sizes.is_empty() {
             build_histogram_from_sizes(
                 &tile_sizes,
                 level_tiles_used,
                 level_bytes_used,
                 options.histogram_buckets,
-                min_len.unwrap(),
-                max_len.unwrap(),
+                min_len,
+                max_len,
                 options.max_tile_bytes,
+                histogram_floor,
             )
         } else {
             // Full scan required
             build_histogram(
-                path,
+                source,
                 options.sample.as_ref(),
+                options.sample_seed,
                 total_tiles,
                 level_tiles_used,
                 level_bytes_used,
                 options.histogram_buckets,
-                min_len.unwrap(),
-                max_len.unwrap(),
+                min_len,
+                max_len,
                 options.zoom,
                 options.max_tile_bytes,
+                histogram_floor,
+                options.uncompressed_sizes,
                 options.no_progress,
             )?
         }
@@ -1451,22 +2567,54 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         Vec::new()
     };
 
-    let histograms_by_zoom =
-        if options.histogram_buckets > 0 && options.zoom.is_none() && options.sample.is_none() {
-            let zoom_counts = zoom_counts.as_ref().expect("zoom counts");
-            build_zoom_histograms(
-                path,
-                options.sample.as_ref(),
-                zoom_counts,
-                &zoom_minmax,
-                options.histogram_buckets,
-                options.max_tile_bytes,
-                options.no_progress,
-                total_tiles,
-            )?
-        } else {
-            Vec::new()
-        };
+    let histograms_by_zoom = if options.histogram_buckets > 0
+        && options.zoom.is_none()
+        && options.sample.is_none()
+        && should_collect_sizes
+    {
+        zoom_minmax
+            .iter()
+            .map(|(&zoom, &(min_len, max_len))| {
+                let sizes = zoom_size_ranges
+                    .get(&zoom)
+                    .map_or(&[][..], |range| &tile_sizes[range.clone()]);
+                let (used_tiles, used_bytes) = by_zoom
+                    .iter()
+                    .find(|z| z.zoom == zoom)
+                    .map_or((0, 0), |z| (z.stats.tile_count, z.stats.total_bytes));
+                ZoomHistogram {
+                    zoom,
+                    buckets: build_histogram_from_sizes(
+                        sizes,
+                        used_tiles,
+                        used_bytes,
+                        options.histogram_buckets,
+                        min_len,
+                        max_len,
+                        options.max_tile_bytes,
+                        histogram_floor,
+                    ),
+                }
+            })
+            .collect()
+    } else if options.histogram_buckets > 0 && options.zoom.is_none() && options.sample.is_none() {
+        let zoom_counts = zoom_counts.as_ref().expect("zoom counts");
+        build_zoom_histograms(
+            source,
+            options.sample.as_ref(),
+            options.sample_seed,
+            zoom_counts,
+            &zoom_minmax,
+            options.histogram_buckets,
+            options.max_tile_bytes,
+            histogram_floor,
+            options.uncompressed_sizes,
+            options.no_progress,
+            total_tiles,
+        )?
+    } else {
+        Vec::new()
+    };
 
     let bucket_count = options
         .bucket
@@ -1507,30 +2655,52 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
             .iter()
             .map(|tile| {
                 build_tile_summary(
-                    &conn,
+                    source,
                     TileCoord {
                         zoom: tile.zoom,
                         x: tile.x,
                         y: tile.y,
                     },
                     &[],
+                    options.check_geometry,
                 )
             })
             .collect::<Result<Vec<_>>>()?
     } else {
         Vec::new()
     };
+    let recommendations = if options.recommend {
+        build_recommendations_with_thresholds(
+            &by_zoom,
+            &top_tile_summaries,
+            &options.recommend_thresholds,
+        )
+    } else {
+        Vec::new()
+    };
+    if raster_format.is_some() {
+        raster_tiles = overall.tile_count;
+    }
 
-    Ok(MbtilesReport {
+    let mut report = MbtilesReport {
         metadata,
         overall,
         by_zoom,
         empty_tiles,
         empty_ratio,
+        empty_tile_max_bytes: options.empty_tile_max_bytes,
+        small_tiles: options.empty_exact.then_some(small_tiles),
         over_limit_tiles,
+        over_limit_tiles_exact,
+        over_limit_tiles_sampled,
+        corrupt_rows,
         sampled: options.sample.is_some(),
         sample_total_tiles: total_tiles,
         sample_used_tiles: used,
+        sample_seed: options
+            .sample
+            .is_some()
+            .then(|| options.sample_seed.unwrap_or(DEFAULT_SAMPLE_SEED)),
         histogram,
         histograms_by_zoom,
         file_layers,
@@ -1538,12 +2708,134 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         bucket_count,
         bucket_tiles,
         tile_summary,
+        tile_summaries,
         recommended_buckets,
+        recommendations,
         top_tile_summaries,
+        dedup,
+        content_hash: overall_hash.as_ref().map(ContentHashAccum::finish),
+        layer_zoom_matrix: layer_zoom.into_matrix(&options.layers),
+        property_histograms,
+        stored_bytes: options.uncompressed_sizes.then_some(stored_bytes),
+        mvt_validation,
+        raster_format,
+        raster_tiles,
+        media_types,
+        high_cardinality_keys,
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
+    };
+    report.check_vector_layers(&options);
+    report.preview_style(style.as_ref(), &options);
+    Ok(report)
+}
+
+/// Tiles of `zooms` stored with more than `max_tile_bytes`, counted from
+/// the tile lengths alone.
+fn count_over_limit_tiles<S: TileSource + ?Sized>(
+    source: &S,
+    zooms: &[u8],
+    max_tile_bytes: u64,
+) -> Result<u64> {
+    let over_limit = TileSizeRange::new(Some(max_tile_bytes.saturating_add(1)), None)?;
+    let mut count = 0u64;
+    for &zoom in zooms {
+        source.scan_tiles_in_size_range(zoom, None, false, over_limit, &mut |_| {
+            count += 1;
+            Ok(true)
+        })?;
+    }
+    Ok(count)
+}
+
+fn merge_media_types(
+    counts: &mut BTreeMap<TileMediaType, u64>,
+    other: BTreeMap<TileMediaType, u64>,
+) {
+    for (media_type, count) in other {
+        *counts.entry(media_type).or_default() += count;
+    }
+}
+
+/// Report holding only the metadata and layer list; the size scan is skipped,
+/// so tile counts and byte statistics stay zero.
+fn inspect_layers_only<S: TileSource + ?Sized>(
+    source: &S,
+    metadata: BTreeMap<String, String>,
+    options: &InspectOptions,
+) -> Result<MbtilesReport> {
+    let (mut file_layers, mut property_histograms, mut high_cardinality_keys) =
+        build_file_layer_list(
+            source,
+            None,
+            None,
+            0,
+            options.zoom,
+            options.no_progress,
+            options.check_geometry,
+            options.attributes_size,
+            options.layer_bytes,
+            options.property_histogram.as_deref(),
+            options.cardinality.as_ref(),
+        )?;
+    if !options.layers.is_empty() {
+        let filter: HashSet<&str> = options.layers.iter().map(|s| s.as_str()).collect();
+        file_layers.retain(|layer| filter.contains(layer.name.as_str()));
+        property_histograms.retain(|histogram| filter.contains(histogram.layer.as_str()));
+        high_cardinality_keys.retain(|key| filter.contains(key.layer.as_str()));
+    }
+    let empty_stats = MbtilesStats {
+        tile_count: 0,
+        total_bytes: 0,
+        max_bytes: 0,
+        avg_bytes: 0,
+        percentiles: None,
+    };
+    Ok(MbtilesReport {
+        metadata,
+        overall: empty_stats,
+        by_zoom: Vec::new(),
+        empty_tiles: 0,
+        empty_ratio: 0.0,
+        empty_tile_max_bytes: options.empty_tile_max_bytes,
+        small_tiles: None,
+        over_limit_tiles: 0,
+        over_limit_tiles_exact: None,
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
+        sampled: false,
+        sample_total_tiles: 0,
+        sample_used_tiles: 0,
+        sample_seed: None,
+        histogram: Vec::new(),
+        histograms_by_zoom: Vec::new(),
+        file_layers,
+        top_tiles: Vec::new(),
+        bucket_count: None,
+        bucket_tiles: Vec::new(),
+        tile_summary: None,
+        tile_summaries: Vec::new(),
+        recommended_buckets: Vec::new(),
+        recommendations: Vec::new(),
+        top_tile_summaries: Vec::new(),
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms,
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys,
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     })
 }
 
-fn read_metadata(conn: &Connection) -> Result<BTreeMap<String, String>> {
+pub(crate) fn read_metadata(conn: &Connection) -> Result<BTreeMap<String, String>> {
     let mut metadata = BTreeMap::new();
     let mut stmt = match conn.prepare("SELECT name, value FROM metadata") {
         Ok(stmt) => stmt,
@@ -1557,26 +2849,141 @@ fn read_metadata(conn: &Connection) -> Result<BTreeMap<String, String>> {
     let mut rows = stmt.query([]).context("query metadata")?;
     while let Some(row) = rows.next().context("read metadata row")? {
         let name: String = row.get(0)?;
-        let value: String = row.get(1)?;
+        let value = match row.get_ref(1)? {
+            rusqlite::types::ValueRef::Null => continue,
+            rusqlite::types::ValueRef::Integer(value) => value.to_string(),
+            rusqlite::types::ValueRef::Real(value) => value.to_string(),
+            rusqlite::types::ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+            rusqlite::types::ValueRef::Blob(data) => {
+                metadata_blob_text(data).with_context(|| format!("decode metadata value {name}"))?
+            }
+        };
         metadata.insert(name, value);
     }
     Ok(metadata)
 }
 
+/// Text of a metadata value stored as a BLOB; some writers gzip large
+/// values such as `json`.
+fn metadata_blob_text(data: &[u8]) -> Result<String> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(data), &mut text)
+            .context("gunzip metadata value")?;
+        Ok(text)
+    } else {
+        Ok(String::from_utf8_lossy(data).into_owned())
+    }
+}
+
 fn tiles_schema_mode(conn: &Connection) -> Result<TilesSchemaMode> {
-    if has_table(conn, "tiles")? || has_view(conn, "tiles")? {
+    // A mapped nonstandard tile table (see `create_tile_table_view`) wins.
+    if has_temp_view(conn, "tiles")? {
+        return Ok(TilesSchemaMode::Tiles);
+    }
+    if has_table(conn, "tiles_shallow")? && has_table(conn, "tiles_data")? {
+        return Ok(TilesSchemaMode::Shallow);
+    }
+    if has_table(conn, "tiles")? {
         return Ok(TilesSchemaMode::Tiles);
     }
+    // map/images is read directly rather than through its `tiles` view, so
+    // the shared `tile_id` stays visible to duplicate detection.
     if has_table(conn, "map")? && has_table(conn, "images")? {
         return Ok(TilesSchemaMode::MapImages);
     }
+    if has_view(conn, "tiles")? {
+        return Ok(TilesSchemaMode::Tiles);
+    }
     anyhow::bail!("mbtiles missing tiles table or map/images tables");
 }
 
-#[derive(Clone, Copy)]
+/// Schema of the tile tables already in an output, or `None` for a new file.
+/// Unlike [`tiles_schema_mode`], a `tiles` view over map/images or
+/// tiles_shallow is not mistaken for a plain `tiles` table.
+fn existing_output_schema(conn: &Connection) -> Result<Option<TilesSchemaMode>> {
+    Ok(
+        if has_table(conn, "tiles_shallow")? && has_table(conn, "tiles_data")? {
+            Some(TilesSchemaMode::Shallow)
+        } else if has_table(conn, "map")? && has_table(conn, "images")? {
+            Some(TilesSchemaMode::MapImages)
+        } else if has_table(conn, "tiles")? {
+            Some(TilesSchemaMode::Tiles)
+        } else {
+            None
+        },
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum TilesSchemaMode {
     Tiles,
     MapImages,
+    /// `tiles_shallow` coordinates pointing at deduplicated `tiles_data` rows
+    /// (tippecanoe/planetiler "compact" MBTiles).
+    Shallow,
+}
+
+impl TilesSchemaMode {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Tiles => "tiles",
+            Self::MapImages => "map/images",
+            Self::Shallow => "tiles_shallow/tiles_data",
+        }
+    }
+
+    /// Row source yielding one row per tile with its data.
+    fn source(self) -> &'static str {
+        match self {
+            Self::Tiles => "tiles",
+            Self::MapImages => "map JOIN images ON map.tile_id = images.tile_id",
+            Self::Shallow => {
+                "tiles_shallow JOIN tiles_data ON tiles_shallow.tile_data_id = tiles_data.tile_data_id"
+            }
+        }
+    }
+
+    /// Table holding one row per tile coordinate.
+    fn coord_table(self) -> &'static str {
+        match self {
+            Self::Tiles => "tiles",
+            Self::MapImages => "map",
+            Self::Shallow => "tiles_shallow",
+        }
+    }
+
+    fn data_expr(self) -> &'static str {
+        match self {
+            Self::Tiles => "tile_data",
+            Self::MapImages => "images.tile_data",
+            Self::Shallow => "tiles_data.tile_data",
+        }
+    }
+
+    /// Id shared by tiles with identical content; `NULL` for the plain
+    /// `tiles` table.
+    fn content_id_expr(self) -> &'static str {
+        match self {
+            Self::Tiles => "NULL",
+            Self::MapImages => "CAST(map.tile_id AS TEXT)",
+            Self::Shallow => "CAST(tiles_shallow.tile_data_id AS TEXT)",
+        }
+    }
+
+    /// Qualified `(zoom_level, tile_column, tile_row)` columns.
+    fn coord_columns(self) -> (String, String, String) {
+        let table = self.coord_table();
+        (
+            format!("{table}.zoom_level"),
+            format!("{table}.tile_column"),
+            format!("{table}.tile_row"),
+        )
+    }
+}
+
+pub(crate) fn create_tiles_output_schema(conn: &Connection) -> Result<()> {
+    create_output_schema(conn, TilesSchemaMode::Tiles)
 }
 
 fn create_output_schema(conn: &Connection, mode: TilesSchemaMode) -> Result<()> {
@@ -1609,14 +3016,57 @@ fn create_output_schema(conn: &Connection, mode: TilesSchemaMode) -> Result<()>
                     tile_id TEXT,
                     tile_data BLOB
                 );
+                CREATE VIEW tiles AS
+                    SELECT
+                        map.zoom_level AS zoom_level,
+                        map.tile_column AS tile_column,
+                        map.tile_row AS tile_row,
+                        images.tile_data AS tile_data
+                    FROM map
+                    JOIN images ON images.tile_id = map.tile_id;
                 ",
             )
             .context("failed to create output schema")?;
         }
-    }
-    Ok(())
-}
-
+        TilesSchemaMode::Shallow => {
+            conn.execute_batch(
+                "
+                CREATE TABLE metadata (name TEXT, value TEXT);
+                CREATE TABLE tiles_shallow (
+                    zoom_level INTEGER,
+                    tile_column INTEGER,
+                    tile_row INTEGER,
+                    tile_data_id INTEGER
+                );
+                CREATE TABLE tiles_data (
+                    tile_data_id INTEGER PRIMARY KEY,
+                    tile_data BLOB
+                );
+                CREATE VIEW tiles AS
+                    SELECT
+                        tiles_shallow.zoom_level AS zoom_level,
+                        tiles_shallow.tile_column AS tile_column,
+                        tiles_shallow.tile_row AS tile_row,
+                        tiles_data.tile_data AS tile_data
+                    FROM tiles_shallow
+                    JOIN tiles_data ON tiles_shallow.tile_data_id = tiles_data.tile_data_id;
+                ",
+            )
+            .context("failed to create output schema")?;
+        }
+    }
+    Ok(())
+}
+
+/// Map/images schema whose `images.tile_id` is unique, so identical tiles
+/// share one blob via `INSERT OR IGNORE`.
+fn create_dedup_output_schema(conn: &Connection) -> Result<()> {
+    create_output_schema(conn, TilesSchemaMode::MapImages)?;
+    conn.execute_batch("CREATE UNIQUE INDEX images_id ON images (tile_id);")
+        .context("failed to create images index")?;
+    Ok(())
+}
+
 fn has_table(conn: &Connection, name: &str) -> Result<bool> {
     let count: i64 = conn
         .query_row(
@@ -1639,52 +3089,57 @@ fn has_view(conn: &Connection, name: &str) -> Result<bool> {
     Ok(count > 0)
 }
 
-fn tiles_source_clause(conn: &Connection) -> Result<&'static str> {
-    if has_table(conn, "tiles")? || has_view(conn, "tiles")? {
-        Ok("tiles")
-    } else if has_table(conn, "map")? && has_table(conn, "images")? {
-        Ok("map JOIN images ON map.tile_id = images.tile_id")
-    } else {
-        anyhow::bail!("mbtiles missing tiles table or map/images tables")
-    }
+fn has_temp_view(conn: &Connection, name: &str) -> Result<bool> {
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_temp_master WHERE type='view' AND name=?1",
+            [name],
+            |row| row.get(0),
+        )
+        .context("check temp view exists")?;
+    Ok(count > 0)
 }
 
 fn tiles_count_source_clause(conn: &Connection) -> Result<&'static str> {
-    if has_table(conn, "tiles_shallow")? {
+    if has_temp_view(conn, "tiles")? {
+        Ok("tiles")
+    } else if has_table(conn, "tiles_shallow")? {
         Ok("tiles_shallow")
-    } else if has_table(conn, "tiles")? || has_view(conn, "tiles")? {
+    } else if has_table(conn, "tiles")? {
         Ok("tiles")
     } else if has_table(conn, "map")? && has_table(conn, "images")? {
         Ok("map")
+    } else if has_view(conn, "tiles")? {
+        Ok("tiles")
     } else {
         anyhow::bail!("mbtiles missing tiles table or map/images tables")
     }
 }
 
-fn tiles_data_expr(conn: &Connection) -> Result<&'static str> {
-    if has_table(conn, "tiles")? || has_view(conn, "tiles")? {
-        Ok("tile_data")
-    } else {
-        Ok("images.tile_data")
-    }
+/// `SELECT zoom, column, row, data` over every tile in zoom/column/row
+/// order, for whichever tile schema `conn` uses.
+pub(crate) fn select_all_tiles_ordered_query(conn: &Connection) -> Result<String> {
+    let mode = tiles_schema_mode(conn)?;
+    let (zoom_col, x_col, y_col) = mode.coord_columns();
+    Ok(format!(
+        "SELECT {zoom_col}, {x_col}, {y_col}, {} FROM {} ORDER BY {zoom_col}, {x_col}, {y_col}",
+        mode.data_expr(),
+        mode.source()
+    ))
 }
 
 fn select_tiles_query_by_zoom(conn: &Connection, with_data: bool) -> Result<String> {
-    let source = tiles_source_clause(conn)?;
-    let data_expr = tiles_data_expr(conn)?;
-    let (zoom_col, x_col, y_col) = if source == "tiles" {
-        ("zoom_level", "tile_column", "tile_row")
-    } else {
-        ("map.zoom_level", "map.tile_column", "map.tile_row")
-    };
+    let mode = tiles_schema_mode(conn)?;
+    let (source, data_expr, id_expr) = (mode.source(), mode.data_expr(), mode.content_id_expr());
+    let (zoom_col, x_col, y_col) = mode.coord_columns();
     let select = if with_data {
         format!(
-            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}), {data_expr} \
+            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}), {id_expr}, {data_expr} \
 FROM {source} WHERE {zoom_col} = ?1",
         )
     } else {
         format!(
-            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}) FROM {source} \
+            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}), {id_expr} FROM {source} \
 WHERE {zoom_col} = ?1",
         )
     };
@@ -1695,21 +3150,17 @@ fn select_tiles_query_by_zoom_and_column_range(
     conn: &Connection,
     with_data: bool,
 ) -> Result<String> {
-    let source = tiles_source_clause(conn)?;
-    let data_expr = tiles_data_expr(conn)?;
-    let (zoom_col, x_col, y_col) = if source == "tiles" {
-        ("zoom_level", "tile_column", "tile_row")
-    } else {
-        ("map.zoom_level", "map.tile_column", "map.tile_row")
-    };
+    let mode = tiles_schema_mode(conn)?;
+    let (source, data_expr, id_expr) = (mode.source(), mode.data_expr(), mode.content_id_expr());
+    let (zoom_col, x_col, y_col) = mode.coord_columns();
     let select = if with_data {
         format!(
-            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}), {data_expr} \
+            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}), {id_expr}, {data_expr} \
 FROM {source} WHERE {zoom_col} = ?1 AND {x_col} BETWEEN ?2 AND ?3",
         )
     } else {
         format!(
-            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}) FROM {source} \
+            "SELECT {zoom_col}, {x_col}, {y_col}, LENGTH({data_expr}), {id_expr} FROM {source} \
 WHERE {zoom_col} = ?1 AND {x_col} BETWEEN ?2 AND ?3",
         )
     };
@@ -1741,44 +3192,14 @@ fn tile_column_chunks(zoom: u8, chunks: u64) -> Option<Vec<(i64, i64)>> {
 }
 
 fn select_tile_data_query(conn: &Connection) -> Result<String> {
-    let source = tiles_source_clause(conn)?;
-    let data_expr = tiles_data_expr(conn)?;
-    let (zoom_col, x_col, y_col) = if source == "tiles" {
-        ("zoom_level", "tile_column", "tile_row")
-    } else {
-        ("map.zoom_level", "map.tile_column", "map.tile_row")
-    };
+    let mode = tiles_schema_mode(conn)?;
+    let (source, data_expr) = (mode.source(), mode.data_expr());
+    let (zoom_col, x_col, y_col) = mode.coord_columns();
     Ok(format!(
         "SELECT {data_expr} FROM {source} WHERE {zoom_col} = ?1 AND {x_col} = ?2 AND {y_col} = ?3",
     ))
 }
 
-fn select_zoom_length_by_zoom_query(conn: &Connection) -> Result<String> {
-    let source = tiles_source_clause(conn)?;
-    let data_expr = tiles_data_expr(conn)?;
-    let zoom_col = if source == "tiles" {
-        "zoom_level"
-    } else {
-        "map.zoom_level"
-    };
-    Ok(format!(
-        "SELECT LENGTH({data_expr}) FROM {source} WHERE {zoom_col} = ?1",
-    ))
-}
-
-fn select_zoom_length_by_zoom_and_column_range_query(conn: &Connection) -> Result<String> {
-    let source = tiles_source_clause(conn)?;
-    let data_expr = tiles_data_expr(conn)?;
-    let (zoom_col, x_col) = if source == "tiles" {
-        ("zoom_level", "tile_column")
-    } else {
-        ("map.zoom_level", "map.tile_column")
-    };
-    Ok(format!(
-        "SELECT LENGTH({data_expr}) FROM {source} WHERE {zoom_col} = ?1 AND {x_col} BETWEEN ?2 AND ?3",
-    ))
-}
-
 fn select_tile_count_query(conn: &Connection, with_zoom: bool) -> Result<String> {
     let source = tiles_count_source_clause(conn)?;
     let zoom_col = if source == "map" {
@@ -1795,9 +3216,40 @@ fn select_tile_count_query(conn: &Connection, with_zoom: bool) -> Result<String>
     }
 }
 
+/// MBTiles tiles are gzip-compressed or uncompressed; brotli is PMTiles only.
+fn ensure_mbtiles_compression(compression: Option<TileCompression>) -> Result<()> {
+    if let Some(TileCompression::Brotli(_)) = compression {
+        anyhow::bail!("brotli tile compression is only supported for PMTiles output");
+    }
+    Ok(())
+}
+
 pub fn copy_mbtiles(input: &Path, output: &Path) -> Result<()> {
+    copy_mbtiles_with_options(input, output, None)?;
+    Ok(())
+}
+
+/// Decodes a stored tile and compresses it with `compression`, recording sizes.
+pub(crate) fn recompress_tile(
+    data: &[u8],
+    compression: TileCompression,
+    stats: &mut RecompressStats,
+) -> Result<Vec<u8>> {
+    let recompressed = compress_tile_payload(&decode_tile_payload(data)?, compression)?;
+    stats.record(data.len(), recompressed.len());
+    Ok(recompressed)
+}
+
+/// Like [`copy_mbtiles`], re-encoding every tile with `recompress` when given.
+pub fn copy_mbtiles_with_options(
+    input: &Path,
+    output: &Path,
+    recompress: Option<TileCompression>,
+) -> Result<RecompressStats> {
     ensure_mbtiles_path(input)?;
     ensure_mbtiles_path(output)?;
+    ensure_mbtiles_compression(recompress)?;
+    let mut stats = RecompressStats::default();
     let input_conn = Connection::open(input)
         .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
     let mut output_conn = Connection::open(output)
@@ -1834,10 +3286,13 @@ pub fn copy_mbtiles(input: &Path, output: &Path) -> Result<()> {
                 .context("prepare tiles")?;
             let mut rows = stmt.query([]).context("query tiles")?;
             while let Some(row) = rows.next().context("read tile row")? {
-                let z: i64 = row.get(0)?;
-                let x: i64 = row.get(1)?;
-                let y: i64 = row.get(2)?;
-                let data: Vec<u8> = row.get(3)?;
+                let z: i64 = coord_column(row, 0)?;
+                let x: i64 = coord_column(row, 1)?;
+                let y: i64 = coord_column(row, 2)?;
+                let mut data: Vec<u8> = row.get(3)?;
+                if let Some(compression) = recompress {
+                    data = recompress_tile(&data, compression, &mut stats)?;
+                }
                 tx.execute(
                     "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
                     params![z, x, y, data],
@@ -1853,11 +3308,14 @@ pub fn copy_mbtiles(input: &Path, output: &Path) -> Result<()> {
                 .context("prepare map/images")?;
             let mut rows = stmt.query([]).context("query map/images")?;
             while let Some(row) = rows.next().context("read map/images row")? {
-                let z: i64 = row.get(0)?;
-                let x: i64 = row.get(1)?;
-                let y: i64 = row.get(2)?;
+                let z: i64 = coord_column(row, 0)?;
+                let x: i64 = coord_column(row, 1)?;
+                let y: i64 = coord_column(row, 2)?;
                 let tile_id: String = row.get(3)?;
-                let data: Vec<u8> = row.get(4)?;
+                let mut data: Vec<u8> = row.get(4)?;
+                if let Some(compression) = recompress {
+                    data = recompress_tile(&data, compression, &mut stats)?;
+                }
                 tx.execute(
                     "INSERT INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (?1, ?2, ?3, ?4)",
                     params![z, x, y, tile_id],
@@ -1870,297 +3328,693 @@ pub fn copy_mbtiles(input: &Path, output: &Path) -> Result<()> {
                 .context("insert image row")?;
             }
         }
+        TilesSchemaMode::Shallow => {
+            // Blobs keep their ids, so each shared tile is recompressed once.
+            let mut stmt = input_conn
+                .prepare("SELECT tile_data_id, tile_data FROM tiles_data ORDER BY tile_data_id")
+                .context("prepare tiles_data")?;
+            let mut rows = stmt.query([]).context("query tiles_data")?;
+            while let Some(row) = rows.next().context("read tiles_data row")? {
+                let data_id: i64 = row.get(0)?;
+                let mut data: Vec<u8> = row.get(1)?;
+                if let Some(compression) = recompress {
+                    data = recompress_tile(&data, compression, &mut stats)?;
+                }
+                tx.execute(
+                    "INSERT INTO tiles_data (tile_data_id, tile_data) VALUES (?1, ?2)",
+                    params![data_id, data],
+                )
+                .context("insert tiles_data row")?;
+            }
+            let mut stmt = input_conn
+                .prepare(
+                    "SELECT zoom_level, tile_column, tile_row, tile_data_id FROM tiles_shallow ORDER BY zoom_level, tile_column, tile_row",
+                )
+                .context("prepare tiles_shallow")?;
+            let mut rows = stmt.query([]).context("query tiles_shallow")?;
+            while let Some(row) = rows.next().context("read tiles_shallow row")? {
+                let z: i64 = coord_column(row, 0)?;
+                let x: i64 = coord_column(row, 1)?;
+                let y: i64 = coord_column(row, 2)?;
+                let data_id: i64 = row.get(3)?;
+                tx.execute(
+                    "INSERT INTO tiles_shallow (zoom_level, tile_column, tile_row, tile_data_id) VALUES (?1, ?2, ?3, ?4)",
+                    params![z, x, y, data_id],
+                )
+                .context("insert tiles_shallow row")?;
+            }
+        }
     }
 
     tx.commit().context("commit output")?;
+    Ok(stats)
+}
+
+/// Copy `input` to `output` verbatim except for the tile at `coord` (TMS
+/// row), whose stored bytes become `data`. Map/images outputs give the new
+/// blob its own content-hash id so tiles sharing the old image keep it.
+pub fn replace_mbtiles_tile(
+    input: &Path,
+    output: &Path,
+    coord: TileCoord,
+    data: Vec<u8>,
+) -> Result<()> {
+    copy_mbtiles_with_options(input, output, None)?;
+    let output_conn = Connection::open(output)
+        .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
+    let position = (coord.zoom as i64, coord.x as i64, coord.y as i64);
+    let updated = match tiles_schema_mode(&output_conn)? {
+        TilesSchemaMode::Tiles => output_conn
+            .execute(
+                "UPDATE tiles SET tile_data = ?4 WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                params![position.0, position.1, position.2, data],
+            )
+            .context("update tile")?,
+        TilesSchemaMode::MapImages => {
+            let tile_id = format!("{:032x}", xxhash_rust::xxh3::xxh3_128(&data));
+            output_conn
+                .execute(
+                    "INSERT INTO images (tile_id, tile_data) SELECT ?1, ?2 WHERE NOT EXISTS (SELECT 1 FROM images WHERE tile_id = ?1)",
+                    params![tile_id, data],
+                )
+                .context("insert image")?;
+            output_conn
+                .execute(
+                    "UPDATE map SET tile_id = ?4 WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                    params![position.0, position.1, position.2, tile_id],
+                )
+                .context("update map")?
+        }
+        TilesSchemaMode::Shallow => {
+            output_conn
+                .execute("INSERT INTO tiles_data (tile_data) VALUES (?1)", (data,))
+                .context("insert tiles_data")?;
+            output_conn
+                .execute(
+                    "UPDATE tiles_shallow SET tile_data_id = ?4 WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                    params![
+                        position.0,
+                        position.1,
+                        position.2,
+                        output_conn.last_insert_rowid()
+                    ],
+                )
+                .context("update tiles_shallow")?
+        }
+    };
+    if updated == 0 {
+        return Err(VtError::TileNotFound(coord).into());
+    }
     Ok(())
 }
 
 pub fn prune_mbtiles_layer_only(
     input: &Path,
     output: &Path,
-    style: &crate::style::MapboxStyle,
+    selection: impl Into<crate::style::LayerSelection>,
+    apply_filters: bool,
+    options: PruneOptions,
+) -> Result<PruneStats, VtError> {
+    prune_mbtiles_layer_only_with_checkpoint(input, output, selection, apply_filters, options, None)
+}
+
+/// Like `prune_mbtiles_layer_only`, but commits periodically and records
+/// per-reader rowid progress in a checkpoint file so a killed run can resume.
+/// Stats cover only the tiles processed by this invocation.
+pub fn prune_mbtiles_layer_only_with_checkpoint(
+    input: &Path,
+    output: &Path,
+    selection: impl Into<crate::style::LayerSelection>,
     apply_filters: bool,
     options: PruneOptions,
+    checkpoint: Option<&CheckpointOptions>,
+) -> Result<PruneStats, VtError> {
+    prune_mbtiles_layer_only_with_checkpoint_impl(
+        input,
+        output,
+        selection.into(),
+        apply_filters,
+        options,
+        checkpoint,
+    )
+    .map_err(VtError::from)
+}
+
+fn prune_mbtiles_layer_only_with_checkpoint_impl(
+    input: &Path,
+    output: &Path,
+    selection: crate::style::LayerSelection,
+    apply_filters: bool,
+    options: PruneOptions,
+    checkpoint: Option<&CheckpointOptions>,
 ) -> Result<PruneStats> {
     ensure_mbtiles_path(input)?;
     ensure_mbtiles_path(output)?;
+    ensure_mbtiles_compression(options.recompress)?;
+
+    let resuming = checkpoint.is_some_and(|checkpoint| checkpoint.resume);
+    if resuming && !output.exists() {
+        anyhow::bail!("cannot resume: output not found: {}", output.display());
+    }
 
     let input_conn = Connection::open(input)
         .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
     apply_read_pragmas_with_cache(&input_conn, options.read_cache_mb)?;
-    let mut output_conn = Connection::open(output)
+    // Counted up front so --strict fails before any output is written; the
+    // readers skip the same rows
+    let corrupt_rows = count_corrupt_rows(&input_conn, options.strict)?;
+    let output_conn = Connection::open(output)
         .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
     apply_write_pragmas_with_cache(&output_conn, options.write_cache_mb)?;
     let schema_mode = tiles_schema_mode(&input_conn)?;
-    create_output_schema(&output_conn, schema_mode)?;
+    let output_mode = if options.dedup {
+        TilesSchemaMode::MapImages
+    } else {
+        schema_mode
+    };
+    // Checkpointed runs commit as they go, so their rows are reordered once
+    // the run completes instead of being staged.
+    let reorder_after = checkpoint.is_some() && options.output_order != OutputOrder::None;
+    if reorder_after && output_mode != TilesSchemaMode::Tiles {
+        anyhow::bail!(
+            "--output-order with --checkpoint needs a plain tiles table output (not --dedup, map/images or shallow)"
+        );
+    }
+    let existing_mode = if options.append && !resuming {
+        existing_output_schema(&output_conn)?
+    } else {
+        None
+    };
+    if let Some(existing) = existing_mode
+        && existing != output_mode
+    {
+        anyhow::bail!(
+            "cannot append to {}: it uses the {} schema, but this run writes {}",
+            output.display(),
+            existing.name(),
+            output_mode.name()
+        );
+    }
+    let reader_count = options.readers.max(1);
 
-    let tx = output_conn
-        .transaction()
-        .context("begin output transaction")?;
+    let mut progress = match checkpoint {
+        Some(checkpoint) if checkpoint.resume => {
+            let state = PruneCheckpoint::read(&checkpoint.path)?;
+            state.ensure_matches(input, output)?;
+            Some(state)
+        }
+        Some(_) => {
+            let table = schema_mode.coord_table();
+            if !supports_rowid(&input_conn, table)? {
+                anyhow::bail!("--checkpoint requires an input {table} table with rowids");
+            }
+            let ranges = rowid_ranges(&input_conn, table, reader_count)?;
+            Some(PruneCheckpoint::new(input, output, &ranges)?)
+        }
+        None => None,
+    };
 
-    let mut meta_stmt = input_conn
-        .prepare("SELECT name, value FROM metadata")
-        .context("prepare metadata read")?;
-    let mut meta_rows = meta_stmt.query([]).context("query metadata")?;
-    while let Some(row) = meta_rows.next().context("read metadata row")? {
-        let name: String = row.get(0)?;
-        let value: String = row.get(1)?;
-        tx.execute(
-            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
-            (name, value),
-        )
-        .context("insert metadata")?;
+    if !resuming && existing_mode.is_none() {
+        if options.dedup {
+            create_dedup_output_schema(&output_conn)?;
+        } else {
+            create_output_schema(&output_conn, schema_mode)?;
+        }
+    }
+
+    // Checkpointed runs commit right before saving the checkpoint state. A
+    // run killed between the two leaves rows the checkpoint does not cover,
+    // so a resume appends: those tiles are written again and replace them.
+    let batch_size = checkpoint.is_none().then_some(u64::from(options.io_batch));
+    let mut writer = MbtilesTileWriter::new(&output_conn, batch_size);
+    if existing_mode.is_some() || resuming {
+        writer.append_to(output_mode)?;
+    }
+    if !reorder_after {
+        writer.order_rows(options.output_order)?;
+    }
+
+    if !resuming {
+        copy_metadata_rows(&input_conn, &mut writer)?;
     }
 
-    let keep_layers = style.source_layers();
+    let zoom_counts = fetch_zoom_counts(&input_conn)?;
+    let tileset_max_zoom = zoom_counts.keys().next_back().copied();
+    let bar = make_eta_progress_bar(zoom_counts.values().sum(), options.no_progress);
+    if let Some(state) = progress.as_ref() {
+        bar.set_position(state.tiles_done);
+    }
+    let selection = selection.with_tileset_max_zoom(tileset_max_zoom);
     let worker_count = options.threads.max(1);
-    let reader_count = options.readers.max(1);
     let queue_capacity = options.io_batch.max(1) as usize;
 
     let (tx_in, rx_in): (Sender<TileInput>, Receiver<TileInput>) = bounded(queue_capacity);
-    let (tx_out, rx_out): (Sender<TileOutput>, Receiver<TileOutput>) = bounded(queue_capacity);
+    let (tx_out, rx_out): (Sender<ProcessedTile>, Receiver<ProcessedTile>) =
+        bounded(queue_capacity);
 
     let mut worker_handles = Vec::with_capacity(worker_count);
     for _ in 0..worker_count {
         let rx_in = rx_in.clone();
         let tx_out = tx_out.clone();
-        let keep_layers = keep_layers.clone();
-        let style = style.clone();
+        let selection = selection.clone();
         let drop_empty_tiles = options.drop_empty_tiles;
+        let drop_empty_max_bytes = options.drop_empty_max_bytes;
         let keep_unknown_filters = options.keep_unknown_filters;
+        let fix_winding = options.fix_winding;
+        let compression_level = options.compression_level;
+        let recompress = options.recompress;
+        let cleanup = options.cleanup.clone();
+        let keep_keys = options.keep_keys.clone();
+        let layer_renames = options.layer_renames.clone();
+        let dedup = options.dedup;
+        let clamp_zoom = options.clamp_zoom;
         worker_handles.push(thread::spawn(move || -> Result<PruneStats> {
             let mut stats = PruneStats::default();
             while let Ok(tile) = rx_in.recv() {
+                if clamp_zoom && !selection.covers_zoom(tile.zoom) {
+                    stats.clamped_tiles += 1;
+                    stats.record_tile(tile.zoom, tile.data.len(), None);
+                    tx_out
+                        .send(tile.processed(None))
+                        .context("send processed tile")?;
+                    continue;
+                }
+                if RasterFormat::from_magic(&tile.data).is_some() {
+                    stats.raster_tiles += 1;
+                    stats.record_tile(tile.zoom, tile.data.len(), Some(tile.data.len()));
+                    let processed = if dedup {
+                        tile.processed_dedup(tile.data.clone())
+                    } else {
+                        tile.processed(Some(tile.data.clone()))
+                    };
+                    tx_out.send(processed).context("send processed tile")?;
+                    continue;
+                }
                 let is_gzip = tile.data.starts_with(&[0x1f, 0x8b]);
-                let payload = decode_tile_payload(&tile.data)?;
+                let coord = TileCoord {
+                    zoom: tile.zoom,
+                    x: tile.x,
+                    y: tile.y,
+                };
+                let payload = decode_tile_payload(&tile.data)
+                    .map_err(|err| VtError::decode_tile(coord, err))?;
                 let encoded = prune_tile_layers(
                     &payload,
                     tile.zoom,
-                    &style,
-                    &keep_layers,
+                    &selection,
                     apply_filters,
                     keep_unknown_filters,
+                    fix_winding,
+                    &cleanup,
                     &mut stats,
-                )?;
+                    keep_keys.as_ref(),
+                    &layer_renames,
+                )
+                .map_err(|err| VtError::decode_tile(coord, err))?;
                 if encoded.empty && drop_empty_tiles {
+                    stats.record_tile(tile.zoom, tile.data.len(), None);
+                    tx_out
+                        .send(tile.processed(None))
+                        .context("send processed tile")?;
                     continue;
                 }
-                let tile_data = encode_tile_payload(&encoded.bytes, is_gzip)?;
-                let output = if tile.map_images {
-                    let tile_id = format!("{}-{}-{}", tile.zoom, tile.x, tile.y);
-                    TileOutput::MapImages {
-                        zoom: tile.zoom,
-                        x: tile.x,
-                        y: tile.y,
-                        tile_id,
-                        data: tile_data,
-                    }
+                // An untouched tile keeps its input bytes, so unchanged
+                // tiles are not recompressed and stay byte-identical.
+                let passthrough = encoded.unchanged && recompress.is_none();
+                let tile_data = if passthrough {
+                    tile.data.clone()
                 } else {
-                    TileOutput::Tiles {
+                    let compression = match recompress {
+                        Some(compression) => compression,
+                        None if is_gzip => TileCompression::Gzip(compression_level),
+                        None => TileCompression::None,
+                    };
+                    compress_tile_payload(&encoded.bytes, compression)?
+                };
+                if drop_empty_tiles
+                    && drop_empty_max_bytes.is_some_and(|max| tile_data.len() as u64 <= max)
+                {
+                    stats.record_tile(tile.zoom, tile.data.len(), None);
+                    tx_out
+                        .send(tile.processed(None))
+                        .context("send processed tile")?;
+                    continue;
+                }
+                if passthrough {
+                    stats.unchanged_tiles += 1;
+                }
+                if recompress.is_some() {
+                    stats
+                        .recompress
+                        .get_or_insert_default()
+                        .record(tile.data.len(), tile_data.len());
+                }
+                stats.record_tile(tile.zoom, tile.data.len(), Some(tile_data.len()));
+                let processed = if dedup {
+                    tile.processed_dedup(tile_data)
+                } else {
+                    tile.processed(Some(tile_data))
+                };
+                tx_out.send(processed).context("send processed tile")?;
+            }
+            Ok(stats)
+        }));
+    }
+    drop(tx_out);
+
+    let reader_handles = spawn_tile_readers(
+        input,
+        &input_conn,
+        schema_mode,
+        reader_count,
+        options.read_cache_mb,
+        progress.as_ref().map(PruneCheckpoint::remaining_ranges),
+        tx_in,
+    )?;
+
+    let interval = checkpoint.map_or(0, |checkpoint| checkpoint.interval.max(1));
+    let mut order = progress
+        .as_ref()
+        .map(|state| RangeOrder::new(state.ranges.len()));
+    let mut since_commit = 0u64;
+    for processed in rx_out.iter() {
+        bar.inc(1);
+        let (Some(order), Some(state), Some(checkpoint)) =
+            (order.as_mut(), progress.as_mut(), checkpoint)
+        else {
+            if let Some(output) = processed.output {
+                writer.write(output)?;
+            }
+            continue;
+        };
+        let (ready, last_rowid, released) = order.push(
+            processed.range,
+            processed.seq,
+            processed.rowid,
+            processed.output,
+        );
+        for output in ready {
+            writer.write(output)?;
+        }
+        if let Some(rowid) = last_rowid {
+            state.ranges[processed.range].next_rowid = rowid + 1;
+        }
+        state.tiles_done += released;
+        since_commit += released;
+        if since_commit >= interval {
+            writer.flush()?;
+            state.write(&checkpoint.path)?;
+            since_commit = 0;
+        }
+    }
+
+    for handle in reader_handles {
+        handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("reader thread panicked"))??;
+    }
+
+    let mut stats = PruneStats {
+        corrupt_rows,
+        ..PruneStats::default()
+    };
+    for handle in worker_handles {
+        let worker_stats = handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("worker thread panicked"))??;
+        stats.merge(worker_stats);
+    }
+    let dedup = writer.finalize(output_mode)?;
+    if options.dedup {
+        stats.dedup = Some(dedup);
+    }
+    if !options.keep_original_metadata {
+        refresh_output_extent_metadata(&output_conn, output_mode)?;
+    }
+    bar.finish_and_clear();
+    if reorder_after {
+        reorder_tiles_table(&output_conn, options.output_order)?;
+    }
+    if let (Some(state), Some(checkpoint)) = (progress.as_mut(), checkpoint) {
+        for range in state.ranges.iter_mut() {
+            range.next_rowid = range.end_rowid + 1;
+        }
+        state.complete = true;
+        state.write(&checkpoint.path)?;
+    }
+    if apply_filters && stats.unknown_filters > 0 {
+        warn!(
+            count = stats.unknown_filters,
+            "unknown filter expressions encountered"
+        );
+    }
+    Ok(stats)
+}
+
+/// Copies the whole tileset and simplifies every tile selected by `options`.
+pub fn simplify_mbtiles_batch(
+    input: &Path,
+    output: &Path,
+    options: &SimplifyOptions,
+) -> Result<SimplifyBatchStats, VtError> {
+    simplify_mbtiles_batch_impl(input, output, options).map_err(VtError::from)
+}
+
+/// Copies the whole tileset and simplifies every tile, ignoring
+/// `options.zoom` and `options.over_limit_only`; tiles are read and
+/// simplified in parallel like `simplify_mbtiles_batch`.
+pub fn simplify_mbtiles_all(
+    input: &Path,
+    output: &Path,
+    options: &SimplifyOptions,
+) -> Result<SimplifyBatchStats, VtError> {
+    let options = SimplifyOptions {
+        zoom: None,
+        over_limit_only: false,
+        ..options.clone()
+    };
+    simplify_mbtiles_batch(input, output, &options)
+}
+
+fn simplify_mbtiles_batch_impl(
+    input: &Path,
+    output: &Path,
+    options: &SimplifyOptions,
+) -> Result<SimplifyBatchStats> {
+    ensure_mbtiles_path(input)?;
+    ensure_mbtiles_path(output)?;
+    ensure_vector_tileset(&MbtilesFile::open(input)?)?;
+
+    let input_conn = Connection::open(input)
+        .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
+    apply_read_pragmas_with_cache(&input_conn, None)?;
+    let output_conn = Connection::open(output)
+        .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
+    apply_write_pragmas_with_cache(&output_conn, None)?;
+    let schema_mode = tiles_schema_mode(&input_conn)?;
+    create_output_schema(&output_conn, schema_mode)?;
+
+    let mut writer = MbtilesTileWriter::new(&output_conn, Some(u64::from(options.io_batch)));
+    copy_metadata_rows(&input_conn, &mut writer)?;
+
+    let simplify_layers: HashSet<String> = options.layers.iter().cloned().collect();
+    let worker_count = options.threads.max(1);
+    let reader_count = options.readers.max(1);
+    let queue_capacity = options.io_batch.max(1) as usize;
+
+    let (tx_in, rx_in): (Sender<TileInput>, Receiver<TileInput>) = bounded(queue_capacity);
+    let (tx_out, rx_out): (Sender<ProcessedTile>, Receiver<ProcessedTile>) =
+        bounded(queue_capacity);
+
+    let mut worker_handles = Vec::with_capacity(worker_count);
+    for _ in 0..worker_count {
+        let rx_in = rx_in.clone();
+        let tx_out = tx_out.clone();
+        let simplify_layers = simplify_layers.clone();
+        let options = options.clone();
+        worker_handles.push(thread::spawn(move || -> Result<SimplifyBatchStats> {
+            let mut stats = SimplifyBatchStats::default();
+            while let Ok(mut tile) = rx_in.recv() {
+                stats.tiles_read += 1;
+                let input_bytes = tile.data.len();
+                let tile_data = if options.matches(tile.zoom, tile.data.len() as u64) {
+                    let is_gzip = tile.data.starts_with(&[0x1f, 0x8b]);
+                    let coord = TileCoord {
                         zoom: tile.zoom,
                         x: tile.x,
                         y: tile.y,
-                        data: tile_data,
-                    }
+                    };
+                    let payload = decode_tile_payload(&tile.data)
+                        .map_err(|err| VtError::decode_tile(coord, err))?;
+                    let (simplified, tile_stats) = simplify_tile_layers(
+                        &payload,
+                        &simplify_layers,
+                        options.tolerance,
+                        options.buffer,
+                        options.preserve_topology,
+                    )
+                    .map_err(|err| VtError::decode_tile(coord, err))?;
+                    stats.record(tile.zoom, tile_stats);
+                    encode_tile_payload(&simplified, is_gzip, DEFAULT_GZIP_LEVEL)?
+                } else {
+                    std::mem::take(&mut tile.data)
                 };
-                tx_out.send(output).context("send processed tile")?;
+                stats.record_tile(tile.zoom, input_bytes, Some(tile_data.len()));
+                tx_out
+                    .send(tile.processed(Some(tile_data)))
+                    .context("send processed tile")?;
             }
             Ok(stats)
         }));
     }
     drop(tx_out);
 
-    let ranges = match schema_mode {
-        TilesSchemaMode::Tiles => rowid_ranges(&input_conn, "tiles", reader_count).ok(),
-        TilesSchemaMode::MapImages => rowid_ranges(&input_conn, "map", reader_count).ok(),
-    };
-    let rowid_available = match schema_mode {
-        TilesSchemaMode::Tiles => supports_rowid(&input_conn, "tiles")?,
-        TilesSchemaMode::MapImages => supports_rowid(&input_conn, "map")?,
+    let reader_handles = spawn_tile_readers(
+        input,
+        &input_conn,
+        schema_mode,
+        reader_count,
+        None,
+        None,
+        tx_in,
+    )?;
+
+    for processed in rx_out.iter() {
+        if let Some(output) = processed.output {
+            writer.write(output)?;
+        }
+    }
+
+    for handle in reader_handles {
+        handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("reader thread panicked"))??;
+    }
+
+    let mut stats = SimplifyBatchStats::default();
+    for handle in worker_handles {
+        let worker_stats = handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("worker thread panicked"))??;
+        stats.merge(worker_stats);
+    }
+
+    writer.finalize(schema_mode)?;
+    Ok(stats)
+}
+
+/// Copies the whole tileset and adds tiles for every zoom above its max zoom
+/// up to `options.target_zoom`, each generated from its ancestor at the max
+/// zoom (see [`overzoom_tile_payload`]). Generated tiles keep the gzip
+/// encoding of their parent.
+pub fn overzoom_mbtiles(
+    input: &Path,
+    output: &Path,
+    options: &OverzoomOptions,
+) -> Result<OverzoomStats> {
+    ensure_mbtiles_path(input)?;
+    ensure_mbtiles_path(output)?;
+    if let Some(format) = MbtilesFile::open(input)?.raster_format()? {
+        anyhow::bail!(
+            "input is a raster tileset ({}); overzoom only supports vector tiles",
+            format.as_str()
+        );
+    }
+    if options.target_zoom > MAX_OVERZOOM_TARGET_ZOOM {
+        anyhow::bail!("--target-zoom must be at most {MAX_OVERZOOM_TARGET_ZOOM}");
+    }
+
+    let input_conn = Connection::open(input)
+        .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
+    apply_read_pragmas_with_cache(&input_conn, None)?;
+    let zoom_counts = fetch_zoom_counts(&input_conn)?;
+    let Some(max_zoom) = zoom_counts.keys().next_back().copied() else {
+        anyhow::bail!("input has no tiles to overzoom");
     };
+    if options.target_zoom <= max_zoom {
+        anyhow::bail!(
+            "--target-zoom {} must be above the tileset max zoom {max_zoom}",
+            options.target_zoom
+        );
+    }
+    let output_conn = Connection::open(output)
+        .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
+    apply_write_pragmas_with_cache(&output_conn, None)?;
+    let schema_mode = tiles_schema_mode(&input_conn)?;
+    create_output_schema(&output_conn, schema_mode)?;
 
-    let reader_handles = if rowid_available {
-        let ranges = ranges.unwrap_or_default();
-        let mut handles = Vec::with_capacity(ranges.len());
-        for (start_rowid, end_rowid) in ranges {
-            let tx_in = tx_in.clone();
-            let input_path = input.to_path_buf();
-            let read_cache_mb = options.read_cache_mb;
-            handles.push(thread::spawn(move || -> Result<()> {
-                let input_conn = Connection::open(&input_path).with_context(|| {
-                    format!("failed to open input mbtiles: {}", input_path.display())
-                })?;
-                apply_read_pragmas_with_cache(&input_conn, read_cache_mb)?;
-                match schema_mode {
-                    TilesSchemaMode::Tiles => {
-                        let mut stmt = input_conn
-                            .prepare(
-                                "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles WHERE rowid BETWEEN ?1 AND ?2 ORDER BY rowid",
-                            )
-                            .context("prepare tile scan")?;
-                        let mut rows = stmt
-                            .query(params![start_rowid, end_rowid])
-                            .context("query tiles")?;
-                        while let Some(row) = rows.next().context("read tile row")? {
-                            let zoom: u8 = row.get(0)?;
-                            let x: u32 = row.get(1)?;
-                            let y: u32 = row.get(2)?;
-                            let data: Vec<u8> = row.get(3)?;
-                            if tx_in
-                                .send(TileInput {
-                                    zoom,
-                                    x,
-                                    y,
-                                    data,
-                                    map_images: false,
-                                })
-                                .is_err()
-                            {
-                                break;
-                            }
-                        }
-                    }
-                    TilesSchemaMode::MapImages => {
-                        let mut stmt = input_conn
-                            .prepare(
-                                "SELECT map.zoom_level, map.tile_column, map.tile_row, images.tile_data FROM map JOIN images ON map.tile_id = images.tile_id WHERE map.rowid BETWEEN ?1 AND ?2 ORDER BY map.rowid",
-                            )
-                            .context("prepare map/images scan")?;
-                        let mut rows = stmt
-                            .query(params![start_rowid, end_rowid])
-                            .context("query map/images")?;
-                        while let Some(row) = rows.next().context("read map/images row")? {
-                            let zoom: u8 = row.get(0)?;
-                            let x: u32 = row.get(1)?;
-                            let y: u32 = row.get(2)?;
-                            let data: Vec<u8> = row.get(3)?;
-                            if tx_in
-                                .send(TileInput {
-                                    zoom,
-                                    x,
-                                    y,
-                                    data,
-                                    map_images: true,
-                                })
-                                .is_err()
-                            {
-                                break;
-                            }
-                        }
-                    }
-                }
-                Ok(())
-            }));
-        }
-        handles
-    } else {
-        let zoom_groups = zoom_partitions(&input_conn, reader_count)?;
-        let mut handles = Vec::with_capacity(zoom_groups.len());
-        for zooms in zoom_groups {
-            let tx_in = tx_in.clone();
-            let input_path = input.to_path_buf();
-            let read_cache_mb = options.read_cache_mb;
-            handles.push(thread::spawn(move || -> Result<()> {
-                let input_conn = Connection::open(&input_path).with_context(|| {
-                    format!("failed to open input mbtiles: {}", input_path.display())
-                })?;
-                apply_read_pragmas_with_cache(&input_conn, read_cache_mb)?;
-                match schema_mode {
-                    TilesSchemaMode::Tiles => {
-                        let mut stmt = input_conn
-                            .prepare(
-                                "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles WHERE zoom_level = ?1 ORDER BY zoom_level, tile_column, tile_row",
-                            )
-                            .context("prepare tile scan by zoom")?;
-                        for zoom in zooms {
-                            let mut rows = stmt.query(params![zoom]).context("query tiles")?;
-                            while let Some(row) = rows.next().context("read tile row")? {
-                                let zoom: u8 = row.get(0)?;
-                                let x: u32 = row.get(1)?;
-                                let y: u32 = row.get(2)?;
-                                let data: Vec<u8> = row.get(3)?;
-                                if tx_in
-                                    .send(TileInput {
-                                        zoom,
-                                        x,
-                                        y,
-                                        data,
-                                        map_images: false,
-                                    })
-                                    .is_err()
-                                {
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    TilesSchemaMode::MapImages => {
-                        let mut stmt = input_conn
-                            .prepare(
-                                "SELECT map.zoom_level, map.tile_column, map.tile_row, images.tile_data FROM map JOIN images ON map.tile_id = images.tile_id WHERE map.zoom_level = ?1 ORDER BY map.zoom_level, map.tile_column, map.tile_row",
-                            )
-                            .context("prepare map/images scan by zoom")?;
-                        for zoom in zooms {
-                            let mut rows = stmt
-                                .query(params![zoom])
-                                .context("query map/images")?;
-                            while let Some(row) = rows.next().context("read map/images row")? {
-                                let zoom: u8 = row.get(0)?;
-                                let x: u32 = row.get(1)?;
-                                let y: u32 = row.get(2)?;
-                                let data: Vec<u8> = row.get(3)?;
-                                if tx_in
-                                    .send(TileInput {
-                                        zoom,
-                                        x,
-                                        y,
-                                        data,
-                                        map_images: true,
-                                    })
-                                    .is_err()
-                                {
-                                    break;
-                                }
-                            }
+    let mut writer = MbtilesTileWriter::new(&output_conn, Some(u64::from(options.io_batch)));
+    copy_metadata_rows(&input_conn, &mut writer)?;
+
+    let bar = make_eta_progress_bar(zoom_counts.values().sum(), options.no_progress);
+    let worker_count = options.threads.max(1);
+    let reader_count = options.readers.max(1);
+    let queue_capacity = options.io_batch.max(1) as usize;
+
+    let (tx_in, rx_in): (Sender<TileInput>, Receiver<TileInput>) = bounded(queue_capacity);
+    let (tx_out, rx_out): (Sender<Vec<TileOutput>>, Receiver<Vec<TileOutput>>) =
+        bounded(queue_capacity);
+
+    let mut worker_handles = Vec::with_capacity(worker_count);
+    for _ in 0..worker_count {
+        let rx_in = rx_in.clone();
+        let tx_out = tx_out.clone();
+        let options = options.clone();
+        worker_handles.push(thread::spawn(move || -> Result<OverzoomStats> {
+            let mut stats = OverzoomStats::default();
+            while let Ok(mut tile) = rx_in.recv() {
+                let mut outputs = Vec::new();
+                if tile.zoom == max_zoom {
+                    stats.parent_tiles += 1;
+                    let is_gzip = tile.data.starts_with(&[0x1f, 0x8b]);
+                    let payload = decode_tile_payload(&tile.data)?;
+                    let parent_y = flip_y(tile.zoom, tile.y);
+                    for zoom_delta in 1..=options.target_zoom - max_zoom {
+                        let zoom = tile.zoom + zoom_delta;
+                        let children = overzoom_tile_payload(&payload, zoom_delta, options.buffer)?;
+                        let scale = 1u32 << zoom_delta;
+                        stats.empty_children += u64::from(scale * scale) - children.len() as u64;
+                        *stats.generated_by_zoom.entry(zoom).or_default() += children.len() as u64;
+                        for child in children {
+                            outputs.push(TileOutput::new(
+                                schema_mode,
+                                zoom,
+                                tile.x * scale + child.dx,
+                                flip_y(zoom, parent_y * scale + child.dy),
+                                encode_tile_payload(&child.bytes, is_gzip, DEFAULT_GZIP_LEVEL)?,
+                            ));
                         }
                     }
                 }
-                Ok(())
-            }));
-        }
-        handles
-    };
-    drop(tx_in);
-
-    let mut stats = PruneStats::default();
-    for output in rx_out.iter() {
-        match output {
-            TileOutput::Tiles { zoom, x, y, data } => {
-                tx.execute(
-                    "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
-                    (zoom as i64, x as i64, y as i64, data),
-                )
-                .context("insert tile")?;
-            }
-            TileOutput::MapImages {
-                zoom,
-                x,
-                y,
-                tile_id,
-                data,
-            } => {
-                tx.execute(
-                    "INSERT INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (?1, ?2, ?3, ?4)",
-                    (zoom as i64, x as i64, y as i64, tile_id.clone()),
-                )
-                .context("insert map row")?;
-                tx.execute(
-                    "INSERT INTO images (tile_id, tile_data) VALUES (?1, ?2)",
-                    (tile_id, data),
-                )
-                .context("insert image row")?;
+                let data = std::mem::take(&mut tile.data);
+                outputs.push(TileOutput::new(
+                    schema_mode,
+                    tile.zoom,
+                    tile.x,
+                    tile.y,
+                    data,
+                ));
+                tx_out.send(outputs).context("send processed tile")?;
             }
+            Ok(stats)
+        }));
+    }
+    drop(tx_out);
+
+    let reader_handles = spawn_tile_readers(
+        input,
+        &input_conn,
+        schema_mode,
+        reader_count,
+        None,
+        None,
+        tx_in,
+    )?;
+
+    for outputs in rx_out.iter() {
+        bar.inc(1);
+        for output in outputs {
+            writer.write(output)?;
         }
     }
 
@@ -2170,6 +4024,7 @@ pub fn prune_mbtiles_layer_only(
             .map_err(|_| anyhow::anyhow!("reader thread panicked"))??;
     }
 
+    let mut stats = OverzoomStats::default();
     for handle in worker_handles {
         let worker_stats = handle
             .join()
@@ -2177,13 +4032,9 @@ pub fn prune_mbtiles_layer_only(
         stats.merge(worker_stats);
     }
 
-    tx.commit().context("commit output")?;
-    if apply_filters && stats.unknown_filters > 0 {
-        warn!(
-            count = stats.unknown_filters,
-            "unknown filter expressions encountered"
-        );
-    }
+    writer.finalize(schema_mode)?;
+    refresh_output_extent_metadata(&output_conn, schema_mode)?;
+    bar.finish_and_clear();
     Ok(stats)
 }
 
@@ -2211,6 +4062,180 @@ mod tests {
     }
 }
 
+/// Spawns reader threads feeding `tx_in`. Explicit `ranges` (rowid bounds,
+/// one reader each) are required for checkpointed runs; otherwise ranges are
+/// derived from the table, falling back to zoom partitions without rowids.
+fn spawn_tile_readers(
+    input: &Path,
+    input_conn: &Connection,
+    schema_mode: TilesSchemaMode,
+    reader_count: usize,
+    read_cache_mb: Option<u64>,
+    ranges: Option<Vec<(i64, i64)>>,
+    tx_in: Sender<TileInput>,
+) -> Result<Vec<thread::JoinHandle<Result<()>>>> {
+    let table = schema_mode.coord_table();
+    let (source, data_expr) = (schema_mode.source(), schema_mode.data_expr());
+    let (zoom_col, x_col, y_col) = schema_mode.coord_columns();
+    let ranges = match ranges {
+        Some(ranges) => Some(ranges),
+        None => {
+            if supports_rowid(input_conn, table)? {
+                Some(rowid_ranges(input_conn, table, reader_count).unwrap_or_default())
+            } else {
+                None
+            }
+        }
+    };
+
+    let handles = if let Some(ranges) = ranges {
+        let query = format!(
+            "SELECT {zoom_col}, {x_col}, {y_col}, {data_expr}, {table}.rowid FROM {source} \
+WHERE {table}.rowid BETWEEN ?1 AND ?2 ORDER BY {table}.rowid"
+        );
+        let mut handles = Vec::with_capacity(ranges.len());
+        for (range, (start_rowid, end_rowid)) in ranges.into_iter().enumerate() {
+            let tx_in = tx_in.clone();
+            let input_path = input.to_path_buf();
+            let query = query.clone();
+            handles.push(thread::spawn(move || -> Result<()> {
+                let input_conn = Connection::open(&input_path).with_context(|| {
+                    format!("failed to open input mbtiles: {}", input_path.display())
+                })?;
+                apply_read_pragmas_with_cache(&input_conn, read_cache_mb)?;
+                let mut stmt = input_conn.prepare(&query).context("prepare tile scan")?;
+                let mut rows = stmt
+                    .query(params![start_rowid, end_rowid])
+                    .context("query tiles")?;
+                let mut seq = 0u64;
+                while let Some(row) = rows.next().context("read tile row")? {
+                    let Some(coord) = tile_address(row, 0)? else {
+                        continue;
+                    };
+                    let tile = TileInput {
+                        zoom: coord.zoom,
+                        x: coord.x,
+                        y: coord.y,
+                        data: row.get(3)?,
+                        schema_mode,
+                        range,
+                        seq,
+                        rowid: row.get(4)?,
+                    };
+                    seq += 1;
+                    if tx_in.send(tile).is_err() {
+                        break;
+                    }
+                }
+                Ok(())
+            }));
+        }
+        handles
+    } else {
+        let query = format!(
+            "SELECT {zoom_col}, {x_col}, {y_col}, {data_expr} FROM {source} \
+WHERE {zoom_col} = ?1 ORDER BY {zoom_col}, {x_col}, {y_col}"
+        );
+        let zoom_groups = zoom_partitions(input_conn, reader_count)?;
+        let mut handles = Vec::with_capacity(zoom_groups.len());
+        for (range, zooms) in zoom_groups.into_iter().enumerate() {
+            let tx_in = tx_in.clone();
+            let input_path = input.to_path_buf();
+            let query = query.clone();
+            handles.push(thread::spawn(move || -> Result<()> {
+                let input_conn = Connection::open(&input_path).with_context(|| {
+                    format!("failed to open input mbtiles: {}", input_path.display())
+                })?;
+                apply_read_pragmas_with_cache(&input_conn, read_cache_mb)?;
+                let mut stmt = input_conn
+                    .prepare(&query)
+                    .context("prepare tile scan by zoom")?;
+                let mut seq = 0u64;
+                for zoom in zooms {
+                    let mut rows = stmt.query(params![zoom]).context("query tiles")?;
+                    while let Some(row) = rows.next().context("read tile row")? {
+                        let Some(coord) = tile_address(row, 0)? else {
+                            continue;
+                        };
+                        let tile = TileInput {
+                            zoom: coord.zoom,
+                            x: coord.x,
+                            y: coord.y,
+                            data: row.get(3)?,
+                            schema_mode,
+                            range,
+                            seq,
+                            rowid: 0,
+                        };
+                        seq += 1;
+                        if tx_in.send(tile).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Ok(())
+            }));
+        }
+        handles
+    };
+    Ok(handles)
+}
+
+/// Rewrite the zoom, bounds and center metadata of an output to match the
+/// tiles it holds (see [`refresh_extent_metadata`]).
+fn refresh_output_extent_metadata(conn: &Connection, mode: TilesSchemaMode) -> Result<()> {
+    let mut extents = TileExtents::default();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT zoom_level, MIN(tile_column), MAX(tile_column), MIN(tile_row), MAX(tile_row) FROM {} GROUP BY zoom_level",
+            mode.coord_table()
+        ))
+        .context("prepare tile extents")?;
+    let mut rows = stmt.query([]).context("query tile extents")?;
+    while let Some(row) = rows.next().context("read tile extent row")? {
+        let zoom: u8 = row.get(0)?;
+        let (x_min, x_max, row_min, row_max): (u32, u32, u32, u32) =
+            (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?);
+        // Rows are TMS, so the lowest row is the southernmost tile
+        extents.add_range(
+            zoom,
+            x_min,
+            x_max,
+            flip_y(zoom, row_max),
+            flip_y(zoom, row_min),
+        );
+    }
+    let before = read_metadata(conn)?;
+    let mut after = before.clone();
+    refresh_extent_metadata(&mut after, &extents);
+    for (name, value) in after {
+        if before.get(&name) == Some(&value) {
+            continue;
+        }
+        conn.execute("DELETE FROM metadata WHERE name = ?1", (&name,))
+            .context("delete metadata")?;
+        conn.execute(
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+            (&name, &value),
+        )
+        .context("insert metadata")?;
+    }
+    Ok(())
+}
+
+fn copy_metadata_rows(input_conn: &Connection, writer: &mut MbtilesTileWriter<'_>) -> Result<()> {
+    let mut meta_stmt = input_conn
+        .prepare("SELECT name, value FROM metadata")
+        .context("prepare metadata read")?;
+    let mut meta_rows = meta_stmt.query([]).context("query metadata")?;
+    while let Some(row) = meta_rows.next().context("read metadata row")? {
+        let name: String = row.get(0)?;
+        let value: String = row.get(1)?;
+        writer.write_metadata(&name, &value)?;
+    }
+    Ok(())
+}
+
 fn rowid_ranges(conn: &Connection, table: &str, readers: usize) -> Result<Vec<(i64, i64)>> {
     let query = format!("SELECT MIN(rowid), MAX(rowid) FROM {table}",);
     let (min_rowid, max_rowid): (Option<i64>, Option<i64>) =
@@ -2262,7 +4287,49 @@ struct TileInput {
     x: u32,
     y: u32,
     data: Vec<u8>,
-    map_images: bool,
+    schema_mode: TilesSchemaMode,
+    /// Reader index and position within that reader's scan order.
+    range: usize,
+    seq: u64,
+    rowid: i64,
+}
+
+impl TileInput {
+    fn processed(&self, data: Option<Vec<u8>>) -> ProcessedTile {
+        let output =
+            data.map(|data| TileOutput::new(self.schema_mode, self.zoom, self.x, self.y, data));
+        ProcessedTile {
+            range: self.range,
+            seq: self.seq,
+            rowid: self.rowid,
+            output,
+        }
+    }
+
+    /// Map/images output keyed by the xxh3-128 digest of the tile bytes.
+    fn processed_dedup(&self, data: Vec<u8>) -> ProcessedTile {
+        ProcessedTile {
+            range: self.range,
+            seq: self.seq,
+            rowid: self.rowid,
+            output: Some(TileOutput::MapImages {
+                zoom: self.zoom,
+                x: self.x,
+                y: self.y,
+                tile_id: format!("{:032x}", xxhash_rust::xxh3::xxh3_128(&data)),
+                data,
+            }),
+        }
+    }
+}
+
+/// Worker result for one input tile; `output` is `None` when the tile is dropped.
+#[derive(Debug)]
+struct ProcessedTile {
+    range: usize,
+    seq: u64,
+    rowid: i64,
+    output: Option<TileOutput>,
 }
 
 #[derive(Debug)]
@@ -2280,6 +4347,38 @@ enum TileOutput {
         tile_id: String,
         data: Vec<u8>,
     },
+    /// `tiles_shallow` row; `hash` (xxh3-128 of `data`) finds an existing
+    /// `tiles_data` row to reuse.
+    Shallow {
+        zoom: u8,
+        x: u32,
+        y: u32,
+        hash: u128,
+        data: Vec<u8>,
+    },
+}
+
+impl TileOutput {
+    /// Row for `mode`; `y` is the TMS row.
+    fn new(mode: TilesSchemaMode, zoom: u8, x: u32, y: u32, data: Vec<u8>) -> Self {
+        match mode {
+            TilesSchemaMode::Tiles => TileOutput::Tiles { zoom, x, y, data },
+            TilesSchemaMode::MapImages => TileOutput::MapImages {
+                zoom,
+                x,
+                y,
+                tile_id: format!("{zoom}-{x}-{y}"),
+                data,
+            },
+            TilesSchemaMode::Shallow => TileOutput::Shallow {
+                zoom,
+                x,
+                y,
+                hash: xxhash_rust::xxh3::xxh3_128(&data),
+                data,
+            },
+        }
+    }
 }
 
 pub fn simplify_mbtiles_tile(
@@ -2288,9 +4387,33 @@ pub fn simplify_mbtiles_tile(
     coord: TileCoord,
     layers: &[String],
     tolerance: Option<f64>,
+    buffer: u32,
+    preserve_topology: bool,
+) -> Result<SimplifyStats, VtError> {
+    simplify_mbtiles_tile_impl(
+        input,
+        output,
+        coord,
+        layers,
+        tolerance,
+        buffer,
+        preserve_topology,
+    )
+    .map_err(VtError::from)
+}
+
+fn simplify_mbtiles_tile_impl(
+    input: &Path,
+    output: &Path,
+    coord: TileCoord,
+    layers: &[String],
+    tolerance: Option<f64>,
+    buffer: u32,
+    preserve_topology: bool,
 ) -> Result<SimplifyStats> {
     ensure_mbtiles_path(input)?;
     ensure_mbtiles_path(output)?;
+    ensure_vector_tileset(&MbtilesFile::open(input)?)?;
 
     let input_conn = Connection::open(input)
         .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
@@ -2316,19 +4439,16 @@ pub fn simplify_mbtiles_tile(
     }
 
     let Some(data) = fetch_tile_data(&input_conn, coord)? else {
-        anyhow::bail!(
-            "tile not found: z={} x={} y={}",
-            coord.zoom,
-            coord.x,
-            coord.y
-        );
+        return Err(VtError::TileNotFound(coord).into());
     };
     let is_gzip = data.starts_with(&[0x1f, 0x8b]);
-    let payload = decode_tile_payload(&data)?;
+    let payload = decode_tile_payload(&data).map_err(|err| VtError::decode_tile(coord, err))?;
 
     let keep_layers: HashSet<String> = layers.iter().cloned().collect();
-    let (filtered, stats) = simplify_tile_payload(&payload, &keep_layers, tolerance)?;
-    let encoded = encode_tile_payload(&filtered, is_gzip)?;
+    let (filtered, stats) =
+        simplify_tile_payload(&payload, &keep_layers, tolerance, buffer, preserve_topology)
+            .map_err(|err| VtError::decode_tile(coord, err))?;
+    let encoded = encode_tile_payload(&filtered, is_gzip, DEFAULT_GZIP_LEVEL)?;
 
     match schema_mode {
         TilesSchemaMode::Tiles => {
@@ -2354,6 +4474,22 @@ pub fn simplify_mbtiles_tile(
                 )
                 .context("insert image")?;
         }
+        TilesSchemaMode::Shallow => {
+            output_conn
+                .execute("INSERT INTO tiles_data (tile_data) VALUES (?1)", (encoded,))
+                .context("insert tiles_data")?;
+            output_conn
+                .execute(
+                    "INSERT INTO tiles_shallow (zoom_level, tile_column, tile_row, tile_data_id) VALUES (?1, ?2, ?3, ?4)",
+                    (
+                        coord.zoom as i64,
+                        coord.x as i64,
+                        coord.y as i64,
+                        output_conn.last_insert_rowid(),
+                    ),
+                )
+                .context("insert tiles_shallow")?;
+        }
     }
 
     Ok(stats)
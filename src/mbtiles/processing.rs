@@ -2,14 +2,22 @@ use anyhow::{Context, Result};
 use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
+use geo_types::{Coord, Geometry};
 use mvt::Tile;
 use mvt_reader::Reader;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Write};
 
-use crate::mbtiles::algo::{count_vertices, encode_geometry, simplify_geometry};
+use crate::error::VtError;
+use crate::mbtiles::algo::{
+    PointMerger, SIMPLIFY_REFERENCE_EXTENT, count_vertices, encode_geometry, fix_polygon_winding,
+    overzoom_geometry, polygon_area, simplify_geometry,
+};
 use crate::mbtiles::stats::{PruneStats, SimplifyStats};
-use crate::mbtiles::types::PrunedTile;
+use crate::mbtiles::types::{
+    DEFAULT_BROTLI_WINDOW, FeatureCleanup, FeatureIdMode, OverzoomedTile, PrunedTile,
+    TileCompression,
+};
 
 pub fn decode_tile_payload(data: &[u8]) -> Result<Vec<u8>> {
     if data.starts_with(&[0x1f, 0x8b]) {
@@ -24,24 +32,87 @@ pub fn decode_tile_payload(data: &[u8]) -> Result<Vec<u8>> {
     }
 }
 
-pub fn encode_tile_payload(data: &[u8], gzip: bool) -> Result<Vec<u8>> {
+/// Gzip level used when none is requested (same as `Compression::default()`).
+pub const DEFAULT_GZIP_LEVEL: u32 = 6;
+
+/// Gzip-compresses `data` at `level` (0-9) when `gzip` is set.
+pub fn encode_tile_payload(data: &[u8], gzip: bool, level: u32) -> Result<Vec<u8>> {
     if !gzip {
         return Ok(data.to_vec());
     }
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(data).context("encode gzip tile data")?;
-    let encoded = encoder.finish().context("finish gzip tile data")?;
-    Ok(encoded)
+    compress_tile_payload(data, TileCompression::Gzip(level))
+}
+
+/// Compresses a decoded tile with an explicit codec and level.
+pub fn compress_tile_payload(data: &[u8], compression: TileCompression) -> Result<Vec<u8>> {
+    compress_tile_payload_with_window(data, compression, DEFAULT_BROTLI_WINDOW)
+}
+
+/// Like [`compress_tile_payload`], with the brotli window bits (10-24).
+pub fn compress_tile_payload_with_window(
+    data: &[u8],
+    compression: TileCompression,
+    brotli_window: u32,
+) -> Result<Vec<u8>> {
+    match compression {
+        TileCompression::None => Ok(data.to_vec()),
+        TileCompression::Gzip(level) => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+            encoder.write_all(data).context("encode gzip tile data")?;
+            encoder.finish().context("finish gzip tile data")
+        }
+        TileCompression::Brotli(quality) => {
+            let mut compressed = Vec::new();
+            {
+                let mut writer =
+                    brotli::CompressorWriter::new(&mut compressed, 4096, quality, brotli_window);
+                writer.write_all(data).context("encode brotli tile data")?;
+            }
+            Ok(compressed)
+        }
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn prune_tile_layers(
     payload: &[u8],
     zoom: u8,
-    style: &crate::style::MapboxStyle,
-    keep_layers: &HashSet<String>,
+    selection: &crate::style::LayerSelection,
     apply_filters: bool,
     keep_unknown_filters: bool,
+    fix_winding: bool,
+    cleanup: &FeatureCleanup,
     stats: &mut PruneStats,
+    keep_keys: Option<&HashMap<String, HashSet<String>>>,
+    layer_renames: &HashMap<String, String>,
+) -> Result<PrunedTile, VtError> {
+    prune_tile_layers_impl(
+        payload,
+        zoom,
+        selection,
+        apply_filters,
+        keep_unknown_filters,
+        fix_winding,
+        cleanup,
+        stats,
+        keep_keys,
+        layer_renames,
+    )
+    .map_err(VtError::from)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prune_tile_layers_impl(
+    payload: &[u8],
+    zoom: u8,
+    selection: &crate::style::LayerSelection,
+    apply_filters: bool,
+    keep_unknown_filters: bool,
+    fix_winding: bool,
+    cleanup: &FeatureCleanup,
+    stats: &mut PruneStats,
+    keep_keys: Option<&HashMap<String, HashSet<String>>>,
+    layer_renames: &HashMap<String, String>,
 ) -> Result<PrunedTile> {
     let reader = Reader::new(payload.to_vec())
         .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
@@ -49,34 +120,72 @@ pub fn prune_tile_layers(
         .get_layer_metadata()
         .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
 
-    let mut extent = 4096;
-    for layer in layers.iter() {
-        if keep_layers.contains(&layer.name) && style.is_layer_visible_on_zoom(&layer.name, zoom) {
-            extent = layer.extent;
-            break;
-        }
-    }
-
-    let mut tile = Tile::new(extent);
-    let mut kept_layers = 0u32;
+    // Output layers in first-seen order; source layers renamed to the same
+    // name share one entry.
+    let mut outputs: Vec<OutputLayer> = Vec::new();
+    let mut unchanged = cleanup.feature_ids == FeatureIdMode::Keep;
     for layer in layers {
-        if !keep_layers.contains(&layer.name) {
+        if !selection.keeps_layer(&layer.name, zoom) {
             stats.record_removed_layer(&layer.name, zoom);
             stats.record_removed_features(zoom, layer.feature_count as u64);
+            unchanged = false;
             continue;
         }
-        if !style.is_layer_visible_on_zoom(&layer.name, zoom) {
-            stats.record_removed_layer(&layer.name, zoom);
-            stats.record_removed_features(zoom, layer.feature_count as u64);
-            continue;
-        }
-        let mut layer_builder = tile.create_layer(&layer.name);
+        // Style lookups use the source name; stats use the output name.
+        let output_name = layer_renames
+            .get(&layer.name)
+            .map_or(layer.name.as_str(), String::as_str);
+        unchanged &= output_name == layer.name;
+        let output_index = match outputs.iter().position(|out| out.name == output_name) {
+            Some(index) => {
+                if outputs[index].extent != layer.extent {
+                    anyhow::bail!(
+                        "cannot merge layer {} into {output_name}: extent {} differs from {}",
+                        layer.name,
+                        layer.extent,
+                        outputs[index].extent
+                    );
+                }
+                unchanged = false;
+                index
+            }
+            None => {
+                outputs.push(OutputLayer {
+                    name: output_name.to_string(),
+                    extent: layer.extent,
+                    builder: Some(Tile::new(layer.extent).create_layer(output_name)),
+                    features: 0,
+                });
+                outputs.len() - 1
+            }
+        };
+        let mut layer_builder = outputs[output_index]
+            .builder
+            .take()
+            .context("output layer builder")?;
         let features = reader
             .get_features(layer.layer_index)
             .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
+        let clean = cleanup.is_enabled()
+            && !cleanup.exclude_layers.contains(&layer.name)
+            && !selection.is_layer_interactive(&layer.name);
+        // Pixels of a 256px tile to tile units.
+        let pixel = layer.extent as f64 / 256.0;
+        let min_area = cleanup
+            .drop_tiny_features
+            .filter(|_| clean)
+            .map(|area| area * pixel * pixel);
+        let mut point_merger = cleanup
+            .merge_nearby_points
+            .filter(|distance| clean && *distance > 0.0)
+            .map(|distance| PointMerger::new((distance * pixel) as f32));
+        let allowed_keys = keep_keys.and_then(|keep_keys| keep_keys.get(&layer.name));
         let mut kept_features = 0u64;
+        // Merged source layers continue the numbering of the output layer.
+        let reindex_base = outputs[output_index].features;
+        let filter_style = selection.style().filter(|_| apply_filters);
         for feature in features {
-            if apply_filters {
+            if let Some(style) = filter_style {
                 match style.should_keep_feature(
                     &layer.name,
                     zoom,
@@ -85,7 +194,7 @@ pub fn prune_tile_layers(
                 ) {
                     crate::style::FilterResult::True => {}
                     crate::style::FilterResult::Unknown => {
-                        stats.record_unknown_layer(&layer.name);
+                        stats.record_unknown_layer(output_name);
                         if !keep_unknown_filters {
                             continue;
                         }
@@ -95,13 +204,53 @@ pub fn prune_tile_layers(
                     }
                 }
             }
-            let geom_data = encode_geometry(feature.get_geometry())?;
+            if let Some(min_area) = min_area
+                && polygon_area(feature.get_geometry()).is_some_and(|area| area < min_area)
+            {
+                stats.record_tiny_feature(output_name, zoom);
+                continue;
+            }
+            if let (Some(merger), Some(point)) =
+                (point_merger.as_mut(), single_point(feature.get_geometry()))
+                && !merger.keep(point)
+            {
+                stats.record_merged_point(output_name, zoom);
+                continue;
+            }
+            let fixed = if fix_winding {
+                fix_polygon_winding(feature.get_geometry())
+            } else {
+                None
+            };
+            let geom_data = match fixed {
+                Some((geometry, corrected)) => {
+                    stats.corrected_rings += corrected;
+                    unchanged &= corrected == 0;
+                    encode_geometry(&geometry)?
+                }
+                None => encode_geometry(feature.get_geometry())?,
+            };
             let mut feature_builder = layer_builder.into_feature(geom_data);
-            if let Some(id) = feature.id {
-                feature_builder.set_id(id);
+            match cleanup.feature_ids {
+                FeatureIdMode::Keep => {
+                    if let Some(id) = feature.id {
+                        feature_builder.set_id(id);
+                    }
+                }
+                FeatureIdMode::Drop => {}
+                FeatureIdMode::Reindex => feature_builder.set_id(reindex_base + kept_features + 1),
             }
             if let Some(props) = feature.properties {
+                // Tags go out in key order so the re-encoded tile does not
+                // depend on `HashMap` iteration order.
+                let mut props: Vec<_> = props.into_iter().collect();
+                props.sort_unstable_by(|a, b| a.0.cmp(&b.0));
                 for (key, value) in props {
+                    if allowed_keys.is_some_and(|allowed| !allowed.contains(&key)) {
+                        stats.record_removed_key(output_name, &key);
+                        unchanged = false;
+                        continue;
+                    }
                     match value {
                         mvt_reader::feature::Value::String(text) => {
                             feature_builder.add_tag_string(&key, &text);
@@ -133,28 +282,143 @@ pub fn prune_tile_layers(
         }
         let removed_features = (layer.feature_count as u64).saturating_sub(kept_features);
         stats.record_removed_features(zoom, removed_features);
-        if kept_features == 0 {
-            stats.record_removed_layer(&layer.name, zoom);
+        unchanged &= removed_features == 0;
+        let output = &mut outputs[output_index];
+        output.builder = Some(layer_builder);
+        output.features += kept_features;
+    }
+
+    let mut tile = LayerEncoder::default();
+    let mut kept_layers = 0u32;
+    for output in outputs {
+        if output.features == 0 {
+            stats.record_removed_layer(&output.name, zoom);
+            unchanged = false;
             continue;
         }
-        tile.add_layer(layer_builder)
-            .map_err(|err| anyhow::anyhow!("add layer: {err}"))?;
+        let builder = output.builder.context("output layer builder")?;
+        tile.add_layer(builder, output.extent)?;
         kept_layers += 1;
     }
 
-    let bytes = tile
-        .to_bytes()
-        .map_err(|err| anyhow::anyhow!("encode vector tile: {err}"))?;
     Ok(PrunedTile {
-        bytes,
+        bytes: tile.bytes,
         empty: kept_layers == 0,
+        unchanged,
     })
 }
 
+/// One layer of a pruned tile, possibly fed by several source layers.
+struct OutputLayer {
+    name: String,
+    extent: u32,
+    /// Taken while a source layer appends its features.
+    builder: Option<mvt::Layer>,
+    features: u64,
+}
+
+/// Encodes layers that each keep their own extent. `mvt::Tile` has a single
+/// extent, so every layer is written as a one-layer tile; the concatenated
+/// messages decode as one tile because `layers` is a repeated field.
+#[derive(Default)]
+pub(crate) struct LayerEncoder {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl LayerEncoder {
+    pub(crate) fn add_layer(&mut self, layer: mvt::Layer, extent: u32) -> Result<()> {
+        let mut tile = Tile::new(extent);
+        tile.add_layer(layer)
+            .map_err(|err| anyhow::anyhow!("add layer: {err}"))?;
+        tile.write_to(&mut self.bytes)
+            .map_err(|err| anyhow::anyhow!("encode vector tile: {err}"))
+    }
+}
+
+/// Encoded size of a layer split into `(geometry_bytes, attribute_bytes)`.
+///
+/// The layer is re-encoded twice: without feature properties (geometry,
+/// ids, and layer framing) and with them; the growth is the attribute
+/// overhead (keys, values, and tags).
+pub fn layer_attribute_sizes(
+    name: &str,
+    extent: u32,
+    features: &[mvt_reader::feature::Feature],
+) -> Result<(u64, u64)> {
+    let mut sizes = [0u64; 2];
+    for (with_properties, size) in [false, true].into_iter().zip(sizes.iter_mut()) {
+        let mut layer_builder = Tile::new(extent).create_layer(name);
+        for feature in features {
+            let mut feature_builder =
+                layer_builder.into_feature(encode_geometry(&feature.geometry)?);
+            if let Some(id) = feature.id {
+                feature_builder.set_id(id);
+            }
+            if with_properties && let Some(props) = feature.properties.as_ref() {
+                add_feature_properties(&mut feature_builder, props);
+            }
+            layer_builder = feature_builder.into_layer();
+        }
+        let mut tile = LayerEncoder::default();
+        tile.add_layer(layer_builder, extent)?;
+        *size = tile.bytes.len() as u64;
+    }
+    Ok((sizes[0], sizes[1].saturating_sub(sizes[0])))
+}
+
+/// Point features decode as a one-point `MultiPoint`.
+fn single_point(geometry: &Geometry<f32>) -> Option<Coord<f32>> {
+    match geometry {
+        Geometry::Point(point) => Some(point.0),
+        Geometry::MultiPoint(points) if points.0.len() == 1 => Some(points.0[0].0),
+        _ => None,
+    }
+}
+
 pub fn simplify_tile_payload(
     payload: &[u8],
     keep_layers: &HashSet<String>,
     tolerance: Option<f64>,
+    buffer: u32,
+    preserve_topology: bool,
+) -> Result<(Vec<u8>, SimplifyStats), VtError> {
+    rewrite_simplified_tile(
+        payload,
+        keep_layers,
+        false,
+        tolerance,
+        buffer,
+        preserve_topology,
+    )
+    .map_err(VtError::from)
+}
+
+/// Simplifies the selected layers (all when empty) and keeps every other layer as-is.
+pub fn simplify_tile_layers(
+    payload: &[u8],
+    simplify_layers: &HashSet<String>,
+    tolerance: Option<f64>,
+    buffer: u32,
+    preserve_topology: bool,
+) -> Result<(Vec<u8>, SimplifyStats), VtError> {
+    rewrite_simplified_tile(
+        payload,
+        simplify_layers,
+        true,
+        tolerance,
+        buffer,
+        preserve_topology,
+    )
+    .map_err(VtError::from)
+}
+
+fn rewrite_simplified_tile(
+    payload: &[u8],
+    keep_layers: &HashSet<String>,
+    keep_other_layers: bool,
+    tolerance: Option<f64>,
+    buffer: u32,
+    preserve_topology: bool,
 ) -> Result<(Vec<u8>, SimplifyStats)> {
     let reader = Reader::new(payload.to_vec())
         .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
@@ -162,77 +426,161 @@ pub fn simplify_tile_payload(
         .get_layer_metadata()
         .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
 
-    let mut extent = 4096;
-    for layer in layers.iter() {
-        if keep_layers.is_empty() || keep_layers.contains(&layer.name) {
-            extent = layer.extent;
-            break;
-        }
-    }
-
-    let mut tile = Tile::new(extent);
-    let mut stats = SimplifyStats {
-        feature_count: 0,
-        vertices_before: 0,
-        vertices_after: 0,
-    };
+    let mut tile = LayerEncoder::default();
+    let mut stats = SimplifyStats::default();
     for layer in layers {
-        if !keep_layers.is_empty() && !keep_layers.contains(&layer.name) {
+        let selected = keep_layers.is_empty() || keep_layers.contains(&layer.name);
+        if !selected && !keep_other_layers {
             continue;
         }
-        let mut layer_builder = tile.create_layer(&layer.name);
+        let mut layer_builder = Tile::new(layer.extent).create_layer(&layer.name);
         let features = reader
             .get_features(layer.layer_index)
             .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
         for feature in features {
             let geometry = feature.get_geometry();
-            stats.feature_count += 1;
-            stats.vertices_before += count_vertices(geometry) as u64;
-            let geometry = match tolerance {
-                Some(value) if value > 0.0 => simplify_geometry(geometry, value as f32),
-                _ => geometry.clone(),
+            let geometry = if selected {
+                stats.feature_count += 1;
+                stats.vertices_before += count_vertices(geometry) as u64;
+                let simplified = match tolerance {
+                    Some(value) if value > 0.0 => simplify_geometry(
+                        geometry,
+                        value as f32,
+                        layer.extent,
+                        buffer,
+                        preserve_topology,
+                        &mut stats,
+                    ),
+                    _ => Some(geometry.clone()),
+                };
+                let Some(geometry) = simplified else {
+                    continue;
+                };
+                stats.vertices_after += count_vertices(&geometry) as u64;
+                geometry
+            } else {
+                geometry.clone()
             };
-            stats.vertices_after += count_vertices(&geometry) as u64;
             let geom_data = encode_geometry(&geometry)?;
             let mut feature_builder = layer_builder.into_feature(geom_data);
             if let Some(id) = feature.id {
                 feature_builder.set_id(id);
             }
-            if let Some(props) = feature.properties {
-                for (key, value) in props {
-                    match value {
-                        mvt_reader::feature::Value::String(text) => {
-                            feature_builder.add_tag_string(&key, &text);
-                        }
-                        mvt_reader::feature::Value::Float(val) => {
-                            feature_builder.add_tag_float(&key, val);
-                        }
-                        mvt_reader::feature::Value::Double(val) => {
-                            feature_builder.add_tag_double(&key, val);
-                        }
-                        mvt_reader::feature::Value::Int(val) => {
-                            feature_builder.add_tag_int(&key, val);
-                        }
-                        mvt_reader::feature::Value::UInt(val) => {
-                            feature_builder.add_tag_uint(&key, val);
-                        }
-                        mvt_reader::feature::Value::SInt(val) => {
-                            feature_builder.add_tag_sint(&key, val);
-                        }
-                        mvt_reader::feature::Value::Bool(val) => {
-                            feature_builder.add_tag_bool(&key, val);
-                        }
-                        mvt_reader::feature::Value::Null => {}
+            if let Some(props) = feature.properties.as_ref() {
+                add_feature_properties(&mut feature_builder, props);
+            }
+            layer_builder = feature_builder.into_layer();
+        }
+        tile.add_layer(layer_builder, layer.extent)?;
+    }
+
+    Ok((tile.bytes, stats))
+}
+
+/// Generates the `4^zoom_delta` tiles `zoom_delta` levels below the tile in
+/// `payload`. Every layer keeps its extent; geometries are scaled up and
+/// clipped to the child tile plus `buffer` (units at extent 4096, scaled to
+/// the layer extent). Features that clip to nothing are dropped, and
+/// children without any feature left are not returned.
+pub fn overzoom_tile_payload(
+    payload: &[u8],
+    zoom_delta: u8,
+    buffer: u32,
+) -> Result<Vec<OverzoomedTile>> {
+    let reader = Reader::new(payload.to_vec())
+        .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+    let layers = reader
+        .get_layer_metadata()
+        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+    let mut decoded = Vec::with_capacity(layers.len());
+    for layer in layers {
+        let features = reader
+            .get_features(layer.layer_index)
+            .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
+        decoded.push((layer, features));
+    }
+
+    let scale = 1u32 << zoom_delta;
+    let mut children = Vec::new();
+    for dy in 0..scale {
+        for dx in 0..scale {
+            let mut tile = LayerEncoder::default();
+            for (layer, features) in decoded.iter() {
+                let extent = layer.extent as f32;
+                let buffer = (buffer as f32 * extent / SIMPLIFY_REFERENCE_EXTENT as f32).round();
+                let offset = Coord {
+                    x: dx as f32 * extent,
+                    y: dy as f32 * extent,
+                };
+                let mut layer_builder = Tile::new(layer.extent).create_layer(&layer.name);
+                let mut kept_features = 0u64;
+                for feature in features {
+                    let Some(geometry) = overzoom_geometry(
+                        feature.get_geometry(),
+                        scale as f32,
+                        offset,
+                        -buffer,
+                        extent + buffer,
+                    ) else {
+                        continue;
+                    };
+                    let mut feature_builder =
+                        layer_builder.into_feature(encode_geometry(&geometry)?);
+                    if let Some(id) = feature.id {
+                        feature_builder.set_id(id);
+                    }
+                    if let Some(props) = feature.properties.as_ref() {
+                        add_feature_properties(&mut feature_builder, props);
                     }
+                    layer_builder = feature_builder.into_layer();
+                    kept_features += 1;
+                }
+                if kept_features > 0 {
+                    tile.add_layer(layer_builder, layer.extent)?;
                 }
             }
-            layer_builder = feature_builder.into_layer();
+            if !tile.bytes.is_empty() {
+                children.push(OverzoomedTile {
+                    dx,
+                    dy,
+                    bytes: tile.bytes,
+                });
+            }
         }
-        tile.add_layer(layer_builder)
-            .map_err(|err| anyhow::anyhow!("add layer: {err}"))?;
     }
+    Ok(children)
+}
 
-    tile.to_bytes()
-        .map_err(|err| anyhow::anyhow!("encode vector tile: {err}"))
-        .map(|bytes| (bytes, stats))
+fn add_feature_properties(
+    feature_builder: &mut mvt::Feature,
+    props: &HashMap<String, mvt_reader::feature::Value>,
+) {
+    let mut props: Vec<_> = props.iter().collect();
+    props.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in props {
+        match value {
+            mvt_reader::feature::Value::String(text) => {
+                feature_builder.add_tag_string(key, text);
+            }
+            mvt_reader::feature::Value::Float(val) => {
+                feature_builder.add_tag_float(key, *val);
+            }
+            mvt_reader::feature::Value::Double(val) => {
+                feature_builder.add_tag_double(key, *val);
+            }
+            mvt_reader::feature::Value::Int(val) => {
+                feature_builder.add_tag_int(key, *val);
+            }
+            mvt_reader::feature::Value::UInt(val) => {
+                feature_builder.add_tag_uint(key, *val);
+            }
+            mvt_reader::feature::Value::SInt(val) => {
+                feature_builder.add_tag_sint(key, *val);
+            }
+            mvt_reader::feature::Value::Bool(val) => {
+                feature_builder.add_tag_bool(key, *val);
+            }
+            mvt_reader::feature::Value::Null => {}
+        }
+    }
 }
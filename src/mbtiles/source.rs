@@ -0,0 +1,319 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params_from_iter};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::mbtiles::{
+    TileCoord, TileScheme, TileSizeRange, TileTableNames, TilesSchemaMode, apply_read_pragmas,
+    count_corrupt_rows, create_tile_table_view, fetch_tile_data, fetch_zoom_counts,
+    has_text_coord_columns, open_readonly_mbtiles_with, read_metadata, select_tile_count_query,
+    select_tiles_query_by_zoom, select_tiles_query_by_zoom_and_column_range, tile_address,
+    tiles_schema_mode,
+};
+use crate::source::{TileRow, TileSource};
+
+/// MBTiles file on disk; every scan opens its own read-only connection so
+/// zoom levels can be read in parallel.
+#[derive(Debug, Clone)]
+pub struct MbtilesFile {
+    path: PathBuf,
+    /// Tiles are read through a deduplicated schema (map/images or
+    /// tiles_shallow/tiles_data) rather than `tiles`.
+    content_ids: bool,
+    /// `tiles` can be scanned by `tile_column` range (not with TEXT columns).
+    column_ranges: bool,
+    read_options: MbtilesReadOptions,
+}
+
+/// How [`MbtilesFile`] connections are opened.
+#[derive(Debug, Clone, Default)]
+pub struct MbtilesReadOptions {
+    /// Open with `immutable=1` (no locking, WAL ignored); only safe when
+    /// nothing writes to the file.
+    pub immutable: bool,
+    /// Read tiles from a nonstandard table instead of `tiles`.
+    pub tile_table: Option<TileTableNames>,
+}
+
+impl MbtilesFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with_options(path, MbtilesReadOptions::default())
+    }
+
+    /// Like [`MbtilesFile::open`], but reads without taking locks, so a
+    /// database locked by another process can still be inspected. Only safe
+    /// when nothing writes to the file meanwhile.
+    pub fn open_immutable(path: &Path) -> Result<Self> {
+        Self::open_with_options(
+            path,
+            MbtilesReadOptions {
+                immutable: true,
+                ..MbtilesReadOptions::default()
+            },
+        )
+    }
+
+    pub fn open_with_options(path: &Path, read_options: MbtilesReadOptions) -> Result<Self> {
+        let read_options = MbtilesReadOptions {
+            tile_table: read_options.tile_table.filter(|names| !names.is_standard()),
+            ..read_options
+        };
+        let conn = open_file_connection(path, &read_options, false)?;
+        let mode = tiles_schema_mode(&conn)?;
+        let content_ids = mode != TilesSchemaMode::Tiles;
+        let column_ranges = !content_ids && !has_text_coord_columns(&conn, mode)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            content_ids,
+            column_ranges,
+            read_options,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        open_file_connection(&self.path, &self.read_options, true)
+    }
+}
+
+/// Read-only connection with the tile table mapped, with the read pragmas
+/// applied when `pragmas` is set.
+fn open_file_connection(
+    path: &Path,
+    read_options: &MbtilesReadOptions,
+    pragmas: bool,
+) -> Result<Connection> {
+    let conn = open_readonly_mbtiles_with(path, read_options.immutable)?;
+    if let Some(names) = read_options.tile_table.as_ref() {
+        // The view must exist before `query_only`, and changing `temp_store`
+        // afterwards would drop it, so pick the read pragmas' value now.
+        conn.execute_batch("PRAGMA temp_store = MEMORY;")
+            .context("failed to set temp_store")?;
+        create_tile_table_view(&conn, names)?;
+    }
+    if pragmas {
+        apply_read_pragmas(&conn)?;
+    }
+    Ok(conn)
+}
+
+impl TileSource for MbtilesFile {
+    fn row_scheme(&self) -> TileScheme {
+        TileScheme::Tms
+    }
+
+    fn metadata(&self) -> Result<BTreeMap<String, String>> {
+        read_metadata(&self.connect()?)
+    }
+
+    fn zoom_counts(&self) -> Result<BTreeMap<u8, u64>> {
+        fetch_zoom_counts(&self.connect()?)
+    }
+
+    fn tile_count(&self, zoom: Option<u8>) -> Result<u64> {
+        count_tiles(&self.connect()?, zoom)
+    }
+
+    fn corrupt_rows(&self, strict: bool) -> Result<u64> {
+        count_corrupt_rows(&self.connect()?, strict)
+    }
+
+    fn supports_column_ranges(&self) -> bool {
+        self.column_ranges
+    }
+
+    fn content_ids(&self) -> bool {
+        self.content_ids
+    }
+
+    fn scan_tiles(
+        &self,
+        zoom: u8,
+        columns: Option<(i64, i64)>,
+        with_data: bool,
+        visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+    ) -> Result<()> {
+        scan_connection(&self.connect()?, zoom, columns, with_data, None, visit)
+    }
+
+    fn scan_tiles_in_size_range(
+        &self,
+        zoom: u8,
+        columns: Option<(i64, i64)>,
+        with_data: bool,
+        lengths: TileSizeRange,
+        visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+    ) -> Result<()> {
+        scan_connection(
+            &self.connect()?,
+            zoom,
+            columns,
+            with_data,
+            Some(lengths),
+            visit,
+        )
+    }
+
+    fn tile_data(&self, coord: TileCoord) -> Result<Option<Vec<u8>>> {
+        fetch_tile_data(&self.connect()?, coord)
+    }
+}
+
+/// MBTiles backed by an already opened connection (for example an in-memory
+/// database). Scans are serialized through the connection.
+pub struct MbtilesConnection {
+    conn: Mutex<Connection>,
+}
+
+impl MbtilesConnection {
+    pub fn new(conn: Connection) -> Self {
+        Self {
+            conn: Mutex::new(conn),
+        }
+    }
+
+    pub fn into_inner(self) -> Connection {
+        self.conn
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("mbtiles connection lock poisoned"))?;
+        f(&conn)
+    }
+}
+
+impl TileSource for MbtilesConnection {
+    fn row_scheme(&self) -> TileScheme {
+        TileScheme::Tms
+    }
+
+    fn metadata(&self) -> Result<BTreeMap<String, String>> {
+        self.with_conn(read_metadata)
+    }
+
+    fn zoom_counts(&self) -> Result<BTreeMap<u8, u64>> {
+        self.with_conn(fetch_zoom_counts)
+    }
+
+    fn tile_count(&self, zoom: Option<u8>) -> Result<u64> {
+        self.with_conn(|conn| count_tiles(conn, zoom))
+    }
+
+    fn corrupt_rows(&self, strict: bool) -> Result<u64> {
+        self.with_conn(|conn| count_corrupt_rows(conn, strict))
+    }
+
+    fn supports_column_ranges(&self) -> bool {
+        self.with_conn(|conn| {
+            let mode = tiles_schema_mode(conn)?;
+            Ok(mode == TilesSchemaMode::Tiles && !has_text_coord_columns(conn, mode)?)
+        })
+        .unwrap_or(false)
+    }
+
+    fn content_ids(&self) -> bool {
+        self.with_conn(tiles_schema_mode)
+            .is_ok_and(|mode| mode != TilesSchemaMode::Tiles)
+    }
+
+    fn scan_tiles(
+        &self,
+        zoom: u8,
+        columns: Option<(i64, i64)>,
+        with_data: bool,
+        visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+    ) -> Result<()> {
+        self.with_conn(|conn| scan_connection(conn, zoom, columns, with_data, None, visit))
+    }
+
+    fn scan_tiles_in_size_range(
+        &self,
+        zoom: u8,
+        columns: Option<(i64, i64)>,
+        with_data: bool,
+        lengths: TileSizeRange,
+        visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+    ) -> Result<()> {
+        self.with_conn(|conn| scan_connection(conn, zoom, columns, with_data, Some(lengths), visit))
+    }
+
+    fn tile_data(&self, coord: TileCoord) -> Result<Option<Vec<u8>>> {
+        self.with_conn(|conn| fetch_tile_data(conn, coord))
+    }
+}
+
+fn count_tiles(conn: &Connection, zoom: Option<u8>) -> Result<u64> {
+    let query = select_tile_count_query(conn, zoom.is_some())?;
+    let count = match zoom {
+        Some(z) => conn
+            .query_row(&query, [z], |row| row.get::<_, i64>(0))
+            .context("failed to read tile count (zoom)")?,
+        None => conn
+            .query_row(&query, [], |row| row.get::<_, i64>(0))
+            .context("failed to read tile count")?,
+    };
+    u64::try_from(count).context("tile count must be non-negative")
+}
+
+fn scan_connection(
+    conn: &Connection,
+    zoom: u8,
+    columns: Option<(i64, i64)>,
+    with_data: bool,
+    lengths: Option<TileSizeRange>,
+    visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+) -> Result<()> {
+    let mut query = if columns.is_some() {
+        select_tiles_query_by_zoom_and_column_range(conn, with_data)?
+    } else {
+        select_tiles_query_by_zoom(conn, with_data)?
+    };
+    let mut values = vec![i64::from(zoom)];
+    if let Some((col_min, col_max)) = columns {
+        values.extend([col_min, col_max]);
+    }
+    // Skipped rows are never read, so only the matching payloads are loaded
+    if let Some(lengths) = lengths {
+        let data_expr = tiles_schema_mode(conn)?.data_expr();
+        query.push_str(&format!(
+            " AND LENGTH({data_expr}) BETWEEN ?{} AND ?{}",
+            values.len() + 1,
+            values.len() + 2
+        ));
+        values.extend(
+            [lengths.min_bytes, lengths.max_bytes]
+                .map(|bytes| i64::try_from(bytes).unwrap_or(i64::MAX)),
+        );
+    }
+    let mut stmt = conn.prepare(&query).context("prepare tiles scan")?;
+    let mut rows = stmt
+        .query(params_from_iter(values))
+        .context("query tiles scan")?;
+    while let Some(row) = rows.next().context("read tile row")? {
+        let Some(coord) = tile_address(row, 0)? else {
+            continue;
+        };
+        let length: i64 = row.get(3)?;
+        let tile = TileRow {
+            zoom: coord.zoom,
+            x: coord.x,
+            y: coord.y,
+            length: u64::try_from(length).context("tile length must be non-negative")?,
+            data: if with_data { Some(row.get(5)?) } else { None },
+            content_id: row.get(4)?,
+        };
+        if !visit(tile)? {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,10 @@
 use serde::Serialize;
-use std::collections::{BTreeMap, BTreeSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+
+use crate::format::{RasterFormat, TileMediaType};
+use crate::mbtiles::{CardinalityOptions, InspectOptions, TileHashAlgorithm};
+use crate::style::MapboxStyle;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct MbtilesStats {
@@ -7,6 +12,19 @@ pub struct MbtilesStats {
     pub total_bytes: u64,
     pub max_bytes: u64,
     pub avg_bytes: u64,
+    /// Tile size percentiles (only with `--percentiles`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentiles: Option<TilePercentiles>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TilePercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p95: u64,
+    pub p99: u64,
+    /// 99.9th percentile.
+    pub p999: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -22,26 +40,626 @@ pub struct MbtilesReport {
     pub by_zoom: Vec<MbtilesZoomStats>,
     pub empty_tiles: u64,
     pub empty_ratio: f64,
+    /// Size in bytes at or below which a tile was counted as empty.
+    pub empty_tile_max_bytes: u64,
+    /// Tiles of at most `empty_tile_max_bytes` that still hold features; only
+    /// set with `--empty-exact`, where `empty_tiles` counts the featureless
+    /// ones alone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub small_tiles: Option<u64>,
+    /// Tiles over `--max-tile-bytes`: `over_limit_tiles_exact` when known,
+    /// otherwise the sampled estimate.
     pub over_limit_tiles: u64,
+    /// Over-limit tiles counted over every tile regardless of sampling;
+    /// `None` when sizes are measured uncompressed in a sample, where only
+    /// the sampled tiles are decoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub over_limit_tiles_exact: Option<u64>,
+    /// Over-limit tiles in the sample, extrapolated to all tiles (only when
+    /// sampled with `--max-tile-bytes`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub over_limit_tiles_sampled: Option<OverLimitEstimate>,
+    /// Rows skipped because their tile address is invalid (negative or
+    /// non-integer values, zoom beyond 30).
+    pub corrupt_rows: u64,
     pub sampled: bool,
     pub sample_total_tiles: u64,
     pub sample_used_tiles: u64,
+    /// Seed the sample was drawn with (only when sampled).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_seed: Option<u64>,
     pub histogram: Vec<HistogramBucket>,
     pub histograms_by_zoom: Vec<ZoomHistogram>,
     pub file_layers: Vec<FileLayerSummary>,
     pub top_tiles: Vec<TopTile>,
     pub bucket_count: Option<u64>,
     pub bucket_tiles: Vec<TopTile>,
+    /// The first of `tile_summaries`; kept for existing consumers.
     pub tile_summary: Option<TileSummary>,
+    /// One summary per `--tile`, in the order requested.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tile_summaries: Vec<TileSummary>,
     pub recommended_buckets: Vec<usize>,
+    /// Layer-level suggestions derived from the top tiles (only with `--recommend`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recommendations: Vec<Recommendation>,
     pub top_tile_summaries: Vec<TileSummary>,
+    /// Duplicate tile contents (only with `--dedup-stats`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupReport>,
+    /// Order-independent digest of every used tile (only with `--hash`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<ContentHash>,
+    /// Feature counts per layer and zoom (only with `--layer-zoom-matrix`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layer_zoom_matrix: Vec<(String, Vec<(u8, u64)>)>,
+    /// Most frequent values of one key per layer (only with `--property-histogram`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub property_histograms: Vec<PropertyHistogram>,
+    /// Stored bytes of the used tiles when sizes were measured uncompressed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored_bytes: Option<u64>,
+    /// Tiles that failed strict decoding (only with `--validate-mvt`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mvt_validation: Option<MvtValidation>,
+    /// Set when the tiles are images; layer analysis is skipped for them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raster_format: Option<RasterFormat>,
+    /// Tiles holding an image rather than MVT data. Every tile of a raster
+    /// tileset; otherwise only counted for tiles whose data was read.
+    pub raster_tiles: u64,
+    /// Scanned tiles per media type (only with `--media-types`).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub media_types: BTreeMap<TileMediaType, u64>,
+    /// Property keys whose values are nearly all distinct, largest estimated
+    /// contribution first (only with `--detect-cardinality`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub high_cardinality_keys: Vec<HighCardinalityKey>,
+    /// Layers declared in the metadata `vector_layers` (from the `json` key
+    /// or a top-level `vector_layers` entry), when present and valid.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vector_layers: Vec<VectorLayerInfo>,
+    /// Differences between `vector_layers` and the layers found in the
+    /// tiles; only checked when a layer list was built.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub metadata_mismatches: Vec<MetadataMismatch>,
+    /// Layers the `--style` would keep or drop (only with a style and a
+    /// layer list).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style_preview: Option<StylePreview>,
+}
+
+/// Over-limit tiles found in a sample and scaled to all tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OverLimitEstimate {
+    /// Estimated over-limit tiles among all tiles.
+    pub estimate: u64,
+    /// Over-limit tiles in the sample.
+    pub sample_count: u64,
+    /// Tiles in the sample.
+    pub sample_size: u64,
+}
+
+impl OverLimitEstimate {
+    /// Scale `sample_count` of `sample_size` tiles to `total_tiles`.
+    pub fn new(sample_count: u64, sample_size: u64, total_tiles: u64) -> Self {
+        let estimate = if sample_size == 0 {
+            0
+        } else {
+            (sample_count as f64 * total_tiles as f64 / sample_size as f64).round() as u64
+        };
+        Self {
+            estimate,
+            sample_count,
+            sample_size,
+        }
+    }
+
+    /// Sum of the estimates of zooms sampled separately, each given as
+    /// `(sample_count, sample_size, total_tiles)`.
+    pub fn by_zoom(zooms: impl IntoIterator<Item = (u64, u64, u64)>) -> Self {
+        zooms.into_iter().fold(
+            Self::new(0, 0, 0),
+            |sum, (sample_count, sample_size, total_tiles)| {
+                let zoom = Self::new(sample_count, sample_size, total_tiles);
+                Self {
+                    estimate: sum.estimate + zoom.estimate,
+                    sample_count: sum.sample_count + zoom.sample_count,
+                    sample_size: sum.sample_size + zoom.sample_size,
+                }
+            },
+        )
+    }
+}
+
+impl MbtilesReport {
+    /// Fill `vector_layers` from the metadata and, when a layer list was
+    /// built, `metadata_mismatches`.
+    pub(crate) fn check_vector_layers(&mut self, options: &InspectOptions) {
+        let Some(declared) = parse_vector_layers(&self.metadata) else {
+            return;
+        };
+        if options.include_layer_list {
+            self.metadata_mismatches = vector_layer_mismatches(
+                &declared,
+                &self.file_layers,
+                options.zoom,
+                &options.layers,
+            );
+        }
+        self.vector_layers = declared;
+    }
+
+    /// Fill `style_preview` from the layer list when a style was given.
+    pub(crate) fn preview_style(&mut self, style: Option<&MapboxStyle>, options: &InspectOptions) {
+        let Some(style) = style.filter(|_| options.include_layer_list) else {
+            return;
+        };
+        let zooms = self
+            .by_zoom
+            .iter()
+            .map(|stats| stats.zoom)
+            .collect::<Vec<_>>();
+        self.style_preview = Some(style_preview(
+            style,
+            &self.file_layers,
+            &self.layer_zoom_matrix,
+            &zooms,
+        ));
+    }
+}
+
+/// Values reported per layer by `--property-histogram`.
+pub const PROPERTY_HISTOGRAM_TOP: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PropertyValueCount {
+    pub value: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PropertyHistogram {
+    pub layer: String,
+    pub key: String,
+    /// Sorted by count, most frequent first.
+    pub values: Vec<PropertyValueCount>,
+    /// Rarer values were dropped to bound memory, so counts are lower bounds.
+    pub truncated: bool,
+}
+
+/// Value counts for one property key, bounded to a small multiple of
+/// [`PROPERTY_HISTOGRAM_TOP`] distinct values.
+#[derive(Debug, Clone, Default)]
+pub struct TopValueCounter {
+    counts: HashMap<String, u64>,
+    truncated: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl TopValueCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn add(&mut self, value: String, count: u64) {
+        *self.counts.entry(value).or_insert(0) += count;
+        self.prune_if_full();
+    }
+
+    pub fn merge(&mut self, other: TopValueCounter) {
+        self.truncated |= other.truncated;
+        for (value, count) in other.counts {
+            *self.counts.entry(value).or_insert(0) += count;
+        }
+        self.prune_if_full();
+    }
+
+    fn prune_if_full(&mut self) {
+        if self.counts.len() > PROPERTY_HISTOGRAM_TOP * 4 {
+            self.counts = top_value_counts(std::mem::take(&mut self.counts))
+                .into_iter()
+                .collect();
+            self.truncated = true;
+        }
+    }
+
+    pub fn into_histogram(self, layer: String, key: String) -> PropertyHistogram {
+        let truncated = self.truncated || self.counts.len() > PROPERTY_HISTOGRAM_TOP;
+        let mut values = top_value_counts(self.counts)
+            .into_iter()
+            .map(|(value, count)| PropertyValueCount { value, count })
+            .collect::<Vec<_>>();
+        values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        PropertyHistogram {
+            layer,
+            key,
+            values,
+            truncated,
+        }
+    }
+}
+
+/// The [`PROPERTY_HISTOGRAM_TOP`] most frequent values, picked with a running
+/// min-heap; ties keep the lexicographically smaller value.
+fn top_value_counts(counts: HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut heap = BinaryHeap::new();
+    for (value, count) in counts {
+        heap.push(Reverse((count, Reverse(value))));
+        if heap.len() > PROPERTY_HISTOGRAM_TOP {
+            heap.pop();
+        }
+    }
+    heap.into_iter()
+        .map(|Reverse((count, Reverse(value)))| (value, count))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HighCardinalityKey {
+    pub layer: String,
+    pub key: String,
+    /// Features carrying the key.
+    pub feature_count: u64,
+    pub distinct_values: u64,
+    /// `distinct_values / feature_count`.
+    pub distinct_ratio: f64,
+    /// More values were seen than tracked, so `distinct_values` is estimated.
+    pub estimated: bool,
+    /// Approximate bytes the key adds to the tiles: each feature's encoded
+    /// value plus its tag pair.
+    pub estimated_bytes: u64,
+}
+
+/// Distinct value counter holding at most `capacity` 64-bit value hashes:
+/// exact until it is full, then a bottom-k estimate from the largest hash
+/// kept. Merging is order independent, so parallel scans agree.
+#[derive(Debug, Clone)]
+pub struct DistinctValueSketch {
+    capacity: usize,
+    hashes: BTreeSet<u64>,
+    saturated: bool,
+}
+
+impl DistinctValueSketch {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(2),
+            hashes: BTreeSet::new(),
+            saturated: false,
+        }
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        self.insert_hash(xxhash_rust::xxh3::xxh3_64(value.as_bytes()));
+    }
+
+    fn insert_hash(&mut self, hash: u64) {
+        if self.saturated && self.hashes.last().is_some_and(|max| hash >= *max) {
+            return;
+        }
+        if self.hashes.insert(hash) && self.hashes.len() > self.capacity {
+            self.hashes.pop_last();
+            self.saturated = true;
+        }
+    }
+
+    pub fn merge(&mut self, other: DistinctValueSketch) {
+        self.saturated |= other.saturated;
+        for hash in other.hashes {
+            self.insert_hash(hash);
+        }
+    }
+
+    pub fn is_estimated(&self) -> bool {
+        self.saturated
+    }
+
+    pub fn estimate(&self) -> u64 {
+        match self.hashes.last() {
+            Some(&max) if self.saturated => {
+                let fraction = (max as f64 + 1.0) / (u64::MAX as f64 + 1.0);
+                ((self.hashes.len() - 1) as f64 / fraction).round() as u64
+            }
+            _ => self.hashes.len() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct KeyValueStats {
+    features: u64,
+    value_bytes: u64,
+    distinct: DistinctValueSketch,
+}
+
+/// Per-key feature counts, value bytes and distinct values of one layer for
+/// `--detect-cardinality`.
+#[derive(Debug, Clone, Default)]
+pub struct KeyCardinality {
+    keys: HashMap<String, KeyValueStats>,
+}
+
+impl KeyCardinality {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `count` features with `key` set to `value`, whose encoded
+    /// value entry takes `value_size` bytes.
+    pub fn add(&mut self, key: &str, value: &str, value_size: u64, count: u64, capacity: usize) {
+        if !self.keys.contains_key(key) {
+            self.keys.insert(
+                key.to_string(),
+                KeyValueStats {
+                    features: 0,
+                    value_bytes: 0,
+                    distinct: DistinctValueSketch::new(capacity),
+                },
+            );
+        }
+        let stats = self.keys.get_mut(key).expect("key inserted above");
+        stats.features += count;
+        // The tag pair is two small varint indices, about a byte each.
+        stats.value_bytes += (value_size + 2) * count;
+        stats.distinct.insert(value);
+    }
+
+    pub fn merge(&mut self, other: KeyCardinality) {
+        for (key, stats) in other.keys {
+            match self.keys.get_mut(&key) {
+                Some(existing) => {
+                    existing.features += stats.features;
+                    existing.value_bytes += stats.value_bytes;
+                    existing.distinct.merge(stats.distinct);
+                }
+                None => {
+                    self.keys.insert(key, stats);
+                }
+            }
+        }
+    }
+
+    /// Keys of `layer` over the limits of `options`.
+    pub fn into_high_cardinality_keys(
+        self,
+        layer: &str,
+        options: &CardinalityOptions,
+    ) -> Vec<HighCardinalityKey> {
+        self.keys
+            .into_iter()
+            .filter(|(_, stats)| stats.features >= options.min_features.max(1))
+            .filter_map(|(key, stats)| {
+                let distinct_values = stats.distinct.estimate().min(stats.features);
+                let distinct_ratio = distinct_values as f64 / stats.features as f64;
+                (distinct_ratio > options.min_distinct_ratio).then(|| HighCardinalityKey {
+                    layer: layer.to_string(),
+                    key,
+                    feature_count: stats.features,
+                    distinct_values,
+                    distinct_ratio,
+                    estimated: stats.distinct.is_estimated(),
+                    estimated_bytes: stats.value_bytes,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Largest estimated contribution first, then by layer and key.
+pub fn sort_high_cardinality_keys(keys: &mut [HighCardinalityKey]) {
+    keys.sort_by(|a, b| {
+        b.estimated_bytes
+            .cmp(&a.estimated_bytes)
+            .then_with(|| a.layer.cmp(&b.layer))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+}
+
+/// Feature counts keyed by `(layer, zoom)`, collected during the tile scan.
+#[derive(Debug, Clone, Default)]
+pub struct LayerZoomCounts {
+    counts: BTreeMap<(String, u8), u64>,
+}
+
+impl LayerZoomCounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, layer: &str, zoom: u8, features: u64) {
+        *self.counts.entry((layer.to_string(), zoom)).or_insert(0) += features;
+    }
+
+    pub fn merge(&mut self, other: LayerZoomCounts) {
+        for (key, count) in other.counts {
+            *self.counts.entry(key).or_insert(0) += count;
+        }
+    }
+
+    /// Rows sorted by layer name, each with its zooms in ascending order;
+    /// a non-empty `layers` keeps only those layers.
+    pub fn into_matrix(self, layers: &[String]) -> Vec<(String, Vec<(u8, u64)>)> {
+        let mut rows: Vec<(String, Vec<(u8, u64)>)> = Vec::new();
+        for ((layer, zoom), count) in self.counts {
+            if !layers.is_empty() && !layers.contains(&layer) {
+                continue;
+            }
+            match rows.last_mut() {
+                Some((name, zooms)) if *name == layer => zooms.push((zoom, count)),
+                _ => rows.push((layer, vec![(zoom, count)])),
+            }
+        }
+        rows
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct DedupStats {
+    /// Distinct tile payloads (distinct blob ids where the schema shares them).
+    pub unique_tiles: u64,
+    /// Tiles repeating an earlier payload or blob id.
+    pub duplicate_tiles: u64,
+    /// Bytes saved if every duplicate referenced a single stored copy; tiles
+    /// already sharing a blob id (map/images, shallow, PMTiles) add nothing.
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZoomDedupStats {
+    pub zoom: u8,
+    pub stats: DedupStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DedupReport {
+    /// Computed from sampled tiles only; totals are not extrapolated.
+    pub estimated: bool,
+    pub overall: DedupStats,
+    pub by_zoom: Vec<ZoomDedupStats>,
+}
+
+/// Concrete change suggested by `--recommend` for one layer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecommendedAction {
+    /// Simplify the layer's geometries with this tolerance (tile units).
+    Simplify { tolerance: f64 },
+    /// Raise the layer's minzoom so it is no longer written at this zoom.
+    RaiseMinzoom { minzoom: u8 },
+}
+
+/// Threshold that caused a layer to be reported by `--recommend`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecommendReason {
+    BytesShare,
+    LayerBytes,
+    FeatureCount,
+    /// The layer has the most vertices in the top tiles of this zoom.
+    MostVertices,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Recommendation {
+    pub zoom: u8,
+    pub layer: String,
+    pub reason: RecommendReason,
+    /// Share of the layer bytes in the top tiles of this zoom (0.0-1.0).
+    pub bytes_share: f64,
+    /// Share of the vertices in the top tiles of this zoom (0.0-1.0).
+    pub vertices_share: f64,
+    /// Average encoded layer bytes per top tile.
+    pub avg_bytes: u64,
+    /// Average feature count per top tile.
+    pub avg_features: u64,
+    pub action: RecommendedAction,
+    /// Estimated bytes saved across the zoom level.
+    pub estimated_savings_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
 pub struct SimplifyStats {
     pub feature_count: u64,
     pub vertices_before: u64,
     pub vertices_after: u64,
+    /// Polygons removed because their exterior ring collapsed.
+    pub dropped_polygons: u64,
+    /// Interior rings removed because they collapsed.
+    pub dropped_holes: u64,
+    /// Rings kept unsimplified by `--preserve-topology`.
+    pub restored_rings: u64,
+}
+
+impl SimplifyStats {
+    pub fn merge(&mut self, other: SimplifyStats) {
+        self.feature_count += other.feature_count;
+        self.vertices_before += other.vertices_before;
+        self.vertices_after += other.vertices_after;
+        self.dropped_polygons += other.dropped_polygons;
+        self.dropped_holes += other.dropped_holes;
+        self.restored_rings += other.restored_rings;
+    }
+}
+
+/// Totals for an overzoom run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct OverzoomStats {
+    /// Tiles at the source max zoom that children were generated from.
+    pub parent_tiles: u64,
+    /// Generated tiles per zoom.
+    pub generated_by_zoom: BTreeMap<u8, u64>,
+    /// Children left without features after clipping, not written.
+    pub empty_children: u64,
+}
+
+impl OverzoomStats {
+    pub fn merge(&mut self, other: OverzoomStats) {
+        self.parent_tiles += other.parent_tiles;
+        self.empty_children += other.empty_children;
+        for (zoom, count) in other.generated_by_zoom {
+            *self.generated_by_zoom.entry(zoom).or_default() += count;
+        }
+    }
+
+    pub fn generated_tiles(&self) -> u64 {
+        self.generated_by_zoom.values().sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SimplifyZoomStats {
+    pub tiles: u64,
+    pub stats: SimplifyStats,
+}
+
+/// Totals for a batch simplify run; `by_zoom` only lists zooms with rewritten tiles.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SimplifyBatchStats {
+    pub tiles_read: u64,
+    pub by_zoom: BTreeMap<u8, SimplifyZoomStats>,
+    /// Every tile read and written, simplified or copied.
+    pub tiles_by_zoom: BTreeMap<u8, ZoomTileCounts>,
+}
+
+impl SimplifyBatchStats {
+    pub fn record(&mut self, zoom: u8, stats: SimplifyStats) {
+        let entry = self.by_zoom.entry(zoom).or_default();
+        entry.tiles += 1;
+        entry.stats.merge(stats);
+    }
+
+    pub fn record_tile(&mut self, zoom: u8, input_bytes: usize, output_bytes: Option<usize>) {
+        self.tiles_by_zoom
+            .entry(zoom)
+            .or_default()
+            .record(input_bytes, output_bytes);
+    }
+
+    pub fn merge(&mut self, other: SimplifyBatchStats) {
+        self.tiles_read += other.tiles_read;
+        merge_zoom_tile_counts(&mut self.tiles_by_zoom, other.tiles_by_zoom);
+        for (zoom, zoom_stats) in other.by_zoom.into_iter() {
+            let entry = self.by_zoom.entry(zoom).or_default();
+            entry.tiles += zoom_stats.tiles;
+            entry.stats.merge(zoom_stats.stats);
+        }
+    }
+
+    pub fn tiles_simplified(&self) -> u64 {
+        self.by_zoom.values().map(|zoom| zoom.tiles).sum()
+    }
+
+    pub fn totals(&self) -> SimplifyStats {
+        let mut totals = SimplifyStats::default();
+        for zoom_stats in self.by_zoom.values() {
+            totals.merge(zoom_stats.stats);
+        }
+        totals
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -71,26 +689,475 @@ pub struct TopTile {
     pub x: u32,
     pub y: u32,
     pub bytes: u64,
+    /// Viewer link expanded from `--tile-url-template`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Digest of the decompressed payload (listed tiles with `--hash`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// Per-tile record emitted while scanning with `--list-tiles` in NDJSON mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TileRecord {
+    pub zoom: u8,
+    pub x: u32,
+    pub y: u32,
+    pub bytes: u64,
+    pub over_limit: bool,
+    /// Digest of the decompressed payload (only with `--hash`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// Callback receiving [`TileRecord`]s as the scan visits them. Scans run on
+/// worker threads, so the sink must be `Sync`; records arrive in scan order
+/// per zoom but zoom levels may interleave.
+pub type TileSink<'a> = &'a (dyn Fn(TileRecord) -> anyhow::Result<()> + Sync);
+
+/// Forwards records to a [`TileSink`] until `limit` records were emitted
+/// (`0` = unlimited).
+pub struct TileStream<'a> {
+    sink: TileSink<'a>,
+    limit: u64,
+    emitted: std::sync::atomic::AtomicU64,
+}
+
+impl<'a> TileStream<'a> {
+    pub fn new(sink: TileSink<'a>, limit: usize) -> Self {
+        Self {
+            sink,
+            limit: limit as u64,
+            emitted: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    pub fn emit(&self, record: TileRecord) -> anyhow::Result<()> {
+        use std::sync::atomic::Ordering;
+        if self.limit > 0 && self.emitted.fetch_add(1, Ordering::Relaxed) >= self.limit {
+            return Ok(());
+        }
+        (self.sink)(record)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct LayerSummary {
     pub name: String,
+    /// Encoded size of the layer in the decompressed tile.
+    pub bytes: u64,
     pub feature_count: usize,
     pub vertex_count: u64,
     pub property_key_count: usize,
     pub property_value_count: usize,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub property_keys: Vec<String>,
+    /// Features failing the geometry validity check (only with `--check-geometry`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalid_geometry_count: Option<u64>,
+    /// Features whose geometry and properties repeat an earlier feature of
+    /// the layer (ids are ignored).
+    pub duplicate_feature_count: u64,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct FileLayerSummary {
     pub name: String,
     pub vertex_count: u64,
     pub feature_count: u64,
     pub property_key_count: usize,
     pub property_value_count: usize,
+    /// Features failing the geometry validity check (only with `--check-geometry`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalid_geometry_count: Option<u64>,
+    /// Tiles containing the layer; this and the per-tile fields below are
+    /// only filled when the list covers a single `--zoom`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiles_with_layer: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_features_per_tile: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_features_per_tile: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_features_per_tile: Option<f64>,
+    /// The [`LAYER_TOP_KEYS`] property keys set on the most features, as
+    /// `(key, feature count)`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub top_keys: Vec<(String, u64)>,
+    /// Re-encoded layer bytes without properties (only with `--attributes-size`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geometry_bytes: Option<u64>,
+    /// Bytes the properties add to the re-encoded layer (only with `--attributes-size`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_bytes: Option<u64>,
+    /// Decompressed bytes of the layer, summed from the length of its
+    /// protobuf message in each scanned tile (only with `--layer-bytes`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approx_bytes_field_length: Option<u64>,
+    /// `approx_bytes_field_length` split by zoom.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub approx_bytes_field_length_by_zoom: BTreeMap<u8, u64>,
+    /// Share of `approx_bytes_field_length` in the sum over all listed layers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pct_of_total: Option<f64>,
+}
+
+/// Fill [`FileLayerSummary::pct_of_total`] for layers with
+/// `approx_bytes_field_length`.
+pub fn fill_layer_byte_shares(layers: &mut [FileLayerSummary]) {
+    let total: u64 = layers
+        .iter()
+        .filter_map(|layer| layer.approx_bytes_field_length)
+        .sum();
+    for layer in layers.iter_mut() {
+        layer.pct_of_total = layer.approx_bytes_field_length.map(|bytes| {
+            if total == 0 {
+                0.0
+            } else {
+                bytes as f64 * 100.0 / total as f64
+            }
+        });
+    }
+}
+
+/// One entry of the metadata `vector_layers` array.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VectorLayerInfo {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minzoom: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxzoom: Option<u8>,
+    /// Field names and their declared types (`String`, `Number`, ...).
+    pub fields: BTreeMap<String, String>,
+}
+
+impl VectorLayerInfo {
+    fn covers_zoom(&self, zoom: u8) -> bool {
+        self.minzoom.is_none_or(|min| zoom >= min) && self.maxzoom.is_none_or(|max| zoom <= max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataMismatchKind {
+    /// Declared in `vector_layers` but not found in the scanned tiles.
+    DeclaredNotInTiles,
+    /// Found in the tiles but missing from `vector_layers`.
+    InTilesNotDeclared,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MetadataMismatch {
+    pub layer: String,
+    pub kind: MetadataMismatchKind,
+}
+
+/// The `vector_layers` declared in `metadata`: the array inside the MBTiles
+/// `json` value, else a top-level `vector_layers` entry (PMTiles). `None`
+/// when neither is present or parses as an array of layers with an `id`.
+pub fn parse_vector_layers(metadata: &BTreeMap<String, String>) -> Option<Vec<VectorLayerInfo>> {
+    let layers = metadata
+        .get("json")
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(json).ok())
+        .and_then(|json| json.get("vector_layers").cloned())
+        .or_else(|| {
+            metadata
+                .get("vector_layers")
+                .and_then(|layers| serde_json::from_str(layers).ok())
+        })?;
+    let zoom = |layer: &serde_json::Value, key: &str| {
+        layer
+            .get(key)
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|zoom| u8::try_from(zoom).ok())
+    };
+    layers
+        .as_array()?
+        .iter()
+        .map(|layer| {
+            Some(VectorLayerInfo {
+                id: layer.get("id")?.as_str()?.to_string(),
+                description: layer
+                    .get("description")
+                    .and_then(serde_json::Value::as_str)
+                    .filter(|text| !text.is_empty())
+                    .map(str::to_string),
+                minzoom: zoom(layer, "minzoom"),
+                maxzoom: zoom(layer, "maxzoom"),
+                fields: layer
+                    .get("fields")
+                    .and_then(serde_json::Value::as_object)
+                    .map(|fields| {
+                        fields
+                            .iter()
+                            .map(|(name, kind)| {
+                                let kind = match kind {
+                                    serde_json::Value::String(text) => text.clone(),
+                                    other => other.to_string(),
+                                };
+                                (name.clone(), kind)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Compare `declared` with the layers `found` in the tiles. Declared layers
+/// outside `zoom` (when the scan was limited to one) or not in a non-empty
+/// `layers` filter are not expected in the tiles.
+pub fn vector_layer_mismatches(
+    declared: &[VectorLayerInfo],
+    found: &[FileLayerSummary],
+    zoom: Option<u8>,
+    layers: &[String],
+) -> Vec<MetadataMismatch> {
+    let found_names: BTreeSet<&str> = found.iter().map(|layer| layer.name.as_str()).collect();
+    let declared_names: BTreeSet<&str> = declared.iter().map(|layer| layer.id.as_str()).collect();
+    let mut mismatches = declared
+        .iter()
+        .filter(|layer| zoom.is_none_or(|zoom| layer.covers_zoom(zoom)))
+        .filter(|layer| layers.is_empty() || layers.contains(&layer.id))
+        .filter(|layer| !found_names.contains(layer.id.as_str()))
+        .map(|layer| MetadataMismatch {
+            layer: layer.id.clone(),
+            kind: MetadataMismatchKind::DeclaredNotInTiles,
+        })
+        .collect::<Vec<_>>();
+    mismatches.extend(
+        found_names
+            .difference(&declared_names)
+            .map(|name| MetadataMismatch {
+                layer: name.to_string(),
+                kind: MetadataMismatchKind::InTilesNotDeclared,
+            }),
+    );
+    mismatches.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.layer.cmp(&b.layer)));
+    mismatches
+}
+
+/// What a style does to one layer when optimize applies it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StyleLayerStatus {
+    /// Displayed at every zoom the layer has tiles at.
+    Kept,
+    /// Displayed at none of them, or not used by the style at all.
+    Dropped,
+    /// Displayed at some of its zooms only.
+    Partial,
+}
+
+/// One layer of a [`StylePreview`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StyleLayerPreview {
+    pub name: String,
+    pub status: StyleLayerStatus,
+    /// Zooms of the layer at which the style displays it.
+    pub kept_zooms: Vec<u8>,
+    /// Zooms of the layer that optimize would drop it from.
+    pub dropped_zooms: Vec<u8>,
+    /// Features in `dropped_zooms`; `None` when the layer is only partly
+    /// dropped and no per-zoom feature counts were collected.
+    pub removed_features: Option<u64>,
+    /// Decompressed layer bytes in `dropped_zooms` (needs `--layer-bytes`).
+    pub removed_bytes: Option<u64>,
+}
+
+/// Layers a style would keep or drop, with the features and bytes optimize
+/// would remove, estimated from the layer list (`inspect --style`). Figures
+/// cover the scanned (sampled) tiles.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StylePreview {
+    pub layers: Vec<StyleLayerPreview>,
+    pub total_features: u64,
+    /// `None` when a partly dropped layer has no per-zoom feature counts.
+    pub removed_features: Option<u64>,
+    /// Share of `total_features` removed, in percent.
+    pub removed_features_pct: Option<f64>,
+    /// Decompressed bytes of the listed layers (needs `--layer-bytes`).
+    pub total_bytes: Option<u64>,
+    pub removed_bytes: Option<u64>,
+    /// Share of `total_bytes` removed, in percent.
+    pub removed_bytes_pct: Option<f64>,
+}
+
+/// Join the keep/drop decisions of `style` with the layer list. A layer's
+/// zooms come from `layer_zoom_matrix`, else its per-zoom bytes, else
+/// `tileset_zooms`; the highest zoom is treated as overzoomed, as optimize
+/// does.
+pub fn style_preview(
+    style: &MapboxStyle,
+    layers: &[FileLayerSummary],
+    layer_zoom_matrix: &[(String, Vec<(u8, u64)>)],
+    tileset_zooms: &[u8],
+) -> StylePreview {
+    let max_zoom = tileset_zooms
+        .iter()
+        .copied()
+        .chain(
+            layer_zoom_matrix
+                .iter()
+                .flat_map(|(_, zooms)| zooms.iter().map(|(zoom, _)| *zoom)),
+        )
+        .max();
+    let style = style.clone().with_tileset_max_zoom(max_zoom);
+    let source_layers = style.source_layers();
+    let matrix: HashMap<&str, &[(u8, u64)]> = layer_zoom_matrix
+        .iter()
+        .map(|(name, zooms)| (name.as_str(), zooms.as_slice()))
+        .collect();
+
+    let previews = layers
+        .iter()
+        .map(|layer| {
+            let per_zoom = matrix.get(layer.name.as_str()).copied();
+            let zooms = match per_zoom {
+                Some(zooms) => zooms.iter().map(|(zoom, _)| *zoom).collect(),
+                None if !layer.approx_bytes_field_length_by_zoom.is_empty() => layer
+                    .approx_bytes_field_length_by_zoom
+                    .keys()
+                    .copied()
+                    .collect(),
+                None => tileset_zooms.to_vec(),
+            };
+            let referenced = source_layers.contains(&layer.name);
+            let (kept_zooms, dropped_zooms): (Vec<u8>, Vec<u8>) = zooms
+                .into_iter()
+                .partition(|zoom| referenced && style.is_layer_visible_on_zoom(&layer.name, *zoom));
+            let status = if !referenced || (kept_zooms.is_empty() && !dropped_zooms.is_empty()) {
+                StyleLayerStatus::Dropped
+            } else if dropped_zooms.is_empty() {
+                StyleLayerStatus::Kept
+            } else {
+                StyleLayerStatus::Partial
+            };
+            let (removed_features, removed_bytes) = match status {
+                StyleLayerStatus::Kept => (Some(0), layer.approx_bytes_field_length.map(|_| 0)),
+                StyleLayerStatus::Dropped => {
+                    (Some(layer.feature_count), layer.approx_bytes_field_length)
+                }
+                StyleLayerStatus::Partial => (
+                    per_zoom.map(|zooms| {
+                        zooms
+                            .iter()
+                            .filter(|(zoom, _)| dropped_zooms.contains(zoom))
+                            .map(|(_, features)| features)
+                            .sum()
+                    }),
+                    layer.approx_bytes_field_length.map(|_| {
+                        dropped_zooms
+                            .iter()
+                            .filter_map(|zoom| layer.approx_bytes_field_length_by_zoom.get(zoom))
+                            .sum()
+                    }),
+                ),
+            };
+            StyleLayerPreview {
+                name: layer.name.clone(),
+                status,
+                kept_zooms,
+                dropped_zooms,
+                removed_features,
+                removed_bytes,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let percent = |removed: u64, total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            removed as f64 * 100.0 / total as f64
+        }
+    };
+    let total_features = layers.iter().map(|layer| layer.feature_count).sum();
+    let removed_features = previews
+        .iter()
+        .map(|layer| layer.removed_features)
+        .sum::<Option<u64>>();
+    let total_bytes = layers
+        .iter()
+        .map(|layer| layer.approx_bytes_field_length)
+        .sum::<Option<u64>>();
+    let removed_bytes = previews
+        .iter()
+        .map(|layer| layer.removed_bytes)
+        .sum::<Option<u64>>();
+    StylePreview {
+        layers: previews,
+        total_features,
+        removed_features,
+        removed_features_pct: removed_features.map(|removed| percent(removed, total_features)),
+        total_bytes,
+        removed_bytes,
+        removed_bytes_pct: removed_bytes
+            .zip(total_bytes)
+            .map(|(removed, total)| percent(removed, total)),
+    }
+}
+
+/// Number of property keys listed per layer in [`FileLayerSummary::top_keys`].
+pub const LAYER_TOP_KEYS: usize = 10;
+
+/// Per-tile feature counts and key frequencies of one layer.
+#[derive(Debug, Clone, Default)]
+pub struct LayerTileStats {
+    tiles: u64,
+    features: u64,
+    min_features: Option<u64>,
+    max_features: u64,
+    key_counts: HashMap<String, u64>,
+}
+
+impl LayerTileStats {
+    /// Record `tiles` tiles that each hold `features` features of the layer.
+    pub fn add_tiles(&mut self, features: u64, tiles: u64) {
+        self.tiles += tiles;
+        self.features += features * tiles;
+        self.min_features = Some(self.min_features.map_or(features, |min| min.min(features)));
+        self.max_features = self.max_features.max(features);
+    }
+
+    /// Record `count` features carrying `key`.
+    pub fn add_key(&mut self, key: &str, count: u64) {
+        match self.key_counts.get_mut(key) {
+            Some(total) => *total += count,
+            None => {
+                self.key_counts.insert(key.to_string(), count);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: LayerTileStats) {
+        self.tiles += other.tiles;
+        self.features += other.features;
+        if let Some(min) = other.min_features {
+            self.min_features = Some(self.min_features.map_or(min, |v| v.min(min)));
+        }
+        self.max_features = self.max_features.max(other.max_features);
+        for (key, count) in other.key_counts {
+            *self.key_counts.entry(key).or_insert(0) += count;
+        }
+    }
+
+    /// Fill the per-tile fields of `summary`.
+    pub fn apply(self, summary: &mut FileLayerSummary) {
+        summary.tiles_with_layer = Some(self.tiles);
+        summary.min_features_per_tile = self.min_features;
+        summary.max_features_per_tile = (self.tiles > 0).then_some(self.max_features);
+        summary.avg_features_per_tile =
+            (self.tiles > 0).then(|| self.features as f64 / self.tiles as f64);
+        let mut keys = self.key_counts.into_iter().collect::<Vec<_>>();
+        keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        keys.truncate(LAYER_TOP_KEYS);
+        summary.top_keys = keys;
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -107,6 +1174,327 @@ pub struct TileSummary {
     pub layers: Vec<LayerSummary>,
 }
 
+/// Relative accuracy of [`QuantileSketch`] estimates.
+const SKETCH_RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Streaming quantile sketch over tile sizes with logarithmic buckets, so any
+/// estimate is within 1% of a size actually present in the stream.
+/// Memory is bounded by the size range, not by the number of tiles.
+#[derive(Debug, Clone, Default)]
+pub struct QuantileSketch {
+    zero_count: u64,
+    buckets: BTreeMap<i32, u64>,
+    count: u64,
+}
+
+impl QuantileSketch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn gamma() -> f64 {
+        (1.0 + SKETCH_RELATIVE_ACCURACY) / (1.0 - SKETCH_RELATIVE_ACCURACY)
+    }
+
+    pub fn insert(&mut self, value: u64) {
+        self.count += 1;
+        if value == 0 {
+            self.zero_count += 1;
+            return;
+        }
+        let index = ((value as f64).ln() / Self::gamma().ln()).ceil() as i32;
+        *self.buckets.entry(index).or_insert(0) += 1;
+    }
+
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        self.count += other.count;
+        self.zero_count += other.zero_count;
+        for (index, count) in &other.buckets {
+            *self.buckets.entry(*index).or_insert(0) += count;
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Estimated size at percentile `pct` (nearest-rank), or `None` when empty.
+    pub fn percentile(&self, pct: u8) -> Option<u64> {
+        self.permille(u16::from(pct) * 10)
+    }
+
+    /// Like [`QuantileSketch::percentile`] with a per-mille rank (999 = p99.9).
+    pub fn permille(&self, permille: u16) -> Option<u64> {
+        let rank = permille_rank(permille, self.count)?;
+        if rank <= self.zero_count {
+            return Some(0);
+        }
+        let gamma = Self::gamma();
+        let mut seen = self.zero_count;
+        for (index, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                let value = 2.0 * gamma.powi(*index) / (gamma + 1.0);
+                return Some(value.round() as u64);
+            }
+        }
+        None
+    }
+}
+
+/// Tile sizes collected for percentile statistics: every size when the scan
+/// is complete, a [`QuantileSketch`] when sampling.
+#[derive(Debug, Clone)]
+pub enum SizeQuantiles {
+    Exact(Vec<u64>),
+    Sketch(QuantileSketch),
+}
+
+impl SizeQuantiles {
+    pub fn new(sampled: bool) -> Self {
+        if sampled {
+            Self::Sketch(QuantileSketch::new())
+        } else {
+            Self::Exact(Vec::new())
+        }
+    }
+
+    pub fn insert(&mut self, value: u64) {
+        match self {
+            Self::Exact(sizes) => sizes.push(value),
+            Self::Sketch(sketch) => sketch.insert(value),
+        }
+    }
+
+    pub fn merge(&mut self, other: &SizeQuantiles) {
+        match (&mut *self, other) {
+            (Self::Exact(sizes), Self::Exact(other)) => sizes.extend_from_slice(other),
+            (Self::Sketch(sketch), Self::Sketch(other)) => sketch.merge(other),
+            (Self::Sketch(sketch), Self::Exact(other)) => {
+                other.iter().for_each(|value| sketch.insert(*value));
+            }
+            (Self::Exact(sizes), Self::Sketch(other)) => {
+                let mut sketch = other.clone();
+                sizes.iter().for_each(|value| sketch.insert(*value));
+                *self = Self::Sketch(sketch);
+            }
+        }
+    }
+
+    /// p50/p90/p95/p99/p99.9, or `None` when no sizes were recorded.
+    pub fn percentiles(&mut self) -> Option<TilePercentiles> {
+        match self {
+            Self::Exact(sizes) => {
+                if sizes.is_empty() {
+                    return None;
+                }
+                sizes.sort_unstable();
+                let at = |permille: u16| {
+                    permille_rank(permille, sizes.len() as u64)
+                        .map(|rank| sizes[(rank - 1) as usize])
+                        .unwrap_or(0)
+                };
+                Some(TilePercentiles {
+                    p50: at(500),
+                    p90: at(900),
+                    p95: at(950),
+                    p99: at(990),
+                    p999: at(999),
+                })
+            }
+            Self::Sketch(sketch) => Some(TilePercentiles {
+                p50: sketch.permille(500)?,
+                p90: sketch.permille(900)?,
+                p95: sketch.permille(950)?,
+                p99: sketch.permille(990)?,
+                p999: sketch.permille(999)?,
+            }),
+        }
+    }
+}
+
+/// Identity of a tile payload for duplicate detection.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ContentKey {
+    /// Shared blob id of the MBTiles map/images (`tile_id`) or shallow
+    /// (`tile_data_id`) schema.
+    Id(String),
+    /// PMTiles tile data offset.
+    Offset(u64),
+    /// xxh3-128 digest of the raw tile bytes.
+    Digest(u128),
+}
+
+impl ContentKey {
+    pub fn digest(data: &[u8]) -> Self {
+        Self::Digest(xxhash_rust::xxh3::xxh3_128(data))
+    }
+}
+
+/// Failures listed by `--validate-mvt`; `invalid_tiles` counts all of them.
+pub const MVT_ERRORS_LISTED: usize = 100;
+
+/// A tile rejected by `--validate-mvt`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TileDecodeError {
+    pub zoom: u8,
+    pub x: u32,
+    pub y: u32,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MvtValidation {
+    pub invalid_tiles: u64,
+    /// The first [`MVT_ERRORS_LISTED`] failures in z/x/y order.
+    pub errors: Vec<TileDecodeError>,
+}
+
+impl MvtValidation {
+    pub fn record(&mut self, error: TileDecodeError) {
+        self.invalid_tiles += 1;
+        self.errors.push(error);
+        if self.errors.len() > MVT_ERRORS_LISTED * 2 {
+            self.truncate();
+        }
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        self.invalid_tiles += other.invalid_tiles;
+        self.errors.extend(other.errors);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        self.errors
+            .sort_by_key(|error| (error.zoom, error.x, error.y));
+        self.errors.truncate(MVT_ERRORS_LISTED);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContentHash {
+    pub algorithm: String,
+    pub tiles: u64,
+    pub digest: String,
+}
+
+/// Builds a [`ContentHash`]. Every tile adds a leaf `H("z/x/y:payload
+/// digest")`, with `y` in XYZ order, to a wrapping per-zoom sum, and the per-zoom sums are hashed in
+/// zoom order, so parallel scans in any order give the same digest.
+#[derive(Debug, Clone)]
+pub struct ContentHashAccum {
+    algorithm: TileHashAlgorithm,
+    zooms: BTreeMap<u8, (u64, u128)>,
+}
+
+impl ContentHashAccum {
+    pub fn new(algorithm: TileHashAlgorithm) -> Self {
+        Self {
+            algorithm,
+            zooms: BTreeMap::new(),
+        }
+    }
+
+    /// Hash a decompressed payload, add its leaf, and return the payload digest.
+    pub fn add_payload(&mut self, zoom: u8, x: u32, y: u32, payload: &[u8]) -> String {
+        let digest = self.algorithm.hex_digest(payload);
+        self.add(zoom, x, y, &digest);
+        digest
+    }
+
+    /// Add the leaf of a tile whose payload digest is already known.
+    pub fn add(&mut self, zoom: u8, x: u32, y: u32, digest: &str) {
+        let leaf = self
+            .algorithm
+            .digest(format!("{zoom}/{x}/{y}:{digest}").as_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&leaf[..16]);
+        let entry = self.zooms.entry(zoom).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 = entry.1.wrapping_add(u128::from_be_bytes(bytes));
+    }
+
+    pub fn merge(&mut self, other: &ContentHashAccum) {
+        for (zoom, (count, sum)) in &other.zooms {
+            let entry = self.zooms.entry(*zoom).or_insert((0, 0));
+            entry.0 += count;
+            entry.1 = entry.1.wrapping_add(*sum);
+        }
+    }
+
+    pub fn algorithm(&self) -> TileHashAlgorithm {
+        self.algorithm
+    }
+
+    pub fn finish(&self) -> ContentHash {
+        let summary = self
+            .zooms
+            .iter()
+            .map(|(zoom, (count, sum))| format!("{zoom} {count} {sum:032x}\n"))
+            .collect::<String>();
+        ContentHash {
+            algorithm: self.algorithm.name().to_string(),
+            tiles: self.zooms.values().map(|(count, _)| count).sum(),
+            digest: self.algorithm.hex_digest(summary.as_bytes()),
+        }
+    }
+}
+
+/// Counts how often each distinct tile payload occurs.
+///
+/// Tiles keyed by a shared blob id ([`ContentKey::Id`], [`ContentKey::Offset`])
+/// are counted from the id alone, so their duplicates are the tiles minus the
+/// distinct ids and no payload is read. Those duplicates already point at one
+/// stored copy and reclaim nothing; only [`ContentKey::Digest`] copies do.
+#[derive(Debug, Clone, Default)]
+pub struct DedupAccum {
+    contents: HashMap<ContentKey, (u64, u64)>,
+}
+
+impl DedupAccum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: ContentKey, length: u64) {
+        self.add(key, length, 1);
+    }
+
+    fn add(&mut self, key: ContentKey, length: u64, count: u64) {
+        self.contents.entry(key).or_insert((length, 0)).1 += count;
+    }
+
+    pub fn merge(&mut self, other: &DedupAccum) {
+        for (key, (length, count)) in &other.contents {
+            self.add(key.clone(), *length, *count);
+        }
+    }
+
+    pub fn stats(&self) -> DedupStats {
+        let mut stats = DedupStats {
+            unique_tiles: self.contents.len() as u64,
+            ..DedupStats::default()
+        };
+        for (key, (length, count)) in &self.contents {
+            let copies = count.saturating_sub(1);
+            stats.duplicate_tiles += copies;
+            if matches!(key, ContentKey::Digest(_)) {
+                stats.reclaimable_bytes += copies * length;
+            }
+        }
+        stats
+    }
+}
+
+/// 1-based nearest-rank position of the `permille`-th per-mille among `count` values.
+fn permille_rank(permille: u16, count: u64) -> Option<u64> {
+    if count == 0 {
+        return None;
+    }
+    Some((u64::from(permille) * count).div_ceil(1000).clamp(1, count))
+}
+
 pub fn finalize_stats(stats: &mut MbtilesStats) {
     if stats.tile_count == 0 {
         stats.avg_bytes = 0;
@@ -121,6 +1509,115 @@ pub struct PruneStats {
     pub removed_layers_by_zoom: BTreeMap<String, BTreeSet<u8>>,
     pub unknown_filters: usize,
     pub unknown_filters_by_layer: BTreeMap<String, u64>,
+    /// Polygon rings reversed by `--fix-winding`.
+    pub corrected_rings: u64,
+    /// Features dropped by `--drop-tiny-features`, by layer and zoom.
+    pub tiny_features_by_layer: BTreeMap<String, BTreeMap<u8, u64>>,
+    /// Points merged by `--merge-nearby-points`, by layer and zoom.
+    pub merged_points_by_layer: BTreeMap<String, BTreeMap<u8, u64>>,
+    /// Features that lost a property to `--prune-attributes`, by layer and key.
+    pub removed_keys_by_layer: BTreeMap<String, BTreeMap<String, u64>>,
+    /// Set when the output was written with content deduplication.
+    pub dedup: Option<TileDedupStats>,
+    /// Set when tiles were re-encoded with `--recompress`.
+    pub recompress: Option<RecompressStats>,
+    /// Tiles and stored bytes read and written per zoom.
+    pub tiles_by_zoom: BTreeMap<u8, ZoomTileCounts>,
+    /// Tiles skipped by `--clamp-zoom`.
+    pub clamped_tiles: u64,
+    /// Raster (PNG/JPEG/WebP) tiles copied through unchanged.
+    pub raster_tiles: u64,
+    /// Vector tiles that pruning left untouched, written with their input
+    /// bytes instead of being re-encoded and recompressed (MBTiles only).
+    pub unchanged_tiles: u64,
+    /// Input rows skipped because their tile address is invalid.
+    pub corrupt_rows: u64,
+}
+
+/// Tiles and stored bytes of one zoom before and after a rewrite.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ZoomTileCounts {
+    pub tiles_before: u64,
+    pub tiles_after: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl ZoomTileCounts {
+    /// Record one input tile; `output_bytes` is `None` when the tile was dropped.
+    pub fn record(&mut self, input_bytes: usize, output_bytes: Option<usize>) {
+        self.tiles_before += 1;
+        self.bytes_before += input_bytes as u64;
+        if let Some(output_bytes) = output_bytes {
+            self.tiles_after += 1;
+            self.bytes_after += output_bytes as u64;
+        }
+    }
+
+    pub fn merge(&mut self, other: ZoomTileCounts) {
+        self.tiles_before += other.tiles_before;
+        self.tiles_after += other.tiles_after;
+        self.bytes_before += other.bytes_before;
+        self.bytes_after += other.bytes_after;
+    }
+
+    /// Sum over all zooms.
+    pub fn total<'a>(counts: impl IntoIterator<Item = &'a ZoomTileCounts>) -> ZoomTileCounts {
+        let mut total = ZoomTileCounts::default();
+        for counts in counts {
+            total.merge(*counts);
+        }
+        total
+    }
+}
+
+fn merge_zoom_tile_counts(
+    target: &mut BTreeMap<u8, ZoomTileCounts>,
+    source: BTreeMap<u8, ZoomTileCounts>,
+) {
+    for (zoom, counts) in source {
+        target.entry(zoom).or_default().merge(counts);
+    }
+}
+
+/// Stored tile bytes read and written by a `--recompress` run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RecompressStats {
+    pub tiles: u64,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+}
+
+impl RecompressStats {
+    pub fn record(&mut self, input_bytes: usize, output_bytes: usize) {
+        self.tiles += 1;
+        self.input_bytes += input_bytes as u64;
+        self.output_bytes += output_bytes as u64;
+    }
+
+    pub fn merge(&mut self, other: RecompressStats) {
+        self.tiles += other.tiles;
+        self.input_bytes += other.input_bytes;
+        self.output_bytes += other.output_bytes;
+    }
+}
+
+/// Tile rows written versus distinct blobs stored by a deduplicated optimize.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TileDedupStats {
+    pub tiles: u64,
+    pub unique_blobs: u64,
+}
+
+impl TileDedupStats {
+    /// Tiles per stored blob (1.0 when nothing was shared).
+    pub fn ratio(&self) -> f64 {
+        if self.unique_blobs == 0 {
+            1.0
+        } else {
+            self.tiles as f64 / self.unique_blobs as f64
+        }
+    }
 }
 
 impl PruneStats {
@@ -138,6 +1635,40 @@ impl PruneStats {
             .insert(zoom);
     }
 
+    pub fn record_tiny_feature(&mut self, layer: &str, zoom: u8) {
+        *self
+            .tiny_features_by_layer
+            .entry(layer.to_string())
+            .or_default()
+            .entry(zoom)
+            .or_insert(0) += 1;
+    }
+
+    pub fn record_merged_point(&mut self, layer: &str, zoom: u8) {
+        *self
+            .merged_points_by_layer
+            .entry(layer.to_string())
+            .or_default()
+            .entry(zoom)
+            .or_insert(0) += 1;
+    }
+
+    pub fn record_removed_key(&mut self, layer: &str, key: &str) {
+        *self
+            .removed_keys_by_layer
+            .entry(layer.to_string())
+            .or_default()
+            .entry(key.to_string())
+            .or_insert(0) += 1;
+    }
+
+    pub fn record_tile(&mut self, zoom: u8, input_bytes: usize, output_bytes: Option<usize>) {
+        self.tiles_by_zoom
+            .entry(zoom)
+            .or_default()
+            .record(input_bytes, output_bytes);
+    }
+
     pub fn record_unknown_layer(&mut self, layer: &str) {
         *self
             .unknown_filters_by_layer
@@ -156,8 +1687,40 @@ impl PruneStats {
                 .extend(zooms);
         }
         self.unknown_filters += other.unknown_filters;
+        self.corrected_rings += other.corrected_rings;
+        self.clamped_tiles += other.clamped_tiles;
+        self.raster_tiles += other.raster_tiles;
+        self.unchanged_tiles += other.unchanged_tiles;
+        self.corrupt_rows += other.corrupt_rows;
+        if let Some(recompress) = other.recompress {
+            self.recompress.get_or_insert_default().merge(recompress);
+        }
+        merge_zoom_tile_counts(&mut self.tiles_by_zoom, other.tiles_by_zoom);
         for (layer, count) in other.unknown_filters_by_layer.into_iter() {
             *self.unknown_filters_by_layer.entry(layer).or_insert(0) += count;
         }
+        for (layer, keys) in other.removed_keys_by_layer {
+            let entry = self.removed_keys_by_layer.entry(layer).or_default();
+            for (key, count) in keys {
+                *entry.entry(key).or_insert(0) += count;
+            }
+        }
+        for (target, source) in [
+            (
+                &mut self.tiny_features_by_layer,
+                other.tiny_features_by_layer,
+            ),
+            (
+                &mut self.merged_points_by_layer,
+                other.merged_points_by_layer,
+            ),
+        ] {
+            for (layer, zooms) in source {
+                let entry = target.entry(layer).or_default();
+                for (zoom, count) in zooms {
+                    *entry.entry(zoom).or_insert(0) += count;
+                }
+            }
+        }
     }
 }
@@ -1,4 +1,10 @@
 use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::format::RasterFormat;
+use crate::style::{MapboxStyle, read_style_for_source};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TileCoord {
@@ -7,27 +13,116 @@ pub struct TileCoord {
     pub y: u32,
 }
 
+/// Table and column names of a nonstandard MBTiles tile table (for example
+/// `z`/`x`/`y`/`data`); the default is the standard `tiles` table. The row
+/// column is read as a TMS row, like `tile_row`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileTableNames {
+    pub table: String,
+    pub zoom: String,
+    pub column: String,
+    pub row: String,
+    pub data: String,
+}
+
+impl Default for TileTableNames {
+    fn default() -> Self {
+        Self {
+            table: "tiles".to_string(),
+            zoom: "zoom_level".to_string(),
+            column: "tile_column".to_string(),
+            row: "tile_row".to_string(),
+            data: "tile_data".to_string(),
+        }
+    }
+}
+
+impl TileTableNames {
+    pub fn is_standard(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SampleSpec {
     Ratio(f64),
     Count(u64),
+    /// The ratio applied within each zoom: every zoom contributes its share
+    /// of tiles (at least one), so the tile-rich high zooms do not crowd out
+    /// the low ones.
+    PerZoom(f64),
 }
 
 #[derive(Debug, Clone)]
 pub struct InspectOptions {
     pub sample: Option<SampleSpec>,
+    pub sample_seed: Option<u64>,
     pub topn: usize,
     pub histogram_buckets: usize,
     pub no_progress: bool,
     pub max_tile_bytes: u64,
     pub zoom: Option<u8>,
     pub bucket: Option<usize>,
-    pub tile: Option<TileCoord>,
+    /// Tiles summarized with `--summary` (`--tile`, repeatable).
+    pub tiles: Vec<TileCoord>,
     pub summary: bool,
     pub layers: Vec<String>,
     pub recommend: bool,
     pub include_layer_list: bool,
     pub list_tiles: Option<TileListOptions>,
+    pub check_geometry: bool,
+    /// Split layer bytes into geometry and attribute overhead (`--attributes-size`).
+    pub attributes_size: bool,
+    /// Attribute decompressed tile bytes to layers by their protobuf field
+    /// lengths (`--layer-bytes`).
+    pub layer_bytes: bool,
+    pub percentiles: bool,
+    pub dedup_stats: bool,
+    pub recommend_thresholds: RecommendThresholds,
+    /// Count features per layer and zoom (`--layer-zoom-matrix`).
+    pub layer_zoom_matrix: bool,
+    /// Property key whose value frequencies are reported per layer
+    /// (`--property-histogram`); requires `include_layer_list`.
+    pub property_histogram: Option<String>,
+    /// Flag property keys whose values are nearly all distinct
+    /// (`--detect-cardinality`); requires `include_layer_list`.
+    pub cardinality: Option<CardinalityOptions>,
+    /// Build size statistics on decoded tile sizes (`--measure uncompressed`).
+    pub uncompressed_sizes: bool,
+    /// Digest decompressed payloads for listed tiles and the whole tileset (`--hash`).
+    pub hash: Option<TileHashAlgorithm>,
+    /// Strictly decode every used tile and report the ones that fail
+    /// (`--validate-mvt`).
+    pub validate_mvt: bool,
+    /// Tiles of at most this many bytes count as empty (`--empty-threshold`).
+    pub empty_tile_max_bytes: u64,
+    /// Keep the tile sizes seen by the stats scan for up to this many tiles
+    /// and build the histograms from them instead of rescanning
+    /// (`--histogram-cache-max-tiles`).
+    pub histogram_cache_max_tiles: u64,
+    /// Only build the layer list (`--layers-only`): the size scan, histograms,
+    /// top tiles and summaries are skipped and their report sections left
+    /// empty.
+    pub layers_only: bool,
+    /// Count the scanned tiles per media type (`--media-types`).
+    pub media_types: bool,
+    /// Leave empty tiles (at most `empty_tile_max_bytes`) out of the
+    /// histograms and their size range (`--exclude-empty`); they are still
+    /// counted in `empty_tiles`.
+    pub histogram_exclude_empty: bool,
+    /// Decode the tiles of at most `empty_tile_max_bytes` and count only
+    /// those without features in `empty_tiles` (`--empty-exact`); the others
+    /// are reported as `small_tiles`.
+    pub empty_exact: bool,
+    /// Fail on rows whose tile address is invalid instead of skipping and
+    /// counting them in `corrupt_rows`.
+    pub strict: bool,
+    /// Style JSON whose keep/drop decisions are previewed against the layer
+    /// list (`--style`); requires `include_layer_list`, and per-zoom figures
+    /// need `layer_zoom_matrix` and `layer_bytes`.
+    pub style: Option<PathBuf>,
+    /// Vector source of `style` to preview; required when it has several.
+    pub style_source: Option<String>,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -35,18 +130,127 @@ impl Default for InspectOptions {
     fn default() -> Self {
         Self {
             sample: None,
+            sample_seed: None,
             topn: 0,
             histogram_buckets: 0,
             no_progress: false,
             max_tile_bytes: 0,
             zoom: None,
             bucket: None,
-            tile: None,
+            tiles: Vec::new(),
             summary: false,
             layers: Vec::new(),
             recommend: false,
             include_layer_list: false,
             list_tiles: None,
+            check_geometry: false,
+            attributes_size: false,
+            layer_bytes: false,
+            percentiles: false,
+            dedup_stats: false,
+            recommend_thresholds: RecommendThresholds::default(),
+            layer_zoom_matrix: false,
+            property_histogram: None,
+            cardinality: None,
+            uncompressed_sizes: false,
+            hash: None,
+            validate_mvt: false,
+            empty_tile_max_bytes: EMPTY_TILE_MAX_BYTES,
+            histogram_cache_max_tiles: DEFAULT_HISTOGRAM_CACHE_MAX_TILES,
+            layers_only: false,
+            media_types: false,
+            histogram_exclude_empty: false,
+            empty_exact: false,
+            strict: false,
+            style: None,
+            style_source: None,
+        }
+    }
+}
+
+impl InspectOptions {
+    /// Read `style`, restricted to `style_source`.
+    pub(crate) fn read_style(&self) -> Result<Option<MapboxStyle>> {
+        self.style
+            .as_deref()
+            .map(|path| read_style_for_source(path, self.style_source.as_deref()))
+            .transpose()
+    }
+
+    /// Tiles at or below this size are left out of the histograms.
+    pub(crate) fn histogram_floor(&self) -> Option<u64> {
+        self.histogram_exclude_empty
+            .then_some(self.empty_tile_max_bytes)
+    }
+
+    /// Options for a raster tileset: size statistics, histograms and top
+    /// tiles are kept, layer and feature analysis is dropped.
+    pub fn for_raster(mut self, format: RasterFormat) -> Result<Self> {
+        let vector_only = if self.summary {
+            Some("--summary")
+        } else if self.validate_mvt {
+            Some("--validate-mvt")
+        } else if self.layers_only {
+            Some("--layers-only")
+        } else {
+            None
+        };
+        if let Some(option) = vector_only {
+            anyhow::bail!(
+                "{option} requires vector tiles, but the input is a raster tileset ({})",
+                format.as_str()
+            );
+        }
+        self.include_layer_list = false;
+        self.layer_zoom_matrix = false;
+        self.property_histogram = None;
+        self.cardinality = None;
+        self.check_geometry = false;
+        self.recommend = false;
+        Ok(self)
+    }
+}
+
+/// Per-layer limits used by `--recommend`; averages are taken over the top tiles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecommendThresholds {
+    /// Minimum share of the top tiles' bytes for a layer to be reported.
+    pub min_bytes_share: f64,
+    /// Report layers averaging more encoded bytes per tile than this.
+    pub max_layer_bytes: Option<u64>,
+    /// Report layers averaging more features per tile than this.
+    pub max_layer_features: Option<u64>,
+}
+
+impl Default for RecommendThresholds {
+    fn default() -> Self {
+        Self {
+            min_bytes_share: 0.2,
+            max_layer_bytes: None,
+            max_layer_features: None,
+        }
+    }
+}
+
+/// Limits used by `--detect-cardinality`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CardinalityOptions {
+    /// Report keys whose distinct values per feature exceed this ratio.
+    pub min_distinct_ratio: f64,
+    /// Distinct values tracked exactly per layer and key; beyond it the
+    /// count is estimated from the smallest value hashes.
+    pub max_tracked_values: usize,
+    /// Keys on fewer features are never reported (a handful of features
+    /// always looks unique).
+    pub min_features: u64,
+}
+
+impl Default for CardinalityOptions {
+    fn default() -> Self {
+        Self {
+            min_distinct_ratio: 0.9,
+            max_tracked_values: 4096,
+            min_features: 100,
         }
     }
 }
@@ -61,9 +265,42 @@ pub enum TileSort {
 pub struct TileListOptions {
     pub limit: usize,
     pub sort: TileSort,
+    /// List the tiles whose size is in this range instead of a histogram
+    /// bucket (`--min-bytes`/`--max-bytes`).
+    pub size_range: Option<TileSizeRange>,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Inclusive tile size range in bytes, measured like the histograms
+/// (stored bytes, or decoded bytes with `--measure uncompressed`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileSizeRange {
+    pub min_bytes: u64,
+    pub max_bytes: u64,
+}
+
+impl TileSizeRange {
+    /// Range from optional bounds; a missing bound is open.
+    pub fn new(min_bytes: Option<u64>, max_bytes: Option<u64>) -> Result<Self> {
+        let range = Self {
+            min_bytes: min_bytes.unwrap_or(0),
+            max_bytes: max_bytes.unwrap_or(u64::MAX),
+        };
+        if range.min_bytes > range.max_bytes {
+            anyhow::bail!(
+                "--min-bytes {} is larger than --max-bytes {}",
+                range.min_bytes,
+                range.max_bytes
+            );
+        }
+        Ok(range)
+    }
+
+    pub fn contains(&self, bytes: u64) -> bool {
+        (self.min_bytes..=self.max_bytes).contains(&bytes)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PruneOptions {
     pub threads: usize,
     pub io_batch: u32,
@@ -72,17 +309,205 @@ pub struct PruneOptions {
     pub write_cache_mb: Option<u64>,
     pub drop_empty_tiles: bool,
     pub keep_unknown_filters: bool,
+    pub fix_winding: bool,
+    /// Gzip level (0-9) for re-encoded gzip tiles.
+    pub compression_level: u32,
+    /// Output tile codec; `None` keeps each input tile's compression.
+    pub recompress: Option<TileCompression>,
+    pub cleanup: FeatureCleanup,
+    /// Write the map/images schema with one `images` row per distinct tile.
+    pub dedup: bool,
+    /// Hide the stderr progress bar.
+    pub no_progress: bool,
+    /// Skip tiles at zooms no style layer is displayed at.
+    pub clamp_zoom: bool,
+    /// Insert into an existing output with the same schema (replacing tiles
+    /// at the same coordinates) instead of creating it.
+    pub append: bool,
+    /// Property keys kept per source layer (`--prune-attributes`); layers
+    /// that are not listed keep every key.
+    pub keep_keys: Option<HashMap<String, HashSet<String>>>,
+    /// Output name per source layer (`--rename-layer`); source layers
+    /// renamed to the same name are merged into one output layer.
+    pub layer_renames: HashMap<String, String>,
+    /// Leave `minzoom`/`maxzoom`/`bounds`/`center` as in the input instead
+    /// of fitting them to the written tiles.
+    pub keep_original_metadata: bool,
+    /// With `drop_empty_tiles`, also drop tiles whose output is at most this
+    /// many bytes (`--drop-empty-threshold`).
+    pub drop_empty_max_bytes: Option<u64>,
+    /// Row order of the output; anything but `None` stages the tiles in a
+    /// temporary table first (`--output-order`).
+    pub output_order: OutputOrder,
+    /// Fail on rows whose tile address is invalid instead of skipping and
+    /// counting them in `PruneStats::corrupt_rows` (`--strict`).
+    pub strict: bool,
+}
+
+/// Matches the `optimize` defaults: one thread and reader, batches of 1000
+/// tiles, unknown filters kept and the default gzip level.
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            io_batch: 1_000,
+            readers: 1,
+            read_cache_mb: None,
+            write_cache_mb: None,
+            drop_empty_tiles: false,
+            keep_unknown_filters: true,
+            fix_winding: false,
+            compression_level: super::DEFAULT_GZIP_LEVEL,
+            recompress: None,
+            cleanup: FeatureCleanup::default(),
+            dedup: false,
+            no_progress: false,
+            clamp_zoom: false,
+            append: false,
+            keep_keys: None,
+            layer_renames: HashMap::new(),
+            keep_original_metadata: false,
+            drop_empty_max_bytes: None,
+            output_order: OutputOrder::None,
+            strict: false,
+        }
+    }
+}
+
+/// Insertion order of the tile rows of an MBTiles output. Workers finish
+/// tiles in a different order on every run, so only an explicit order makes
+/// two runs over the same input write identical files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputOrder {
+    /// As the workers finish the tiles.
+    #[default]
+    None,
+    /// By `zoom_level`, `tile_column`, `tile_row`.
+    Zxy,
+    /// By PMTiles tile id: zoom first, then along the Hilbert curve.
+    Hilbert,
+}
+
+/// Optional feature cleanup applied by optimize after the style filter.
+/// Sizes are in pixels of a 256px tile and scaled by each layer's extent.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureCleanup {
+    /// Drop polygon features whose area is below this many square pixels.
+    pub drop_tiny_features: Option<f64>,
+    /// Merge point features closer than this many pixels (the first is kept).
+    pub merge_nearby_points: Option<f64>,
+    /// Source layers never cleaned up, in addition to the style's interactive layers.
+    pub exclude_layers: HashSet<String>,
+    /// How feature ids are written; applies to every layer.
+    pub feature_ids: FeatureIdMode,
+}
+
+/// Feature id handling for optimize (`--feature-ids`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeatureIdMode {
+    /// Copy ids from the input.
+    #[default]
+    Keep,
+    /// Write features without ids.
+    Drop,
+    /// Number the kept features of each layer 1, 2, ... in tile order.
+    Reindex,
+}
+
+impl FeatureCleanup {
+    pub fn is_enabled(&self) -> bool {
+        self.drop_tiny_features.is_some() || self.merge_nearby_points.is_some()
+    }
+}
+
+/// Tile selection and worker settings for batch simplify.
+#[derive(Debug, Clone)]
+pub struct SimplifyOptions {
+    pub layers: Vec<String>,
+    pub tolerance: Option<f64>,
+    /// Clamp buffer in units at extent 4096.
+    pub buffer: u32,
+    pub preserve_topology: bool,
+    /// Restrict to one zoom; `None` covers all zooms.
+    pub zoom: Option<u8>,
+    /// Only rewrite tiles whose stored size exceeds `max_tile_bytes`.
+    pub over_limit_only: bool,
+    pub max_tile_bytes: u64,
+    pub threads: usize,
+    pub readers: usize,
+    pub io_batch: u32,
+}
+
+impl SimplifyOptions {
+    pub fn matches(&self, zoom: u8, stored_bytes: u64) -> bool {
+        if self.zoom.is_some_and(|target| target != zoom) {
+            return false;
+        }
+        !self.over_limit_only || stored_bytes > self.max_tile_bytes
+    }
+}
+
+/// Highest zoom a tile address may have; MBTiles rows above it are corrupt
+/// and PMTiles tile ids beyond it are rejected.
+pub const MAX_TILE_ZOOM: u8 = 30;
+
+/// Highest zoom overzoom generates (the deepest zoom MapLibre renders).
+pub const MAX_OVERZOOM_TARGET_ZOOM: u8 = 24;
+
+/// Target zoom and worker settings for overzoom.
+#[derive(Debug, Clone)]
+pub struct OverzoomOptions {
+    /// Highest zoom to generate; tiles are added for every zoom between the
+    /// tileset's max zoom and this one.
+    pub target_zoom: u8,
+    /// Clip buffer around each generated tile, in units at extent 4096.
+    pub buffer: u32,
+    pub threads: usize,
+    pub readers: usize,
+    pub io_batch: u32,
+    pub no_progress: bool,
 }
 
+/// Default size in bytes at or below which inspect counts a tile as empty,
+/// shared by the MBTiles and PMTiles reports.
 pub const EMPTY_TILE_MAX_BYTES: u64 = 50;
 
+/// Default tile count up to which inspect keeps tile sizes in memory
+/// (8 bytes each) to build histograms without rescanning.
+pub const DEFAULT_HISTOGRAM_CACHE_MAX_TILES: u64 = 16_000_000;
+
+/// Seed used by `--sample` when `--sample-seed` is not given.
+pub const DEFAULT_SAMPLE_SEED: u64 = 0x5eed_f00d;
+
 pub struct PrunedTile {
     pub bytes: Vec<u8>,
     pub empty: bool,
+    /// Every layer and feature was kept under its own name with its ids,
+    /// properties and rings as-is, so the input tile can be written instead
+    /// of `bytes`.
+    pub unchanged: bool,
+}
+
+/// A tile generated by overzoom; `dx`/`dy` locate it within its parent
+/// (XYZ order, so `dy = 0` is the northern row).
+pub struct OverzoomedTile {
+    pub dx: u32,
+    pub dy: u32,
+    pub bytes: Vec<u8>,
 }
 
 pub fn parse_sample_spec(value: &str) -> Result<SampleSpec> {
     let trimmed = value.trim();
+    if let Some(ratio) = trimmed.strip_prefix("z:") {
+        let ratio: f64 = ratio
+            .trim()
+            .parse()
+            .context("invalid per-zoom sample ratio")?;
+        if ratio <= 0.0 || ratio > 1.0 {
+            anyhow::bail!("per-zoom sample ratio must be in (0, 1]");
+        }
+        return Ok(SampleSpec::PerZoom(ratio));
+    }
     let as_f64: f64 = trimmed.parse().context("invalid sample value")?;
     if as_f64 <= 0.0 {
         anyhow::bail!("sample must be greater than zero");
@@ -94,7 +519,180 @@ pub fn parse_sample_spec(value: &str) -> Result<SampleSpec> {
     Ok(SampleSpec::Count(as_u64))
 }
 
+/// Digest computed by `--hash` over decompressed tile payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileHashAlgorithm {
+    Sha256,
+    /// 128-bit xxh3.
+    Xxh3,
+}
+
+impl TileHashAlgorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            TileHashAlgorithm::Sha256 => "sha256",
+            TileHashAlgorithm::Xxh3 => "xxh3",
+        }
+    }
+
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            TileHashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            TileHashAlgorithm::Xxh3 => xxhash_rust::xxh3::xxh3_128(data).to_be_bytes().to_vec(),
+        }
+    }
+
+    pub fn hex_digest(self, data: &[u8]) -> String {
+        self.digest(data)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+/// Row convention of the MBTiles `tile_row` column. The MBTiles spec uses
+/// TMS (origin at the bottom left); some producers store XYZ rows instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TileScheme {
+    #[default]
+    Tms,
+    Xyz,
+}
+
+impl TileScheme {
+    /// Map an MBTiles `tile_row` to an XYZ `y` and back (the flip is its own inverse).
+    pub fn convert_row(self, zoom: u8, row: u32) -> u32 {
+        match self {
+            TileScheme::Tms => flip_y(zoom, row),
+            TileScheme::Xyz => row,
+        }
+    }
+}
+
+/// Flip a row between TMS and XYZ: `(1 << z) - 1 - y`.
+pub fn flip_y(zoom: u8, y: u32) -> u32 {
+    ((1u64 << zoom)
+        .saturating_sub(1)
+        .saturating_sub(u64::from(y))) as u32
+}
+
+/// Brotli quality used when none is requested.
+pub const DEFAULT_BROTLI_QUALITY: u32 = 5;
+
+/// Brotli window (log2 of the sliding window size) used when none is requested.
+pub const DEFAULT_BROTLI_WINDOW: u32 = 22;
+
+/// Tile codec and level written by `--recompress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileCompression {
+    None,
+    /// Gzip level 0-9.
+    Gzip(u32),
+    /// Brotli quality 0-11.
+    Brotli(u32),
+}
+
+impl TileCompression {
+    /// PMTiles `tile_compression` byte for this codec.
+    pub fn pmtiles_code(self) -> u8 {
+        match self {
+            TileCompression::None => 0,
+            TileCompression::Gzip(_) => 1,
+            TileCompression::Brotli(_) => 2,
+        }
+    }
+
+    /// Codec named by a PMTiles `tile_compression` byte; gzip uses `gzip_level`.
+    pub fn from_pmtiles_code(code: u8, gzip_level: u32) -> Result<Self> {
+        match code {
+            0 => Ok(TileCompression::None),
+            1 => Ok(TileCompression::Gzip(gzip_level)),
+            2 => Ok(TileCompression::Brotli(DEFAULT_BROTLI_QUALITY)),
+            other => anyhow::bail!("unsupported PMTiles tile compression: {other}"),
+        }
+    }
+}
+
+/// Parses `none`, `gzip[:0-9]`, or `brotli[:0-11]`.
+pub fn parse_tile_compression(value: &str) -> Result<TileCompression> {
+    let trimmed = value.trim();
+    let (codec, level) = match trimmed.split_once(':') {
+        Some((codec, level)) => (
+            codec,
+            Some(level.parse::<u32>().context("invalid recompress level")?),
+        ),
+        None => (trimmed, None),
+    };
+    match (codec, level) {
+        ("none", None) => Ok(TileCompression::None),
+        ("gzip", level) => {
+            let level = level.unwrap_or(crate::mbtiles::DEFAULT_GZIP_LEVEL);
+            if level > 9 {
+                anyhow::bail!("gzip level must be between 0 and 9");
+            }
+            Ok(TileCompression::Gzip(level))
+        }
+        ("brotli", level) => {
+            let quality = level.unwrap_or(DEFAULT_BROTLI_QUALITY);
+            if quality > 11 {
+                anyhow::bail!("brotli quality must be between 0 and 11");
+            }
+            Ok(TileCompression::Brotli(quality))
+        }
+        _ => anyhow::bail!("recompress must be none, gzip[:LEVEL], or brotli[:LEVEL]"),
+    }
+}
+
+/// Parses repeated `--rename-layer OLD=NEW` values into a source layer to
+/// output layer map.
+pub fn parse_layer_renames(values: &[String]) -> Result<HashMap<String, String>> {
+    let mut renames = HashMap::new();
+    for value in values {
+        let (old, new) = value
+            .split_once('=')
+            .with_context(|| format!("rename must be in OLD=NEW format: {value}"))?;
+        let (old, new) = (old.trim(), new.trim());
+        if old.is_empty() || new.is_empty() {
+            anyhow::bail!("rename must be in OLD=NEW format: {value}");
+        }
+        if let Some(previous) = renames.insert(old.to_string(), new.to_string())
+            && previous != new
+        {
+            anyhow::bail!("layer {old} is renamed to both {previous} and {new}");
+        }
+    }
+    Ok(renames)
+}
+
+/// Largest number of tiles a `--tile` range may expand to.
+pub const MAX_TILE_RANGE_TILES: u64 = 10_000;
+
 pub fn parse_tile_spec(value: &str) -> Result<TileCoord> {
+    let (zoom, x_str, y_str) = split_tile_spec(value)?;
+    let x = parse_tile_index(x_str, zoom, "x")?;
+    let y = parse_tile_index(y_str, zoom, "y")?;
+    Ok(TileCoord { zoom, x, y })
+}
+
+/// Parse a `--tile` value: a single z/x/y, or inclusive x/y ranges such as
+/// `14/8700-8705/5450-5455`. Tiles are returned row by row.
+pub fn parse_tile_range(value: &str) -> Result<Vec<TileCoord>> {
+    let (zoom, x_str, y_str) = split_tile_spec(value)?;
+    let (x_min, x_max) = parse_tile_index_range(x_str, zoom, "x")?;
+    let (y_min, y_max) = parse_tile_index_range(y_str, zoom, "y")?;
+    let count = (u64::from(x_max - x_min) + 1) * (u64::from(y_max - y_min) + 1);
+    if count > MAX_TILE_RANGE_TILES {
+        anyhow::bail!(
+            "tile range {} covers {count} tiles (at most {MAX_TILE_RANGE_TILES})",
+            value.trim()
+        );
+    }
+    Ok((y_min..=y_max)
+        .flat_map(|y| (x_min..=x_max).map(move |x| TileCoord { zoom, x, y }))
+        .collect())
+}
+
+fn split_tile_spec(value: &str) -> Result<(u8, &str, &str)> {
     let trimmed = value.trim();
     let mut parts = trimmed.split('/');
     let zoom_str = parts.next().context("tile must be in z/x/y format")?;
@@ -104,7 +702,92 @@ pub fn parse_tile_spec(value: &str) -> Result<TileCoord> {
         anyhow::bail!("tile must be in z/x/y format");
     }
     let zoom: u8 = zoom_str.parse().context("invalid tile zoom")?;
-    let x: u32 = x_str.parse().context("invalid tile x")?;
-    let y: u32 = y_str.parse().context("invalid tile y")?;
-    Ok(TileCoord { zoom, x, y })
+    if zoom > 32 {
+        anyhow::bail!("tile zoom must be at most 32");
+    }
+    Ok((zoom, x_str, y_str))
+}
+
+fn parse_tile_index(value: &str, zoom: u8, axis: &str) -> Result<u32> {
+    let index: u32 = value
+        .parse()
+        .with_context(|| format!("invalid tile {axis}"))?;
+    if u64::from(index) >= 1u64 << zoom {
+        anyhow::bail!(
+            "tile {axis} {index} is out of range for zoom {zoom} (0..{})",
+            (1u64 << zoom) - 1
+        );
+    }
+    Ok(index)
+}
+
+fn parse_tile_index_range(value: &str, zoom: u8, axis: &str) -> Result<(u32, u32)> {
+    let Some((start, end)) = value.split_once('-') else {
+        let index = parse_tile_index(value, zoom, axis)?;
+        return Ok((index, index));
+    };
+    let start = parse_tile_index(start, zoom, axis)?;
+    let end = parse_tile_index(end, zoom, axis)?;
+    if start > end {
+        anyhow::bail!("tile {axis} range {value} is reversed");
+    }
+    Ok((start, end))
+}
+
+/// Tile URL pattern from `--tile-url-template`, with `{z}`, `{x}`, `{y}`
+/// (XYZ row) and `{-y}` (TMS row) placeholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileUrlTemplate {
+    template: String,
+}
+
+impl TileUrlTemplate {
+    /// Expand for the tile at XYZ row `y`.
+    pub fn expand(&self, zoom: u8, x: u32, y: u32) -> String {
+        self.template
+            .replace("{z}", &zoom.to_string())
+            .replace("{x}", &x.to_string())
+            .replace("{-y}", &flip_y(zoom, y).to_string())
+            .replace("{y}", &y.to_string())
+    }
+
+    /// Expand for a tile whose `row` follows `scheme` (TMS for MBTiles
+    /// `tile_row`s, see [`crate::source::TileSource::row_scheme`]).
+    pub fn expand_row(&self, zoom: u8, x: u32, row: u32, scheme: TileScheme) -> String {
+        self.expand(zoom, x, scheme.convert_row(zoom, row))
+    }
+}
+
+pub fn parse_tile_url_template(value: &str) -> Result<TileUrlTemplate> {
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("unclosed placeholder in tile URL template: {value}"))?;
+        let placeholder = &rest[start..start + end + 1];
+        if !matches!(placeholder, "{z}" | "{x}" | "{y}" | "{-y}") {
+            anyhow::bail!(
+                "unknown placeholder {placeholder} in tile URL template (expected {{z}}, {{x}}, {{y}} or {{-y}})"
+            );
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(TileUrlTemplate {
+        template: value.to_string(),
+    })
+}
+
+/// Parse a `--tiles-from` list: one z/x/y per line, ignoring blank lines
+/// and `#` comments.
+pub fn parse_tile_list(text: &str) -> Result<Vec<TileCoord>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(index, line)| {
+            parse_tile_spec(line).with_context(|| format!("line {}: {}", index + 1, line.trim()))
+        })
+        .collect()
 }
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use mvt_reader::Reader;
+
+/// Decode `payload` as an MVT tile, rejecting what the lenient reader would
+/// silently skip: unknown or malformed geometry commands, truncated command
+/// parameters, and layers or features that fail to decode.
+pub fn validate_mvt(payload: &[u8]) -> Result<()> {
+    for_each_field(payload, |field, value| {
+        if let (3, FieldValue::Bytes(layer)) = (field, value) {
+            check_layer(layer)?;
+        }
+        Ok(())
+    })
+    .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+
+    let reader = Reader::new(payload.to_vec())
+        .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+    let layers = reader
+        .get_layer_metadata()
+        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+    for layer in layers {
+        reader
+            .get_features(layer.layer_index)
+            .map_err(|err| anyhow::anyhow!("layer {}: read features: {err}", layer.name))?;
+    }
+    Ok(())
+}
+
+fn check_layer(layer: &[u8]) -> Result<()> {
+    let mut name = String::new();
+    let mut features = Vec::new();
+    for_each_field(layer, |field, value| {
+        match (field, value) {
+            (1, FieldValue::Bytes(bytes)) => name = String::from_utf8_lossy(bytes).into_owned(),
+            (2, FieldValue::Bytes(bytes)) => features.push(bytes),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    for (index, feature) in features.into_iter().enumerate() {
+        check_feature(feature)
+            .map_err(|err| anyhow::anyhow!("layer {name} feature {index}: {err}"))?;
+    }
+    Ok(())
+}
+
+fn check_feature(feature: &[u8]) -> Result<()> {
+    let mut geometry = Vec::new();
+    for_each_field(feature, |field, value| {
+        match (field, value) {
+            (4, FieldValue::Bytes(packed)) => {
+                let mut pos = 0;
+                while pos < packed.len() {
+                    geometry.push(read_varint(packed, &mut pos)? as u32);
+                }
+            }
+            (4, FieldValue::Varint(value)) => geometry.push(value as u32),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    check_geometry_commands(&geometry)
+}
+
+/// Walks the command stream of one feature (MVT spec 4.3).
+fn check_geometry_commands(geometry: &[u32]) -> Result<()> {
+    let mut pos = 0;
+    while pos < geometry.len() {
+        let command = geometry[pos];
+        let (id, count) = (command & 0x7, (command >> 3) as usize);
+        pos += 1;
+        match id {
+            1 | 2 => {
+                let end = pos + count * 2;
+                if end > geometry.len() {
+                    let name = if id == 1 { "MoveTo" } else { "LineTo" };
+                    anyhow::bail!("truncated {name} parameters");
+                }
+                pos = end;
+            }
+            7 if count == 1 => {}
+            7 => anyhow::bail!("ClosePath with count {count}"),
+            _ => anyhow::bail!("unknown geometry command {id}"),
+        }
+    }
+    Ok(())
+}
+
+enum FieldValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Visit the top-level fields of a protobuf message.
+fn for_each_field<'a>(
+    buf: &'a [u8],
+    mut visit: impl FnMut(u32, FieldValue<'a>) -> Result<()>,
+) -> Result<()> {
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = read_varint(buf, &mut pos)?;
+        let field = (key >> 3) as u32;
+        let value = match key & 0x7 {
+            0 => FieldValue::Varint(read_varint(buf, &mut pos)?),
+            1 => {
+                skip(buf, &mut pos, 8)?;
+                FieldValue::Fixed
+            }
+            2 => {
+                let len = read_varint(buf, &mut pos)? as usize;
+                FieldValue::Bytes(skip(buf, &mut pos, len)?)
+            }
+            5 => {
+                skip(buf, &mut pos, 4)?;
+                FieldValue::Fixed
+            }
+            wire => anyhow::bail!("unsupported protobuf wire type {wire}"),
+        };
+        visit(field, value)?;
+    }
+    Ok(())
+}
+
+fn skip<'a>(buf: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = pos
+        .checked_add(len)
+        .filter(|end| *end <= buf.len())
+        .ok_or_else(|| anyhow::anyhow!("truncated protobuf field"))?;
+    let bytes = &buf[*pos..end];
+    *pos = end;
+    Ok(bytes)
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let Some(&byte) = buf.get(*pos) else {
+            anyhow::bail!("truncated varint");
+        };
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    anyhow::bail!("varint too long")
+}
@@ -0,0 +1,427 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+use super::{OutputOrder, TileDedupStats, TileOutput, TilesSchemaMode, flip_y};
+use crate::pmtiles::tile_id_from_xyz;
+
+/// Writes tiles to an output MBTiles, committing every `batch_size` rows.
+///
+/// Indexes are only built by [`MbtilesTileWriter::finalize`], once every row
+/// is loaded, which is much cheaper than maintaining them during the load.
+pub(super) struct MbtilesTileWriter<'a> {
+    conn: &'a Connection,
+    batch_size: u64,
+    pending: u64,
+    in_transaction: bool,
+    /// Appending to an existing output: tiles replace rows at the same
+    /// coordinates and metadata keys already present are kept.
+    append: bool,
+    stats: TileDedupStats,
+    /// `tiles_data_id` of every blob stored for a shallow output, by hash.
+    data_ids: HashMap<u128, i64>,
+    /// Tiles go to the `staged_tiles` temp table until `finalize` inserts
+    /// them in this order.
+    order: OutputOrder,
+}
+
+impl<'a> MbtilesTileWriter<'a> {
+    /// With `batch_size` of `None`, rows are only committed by explicit
+    /// [`flush`](Self::flush) calls (used by checkpointed runs).
+    pub(super) fn new(conn: &'a Connection, batch_size: Option<u64>) -> Self {
+        Self {
+            conn,
+            batch_size: batch_size.map_or(u64::MAX, |size| size.max(1)),
+            pending: 0,
+            in_transaction: false,
+            append: false,
+            stats: TileDedupStats::default(),
+            data_ids: HashMap::new(),
+            order: OutputOrder::None,
+        }
+    }
+
+    /// Stage every tile and insert them in `order` on
+    /// [`finalize`](Self::finalize). The staging table is kept in a temp
+    /// file (`temp_store = FILE`), so memory stays flat but the temp
+    /// directory needs room for about the whole output, and each tile is
+    /// written twice.
+    pub(super) fn order_rows(&mut self, order: OutputOrder) -> Result<()> {
+        if order == OutputOrder::None {
+            return Ok(());
+        }
+        self.conn
+            .execute_batch(
+                "
+                PRAGMA temp_store = FILE;
+                CREATE TEMP TABLE staged_tiles (
+                    sort_key INTEGER NOT NULL,
+                    zoom_level INTEGER NOT NULL,
+                    tile_column INTEGER NOT NULL,
+                    tile_row INTEGER NOT NULL,
+                    tile_id TEXT,
+                    tile_data BLOB NOT NULL
+                );
+                ",
+            )
+            .context("create tile staging table")?;
+        self.order = order;
+        Ok(())
+    }
+
+    /// Write into an output that already holds a `mode` tileset. The unique
+    /// indexes are built first so rows at existing coordinates are replaced.
+    pub(super) fn append_to(&mut self, mode: TilesSchemaMode) -> Result<()> {
+        create_output_indexes(self.conn, mode)?;
+        if mode == TilesSchemaMode::Shallow {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT tile_data_id, tile_data FROM tiles_data")
+                .context("prepare tiles_data scan")?;
+            let mut rows = stmt.query([]).context("query tiles_data")?;
+            while let Some(row) = rows.next().context("read tiles_data row")? {
+                let data_id: i64 = row.get(0)?;
+                let data: Vec<u8> = row.get(1)?;
+                self.data_ids
+                    .insert(xxhash_rust::xxh3::xxh3_128(&data), data_id);
+            }
+        }
+        self.append = true;
+        Ok(())
+    }
+
+    pub(super) fn write_metadata(&mut self, name: &str, value: &str) -> Result<()> {
+        self.begin()?;
+        let sql = if self.append {
+            "INSERT INTO metadata (name, value) SELECT ?1, ?2 WHERE NOT EXISTS (SELECT 1 FROM metadata WHERE name = ?1)"
+        } else {
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)"
+        };
+        self.conn
+            .execute(sql, (name, value))
+            .context("insert metadata")?;
+        Ok(())
+    }
+
+    /// Returns whether a new tile blob was stored (false when an identical
+    /// `images` row already existed). Staged tiles always return false.
+    pub(super) fn write(&mut self, output: TileOutput) -> Result<bool> {
+        self.begin()?;
+        let stored = if self.order == OutputOrder::None {
+            let stored = self.insert(output)?;
+            self.record(stored);
+            stored
+        } else {
+            self.stage(output)?;
+            false
+        };
+        self.pending += 1;
+        if self.pending >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(stored)
+    }
+
+    /// Commit every row written so far.
+    pub(super) fn flush(&mut self) -> Result<()> {
+        if self.in_transaction {
+            self.conn.execute_batch("COMMIT").context("commit output")?;
+            self.in_transaction = false;
+            self.pending = 0;
+        }
+        Ok(())
+    }
+
+    /// Commit, build the standard unique indexes for `mode`, and refresh the
+    /// query planner statistics.
+    pub(super) fn finalize(mut self, mode: TilesSchemaMode) -> Result<TileDedupStats> {
+        self.flush()?;
+        if self.order != OutputOrder::None {
+            self.insert_staged(mode)?;
+        }
+        if self.append {
+            remove_unreferenced_blobs(self.conn, mode)?;
+        }
+        create_output_indexes(self.conn, mode)?;
+        self.conn
+            .execute_batch("ANALYZE; PRAGMA optimize;")
+            .context("failed to analyze output")?;
+        Ok(self.stats)
+    }
+
+    fn record(&mut self, stored: bool) {
+        self.stats.tiles += 1;
+        self.stats.unique_blobs += u64::from(stored);
+    }
+
+    fn stage(&mut self, output: TileOutput) -> Result<()> {
+        let (zoom, x, y, tile_id, data) = match output {
+            TileOutput::Tiles { zoom, x, y, data }
+            | TileOutput::Shallow {
+                zoom, x, y, data, ..
+            } => (zoom, x, y, None, data),
+            TileOutput::MapImages {
+                zoom,
+                x,
+                y,
+                tile_id,
+                data,
+            } => (zoom, x, y, Some(tile_id), data),
+        };
+        // Tile ids stay below 2^63 up to z31
+        let sort_key = match self.order {
+            OutputOrder::Hilbert => tile_id_from_xyz(zoom, x, flip_y(zoom, y))? as i64,
+            OutputOrder::None | OutputOrder::Zxy => 0,
+        };
+        self.conn
+            .execute(
+                "INSERT INTO staged_tiles (sort_key, zoom_level, tile_column, tile_row, tile_id, tile_data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                (sort_key, zoom as i64, x as i64, y as i64, tile_id, data),
+            )
+            .context("stage tile")?;
+        Ok(())
+    }
+
+    /// Insert the staged tiles in order, in one transaction.
+    fn insert_staged(&mut self, mode: TilesSchemaMode) -> Result<()> {
+        let conn = self.conn;
+        let order_by = match self.order {
+            OutputOrder::Hilbert => "sort_key",
+            OutputOrder::None | OutputOrder::Zxy => "zoom_level, tile_column, tile_row",
+        };
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT zoom_level, tile_column, tile_row, tile_id, tile_data FROM staged_tiles ORDER BY {order_by}"
+            ))
+            .context("prepare staged tile scan")?;
+        self.begin()?;
+        let mut rows = stmt.query([]).context("query staged tiles")?;
+        while let Some(row) = rows.next().context("read staged tile")? {
+            let zoom: u8 = row.get(0)?;
+            let x: u32 = row.get(1)?;
+            let y: u32 = row.get(2)?;
+            let tile_id: Option<String> = row.get(3)?;
+            let data: Vec<u8> = row.get(4)?;
+            let output = match mode {
+                TilesSchemaMode::Tiles => TileOutput::Tiles { zoom, x, y, data },
+                TilesSchemaMode::MapImages => TileOutput::MapImages {
+                    zoom,
+                    x,
+                    y,
+                    tile_id: tile_id.unwrap_or_else(|| format!("{zoom}-{x}-{y}")),
+                    data,
+                },
+                TilesSchemaMode::Shallow => TileOutput::Shallow {
+                    zoom,
+                    x,
+                    y,
+                    hash: xxhash_rust::xxh3::xxh3_128(&data),
+                    data,
+                },
+            };
+            let stored = self.insert(output)?;
+            self.record(stored);
+        }
+        drop(rows);
+        drop(stmt);
+        self.flush()?;
+        conn.execute_batch("DROP TABLE staged_tiles")
+            .context("drop tile staging table")
+    }
+
+    fn begin(&mut self) -> Result<()> {
+        if !self.in_transaction {
+            self.conn
+                .execute_batch("BEGIN")
+                .context("begin output transaction")?;
+            self.in_transaction = true;
+        }
+        Ok(())
+    }
+
+    fn insert_verb(&self) -> &'static str {
+        if self.append {
+            "INSERT OR REPLACE"
+        } else {
+            "INSERT"
+        }
+    }
+
+    /// Returns whether a new tile blob was stored.
+    fn insert(&mut self, output: TileOutput) -> Result<bool> {
+        match output {
+            TileOutput::Tiles { zoom, x, y, data } => {
+                self.conn.execute(
+                    &format!("{} INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)", self.insert_verb()),
+                    (zoom as i64, x as i64, y as i64, data),
+                )
+                .context("insert tile")?;
+                Ok(true)
+            }
+            TileOutput::MapImages {
+                zoom,
+                x,
+                y,
+                tile_id,
+                data,
+            } => {
+                self.conn.execute(
+                    &format!("{} INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (?1, ?2, ?3, ?4)", self.insert_verb()),
+                    (zoom as i64, x as i64, y as i64, tile_id.clone()),
+                )
+                .context("insert map row")?;
+                // Appended `{z}-{x}-{y}` ids name an existing tile, whose bytes
+                // are replaced; an unchanged image (or a `--dedup` digest id)
+                // counts as already stored.
+                let sql = if self.append {
+                    "INSERT INTO images (tile_id, tile_data) VALUES (?1, ?2) ON CONFLICT (tile_id) DO UPDATE SET tile_data = excluded.tile_data WHERE images.tile_data IS NOT excluded.tile_data"
+                } else {
+                    "INSERT OR IGNORE INTO images (tile_id, tile_data) VALUES (?1, ?2)"
+                };
+                let inserted = self
+                    .conn
+                    .execute(sql, (tile_id, data))
+                    .context("insert image row")?;
+                Ok(inserted > 0)
+            }
+            TileOutput::Shallow {
+                zoom,
+                x,
+                y,
+                hash,
+                data,
+            } => {
+                let (data_id, stored) = match self.data_ids.get(&hash) {
+                    Some(&data_id) => (data_id, false),
+                    None => {
+                        self.conn
+                            .execute("INSERT INTO tiles_data (tile_data) VALUES (?1)", (data,))
+                            .context("insert tiles_data row")?;
+                        let data_id = self.conn.last_insert_rowid();
+                        self.data_ids.insert(hash, data_id);
+                        (data_id, true)
+                    }
+                };
+                self.conn
+                    .execute(
+                        &format!("{} INTO tiles_shallow (zoom_level, tile_column, tile_row, tile_data_id) VALUES (?1, ?2, ?3, ?4)", self.insert_verb()),
+                        (zoom as i64, x as i64, y as i64, data_id),
+                    )
+                    .context("insert tiles_shallow row")?;
+                Ok(stored)
+            }
+        }
+    }
+}
+
+/// Delete the blobs that appended tiles replaced and no row references any more.
+fn remove_unreferenced_blobs(conn: &Connection, mode: TilesSchemaMode) -> Result<()> {
+    let sql = match mode {
+        TilesSchemaMode::Tiles => return Ok(()),
+        TilesSchemaMode::MapImages => {
+            "DELETE FROM images WHERE tile_id NOT IN (SELECT tile_id FROM map WHERE tile_id IS NOT NULL)"
+        }
+        TilesSchemaMode::Shallow => {
+            "DELETE FROM tiles_data WHERE tile_data_id NOT IN (SELECT tile_data_id FROM tiles_shallow WHERE tile_data_id IS NOT NULL)"
+        }
+    };
+    conn.execute(sql, [])
+        .context("failed to remove unreferenced tile blobs")?;
+    Ok(())
+}
+
+/// The standard unique indexes for an output written in `mode`.
+fn create_output_indexes(conn: &Connection, mode: TilesSchemaMode) -> Result<()> {
+    let indexes = match mode {
+        TilesSchemaMode::Tiles => {
+            "CREATE UNIQUE INDEX IF NOT EXISTS tile_index ON tiles (zoom_level, tile_column, tile_row);"
+        }
+        TilesSchemaMode::MapImages => {
+            "
+            CREATE UNIQUE INDEX IF NOT EXISTS map_index ON map (zoom_level, tile_column, tile_row);
+            CREATE UNIQUE INDEX IF NOT EXISTS images_id ON images (tile_id);
+            "
+        }
+        TilesSchemaMode::Shallow => {
+            "CREATE UNIQUE INDEX IF NOT EXISTS tiles_shallow_index ON tiles_shallow (zoom_level, tile_column, tile_row);"
+        }
+    };
+    conn.execute_batch(indexes)
+        .context("failed to create output indexes")
+}
+
+/// Rewrite the committed `tiles` table of an output in `order`, for runs
+/// that could not stage their tiles (`--checkpoint`). The rows are copied
+/// into a fresh table in one transaction, so an interrupted reorder leaves
+/// the output as it was; the file needs room for a second copy of the rows
+/// until the next `VACUUM`.
+pub(super) fn reorder_tiles_table(conn: &Connection, order: OutputOrder) -> Result<()> {
+    if order == OutputOrder::None {
+        return Ok(());
+    }
+    conn.execute_batch(
+        "
+        BEGIN;
+        DROP INDEX IF EXISTS tile_index;
+        ALTER TABLE tiles RENAME TO tiles_unordered;
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .context("prepare tiles reorder")?;
+    let copied = match order {
+        OutputOrder::None | OutputOrder::Zxy => conn
+            .execute_batch(
+                "INSERT INTO tiles SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles_unordered ORDER BY zoom_level, tile_column, tile_row",
+            )
+            .context("copy tiles in z/x/y order"),
+        OutputOrder::Hilbert => copy_tiles_by_tile_id(conn),
+    };
+    if let Err(err) = copied {
+        let _ = conn.execute_batch("ROLLBACK");
+        return Err(err);
+    }
+    conn.execute_batch(
+        "
+        DROP TABLE tiles_unordered;
+        COMMIT;
+        ",
+    )
+    .context("finish tiles reorder")?;
+    create_output_indexes(conn, TilesSchemaMode::Tiles)?;
+    conn.execute_batch("ANALYZE; PRAGMA optimize;")
+        .context("failed to analyze output")
+}
+
+/// Copy `tiles_unordered` into `tiles` by PMTiles tile id; only the row
+/// keys (16 bytes per tile) are held in memory.
+fn copy_tiles_by_tile_id(conn: &Connection) -> Result<()> {
+    let mut keys = Vec::new();
+    {
+        let mut stmt = conn
+            .prepare("SELECT rowid, zoom_level, tile_column, tile_row FROM tiles_unordered")
+            .context("prepare tile key scan")?;
+        let mut rows = stmt.query([]).context("query tile keys")?;
+        while let Some(row) = rows.next().context("read tile key")? {
+            let rowid: i64 = row.get(0)?;
+            let zoom: u8 = row.get(1)?;
+            let x: u32 = row.get(2)?;
+            let y: u32 = row.get(3)?;
+            keys.push((tile_id_from_xyz(zoom, x, flip_y(zoom, y))?, rowid));
+        }
+    }
+    keys.sort_unstable();
+    let mut stmt = conn
+        .prepare(
+            "INSERT INTO tiles SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles_unordered WHERE rowid = ?1",
+        )
+        .context("prepare ordered tile copy")?;
+    for (_, rowid) in keys {
+        stmt.execute([rowid]).context("copy tile")?;
+    }
+    Ok(())
+}
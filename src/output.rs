@@ -1,17 +1,24 @@
 use anyhow::Result;
 use nu_ansi_term::Color;
-use serde_json::json;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::cli::{ReportFormat, TileInfoFormat};
+use crate::cli::{CsvSection, InspectReportFormat, TileInfoFormat};
+use crate::format::TileMediaType;
 use std::collections::BTreeMap;
 
 use crate::mbtiles::{
-    FileLayerSummary, HistogramBucket, MbtilesReport, MbtilesZoomStats, TileSummary, TopTile,
-    ZoomHistogram,
+    FileLayerSummary, HighCardinalityKey, HistogramBucket, MbtilesReport, MbtilesZoomStats,
+    MetadataMismatch, MetadataMismatchKind, PropertyHistogram, RecommendReason, Recommendation,
+    RecommendedAction, TileRecord, TileScheme, TileSummary, TileUrlTemplate, TopTile,
+    VectorLayerInfo, ZoomHistogram,
 };
 
 use std::collections::BTreeSet;
 
+use crate::style::StyleCheckReport;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum StatsSection {
     Metadata,
@@ -45,6 +52,16 @@ impl StatsFilter {
     pub fn includes(&self, section: StatsSection) -> bool {
         self.include_all || self.sections.contains(&section)
     }
+
+    /// Whether the layer list is requested and nothing else but metadata.
+    pub fn layers_only(&self) -> bool {
+        !self.include_all
+            && self.sections.contains(&StatsSection::Layers)
+            && self
+                .sections
+                .iter()
+                .all(|section| matches!(section, StatsSection::Layers | StatsSection::Metadata))
+    }
 }
 
 pub fn parse_stats_filter(value: Option<&str>) -> Result<StatsFilter> {
@@ -95,14 +112,27 @@ pub fn parse_stats_filter(value: Option<&str>) -> Result<StatsFilter> {
     })
 }
 
-pub fn resolve_output_format(requested: ReportFormat, ndjson_compact: bool) -> ReportFormat {
+pub fn resolve_output_format(
+    requested: InspectReportFormat,
+    ndjson_compact: bool,
+) -> InspectReportFormat {
     if ndjson_compact {
-        ReportFormat::Ndjson
+        InspectReportFormat::Ndjson
     } else {
         requested
     }
 }
 
+/// JSON report text: indented by default, a single minified line with
+/// `compact` (`--compact`).
+pub fn json_report_string<T: Serialize + ?Sized>(value: &T, compact: bool) -> Result<String> {
+    Ok(if compact {
+        serde_json::to_string(value)?
+    } else {
+        serde_json::to_string_pretty(value)?
+    })
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct NdjsonOptions {
     pub include_summary: bool,
@@ -111,7 +141,11 @@ pub struct NdjsonOptions {
 
 pub fn apply_tile_info_format(mut report: MbtilesReport, format: TileInfoFormat) -> MbtilesReport {
     if matches!(format, TileInfoFormat::Compact) {
-        if let Some(summary) = report.tile_summary.as_mut() {
+        for summary in report
+            .tile_summary
+            .iter_mut()
+            .chain(report.tile_summaries.iter_mut())
+        {
             for layer in summary.layers.iter_mut() {
                 layer.property_keys.clear();
             }
@@ -128,6 +162,8 @@ pub fn apply_tile_info_format(mut report: MbtilesReport, format: TileInfoFormat)
 pub fn apply_stats_filter(mut report: MbtilesReport, filter: &StatsFilter) -> MbtilesReport {
     if !filter.includes(StatsSection::Metadata) {
         report.metadata.clear();
+        report.vector_layers.clear();
+        report.metadata_mismatches.clear();
     }
     if !filter.includes(StatsSection::Summary) {
         report.overall.tile_count = 0;
@@ -135,11 +171,20 @@ pub fn apply_stats_filter(mut report: MbtilesReport, filter: &StatsFilter) -> Mb
         report.overall.max_bytes = 0;
         report.overall.avg_bytes = 0;
         report.empty_tiles = 0;
+        report.small_tiles = None;
         report.empty_ratio = 0.0;
         report.over_limit_tiles = 0;
+        report.over_limit_tiles_exact = None;
+        report.over_limit_tiles_sampled = None;
+        report.corrupt_rows = 0;
         report.sampled = false;
         report.sample_total_tiles = 0;
         report.sample_used_tiles = 0;
+        report.sample_seed = None;
+        report.dedup = None;
+        report.content_hash = None;
+        report.raster_tiles = 0;
+        report.media_types.clear();
     }
     if !filter.includes(StatsSection::Zoom) {
         report.by_zoom.clear();
@@ -152,9 +197,14 @@ pub fn apply_stats_filter(mut report: MbtilesReport, filter: &StatsFilter) -> Mb
     }
     if !filter.includes(StatsSection::Layers) {
         report.file_layers.clear();
+        report.layer_zoom_matrix.clear();
+        report.property_histograms.clear();
+        report.high_cardinality_keys.clear();
+        report.style_preview = None;
     }
     if !filter.includes(StatsSection::Recommendations) {
         report.recommended_buckets.clear();
+        report.recommendations.clear();
     }
     if !filter.includes(StatsSection::Bucket) {
         report.bucket_count = None;
@@ -167,6 +217,7 @@ pub fn apply_stats_filter(mut report: MbtilesReport, filter: &StatsFilter) -> Mb
     }
     if !filter.includes(StatsSection::TileSummary) {
         report.tile_summary = None;
+        report.tile_summaries.clear();
     }
     if !filter.includes(StatsSection::TopTileSummaries) {
         report.top_tile_summaries.clear();
@@ -174,23 +225,111 @@ pub fn apply_stats_filter(mut report: MbtilesReport, filter: &StatsFilter) -> Mb
     report
 }
 
+/// Fill [`TopTile::url`] for the top and bucket tiles from `template`;
+/// `rows` is the row convention of the report's tiles (TMS for MBTiles).
+pub fn apply_tile_url_template(
+    mut report: MbtilesReport,
+    template: &TileUrlTemplate,
+    rows: TileScheme,
+) -> MbtilesReport {
+    for tile in report
+        .top_tiles
+        .iter_mut()
+        .chain(report.bucket_tiles.iter_mut())
+    {
+        tile.url = Some(template.expand_row(tile.zoom, tile.x, tile.y, rows));
+    }
+    report
+}
+
+/// One NDJSON line for a tile streamed by `--list-tiles`.
+pub fn ndjson_tile_line(record: &TileRecord) -> Result<String> {
+    ndjson_tile_line_with_url(record, None)
+}
+
+/// Like [`ndjson_tile_line`], adding a `url` field when given.
+pub fn ndjson_tile_line_with_url(record: &TileRecord, url: Option<String>) -> Result<String> {
+    let mut line = json!({
+        "type": "tile",
+        "z": record.zoom,
+        "x": record.x,
+        "y": record.y,
+        "bytes": record.bytes,
+        "over_limit": record.over_limit,
+    });
+    if let Some(hash) = record.hash.as_ref() {
+        line["hash"] = json!(hash);
+    }
+    if let Some(url) = url {
+        line["url"] = json!(url);
+    }
+    Ok(serde_json::to_string(&line)?)
+}
+
 pub fn ndjson_lines(report: &MbtilesReport, mut options: NdjsonOptions) -> Result<Vec<String>> {
     if options.compact {
         options.include_summary = false;
     }
     let mut lines = Vec::new();
     if options.include_summary {
-        lines.push(serde_json::to_string(&json!({
+        let mut summary = json!({
             "type": "summary",
             "overall": report.overall,
             "by_zoom": report.by_zoom,
             "empty_tiles": report.empty_tiles,
             "empty_ratio": report.empty_ratio,
+            "empty_tile_max_bytes": report.empty_tile_max_bytes,
             "over_limit_tiles": report.over_limit_tiles,
             "sampled": report.sampled,
             "sample_total_tiles": report.sample_total_tiles,
             "sample_used_tiles": report.sample_used_tiles,
-        }))?);
+        });
+        if let Some(seed) = report.sample_seed {
+            summary["sample_seed"] = json!(seed);
+        }
+        if let Some(exact) = report.over_limit_tiles_exact {
+            summary["over_limit_tiles_exact"] = json!(exact);
+        }
+        if let Some(sampled) = report.over_limit_tiles_sampled {
+            summary["over_limit_tiles_sampled"] = json!(sampled);
+        }
+        if let Some(small_tiles) = report.small_tiles {
+            summary["small_tiles"] = json!(small_tiles);
+        }
+        if let Some(dedup) = report.dedup.as_ref() {
+            summary["dedup"] = serde_json::to_value(dedup)?;
+        }
+        if let Some(content_hash) = report.content_hash.as_ref() {
+            summary["content_hash"] = serde_json::to_value(content_hash)?;
+        }
+        if let Some(validation) = report.mvt_validation.as_ref() {
+            summary["invalid_mvt_tiles"] = json!(validation.invalid_tiles);
+        }
+        if let Some(format) = report.raster_format {
+            summary["raster_format"] = json!(format);
+        }
+        if report.raster_tiles > 0 {
+            summary["raster_tiles"] = json!(report.raster_tiles);
+        }
+        if report.corrupt_rows > 0 {
+            summary["corrupt_rows"] = json!(report.corrupt_rows);
+        }
+        if !report.media_types.is_empty() {
+            summary["media_types"] = json!(report.media_types);
+        }
+        lines.push(serde_json::to_string(&summary)?);
+    }
+
+    if let Some(validation) = report.mvt_validation.as_ref() {
+        for error in validation.errors.iter() {
+            lines.push(serde_json::to_string(&json!({
+                "type": "mvt_error",
+                "z": error.zoom,
+                "x": error.x,
+                "y": error.y,
+                "error": error.error,
+            }))?);
+        }
     }
 
     if !report.histogram.is_empty() {
@@ -227,6 +366,44 @@ pub fn ndjson_lines(report: &MbtilesReport, mut options: NdjsonOptions) -> Resul
         }
     }
 
+    for (layer, zooms) in report.layer_zoom_matrix.iter() {
+        lines.push(serde_json::to_string(&json!({
+            "type": "layer_zoom_matrix",
+            "layer": layer,
+            "zooms": zooms,
+        }))?);
+    }
+
+    for histogram in report.property_histograms.iter() {
+        lines.push(serde_json::to_string(&json!({
+            "type": "property_histogram",
+            "layer": histogram.layer,
+            "key": histogram.key,
+            "values": histogram.values,
+            "truncated": histogram.truncated,
+        }))?);
+    }
+
+    for mismatch in report.metadata_mismatches.iter() {
+        lines.push(serde_json::to_string(&json!({
+            "type": "metadata_mismatch",
+            "layer": mismatch.layer,
+            "kind": mismatch.kind,
+        }))?);
+    }
+
+    if let Some(preview) = report.style_preview.as_ref() {
+        let mut line = serde_json::to_value(preview)?;
+        line["type"] = json!("style_preview");
+        lines.push(serde_json::to_string(&line)?);
+    }
+
+    for key in report.high_cardinality_keys.iter() {
+        let mut line = serde_json::to_value(key)?;
+        line["type"] = json!("high_cardinality_key");
+        lines.push(serde_json::to_string(&line)?);
+    }
+
     if let Some(count) = report.bucket_count {
         lines.push(serde_json::to_string(&json!({
             "type": "bucket_count",
@@ -237,13 +414,20 @@ pub fn ndjson_lines(report: &MbtilesReport, mut options: NdjsonOptions) -> Resul
     if !report.bucket_tiles.is_empty() {
         for tile in report.bucket_tiles.iter() {
             if options.compact {
-                lines.push(serde_json::to_string(&json!({
+                let mut line = json!({
                     "type": "bucket_tile",
                     "z": tile.zoom,
                     "x": tile.x,
                     "y": tile.y,
                     "bytes": tile.bytes,
-                }))?);
+                });
+                if let Some(hash) = tile.hash.as_ref() {
+                    line["hash"] = json!(hash);
+                }
+                if let Some(url) = tile.url.as_ref() {
+                    line["url"] = json!(url);
+                }
+                lines.push(serde_json::to_string(&line)?);
             } else {
                 lines.push(serde_json::to_string(&json!({
                     "type": "bucket_tile",
@@ -256,13 +440,20 @@ pub fn ndjson_lines(report: &MbtilesReport, mut options: NdjsonOptions) -> Resul
     if !report.top_tiles.is_empty() {
         for tile in report.top_tiles.iter() {
             if options.compact {
-                lines.push(serde_json::to_string(&json!({
+                let mut line = json!({
                     "type": "top_tile",
                     "z": tile.zoom,
                     "x": tile.x,
                     "y": tile.y,
                     "bytes": tile.bytes,
-                }))?);
+                });
+                if let Some(hash) = tile.hash.as_ref() {
+                    line["hash"] = json!(hash);
+                }
+                if let Some(url) = tile.url.as_ref() {
+                    line["url"] = json!(url);
+                }
+                lines.push(serde_json::to_string(&line)?);
             } else {
                 lines.push(serde_json::to_string(&json!({
                     "type": "top_tile",
@@ -272,7 +463,7 @@ pub fn ndjson_lines(report: &MbtilesReport, mut options: NdjsonOptions) -> Resul
         }
     }
 
-    if let Some(summary) = report.tile_summary.as_ref() {
+    for summary in report.tile_summaries.iter() {
         if options.compact {
             lines.push(serde_json::to_string(&json!({
                 "type": "tile_summary",
@@ -303,6 +494,13 @@ pub fn ndjson_lines(report: &MbtilesReport, mut options: NdjsonOptions) -> Resul
         }))?);
     }
 
+    for recommendation in report.recommendations.iter() {
+        lines.push(serde_json::to_string(&json!({
+            "type": "recommendation",
+            "recommendation": recommendation,
+        }))?);
+    }
+
     if !report.top_tile_summaries.is_empty() {
         for summary in report.top_tile_summaries.iter() {
             if options.compact {
@@ -330,6 +528,217 @@ pub fn ndjson_lines(report: &MbtilesReport, mut options: NdjsonOptions) -> Resul
     Ok(lines)
 }
 
+const CSV_SECTIONS: [CsvSection; 4] = [
+    CsvSection::Zoom,
+    CsvSection::Histogram,
+    CsvSection::Layers,
+    CsvSection::TopTiles,
+];
+
+fn csv_section_name(section: CsvSection) -> &'static str {
+    match section {
+        CsvSection::Zoom => "zoom",
+        CsvSection::Histogram => "histogram",
+        CsvSection::Layers => "layers",
+        CsvSection::TopTiles => "top_tiles",
+    }
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn csv_opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Header and data rows of one CSV table. Numbers are written raw and the
+/// headers do not depend on the report contents.
+fn csv_table(report: &MbtilesReport, section: CsvSection) -> (Vec<&'static str>, Vec<Vec<String>>) {
+    match section {
+        CsvSection::Zoom => {
+            let header = vec![
+                "zoom",
+                "tile_count",
+                "total_bytes",
+                "max_bytes",
+                "avg_bytes",
+                "p50",
+                "p90",
+                "p95",
+                "p99",
+                "p999",
+            ];
+            let rows = report
+                .by_zoom
+                .iter()
+                .map(|item| {
+                    let stats = &item.stats;
+                    let pct = stats.percentiles;
+                    vec![
+                        item.zoom.to_string(),
+                        stats.tile_count.to_string(),
+                        stats.total_bytes.to_string(),
+                        stats.max_bytes.to_string(),
+                        stats.avg_bytes.to_string(),
+                        csv_opt(pct.map(|p| p.p50)),
+                        csv_opt(pct.map(|p| p.p90)),
+                        csv_opt(pct.map(|p| p.p95)),
+                        csv_opt(pct.map(|p| p.p99)),
+                        csv_opt(pct.map(|p| p.p999)),
+                    ]
+                })
+                .collect();
+            (header, rows)
+        }
+        CsvSection::Histogram => {
+            let header = vec![
+                "zoom",
+                "bucket",
+                "min_bytes",
+                "max_bytes",
+                "count",
+                "total_bytes",
+                "running_avg_bytes",
+                "pct_tiles",
+                "pct_level_bytes",
+                "accum_pct_tiles",
+                "accum_pct_level_bytes",
+                "avg_near_limit",
+                "avg_over_limit",
+            ];
+            // Overall buckets have an empty zoom column.
+            let tables = std::iter::once((None, &report.histogram)).chain(
+                report
+                    .histograms_by_zoom
+                    .iter()
+                    .map(|item| (Some(item.zoom), &item.buckets)),
+            );
+            let mut rows = Vec::new();
+            for (zoom, buckets) in tables {
+                for (idx, bucket) in buckets.iter().enumerate() {
+                    rows.push(vec![
+                        csv_opt(zoom),
+                        idx.to_string(),
+                        bucket.min_bytes.to_string(),
+                        bucket.max_bytes.to_string(),
+                        bucket.count.to_string(),
+                        bucket.total_bytes.to_string(),
+                        bucket.running_avg_bytes.to_string(),
+                        bucket.pct_tiles.to_string(),
+                        bucket.pct_level_bytes.to_string(),
+                        bucket.accum_pct_tiles.to_string(),
+                        bucket.accum_pct_level_bytes.to_string(),
+                        bucket.avg_near_limit.to_string(),
+                        bucket.avg_over_limit.to_string(),
+                    ]);
+                }
+            }
+            (header, rows)
+        }
+        CsvSection::Layers => {
+            let header = vec![
+                "name",
+                "vertex_count",
+                "feature_count",
+                "property_key_count",
+                "property_value_count",
+                "invalid_geometry_count",
+                "geometry_bytes",
+                "attribute_bytes",
+                "approx_bytes_field_length",
+                "pct_of_total",
+            ];
+            let rows = report
+                .file_layers
+                .iter()
+                .map(|layer| {
+                    vec![
+                        layer.name.clone(),
+                        layer.vertex_count.to_string(),
+                        layer.feature_count.to_string(),
+                        layer.property_key_count.to_string(),
+                        layer.property_value_count.to_string(),
+                        csv_opt(layer.invalid_geometry_count),
+                        csv_opt(layer.geometry_bytes),
+                        csv_opt(layer.attribute_bytes),
+                        csv_opt(layer.approx_bytes_field_length),
+                        csv_opt(layer.pct_of_total.map(|pct| format!("{pct:.2}"))),
+                    ]
+                })
+                .collect();
+            (header, rows)
+        }
+        CsvSection::TopTiles => {
+            let header = vec!["zoom", "x", "y", "bytes"];
+            let rows = report
+                .top_tiles
+                .iter()
+                .map(|tile| {
+                    vec![
+                        tile.zoom.to_string(),
+                        tile.x.to_string(),
+                        tile.y.to_string(),
+                        tile.bytes.to_string(),
+                    ]
+                })
+                .collect();
+            (header, rows)
+        }
+    }
+}
+
+/// Render the report as CSV.
+///
+/// With `section`, only that table is written (header always present).
+/// Otherwise every non-empty table is written as its own block, separated by
+/// a blank line, with a leading `section` column naming the table.
+pub fn csv_lines(report: &MbtilesReport, section: Option<CsvSection>) -> Vec<String> {
+    if let Some(section) = section {
+        let (header, rows) = csv_table(report, section);
+        let header = header
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        return std::iter::once(csv_row(&header))
+            .chain(rows.iter().map(|row| csv_row(row)))
+            .collect();
+    }
+    let mut lines = Vec::new();
+    for section in CSV_SECTIONS {
+        let (header, rows) = csv_table(report, section);
+        if rows.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        let name = csv_section_name(section).to_string();
+        let mut header_row = vec!["section".to_string()];
+        header_row.extend(header.iter().map(|name| name.to_string()));
+        lines.push(csv_row(&header_row));
+        for row in rows {
+            let mut fields = vec![name.clone()];
+            fields.extend(row);
+            lines.push(csv_row(&fields));
+        }
+    }
+    lines
+}
+
 pub fn format_histogram_table(buckets: &[HistogramBucket]) -> Vec<String> {
     if buckets.is_empty() {
         return Vec::new();
@@ -449,9 +858,32 @@ pub fn format_zoom_table(
             (bytes as f64 / total_bytes as f64) * 100.0
         }
     };
+    let show_percentiles = items.iter().any(|item| item.stats.percentiles.is_some());
+    let percentile_cells = |item: &MbtilesZoomStats| {
+        item.stats
+            .percentiles
+            .map(|p| [p.p50, p.p90, p.p95, p.p99, p.p999].map(format_bytes))
+            .unwrap_or_else(|| std::array::from_fn(|_| "-".to_string()))
+    };
+    let percentile_widths = items.iter().fold([5usize; 5], |mut widths, item| {
+        for (width, cell) in widths.iter_mut().zip(percentile_cells(item)) {
+            *width = (*width).max(cell.len());
+        }
+        widths
+    });
+    let percentile_suffix = |cells: [String; 5]| {
+        if !show_percentiles {
+            return String::new();
+        }
+        cells
+            .iter()
+            .zip(percentile_widths)
+            .map(|(cell, width)| format!(" {}", pad_left(cell, width)))
+            .collect::<String>()
+    };
     let mut lines = Vec::with_capacity(items.len() + 1);
     lines.push(format!(
-        "  {} {} {} {} {} {} {} {} {}",
+        "  {} {} {} {} {} {} {} {} {}{}",
         pad_right("zoom", zoom_width),
         pad_left("tiles", tiles_width),
         pad_left("total", total_width),
@@ -461,6 +893,7 @@ pub fn format_zoom_table(
         pad_left("%size", 8),
         pad_left("acc%tiles", 10),
         pad_left("acc%size", 10),
+        percentile_suffix(["p50", "p90", "p95", "p99", "p99.9"].map(String::from)),
     ));
     let mut acc_tiles = 0u64;
     let mut acc_bytes = 0u64;
@@ -468,7 +901,7 @@ pub fn format_zoom_table(
         acc_tiles = acc_tiles.saturating_add(item.stats.tile_count);
         acc_bytes = acc_bytes.saturating_add(item.stats.total_bytes);
         lines.push(format!(
-            "  {} {} {} {} {} {:>7.2}% {:>7.2}% {:>9.2}% {:>9.2}%",
+            "  {} {} {} {} {} {:>7.2}% {:>7.2}% {:>9.2}% {:>9.2}%{}",
             pad_right(&item.zoom.to_string(), zoom_width),
             pad_left(&item.stats.tile_count.to_string(), tiles_width),
             pad_left(&format_bytes(item.stats.total_bytes), total_width),
@@ -478,22 +911,98 @@ pub fn format_zoom_table(
             pct_bytes(item.stats.total_bytes),
             pct_tiles(acc_tiles),
             pct_bytes(acc_bytes),
+            percentile_suffix(percentile_cells(&item)),
         ));
     }
     lines
 }
 
+const SPARKLINE_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Per-zoom total bytes as one block character per zoom, scaled to the
+/// largest zoom. Zoom levels between the lowest and highest present zoom that
+/// have no tiles are rendered as a space.
+pub fn format_zoom_sparkline(stats: &[MbtilesZoomStats]) -> String {
+    let (Some(min_zoom), Some(max_zoom)) = (
+        stats.iter().map(|item| item.zoom).min(),
+        stats.iter().map(|item| item.zoom).max(),
+    ) else {
+        return String::new();
+    };
+    let max_bytes = stats
+        .iter()
+        .map(|item| item.stats.total_bytes)
+        .max()
+        .unwrap_or(0);
+    (min_zoom..=max_zoom)
+        .map(|zoom| {
+            let Some(item) = stats.iter().find(|item| item.zoom == zoom) else {
+                return ' ';
+            };
+            if max_bytes == 0 {
+                return SPARKLINE_BLOCKS[0];
+            }
+            let level = (item.stats.total_bytes as f64 / max_bytes as f64
+                * SPARKLINE_BLOCKS.len() as f64)
+                .ceil() as usize;
+            SPARKLINE_BLOCKS[level.clamp(1, SPARKLINE_BLOCKS.len()) - 1]
+        })
+        .collect()
+}
+
+/// One-line description of a [`Recommendation`] for the text report.
+pub fn format_recommendation(recommendation: &Recommendation) -> String {
+    let action = match recommendation.action {
+        RecommendedAction::Simplify { tolerance } => {
+            format!("consider simplify tolerance {tolerance:.1}")
+        }
+        RecommendedAction::RaiseMinzoom { minzoom } => {
+            format!("consider raising its minzoom to {minzoom}")
+        }
+    };
+    let finding = match recommendation.reason {
+        RecommendReason::BytesShare => format!(
+            "contributes {:.0}% of bytes ({:.0}% of vertices)",
+            recommendation.bytes_share * 100.0,
+            recommendation.vertices_share * 100.0
+        ),
+        RecommendReason::LayerBytes => format!(
+            "averages {} per tile",
+            format_bytes(recommendation.avg_bytes)
+        ),
+        RecommendReason::FeatureCount => {
+            format!("averages {} features per tile", recommendation.avg_features)
+        }
+        RecommendReason::MostVertices => format!(
+            "has the most vertices ({:.0}%)",
+            recommendation.vertices_share * 100.0
+        ),
+    };
+    format!(
+        "layer `{}` {} at z{} — {} (est. -{})",
+        recommendation.layer,
+        finding,
+        recommendation.zoom,
+        action,
+        format_bytes(recommendation.estimated_savings_bytes)
+    )
+}
+
 pub fn format_top_tiles_lines(tiles: &[TopTile]) -> Vec<String> {
     tiles
         .iter()
         .map(|tile| {
-            format!(
+            let line = format!(
                 "-z {} -x {} -y {} size={}",
                 tile.zoom,
                 tile.x,
                 tile.y,
                 format_bytes(tile.bytes)
-            )
+            );
+            match tile.url.as_ref() {
+                Some(url) => format!("{line} {url}"),
+                None => line,
+            }
         })
         .collect()
 }
@@ -593,6 +1102,203 @@ pub fn format_histograms_by_zoom_section(histograms: &[ZoomHistogram]) -> Vec<St
     lines
 }
 
+/// Layers × zoom table of feature counts; `-` marks zooms where a layer is absent.
+pub fn format_layer_zoom_matrix(matrix: &[(String, Vec<(u8, u64)>)]) -> Vec<String> {
+    if matrix.is_empty() {
+        return Vec::new();
+    }
+    let zooms = matrix
+        .iter()
+        .flat_map(|(_, counts)| counts.iter().map(|(zoom, _)| *zoom))
+        .collect::<BTreeSet<_>>();
+    let name_width = matrix
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("name".len());
+    let cells = matrix
+        .iter()
+        .map(|(_, counts)| {
+            let counts = counts.iter().copied().collect::<BTreeMap<u8, u64>>();
+            zooms
+                .iter()
+                .map(|zoom| {
+                    counts
+                        .get(zoom)
+                        .map_or_else(|| "-".to_string(), |count| count.to_string())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let widths = zooms
+        .iter()
+        .enumerate()
+        .map(|(idx, zoom)| {
+            cells
+                .iter()
+                .map(|row| row[idx].len())
+                .max()
+                .unwrap_or(0)
+                .max(format!("z{zoom}").len())
+        })
+        .collect::<Vec<_>>();
+    let mut header = format!("  {}", pad_right("name", name_width));
+    for (zoom, width) in zooms.iter().zip(widths.iter()) {
+        header.push(' ');
+        header.push_str(&pad_left(&format!("z{zoom}"), *width));
+    }
+    let mut lines = vec![header];
+    for ((name, _), row) in matrix.iter().zip(cells.iter()) {
+        let mut line = format!("  {}", pad_right(name, name_width));
+        for (cell, width) in row.iter().zip(widths.iter()) {
+            line.push(' ');
+            line.push_str(&pad_left(cell, *width));
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Value/count table of one `--property-histogram` layer, most frequent first.
+pub fn format_property_histogram(histogram: &PropertyHistogram) -> Vec<String> {
+    let value_width = histogram
+        .values
+        .iter()
+        .map(|item| item.value.len())
+        .max()
+        .unwrap_or(0)
+        .max("value".len());
+    let count_width = histogram
+        .values
+        .iter()
+        .map(|item| item.count.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("count".len());
+    let mut lines = vec![format!(
+        "  {} {}",
+        pad_right("value", value_width),
+        pad_left("count", count_width)
+    )];
+    for item in histogram.values.iter() {
+        lines.push(format!(
+            "  {} {}",
+            pad_right(&item.value, value_width),
+            pad_left(&item.count.to_string(), count_width)
+        ));
+    }
+    lines
+}
+
+/// Table of the keys flagged by `--detect-cardinality`, largest first;
+/// estimated distinct counts are prefixed with `~`.
+pub fn format_high_cardinality_keys(keys: &[HighCardinalityKey]) -> Vec<String> {
+    let rows = keys
+        .iter()
+        .map(|key| {
+            [
+                format!("{}.{}", key.layer, key.key),
+                key.feature_count.to_string(),
+                format!(
+                    "{}{}",
+                    if key.estimated { "~" } else { "" },
+                    key.distinct_values
+                ),
+                format!("{:.2}", key.distinct_ratio),
+                format_bytes(key.estimated_bytes),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let headers = ["layer.key", "features", "distinct", "ratio", "~bytes"];
+    let widths = headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| {
+            rows.iter()
+                .map(|row| row[index].len())
+                .max()
+                .unwrap_or(0)
+                .max(header.len())
+        })
+        .collect::<Vec<_>>();
+    let format_row = |cells: [&str; 5]| {
+        let mut line = format!("  {}", pad_right(cells[0], widths[0]));
+        for (cell, width) in cells.iter().zip(widths.iter()).skip(1) {
+            line.push(' ');
+            line.push_str(&pad_left(cell, *width));
+        }
+        line
+    };
+    let mut lines = vec![format_row(headers)];
+    for row in rows.iter() {
+        lines.push(format_row([&row[0], &row[1], &row[2], &row[3], &row[4]]));
+    }
+    lines
+}
+
+/// Table of the metadata `vector_layers`: id, min/max zoom, field count and
+/// description (`-` when absent).
+pub fn format_vector_layers_table(layers: &[VectorLayerInfo]) -> Vec<String> {
+    let zoom = |zoom: Option<u8>| zoom.map_or_else(|| "-".to_string(), |zoom| zoom.to_string());
+    let rows = layers
+        .iter()
+        .map(|layer| {
+            [
+                layer.id.clone(),
+                zoom(layer.minzoom),
+                zoom(layer.maxzoom),
+                layer.fields.len().to_string(),
+                layer.description.clone().unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let headers = ["id", "minzoom", "maxzoom", "fields", "description"];
+    let widths = headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| {
+            rows.iter()
+                .map(|row| row[index].len())
+                .max()
+                .unwrap_or(0)
+                .max(header.len())
+        })
+        .collect::<Vec<_>>();
+    let format_row = |cells: [&str; 5]| {
+        format!(
+            "  {} {} {} {} {}",
+            pad_right(cells[0], widths[0]),
+            pad_left(cells[1], widths[1]),
+            pad_left(cells[2], widths[2]),
+            pad_left(cells[3], widths[3]),
+            cells[4]
+        )
+    };
+    let mut lines = vec![format_row(headers)];
+    for row in rows.iter() {
+        lines.push(format_row([&row[0], &row[1], &row[2], &row[3], &row[4]]));
+    }
+    lines
+}
+
+/// One warning line per `vector_layers` mismatch.
+pub fn format_metadata_mismatches(mismatches: &[MetadataMismatch]) -> Vec<String> {
+    mismatches
+        .iter()
+        .map(|mismatch| match mismatch.kind {
+            MetadataMismatchKind::DeclaredNotInTiles => format!(
+                "- warning: layer {} is declared in vector_layers but not found in the tiles",
+                mismatch.layer
+            ),
+            MetadataMismatchKind::InTilesNotDeclared => format!(
+                "- warning: layer {} is in the tiles but not declared in vector_layers",
+                mismatch.layer
+            ),
+        })
+        .collect()
+}
+
 pub fn format_metadata_section(metadata: &BTreeMap<String, String>) -> Vec<String> {
     if metadata.is_empty() {
         return Vec::new();
@@ -608,6 +1314,15 @@ pub fn format_metadata_section(metadata: &BTreeMap<String, String>) -> Vec<Strin
     lines
 }
 
+/// Media type counts as `mvt=120, png=3`, in media type order.
+pub fn format_media_types(counts: &BTreeMap<TileMediaType, u64>) -> String {
+    counts
+        .iter()
+        .map(|(media_type, count)| format!("{}={count}", media_type.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 pub fn format_bytes(bytes: u64) -> String {
     const KB: f64 = 1024.0;
     const MB: f64 = 1024.0 * 1024.0;
@@ -628,3 +1343,652 @@ pub fn pad_right(value: &str, width: usize) -> String {
 pub fn pad_left(value: &str, width: usize) -> String {
     format!("{:>width$}", value, width = width)
 }
+
+/// Compact zoom list such as `z0-z5, z8`.
+pub fn format_zoom_ranges(zooms: &[u8]) -> String {
+    let mut ranges: Vec<(u8, u8)> = Vec::new();
+    for &zoom in zooms {
+        match ranges.last_mut() {
+            Some((_, end)) if end.checked_add(1) == Some(zoom) => *end = zoom,
+            _ => ranges.push((zoom, zoom)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                format!("z{start}")
+            } else {
+                format!("z{start}-z{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn format_style_check_lines(report: &StyleCheckReport) -> Vec<String> {
+    let filtered = report
+        .layers
+        .iter()
+        .filter(|layer| layer.has_filter)
+        .count();
+    let mut lines = vec![format!(
+        "filters: layers={} filtered={} supported={} unsupported={}",
+        report.layers.len(),
+        filtered,
+        filtered - report.unsupported_layers(),
+        report.unsupported_layers()
+    )];
+    for layer in report.layers.iter().filter(|layer| !layer.filter.supported) {
+        lines.push(format!(
+            "- unsupported filter in layer `{}` (source-layer {}): {}",
+            layer.id,
+            layer.source_layer,
+            layer.filter.unknown_ops.join(", ")
+        ));
+    }
+    lines.push("source-layers:".to_string());
+    for coverage in report.source_layers.iter() {
+        let zooms = if coverage.kept_zooms.is_empty() {
+            "removed at every zoom".to_string()
+        } else {
+            format!("kept at {}", format_zoom_ranges(&coverage.kept_zooms))
+        };
+        lines.push(format!("  {}: {}", coverage.source_layer, zooms));
+    }
+    lines.push("  (source-layers not referenced by the style are removed)".to_string());
+    lines
+}
+
+/// Bumped whenever a field of the inspect JSON report is renamed, removed or
+/// changes type or meaning; [`report_json_schema`] describes the current
+/// version.
+///
+/// * 2: `over_limit_tiles` of a sampled scan is the exact count (or the
+///   sampled estimate) instead of the count up to where sampling stopped.
+pub const INSPECT_REPORT_VERSION: u32 = 2;
+
+/// The tool and invocation that produced an inspect JSON report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportGenerator {
+    pub name: String,
+    pub version: String,
+    pub command_line: Vec<String>,
+    /// UTC time the report was written, as RFC 3339.
+    pub timestamp: String,
+}
+
+impl ReportGenerator {
+    /// This build of the crate, invoked with the current process arguments.
+    pub fn current() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            command_line: std::env::args().collect(),
+            timestamp: format_utc_timestamp(now),
+        }
+    }
+}
+
+/// Inspect JSON output: an [`MbtilesReport`] with its version and generator.
+#[derive(Debug, Serialize)]
+pub struct VersionedReport<'a> {
+    pub report_version: u32,
+    pub generator: ReportGenerator,
+    #[serde(flatten)]
+    pub report: &'a MbtilesReport,
+}
+
+impl<'a> VersionedReport<'a> {
+    pub fn new(report: &'a MbtilesReport, generator: ReportGenerator) -> Self {
+        Self {
+            report_version: INSPECT_REPORT_VERSION,
+            generator,
+            report,
+        }
+    }
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DDTHH:MM:SSZ`.
+pub fn format_utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let secs_of_day = secs % 86_400;
+    // Civil date from day count (H. Hinnant's days_from_civil inverse)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// JSON Schema (draft 2020-12) of the inspect JSON report, version
+/// [`INSPECT_REPORT_VERSION`]. Optional sections are absent rather than null
+/// unless typed as nullable.
+pub fn report_json_schema() -> Value {
+    let uint = || json!({ "type": "integer", "minimum": 0 });
+    let number = || json!({ "type": "number" });
+    let string = || json!({ "type": "string" });
+    let boolean = || json!({ "type": "boolean" });
+    let array = |items: Value| json!({ "type": "array", "items": items });
+    let nullable = |schema: Value| json!({ "anyOf": [schema, { "type": "null" }] });
+    let reference = |name: &str| json!({ "$ref": format!("#/$defs/{name}") });
+    let zoom = || json!({ "type": "integer", "minimum": 0, "maximum": 32 });
+
+    let mut defs = serde_json::Map::new();
+    defs.insert(
+        "generator".to_string(),
+        schema_object(
+            vec![
+                ("name", string()),
+                ("version", string()),
+                ("command_line", array(string())),
+                (
+                    "timestamp",
+                    json!({ "type": "string", "format": "date-time" }),
+                ),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "percentiles".to_string(),
+        schema_object(
+            ["p50", "p90", "p95", "p99", "p999"]
+                .into_iter()
+                .map(|name| (name, uint()))
+                .collect(),
+            &[],
+        ),
+    );
+    defs.insert(
+        "stats".to_string(),
+        schema_object(
+            vec![
+                ("tile_count", uint()),
+                ("total_bytes", uint()),
+                ("max_bytes", uint()),
+                ("avg_bytes", uint()),
+                ("percentiles", reference("percentiles")),
+            ],
+            &["percentiles"],
+        ),
+    );
+    defs.insert(
+        "zoom_stats".to_string(),
+        schema_object(vec![("zoom", zoom()), ("stats", reference("stats"))], &[]),
+    );
+    defs.insert(
+        "histogram_bucket".to_string(),
+        schema_object(
+            vec![
+                ("min_bytes", uint()),
+                ("max_bytes", uint()),
+                ("count", uint()),
+                ("total_bytes", uint()),
+                ("running_avg_bytes", uint()),
+                ("pct_tiles", number()),
+                ("pct_level_bytes", number()),
+                ("accum_pct_tiles", number()),
+                ("accum_pct_level_bytes", number()),
+                ("avg_near_limit", boolean()),
+                ("avg_over_limit", boolean()),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "zoom_histogram".to_string(),
+        schema_object(
+            vec![
+                ("zoom", zoom()),
+                ("buckets", array(reference("histogram_bucket"))),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "file_layer".to_string(),
+        schema_object(
+            vec![
+                ("name", string()),
+                ("vertex_count", uint()),
+                ("feature_count", uint()),
+                ("property_key_count", uint()),
+                ("property_value_count", uint()),
+                ("invalid_geometry_count", uint()),
+                ("tiles_with_layer", uint()),
+                ("min_features_per_tile", uint()),
+                ("max_features_per_tile", uint()),
+                ("avg_features_per_tile", number()),
+                (
+                    "top_keys",
+                    array(json!({
+                        "type": "array",
+                        "prefixItems": [string(), uint()],
+                        "items": false,
+                    })),
+                ),
+                ("geometry_bytes", uint()),
+                ("attribute_bytes", uint()),
+                ("approx_bytes_field_length", uint()),
+                (
+                    "approx_bytes_field_length_by_zoom",
+                    json!({
+                        "type": "object",
+                        "propertyNames": { "pattern": "^[0-9]+$" },
+                        "additionalProperties": uint(),
+                    }),
+                ),
+                ("pct_of_total", number()),
+            ],
+            &[
+                "invalid_geometry_count",
+                "tiles_with_layer",
+                "min_features_per_tile",
+                "max_features_per_tile",
+                "avg_features_per_tile",
+                "top_keys",
+                "geometry_bytes",
+                "attribute_bytes",
+                "approx_bytes_field_length",
+                "approx_bytes_field_length_by_zoom",
+                "pct_of_total",
+            ],
+        ),
+    );
+    defs.insert(
+        "top_tile".to_string(),
+        schema_object(
+            vec![
+                ("zoom", zoom()),
+                ("x", uint()),
+                ("y", uint()),
+                ("bytes", uint()),
+                ("url", string()),
+                ("hash", string()),
+            ],
+            &["url", "hash"],
+        ),
+    );
+    defs.insert(
+        "layer_summary".to_string(),
+        schema_object(
+            vec![
+                ("name", string()),
+                ("bytes", uint()),
+                ("feature_count", uint()),
+                ("vertex_count", uint()),
+                ("property_key_count", uint()),
+                ("property_value_count", uint()),
+                ("property_keys", array(string())),
+                ("invalid_geometry_count", uint()),
+                ("duplicate_feature_count", uint()),
+            ],
+            &["property_keys", "invalid_geometry_count"],
+        ),
+    );
+    defs.insert(
+        "tile_summary".to_string(),
+        schema_object(
+            vec![
+                ("zoom", zoom()),
+                ("x", uint()),
+                ("y", uint()),
+                ("tile_bytes", uint()),
+                ("layer_count", uint()),
+                ("total_features", uint()),
+                ("vertex_count", uint()),
+                ("property_key_count", uint()),
+                ("property_value_count", uint()),
+                ("layers", array(reference("layer_summary"))),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "recommendation".to_string(),
+        schema_object(
+            vec![
+                ("zoom", zoom()),
+                ("layer", string()),
+                (
+                    "reason",
+                    json!({
+                        "enum": ["bytes_share", "layer_bytes", "feature_count", "most_vertices"],
+                    }),
+                ),
+                ("bytes_share", number()),
+                ("vertices_share", number()),
+                ("avg_bytes", uint()),
+                ("avg_features", uint()),
+                (
+                    "action",
+                    json!({
+                        "oneOf": [
+                            schema_object(
+                                vec![("kind", json!({ "const": "simplify" })), ("tolerance", number())],
+                                &[],
+                            ),
+                            schema_object(
+                                vec![("kind", json!({ "const": "raise_minzoom" })), ("minzoom", zoom())],
+                                &[],
+                            ),
+                        ],
+                    }),
+                ),
+                ("estimated_savings_bytes", uint()),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "dedup_stats".to_string(),
+        schema_object(
+            vec![
+                ("unique_tiles", uint()),
+                ("duplicate_tiles", uint()),
+                ("reclaimable_bytes", uint()),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "dedup".to_string(),
+        schema_object(
+            vec![
+                ("estimated", boolean()),
+                ("overall", reference("dedup_stats")),
+                (
+                    "by_zoom",
+                    array(schema_object(
+                        vec![("zoom", zoom()), ("stats", reference("dedup_stats"))],
+                        &[],
+                    )),
+                ),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "content_hash".to_string(),
+        schema_object(
+            vec![
+                ("algorithm", string()),
+                ("tiles", uint()),
+                ("digest", string()),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "property_histogram".to_string(),
+        schema_object(
+            vec![
+                ("layer", string()),
+                ("key", string()),
+                (
+                    "values",
+                    array(schema_object(
+                        vec![("value", string()), ("count", uint())],
+                        &[],
+                    )),
+                ),
+                ("truncated", boolean()),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "vector_layer".to_string(),
+        schema_object(
+            vec![
+                ("id", string()),
+                ("description", string()),
+                ("minzoom", zoom()),
+                ("maxzoom", zoom()),
+                (
+                    "fields",
+                    json!({ "type": "object", "additionalProperties": string() }),
+                ),
+            ],
+            &["description", "minzoom", "maxzoom"],
+        ),
+    );
+    defs.insert(
+        "metadata_mismatch".to_string(),
+        schema_object(
+            vec![
+                ("layer", string()),
+                (
+                    "kind",
+                    json!({ "enum": ["declared_not_in_tiles", "in_tiles_not_declared"] }),
+                ),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "style_preview".to_string(),
+        schema_object(
+            vec![
+                (
+                    "layers",
+                    array(schema_object(
+                        vec![
+                            ("name", string()),
+                            ("status", json!({ "enum": ["kept", "dropped", "partial"] })),
+                            ("kept_zooms", array(zoom())),
+                            ("dropped_zooms", array(zoom())),
+                            ("removed_features", nullable(uint())),
+                            ("removed_bytes", nullable(uint())),
+                        ],
+                        &[],
+                    )),
+                ),
+                ("total_features", uint()),
+                ("removed_features", nullable(uint())),
+                ("removed_features_pct", nullable(number())),
+                ("total_bytes", nullable(uint())),
+                ("removed_bytes", nullable(uint())),
+                ("removed_bytes_pct", nullable(number())),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "high_cardinality_key".to_string(),
+        schema_object(
+            vec![
+                ("layer", string()),
+                ("key", string()),
+                ("feature_count", uint()),
+                ("distinct_values", uint()),
+                ("distinct_ratio", number()),
+                ("estimated", boolean()),
+                ("estimated_bytes", uint()),
+            ],
+            &[],
+        ),
+    );
+    defs.insert(
+        "mvt_validation".to_string(),
+        schema_object(
+            vec![
+                ("invalid_tiles", uint()),
+                (
+                    "errors",
+                    array(schema_object(
+                        vec![
+                            ("zoom", zoom()),
+                            ("x", uint()),
+                            ("y", uint()),
+                            ("error", string()),
+                        ],
+                        &[],
+                    )),
+                ),
+            ],
+            &[],
+        ),
+    );
+
+    let mut schema = schema_object(
+        vec![
+            ("report_version", json!({ "const": INSPECT_REPORT_VERSION })),
+            ("generator", reference("generator")),
+            (
+                "metadata",
+                json!({ "type": "object", "additionalProperties": string() }),
+            ),
+            ("overall", reference("stats")),
+            ("by_zoom", array(reference("zoom_stats"))),
+            ("empty_tiles", uint()),
+            ("empty_ratio", number()),
+            ("empty_tile_max_bytes", uint()),
+            ("small_tiles", uint()),
+            ("over_limit_tiles", uint()),
+            ("over_limit_tiles_exact", uint()),
+            (
+                "over_limit_tiles_sampled",
+                schema_object(
+                    vec![
+                        ("estimate", uint()),
+                        ("sample_count", uint()),
+                        ("sample_size", uint()),
+                    ],
+                    &[],
+                ),
+            ),
+            ("corrupt_rows", uint()),
+            ("sampled", boolean()),
+            ("sample_total_tiles", uint()),
+            ("sample_used_tiles", uint()),
+            ("sample_seed", uint()),
+            ("histogram", array(reference("histogram_bucket"))),
+            ("histograms_by_zoom", array(reference("zoom_histogram"))),
+            ("file_layers", array(reference("file_layer"))),
+            ("top_tiles", array(reference("top_tile"))),
+            ("bucket_count", nullable(uint())),
+            ("bucket_tiles", array(reference("top_tile"))),
+            ("tile_summary", nullable(reference("tile_summary"))),
+            ("tile_summaries", array(reference("tile_summary"))),
+            ("recommended_buckets", array(uint())),
+            ("recommendations", array(reference("recommendation"))),
+            ("top_tile_summaries", array(reference("tile_summary"))),
+            ("dedup", reference("dedup")),
+            ("content_hash", reference("content_hash")),
+            (
+                "layer_zoom_matrix",
+                array(json!({
+                    "type": "array",
+                    "prefixItems": [
+                        string(),
+                        array(json!({
+                            "type": "array",
+                            "prefixItems": [zoom(), uint()],
+                            "items": false,
+                        })),
+                    ],
+                    "items": false,
+                })),
+            ),
+            (
+                "property_histograms",
+                array(reference("property_histogram")),
+            ),
+            ("stored_bytes", uint()),
+            ("mvt_validation", reference("mvt_validation")),
+            (
+                "raster_format",
+                json!({ "enum": ["png", "jpeg", "webp", "avif"] }),
+            ),
+            ("raster_tiles", uint()),
+            (
+                "media_types",
+                json!({
+                    "type": "object",
+                    "propertyNames": {
+                        "enum": ["mvt", "png", "jpeg", "webp", "avif", "unknown"],
+                    },
+                    "additionalProperties": uint(),
+                }),
+            ),
+            (
+                "high_cardinality_keys",
+                array(reference("high_cardinality_key")),
+            ),
+            ("vector_layers", array(reference("vector_layer"))),
+            ("metadata_mismatches", array(reference("metadata_mismatch"))),
+            ("style_preview", reference("style_preview")),
+        ],
+        &[
+            "small_tiles",
+            "over_limit_tiles_exact",
+            "over_limit_tiles_sampled",
+            "sample_seed",
+            "tile_summaries",
+            "recommendations",
+            "dedup",
+            "content_hash",
+            "layer_zoom_matrix",
+            "property_histograms",
+            "stored_bytes",
+            "mvt_validation",
+            "raster_format",
+            "media_types",
+            "high_cardinality_keys",
+            "vector_layers",
+            "metadata_mismatches",
+            "style_preview",
+        ],
+    );
+    let object = schema
+        .as_object_mut()
+        .expect("schema_object returns an object");
+    object.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    object.insert(
+        "$id".to_string(),
+        json!(format!(
+            "https://github.com/yuiseki/vt-optimizer-rs/inspect-report-v{INSPECT_REPORT_VERSION}.json"
+        )),
+    );
+    object.insert("title".to_string(), json!("vt-optimizer inspect report"));
+    object.insert("$defs".to_string(), Value::Object(defs));
+    schema
+}
+
+/// A closed object schema requiring every property except `optional`.
+fn schema_object(properties: Vec<(&str, Value)>, optional: &[&str]) -> Value {
+    let required: Vec<&str> = properties
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !optional.contains(name))
+        .collect();
+    let properties: serde_json::Map<String, Value> = properties
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
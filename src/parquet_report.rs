@@ -0,0 +1,181 @@
+//! Parquet output of `inspect --report-format parquet` (feature `parquet`).
+//!
+//! Tiles listed with `--list-tiles` go to `tiles.parquet` as the scan visits
+//! them; the layer list goes to `layers.parquet` once the report is done.
+use anyhow::{Context, Result};
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt8Array, UInt32Array,
+    UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::mbtiles::{FileLayerSummary, TileRecord};
+
+/// File name of the tile table inside `--parquet-dir`.
+pub const TILES_FILE_NAME: &str = "tiles.parquet";
+/// File name of the layer table inside `--parquet-dir`.
+pub const LAYERS_FILE_NAME: &str = "layers.parquet";
+
+/// Tile records buffered before they are handed to the Parquet writer.
+const TILE_BATCH_ROWS: usize = 64 * 1024;
+
+fn create_writer(path: &Path, schema: SchemaRef) -> Result<ArrowWriter<File>> {
+    let file = File::create(path)
+        .with_context(|| format!("failed to create parquet file: {}", path.display()))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    ArrowWriter::try_new(file, schema, Some(properties)).context("create parquet writer")
+}
+
+/// Schema of `tiles.parquet`: `zoom`, `x`, `y` (XYZ), stored or decoded
+/// `bytes` (per `--measure`), `over_limit` and the optional `hash`.
+pub fn tile_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("zoom", DataType::UInt8, false),
+        Field::new("x", DataType::UInt32, false),
+        Field::new("y", DataType::UInt32, false),
+        Field::new("bytes", DataType::UInt64, false),
+        Field::new("over_limit", DataType::Boolean, false),
+        Field::new("hash", DataType::Utf8, true),
+    ]))
+}
+
+/// Schema of `layers.parquet`, one row per listed layer. The optional
+/// columns are null when the option filling them was not given.
+pub fn layer_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("layer", DataType::Utf8, false),
+        Field::new("features", DataType::UInt64, false),
+        Field::new("vertices", DataType::UInt64, false),
+        Field::new("property_keys", DataType::UInt64, false),
+        Field::new("property_values", DataType::UInt64, false),
+        Field::new("invalid_geometries", DataType::UInt64, true),
+        Field::new("geometry_bytes", DataType::UInt64, true),
+        Field::new("attribute_bytes", DataType::UInt64, true),
+        Field::new("approx_bytes", DataType::UInt64, true),
+        Field::new("pct_of_total", DataType::Float64, true),
+    ]))
+}
+
+struct TileWriterState {
+    writer: ArrowWriter<File>,
+    pending: Vec<TileRecord>,
+    rows: u64,
+}
+
+impl TileWriterState {
+    fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let tiles = std::mem::take(&mut self.pending);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt8Array::from_iter_values(tiles.iter().map(|t| t.zoom))),
+            Arc::new(UInt32Array::from_iter_values(tiles.iter().map(|t| t.x))),
+            Arc::new(UInt32Array::from_iter_values(tiles.iter().map(|t| t.y))),
+            Arc::new(UInt64Array::from_iter_values(tiles.iter().map(|t| t.bytes))),
+            Arc::new(BooleanArray::from(
+                tiles.iter().map(|t| t.over_limit).collect::<Vec<_>>(),
+            )),
+            Arc::new(StringArray::from(
+                tiles.iter().map(|t| t.hash.as_deref()).collect::<Vec<_>>(),
+            )),
+        ];
+        let batch = RecordBatch::try_new(tile_schema(), columns).context("build tile batch")?;
+        self.writer.write(&batch).context("write tile batch")?;
+        self.rows += tiles.len() as u64;
+        self.pending = tiles;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// Streams [`TileRecord`]s into `tiles.parquet`. Records may arrive from
+/// the scan's worker threads, so writes go through a lock; rows keep the
+/// order they were written in.
+pub struct ParquetTileWriter {
+    state: Mutex<TileWriterState>,
+}
+
+impl ParquetTileWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            state: Mutex::new(TileWriterState {
+                writer: create_writer(path, tile_schema())?,
+                pending: Vec::with_capacity(TILE_BATCH_ROWS),
+                rows: 0,
+            }),
+        })
+    }
+
+    pub fn write(&self, record: TileRecord) -> Result<()> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| anyhow::anyhow!("parquet writer lock poisoned"))?;
+        state.pending.push(record);
+        if state.pending.len() >= TILE_BATCH_ROWS {
+            state.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining records and the file footer; returns the rows written.
+    pub fn finish(self) -> Result<u64> {
+        let mut state = self
+            .state
+            .into_inner()
+            .map_err(|_| anyhow::anyhow!("parquet writer lock poisoned"))?;
+        state.flush()?;
+        state.writer.close().context("close tile parquet file")?;
+        Ok(state.rows)
+    }
+}
+
+/// Write the layer list to `path` as one row group.
+pub fn write_layers_parquet(path: &Path, layers: &[FileLayerSummary]) -> Result<u64> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            layers.iter().map(|l| l.name.as_str()),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            layers.iter().map(|l| l.feature_count),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            layers.iter().map(|l| l.vertex_count),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            layers.iter().map(|l| l.property_key_count as u64),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            layers.iter().map(|l| l.property_value_count as u64),
+        )),
+        Arc::new(UInt64Array::from_iter(
+            layers.iter().map(|l| l.invalid_geometry_count),
+        )),
+        Arc::new(UInt64Array::from_iter(
+            layers.iter().map(|l| l.geometry_bytes),
+        )),
+        Arc::new(UInt64Array::from_iter(
+            layers.iter().map(|l| l.attribute_bytes),
+        )),
+        Arc::new(UInt64Array::from_iter(
+            layers.iter().map(|l| l.approx_bytes_field_length),
+        )),
+        Arc::new(Float64Array::from_iter(
+            layers.iter().map(|l| l.pct_of_total),
+        )),
+    ];
+    let batch = RecordBatch::try_new(layer_schema(), columns).context("build layer batch")?;
+    let mut writer = create_writer(path, layer_schema())?;
+    writer.write(&batch).context("write layer batch")?;
+    writer.close().context("close layer parquet file")?;
+    Ok(layers.len() as u64)
+}
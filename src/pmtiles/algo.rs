@@ -1,3 +1,4 @@
+use crate::mbtiles::MAX_TILE_ZOOM;
 use crate::pmtiles::{
     Header,
     types::{Entry, HEADER_SIZE},
@@ -6,6 +7,7 @@ use anyhow::{Context, Result};
 use hilbert_2d::{Variant, h2xy_discrete, xy2h_discrete};
 use varint_rs::{VarintReader, VarintWriter};
 
+/// Values below `min_len` (tiles left out of the histogram) have no bucket.
 pub fn histogram_bucket_index_pmtiles(
     value: u64,
     min_len: Option<u64>,
@@ -17,7 +19,7 @@ pub fn histogram_bucket_index_pmtiles(
     }
     let min_len = min_len?;
     let max_len = max_len?;
-    if min_len > max_len {
+    if min_len > max_len || value < min_len {
         return None;
     }
     let range = (max_len - min_len).max(1);
@@ -29,31 +31,63 @@ pub fn histogram_bucket_index_pmtiles(
     Some(bucket)
 }
 
-pub fn tile_id_from_xyz(z: u8, x: u32, y: u32) -> u64 {
+/// First tile id of every zoom, `(4^z - 1) / 3`; the entry after
+/// [`MAX_TILE_ZOOM`] is the first id beyond it.
+const ZOOM_BASE_IDS: [u64; MAX_TILE_ZOOM as usize + 2] = {
+    let mut ids = [0u64; MAX_TILE_ZOOM as usize + 2];
+    let mut z = 0;
+    while z < ids.len() {
+        ids[z] = ((1u64 << (2 * z)) - 1) / 3;
+        z += 1;
+    }
+    ids
+};
+
+/// Tile id of XYZ tile `z/x/y`. Errors for zooms beyond [`MAX_TILE_ZOOM`]
+/// and for columns or rows outside the zoom's grid.
+pub fn tile_id_from_xyz(z: u8, x: u32, y: u32) -> Result<u64> {
+    if z > MAX_TILE_ZOOM {
+        anyhow::bail!("zoom {z} is beyond zoom {MAX_TILE_ZOOM}");
+    }
+    if u64::from(x.max(y)) >= 1u64 << z {
+        anyhow::bail!("tile {z}/{x}/{y} is outside the zoom {z} grid");
+    }
     if z == 0 {
-        return 0;
+        return Ok(0);
     }
     let order = z as usize;
     let hilbert = xy2h_discrete(x as usize, y as usize, order, Variant::Hilbert) as u64;
-    let base_id = (pow4(z) - 1) / 3;
-    base_id + hilbert
+    Ok(ZOOM_BASE_IDS[z as usize] + hilbert)
 }
 
-pub fn tile_id_to_xyz(tile_id: u64) -> (u8, u32, u32) {
-    if tile_id == 0 {
-        return (0, 0, 0);
-    }
-    let mut z = 1u8;
-    loop {
-        let base_id = (pow4(z) - 1) / 3;
-        let next_base = (pow4(z + 1) - 1) / 3;
-        if tile_id < next_base {
-            let idx = tile_id - base_id;
-            let (x, y) = h2xy_discrete(idx as usize, z as usize, Variant::Hilbert);
-            return (z, x as u32, y as u32);
-        }
-        z += 1;
+/// Zoom of a tile id without decoding its x/y. `3 * base + 1` is `4^z`, so
+/// the zoom is half the bit length of `3 * id + 1`.
+pub fn tile_id_zoom(tile_id: u64) -> Result<u8> {
+    if tile_id >= ZOOM_BASE_IDS[MAX_TILE_ZOOM as usize + 1] {
+        anyhow::bail!("tile id {tile_id} is beyond zoom {MAX_TILE_ZOOM}");
     }
+    Ok((u64::BITS - 1 - (3 * tile_id + 1).leading_zeros()) as u8 / 2)
+}
+
+/// Errors for ids beyond [`MAX_TILE_ZOOM`] (corrupt directory entries), whose
+/// zoom would overflow the id arithmetic.
+pub fn tile_id_to_xyz(tile_id: u64) -> Result<(u8, u32, u32)> {
+    let z = tile_id_zoom(tile_id)?;
+    if z == 0 {
+        return Ok((0, 0, 0));
+    }
+    let idx = tile_id - ZOOM_BASE_IDS[z as usize];
+    let (x, y) = h2xy_discrete(idx as usize, z as usize, Variant::Hilbert);
+    Ok((z, x as u32, y as u32))
+}
+
+/// Zoom shared by the `run_length` tiles starting at `tile_id`, or `None`
+/// when the run crosses into the next zoom. Ids are ordered by zoom, so
+/// comparing the ends of the run is enough.
+pub fn run_zoom(tile_id: u64, run_length: u32) -> Result<Option<u8>> {
+    let first = tile_id_zoom(tile_id)?;
+    let last = tile_id_zoom(tile_id.saturating_add(u64::from(run_length.max(1)) - 1))?;
+    Ok((first == last).then_some(first))
 }
 
 pub fn pow4(z: u8) -> u64 {
@@ -145,6 +179,8 @@ pub fn decode_directory(mut data: &[u8]) -> Result<Vec<Entry>> {
     Ok(entries)
 }
 
+/// Header for an archive without metadata whose root directory is written
+/// uncompressed (`internal_compression: 0`).
 pub fn build_header(
     root_length: u64,
     data_length: u64,
@@ -165,7 +201,7 @@ pub fn build_header(
         n_tile_entries: tile_count,
         n_tile_contents: tile_count,
         clustered: 0,
-        internal_compression: 1,
+        internal_compression: 0,
         tile_compression: 1,
         tile_type: 0,
         min_zoom,
@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::pmtiles::{
+    read_directory_section,
+    reader::RangeReader,
+    types::{Entry, Header, ProgressTracker},
+};
+
+/// Leaf directory entries kept in memory by a [`DirectoryIndex`]
+/// (24 bytes each, so about 200 MiB).
+pub const DEFAULT_DIRECTORY_CACHE_ENTRIES: usize = 8 * 1024 * 1024;
+
+/// Decoded directories of a PMTiles archive, shared by the inspect phases.
+///
+/// Leaf directories are read and decoded once and kept in an LRU cache
+/// bounded by the total number of entries; only archives whose leaves
+/// exceed the cap read some of them again. [`DirectoryIndex::fold`] walks
+/// the tile entries in tile-id order, spreading the root's leaf directories
+/// over the rayon pool.
+pub struct DirectoryIndex<'a> {
+    file: &'a dyn RangeReader,
+    header: &'a Header,
+    root: Vec<Entry>,
+    cache: Mutex<LeafCache>,
+}
+
+struct LeafCache {
+    limit: usize,
+    entries: usize,
+    tick: u64,
+    leaves: HashMap<u64, (Arc<Vec<Entry>>, u64)>,
+    recency: BTreeMap<u64, u64>,
+    reads: u64,
+}
+
+impl LeafCache {
+    fn get(&mut self, offset: u64) -> Option<Arc<Vec<Entry>>> {
+        let (leaf, tick) = self.leaves.get_mut(&offset)?;
+        self.recency.remove(tick);
+        self.tick += 1;
+        *tick = self.tick;
+        self.recency.insert(self.tick, offset);
+        Some(Arc::clone(leaf))
+    }
+
+    fn insert(&mut self, offset: u64, leaf: Arc<Vec<Entry>>) {
+        if leaf.len() > self.limit || self.leaves.contains_key(&offset) {
+            return;
+        }
+        while self.entries + leaf.len() > self.limit {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some((evicted, _)) = self.leaves.remove(&oldest) {
+                self.entries -= evicted.len();
+            }
+        }
+        self.tick += 1;
+        self.entries += leaf.len();
+        self.recency.insert(self.tick, offset);
+        self.leaves.insert(offset, (leaf, self.tick));
+    }
+}
+
+impl<'a> DirectoryIndex<'a> {
+    /// Read and decode the root directory of `header`.
+    pub fn new(file: &'a dyn RangeReader, header: &'a Header) -> Result<Self> {
+        let root = read_directory_section(file, header, header.root_offset, header.root_length)
+            .context("read root directory")?;
+        Ok(Self::with_root(file, header, root))
+    }
+
+    /// Index over an already decoded root directory.
+    pub fn with_root(file: &'a dyn RangeReader, header: &'a Header, root: Vec<Entry>) -> Self {
+        Self {
+            file,
+            header,
+            root,
+            cache: Mutex::new(LeafCache {
+                limit: DEFAULT_DIRECTORY_CACHE_ENTRIES,
+                entries: 0,
+                tick: 0,
+                leaves: HashMap::new(),
+                recency: BTreeMap::new(),
+                reads: 0,
+            }),
+        }
+    }
+
+    /// Cap the cached leaf entries at `limit` instead of
+    /// [`DEFAULT_DIRECTORY_CACHE_ENTRIES`].
+    pub fn with_cache_limit(self, limit: usize) -> Self {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.limit = limit;
+        }
+        self
+    }
+
+    pub fn file(&self) -> &'a dyn RangeReader {
+        self.file
+    }
+
+    pub fn header(&self) -> &'a Header {
+        self.header
+    }
+
+    /// Number of leaf directories read from the archive so far.
+    pub fn leaf_reads(&self) -> u64 {
+        self.cache.lock().map_or(0, |cache| cache.reads)
+    }
+
+    fn leaf(&self, entry: &Entry) -> Result<Arc<Vec<Entry>>> {
+        let lock = || {
+            self.cache
+                .lock()
+                .map_err(|_| anyhow::anyhow!("directory cache lock poisoned"))
+        };
+        if let Some(leaf) = lock()?.get(entry.offset) {
+            return Ok(leaf);
+        }
+        let leaf = Arc::new(read_directory_section(
+            self.file,
+            self.header,
+            self.header.leaf_offset + entry.offset,
+            entry.length as u64,
+        )?);
+        let mut cache = lock()?;
+        cache.reads += 1;
+        cache.insert(entry.offset, Arc::clone(&leaf));
+        Ok(leaf)
+    }
+
+    /// Read the stored bytes of `tile_id`, resolving leaf directories.
+    pub fn read_tile(&self, tile_id: u64) -> Result<Option<Vec<u8>>> {
+        self.read_tile_in(&self.root, tile_id)
+    }
+
+    fn read_tile_in(&self, entries: &[Entry], tile_id: u64) -> Result<Option<Vec<u8>>> {
+        let idx = entries.partition_point(|entry| entry.tile_id <= tile_id);
+        let Some(entry) = idx.checked_sub(1).map(|idx| &entries[idx]) else {
+            return Ok(None);
+        };
+        if entry.run_length == 0 {
+            if entry.length == 0 {
+                return Ok(None);
+            }
+            let leaf = self.leaf(entry)?;
+            return self.read_tile_in(&leaf, tile_id);
+        }
+        if tile_id >= entry.tile_id + u64::from(entry.run_length) {
+            return Ok(None);
+        }
+        self.file
+            .read_tile(self.header.data_offset + entry.offset, entry.length as u64)
+            .context("read tile data")
+            .map(Some)
+    }
+
+    /// Visit every tile entry in tile-id order on the calling thread.
+    pub fn walk(&self, mut visit: impl FnMut(&Entry) -> Result<()>) -> Result<()> {
+        self.walk_entries(&self.root, &mut visit)
+    }
+
+    fn walk_entries(
+        &self,
+        entries: &[Entry],
+        visit: &mut dyn FnMut(&Entry) -> Result<()>,
+    ) -> Result<()> {
+        for entry in entries {
+            if entry.run_length == 0 {
+                if entry.length == 0 {
+                    continue;
+                }
+                let leaf = self.leaf(entry)?;
+                self.walk_entries(&leaf, visit)?;
+                continue;
+            }
+            visit(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Fold every tile entry into per-task accumulators and merge them in
+    /// tile-id order. Each leaf directory of the root (and each run of tile
+    /// entries between them) is one task; `visit` returns the progress ticks
+    /// of an entry, applied to `progress` when its task completes.
+    pub fn fold<A, I, V, M>(
+        &self,
+        init: I,
+        visit: V,
+        merge: M,
+        progress: Option<&mut ProgressTracker>,
+    ) -> Result<A>
+    where
+        A: Send,
+        I: Fn() -> A + Sync,
+        V: Fn(&mut A, &Entry) -> Result<u64> + Sync,
+        M: Fn(&mut A, A) + Sync,
+    {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for (idx, entry) in self.root.iter().enumerate() {
+            if entry.run_length == 0 {
+                if start < idx {
+                    segments.push(&self.root[start..idx]);
+                }
+                segments.push(&self.root[idx..=idx]);
+                start = idx + 1;
+            }
+        }
+        if start < self.root.len() {
+            segments.push(&self.root[start..]);
+        }
+        let progress = Mutex::new(progress);
+        segments
+            .into_par_iter()
+            .map(|segment| -> Result<A> {
+                let mut accum = init();
+                let mut ticks = 0u64;
+                self.walk_entries(segment, &mut |entry| {
+                    ticks += visit(&mut accum, entry)?;
+                    Ok(())
+                })?;
+                if ticks > 0
+                    && let Some(progress) = progress
+                        .lock()
+                        .map_err(|_| anyhow::anyhow!("progress lock poisoned"))?
+                        .as_deref_mut()
+                {
+                    progress.inc(ticks);
+                }
+                Ok(accum)
+            })
+            .reduce(
+                || Ok(init()),
+                |left, right| -> Result<A> {
+                    let mut left = left?;
+                    merge(&mut left, right?);
+                    Ok(left)
+                },
+            )
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::Path;
+
+use crate::mbtiles::TileCoord;
+use crate::pmtiles::{
+    Entry, Header, RangeReader, ensure_pmtiles_path, read_directory_section, read_header,
+    tile_id_to_xyz,
+};
+
+/// Streaming iterator over every tile of a PMTiles file, in tile id order.
+///
+/// Leaf directories are read lazily while walking the root directory; the
+/// bytes of a run-length entry are read once and repeated for each tile in
+/// the run.
+pub struct PmtilesTiles {
+    file: File,
+    header: Header,
+    stack: Vec<std::vec::IntoIter<Entry>>,
+    run: Option<(Entry, u32, Vec<u8>)>,
+}
+
+impl PmtilesTiles {
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl Iterator for PmtilesTiles {
+    type Item = Result<(TileCoord, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((entry, index, data)) = self.run.as_mut() {
+                if *index < entry.run_length.max(1) {
+                    let tile_id = entry.tile_id + u64::from(*index);
+                    *index += 1;
+                    return Some(
+                        tile_id_to_xyz(tile_id)
+                            .map(|(zoom, x, y)| (TileCoord { zoom, x, y }, data.clone())),
+                    );
+                }
+                self.run = None;
+            }
+            let entries = self.stack.last_mut()?;
+            let Some(entry) = entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if entry.run_length == 0 {
+                if entry.length == 0 {
+                    continue;
+                }
+                let leaf_offset = self.header.leaf_offset + entry.offset;
+                match read_directory_section(
+                    &self.file,
+                    &self.header,
+                    leaf_offset,
+                    entry.length as u64,
+                ) {
+                    Ok(leaf) => self.stack.push(leaf.into_iter()),
+                    Err(err) => return Some(Err(err)),
+                }
+                continue;
+            }
+            let data = match self
+                .file
+                .read_tile(self.header.data_offset + entry.offset, entry.length as u64)
+                .context("read tile data")
+            {
+                Ok(data) => data,
+                Err(err) => return Some(Err(err)),
+            };
+            self.run = Some((entry, 0, data));
+        }
+    }
+}
+
+/// Iterate over all tiles of `path`, yielding raw tile bytes as stored;
+/// decompress on demand with [`crate::pmtiles::decode_tile_payload_pmtiles`]
+/// using the header's `tile_compression`.
+pub fn tiles_iter(path: &Path) -> Result<PmtilesTiles> {
+    ensure_pmtiles_path(path)?;
+    let file = File::open(path)
+        .with_context(|| format!("failed to open input pmtiles: {}", path.display()))?;
+    let header = read_header(&file).context("read header")?;
+    let root = read_directory_section(&file, &header, header.root_offset, header.root_length)
+        .context("read root directory")?;
+    Ok(PmtilesTiles {
+        file,
+        header,
+        stack: vec![root.into_iter()],
+        run: None,
+    })
+}
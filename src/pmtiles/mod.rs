@@ -1,9 +1,19 @@
 pub mod algo;
+pub mod directory;
+pub mod iter;
 pub mod processing;
+pub mod reader;
+pub mod source;
 pub mod stats;
 pub mod types;
+pub mod writer;
 
 pub use self::algo::*;
+pub use self::directory::*;
+pub use self::iter::*;
 pub use self::processing::*;
+pub use self::reader::*;
+pub use self::source::*;
 pub use self::stats::*;
 pub use self::types::*;
+pub use self::writer::*;
@@ -1,19 +1,36 @@
+use crate::error::VtError;
+use crate::format::{RasterFormat, TileFormat, TileMediaType};
 use crate::mbtiles::{
-    HistogramBucket, InspectOptions, MbtilesReport, MbtilesZoomStats, PruneStats, TileListOptions,
-    TileSort, TopTile, ZoomHistogram, count_vertices, encode_tile_payload, format_property_value,
-    prune_tile_layers, simplify_tile_payload,
+    ContentHashAccum, ContentKey, DEFAULT_GZIP_LEVEL, DedupAccum, DedupReport, FeatureCleanup,
+    HighCardinalityKey, HistogramBucket, InspectOptions, LayerZoomCounts, MbtilesReport,
+    MbtilesStats, MbtilesZoomStats, PropertyHistogram, PruneStats, RecompressStats,
+    SimplifyBatchStats, SimplifyOptions, SizeQuantiles, TileCompression, TileCoord, TileExtents,
+    TileListOptions, TileRecord, TileScheme, TileSink, TileSort, TileStream, TileSummary, TopHeap,
+    TopTile, ZoomDedupStats, ZoomHistogram, build_recommendations_with_thresholds,
+    compress_tile_payload, compress_tile_payload_with_window, count_vertices, decode_tile_payload,
+    encoded_value_size, ensure_vector_tileset, format_property_value, include_zoom_sample,
+    inspect_tile_source_streaming, inspect_tile_source_with_options, is_geometry_valid,
+    layer_attribute_sizes, layer_encoded_sizes, make_eta_progress_bar, open_readonly_mbtiles,
+    prune_tile_layers, read_metadata, refresh_extent_metadata, sample_zoom_offsets,
+    select_all_tiles_ordered_query, simplify_tile_layers, simplify_tile_payload,
+    sort_high_cardinality_keys, summarize_tile_payload, summarize_tiles, tile_address,
 };
 use crate::pmtiles::{
-    LayerAccum, StatAccum,
+    DirectoryIndex, LayerAccum, PmtilesFile, PmtilesWriter, StatAccum,
     algo::{
-        build_header, decode_directory, encode_directory, histogram_bucket_index_pmtiles,
-        splitmix64, tile_id_from_xyz, tile_id_to_xyz,
+        decode_directory, encode_directory, histogram_bucket_index_pmtiles, run_zoom,
+        tile_id_from_xyz, tile_id_to_xyz, tile_id_zoom,
     },
     build_header_with_metadata, progress_for_phase,
-    types::{Entry, HEADER_SIZE, Header, MAGIC, ProgressTracker, VERSION},
+    reader::{LocalReader, RangeReader},
+    types::{
+        Entry, HEADER_SIZE, Header, InternalCompression, MAGIC, PmtilesWriteOptions,
+        ProgressTracker, VERSION,
+    },
 };
 use anyhow::{Context, Result};
 use brotli::{CompressorWriter, Decompressor};
+use crossbeam_channel::bounded;
 use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
@@ -21,27 +38,19 @@ use mvt_reader::Reader;
 use rusqlite::Connection;
 use serde_json::Value;
 use std::cmp::Reverse;
-use std::collections::{BTreeMap, BinaryHeap, HashSet};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
-
-pub fn include_sample(index: u64, total: u64, sample: Option<&crate::mbtiles::SampleSpec>) -> bool {
-    match sample {
-        None => true,
-        Some(crate::mbtiles::SampleSpec::Count(count)) => index <= *count,
-        Some(crate::mbtiles::SampleSpec::Ratio(ratio)) => {
-            if *ratio >= 1.0 {
-                return true;
-            }
-            if *ratio <= 0.0 {
-                return false;
-            }
-            let threshold = (ratio * u64::MAX as f64) as u64;
-            let hash = splitmix64(index ^ total);
-            hash <= threshold
-        }
-    }
+use std::thread;
+
+pub fn include_sample(
+    index: u64,
+    total: u64,
+    sample: Option<&crate::mbtiles::SampleSpec>,
+    seed: Option<u64>,
+) -> bool {
+    crate::mbtiles::include_sample(index, total, sample, seed)
 }
 
 pub fn read_u8(input: &mut &[u8]) -> Result<u8> {
@@ -53,11 +62,26 @@ pub fn read_u8(input: &mut &[u8]) -> Result<u8> {
     Ok(value)
 }
 
-pub fn read_header(mut x: &File) -> Result<Header> {
-    let mut buf = [0u8; HEADER_SIZE];
-    x.read_exact(&mut buf).context("read header")?;
+pub fn read_header(reader: &dyn RangeReader) -> Result<Header> {
+    let size = reader.size();
+    if let Some(size) = size.filter(|size| *size < HEADER_SIZE as u64) {
+        let buf = reader.read_range(0, size).context("read header")?;
+        if !buf.starts_with(MAGIC) {
+            return Err(VtError::NotPmtiles {
+                reason: bad_magic_message(&buf, Some(size)),
+            }
+            .into());
+        }
+        anyhow::bail!("truncated PMTiles header ({size} bytes, expected {HEADER_SIZE})");
+    }
+    let buf = reader
+        .read_range(0, HEADER_SIZE as u64)
+        .context("read header")?;
     if &buf[0..MAGIC.len()] != MAGIC {
-        anyhow::bail!("invalid PMTiles magic");
+        return Err(VtError::NotPmtiles {
+            reason: bad_magic_message(&buf, size),
+        }
+        .into());
     }
 
     let mut cursor = &buf[MAGIC.len()..];
@@ -128,6 +152,29 @@ pub fn read_header(mut x: &File) -> Result<Header> {
     })
 }
 
+/// Describes the leading bytes of a file that is not a PMTiles archive and
+/// suggests what to run instead for the usual mix-ups.
+fn bad_magic_message(buf: &[u8], size: Option<u64>) -> String {
+    let hex = buf
+        .iter()
+        .take(MAGIC.len())
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut message = format!("invalid PMTiles magic: first bytes [{hex}]");
+    if let Some(size) = size {
+        message.push_str(&format!(", file size {size} bytes"));
+    }
+    if buf.starts_with(b"SQLite format 3\0") {
+        message.push_str(
+            "; this is an SQLite (MBTiles) file: pass `--input-format mbtiles`, or convert it with `copy <in> --input-format mbtiles --output <out.pmtiles>`",
+        );
+    } else if buf.starts_with(&[0x1f, 0x8b]) {
+        message.push_str("; this file is gzip-compressed: decompress it (e.g. `gunzip`) first");
+    }
+    message
+}
+
 pub fn write_header(mut file: &File, header: &Header) -> Result<()> {
     let mut buf = Vec::with_capacity(HEADER_SIZE);
     buf.write_all(MAGIC)?;
@@ -178,14 +225,16 @@ pub fn write_header(mut file: &File, header: &Header) -> Result<()> {
     Ok(())
 }
 
-pub fn read_metadata_section(mut file: &File, header: &Header) -> Result<BTreeMap<String, String>> {
+pub fn read_metadata_section(
+    reader: &dyn RangeReader,
+    header: &Header,
+) -> Result<BTreeMap<String, String>> {
     if header.metadata_length == 0 {
         return Ok(BTreeMap::new());
     }
-    file.seek(SeekFrom::Start(header.metadata_offset))
-        .context("seek metadata")?;
-    let mut data = vec![0u8; header.metadata_length as usize];
-    file.read_exact(&mut data).context("read metadata")?;
+    let data = reader
+        .read_range(header.metadata_offset, header.metadata_length)
+        .context("read metadata")?;
 
     let decoded = decode_internal_bytes(data, header.internal_compression)?;
 
@@ -239,6 +288,15 @@ pub fn decode_internal_bytes(data: Vec<u8>, internal_compression: u8) -> Result<
 }
 
 pub fn encode_internal_bytes(data: &[u8], internal_compression: u8) -> Result<Vec<u8>> {
+    encode_internal_bytes_with_options(data, internal_compression, &PmtilesWriteOptions::default())
+}
+
+/// Like [`encode_internal_bytes`], with the brotli quality and window of `options`.
+pub fn encode_internal_bytes_with_options(
+    data: &[u8],
+    internal_compression: u8,
+    options: &PmtilesWriteOptions,
+) -> Result<Vec<u8>> {
     match internal_compression {
         0 => Ok(data.to_vec()),
         1 => {
@@ -251,7 +309,12 @@ pub fn encode_internal_bytes(data: &[u8], internal_compression: u8) -> Result<Ve
         2 => {
             let mut compressed = Vec::new();
             {
-                let mut writer = CompressorWriter::new(&mut compressed, 4096, 5, 22);
+                let mut writer = CompressorWriter::new(
+                    &mut compressed,
+                    4096,
+                    options.brotli_quality,
+                    options.brotli_window,
+                );
                 writer
                     .write_all(data)
                     .context("encode brotli internal data")?;
@@ -286,24 +349,50 @@ pub fn decode_tile_payload_pmtiles(data: &[u8], tile_compression: u8) -> Result<
     }
 }
 
-pub fn encode_tile_payload_pmtiles(data: &[u8], tile_compression: u8) -> Result<Vec<u8>> {
-    match tile_compression {
-        0 => Ok(data.to_vec()),
-        1 => encode_tile_payload(data, true),
-        2 => {
-            let mut compressed = Vec::new();
-            {
-                let mut writer = CompressorWriter::new(&mut compressed, 4096, 5, 22);
-                writer.write_all(data).context("encode brotli tile data")?;
-            }
-            Ok(compressed)
-        }
-        other => anyhow::bail!("unsupported PMTiles tile compression: {other}"),
-    }
+/// Compresses `data` per the header's tile compression; `gzip_level` applies to gzip.
+pub fn encode_tile_payload_pmtiles(
+    data: &[u8],
+    tile_compression: u8,
+    gzip_level: u32,
+) -> Result<Vec<u8>> {
+    encode_tile_payload_pmtiles_with_options(
+        data,
+        tile_compression,
+        gzip_level,
+        &PmtilesWriteOptions::default(),
+    )
+}
+
+/// Like [`encode_tile_payload_pmtiles`]; brotli uses the quality and window of `options`.
+pub fn encode_tile_payload_pmtiles_with_options(
+    data: &[u8],
+    tile_compression: u8,
+    gzip_level: u32,
+    options: &PmtilesWriteOptions,
+) -> Result<Vec<u8>> {
+    compress_tile_payload_with_window(
+        data,
+        output_tile_compression(tile_compression, gzip_level, options)?,
+        options.brotli_window,
+    )
+}
+
+/// Codec for re-encoding tiles in the header's `tile_compression`.
+fn output_tile_compression(
+    tile_compression: u8,
+    gzip_level: u32,
+    options: &PmtilesWriteOptions,
+) -> Result<TileCompression> {
+    Ok(
+        match TileCompression::from_pmtiles_code(tile_compression, gzip_level)? {
+            TileCompression::Brotli(_) => TileCompression::Brotli(options.brotli_quality),
+            other => other,
+        },
+    )
 }
 
 pub fn read_directory_section(
-    mut file: &File,
+    reader: &dyn RangeReader,
     header: &Header,
     offset: u64,
     length: u64,
@@ -311,19 +400,67 @@ pub fn read_directory_section(
     if length == 0 {
         return Ok(Vec::new());
     }
-    file.seek(SeekFrom::Start(offset))
-        .context("seek directory")?;
-    let mut data = vec![0u8; length as usize];
-    file.read_exact(&mut data).context("read directory")?;
+    let data = reader
+        .read_range(offset, length)
+        .context("read directory")?;
     let decoded = decode_internal_bytes(data, header.internal_compression)?;
     decode_directory(&decoded)
 }
 
+/// Per-task accumulator of [`accumulate_tile_counts`].
+#[derive(Default)]
+struct TileCountAccum {
+    overall: StatAccum,
+    by_zoom: BTreeMap<u8, StatAccum>,
+    empty_tiles: u64,
+    small_tiles: u64,
+    over_limit_tiles: u64,
+    min_len: Option<u64>,
+    max_len: Option<u64>,
+    zoom_minmax: BTreeMap<u8, (u64, u64)>,
+    dedup_by_zoom: BTreeMap<u8, DedupAccum>,
+}
+
+impl TileCountAccum {
+    fn merge(&mut self, other: TileCountAccum) {
+        self.overall.merge(&other.overall);
+        for (zoom, stats) in other.by_zoom {
+            self.by_zoom.entry(zoom).or_default().merge(&stats);
+        }
+        self.empty_tiles += other.empty_tiles;
+        self.small_tiles += other.small_tiles;
+        self.over_limit_tiles += other.over_limit_tiles;
+        self.min_len = min_option(self.min_len, other.min_len);
+        self.max_len = self.max_len.max(other.max_len);
+        merge_zoom_minmax(&mut self.zoom_minmax, &other.zoom_minmax);
+        for (zoom, dedup) in other.dedup_by_zoom {
+            self.dedup_by_zoom.entry(zoom).or_default().merge(&dedup);
+        }
+    }
+}
+
+fn min_option(left: Option<u64>, right: Option<u64>) -> Option<u64> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(left.min(right)),
+        (left, right) => left.or(right),
+    }
+}
+
+fn merge_zoom_minmax(target: &mut BTreeMap<u8, (u64, u64)>, other: &BTreeMap<u8, (u64, u64)>) {
+    for (&zoom, &(other_min, other_max)) in other {
+        target
+            .entry(zoom)
+            .and_modify(|(min, max)| {
+                *min = (*min).min(other_min);
+                *max = (*max).max(other_max);
+            })
+            .or_insert((other_min, other_max));
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn accumulate_tile_counts(
-    file: &File,
-    header: &Header,
-    entries: &[Entry],
+    index: &DirectoryIndex<'_>,
     zoom_filter: Option<u8>,
     overall: &mut StatAccum,
     by_zoom: &mut BTreeMap<u8, StatAccum>,
@@ -333,80 +470,169 @@ pub fn accumulate_tile_counts(
     max_len: &mut Option<u64>,
     zoom_minmax: &mut BTreeMap<u8, (u64, u64)>,
     max_tile_bytes: u64,
-    mut progress: Option<&mut ProgressTracker>,
+    dedup_by_zoom: Option<&mut BTreeMap<u8, DedupAccum>>,
+    progress: Option<&mut ProgressTracker>,
+    empty_tile_max_bytes: u64,
+    histogram_floor: Option<u64>,
+    small_tiles: Option<&mut u64>,
 ) -> Result<()> {
-    for entry in entries {
-        if entry.run_length == 0 {
-            if entry.length == 0 {
-                continue;
-            }
-            let leaf_offset = header.leaf_offset + entry.offset;
-            let leaf_entries =
-                read_directory_section(file, header, leaf_offset, entry.length as u64)?;
-            accumulate_tile_counts(
-                file,
-                header,
-                &leaf_entries,
-                zoom_filter,
-                overall,
-                by_zoom,
-                empty_tiles,
-                over_limit_tiles,
-                min_len,
-                max_len,
-                zoom_minmax,
-                max_tile_bytes,
-                progress.as_deref_mut(),
-            )?;
-            continue;
-        }
-        let run = entry.run_length.max(1);
-        let length = entry.length as u64;
-        for idx in 0..run {
-            let tile_id = entry.tile_id + idx as u64;
-            let (z, _x, _y) = tile_id_to_xyz(tile_id);
-            if let Some(target_zoom) = zoom_filter
+    let file = index.file();
+    let header = index.header();
+    let collect_sizes = overall.sizes.is_some();
+    let collect_dedup = dedup_by_zoom.is_some();
+    let empty_exact = small_tiles.is_some();
+    let new_stats = || StatAccum {
+        sizes: collect_sizes.then(|| SizeQuantiles::new(false)),
+        ..StatAccum::default()
+    };
+    let counts = index.fold(
+        || TileCountAccum {
+            overall: new_stats(),
+            ..TileCountAccum::default()
+        },
+        |accum, entry| {
+            let run = entry.run_length.max(1);
+            let length = entry.length as u64;
+            // Runs almost never cross a zoom, so the zoom is usually found once
+            let shared_zoom = run_zoom(entry.tile_id, run)?;
+            if let (Some(target_zoom), Some(z)) = (zoom_filter, shared_zoom)
                 && z != target_zoom
             {
-                continue;
-            }
-            overall.add_tile(length);
-            by_zoom
-                .entry(z)
-                .or_insert_with(|| StatAccum {
-                    tile_count: 0,
-                    total_bytes: 0,
-                    max_bytes: 0,
-                })
-                .add_tile(length);
-            if max_tile_bytes > 0 && length > max_tile_bytes {
-                *over_limit_tiles += 1;
-            }
-            if length <= crate::mbtiles::EMPTY_TILE_MAX_BYTES {
-                *empty_tiles += 1;
+                return Ok(0);
             }
-            *min_len = Some(min_len.map_or(length, |min| min.min(length)));
-            *max_len = Some(max_len.map_or(length, |max| max.max(length)));
-            zoom_minmax
-                .entry(z)
-                .and_modify(|(min, max)| {
-                    *min = (*min).min(length);
-                    *max = (*max).max(length);
+            // With `--empty-exact`, a small entry is read and decoded once for
+            // its whole run; otherwise every small tile counts as empty.
+            let featureless = if empty_exact && length <= empty_tile_max_bytes {
+                let data = file.read_tile(header.data_offset + entry.offset, length)?;
+                crate::mbtiles::stored_tile_is_featureless(&data, |data| {
+                    decode_tile_payload_pmtiles(data, header.tile_compression)
                 })
-                .or_insert((length, length));
-            if let Some(progress) = progress.as_deref_mut() {
-                progress.inc(1);
+            } else {
+                true
+            };
+            let mut ticks = 0u64;
+            for idx in 0..run {
+                let z = match shared_zoom {
+                    Some(z) => z,
+                    None => tile_id_zoom(entry.tile_id + idx as u64)?,
+                };
+                if let Some(target_zoom) = zoom_filter
+                    && z != target_zoom
+                {
+                    continue;
+                }
+                accum.overall.add_tile(length);
+                accum
+                    .by_zoom
+                    .entry(z)
+                    .or_insert_with(new_stats)
+                    .add_tile(length);
+                if max_tile_bytes > 0 && length > max_tile_bytes {
+                    accum.over_limit_tiles += 1;
+                }
+                if length <= empty_tile_max_bytes {
+                    if empty_exact && !featureless {
+                        accum.small_tiles += 1;
+                    } else {
+                        accum.empty_tiles += 1;
+                    }
+                }
+                // Histogram bounds only cover the tiles the histograms keep
+                if histogram_floor.is_none_or(|floor| length > floor) {
+                    accum.min_len = min_option(accum.min_len, Some(length));
+                    accum.max_len = accum.max_len.max(Some(length));
+                    accum
+                        .zoom_minmax
+                        .entry(z)
+                        .and_modify(|(min, max)| {
+                            *min = (*min).min(length);
+                            *max = (*max).max(length);
+                        })
+                        .or_insert((length, length));
+                }
+                if collect_dedup {
+                    accum
+                        .dedup_by_zoom
+                        .entry(z)
+                        .or_default()
+                        .insert(ContentKey::Offset(entry.offset), length);
+                }
+                ticks += 1;
             }
+            Ok(ticks)
+        },
+        TileCountAccum::merge,
+        progress,
+    )?;
+
+    overall.merge(&counts.overall);
+    for (zoom, stats) in counts.by_zoom {
+        by_zoom.entry(zoom).or_default().merge(&stats);
+    }
+    *empty_tiles += counts.empty_tiles;
+    *over_limit_tiles += counts.over_limit_tiles;
+    *min_len = min_option(*min_len, counts.min_len);
+    *max_len = (*max_len).max(counts.max_len);
+    merge_zoom_minmax(zoom_minmax, &counts.zoom_minmax);
+    if let Some(dedup_by_zoom) = dedup_by_zoom {
+        for (zoom, dedup) in counts.dedup_by_zoom {
+            dedup_by_zoom.entry(zoom).or_default().merge(&dedup);
         }
     }
+    if let Some(small_tiles) = small_tiles {
+        *small_tiles += counts.small_tiles;
+    }
     Ok(())
 }
 
+/// Per-task bucket counts of the size histograms.
+struct BucketCounts {
+    counts: Vec<u64>,
+    bytes: Vec<u64>,
+    used_tiles: u64,
+    used_bytes: u64,
+    excluded_tiles: u64,
+    excluded_bytes: u64,
+}
+
+impl BucketCounts {
+    fn new(buckets: usize) -> Self {
+        Self {
+            counts: vec![0u64; buckets],
+            bytes: vec![0u64; buckets],
+            used_tiles: 0,
+            used_bytes: 0,
+            excluded_tiles: 0,
+            excluded_bytes: 0,
+        }
+    }
+
+    fn add(&mut self, bucket: usize, length: u64) {
+        self.counts[bucket] += 1;
+        self.bytes[bucket] += length;
+        self.used_tiles += 1;
+        self.used_bytes += length;
+    }
+
+    fn merge(&mut self, other: BucketCounts) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+        for (bytes, other) in self.bytes.iter_mut().zip(other.bytes) {
+            *bytes += other;
+        }
+        self.used_tiles += other.used_tiles;
+        self.used_bytes += other.used_bytes;
+        self.excluded_tiles += other.excluded_tiles;
+        self.excluded_bytes += other.excluded_bytes;
+    }
+}
+
+/// Tiles at or below `floor` (`--exclude-empty`) are left out, and the
+/// totals used for the percentages are reduced by them.
 #[allow(clippy::too_many_arguments)]
 pub fn build_histogram_from_entries(
-    file: &File,
-    header: &Header,
-    entries: &[Entry],
+    index: &DirectoryIndex<'_>,
     zoom_filter: Option<u8>,
     total_tiles_used: u64,
     total_bytes_used: u64,
@@ -414,51 +640,47 @@ pub fn build_histogram_from_entries(
     min_len: u64,
     max_len: u64,
     max_tile_bytes: u64,
-    mut progress: Option<&mut ProgressTracker>,
+    floor: Option<u64>,
+    progress: Option<&mut ProgressTracker>,
 ) -> Result<Vec<HistogramBucket>> {
     if buckets == 0 || min_len > max_len {
         return Ok(Vec::new());
     }
     let range = (max_len - min_len).max(1);
     let bucket_size = ((range as f64) / buckets as f64).ceil() as u64;
-    let mut counts = vec![0u64; buckets];
-    let mut bytes = vec![0u64; buckets];
 
-    let mut stack = vec![entries.to_vec()];
-    while let Some(entries) = stack.pop() {
-        for entry in entries.iter() {
-            if entry.run_length == 0 {
-                if entry.length == 0 {
-                    continue;
-                }
-                let leaf_offset = header.leaf_offset + entry.offset;
-                let leaf_entries =
-                    read_directory_section(file, header, leaf_offset, entry.length as u64)?;
-                stack.push(leaf_entries);
-                continue;
-            }
+    let histogram = index.fold(
+        || BucketCounts::new(buckets),
+        |accum, entry| {
             let length = entry.length as u64;
             let run = entry.run_length.max(1);
+            let mut ticks = 0u64;
             for idx in 0..run {
                 let tile_id = entry.tile_id + idx as u64;
-                let (z, _x, _y) = tile_id_to_xyz(tile_id);
+                let z = tile_id_zoom(tile_id)?;
                 if let Some(target_zoom) = zoom_filter
                     && z != target_zoom
                 {
                     continue;
                 }
-                let mut bucket = ((length.saturating_sub(min_len)) / bucket_size) as usize;
-                if bucket >= buckets {
-                    bucket = buckets - 1;
-                }
-                counts[bucket] += 1;
-                bytes[bucket] += length;
-                if let Some(progress) = progress.as_deref_mut() {
-                    progress.inc(1);
+                ticks += 1;
+                if floor.is_some_and(|floor| length <= floor) {
+                    accum.excluded_tiles += 1;
+                    accum.excluded_bytes += length;
+                    continue;
                 }
+                let bucket = ((length.saturating_sub(min_len)) / bucket_size) as usize;
+                accum.add(bucket.min(buckets - 1), length);
             }
-        }
-    }
+            Ok(ticks)
+        },
+        BucketCounts::merge,
+        progress,
+    )?;
+    let counts = histogram.counts;
+    let bytes = histogram.bytes;
+    let total_tiles_used = total_tiles_used.saturating_sub(histogram.excluded_tiles);
+    let total_bytes_used = total_bytes_used.saturating_sub(histogram.excluded_bytes);
 
     let mut result = Vec::with_capacity(buckets);
     let mut accum_count = 0u64;
@@ -521,30 +743,25 @@ pub fn build_histogram_from_entries(
 
 #[allow(clippy::too_many_arguments)]
 pub fn build_zoom_histograms_from_entries(
-    file: &File,
-    header: &Header,
-    entries: &[Entry],
+    index: &DirectoryIndex<'_>,
     zoom_filter: Option<u8>,
     zoom_minmax: &BTreeMap<u8, (u64, u64)>,
     buckets: usize,
     max_tile_bytes: u64,
-    mut progress: Option<&mut ProgressTracker>,
+    floor: Option<u64>,
+    progress: Option<&mut ProgressTracker>,
 ) -> Result<Vec<ZoomHistogram>> {
     if buckets == 0 || zoom_minmax.is_empty() {
         return Ok(Vec::new());
     }
 
-    struct ZoomAccum {
+    struct ZoomBounds {
         min_len: u64,
         max_len: u64,
         bucket_size: u64,
-        counts: Vec<u64>,
-        bytes: Vec<u64>,
-        used_tiles: u64,
-        used_bytes: u64,
     }
 
-    let mut accums: BTreeMap<u8, ZoomAccum> = BTreeMap::new();
+    let mut bounds: BTreeMap<u8, ZoomBounds> = BTreeMap::new();
     for (zoom, (min_len, max_len)) in zoom_minmax.iter() {
         if let Some(target_zoom) = zoom_filter
             && *zoom != target_zoom
@@ -553,74 +770,76 @@ pub fn build_zoom_histograms_from_entries(
         }
         let range = (max_len - min_len).max(1);
         let bucket_size = ((range as f64) / buckets as f64).ceil() as u64;
-        accums.insert(
+        bounds.insert(
             *zoom,
-            ZoomAccum {
+            ZoomBounds {
                 min_len: *min_len,
                 max_len: *max_len,
                 bucket_size,
-                counts: vec![0u64; buckets],
-                bytes: vec![0u64; buckets],
-                used_tiles: 0,
-                used_bytes: 0,
             },
         );
     }
 
-    let mut stack = vec![entries.to_vec()];
-    while let Some(entries) = stack.pop() {
-        for entry in entries.iter() {
-            if entry.run_length == 0 {
-                if entry.length == 0 {
-                    continue;
-                }
-                let leaf_offset = header.leaf_offset + entry.offset;
-                let leaf_entries =
-                    read_directory_section(file, header, leaf_offset, entry.length as u64)?;
-                stack.push(leaf_entries);
-                continue;
-            }
+    let mut counts = index.fold(
+        BTreeMap::<u8, BucketCounts>::new,
+        |accums, entry| {
             let length = entry.length as u64;
             let run = entry.run_length.max(1);
+            let mut ticks = 0u64;
             for idx in 0..run {
                 let tile_id = entry.tile_id + idx as u64;
-                let (z, _x, _y) = tile_id_to_xyz(tile_id);
+                let z = tile_id_zoom(tile_id)?;
                 if let Some(target_zoom) = zoom_filter
                     && z != target_zoom
                 {
                     continue;
                 }
-                let Some(accum) = accums.get_mut(&z) else {
+                let Some(bounds) = bounds.get(&z) else {
                     continue;
                 };
-                let mut bucket =
-                    ((length.saturating_sub(accum.min_len)) / accum.bucket_size) as usize;
-                if bucket >= buckets {
-                    bucket = buckets - 1;
+                if floor.is_some_and(|floor| length <= floor) {
+                    continue;
                 }
-                accum.counts[bucket] += 1;
-                accum.bytes[bucket] += length;
-                accum.used_tiles += 1;
-                accum.used_bytes += length;
-                if let Some(progress) = progress.as_deref_mut() {
-                    progress.inc(1);
+                let bucket =
+                    ((length.saturating_sub(bounds.min_len)) / bounds.bucket_size) as usize;
+                accums
+                    .entry(z)
+                    .or_insert_with(|| BucketCounts::new(buckets))
+                    .add(bucket.min(buckets - 1), length);
+                ticks += 1;
+            }
+            Ok(ticks)
+        },
+        |accums, other| {
+            for (zoom, counts) in other {
+                match accums.entry(zoom) {
+                    std::collections::btree_map::Entry::Occupied(mut entry) => {
+                        entry.get_mut().merge(counts)
+                    }
+                    std::collections::btree_map::Entry::Vacant(entry) => {
+                        entry.insert(counts);
+                    }
                 }
             }
-        }
-    }
+        },
+        progress,
+    )?;
 
     let mut result = Vec::new();
     let limit_threshold = (max_tile_bytes as f64) * 0.9;
-    for (zoom, accum) in accums.into_iter() {
+    for (zoom, bounds) in bounds.into_iter() {
+        let accum = counts
+            .remove(&zoom)
+            .unwrap_or_else(|| BucketCounts::new(buckets));
         let mut buckets_vec = Vec::with_capacity(buckets);
         let mut accum_count = 0u64;
         let mut accum_bytes = 0u64;
         for i in 0..buckets {
-            let b_min = accum.min_len + accum.bucket_size * i as u64;
+            let b_min = bounds.min_len + bounds.bucket_size * i as u64;
             let b_max = if i + 1 == buckets {
-                accum.max_len
+                bounds.max_len
             } else {
-                (accum.min_len + accum.bucket_size * (i as u64 + 1)).saturating_sub(1)
+                (bounds.min_len + bounds.bucket_size * (i as u64 + 1)).saturating_sub(1)
             };
             accum_count += accum.counts[i];
             accum_bytes += accum.bytes[i];
@@ -674,11 +893,47 @@ pub fn build_zoom_histograms_from_entries(
     Ok(result)
 }
 
+/// Per-task accumulator of [`collect_top_tiles_from_entries`].
+struct TopTilesAccum {
+    top_heap: TopHeap,
+    bucket_tiles: Vec<TopTile>,
+    content_hash: Option<ContentHashAccum>,
+}
+
+impl TopTilesAccum {
+    fn merge(&mut self, other: TopTilesAccum, topn: usize, list_options: Option<&TileListOptions>) {
+        for tile in other.top_heap {
+            self.top_heap.push(tile);
+            if self.top_heap.len() > topn {
+                self.top_heap.pop();
+            }
+        }
+        self.bucket_tiles.extend(other.bucket_tiles);
+        if let Some(list_options) = list_options {
+            truncate_listed_tiles(&mut self.bucket_tiles, list_options);
+        }
+        if let (Some(accum), Some(other)) = (self.content_hash.as_mut(), other.content_hash) {
+            accum.merge(&other);
+        }
+    }
+}
+
+fn truncate_listed_tiles(tiles: &mut Vec<TopTile>, list_options: &TileListOptions) {
+    if tiles.len() > list_options.limit {
+        if list_options.sort == TileSort::Size {
+            tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        } else {
+            tiles.sort_by(|a, b| (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y)));
+        }
+        tiles.truncate(list_options.limit);
+    }
+}
+
+/// Streamed tiles are emitted in tile-id order from the calling thread;
+/// otherwise the walk is spread over the rayon pool.
 #[allow(clippy::too_many_arguments)]
 pub fn collect_top_tiles_from_entries(
-    file: &File,
-    header: &Header,
-    entries: &[Entry],
+    index: &DirectoryIndex<'_>,
     zoom_filter: Option<u8>,
     topn: usize,
     bucket: Option<usize>,
@@ -686,212 +941,578 @@ pub fn collect_top_tiles_from_entries(
     min_len: Option<u64>,
     max_len: Option<u64>,
     histogram_buckets: usize,
+    max_tile_bytes: u64,
+    stream: Option<&TileStream<'_>>,
+    content_hash: Option<&mut ContentHashAccum>,
     mut progress: Option<&mut ProgressTracker>,
 ) -> Result<(Vec<TopTile>, Vec<TopTile>)> {
-    if topn == 0 && (bucket.is_none() || list_options.is_none()) {
+    let size_range = list_options.and_then(|list| list.size_range);
+    if topn == 0
+        && stream.is_none()
+        && content_hash.is_none()
+        && ((bucket.is_none() && size_range.is_none()) || list_options.is_none())
+    {
         return Ok((Vec::new(), Vec::new()));
     }
 
-    let mut top_heap: BinaryHeap<Reverse<(u64, u8, u32, u32)>> = BinaryHeap::new();
-    let mut bucket_tiles: Vec<TopTile> = Vec::new();
+    let file = index.file();
+    let header = index.header();
+    let hash_algorithm = content_hash.as_deref().map(ContentHashAccum::algorithm);
     let bucket_target = bucket.unwrap_or(0);
-    let bucketable = bucket.is_some()
-        && list_options.is_some()
+    let listable = list_options.is_some() || stream.is_some();
+    let bucketable = listable
+        && bucket.is_some()
         && histogram_buckets > 0
         && min_len.is_some()
         && max_len.is_some();
-
-    let mut stack = vec![entries.to_vec()];
-    while let Some(entries) = stack.pop() {
-        for entry in entries.iter() {
-            if entry.run_length == 0 {
-                if entry.length == 0 {
-                    continue;
-                }
-                let leaf_offset = header.leaf_offset + entry.offset;
-                let leaf_entries =
-                    read_directory_section(file, header, leaf_offset, entry.length as u64)?;
-                stack.push(leaf_entries);
+    // A size range replaces the bucket and needs no histogram
+    let selects = |length: u64| match size_range {
+        Some(range) => listable && range.contains(length),
+        None => {
+            bucketable
+                && histogram_bucket_index_pmtiles(length, min_len, max_len, histogram_buckets)
+                    == Some(bucket_target)
+        }
+    };
+    // Entries outside the size range are only needed by the top tiles and
+    // the content hash
+    let skip_outside_range = topn == 0 && hash_algorithm.is_none();
+
+    let init = || TopTilesAccum {
+        top_heap: BinaryHeap::new(),
+        bucket_tiles: Vec::new(),
+        content_hash: hash_algorithm.map(ContentHashAccum::new),
+    };
+    let visit = |accum: &mut TopTilesAccum, entry: &Entry| -> Result<u64> {
+        let length = entry.length as u64;
+        let run = entry.run_length.max(1);
+        if skip_outside_range && size_range.is_some_and(|range| !range.contains(length)) {
+            return Ok(if zoom_filter.is_none() {
+                u64::from(run)
+            } else {
+                0
+            });
+        }
+        let mut ticks = 0u64;
+        // Run-length entries share one payload, so it is read and hashed once
+        let mut tile_hash = None;
+        for idx in 0..run {
+            let tile_id = entry.tile_id + idx as u64;
+            let (z, x, y) = tile_id_to_xyz(tile_id)?;
+            if let Some(target_zoom) = zoom_filter
+                && z != target_zoom
+            {
                 continue;
             }
-            let length = entry.length as u64;
-            let run = entry.run_length.max(1);
-            for idx in 0..run {
-                let tile_id = entry.tile_id + idx as u64;
-                let (z, x, y) = tile_id_to_xyz(tile_id);
-                if let Some(target_zoom) = zoom_filter
-                    && z != target_zoom
-                {
-                    continue;
+            ticks += 1;
+            if let Some(hash_accum) = accum.content_hash.as_mut() {
+                if tile_hash.is_none() {
+                    let data =
+                        file.read_tile(header.data_offset + entry.offset, entry.length as u64)?;
+                    let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
+                    tile_hash = Some(hash_accum.algorithm().hex_digest(&payload));
                 }
-                if let Some(progress) = progress.as_deref_mut() {
-                    progress.inc(1);
-                }
-                if topn > 0 {
-                    top_heap.push(Reverse((length, z, x, y)));
-                    if top_heap.len() > topn {
-                        top_heap.pop();
-                    }
+                hash_accum.add(z, x, y, tile_hash.as_deref().unwrap_or_default());
+            }
+            if topn > 0 {
+                accum
+                    .top_heap
+                    .push(Reverse((length, z, x, y, tile_hash.clone())));
+                if accum.top_heap.len() > topn {
+                    accum.top_heap.pop();
                 }
-                if bucketable
-                    && let Some(bucket_idx) =
-                        histogram_bucket_index_pmtiles(length, min_len, max_len, histogram_buckets)
-                    && bucket_idx == bucket_target
-                {
-                    bucket_tiles.push(TopTile {
-                        zoom: z,
-                        x,
-                        y,
-                        bytes: length,
-                    });
-                    let list_options = list_options.expect("list options");
-                    if bucket_tiles.len() > list_options.limit {
-                        if list_options.sort == TileSort::Size {
-                            bucket_tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
-                        } else {
-                            bucket_tiles
-                                .sort_by(|a, b| (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y)));
-                        }
-                        bucket_tiles.truncate(list_options.limit);
-                    }
+            }
+            let record = TileRecord {
+                zoom: z,
+                x,
+                y,
+                bytes: length,
+                over_limit: max_tile_bytes > 0 && length > max_tile_bytes,
+                hash: tile_hash.clone(),
+            };
+            if bucket.is_none()
+                && size_range.is_none()
+                && let Some(stream) = stream
+            {
+                stream.emit(record.clone())?;
+            }
+            if selects(length) {
+                if let Some(stream) = stream {
+                    stream.emit(record)?;
+                    continue;
                 }
+                accum.bucket_tiles.push(TopTile {
+                    zoom: z,
+                    x,
+                    y,
+                    bytes: length,
+                    url: None,
+                    hash: tile_hash.clone(),
+                });
+                truncate_listed_tiles(&mut accum.bucket_tiles, list_options.expect("list options"));
             }
         }
+        Ok(ticks)
+    };
+
+    let accum = if stream.is_some() {
+        let mut accum = init();
+        index.walk(|entry| {
+            let ticks = visit(&mut accum, entry)?;
+            if let Some(progress) = progress.as_deref_mut() {
+                progress.inc(ticks);
+            }
+            Ok(())
+        })?;
+        accum
+    } else {
+        index.fold(
+            init,
+            visit,
+            |accum, other| accum.merge(other, topn, list_options),
+            progress,
+        )?
+    };
+    if let (Some(target), Some(hashes)) = (content_hash, accum.content_hash.as_ref()) {
+        target.merge(hashes);
     }
 
-    let mut top_tiles = top_heap
+    let mut top_tiles = accum
+        .top_heap
         .into_iter()
-        .map(|Reverse((bytes, zoom, x, y))| TopTile { zoom, x, y, bytes })
+        .map(|Reverse((bytes, zoom, x, y, hash))| TopTile {
+            zoom,
+            x,
+            y,
+            bytes,
+            url: None,
+            hash,
+        })
         .collect::<Vec<_>>();
-    top_tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
-
-    Ok((top_tiles, bucket_tiles))
+    // Ties are ordered by tile so the result does not depend on the merge order
+    top_tiles.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y)))
+    });
+
+    Ok((top_tiles, accum.bucket_tiles))
 }
 
+/// Builds the layer list (with `include_layer_list`) and, when `layer_zoom`
+/// is given, accumulates per-zoom feature counts in the same pass. Value
+/// histograms for `options.property_histogram` are pushed to `histograms`
+/// and the keys flagged by `options.cardinality` to `high_cardinality`.
+#[allow(clippy::too_many_arguments)]
 pub fn build_file_layer_list_pmtiles(
-    mut file: &File,
-    header: &Header,
-    entries: &[Entry],
+    index: &DirectoryIndex<'_>,
     options: &InspectOptions,
-    total_tiles: u64,
+    zoom_counts: &BTreeMap<u8, u64>,
+    mut layer_zoom: Option<&mut LayerZoomCounts>,
+    histograms: Option<&mut Vec<PropertyHistogram>>,
+    high_cardinality: Option<&mut Vec<HighCardinalityKey>>,
     mut progress: Option<&mut ProgressTracker>,
 ) -> Result<Vec<crate::mbtiles::FileLayerSummary>> {
-    if !options.include_layer_list {
+    if !options.include_layer_list && layer_zoom.is_none() {
         return Ok(Vec::new());
     }
 
+    let file = index.file();
+    let header = index.header();
     let mut map: BTreeMap<String, LayerAccum> = BTreeMap::new();
-    let mut index: u64 = 0;
-    let mut stack = vec![entries.to_vec()];
+    let mut tile_index: u64 = 0;
+    // Tiles are walked in tile-id order, so zoom by zoom
+    let (sample_offsets, total_tiles) = sample_zoom_offsets(zoom_counts, options.zoom);
 
-    while let Some(entries) = stack.pop() {
-        for entry in entries.iter() {
-            if entry.run_length == 0 {
-                if entry.length == 0 {
-                    continue;
-                }
-                let leaf_offset = header.leaf_offset + entry.offset;
-                let leaf_entries =
-                    read_directory_section(file, header, leaf_offset, entry.length as u64)?;
-                stack.push(leaf_entries);
+    index.walk(|entry| {
+        let run = entry.run_length.max(1);
+        let mut selected = 0u64;
+        let mut selected_by_zoom: BTreeMap<u8, u64> = BTreeMap::new();
+        for idx in 0..run {
+            let tile_id = entry.tile_id + idx as u64;
+            let z = tile_id_zoom(tile_id)?;
+            if let Some(target_zoom) = options.zoom
+                && z != target_zoom
+            {
                 continue;
             }
-            let run = entry.run_length.max(1);
-            let mut selected = 0u64;
-            for idx in 0..run {
-                let tile_id = entry.tile_id + idx as u64;
-                let (z, _x, _y) = tile_id_to_xyz(tile_id);
-                if let Some(target_zoom) = options.zoom
-                    && z != target_zoom
-                {
-                    continue;
-                }
-                index += 1;
-                if let Some(progress) = progress.as_deref_mut() {
-                    progress.inc(1);
-                }
-                if include_sample(index, total_tiles, options.sample.as_ref()) {
-                    selected += 1;
+            tile_index += 1;
+            if let Some(progress) = progress.as_deref_mut() {
+                progress.inc(1);
+            }
+            if include_zoom_sample(
+                tile_index,
+                total_tiles,
+                z,
+                zoom_counts,
+                &sample_offsets,
+                options.sample.as_ref(),
+                options.sample_seed,
+            ) {
+                selected += 1;
+                *selected_by_zoom.entry(z).or_insert(0) += 1;
+            }
+        }
+        if selected == 0 {
+            return Ok(());
+        }
+        let data = file
+            .read_tile(header.data_offset + entry.offset, entry.length as u64)
+            .context("read tile data")?;
+        let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
+        let layer_sizes = if options.include_layer_list && options.layer_bytes {
+            layer_encoded_sizes(&payload)?
+        } else {
+            Vec::new()
+        };
+        let reader =
+            Reader::new(payload).map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+        let layers = reader
+            .get_layer_metadata()
+            .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+        for layer in layers {
+            if let Some(layer_zoom) = layer_zoom.as_deref_mut() {
+                for (&zoom, &count) in selected_by_zoom.iter() {
+                    layer_zoom.add(&layer.name, zoom, layer.feature_count as u64 * count);
                 }
             }
-            if selected == 0 {
+            if !options.include_layer_list {
                 continue;
             }
-            let data_offset = header.data_offset + entry.offset;
-            let mut data = vec![0u8; entry.length as usize];
-            file.seek(SeekFrom::Start(data_offset))
-                .context("seek tile data")?;
-            file.read_exact(&mut data).context("read tile data")?;
-            let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
-            let reader =
-                Reader::new(payload).map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
-            let layers = reader
-                .get_layer_metadata()
-                .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
-            for layer in layers {
-                let entry = map.entry(layer.name.clone()).or_default();
-                entry.feature_count += (layer.feature_count as u64) * selected;
-                let features = reader
-                    .get_features(layer.layer_index)
-                    .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
-                for feature in features {
-                    entry.vertex_count += (count_vertices(&feature.geometry) as u64) * selected;
-                    if let Some(props) = feature.properties {
-                        for (key, value) in props {
-                            entry.property_keys.insert(key.clone());
-                            entry.property_values.insert(format_property_value(&value));
+            let entry = map.entry(layer.name.clone()).or_default();
+            entry.feature_count += (layer.feature_count as u64) * selected;
+            entry
+                .tile_stats
+                .add_tiles(layer.feature_count as u64, selected);
+            if let Some(&bytes) = layer_sizes.get(layer.layer_index) {
+                for (&zoom, &count) in selected_by_zoom.iter() {
+                    *entry.field_length_bytes_by_zoom.entry(zoom).or_insert(0) += bytes * count;
+                }
+            }
+            let features = reader
+                .get_features(layer.layer_index)
+                .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
+            if options.attributes_size {
+                let (geometry, attributes) =
+                    layer_attribute_sizes(&layer.name, layer.extent, &features)?;
+                entry.geometry_bytes += geometry * selected;
+                entry.attribute_bytes += attributes * selected;
+            }
+            for feature in features {
+                entry.vertex_count += (count_vertices(&feature.geometry) as u64) * selected;
+                if options.check_geometry && !is_geometry_valid(&feature.geometry) {
+                    entry.invalid_geometry_count += selected;
+                }
+                if let Some(props) = feature.properties {
+                    for (key, value) in props {
+                        let value_text = format_property_value(&value);
+                        if let Some(cardinality) = options.cardinality.as_ref() {
+                            entry.key_cardinality.add(
+                                &key,
+                                &value_text,
+                                encoded_value_size(&value),
+                                selected,
+                                cardinality.max_tracked_values,
+                            );
+                        }
+                        if options.property_histogram.as_deref() == Some(key.as_str()) {
+                            entry.top_values.add(value_text.clone(), selected);
                         }
+                        entry.tile_stats.add_key(&key, selected);
+                        entry.property_keys.insert(key);
+                        entry.property_values.insert(value_text);
                     }
                 }
             }
         }
+        Ok(())
+    })?;
+
+    if let (Some(histograms), Some(key)) = (histograms, options.property_histogram.as_ref()) {
+        for (name, accum) in map.iter_mut() {
+            if !accum.top_values.is_empty() {
+                let top_values = std::mem::take(&mut accum.top_values);
+                histograms.push(top_values.into_histogram(name.clone(), key.clone()));
+            }
+        }
+    }
+    if let (Some(high_cardinality), Some(cardinality)) =
+        (high_cardinality, options.cardinality.as_ref())
+    {
+        for (name, accum) in map.iter_mut() {
+            let key_cardinality = std::mem::take(&mut accum.key_cardinality);
+            high_cardinality.extend(key_cardinality.into_high_cardinality_keys(name, cardinality));
+        }
+        sort_high_cardinality_keys(high_cardinality);
     }
 
     let mut result = map
         .into_iter()
-        .map(|(name, accum)| crate::mbtiles::FileLayerSummary {
-            name,
-            vertex_count: accum.vertex_count,
-            feature_count: accum.feature_count,
-            property_key_count: accum.property_keys.len(),
-            property_value_count: accum.property_values.len(),
+        .map(|(name, accum)| {
+            let mut summary = crate::mbtiles::FileLayerSummary {
+                name,
+                vertex_count: accum.vertex_count,
+                feature_count: accum.feature_count,
+                property_key_count: accum.property_keys.len(),
+                property_value_count: accum.property_values.len(),
+                invalid_geometry_count: options
+                    .check_geometry
+                    .then_some(accum.invalid_geometry_count),
+                tiles_with_layer: None,
+                min_features_per_tile: None,
+                max_features_per_tile: None,
+                avg_features_per_tile: None,
+                top_keys: Vec::new(),
+                geometry_bytes: options.attributes_size.then_some(accum.geometry_bytes),
+                attribute_bytes: options.attributes_size.then_some(accum.attribute_bytes),
+                approx_bytes_field_length: options
+                    .layer_bytes
+                    .then(|| accum.field_length_bytes_by_zoom.values().sum()),
+                approx_bytes_field_length_by_zoom: if options.layer_bytes {
+                    accum.field_length_bytes_by_zoom.clone()
+                } else {
+                    BTreeMap::new()
+                },
+                pct_of_total: None,
+            };
+            if options.zoom.is_some() {
+                accum.tile_stats.apply(&mut summary);
+            }
+            summary
         })
         .collect::<Vec<_>>();
     result.sort_by(|a, b| a.name.cmp(&b.name));
+    crate::mbtiles::fill_layer_byte_shares(&mut result);
     Ok(result)
 }
 
+/// Rejects paths whose extension names another tile format. Paths without a
+/// recognised extension are accepted since the caller picked the format
+/// explicitly (e.g. `--input-format`).
 pub fn ensure_pmtiles_path(path: &Path) -> Result<()> {
-    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-    if ext.eq_ignore_ascii_case("pmtiles") {
-        Ok(())
-    } else {
-        anyhow::bail!("only .pmtiles paths are supported in v0.0.3");
+    match TileFormat::from_extension(path) {
+        Some(TileFormat::Pmtiles) | None => Ok(()),
+        Some(_) => Err(VtError::NotPmtiles {
+            reason: format!("only .pmtiles paths are supported: {}", path.display()),
+        }
+        .into()),
     }
 }
 
+/// See [`ensure_pmtiles_path`].
 pub fn ensure_mbtiles_path(path: &Path) -> Result<()> {
-    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-    if ext.eq_ignore_ascii_case("mbtiles") {
-        Ok(())
-    } else {
-        anyhow::bail!("only .mbtiles paths are supported");
+    match TileFormat::from_extension(path) {
+        Some(TileFormat::Mbtiles) | None => Ok(()),
+        Some(_) => Err(VtError::NotMbtiles {
+            reason: format!("only .mbtiles paths are supported: {}", path.display()),
+        }
+        .into()),
+    }
+}
+
+/// PMTiles counterpart of [`crate::mbtiles::quick_stats`]: sums the entry
+/// lengths of the directories (run-length entries count every tile they
+/// address) without reading tile data.
+pub fn quick_stats_pmtiles(path: &Path, zoom: Option<u8>) -> Result<MbtilesStats> {
+    ensure_pmtiles_path(path)?;
+    let file = LocalReader::open(path)
+        .with_context(|| format!("failed to open pmtiles: {}", path.display()))?;
+    let header = read_header(&file).context("read header")?;
+    let mut stack = vec![read_directory_section(
+        &file,
+        &header,
+        header.root_offset,
+        header.root_length,
+    )?];
+    let mut accum = StatAccum {
+        tile_count: 0,
+        total_bytes: 0,
+        max_bytes: 0,
+        sizes: None,
+    };
+    while let Some(entries) = stack.pop() {
+        for entry in entries {
+            if entry.run_length == 0 {
+                if entry.length > 0 {
+                    let leaf_offset = header.leaf_offset + entry.offset;
+                    stack.push(read_directory_section(
+                        &file,
+                        &header,
+                        leaf_offset,
+                        entry.length as u64,
+                    )?);
+                }
+                continue;
+            }
+            for idx in 0..entry.run_length {
+                if let Some(zoom) = zoom
+                    && tile_id_zoom(entry.tile_id + idx as u64)? != zoom
+                {
+                    continue;
+                }
+                accum.add_tile(entry.length as u64);
+            }
+        }
     }
+    Ok(accum.into_stats())
+}
+
+/// Number of addressed tiles from the header, or from a directory walk for
+/// one zoom (and for archives whose header leaves the count at 0).
+pub fn tile_count_pmtiles(path: &Path, zoom: Option<u8>) -> Result<u64> {
+    ensure_pmtiles_path(path)?;
+    if zoom.is_none() {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open pmtiles: {}", path.display()))?;
+        let header = read_header(&file).context("read header")?;
+        if header.n_addressed_tiles > 0 {
+            return Ok(header.n_addressed_tiles);
+        }
+    }
+    Ok(quick_stats_pmtiles(path, zoom)?.tile_count)
 }
 
 pub fn inspect_pmtiles_with_options(
     path: &Path,
     options: &InspectOptions,
+) -> Result<MbtilesReport, VtError> {
+    ensure_pmtiles_path(path)?;
+    // Decoded sizes, validation and the layers-only scan need tile data,
+    // which the generic tile-source scan reads
+    if options.uncompressed_sizes || options.validate_mvt || options.layers_only {
+        let source = PmtilesFile::open(path)?;
+        return Ok(inspect_tile_source_with_options(&source, options.clone())?);
+    }
+    let file = LocalReader::open(path)
+        .with_context(|| format!("failed to open input pmtiles: {}", path.display()))?;
+    Ok(inspect_pmtiles_reader_with_options(&file, options)?)
+}
+
+/// Like [`inspect_pmtiles_with_options`], but listed tiles are passed to
+/// `sink` during the directory walk; see
+/// [`crate::mbtiles::inspect_mbtiles_streaming`].
+pub fn inspect_pmtiles_streaming(
+    path: &Path,
+    options: &InspectOptions,
+    sink: TileSink<'_>,
 ) -> Result<MbtilesReport> {
     ensure_pmtiles_path(path)?;
-    let file = File::open(path)
+    if options.uncompressed_sizes || options.validate_mvt || options.layers_only {
+        let source = PmtilesFile::open(path)?;
+        return inspect_tile_source_streaming(&source, options.clone(), sink);
+    }
+    let file = LocalReader::open(path)
         .with_context(|| format!("failed to open input pmtiles: {}", path.display()))?;
-    let header = read_header(&file).context("read header")?;
-    let metadata = read_metadata_section(&file, &header)?;
+    inspect_pmtiles_reader_streaming(&file, options, sink)
+}
 
-    let root_entries =
-        read_directory_section(&file, &header, header.root_offset, header.root_length)
-            .context("read root directory")?;
+/// [`crate::mbtiles::summarize_tiles`] for a local PMTiles file.
+pub fn summarize_pmtiles_tiles(
+    path: &Path,
+    coords: &[TileCoord],
+    layers_filter: &[String],
+    check_geometry: bool,
+    visit: &mut dyn FnMut(TileSummary) -> Result<()>,
+) -> Result<u64> {
+    ensure_pmtiles_path(path)?;
+    let source = PmtilesFile::open(path)?;
+    summarize_tiles(&source, coords, layers_filter, check_geometry, visit)
+}
+
+/// Inspect a remote PMTiles archive using HTTP Range requests.
+///
+/// Only directories and metadata are fetched. The layer list needs tile
+/// data, so it is computed only for `--sample N` (count) and skipped
+/// otherwise.
+#[cfg(feature = "http")]
+pub fn inspect_pmtiles_url_with_options(
+    url: &str,
+    options: &InspectOptions,
+) -> Result<MbtilesReport> {
+    inspect_pmtiles_url_impl(url, options, None)
+}
+
+/// Streaming variant of [`inspect_pmtiles_url_with_options`].
+#[cfg(feature = "http")]
+pub fn inspect_pmtiles_url_streaming(
+    url: &str,
+    options: &InspectOptions,
+    sink: TileSink<'_>,
+) -> Result<MbtilesReport> {
+    let limit = options.list_tiles.as_ref().map_or(0, |list| list.limit);
+    inspect_pmtiles_url_impl(url, options, Some(&TileStream::new(sink, limit)))
+}
+
+#[cfg(feature = "http")]
+fn inspect_pmtiles_url_impl(
+    url: &str,
+    options: &InspectOptions,
+    stream: Option<&TileStream<'_>>,
+) -> Result<MbtilesReport> {
+    let reader = crate::pmtiles::HttpRangeReader::open(url)?;
+    let mut options = options.clone();
+    if (options.include_layer_list || options.layer_zoom_matrix)
+        && !matches!(options.sample, Some(crate::mbtiles::SampleSpec::Count(_)))
+    {
+        tracing::warn!("skipping layer list for remote PMTiles; use --sample N to fetch N tiles");
+        options.include_layer_list = false;
+        options.layer_zoom_matrix = false;
+    }
+    let report = inspect_pmtiles_reader_impl(&reader, &options, stream)?;
+    tracing::debug!(
+        requests = reader.request_count(),
+        "remote pmtiles inspected"
+    );
+    Ok(report)
+}
+
+/// Inspect a PMTiles archive from any [`RangeReader`].
+pub fn inspect_pmtiles_reader_with_options(
+    file: &dyn RangeReader,
+    options: &InspectOptions,
+) -> Result<MbtilesReport> {
+    inspect_pmtiles_reader_impl(file, options, None)
+}
+
+/// Streaming variant of [`inspect_pmtiles_reader_with_options`].
+pub fn inspect_pmtiles_reader_streaming(
+    file: &dyn RangeReader,
+    options: &InspectOptions,
+    sink: TileSink<'_>,
+) -> Result<MbtilesReport> {
+    let limit = options.list_tiles.as_ref().map_or(0, |list| list.limit);
+    inspect_pmtiles_reader_impl(file, options, Some(&TileStream::new(sink, limit)))
+}
+
+fn inspect_pmtiles_reader_impl(
+    file: &dyn RangeReader,
+    options: &InspectOptions,
+    stream: Option<&TileStream<'_>>,
+) -> Result<MbtilesReport> {
+    if options.uncompressed_sizes {
+        anyhow::bail!("--measure uncompressed requires a local PMTiles file");
+    }
+    if options.validate_mvt {
+        anyhow::bail!("--validate-mvt requires a local PMTiles file");
+    }
+    let style = options.read_style()?;
+    let header = read_header(file).context("read header")?;
+    let metadata = read_metadata_section(file, &header)?;
+    let raster_format = RasterFormat::from_pmtiles_tile_type(header.tile_type).or_else(|| {
+        metadata
+            .get("format")
+            .and_then(|format| RasterFormat::from_metadata(format))
+    });
+    let raster_options;
+    let options = match raster_format {
+        Some(format) => {
+            raster_options = options.clone().for_raster(format)?;
+            &raster_options
+        }
+        None => options,
+    };
+
+    let index = DirectoryIndex::new(file, &header)?;
     let total_estimate = header
         .n_addressed_tiles
         .max(header.n_tile_entries)
@@ -901,13 +1522,16 @@ pub fn inspect_pmtiles_with_options(
         tile_count: 0,
         total_bytes: 0,
         max_bytes: 0,
+        sizes: options.percentiles.then(|| SizeQuantiles::new(false)),
     };
     let mut by_zoom: BTreeMap<u8, StatAccum> = BTreeMap::new();
     let mut empty_tiles = 0u64;
+    let mut small_tiles = options.empty_exact.then_some(0u64);
     let mut over_limit_tiles = 0u64;
     let mut min_len: Option<u64> = None;
     let mut max_len: Option<u64> = None;
     let mut zoom_minmax: BTreeMap<u8, (u64, u64)> = BTreeMap::new();
+    let mut dedup_by_zoom = options.dedup_stats.then(BTreeMap::new);
     let mut counting_progress = progress_for_phase(
         "counting tiles",
         total_estimate,
@@ -915,9 +1539,7 @@ pub fn inspect_pmtiles_with_options(
         options.no_progress,
     );
     accumulate_tile_counts(
-        &file,
-        &header,
-        &root_entries,
+        &index,
         options.zoom,
         &mut overall,
         &mut by_zoom,
@@ -927,7 +1549,11 @@ pub fn inspect_pmtiles_with_options(
         &mut max_len,
         &mut zoom_minmax,
         options.max_tile_bytes,
+        dedup_by_zoom.as_mut(),
         counting_progress.as_mut(),
+        options.empty_tile_max_bytes,
+        options.histogram_floor(),
+        small_tiles.as_mut(),
     )?;
     if let Some(progress) = counting_progress {
         progress.finish();
@@ -942,9 +1568,7 @@ pub fn inspect_pmtiles_with_options(
                 options.no_progress,
             );
             let histogram = build_histogram_from_entries(
-                &file,
-                &header,
-                &root_entries,
+                &index,
                 options.zoom,
                 overall.tile_count,
                 overall.total_bytes,
@@ -952,6 +1576,7 @@ pub fn inspect_pmtiles_with_options(
                 min_len,
                 max_len,
                 options.max_tile_bytes,
+                options.histogram_floor(),
                 histogram_progress.as_mut(),
             )?;
             if let Some(progress) = histogram_progress {
@@ -962,8 +1587,14 @@ pub fn inspect_pmtiles_with_options(
         _ => Vec::new(),
     };
 
-    let needs_top_tiles =
-        options.topn > 0 || (options.bucket.is_some() && options.list_tiles.is_some());
+    let needs_top_tiles = options.topn > 0
+        || stream.is_some()
+        || options.hash.is_some()
+        || options
+            .list_tiles
+            .as_ref()
+            .is_some_and(|list| options.bucket.is_some() || list.size_range.is_some());
+    let mut content_hash = options.hash.map(ContentHashAccum::new);
     let mut top_tiles_progress = if needs_top_tiles {
         progress_for_phase(
             "processing top tiles",
@@ -975,9 +1606,7 @@ pub fn inspect_pmtiles_with_options(
         None
     };
     let (top_tiles, bucket_tiles) = collect_top_tiles_from_entries(
-        &file,
-        &header,
-        &root_entries,
+        &index,
         options.zoom,
         options.topn,
         options.bucket,
@@ -985,6 +1614,9 @@ pub fn inspect_pmtiles_with_options(
         min_len,
         max_len,
         options.histogram_buckets,
+        options.max_tile_bytes,
+        stream,
+        content_hash.as_mut(),
         top_tiles_progress.as_mut(),
     )?;
     if let Some(progress) = top_tiles_progress {
@@ -998,19 +1630,18 @@ pub fn inspect_pmtiles_with_options(
         options.no_progress,
     );
     let histograms_by_zoom = build_zoom_histograms_from_entries(
-        &file,
-        &header,
-        &root_entries,
+        &index,
         options.zoom,
         &zoom_minmax,
         options.histogram_buckets,
         options.max_tile_bytes,
+        options.histogram_floor(),
         histograms_by_zoom_progress.as_mut(),
     )?;
     if let Some(progress) = histograms_by_zoom_progress {
         progress.finish();
     }
-    let mut layers_progress = if options.include_layer_list {
+    let mut layers_progress = if options.include_layer_list || options.layer_zoom_matrix {
         progress_for_phase(
             "processing layers",
             total_estimate,
@@ -1020,12 +1651,19 @@ pub fn inspect_pmtiles_with_options(
     } else {
         None
     };
+    let mut layer_zoom = options.layer_zoom_matrix.then(LayerZoomCounts::new);
+    let mut property_histograms = Vec::new();
+    let mut high_cardinality_keys = Vec::new();
     let mut file_layers = build_file_layer_list_pmtiles(
-        &file,
-        &header,
-        &root_entries,
+        &index,
         options,
-        overall.tile_count,
+        &by_zoom
+            .iter()
+            .map(|(&zoom, stats)| (zoom, stats.tile_count))
+            .collect(),
+        layer_zoom.as_mut(),
+        Some(&mut property_histograms),
+        Some(&mut high_cardinality_keys),
         layers_progress.as_mut(),
     )?;
     if let Some(progress) = layers_progress {
@@ -1034,6 +1672,8 @@ pub fn inspect_pmtiles_with_options(
     if !options.layers.is_empty() {
         let filter: HashSet<&str> = options.layers.iter().map(|s| s.as_str()).collect();
         file_layers.retain(|layer| filter.contains(layer.name.as_str()));
+        property_histograms.retain(|histogram| filter.contains(histogram.layer.as_str()));
+        high_cardinality_keys.retain(|key| filter.contains(key.layer.as_str()));
     }
 
     let by_zoom = by_zoom
@@ -1045,6 +1685,37 @@ pub fn inspect_pmtiles_with_options(
         .collect::<Vec<_>>();
 
     let overall_stats = overall.into_stats();
+    let raster_tiles = if raster_format.is_some() {
+        overall_stats.tile_count
+    } else {
+        0
+    };
+    // PMTiles declares one tile type for the whole archive
+    let mut media_types = BTreeMap::new();
+    if options.media_types && overall_stats.tile_count > 0 {
+        media_types.insert(
+            TileMediaType::from_pmtiles_tile_type(header.tile_type),
+            overall_stats.tile_count,
+        );
+    }
+    let dedup = dedup_by_zoom.map(|dedup_by_zoom| {
+        let mut overall = DedupAccum::new();
+        let by_zoom = dedup_by_zoom
+            .into_iter()
+            .map(|(zoom, dedup)| {
+                overall.merge(&dedup);
+                ZoomDedupStats {
+                    zoom,
+                    stats: dedup.stats(),
+                }
+            })
+            .collect();
+        DedupReport {
+            estimated: false,
+            overall: overall.stats(),
+            by_zoom,
+        }
+    });
     let empty_ratio = if overall_stats.tile_count == 0 {
         0.0
     } else {
@@ -1085,16 +1756,58 @@ pub fn inspect_pmtiles_with_options(
         Vec::new()
     };
 
-    Ok(MbtilesReport {
+    let top_tile_summaries = if options.recommend {
+        top_tiles
+            .iter()
+            .map(|tile| {
+                let tile_id = tile_id_from_xyz(tile.zoom, tile.x, tile.y)?;
+                let data = index
+                    .read_tile(tile_id)?
+                    .context("failed to read tile data")?;
+                let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
+                summarize_tile_payload(
+                    TileCoord {
+                        zoom: tile.zoom,
+                        x: tile.x,
+                        y: tile.y,
+                    },
+                    data.len() as u64,
+                    payload,
+                    &[],
+                    options.check_geometry,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+    let recommendations = if options.recommend {
+        build_recommendations_with_thresholds(
+            &by_zoom,
+            &top_tile_summaries,
+            &options.recommend_thresholds,
+        )
+    } else {
+        Vec::new()
+    };
+
+    let mut report = MbtilesReport {
         metadata,
         overall: overall_stats,
         by_zoom,
         empty_tiles,
         empty_ratio,
+        empty_tile_max_bytes: options.empty_tile_max_bytes,
+        small_tiles,
         over_limit_tiles,
+        // Inspect reads every directory entry, so PMTiles counts are never sampled
+        over_limit_tiles_exact: Some(over_limit_tiles),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 0,
         sample_used_tiles: 0,
+        sample_seed: None,
         histogram,
         histograms_by_zoom,
         file_layers,
@@ -1102,197 +1815,399 @@ pub fn inspect_pmtiles_with_options(
         bucket_count,
         bucket_tiles,
         tile_summary: None,
+        tile_summaries: Vec::new(),
         recommended_buckets,
-        top_tile_summaries: Vec::new(),
-    })
+        recommendations,
+        top_tile_summaries,
+        dedup,
+        content_hash: content_hash.as_ref().map(ContentHashAccum::finish),
+        layer_zoom_matrix: layer_zoom
+            .map(|counts| counts.into_matrix(&options.layers))
+            .unwrap_or_default(),
+        property_histograms,
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format,
+        raster_tiles,
+        media_types,
+        high_cardinality_keys,
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
+    };
+    report.check_vector_layers(options);
+    report.preview_style(style.as_ref(), options);
+    Ok(report)
 }
 
+/// Bounded queue size between the prune reader, workers, and collector.
+const PRUNE_QUEUE_CAPACITY: usize = 1_000;
+
+#[allow(clippy::too_many_arguments)]
 pub fn prune_pmtiles_layer_only(
     input: &Path,
     output: &Path,
-    style: &crate::style::MapboxStyle,
+    selection: impl Into<crate::style::LayerSelection>,
+    apply_filters: bool,
+    keep_unknown_filters: bool,
+    fix_winding: bool,
+    gzip_level: u32,
+    cleanup: &FeatureCleanup,
+) -> Result<PruneStats, VtError> {
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    prune_pmtiles_layer_only_with_threads(
+        input,
+        output,
+        selection,
+        apply_filters,
+        keep_unknown_filters,
+        fix_winding,
+        gzip_level,
+        None,
+        cleanup,
+        threads,
+        true,
+        false,
+        None,
+        &HashMap::new(),
+        false,
+        PmtilesWriteOptions::default(),
+    )
+}
+
+/// Like `prune_pmtiles_layer_only`, with an explicit worker count: one
+/// reader walks the directories, `threads` workers prune and re-encode, and
+/// the collector writes tiles in tile id order. With `recompress` the output
+/// tiles (and the header's tile compression) use that codec instead of the input's;
+/// `write_options` sets the brotli quality and window of the output.
+#[allow(clippy::too_many_arguments)]
+pub fn prune_pmtiles_layer_only_with_threads(
+    input: &Path,
+    output: &Path,
+    selection: impl Into<crate::style::LayerSelection>,
+    apply_filters: bool,
+    keep_unknown_filters: bool,
+    fix_winding: bool,
+    gzip_level: u32,
+    recompress: Option<TileCompression>,
+    cleanup: &FeatureCleanup,
+    threads: usize,
+    no_progress: bool,
+    clamp_zoom: bool,
+    keep_keys: Option<&HashMap<String, HashSet<String>>>,
+    layer_renames: &HashMap<String, String>,
+    keep_original_metadata: bool,
+    write_options: PmtilesWriteOptions,
+) -> Result<PruneStats, VtError> {
+    prune_pmtiles_layer_only_with_threads_impl(
+        input,
+        output,
+        selection.into(),
+        apply_filters,
+        keep_unknown_filters,
+        fix_winding,
+        gzip_level,
+        recompress,
+        cleanup,
+        threads,
+        no_progress,
+        clamp_zoom,
+        keep_keys,
+        layer_renames,
+        keep_original_metadata,
+        write_options,
+    )
+    .map_err(VtError::from)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prune_pmtiles_layer_only_with_threads_impl(
+    input: &Path,
+    output: &Path,
+    selection: crate::style::LayerSelection,
     apply_filters: bool,
     keep_unknown_filters: bool,
+    fix_winding: bool,
+    gzip_level: u32,
+    recompress: Option<TileCompression>,
+    cleanup: &FeatureCleanup,
+    threads: usize,
+    no_progress: bool,
+    clamp_zoom: bool,
+    keep_keys: Option<&HashMap<String, HashSet<String>>>,
+    layer_renames: &HashMap<String, String>,
+    keep_original_metadata: bool,
+    write_options: PmtilesWriteOptions,
 ) -> Result<PruneStats> {
     ensure_pmtiles_path(input)?;
     ensure_pmtiles_path(output)?;
+    write_options.validate()?;
 
-    let file = File::open(input)
+    let file = LocalReader::open(input)
         .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
     let header = read_header(&file).context("read header")?;
     let root_entries =
         read_directory_section(&file, &header, header.root_offset, header.root_length)?;
 
     let metadata = read_metadata_section(&file, &header)?;
-    let keep_layers = style.source_layers();
-    let mut stats = PruneStats::default();
-    let mut tiles: Vec<(u64, Vec<u8>)> = Vec::new();
-    let mut min_zoom = u8::MAX;
-    let mut max_zoom = u8::MIN;
+    let selection = selection.with_tileset_max_zoom(Some(header.max_zoom));
+    let worker_count = threads.max(1);
+    // The header says every tile is an image, so none are decoded
+    let raster_tileset = RasterFormat::from_pmtiles_tile_type(header.tile_type).is_some();
+    if raster_tileset && recompress.is_some() {
+        anyhow::bail!("--recompress does not apply to raster tilesets");
+    }
+    let output_compression = match recompress {
+        Some(compression) => compression,
+        None => output_tile_compression(header.tile_compression, gzip_level, &write_options)?,
+    };
 
-    let mut stack = vec![root_entries];
-    let mut file = file;
-    while let Some(entries) = stack.pop() {
-        for entry in entries {
-            if entry.run_length == 0 {
-                if entry.length == 0 {
+    // Both queues carry `(tile_id, tile bytes)`; output bytes are `None` for
+    // tiles skipped by `clamp_zoom`.
+    let (tx_in, rx_in) = bounded::<(u64, Vec<u8>)>(PRUNE_QUEUE_CAPACITY);
+    let (tx_out, rx_out) = bounded::<(u64, Option<Vec<u8>>)>(PRUNE_QUEUE_CAPACITY);
+
+    let mut worker_handles = Vec::with_capacity(worker_count);
+    for _ in 0..worker_count {
+        let rx_in = rx_in.clone();
+        let tx_out = tx_out.clone();
+        let selection = selection.clone();
+        let cleanup = cleanup.clone();
+        let keep_keys = keep_keys.cloned();
+        let layer_renames = layer_renames.clone();
+        let tile_compression = header.tile_compression;
+        worker_handles.push(thread::spawn(move || -> Result<PruneStats> {
+            let mut stats = PruneStats::default();
+            while let Ok((tile_id, data)) = rx_in.recv() {
+                let (z, x, y) = tile_id_to_xyz(tile_id)?;
+                let coord = TileCoord { zoom: z, x, y };
+                if clamp_zoom && !selection.covers_zoom(z) {
+                    stats.clamped_tiles += 1;
+                    stats.record_tile(z, data.len(), None);
+                    tx_out
+                        .send((tile_id, None))
+                        .context("send processed tile")?;
                     continue;
                 }
-                let leaf_offset = header.leaf_offset + entry.offset;
-                let leaf_entries =
-                    read_directory_section(&file, &header, leaf_offset, entry.length as u64)?;
-                stack.push(leaf_entries);
-                continue;
-            }
-            let data_offset = header.data_offset + entry.offset;
-            let mut data = vec![0u8; entry.length as usize];
-            file.seek(SeekFrom::Start(data_offset))
-                .context("seek tile")?;
-            file.read_exact(&mut data).context("read tile data")?;
-            let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
-            let run = entry.run_length.max(1);
-            for idx in 0..run {
-                let tile_id = entry.tile_id + idx as u64;
-                let (z, _x, _y) = tile_id_to_xyz(tile_id);
-                min_zoom = min_zoom.min(z);
-                max_zoom = max_zoom.max(z);
+                if raster_tileset || RasterFormat::from_magic(&data).is_some() {
+                    stats.raster_tiles += 1;
+                    stats.record_tile(z, data.len(), Some(data.len()));
+                    tx_out
+                        .send((tile_id, Some(data)))
+                        .context("send processed tile")?;
+                    continue;
+                }
+                let payload = decode_tile_payload_pmtiles(&data, tile_compression)
+                    .map_err(|err| VtError::decode_tile(coord, err))?;
                 let encoded = prune_tile_layers(
                     &payload,
                     z,
-                    style,
-                    &keep_layers,
+                    &selection,
                     apply_filters,
                     keep_unknown_filters,
+                    fix_winding,
+                    &cleanup,
                     &mut stats,
+                    keep_keys.as_ref(),
+                    &layer_renames,
+                )
+                .map_err(|err| VtError::decode_tile(coord, err))?;
+                let tile_data = compress_tile_payload_with_window(
+                    &encoded.bytes,
+                    output_compression,
+                    write_options.brotli_window,
                 )?;
-                let tile_data =
-                    encode_tile_payload_pmtiles(&encoded.bytes, header.tile_compression)?;
-                tiles.push((tile_id, tile_data));
+                if recompress.is_some() {
+                    stats
+                        .recompress
+                        .get_or_insert_default()
+                        .record(data.len(), tile_data.len());
+                }
+                stats.record_tile(z, data.len(), Some(tile_data.len()));
+                tx_out
+                    .send((tile_id, Some(tile_data)))
+                    .context("send processed tile")?;
             }
+            Ok(stats)
+        }));
+    }
+    drop(rx_in);
+    drop(tx_out);
+
+    let reader_header = header.clone();
+    let reader_handle = thread::spawn(move || -> Result<()> {
+        let mut stack = vec![root_entries];
+        while let Some(entries) = stack.pop() {
+            for entry in entries {
+                if entry.run_length == 0 {
+                    if entry.length == 0 {
+                        continue;
+                    }
+                    let leaf_offset = reader_header.leaf_offset + entry.offset;
+                    stack.push(read_directory_section(
+                        &file,
+                        &reader_header,
+                        leaf_offset,
+                        entry.length as u64,
+                    )?);
+                    continue;
+                }
+                let data = file
+                    .read_tile(
+                        reader_header.data_offset + entry.offset,
+                        entry.length as u64,
+                    )
+                    .context("read tile data")?;
+                for idx in 0..entry.run_length.max(1) {
+                    // A closed channel means a worker failed; its error is reported on join.
+                    if tx_in
+                        .send((entry.tile_id + idx as u64, data.clone()))
+                        .is_err()
+                    {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Ok(())
+    });
+
+    let bar = make_eta_progress_bar(header.n_addressed_tiles, no_progress);
+    let mut writer = PmtilesWriter::create(output)?;
+    writer.set_write_options(write_options);
+    let mut extents = TileExtents::default();
+    for (tile_id, tile_data) in rx_out.iter() {
+        if let Some(tile_data) = tile_data {
+            writer.add_tile(tile_id, &tile_data)?;
+            let (z, x, y) = tile_id_to_xyz(tile_id)?;
+            extents.add(z, x, y);
         }
+        bar.inc(1);
     }
+    bar.finish_and_clear();
 
-    tiles.sort_by(|a, b| a.0.cmp(&b.0));
-    let mut entries = Vec::with_capacity(tiles.len());
-    let mut data_section = Vec::new();
-    for (tile_id, data) in tiles.iter() {
-        let offset = data_section.len() as u64;
-        let length = data.len() as u32;
-        data_section.extend_from_slice(data);
-        entries.push(Entry {
-            tile_id: *tile_id,
-            offset,
-            length,
-            run_length: 1,
-        });
+    let mut stats = PruneStats::default();
+    for handle in worker_handles {
+        let worker_stats = handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("worker thread panicked"))??;
+        stats.merge(worker_stats);
     }
-
-    let dir_bytes = encode_directory(&entries)?;
-    let dir_section = encode_internal_bytes(&dir_bytes, header.internal_compression)?;
-    let metadata_bytes = if metadata.is_empty() {
-        Vec::new()
-    } else {
-        let mut map = serde_json::Map::new();
-        for (key, value) in metadata.into_iter() {
-            map.insert(key, Value::String(value));
-        }
-        let json = Value::Object(map).to_string();
-        encode_internal_bytes(json.as_bytes(), header.internal_compression)?
+    reader_handle
+        .join()
+        .map_err(|_| anyhow::anyhow!("reader thread panicked"))??;
+
+    let mut metadata = metadata;
+    let degrees = |value: i32| f64::from(value) / 10_000_000.0;
+    let mut bounds = [
+        degrees(header.min_longitude),
+        degrees(header.min_latitude),
+        degrees(header.max_longitude),
+        degrees(header.max_latitude),
+    ];
+    let mut center = [
+        degrees(header.center_longitude),
+        degrees(header.center_latitude),
+        f64::from(header.center_zoom),
+    ];
+    if !keep_original_metadata && let Some(fitted) = extents.fit_bounds(bounds) {
+        refresh_extent_metadata(&mut metadata, &extents);
+        center = extents.fit_center(center, fitted);
+        bounds = fitted;
+    }
+    writer.set_bounds(bounds, center);
+    let output_header = Header {
+        tile_compression: output_compression.pmtiles_code(),
+        ..header
     };
-    let header = build_header_with_metadata(
-        dir_section.len() as u64,
-        metadata_bytes.len() as u64,
-        data_section.len() as u64,
-        entries.len() as u64,
-        if min_zoom == u8::MAX { 0 } else { min_zoom },
-        if max_zoom == u8::MIN { 0 } else { max_zoom },
+    finish_pmtiles_copy(writer, &output_header, metadata)?;
+
+    Ok(stats)
+}
+
+/// Finishes an archive derived from `header`, keeping its compression and tile type.
+fn finish_pmtiles_copy(
+    writer: PmtilesWriter,
+    header: &Header,
+    metadata: BTreeMap<String, String>,
+) -> Result<()> {
+    let metadata = metadata
+        .into_iter()
+        .map(|(key, value)| (key, Value::String(value)))
+        .collect();
+    writer.finish(
+        metadata,
         header.internal_compression,
         header.tile_compression,
         header.tile_type,
-    );
-
-    let mut file = File::create(output)
-        .with_context(|| format!("failed to create output pmtiles: {}", output.display()))?;
-    write_header(&file, &header).context("write header")?;
-    file.seek(SeekFrom::Start(header.root_offset))
-        .context("seek root directory")?;
-    file.write_all(&dir_section)
-        .context("write root directory")?;
+    )
+}
 
-    if header.metadata_length > 0 {
-        file.seek(SeekFrom::Start(header.metadata_offset))
-            .context("seek metadata")?;
-        file.write_all(&metadata_bytes).context("write metadata")?;
-    }
-    file.seek(SeekFrom::Start(header.data_offset))
-        .context("seek data")?;
-    file.write_all(&data_section).context("write data")?;
+pub fn simplify_pmtiles_tile(
+    input: &Path,
+    output: &Path,
+    coord: crate::mbtiles::TileCoord,
+    layers: &[String],
+    tolerance: Option<f64>,
+    buffer: u32,
+    preserve_topology: bool,
+) -> Result<crate::mbtiles::SimplifyStats, VtError> {
+    simplify_pmtiles_tile_impl(
+        input,
+        output,
+        coord,
+        layers,
+        tolerance,
+        buffer,
+        preserve_topology,
+    )
+    .map_err(VtError::from)
+}
 
-    Ok(stats)
+/// Stored bytes of tile `coord` (XYZ), still compressed as in the archive,
+/// or `None` when the archive has no such tile.
+pub fn read_pmtiles_tile(path: &Path, coord: crate::mbtiles::TileCoord) -> Result<Option<Vec<u8>>> {
+    let file = LocalReader::open(path)
+        .with_context(|| format!("failed to open input pmtiles: {}", path.display()))?;
+    let header = read_header(&file).context("read header")?;
+    DirectoryIndex::new(&file, &header)?.read_tile(tile_id_from_xyz(coord.zoom, coord.x, coord.y)?)
 }
 
-pub fn simplify_pmtiles_tile(
+fn simplify_pmtiles_tile_impl(
     input: &Path,
     output: &Path,
     coord: crate::mbtiles::TileCoord,
     layers: &[String],
     tolerance: Option<f64>,
+    buffer: u32,
+    preserve_topology: bool,
 ) -> Result<crate::mbtiles::SimplifyStats> {
     ensure_pmtiles_path(input)?;
     ensure_pmtiles_path(output)?;
+    ensure_vector_tileset(&PmtilesFile::open(input)?)?;
 
-    let file = File::open(input)
+    let file = LocalReader::open(input)
         .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
     let header = read_header(&file).context("read header")?;
-    let root_entries =
-        read_directory_section(&file, &header, header.root_offset, header.root_length)?;
     let metadata = read_metadata_section(&file, &header)?;
 
-    let target_id = tile_id_from_xyz(coord.zoom, coord.x, coord.y);
-    let mut data: Option<Vec<u8>> = None;
-
-    let mut stack = vec![root_entries];
-    let mut file = file;
-    'search: while let Some(entries) = stack.pop() {
-        for entry in entries {
-            if entry.run_length == 0 {
-                if entry.length == 0 {
-                    continue;
-                }
-                let leaf_offset = header.leaf_offset + entry.offset;
-                let leaf_entries =
-                    read_directory_section(&file, &header, leaf_offset, entry.length as u64)?;
-                stack.push(leaf_entries);
-                continue;
-            }
-            let run = entry.run_length.max(1);
-            let end = entry.tile_id + run as u64;
-            if target_id < entry.tile_id || target_id >= end {
-                continue;
-            }
-            let data_offset = header.data_offset + entry.offset;
-            let mut buf = vec![0u8; entry.length as usize];
-            file.seek(SeekFrom::Start(data_offset))
-                .context("seek tile")?;
-            file.read_exact(&mut buf).context("read tile data")?;
-            data = Some(buf);
-            break 'search;
-        }
-    }
+    let target_id = tile_id_from_xyz(coord.zoom, coord.x, coord.y)?;
+    let data = DirectoryIndex::new(&file, &header)?.read_tile(target_id)?;
 
     let Some(data) = data else {
-        anyhow::bail!(
-            "tile not found: z={} x={} y={}",
-            coord.zoom,
-            coord.x,
-            coord.y
-        );
+        return Err(VtError::TileNotFound(coord).into());
     };
 
-    let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
+    let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)
+        .map_err(|err| VtError::decode_tile(coord, err))?;
     let keep_layers: HashSet<String> = layers.iter().cloned().collect();
-    let (filtered, stats) = simplify_tile_payload(&payload, &keep_layers, tolerance)?;
-    let tile_data = encode_tile_payload_pmtiles(&filtered, header.tile_compression)?;
+    let (filtered, stats) =
+        simplify_tile_payload(&payload, &keep_layers, tolerance, buffer, preserve_topology)
+            .map_err(|err| VtError::decode_tile(coord, err))?;
+    let tile_data =
+        encode_tile_payload_pmtiles(&filtered, header.tile_compression, DEFAULT_GZIP_LEVEL)?;
 
     let entry = Entry {
         tile_id: target_id,
@@ -1347,100 +2262,248 @@ pub fn simplify_pmtiles_tile(
     Ok(stats)
 }
 
+/// Copies the whole archive and simplifies every tile selected by `options`.
+pub fn simplify_pmtiles_batch(
+    input: &Path,
+    output: &Path,
+    options: &SimplifyOptions,
+) -> Result<SimplifyBatchStats, VtError> {
+    simplify_pmtiles_batch_impl(input, output, options).map_err(VtError::from)
+}
+
+fn simplify_pmtiles_batch_impl(
+    input: &Path,
+    output: &Path,
+    options: &SimplifyOptions,
+) -> Result<SimplifyBatchStats> {
+    ensure_pmtiles_path(input)?;
+    ensure_pmtiles_path(output)?;
+    ensure_vector_tileset(&PmtilesFile::open(input)?)?;
+
+    let file = LocalReader::open(input)
+        .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
+    let header = read_header(&file).context("read header")?;
+    let root_entries =
+        read_directory_section(&file, &header, header.root_offset, header.root_length)?;
+    let metadata = read_metadata_section(&file, &header)?;
+
+    let simplify_layers: HashSet<String> = options.layers.iter().cloned().collect();
+    let mut stats = SimplifyBatchStats::default();
+    let mut writer = PmtilesWriter::create(output)?;
+
+    let mut stack = vec![root_entries];
+    while let Some(entries) = stack.pop() {
+        for entry in entries {
+            if entry.run_length == 0 {
+                if entry.length == 0 {
+                    continue;
+                }
+                let leaf_offset = header.leaf_offset + entry.offset;
+                let leaf_entries =
+                    read_directory_section(&file, &header, leaf_offset, entry.length as u64)?;
+                stack.push(leaf_entries);
+                continue;
+            }
+            let data = file
+                .read_tile(header.data_offset + entry.offset, entry.length as u64)
+                .context("read tile data")?;
+            let run = entry.run_length.max(1);
+            for idx in 0..run {
+                let tile_id = entry.tile_id + idx as u64;
+                let (z, x, y) = tile_id_to_xyz(tile_id)?;
+                stats.tiles_read += 1;
+                if !options.matches(z, entry.length as u64) {
+                    stats.record_tile(z, data.len(), Some(data.len()));
+                    writer.add_tile(tile_id, &data)?;
+                    continue;
+                }
+                let coord = TileCoord { zoom: z, x, y };
+                let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)
+                    .map_err(|err| VtError::decode_tile(coord, err))?;
+                let (simplified, tile_stats) = simplify_tile_layers(
+                    &payload,
+                    &simplify_layers,
+                    options.tolerance,
+                    options.buffer,
+                    options.preserve_topology,
+                )
+                .map_err(|err| VtError::decode_tile(coord, err))?;
+                stats.record(z, tile_stats);
+                let tile_data = encode_tile_payload_pmtiles(
+                    &simplified,
+                    header.tile_compression,
+                    DEFAULT_GZIP_LEVEL,
+                )?;
+                stats.record_tile(z, data.len(), Some(tile_data.len()));
+                writer.add_tile(tile_id, &tile_data)?;
+            }
+        }
+    }
+
+    finish_pmtiles_copy(writer, &header, metadata)?;
+
+    Ok(stats)
+}
+
+/// Copy `input` to `output` verbatim except for the tile at `coord` (XYZ),
+/// whose stored bytes become `data`.
+pub fn replace_pmtiles_tile(
+    input: &Path,
+    output: &Path,
+    coord: crate::mbtiles::TileCoord,
+    data: &[u8],
+) -> Result<()> {
+    ensure_pmtiles_path(input)?;
+    ensure_pmtiles_path(output)?;
+
+    let file = LocalReader::open(input)
+        .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
+    let header = read_header(&file).context("read header")?;
+    let root_entries =
+        read_directory_section(&file, &header, header.root_offset, header.root_length)?;
+    let metadata = read_metadata_section(&file, &header)?;
+
+    let target_id = tile_id_from_xyz(coord.zoom, coord.x, coord.y)?;
+    let mut replaced = false;
+    let mut writer = PmtilesWriter::create(output)?;
+
+    let mut stack = vec![root_entries];
+    while let Some(entries) = stack.pop() {
+        for entry in entries {
+            if entry.run_length == 0 {
+                if entry.length == 0 {
+                    continue;
+                }
+                let leaf_offset = header.leaf_offset + entry.offset;
+                let leaf_entries =
+                    read_directory_section(&file, &header, leaf_offset, entry.length as u64)?;
+                stack.push(leaf_entries);
+                continue;
+            }
+            let tile_data = file
+                .read_tile(header.data_offset + entry.offset, entry.length as u64)
+                .context("read tile data")?;
+            for idx in 0..entry.run_length.max(1) {
+                let tile_id = entry.tile_id + idx as u64;
+                if tile_id == target_id {
+                    writer.add_tile(tile_id, data)?;
+                    replaced = true;
+                } else {
+                    writer.add_tile(tile_id, &tile_data)?;
+                }
+            }
+        }
+    }
+    if !replaced {
+        return Err(VtError::TileNotFound(coord).into());
+    }
+
+    finish_pmtiles_copy(writer, &header, metadata)
+}
+
 pub fn mbtiles_to_pmtiles(input: &Path, output: &Path) -> Result<()> {
+    mbtiles_to_pmtiles_with_options(
+        input,
+        output,
+        InternalCompression::default(),
+        None,
+        TileScheme::default(),
+        PmtilesWriteOptions::default(),
+    )?;
+    Ok(())
+}
+
+/// Converts MBTiles to PMTiles, compressing the root directory and metadata
+/// with `internal_compression` and, with `recompress`, re-encoding every tile.
+/// `scheme` is the row convention of the input's `tile_row` column;
+/// `write_options` sets the brotli quality and window.
+pub fn mbtiles_to_pmtiles_with_options(
+    input: &Path,
+    output: &Path,
+    internal_compression: InternalCompression,
+    recompress: Option<TileCompression>,
+    scheme: TileScheme,
+    write_options: PmtilesWriteOptions,
+) -> Result<RecompressStats> {
     ensure_mbtiles_path(input)?;
     ensure_pmtiles_path(output)?;
+    write_options.validate()?;
 
-    let conn = Connection::open(input)
-        .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
+    let conn = open_readonly_mbtiles(input)?;
+
+    // Gzipped BLOB values (such as `json`) are stored decompressed.
+    let metadata = read_metadata(&conn)?
+        .into_iter()
+        .map(|(name, value)| (name, Value::String(value)))
+        .collect::<serde_json::Map<_, _>>();
 
     let mut stmt = conn
-        .prepare(
-            "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles ORDER BY zoom_level, tile_column, tile_row",
-        )
+        .prepare(&select_all_tiles_ordered_query(&conn)?)
         .context("prepare tiles")?;
     let mut rows = stmt.query([]).context("query tiles")?;
 
-    let mut tiles = Vec::new();
-    let mut min_zoom = u8::MAX;
-    let mut max_zoom = u8::MIN;
+    let mut writer = PmtilesWriter::create(output)?;
+    writer.set_write_options(write_options);
+    let mut tile_compression = recompress.map(TileCompression::pmtiles_code);
+    let mut stats = RecompressStats::default();
     while let Some(row) = rows.next().context("read tile row")? {
-        let z: u8 = row.get::<_, u8>(0)?;
-        let x: u32 = row.get::<_, u32>(1)?;
-        let y: u32 = row.get::<_, u32>(2)?;
-        let data: Vec<u8> = row.get::<_, Vec<u8>>(3)?;
-        min_zoom = min_zoom.min(z);
-        max_zoom = max_zoom.max(z);
-        let tile_id = tile_id_from_xyz(z, x, y);
-        tiles.push((tile_id, data));
-    }
-
-    tiles.sort_by(|a, b| a.0.cmp(&b.0));
-
-    let mut entries = Vec::with_capacity(tiles.len());
-    let mut data_section = Vec::new();
-    for (tile_id, data) in tiles.iter() {
-        let offset = data_section.len() as u64;
-        let length = data.len() as u32;
-        data_section.extend_from_slice(data);
-        entries.push(Entry {
-            tile_id: *tile_id,
-            offset,
-            length,
-            run_length: 1,
-        });
-    }
-
-    let dir_bytes = encode_directory(&entries)?;
-    let mut header = build_header(
-        dir_bytes.len() as u64,
-        data_section.len() as u64,
-        entries.len() as u64,
-        if min_zoom == u8::MAX { 0 } else { min_zoom },
-        if max_zoom == u8::MIN { 0 } else { max_zoom },
-    );
-
-    if let Some((_, first_data)) = tiles.first() {
-        if first_data.starts_with(&[0x1f, 0x8b]) {
-            header.tile_compression = 1;
-        } else {
-            header.tile_compression = 0;
+        let Some(TileCoord { zoom: z, x, y }) = tile_address(row, 0)? else {
+            continue;
+        };
+        let y = scheme.convert_row(z, y);
+        let mut data: Vec<u8> = row.get::<_, Vec<u8>>(3)?;
+        if let Some(compression) = recompress {
+            let recompressed = compress_tile_payload_with_window(
+                &decode_tile_payload(&data)?,
+                compression,
+                write_options.brotli_window,
+            )?;
+            stats.record(data.len(), recompressed.len());
+            data = recompressed;
         }
+        tile_compression.get_or_insert(if data.starts_with(&[0x1f, 0x8b]) {
+            1
+        } else {
+            0
+        });
+        writer.add_tile(tile_id_from_xyz(z, x, y)?, &data)?;
     }
-    header.internal_compression = 0;
-
-    let file = File::create(output)
-        .with_context(|| format!("failed to create output pmtiles: {}", output.display()))?;
-    write_header(&file, &header)?;
-
-    let mut file = file;
-    file.seek(SeekFrom::Start(header.root_offset))
-        .context("seek root directory")?;
-    file.write_all(&dir_bytes).context("write root directory")?;
 
-    file.seek(SeekFrom::Start(header.data_offset))
-        .context("seek data")?;
-    file.write_all(&data_section).context("write data")?;
+    writer.finish(
+        metadata,
+        internal_compression.code(),
+        tile_compression.unwrap_or(1),
+        0,
+    )?;
+    Ok(stats)
+}
 
+pub fn pmtiles_to_mbtiles(input: &Path, output: &Path) -> Result<()> {
+    pmtiles_to_mbtiles_with_options(input, output, None, TileScheme::default())?;
     Ok(())
 }
 
-pub fn pmtiles_to_mbtiles(input: &Path, output: &Path) -> Result<()> {
+/// Like [`pmtiles_to_mbtiles`], re-encoding every tile with `recompress` when
+/// given and writing `tile_row` in `scheme`.
+pub fn pmtiles_to_mbtiles_with_options(
+    input: &Path,
+    output: &Path,
+    recompress: Option<TileCompression>,
+    scheme: TileScheme,
+) -> Result<RecompressStats> {
     ensure_pmtiles_path(input)?;
     ensure_mbtiles_path(output)?;
+    if let Some(TileCompression::Brotli(_)) = recompress {
+        anyhow::bail!("brotli tile compression is only supported for PMTiles output");
+    }
+    let mut stats = RecompressStats::default();
 
-    let file = File::open(input)
+    let file = LocalReader::open(input)
         .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
     let header = read_header(&file).context("read header")?;
-
-    let mut file = file;
-    file.seek(SeekFrom::Start(header.root_offset))
-        .context("seek root directory")?;
-    let mut dir_buf = vec![0u8; header.root_length as usize];
-    file.read_exact(&mut dir_buf)
-        .context("read root directory")?;
-    let dir_bytes = decode_internal_bytes(dir_buf, header.internal_compression)?;
-    let entries = decode_directory(&dir_bytes)?;
+    let root_entries =
+        read_directory_section(&file, &header, header.root_offset, header.root_length)?;
+    let metadata = read_metadata_section(&file, &header)?;
 
     let mut output_conn = Connection::open(output)
         .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
@@ -1462,24 +2525,52 @@ pub fn pmtiles_to_mbtiles(input: &Path, output: &Path) -> Result<()> {
         .transaction()
         .context("begin output transaction")?;
 
-    for entry in entries {
-        let mut data = vec![0u8; entry.length as usize];
-        let data_offset = header.data_offset + entry.offset;
-        file.seek(SeekFrom::Start(data_offset))
-            .context("seek tile")?;
-        file.read_exact(&mut data).context("read tile data")?;
-
-        for i in 0..entry.run_length.max(1) {
-            let tile_id = entry.tile_id + i as u64;
-            let (z, x, y) = tile_id_to_xyz(tile_id);
-            tx.execute(
-                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
-                (z as i64, x as i64, y as i64, data.clone()),
-            )
-            .context("insert tile")?;
+    for (name, value) in metadata.iter() {
+        tx.execute(
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+            (name, value),
+        )
+        .context("insert metadata")?;
+    }
+
+    let mut stack = vec![root_entries];
+    while let Some(entries) = stack.pop() {
+        for entry in entries {
+            if entry.run_length == 0 {
+                if entry.length == 0 {
+                    continue;
+                }
+                let leaf_offset = header.leaf_offset + entry.offset;
+                let leaf_entries =
+                    read_directory_section(&file, &header, leaf_offset, entry.length as u64)?;
+                stack.push(leaf_entries);
+                continue;
+            }
+            let mut data = file
+                .read_tile(header.data_offset + entry.offset, entry.length as u64)
+                .context("read tile data")?;
+            if let Some(compression) = recompress {
+                let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
+                let recompressed = compress_tile_payload(&payload, compression)?;
+                for _ in 0..entry.run_length {
+                    stats.record(data.len(), recompressed.len());
+                }
+                data = recompressed;
+            }
+
+            for i in 0..entry.run_length {
+                let tile_id = entry.tile_id + i as u64;
+                let (z, x, y) = tile_id_to_xyz(tile_id)?;
+                let y = scheme.convert_row(z, y);
+                tx.execute(
+                    "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                    (z as i64, x as i64, y as i64, data.clone()),
+                )
+                .context("insert tile")?;
+            }
         }
     }
 
     tx.commit().context("commit output")?;
-    Ok(())
+    Ok(stats)
 }
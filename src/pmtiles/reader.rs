@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Random access to the bytes of a PMTiles archive.
+///
+/// Directory and metadata reads go through `read_range`; tile payloads go
+/// through `read_tile` so remote readers can keep them out of their cache.
+pub trait RangeReader: Sync {
+    fn read_range(&self, offset: u64, length: u64) -> Result<Vec<u8>>;
+
+    fn read_tile(&self, offset: u64, length: u64) -> Result<Vec<u8>> {
+        self.read_range(offset, length)
+    }
+
+    /// Total size in bytes, when cheaply known; used in error messages.
+    fn size(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl RangeReader for File {
+    fn read_range(&self, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let mut file = self;
+        file.seek(SeekFrom::Start(offset)).context("seek range")?;
+        let mut buf = vec![0u8; length as usize];
+        file.read_exact(&mut buf).context("read range")?;
+        Ok(buf)
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.metadata().ok().map(|metadata| metadata.len())
+    }
+}
+
+/// Local PMTiles archive for directory and tile reads.
+///
+/// With the `mmap` feature the file is memory-mapped once and ranges are
+/// copied out of the mapping, avoiding a seek and read per directory and
+/// tile; it falls back to plain file reads when mapping fails or the feature
+/// is off. Every range is checked against the file size first, so offsets
+/// from a malformed header are an error rather than a panic or a huge
+/// allocation.
+pub struct LocalReader {
+    len: u64,
+    backend: LocalBackend,
+}
+
+enum LocalBackend {
+    File(File),
+    #[cfg(feature = "mmap")]
+    Mmap(memmap2::Mmap),
+}
+
+impl LocalReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        // SAFETY: the archive is only read; like other PMTiles readers we
+        // assume it is not truncated by another process while mapped.
+        #[cfg(feature = "mmap")]
+        if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+            return Ok(Self {
+                len,
+                backend: LocalBackend::Mmap(map),
+            });
+        }
+        Ok(Self {
+            len,
+            backend: LocalBackend::File(file),
+        })
+    }
+
+    /// Whether reads are served from a memory map.
+    pub fn is_mapped(&self) -> bool {
+        match self.backend {
+            LocalBackend::File(_) => false,
+            #[cfg(feature = "mmap")]
+            LocalBackend::Mmap(_) => true,
+        }
+    }
+
+    fn checked_range(&self, offset: u64, length: u64) -> Result<Range<usize>> {
+        match offset.checked_add(length) {
+            Some(end) if end <= self.len => Ok(offset as usize..end as usize),
+            _ => anyhow::bail!(
+                "range {offset}+{length} is beyond the end of the archive ({} bytes)",
+                self.len
+            ),
+        }
+    }
+}
+
+impl RangeReader for LocalReader {
+    fn read_range(&self, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let range = self.checked_range(offset, length)?;
+        match &self.backend {
+            LocalBackend::File(file) => file.read_range(offset, range.len() as u64),
+            #[cfg(feature = "mmap")]
+            LocalBackend::Mmap(map) => Ok(map[range].to_vec()),
+        }
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.len)
+    }
+}
+
+#[cfg(feature = "http")]
+pub use self::http::HttpRangeReader;
+
+#[cfg(feature = "http")]
+mod http {
+    use super::RangeReader;
+    use anyhow::{Context, Result};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// The PMTiles spec guarantees the header and root directory fit here.
+    const INITIAL_FETCH_BYTES: u64 = 16_384;
+    /// Directory reads are widened to this size so neighbouring leaf
+    /// directories are served from the cache.
+    const MIN_FETCH_BYTES: u64 = 256 * 1024;
+
+    /// PMTiles archive served over HTTP(S) and read with Range requests.
+    ///
+    /// Fetched directory/metadata ranges are cached so repeated directory
+    /// walks do not issue new requests; tile payloads are never cached.
+    pub struct HttpRangeReader {
+        url: String,
+        agent: ureq::Agent,
+        cache: Mutex<Vec<(u64, Vec<u8>)>>,
+        requests: AtomicU64,
+    }
+
+    impl HttpRangeReader {
+        pub fn open(url: &str) -> Result<Self> {
+            let reader = Self {
+                url: url.to_string(),
+                agent: ureq::Agent::new_with_defaults(),
+                cache: Mutex::new(Vec::new()),
+                requests: AtomicU64::new(0),
+            };
+            let initial = reader.fetch(0, INITIAL_FETCH_BYTES)?;
+            reader.cache_insert(0, initial)?;
+            Ok(reader)
+        }
+
+        pub fn url(&self) -> &str {
+            &self.url
+        }
+
+        /// Number of HTTP requests issued so far.
+        pub fn request_count(&self) -> u64 {
+            self.requests.load(Ordering::Relaxed)
+        }
+
+        fn fetch(&self, offset: u64, length: u64) -> Result<Vec<u8>> {
+            if length == 0 {
+                return Ok(Vec::new());
+            }
+            let end = offset + length - 1;
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            let mut response = self
+                .agent
+                .get(&self.url)
+                .header("Range", format!("bytes={offset}-{end}"))
+                .call()
+                .with_context(|| format!("range request failed: {}", self.url))?;
+            if response.status().as_u16() != 206 {
+                anyhow::bail!(
+                    "server did not honor range request (status {}): {}",
+                    response.status(),
+                    self.url
+                );
+            }
+            response
+                .body_mut()
+                .with_config()
+                .limit(length)
+                .read_to_vec()
+                .with_context(|| format!("read range response: {}", self.url))
+        }
+
+        fn cache_lookup(&self, offset: u64, length: u64) -> Result<Option<Vec<u8>>> {
+            let cache = self
+                .cache
+                .lock()
+                .map_err(|_| anyhow::anyhow!("range cache lock poisoned"))?;
+            for (start, data) in cache.iter() {
+                let cached_end = start + data.len() as u64;
+                if offset >= *start && offset + length <= cached_end {
+                    let from = (offset - start) as usize;
+                    return Ok(Some(data[from..from + length as usize].to_vec()));
+                }
+            }
+            Ok(None)
+        }
+
+        fn cache_insert(&self, offset: u64, data: Vec<u8>) -> Result<()> {
+            self.cache
+                .lock()
+                .map_err(|_| anyhow::anyhow!("range cache lock poisoned"))?
+                .push((offset, data));
+            Ok(())
+        }
+    }
+
+    impl RangeReader for HttpRangeReader {
+        fn read_range(&self, offset: u64, length: u64) -> Result<Vec<u8>> {
+            if let Some(data) = self.cache_lookup(offset, length)? {
+                return Ok(data);
+            }
+            let data = self.fetch(offset, length.max(MIN_FETCH_BYTES))?;
+            if (data.len() as u64) < length {
+                anyhow::bail!("short range response: {}", self.url);
+            }
+            let result = data[..length as usize].to_vec();
+            self.cache_insert(offset, data)?;
+            Ok(result)
+        }
+
+        fn read_tile(&self, offset: u64, length: u64) -> Result<Vec<u8>> {
+            let data = self.fetch(offset, length)?;
+            if (data.len() as u64) < length {
+                anyhow::bail!("short range response: {}", self.url);
+            }
+            Ok(data)
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::format::RasterFormat;
+use crate::mbtiles::TileCoord;
+use crate::pmtiles::{
+    DirectoryIndex, Entry, Header, decode_tile_payload_pmtiles, ensure_pmtiles_path, pow4,
+    read_directory_section, read_header, read_metadata_section, tile_id_from_xyz, tile_id_to_xyz,
+    tile_id_zoom,
+};
+use crate::source::{TileRow, TileSource, detect_raster_format};
+
+/// PMTiles file on disk. The header and root directory are read once; every
+/// scan opens its own file handle so zoom levels can be read in parallel.
+#[derive(Debug, Clone)]
+pub struct PmtilesFile {
+    path: PathBuf,
+    header: Header,
+    root_entries: Vec<Entry>,
+}
+
+impl PmtilesFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        ensure_pmtiles_path(path)?;
+        let file = File::open(path)
+            .with_context(|| format!("failed to open input pmtiles: {}", path.display()))?;
+        let header = read_header(&file).context("read header")?;
+        let root_entries =
+            read_directory_section(&file, &header, header.root_offset, header.root_length)
+                .context("read root directory")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            header,
+            root_entries,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn open_file(&self) -> Result<File> {
+        File::open(&self.path)
+            .with_context(|| format!("failed to open input pmtiles: {}", self.path.display()))
+    }
+
+    /// Visit every tile entry (leaf directories resolved) in tile id order.
+    /// `visit` returns `Ok(false)` to stop the walk.
+    fn walk_entries(
+        &self,
+        file: &File,
+        entries: &[Entry],
+        visit: &mut dyn FnMut(&Entry) -> Result<bool>,
+    ) -> Result<bool> {
+        for entry in entries {
+            if entry.run_length == 0 {
+                if entry.length == 0 {
+                    continue;
+                }
+                let leaf_offset = self.header.leaf_offset + entry.offset;
+                let leaf_entries =
+                    read_directory_section(file, &self.header, leaf_offset, entry.length as u64)?;
+                if !self.walk_entries(file, &leaf_entries, visit)? {
+                    return Ok(false);
+                }
+                continue;
+            }
+            if !visit(entry)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn read_tile(&self, mut file: &File, entry: &Entry) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; entry.length as usize];
+        file.seek(SeekFrom::Start(self.header.data_offset + entry.offset))
+            .context("seek tile")?;
+        file.read_exact(&mut buf).context("read tile data")?;
+        Ok(buf)
+    }
+}
+
+impl TileSource for PmtilesFile {
+    fn metadata(&self) -> Result<BTreeMap<String, String>> {
+        read_metadata_section(&self.open_file()?, &self.header)
+    }
+
+    fn zoom_counts(&self) -> Result<BTreeMap<u8, u64>> {
+        let file = self.open_file()?;
+        let mut counts = BTreeMap::new();
+        self.walk_entries(&file, &self.root_entries, &mut |entry| {
+            for idx in 0..entry.run_length.max(1) {
+                let z = tile_id_zoom(entry.tile_id + idx as u64)?;
+                *counts.entry(z).or_insert(0u64) += 1;
+            }
+            Ok(true)
+        })?;
+        Ok(counts)
+    }
+
+    fn scan_tiles(
+        &self,
+        zoom: u8,
+        columns: Option<(i64, i64)>,
+        with_data: bool,
+        visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+    ) -> Result<()> {
+        let file = self.open_file()?;
+        let first_id = tile_id_from_xyz(zoom, 0, 0)?;
+        let end_id = first_id + pow4(zoom);
+        self.walk_entries(&file, &self.root_entries, &mut |entry| {
+            let run = entry.run_length.max(1) as u64;
+            if entry.tile_id + run <= first_id {
+                return Ok(true);
+            }
+            if entry.tile_id >= end_id {
+                return Ok(false);
+            }
+            let data = if with_data {
+                Some(self.read_tile(&file, entry)?)
+            } else {
+                None
+            };
+            for idx in 0..run {
+                let tile_id = entry.tile_id + idx;
+                if tile_id < first_id || tile_id >= end_id {
+                    continue;
+                }
+                let (z, x, y) = tile_id_to_xyz(tile_id)?;
+                if let Some((col_min, col_max)) = columns
+                    && (i64::from(x) < col_min || i64::from(x) > col_max)
+                {
+                    continue;
+                }
+                let tile = TileRow {
+                    zoom: z,
+                    x,
+                    y,
+                    length: entry.length as u64,
+                    data: data.clone(),
+                    content_id: None,
+                };
+                if !visit(tile)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        })?;
+        Ok(())
+    }
+
+    fn tile_data(&self, coord: TileCoord) -> Result<Option<Vec<u8>>> {
+        let file = self.open_file()?;
+        let target_id = tile_id_from_xyz(coord.zoom, coord.x, coord.y)?;
+        DirectoryIndex::with_root(&file, &self.header, self.root_entries.clone())
+            .read_tile(target_id)
+    }
+
+    fn decode_tile(&self, data: &[u8]) -> Result<Vec<u8>> {
+        decode_tile_payload_pmtiles(data, self.header.tile_compression)
+    }
+
+    fn raster_format(&self) -> Result<Option<RasterFormat>> {
+        match self.header.tile_type {
+            0 => detect_raster_format(self),
+            tile_type => Ok(RasterFormat::from_pmtiles_tile_type(tile_type)),
+        }
+    }
+}
@@ -1,11 +1,15 @@
-use crate::mbtiles::MbtilesStats;
-use std::collections::HashSet;
+use crate::mbtiles::{
+    KeyCardinality, LayerTileStats, MbtilesStats, SizeQuantiles, TopValueCounter,
+};
+use std::collections::{BTreeMap, HashSet};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Default)]
 pub struct StatAccum {
     pub tile_count: u64,
     pub total_bytes: u64,
     pub max_bytes: u64,
+    /// Entry lengths kept for percentile statistics (only with `--percentiles`).
+    pub sizes: Option<SizeQuantiles>,
 }
 
 impl StatAccum {
@@ -13,9 +17,23 @@ impl StatAccum {
         self.tile_count += 1;
         self.total_bytes += length;
         self.max_bytes = self.max_bytes.max(length);
+        if let Some(sizes) = self.sizes.as_mut() {
+            sizes.insert(length);
+        }
+    }
+
+    pub fn merge(&mut self, other: &StatAccum) {
+        self.tile_count += other.tile_count;
+        self.total_bytes += other.total_bytes;
+        self.max_bytes = self.max_bytes.max(other.max_bytes);
+        match (self.sizes.as_mut(), other.sizes.as_ref()) {
+            (Some(sizes), Some(other)) => sizes.merge(other),
+            (None, Some(other)) => self.sizes = Some(other.clone()),
+            _ => {}
+        }
     }
 
-    pub fn into_stats(self) -> MbtilesStats {
+    pub fn into_stats(mut self) -> MbtilesStats {
         let avg_bytes = if self.tile_count == 0 {
             0
         } else {
@@ -26,6 +44,7 @@ impl StatAccum {
             total_bytes: self.total_bytes,
             max_bytes: self.max_bytes,
             avg_bytes,
+            percentiles: self.sizes.as_mut().and_then(SizeQuantiles::percentiles),
         }
     }
 }
@@ -37,6 +56,13 @@ pub struct LayerAccum {
     pub layer_type: Option<String>,
     pub property_keys: HashSet<String>,
     pub property_values: HashSet<String>,
+    pub invalid_geometry_count: u64,
+    pub geometry_bytes: u64,
+    pub attribute_bytes: u64,
+    pub field_length_bytes_by_zoom: BTreeMap<u8, u64>,
+    pub top_values: TopValueCounter,
+    pub key_cardinality: KeyCardinality,
+    pub tile_stats: LayerTileStats,
 }
 
 impl LayerAccum {
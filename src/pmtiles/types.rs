@@ -1,6 +1,8 @@
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::time::Duration;
 
+use crate::mbtiles::{DEFAULT_BROTLI_QUALITY, DEFAULT_BROTLI_WINDOW};
+
 pub const HEADER_SIZE: usize = 127;
 pub const MAGIC: &[u8; 7] = b"PMTiles";
 pub const VERSION: u8 = 3;
@@ -33,6 +35,62 @@ pub struct Header {
     pub center_latitude: i32,
 }
 
+/// Compression for PMTiles directories and metadata.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InternalCompression {
+    None,
+    Gzip,
+    #[default]
+    Brotli,
+}
+
+impl InternalCompression {
+    /// Code understood by `encode_internal_bytes` / `decode_internal_bytes`.
+    pub fn code(self) -> u8 {
+        match self {
+            InternalCompression::None => 0,
+            InternalCompression::Gzip => 1,
+            InternalCompression::Brotli => 2,
+        }
+    }
+}
+
+/// Encoder settings for PMTiles output.
+///
+/// Brotli quality applies to directories, metadata, and tiles re-encoded in
+/// the input's brotli codec (`--recompress brotli:N` sets its own quality);
+/// the window applies to everything written with brotli. Higher quality and
+/// larger windows give smaller archives at the cost of much slower writes
+/// (quality 11 is several times slower than the default 5).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PmtilesWriteOptions {
+    /// Brotli quality 0-11.
+    pub brotli_quality: u32,
+    /// Brotli window bits 10-24 (window size `2^bits - 16` bytes).
+    pub brotli_window: u32,
+}
+
+impl Default for PmtilesWriteOptions {
+    fn default() -> Self {
+        Self {
+            brotli_quality: DEFAULT_BROTLI_QUALITY,
+            brotli_window: DEFAULT_BROTLI_WINDOW,
+        }
+    }
+}
+
+impl PmtilesWriteOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.brotli_quality > 11 {
+            anyhow::bail!("brotli quality must be between 0 and 11");
+        }
+        if !(10..=24).contains(&self.brotli_window) {
+            anyhow::bail!("brotli window must be between 10 and 24");
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub tile_id: u64,
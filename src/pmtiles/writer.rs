@@ -0,0 +1,219 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::pmtiles::{
+    Entry, HEADER_SIZE, PmtilesWriteOptions, build_header_with_metadata, encode_directory,
+    encode_internal_bytes_with_options, tile_id_zoom, write_header,
+};
+
+/// Readers fetch the header and root directory with one 16 KiB request, so
+/// the compressed root must fit in what the header leaves of it.
+const MAX_ROOT_DIRECTORY_BYTES: usize = 16 * 1024 - HEADER_SIZE;
+
+/// Entries per leaf directory tried first; grown until the root fits.
+const INITIAL_LEAF_ENTRIES: usize = 4096;
+
+/// Writes a PMTiles archive without holding the data section in memory.
+///
+/// Tile blobs are streamed to an unnamed temporary file next to the output
+/// while their offsets are recorded; [`PmtilesWriter::finish`] then writes
+/// the header, root directory, metadata, and leaf directories (when the
+/// root alone would not fit in the first 16 KiB), and copies the data after
+/// them.
+/// Tiles may be added in any order (the archive is written unclustered).
+pub struct PmtilesWriter {
+    output: PathBuf,
+    data: BufWriter<File>,
+    data_length: u64,
+    entries: Vec<Entry>,
+    min_zoom: u8,
+    max_zoom: u8,
+    /// Header bounds and center (lon, lat, zoom); the whole world otherwise.
+    bounds: Option<([f64; 4], [f64; 3])>,
+    write_options: PmtilesWriteOptions,
+}
+
+impl PmtilesWriter {
+    pub fn create(output: &Path) -> Result<Self> {
+        let dir = output
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let data = tempfile::tempfile_in(dir).with_context(|| {
+            format!("failed to create temporary data file in {}", dir.display())
+        })?;
+        Ok(Self {
+            output: output.to_path_buf(),
+            data: BufWriter::new(data),
+            data_length: 0,
+            entries: Vec::new(),
+            min_zoom: u8::MAX,
+            max_zoom: u8::MIN,
+            bounds: None,
+            write_options: PmtilesWriteOptions::default(),
+        })
+    }
+
+    pub fn add_tile(&mut self, tile_id: u64, data: &[u8]) -> Result<()> {
+        let length = u32::try_from(data.len()).context("tile larger than 4 GiB")?;
+        self.data.write_all(data).context("write tile data")?;
+        self.entries.push(Entry {
+            tile_id,
+            offset: self.data_length,
+            length,
+            run_length: 1,
+        });
+        self.data_length += data.len() as u64;
+        let z = tile_id_zoom(tile_id)?;
+        self.min_zoom = self.min_zoom.min(z);
+        self.max_zoom = self.max_zoom.max(z);
+        Ok(())
+    }
+
+    /// Set the header bounds (`[west, south, east, north]`) and center
+    /// (`[lon, lat, zoom]`) in degrees.
+    pub fn set_bounds(&mut self, bounds: [f64; 4], center: [f64; 3]) {
+        self.bounds = Some((bounds, center));
+    }
+
+    /// Set the brotli quality and window for the directory and metadata.
+    pub fn set_write_options(&mut self, options: PmtilesWriteOptions) {
+        self.write_options = options;
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn finish(
+        mut self,
+        metadata: serde_json::Map<String, Value>,
+        internal_compression: u8,
+        tile_compression: u8,
+        tile_type: u8,
+    ) -> Result<()> {
+        self.entries.sort_by_key(|entry| entry.tile_id);
+        let (dir_section, leaf_section) =
+            build_directories(&self.entries, internal_compression, &self.write_options)?;
+        let metadata_bytes = if metadata.is_empty() {
+            Vec::new()
+        } else {
+            let json = Value::Object(metadata).to_string();
+            encode_internal_bytes_with_options(
+                json.as_bytes(),
+                internal_compression,
+                &self.write_options,
+            )?
+        };
+        let mut header = build_header_with_metadata(
+            dir_section.len() as u64,
+            metadata_bytes.len() as u64,
+            self.data_length,
+            self.entries.len() as u64,
+            if self.min_zoom == u8::MAX {
+                0
+            } else {
+                self.min_zoom
+            },
+            if self.max_zoom == u8::MIN {
+                0
+            } else {
+                self.max_zoom
+            },
+            internal_compression,
+            tile_compression,
+            tile_type,
+        );
+        if let Some((bounds, center)) = self.bounds {
+            let e7 = |value: f64| (value * 10_000_000.0).round() as i32;
+            header.min_longitude = e7(bounds[0]);
+            header.min_latitude = e7(bounds[1]);
+            header.max_longitude = e7(bounds[2]);
+            header.max_latitude = e7(bounds[3]);
+            header.center_longitude = e7(center[0]);
+            header.center_latitude = e7(center[1]);
+            header.center_zoom = center[2].round() as u8;
+        }
+        if !leaf_section.is_empty() {
+            header.leaf_offset = header.data_offset;
+            header.leaf_length = leaf_section.len() as u64;
+            header.data_offset += header.leaf_length;
+        }
+
+        let mut data = self
+            .data
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .context("flush tile data")?;
+        data.seek(SeekFrom::Start(0)).context("rewind tile data")?;
+
+        let mut file = File::create(&self.output).with_context(|| {
+            format!("failed to create output pmtiles: {}", self.output.display())
+        })?;
+        write_header(&file, &header).context("write header")?;
+        file.seek(SeekFrom::Start(header.root_offset))
+            .context("seek root directory")?;
+        file.write_all(&dir_section)
+            .context("write root directory")?;
+        if header.metadata_length > 0 {
+            file.seek(SeekFrom::Start(header.metadata_offset))
+                .context("seek metadata")?;
+            file.write_all(&metadata_bytes).context("write metadata")?;
+        }
+        if header.leaf_length > 0 {
+            file.seek(SeekFrom::Start(header.leaf_offset))
+                .context("seek leaf directories")?;
+            file.write_all(&leaf_section)
+                .context("write leaf directories")?;
+        }
+        file.seek(SeekFrom::Start(header.data_offset))
+            .context("seek data")?;
+        io::copy(&mut data, &mut file).context("write data")?;
+        Ok(())
+    }
+}
+
+/// Encode `entries` (sorted by tile id) as a root directory section, and
+/// leaf directory sections when the root alone exceeds
+/// [`MAX_ROOT_DIRECTORY_BYTES`]. Leaves start at [`INITIAL_LEAF_ENTRIES`]
+/// entries each, grown by a fifth until the root pointing at them fits.
+fn build_directories(
+    entries: &[Entry],
+    internal_compression: u8,
+    options: &PmtilesWriteOptions,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let encode = |entries: &[Entry]| -> Result<Vec<u8>> {
+        encode_internal_bytes_with_options(
+            &encode_directory(entries)?,
+            internal_compression,
+            options,
+        )
+    };
+    let root = encode(entries)?;
+    if root.len() <= MAX_ROOT_DIRECTORY_BYTES {
+        return Ok((root, Vec::new()));
+    }
+    let mut leaf_entries = INITIAL_LEAF_ENTRIES;
+    loop {
+        let mut root_entries = Vec::with_capacity(entries.len().div_ceil(leaf_entries));
+        let mut leaves = Vec::new();
+        for chunk in entries.chunks(leaf_entries) {
+            let leaf = encode(chunk)?;
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: u32::try_from(leaf.len()).context("leaf directory larger than 4 GiB")?,
+                run_length: 0,
+            });
+            leaves.extend_from_slice(&leaf);
+        }
+        let root = encode(&root_entries)?;
+        if root.len() <= MAX_ROOT_DIRECTORY_BYTES {
+            return Ok((root, leaves));
+        }
+        leaf_entries += leaf_entries / 5;
+    }
+}
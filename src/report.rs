@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::mbtiles::{PruneStats, SimplifyBatchStats, ZoomTileCounts};
+
+/// Bumped whenever a field of [`OptimizeReport`] is renamed or removed.
+pub const OPTIMIZE_REPORT_VERSION: u32 = 1;
+
+/// A file read or written by the run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportFile {
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+impl ReportFile {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let size_bytes = fs::metadata(path)
+            .with_context(|| format!("failed to read file size: {}", path.display()))?
+            .len();
+        Ok(Self {
+            path: path.display().to_string(),
+            size_bytes,
+        })
+    }
+}
+
+/// Run report written by `optimize --report` and `simplify --report`.
+///
+/// Field names are stable so reports from different runs can be diffed.
+#[derive(Debug, Serialize)]
+pub struct OptimizeReport {
+    pub version: u32,
+    pub command: String,
+    pub input: ReportFile,
+    pub output: ReportFile,
+    pub wall_time_secs: f64,
+    /// Options the run was invoked with.
+    pub options: Value,
+    pub totals: ZoomTileCounts,
+    pub zooms: BTreeMap<u8, ZoomTileCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune: Option<PruneStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simplify: Option<SimplifyBatchStats>,
+}
+
+impl OptimizeReport {
+    pub fn for_prune(
+        input: &Path,
+        output: &Path,
+        wall_time: Duration,
+        options: Value,
+        stats: PruneStats,
+    ) -> Result<Self> {
+        let mut report = Self::new("optimize", input, output, wall_time, options)?;
+        report.set_zooms(stats.tiles_by_zoom.clone());
+        report.prune = Some(stats);
+        Ok(report)
+    }
+
+    pub fn for_simplify(
+        input: &Path,
+        output: &Path,
+        wall_time: Duration,
+        options: Value,
+        stats: SimplifyBatchStats,
+    ) -> Result<Self> {
+        let mut report = Self::new("simplify", input, output, wall_time, options)?;
+        report.set_zooms(stats.tiles_by_zoom.clone());
+        report.simplify = Some(stats);
+        Ok(report)
+    }
+
+    fn new(
+        command: &str,
+        input: &Path,
+        output: &Path,
+        wall_time: Duration,
+        options: Value,
+    ) -> Result<Self> {
+        Ok(Self {
+            version: OPTIMIZE_REPORT_VERSION,
+            command: command.to_string(),
+            input: ReportFile::from_path(input)?,
+            output: ReportFile::from_path(output)?,
+            wall_time_secs: wall_time.as_secs_f64(),
+            options,
+            totals: ZoomTileCounts::default(),
+            zooms: BTreeMap::new(),
+            prune: None,
+            simplify: None,
+        })
+    }
+
+    fn set_zooms(&mut self, zooms: BTreeMap<u8, ZoomTileCounts>) {
+        self.totals = ZoomTileCounts::total(zooms.values());
+        self.zooms = zooms;
+    }
+
+    /// Write the report as pretty-printed JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self).context("serialize report")?;
+        fs::write(path, json).with_context(|| format!("failed to write report: {}", path.display()))
+    }
+}
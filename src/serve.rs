@@ -0,0 +1,448 @@
+//! `serve`: a local viewer for visual QA of a tileset (`serve` feature).
+//!
+//! HTTP parsing and keep-alive are left to `tiny_http`; requests are answered
+//! by a fixed pool of handler threads, one per available core.
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::debug;
+
+use crate::format::RasterFormat;
+use crate::mbtiles::{MAX_TILE_ZOOM, MbtilesFile, TileCoord, flip_y};
+use crate::pmtiles::PmtilesFile;
+use crate::source::TileSource;
+use crate::style::resolve_vector_source;
+
+/// Port `serve` listens on when none is given.
+pub const DEFAULT_SERVE_PORT: u16 = 8080;
+
+const MAPLIBRE_VERSION: &str = "4.7.1";
+
+#[derive(Debug, Clone, Default)]
+pub struct ServeOptions {
+    /// Style whose vector source is pointed at the served tiles; a debug
+    /// style drawing every vector layer is generated when absent.
+    pub style: Option<PathBuf>,
+    /// Vector source of `style` to replace (required for several sources).
+    pub source: Option<String>,
+}
+
+/// A response produced by [`TileServer::handle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub content_encoding: Option<&'static str>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn new(status: u16, content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type,
+            content_encoding: None,
+            body,
+        }
+    }
+
+    fn text(status: u16, body: &str) -> Self {
+        Self::new(
+            status,
+            "text/plain; charset=utf-8",
+            body.as_bytes().to_vec(),
+        )
+    }
+
+    fn json(value: &Value) -> Self {
+        Self::new(
+            200,
+            "application/json",
+            serde_json::to_vec(value).unwrap_or_default(),
+        )
+    }
+}
+
+/// Serves the tiles of a [`TileSource`] at `/tiles/{z}/{x}/{y}.pbf` together
+/// with a MapLibre viewer at `/` and its style at `/style.json`.
+pub struct TileServer<S> {
+    source: S,
+    /// Tiles are looked up by TMS row (MBTiles) rather than XYZ `y`.
+    tms_rows: bool,
+    metadata: BTreeMap<String, String>,
+    raster_format: Option<RasterFormat>,
+    style: Option<Value>,
+    style_source: Option<String>,
+}
+
+impl TileServer<MbtilesFile> {
+    pub fn mbtiles(input: &Path, options: &ServeOptions) -> Result<Self> {
+        TileServer::new(MbtilesFile::open(input)?, true, options)
+    }
+}
+
+impl TileServer<PmtilesFile> {
+    pub fn pmtiles(input: &Path, options: &ServeOptions) -> Result<Self> {
+        TileServer::new(PmtilesFile::open(input)?, false, options)
+    }
+}
+
+impl<S: TileSource> TileServer<S> {
+    pub fn new(source: S, tms_rows: bool, options: &ServeOptions) -> Result<Self> {
+        let metadata = source.metadata()?;
+        let raster_format = source.raster_format()?;
+        let (style, style_source) = match &options.style {
+            Some(path) => {
+                let contents = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read style file: {}", path.display()))?;
+                let style: Value = serde_json::from_str(&contents).context("parse style json")?;
+                let style_source = resolve_vector_source(&style, options.source.as_deref())?;
+                (Some(style), style_source)
+            }
+            None => (None, None),
+        };
+        Ok(Self {
+            source,
+            tms_rows,
+            metadata,
+            raster_format,
+            style,
+            style_source,
+        })
+    }
+
+    /// Answer a `GET` of `path`; `origin` (e.g. `http://127.0.0.1:8080`)
+    /// prefixes the tile URL written into the style.
+    pub fn handle(&self, path: &str, origin: &str) -> HttpResponse {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let result = match path {
+            "/" | "/index.html" => Ok(HttpResponse::new(
+                200,
+                "text/html; charset=utf-8",
+                viewer_html().into_bytes(),
+            )),
+            "/style.json" => self
+                .style_json(origin)
+                .map(|style| HttpResponse::json(&style)),
+            _ => match path.strip_prefix("/tiles/").and_then(parse_tile_path) {
+                Some(coord) => self.tile(coord),
+                None => Ok(HttpResponse::text(404, "not found")),
+            },
+        };
+        result.unwrap_or_else(|err| HttpResponse::text(500, &format!("{err:#}")))
+    }
+
+    fn tile(&self, coord: TileCoord) -> Result<HttpResponse> {
+        if coord.zoom > MAX_TILE_ZOOM
+            || u64::from(coord.x).max(u64::from(coord.y)) >= 1u64 << coord.zoom
+        {
+            return Ok(HttpResponse::text(404, "tile out of range"));
+        }
+        let stored = if self.tms_rows {
+            TileCoord {
+                y: flip_y(coord.zoom, coord.y),
+                ..coord
+            }
+        } else {
+            coord
+        };
+        // MapLibre renders 204 as an empty tile rather than an error
+        let Some(data) = self.source.tile_data(stored)? else {
+            return Ok(HttpResponse::new(204, "application/x-protobuf", Vec::new()));
+        };
+        if let Some(format) = self.raster_format {
+            return Ok(HttpResponse::new(200, raster_content_type(format), data));
+        }
+        // Browsers decode gzip themselves; other encodings are decoded here
+        if data.starts_with(&[0x1f, 0x8b]) {
+            return Ok(HttpResponse {
+                content_encoding: Some("gzip"),
+                ..HttpResponse::new(200, "application/x-protobuf", data)
+            });
+        }
+        let payload = self.source.decode_tile(&data)?;
+        Ok(HttpResponse::new(200, "application/x-protobuf", payload))
+    }
+
+    /// The `--style` with its vector source replaced by the served tiles, or
+    /// a generated debug style.
+    pub fn style_json(&self, origin: &str) -> Result<Value> {
+        let extension = self.raster_format.map_or("pbf", RasterFormat::as_str);
+        let mut tiles = json!({
+            "type": if self.raster_format.is_some() { "raster" } else { "vector" },
+            "tiles": [format!("{origin}/tiles/{{z}}/{{x}}/{{y}}.{extension}")],
+        });
+        for key in ["minzoom", "maxzoom"] {
+            if let Some(zoom) = self.metadata.get(key).and_then(|v| v.parse::<u8>().ok()) {
+                tiles[key] = json!(zoom);
+            }
+        }
+        if let Some(style) = &self.style {
+            let mut style = style.clone();
+            if let Some(id) = &self.style_source {
+                style["sources"][id.as_str()] = tiles;
+            }
+            return Ok(style);
+        }
+
+        let mut layers = vec![json!({
+            "id": "background",
+            "type": "background",
+            "paint": { "background-color": "#f8f8f8" },
+        })];
+        if self.raster_format.is_some() {
+            layers.push(json!({ "id": "tiles", "type": "raster", "source": "tiles" }));
+        } else {
+            for (index, name) in self.vector_layer_names()?.iter().enumerate() {
+                let color = LAYER_COLORS[index % LAYER_COLORS.len()];
+                layers.extend(debug_layers(name, color));
+            }
+        }
+        let mut style = json!({
+            "version": 8,
+            "sources": { "tiles": tiles },
+            "layers": layers,
+        });
+        if let Some((lon, lat, zoom)) = self.center() {
+            style["center"] = json!([lon, lat]);
+            style["zoom"] = json!(zoom);
+        }
+        Ok(style)
+    }
+
+    /// Layer names from the metadata `vector_layers`, else from the first
+    /// stored tile.
+    fn vector_layer_names(&self) -> Result<Vec<String>> {
+        let vector_layers = self
+            .metadata
+            .get("json")
+            .and_then(|json| serde_json::from_str::<Value>(json).ok())
+            .and_then(|json| json.get("vector_layers").cloned())
+            .or_else(|| {
+                self.metadata
+                    .get("vector_layers")
+                    .and_then(|layers| serde_json::from_str(layers).ok())
+            });
+        if let Some(layers) = vector_layers.as_ref().and_then(Value::as_array) {
+            return Ok(layers
+                .iter()
+                .filter_map(|layer| layer.get("id").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect());
+        }
+        let Some(&zoom) = self.source.zoom_counts()?.keys().next() else {
+            return Ok(Vec::new());
+        };
+        let mut names = Vec::new();
+        let mut error = None;
+        self.source.scan_tiles(zoom, None, true, &mut |tile| {
+            let Some(data) = tile.data else {
+                return Ok(true);
+            };
+            let payload = self.source.decode_tile(&data)?;
+            match mvt_reader::Reader::new(payload) {
+                Ok(reader) => names = reader.get_layer_names().unwrap_or_default(),
+                Err(err) => error = Some(anyhow::anyhow!("decode vector tile: {err}")),
+            }
+            Ok(false)
+        })?;
+        match error {
+            Some(err) => Err(err),
+            None => Ok(names),
+        }
+    }
+
+    /// Initial view from the metadata `center`, else the middle of `bounds`.
+    fn center(&self) -> Option<(f64, f64, f64)> {
+        let numbers = |key: &str| -> Option<Vec<f64>> {
+            self.metadata
+                .get(key)?
+                .trim_matches(|c| c == '[' || c == ']')
+                .split(',')
+                .map(|part| part.trim().parse().ok())
+                .collect()
+        };
+        if let Some(center) = numbers("center").filter(|center| center.len() >= 2) {
+            return Some((center[0], center[1], center.get(2).copied().unwrap_or(0.0)));
+        }
+        let bounds = numbers("bounds").filter(|bounds| bounds.len() == 4)?;
+        let zoom = self
+            .metadata
+            .get("minzoom")
+            .and_then(|zoom| zoom.parse().ok())
+            .unwrap_or(0.0);
+        Some((
+            (bounds[0] + bounds[2]) / 2.0,
+            (bounds[1] + bounds[3]) / 2.0,
+            zoom,
+        ))
+    }
+
+    /// Answer requests on `listener` until it fails, with one handler
+    /// thread per available core.
+    pub fn serve(&self, listener: TcpListener) -> Result<()>
+    where
+        S: Sync,
+    {
+        let fallback_host = listener
+            .local_addr()
+            .context("listener address")?
+            .to_string();
+        let server = Server::from_listener(listener, None)
+            .map_err(|err| anyhow::anyhow!("start http server: {err}"))?;
+        let handlers = thread::available_parallelism().map_or(4, usize::from);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..handlers)
+                .map(|_| {
+                    scope.spawn(|| -> Result<()> {
+                        loop {
+                            let request = server.recv().context("receive request")?;
+                            self.respond(request, &fallback_host);
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().expect("serve handler panicked"))
+        })
+    }
+
+    fn respond(&self, request: Request, fallback_host: &str) {
+        let host = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Host"))
+            .map(|header| header.value.to_string());
+        let origin = format!("http://{}", host.as_deref().unwrap_or(fallback_host));
+        let response = match request.method() {
+            Method::Get | Method::Head => self.handle(request.url(), &origin),
+            _ => HttpResponse::text(405, "method not allowed"),
+        };
+        debug!(
+            "serve: {} {} -> {}",
+            request.method(),
+            request.url(),
+            response.status
+        );
+        let mut reply = Response::from_data(response.body)
+            .with_status_code(response.status)
+            .with_header(header("Content-Type", response.content_type))
+            .with_header(header("Access-Control-Allow-Origin", "*"))
+            .with_header(header("Cache-Control", "no-cache"));
+        if let Some(encoding) = response.content_encoding {
+            reply.add_header(header("Content-Encoding", encoding));
+        }
+        // HEAD bodies are dropped by tiny_http
+        if let Err(err) = request.respond(reply) {
+            debug!("serve: response not sent: {err}");
+        }
+    }
+}
+
+/// Serve an MBTiles file on `127.0.0.1:port` until interrupted.
+pub fn serve_mbtiles(input: &Path, port: u16, options: &ServeOptions) -> Result<()> {
+    TileServer::mbtiles(input, options)?.serve(bind(port)?)
+}
+
+/// PMTiles counterpart of [`serve_mbtiles`].
+pub fn serve_pmtiles(input: &Path, port: u16, options: &ServeOptions) -> Result<()> {
+    TileServer::pmtiles(input, options)?.serve(bind(port)?)
+}
+
+fn bind(port: u16) -> Result<TcpListener> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("failed to listen on 127.0.0.1:{port}"))?;
+    println!("serve: http://{}/", listener.local_addr()?);
+    Ok(listener)
+}
+
+/// Parse `{z}/{x}/{y}.{ext}` (XYZ).
+fn parse_tile_path(path: &str) -> Option<TileCoord> {
+    let (path, _extension) = path.rsplit_once('.')?;
+    let mut parts = path.split('/');
+    let coord = TileCoord {
+        zoom: parts.next()?.parse().ok()?,
+        x: parts.next()?.parse().ok()?,
+        y: parts.next()?.parse().ok()?,
+    };
+    parts.next().is_none().then_some(coord)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("valid header")
+}
+
+fn raster_content_type(format: RasterFormat) -> &'static str {
+    match format {
+        RasterFormat::Png => "image/png",
+        RasterFormat::Jpeg => "image/jpeg",
+        RasterFormat::Webp => "image/webp",
+        RasterFormat::Avif => "image/avif",
+    }
+}
+
+const LAYER_COLORS: [&str; 8] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+];
+
+/// Fill, line and circle layers drawing every feature of `source_layer`.
+fn debug_layers(source_layer: &str, color: &str) -> Vec<Value> {
+    let geometry = |kind: &str| json!(["==", ["geometry-type"], kind]);
+    vec![
+        json!({
+            "id": format!("{source_layer}-fill"),
+            "type": "fill",
+            "source": "tiles",
+            "source-layer": source_layer,
+            "filter": geometry("Polygon"),
+            "paint": { "fill-color": color, "fill-opacity": 0.2 },
+        }),
+        json!({
+            "id": format!("{source_layer}-line"),
+            "type": "line",
+            "source": "tiles",
+            "source-layer": source_layer,
+            "filter": ["!=", ["geometry-type"], "Point"],
+            "paint": { "line-color": color, "line-width": 1 },
+        }),
+        json!({
+            "id": format!("{source_layer}-circle"),
+            "type": "circle",
+            "source": "tiles",
+            "source-layer": source_layer,
+            "filter": geometry("Point"),
+            "paint": { "circle-color": color, "circle-radius": 3 },
+        }),
+    ]
+}
+
+fn viewer_html() -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>vt-optimizer serve</title>
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<link rel="stylesheet" href="https://unpkg.com/maplibre-gl@{MAPLIBRE_VERSION}/dist/maplibre-gl.css">
+<script src="https://unpkg.com/maplibre-gl@{MAPLIBRE_VERSION}/dist/maplibre-gl.js"></script>
+<style>html, body, #map {{ margin: 0; height: 100%; }}</style>
+</head>
+<body>
+<div id="map"></div>
+<script>
+const map = new maplibregl.Map({{ container: "map", style: "/style.json", hash: true }});
+map.addControl(new maplibregl.NavigationControl());
+map.showTileBoundaries = true;
+</script>
+</body>
+</html>
+"#
+    )
+}
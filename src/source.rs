@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::format::{RasterFormat, TileFormat, resolve_input_format};
+use crate::mbtiles::{TileCoord, TileScheme, TileSizeRange};
+
+/// A single tile yielded by [`TileSource::scan_tiles`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileRow {
+    pub zoom: u8,
+    pub x: u32,
+    /// Row in the source's own convention ([`TileSource::row_scheme`]): the
+    /// TMS `tile_row` for MBTiles, the XYZ row for PMTiles.
+    pub y: u32,
+    pub length: u64,
+    /// Raw (possibly compressed) tile bytes; only populated when requested.
+    pub data: Option<Vec<u8>>,
+    /// Identifier shared by tiles stored as one payload, for sources that
+    /// deduplicate natively (see [`TileSource::content_ids`]).
+    pub content_id: Option<String>,
+}
+
+/// Read access to a tileset, independent of the container it is stored in.
+///
+/// Implementations must be shareable across threads because inspect scans
+/// zoom levels (and column ranges) in parallel.
+pub trait TileSource: Sync {
+    /// Metadata key/value pairs for the tileset.
+    fn metadata(&self) -> Result<BTreeMap<String, String>>;
+
+    /// Number of stored tiles for every zoom level that has tiles.
+    fn zoom_counts(&self) -> Result<BTreeMap<u8, u64>>;
+
+    /// Number of stored tiles, optionally limited to a single zoom level.
+    fn tile_count(&self, zoom: Option<u8>) -> Result<u64> {
+        let counts = self.zoom_counts()?;
+        Ok(match zoom {
+            Some(zoom) => counts.get(&zoom).copied().unwrap_or(0),
+            None => counts.values().sum(),
+        })
+    }
+
+    /// Number of stored rows without a valid tile address (negative or
+    /// non-integer values, zoom beyond 30), which scans skip
+    /// (and `zoom_counts` leaves out); with `strict` the first one is an
+    /// error instead.
+    fn corrupt_rows(&self, _strict: bool) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// Whether `scan_tiles` can efficiently restrict a scan to a column range.
+    fn supports_column_ranges(&self) -> bool {
+        false
+    }
+
+    /// Row convention of [`TileRow::y`] and of the coordinates passed to
+    /// `tile_data`; map rows to XYZ with [`TileScheme::convert_row`].
+    fn row_scheme(&self) -> TileScheme {
+        TileScheme::Xyz
+    }
+
+    /// Whether `scan_tiles` fills [`TileRow::content_id`], the id of the
+    /// stored blob a tile may share with others, so duplicate payloads can
+    /// be counted without reading tile data.
+    fn content_ids(&self) -> bool {
+        false
+    }
+
+    /// Visit every tile at `zoom`, optionally limited to an inclusive
+    /// `(min, max)` tile column range. `visit` returns `Ok(false)` to stop
+    /// the scan early.
+    fn scan_tiles(
+        &self,
+        zoom: u8,
+        columns: Option<(i64, i64)>,
+        with_data: bool,
+        visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+    ) -> Result<()>;
+
+    /// Like `scan_tiles`, but only visits tiles whose stored length is in
+    /// `lengths`. The default filters the rows of `scan_tiles`; sources that
+    /// can filter before reading a row override it.
+    fn scan_tiles_in_size_range(
+        &self,
+        zoom: u8,
+        columns: Option<(i64, i64)>,
+        with_data: bool,
+        lengths: TileSizeRange,
+        visit: &mut dyn FnMut(TileRow) -> Result<bool>,
+    ) -> Result<()> {
+        self.scan_tiles(zoom, columns, with_data, &mut |tile| {
+            if lengths.contains(tile.length) {
+                visit(tile)
+            } else {
+                Ok(true)
+            }
+        })
+    }
+
+    /// Raw bytes of a single tile, or `None` if it is not stored.
+    fn tile_data(&self, coord: TileCoord) -> Result<Option<Vec<u8>>>;
+
+    /// Decompress a stored tile payload into raw MVT bytes.
+    fn decode_tile(&self, data: &[u8]) -> Result<Vec<u8>> {
+        crate::mbtiles::decode_tile_payload(data)
+    }
+
+    /// Image format when the tileset holds raster rather than vector tiles.
+    fn raster_format(&self) -> Result<Option<RasterFormat>> {
+        detect_raster_format(self)
+    }
+}
+
+/// Raster format from the metadata `format`, or from the magic bytes of the
+/// first stored tile when the metadata does not say.
+pub fn detect_raster_format<S: TileSource + ?Sized>(source: &S) -> Result<Option<RasterFormat>> {
+    if let Some(format) = source.metadata()?.get("format") {
+        return Ok(RasterFormat::from_metadata(format));
+    }
+    let Some(&zoom) = source.zoom_counts()?.keys().next() else {
+        return Ok(None);
+    };
+    let mut detected = None;
+    source.scan_tiles(zoom, None, true, &mut |tile| {
+        detected = tile.data.as_deref().and_then(RasterFormat::from_magic);
+        Ok(false)
+    })?;
+    Ok(detected)
+}
+
+/// Stored bytes of tile `coord` (XYZ) from an MBTiles or PMTiles file, chosen
+/// by extension, without decompressing them. `None` when the tile is not
+/// stored.
+pub fn read_tile(path: &Path, coord: TileCoord) -> Result<Option<Vec<u8>>> {
+    match resolve_input_format(path, None)? {
+        TileFormat::Mbtiles => crate::mbtiles::read_mbtiles_tile(path, coord),
+        TileFormat::Pmtiles => crate::pmtiles::read_pmtiles_tile(path, coord),
+    }
+}
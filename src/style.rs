@@ -1,8 +1,10 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 use serde_json::Value;
 
 const PAINT_PROPERTIES_TO_CHECK: &[&str] = &[
@@ -44,17 +46,31 @@ impl PaintValue {
 
 #[derive(Debug, Clone)]
 struct MapboxStyleLayer {
+    id: String,
+    /// Position of the owning style in [`MapboxStyle::style_names`].
+    style: usize,
+    /// Position in the style's `layers` array.
+    index: usize,
     minzoom: Option<f64>,
     maxzoom: Option<f64>,
     visibility: Option<String>,
     paint: HashMap<String, PaintValue>,
     filter: Option<Filter>,
+    /// Operators of the filter that cannot be evaluated.
+    unknown_ops: Vec<String>,
+    interactive: bool,
+    /// Feature property keys read by the filter, layout and paint, or `None`
+    /// when they cannot all be listed.
+    property_keys: Option<HashSet<String>>,
 }
 
 impl MapboxStyleLayer {
-    fn is_visible_on_zoom(&self, zoom: u8) -> bool {
+    /// Whether the layer is displayed anywhere in `[zoom, zoom + 1)`, the
+    /// range tile zoom `zoom` is shown at. Overzoomed tiles (the tileset's
+    /// max zoom) are also shown above that range, so `minzoom` is ignored.
+    fn is_visible_on_zoom(&self, zoom: u8, overzoomed: bool) -> bool {
         self.check_layout_visibility()
-            && self.check_zoom_underflow(zoom)
+            && (overzoomed || self.check_zoom_underflow(zoom))
             && self.check_zoom_overflow(zoom)
     }
 
@@ -63,7 +79,8 @@ impl MapboxStyleLayer {
     }
 
     fn check_zoom_underflow(&self, zoom: u8) -> bool {
-        self.minzoom.is_none_or(|minzoom| (zoom as f64) >= minzoom)
+        self.minzoom
+            .is_none_or(|minzoom| minzoom < zoom as f64 + 1.0)
     }
 
     fn check_zoom_overflow(&self, zoom: u8) -> bool {
@@ -87,12 +104,144 @@ impl MapboxStyleLayer {
     }
 }
 
+/// Whether a style layer's filter can be fully evaluated by `layer+filter` pruning.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FilterSupport {
+    pub supported: bool,
+    /// Unsupported operators, in order of first appearance.
+    pub unknown_ops: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StyleLayerCheck {
+    pub id: String,
+    pub source_layer: String,
+    pub minzoom: Option<f64>,
+    pub maxzoom: Option<f64>,
+    pub has_filter: bool,
+    pub filter: FilterSupport,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceLayerCoverage {
+    pub source_layer: String,
+    /// Zooms at which layer pruning keeps the source layer; empty when it is
+    /// removed everywhere.
+    pub kept_zooms: Vec<u8>,
+}
+
+/// Result of `style-check`: filter support per style layer and the zooms
+/// each source layer survives pruning at.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StyleCheckReport {
+    pub layers: Vec<StyleLayerCheck>,
+    pub source_layers: Vec<SourceLayerCoverage>,
+}
+
+impl StyleCheckReport {
+    pub fn unsupported_layers(&self) -> usize {
+        self.layers
+            .iter()
+            .filter(|layer| !layer.filter.supported)
+            .count()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MapboxStyle {
     layers_by_source_layer: HashMap<String, Vec<MapboxStyleLayer>>,
+    /// Paths of the styles merged into this one, in `--style` order.
+    style_names: Vec<String>,
+    /// Vector source ids whose layers were collected.
+    sources: Vec<String>,
+    /// Highest zoom stored in the tileset being pruned; tiles at this zoom
+    /// are overzoomed by renderers.
+    tileset_max_zoom: Option<u8>,
 }
 
 impl MapboxStyle {
+    /// Merge several styles: a source layer is kept when any style keeps it,
+    /// and a feature is dropped only if every style's filter rejects it.
+    pub fn union(styles: impl IntoIterator<Item = MapboxStyle>) -> Self {
+        let mut merged = MapboxStyle {
+            layers_by_source_layer: HashMap::new(),
+            style_names: Vec::new(),
+            sources: Vec::new(),
+            tileset_max_zoom: None,
+        };
+        for style in styles {
+            merged.merge(style);
+        }
+        merged
+    }
+
+    /// Add the layers of `other` to this style. Style layers are kept side
+    /// by side, so a source layer's zoom range becomes the union of both
+    /// styles' ranges.
+    pub fn merge(&mut self, other: MapboxStyle) {
+        let offset = self.style_names.len();
+        self.style_names.extend(other.style_names);
+        for source in other.sources {
+            if !self.sources.contains(&source) {
+                self.sources.push(source);
+            }
+        }
+        for (source_layer, layers) in other.layers_by_source_layer {
+            self.layers_by_source_layer
+                .entry(source_layer)
+                .or_default()
+                .extend(layers.into_iter().map(|mut layer| {
+                    layer.style += offset;
+                    layer
+                }));
+        }
+        self.tileset_max_zoom = self.tileset_max_zoom.max(other.tileset_max_zoom);
+    }
+
+    pub fn style_names(&self) -> &[String] {
+        &self.style_names
+    }
+
+    /// Vector source ids the style layers were restricted to; empty when the
+    /// style declares no vector source.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// Styles that keep each source layer at some zoom in `0..=max_zoom`.
+    pub fn retaining_styles(&self, max_zoom: u8) -> BTreeMap<String, Vec<String>> {
+        self.layers_by_source_layer
+            .iter()
+            .map(|(source_layer, layers)| {
+                let styles = (0..self.style_names.len())
+                    .filter(|style| {
+                        layers.iter().any(|layer| {
+                            layer.style == *style
+                                && (0..=max_zoom).any(|zoom| {
+                                    layer.is_visible_on_zoom(zoom, self.is_overzoomed(zoom))
+                                        && layer.is_rendered(zoom)
+                                })
+                        })
+                    })
+                    .map(|style| self.style_names[style].clone())
+                    .collect();
+                (source_layer.clone(), styles)
+            })
+            .collect()
+    }
+
+    /// Treat tiles at `max_zoom` (and above) as overzoomed, keeping layers
+    /// whose `minzoom` lies beyond the tileset.
+    pub fn with_tileset_max_zoom(mut self, max_zoom: Option<u8>) -> Self {
+        self.tileset_max_zoom = max_zoom;
+        self
+    }
+
+    fn is_overzoomed(&self, zoom: u8) -> bool {
+        self.tileset_max_zoom
+            .is_some_and(|max_zoom| zoom >= max_zoom)
+    }
+
     pub fn source_layers(&self) -> HashSet<String> {
         self.layers_by_source_layer.keys().cloned().collect()
     }
@@ -101,13 +250,104 @@ impl MapboxStyle {
         self.layers_by_source_layer
             .get(layer_name)
             .map(|layers| {
-                layers
-                    .iter()
-                    .any(|layer| layer.is_visible_on_zoom(zoom) && layer.is_rendered(zoom))
+                layers.iter().any(|layer| {
+                    layer.is_visible_on_zoom(zoom, self.is_overzoomed(zoom))
+                        && layer.is_rendered(zoom)
+                })
             })
             .unwrap_or(false)
     }
 
+    /// Whether any style layer is displayed at tile zoom `zoom`, ignoring
+    /// paint; zooms outside the union of the layers' zoom ranges are never drawn.
+    pub fn covers_zoom(&self, zoom: u8) -> bool {
+        self.layers_by_source_layer
+            .values()
+            .flatten()
+            .any(|layer| layer.is_visible_on_zoom(zoom, self.is_overzoomed(zoom)))
+    }
+
+    /// Filter support of every style layer with a source layer, in style order.
+    pub fn layer_filter_support(&self) -> Vec<StyleLayerCheck> {
+        let mut layers = self
+            .layers_by_source_layer
+            .iter()
+            .flat_map(|(source_layer, layers)| {
+                layers.iter().map(move |layer| (source_layer, layer))
+            })
+            .collect::<Vec<_>>();
+        layers.sort_by_key(|(_, layer)| (layer.style, layer.index));
+        layers
+            .into_iter()
+            .map(|(source_layer, layer)| StyleLayerCheck {
+                id: layer.id.clone(),
+                source_layer: source_layer.clone(),
+                minzoom: layer.minzoom,
+                maxzoom: layer.maxzoom,
+                has_filter: layer.filter.is_some() || !layer.unknown_ops.is_empty(),
+                filter: FilterSupport {
+                    supported: layer.unknown_ops.is_empty(),
+                    unknown_ops: layer.unknown_ops.clone(),
+                },
+            })
+            .collect()
+    }
+
+    /// Zooms in `0..=max_zoom` at which each source layer is kept by layer pruning.
+    pub fn source_layer_zooms(&self, max_zoom: u8) -> BTreeMap<String, Vec<u8>> {
+        self.layers_by_source_layer
+            .keys()
+            .map(|name| {
+                let zooms = (0..=max_zoom)
+                    .filter(|zoom| self.is_layer_visible_on_zoom(name, *zoom))
+                    .collect();
+                (name.clone(), zooms)
+            })
+            .collect()
+    }
+
+    /// Inspects filters and zoom constraints without reading any tiles.
+    pub fn check(&self, max_zoom: u8) -> StyleCheckReport {
+        StyleCheckReport {
+            layers: self.layer_filter_support(),
+            source_layers: self
+                .source_layer_zooms(max_zoom)
+                .into_iter()
+                .map(|(source_layer, kept_zooms)| SourceLayerCoverage {
+                    source_layer,
+                    kept_zooms,
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether any style layer on this source layer sets `"interactive": true`.
+    /// Property keys read by the style layers of each source layer, for
+    /// `--prune-attributes`. Source layers with an interactive layer, or a
+    /// layer whose keys cannot all be listed (`["properties"]`, computed
+    /// `["get", ...]` keys), are left out so every key is kept.
+    pub fn referenced_property_keys(&self) -> HashMap<String, HashSet<String>> {
+        self.layers_by_source_layer
+            .iter()
+            .filter_map(|(source_layer, layers)| {
+                let mut keys = HashSet::new();
+                for layer in layers {
+                    if layer.interactive {
+                        return None;
+                    }
+                    keys.extend(layer.property_keys.as_ref()?.iter().cloned());
+                }
+                Some((source_layer.clone(), keys))
+            })
+            .collect()
+    }
+
+    pub fn is_layer_interactive(&self, layer_name: &str) -> bool {
+        self.layers_by_source_layer
+            .get(layer_name)
+            .is_some_and(|layers| layers.iter().any(|layer| layer.interactive))
+    }
+
     pub fn should_keep_feature(
         &self,
         layer_name: &str,
@@ -120,7 +360,8 @@ impl MapboxStyle {
         };
         let mut saw_unknown = false;
         for layer in layers {
-            if !layer.is_visible_on_zoom(zoom) || !layer.is_rendered(zoom) {
+            if !layer.is_visible_on_zoom(zoom, self.is_overzoomed(zoom)) || !layer.is_rendered(zoom)
+            {
                 continue;
             }
             let result = match layer.filter.as_ref() {
@@ -144,6 +385,68 @@ impl MapboxStyle {
     }
 }
 
+/// Which source layers optimize keeps.
+#[derive(Debug, Clone)]
+pub enum LayerSelection {
+    /// Keep what the style renders, honouring its zoom ranges and filters.
+    StyleDriven(MapboxStyle),
+    /// Keep layers by name (`--keep-layer` / `--drop-layer`) at every zoom;
+    /// features are never filtered.
+    ExplicitList {
+        /// Layers to keep; `None` keeps every layer not in `drop`.
+        keep: Option<HashSet<String>>,
+        drop: HashSet<String>,
+    },
+}
+
+impl LayerSelection {
+    pub fn style(&self) -> Option<&MapboxStyle> {
+        match self {
+            Self::StyleDriven(style) => Some(style),
+            Self::ExplicitList { .. } => None,
+        }
+    }
+
+    pub fn with_tileset_max_zoom(self, max_zoom: Option<u8>) -> Self {
+        match self {
+            Self::StyleDriven(style) => Self::StyleDriven(style.with_tileset_max_zoom(max_zoom)),
+            explicit => explicit,
+        }
+    }
+
+    /// Whether `layer_name` survives at tile zoom `zoom`.
+    pub fn keeps_layer(&self, layer_name: &str, zoom: u8) -> bool {
+        match self {
+            Self::StyleDriven(style) => style.is_layer_visible_on_zoom(layer_name, zoom),
+            Self::ExplicitList { keep, drop } => {
+                keep.as_ref().is_none_or(|keep| keep.contains(layer_name))
+                    && !drop.contains(layer_name)
+            }
+        }
+    }
+
+    pub fn covers_zoom(&self, zoom: u8) -> bool {
+        self.style().is_none_or(|style| style.covers_zoom(zoom))
+    }
+
+    pub fn is_layer_interactive(&self, layer_name: &str) -> bool {
+        self.style()
+            .is_some_and(|style| style.is_layer_interactive(layer_name))
+    }
+}
+
+impl From<MapboxStyle> for LayerSelection {
+    fn from(style: MapboxStyle) -> Self {
+        Self::StyleDriven(style)
+    }
+}
+
+impl From<&MapboxStyle> for LayerSelection {
+    fn from(style: &MapboxStyle) -> Self {
+        Self::StyleDriven(style.clone())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FilterResult {
     True,
@@ -156,6 +459,8 @@ enum FilterValue {
     String(String),
     Number(f64),
     Bool(bool),
+    /// Missing property or explicit `null`.
+    Null,
 }
 
 impl FilterValue {
@@ -164,9 +469,60 @@ impl FilterValue {
             (FilterValue::String(a), FilterValue::String(b)) => a == b,
             (FilterValue::Number(a), FilterValue::Number(b)) => (*a - *b).abs() < f64::EPSILON,
             (FilterValue::Bool(a), FilterValue::Bool(b)) => a == b,
+            (FilterValue::Null, FilterValue::Null) => true,
+            (FilterValue::String(text), FilterValue::Number(number))
+            | (FilterValue::Number(number), FilterValue::String(text)) => text
+                .trim()
+                .parse::<f64>()
+                .is_ok_and(|parsed| (parsed - *number).abs() < f64::EPSILON),
             _ => false,
         }
     }
+
+    /// Ordering of numbers (numeric strings are coerced) or of two strings;
+    /// `None` when the values are not comparable.
+    fn compare(&self, other: &FilterValue) -> Option<Ordering> {
+        match (self, other) {
+            (FilterValue::Number(a), FilterValue::Number(b)) => a.partial_cmp(b),
+            (FilterValue::String(a), FilterValue::String(b)) => Some(a.cmp(b)),
+            (FilterValue::String(text), FilterValue::Number(number)) => {
+                text.trim().parse::<f64>().ok()?.partial_cmp(number)
+            }
+            (FilterValue::Number(number), FilterValue::String(text)) => {
+                number.partial_cmp(&text.trim().parse::<f64>().ok()?)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            "<" => Some(CompareOp::Lt),
+            "<=" => Some(CompareOp::Le),
+            ">" => Some(CompareOp::Gt),
+            ">=" => Some(CompareOp::Ge),
+            _ => None,
+        }
+    }
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -185,7 +541,8 @@ enum Expr {
     Coalesce(Vec<Expr>),
     Match {
         input: Box<Expr>,
-        cases: Vec<(FilterValue, Expr)>,
+        /// Each case lists one or more labels.
+        cases: Vec<(Vec<FilterValue>, Expr)>,
         fallback: Box<Expr>,
     },
     Case {
@@ -194,10 +551,42 @@ enum Expr {
     },
 }
 
+impl Expr {
+    fn collect_unknown_ops(&self, ops: &mut Vec<String>) {
+        match self {
+            Expr::Coalesce(items) => {
+                for item in items {
+                    item.collect_unknown_ops(ops);
+                }
+            }
+            Expr::Match {
+                input,
+                cases,
+                fallback,
+            } => {
+                input.collect_unknown_ops(ops);
+                for (_, output) in cases {
+                    output.collect_unknown_ops(ops);
+                }
+                fallback.collect_unknown_ops(ops);
+            }
+            Expr::Case { branches, fallback } => {
+                for (condition, output) in branches {
+                    condition.collect_unknown_ops(ops);
+                    output.collect_unknown_ops(ops);
+                }
+                fallback.collect_unknown_ops(ops);
+            }
+            Expr::Literal(_) | Expr::Get(_) | Expr::Zoom | Expr::Type => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum Filter {
     Eq(Expr, Expr),
     Neq(Expr, Expr),
+    Compare(CompareOp, Expr, Expr),
     In(FilterKey, Vec<FilterValue>),
     NotIn(FilterKey, Vec<FilterValue>),
     Has(FilterKey),
@@ -206,10 +595,37 @@ enum Filter {
     Any(Vec<Filter>),
     None(Vec<Filter>),
     Not(Box<Filter>),
-    Unknown,
+    /// Boolean-valued expression such as `match`, `case` or a literal.
+    Expr(Expr),
+    /// Unsupported expression, labelled with its operator.
+    Unknown(String),
 }
 
 impl Filter {
+    fn collect_unknown_ops(&self, ops: &mut Vec<String>) {
+        match self {
+            Filter::Eq(left, right)
+            | Filter::Neq(left, right)
+            | Filter::Compare(_, left, right) => {
+                left.collect_unknown_ops(ops);
+                right.collect_unknown_ops(ops);
+            }
+            Filter::Expr(expr) => expr.collect_unknown_ops(ops),
+            Filter::All(filters) | Filter::Any(filters) | Filter::None(filters) => {
+                for filter in filters {
+                    filter.collect_unknown_ops(ops);
+                }
+            }
+            Filter::Not(filter) => filter.collect_unknown_ops(ops),
+            Filter::Unknown(op) => {
+                if !ops.contains(op) {
+                    ops.push(op.clone());
+                }
+            }
+            Filter::In(..) | Filter::NotIn(..) | Filter::Has(_) | Filter::NotHas(_) => {}
+        }
+    }
+
     fn evaluate(&self, feature: &mvt_reader::feature::Feature, zoom: u8) -> FilterResult {
         match self {
             Filter::Eq(left, right) => match (
@@ -228,6 +644,21 @@ impl Filter {
                 }
                 _ => FilterResult::Unknown,
             },
+            Filter::Compare(op, left, right) => match (
+                eval_expr(left, feature, zoom),
+                eval_expr(right, feature, zoom),
+            ) {
+                (Some(actual), Some(expected)) => FilterResult::from_bool(
+                    actual
+                        .compare(&expected)
+                        .is_some_and(|ordering| op.holds(ordering)),
+                ),
+                _ => FilterResult::Unknown,
+            },
+            Filter::Expr(expr) => match eval_expr(expr, feature, zoom) {
+                Some(FilterValue::Bool(value)) => FilterResult::from_bool(value),
+                _ => FilterResult::Unknown,
+            },
             Filter::In(key, values) => match feature_value_by_key(feature, key, zoom) {
                 Some(actual) => FilterResult::from_bool(values.iter().any(|v| actual.equals(v))),
                 None => FilterResult::Unknown,
@@ -288,7 +719,7 @@ impl Filter {
                 FilterResult::False => FilterResult::True,
                 FilterResult::Unknown => FilterResult::Unknown,
             },
-            Filter::Unknown => FilterResult::Unknown,
+            Filter::Unknown(_) => FilterResult::Unknown,
         }
     }
 }
@@ -323,8 +754,13 @@ fn feature_value_by_key(
         FilterKey::Type => Some(FilterValue::String(feature_type(feature).to_string())),
         FilterKey::Zoom => Some(FilterValue::Number(zoom as f64)),
         FilterKey::Property(name) => {
-            let props = feature.properties.as_ref()?;
-            let value = props.get(name)?;
+            let Some(value) = feature
+                .properties
+                .as_ref()
+                .and_then(|props| props.get(name))
+            else {
+                return Some(FilterValue::Null);
+            };
             match value {
                 mvt_reader::feature::Value::String(text) => Some(FilterValue::String(text.clone())),
                 mvt_reader::feature::Value::Float(val) => Some(FilterValue::Number(*val as f64)),
@@ -333,7 +769,7 @@ fn feature_value_by_key(
                 mvt_reader::feature::Value::UInt(val) => Some(FilterValue::Number(*val as f64)),
                 mvt_reader::feature::Value::SInt(val) => Some(FilterValue::Number(*val as f64)),
                 mvt_reader::feature::Value::Bool(val) => Some(FilterValue::Bool(*val)),
-                mvt_reader::feature::Value::Null => None,
+                mvt_reader::feature::Value::Null => Some(FilterValue::Null),
             }
         }
     }
@@ -378,6 +814,9 @@ fn parse_paint_value(value: &Value) -> Option<PaintValue> {
 }
 
 fn parse_filter(value: &Value) -> Option<Filter> {
+    if let Some(boolean) = value.as_bool() {
+        return Some(Filter::Expr(Expr::Literal(FilterValue::Bool(boolean))));
+    }
     let array = value.as_array()?;
     if array.is_empty() {
         return None;
@@ -386,14 +825,14 @@ fn parse_filter(value: &Value) -> Option<Filter> {
     match op {
         "!" => {
             if array.len() < 2 {
-                return Some(Filter::Unknown);
+                return Some(Filter::Unknown(op.to_string()));
             }
-            let inner = parse_filter(&array[1]).unwrap_or(Filter::Unknown);
+            let inner = parse_filter_or_unknown(&array[1]);
             Some(Filter::Not(Box::new(inner)))
         }
         "==" | "!=" => {
             if array.len() < 3 {
-                return Some(Filter::Unknown);
+                return Some(Filter::Unknown(op.to_string()));
             }
             let left = parse_filter_lhs(&array[1])?;
             let right = parse_expr(&array[2])?;
@@ -403,18 +842,43 @@ fn parse_filter(value: &Value) -> Option<Filter> {
                 Some(Filter::Neq(left, right))
             }
         }
+        "<" | "<=" | ">" | ">=" => {
+            if array.len() < 3 {
+                return Some(Filter::Unknown(op.to_string()));
+            }
+            let left = parse_filter_lhs(&array[1])?;
+            let right = parse_expr(&array[2])?;
+            Some(Filter::Compare(CompareOp::parse(op)?, left, right))
+        }
         "in" | "!in" => {
             if array.len() < 3 {
-                return Some(Filter::Unknown);
+                return Some(Filter::Unknown(op.to_string()));
             }
             let key = parse_filter_key(&array[1])?;
             let mut values = Vec::new();
-            if let Some(list) = array[2].as_array() {
+            if let Some(haystack) = array[2].as_array()
+                && array.len() == 3
+                && (haystack.first().is_some_and(|tag| tag == "literal")
+                    || parse_expr(&array[2]).is_some())
+            {
+                // Expression form: only `["literal", [...]]` haystacks are
+                // supported; substring or array-valued lookups are not.
+                let list = match haystack.as_slice() {
+                    [tag, Value::Array(list)] if tag == "literal" => list,
+                    _ => return Some(Filter::Unknown(op.to_string())),
+                };
+                for item in list {
+                    match parse_filter_value(item) {
+                        Some(value) => values.push(value),
+                        None => return Some(Filter::Unknown(op.to_string())),
+                    }
+                }
+            } else if let Some(list) = array[2].as_array() {
                 for item in list {
                     if let Some(value) = parse_filter_value(item) {
                         values.push(value);
                     } else {
-                        return Some(Filter::Unknown);
+                        return Some(Filter::Unknown(op.to_string()));
                     }
                 }
             } else {
@@ -422,7 +886,7 @@ fn parse_filter(value: &Value) -> Option<Filter> {
                     if let Some(value) = parse_filter_value(item) {
                         values.push(value);
                     } else {
-                        return Some(Filter::Unknown);
+                        return Some(Filter::Unknown(op.to_string()));
                     }
                 }
             }
@@ -434,7 +898,7 @@ fn parse_filter(value: &Value) -> Option<Filter> {
         }
         "has" | "!has" => {
             if array.len() < 2 {
-                return Some(Filter::Unknown);
+                return Some(Filter::Unknown(op.to_string()));
             }
             let key = parse_filter_key(&array[1])?;
             if op == "has" {
@@ -446,11 +910,7 @@ fn parse_filter(value: &Value) -> Option<Filter> {
         "all" | "any" | "none" => {
             let mut filters = Vec::new();
             for item in &array[1..] {
-                if let Some(filter) = parse_filter(item) {
-                    filters.push(filter);
-                } else {
-                    filters.push(Filter::Unknown);
-                }
+                filters.push(parse_filter_or_unknown(item));
             }
             match op {
                 "all" => Some(Filter::All(filters)),
@@ -458,10 +918,45 @@ fn parse_filter(value: &Value) -> Option<Filter> {
                 _ => Some(Filter::None(filters)),
             }
         }
-        _ => Some(Filter::Unknown),
+        "match" | "case" | "coalesce" => match parse_expr(value) {
+            Some(expr) => Some(Filter::Expr(expr)),
+            None => Some(Filter::Unknown(op.to_string())),
+        },
+        _ => Some(Filter::Unknown(op.to_string())),
     }
 }
 
+fn parse_filter_or_unknown(value: &Value) -> Filter {
+    parse_filter(value).unwrap_or_else(|| Filter::Unknown(filter_op_label(value)))
+}
+
+/// Operator of an unparseable filter; for comparisons, the operator of the
+/// operand that could not be parsed.
+fn filter_op_label(value: &Value) -> String {
+    let Some(array) = value.as_array() else {
+        return "<invalid>".to_string();
+    };
+    let op = array
+        .first()
+        .and_then(|op| op.as_str())
+        .unwrap_or("<invalid>");
+    if matches!(op, "==" | "!=" | "<" | "<=" | ">" | ">=") && array.len() >= 3 {
+        let operand = if parse_filter_lhs(&array[1]).is_none() {
+            &array[1]
+        } else {
+            &array[2]
+        };
+        if let Some(inner) = operand
+            .as_array()
+            .and_then(|array| array.first())
+            .and_then(|op| op.as_str())
+        {
+            return inner.to_string();
+        }
+    }
+    op.to_string()
+}
+
 fn parse_filter_value(value: &Value) -> Option<FilterValue> {
     if let Some(text) = value.as_str() {
         return Some(FilterValue::String(text.to_string()));
@@ -476,6 +971,9 @@ fn parse_filter_value(value: &Value) -> Option<FilterValue> {
 }
 
 fn parse_expr(value: &Value) -> Option<Expr> {
+    if value.is_null() {
+        return Some(Expr::Literal(FilterValue::Null));
+    }
     if let Some(text) = value.as_str() {
         return Some(Expr::Literal(FilterValue::String(text.to_string())));
     }
@@ -497,6 +995,10 @@ fn parse_expr(value: &Value) -> Option<Expr> {
         }
         "zoom" => Some(Expr::Zoom),
         "geometry-type" => Some(Expr::Type),
+        "literal" => match array.get(1)? {
+            Value::Array(_) | Value::Object(_) => None,
+            literal => parse_expr(literal),
+        },
         "coalesce" => {
             let mut items = Vec::new();
             for item in array.iter().skip(1) {
@@ -516,9 +1018,15 @@ fn parse_expr(value: &Value) -> Option<Expr> {
             let mut cases = Vec::new();
             let mut idx = 2;
             while idx + 1 < array.len() - 1 {
-                let match_value = parse_filter_value(&array[idx])?;
+                let labels = match array[idx].as_array() {
+                    Some(labels) => labels
+                        .iter()
+                        .map(parse_filter_value)
+                        .collect::<Option<Vec<_>>>()?,
+                    None => vec![parse_filter_value(&array[idx])?],
+                };
                 let output = parse_expr(&array[idx + 1])?;
-                cases.push((match_value, output));
+                cases.push((labels, output));
                 idx += 2;
             }
             let fallback = parse_expr(array.last()?)?;
@@ -535,7 +1043,7 @@ fn parse_expr(value: &Value) -> Option<Expr> {
             let mut branches = Vec::new();
             let mut idx = 1;
             while idx + 1 < array.len() - 1 {
-                let condition = parse_filter(&array[idx]).unwrap_or(Filter::Unknown);
+                let condition = parse_filter_or_unknown(&array[idx]);
                 let output = parse_expr(&array[idx + 1])?;
                 branches.push((condition, output));
                 idx += 2;
@@ -597,11 +1105,12 @@ fn eval_expr(expr: &Expr, feature: &mvt_reader::feature::Feature, zoom: u8) -> O
         Expr::Type => Some(FilterValue::String(feature_type(feature).to_string())),
         Expr::Coalesce(items) => {
             for item in items {
-                if let Some(value) = eval_expr(item, feature, zoom) {
-                    return Some(value);
+                match eval_expr(item, feature, zoom)? {
+                    FilterValue::Null => {}
+                    value => return Some(value),
                 }
             }
-            None
+            Some(FilterValue::Null)
         }
         Expr::Match {
             input,
@@ -609,8 +1118,8 @@ fn eval_expr(expr: &Expr, feature: &mvt_reader::feature::Feature, zoom: u8) -> O
             fallback,
         } => {
             let input_value = eval_expr(input, feature, zoom)?;
-            for (match_value, output) in cases {
-                if input_value.equals(match_value) {
+            for (labels, output) in cases {
+                if labels.iter().any(|label| input_value.equals(label)) {
                     return eval_expr(output, feature, zoom);
                 }
             }
@@ -630,6 +1139,13 @@ fn eval_expr(expr: &Expr, feature: &mvt_reader::feature::Feature, zoom: u8) -> O
 }
 
 pub fn read_style(path: &Path) -> Result<MapboxStyle> {
+    read_style_for_source(path, None)
+}
+
+/// Reads a style, keeping only layers of the vector source `source`. Without
+/// a source id the style's only vector source is used; styles with several
+/// vector sources must name one.
+pub fn read_style_for_source(path: &Path, source: Option<&str>) -> Result<MapboxStyle> {
     let contents = fs::read_to_string(path)
         .with_context(|| format!("failed to read style file: {}", path.display()))?;
     let value: Value = serde_json::from_str(&contents).context("parse style json")?;
@@ -637,10 +1153,17 @@ pub fn read_style(path: &Path) -> Result<MapboxStyle> {
         .get("layers")
         .and_then(|layers| layers.as_array())
         .ok_or_else(|| anyhow::anyhow!("style json missing layers array"))?;
+    let source = resolve_vector_source(&value, source)?;
 
     let mut layers_by_source_layer: HashMap<String, Vec<MapboxStyleLayer>> = HashMap::new();
-    for layer in layers {
-        if layer.get("source").is_none() {
+    for (index, layer) in layers.iter().enumerate() {
+        let Some(layer_source) = layer.get("source") else {
+            continue;
+        };
+        if source
+            .as_deref()
+            .is_some_and(|source| layer_source.as_str() != Some(source))
+        {
             continue;
         }
         let Some(source_layer) = layer.get("source-layer").and_then(|v| v.as_str()) else {
@@ -661,16 +1184,42 @@ pub fn read_style(path: &Path) -> Result<MapboxStyle> {
                 }
             }
         }
-        let filter = layer.get("filter").and_then(parse_filter);
+        // A non-empty expression that fails to parse evaluates as unknown.
+        let filter = layer.get("filter").and_then(|value| {
+            if value.as_array().is_some_and(|array| !array.is_empty()) {
+                Some(parse_filter_or_unknown(value))
+            } else {
+                parse_filter(value)
+            }
+        });
+        let mut unknown_ops = Vec::new();
+        if let Some(filter) = &filter {
+            filter.collect_unknown_ops(&mut unknown_ops);
+        }
+        let interactive = layer
+            .get("interactive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let property_keys = referenced_keys(layer);
         layers_by_source_layer
             .entry(source_layer.to_string())
             .or_default()
             .push(MapboxStyleLayer {
+                id: layer
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                style: 0,
+                index,
                 minzoom,
                 maxzoom,
                 visibility,
                 paint,
                 filter,
+                unknown_ops,
+                interactive,
+                property_keys,
             });
     }
 
@@ -679,9 +1228,144 @@ pub fn read_style(path: &Path) -> Result<MapboxStyle> {
     }
     Ok(MapboxStyle {
         layers_by_source_layer,
+        style_names: vec![path.display().to_string()],
+        sources: source.into_iter().collect(),
+        tileset_max_zoom: None,
     })
 }
 
+/// Feature property keys a style layer reads in its filter, layout and
+/// paint; `None` if some key is only known at render time.
+fn referenced_keys(layer: &Value) -> Option<HashSet<String>> {
+    let mut keys = HashSet::new();
+    if let Some(filter) = layer.get("filter") {
+        collect_expression_keys(filter, &mut keys)?;
+    }
+    if let Some(layout) = layer.get("layout").and_then(|layout| layout.as_object()) {
+        for value in layout.values() {
+            // `{key}` tokens, e.g. "text-field": "{name}"
+            if let Some(text) = value.as_str() {
+                collect_token_keys(text, &mut keys);
+            }
+            collect_expression_keys(value, &mut keys)?;
+        }
+    }
+    if let Some(paint) = layer.get("paint").and_then(|paint| paint.as_object()) {
+        for value in paint.values() {
+            collect_expression_keys(value, &mut keys)?;
+        }
+    }
+    Some(keys)
+}
+
+/// Walks legacy filters, expressions and property functions.
+fn collect_expression_keys(value: &Value, keys: &mut HashSet<String>) -> Option<()> {
+    match value {
+        Value::Array(items) => {
+            let Some(op) = items.first().and_then(|op| op.as_str()) else {
+                return items
+                    .iter()
+                    .try_for_each(|item| collect_expression_keys(item, keys));
+            };
+            match (op, items.get(1)) {
+                ("literal", _) => Some(()),
+                ("properties", _) => None,
+                ("get" | "has" | "!has", Some(Value::String(key))) => {
+                    keys.insert(key.clone());
+                    items[2..]
+                        .iter()
+                        .try_for_each(|item| collect_expression_keys(item, keys))
+                }
+                ("get" | "has", _) => None,
+                // Legacy comparisons name the key directly; `$type`/`$id` are
+                // not properties. A string operand of an expression comparison
+                // is taken as a key too, which only keeps more.
+                (
+                    "==" | "!=" | "<" | "<=" | ">" | ">=" | "in" | "!in",
+                    Some(Value::String(key)),
+                ) => {
+                    if !key.starts_with('$') {
+                        keys.insert(key.clone());
+                    }
+                    items[2..]
+                        .iter()
+                        .try_for_each(|item| collect_expression_keys(item, keys))
+                }
+                _ => items[1..]
+                    .iter()
+                    .try_for_each(|item| collect_expression_keys(item, keys)),
+            }
+        }
+        Value::Object(object) => {
+            if let Some(key) = object.get("property").and_then(|key| key.as_str()) {
+                keys.insert(key.to_string());
+            }
+            object
+                .values()
+                .try_for_each(|item| collect_expression_keys(item, keys))
+        }
+        _ => Some(()),
+    }
+}
+
+fn collect_token_keys(text: &str, keys: &mut HashSet<String>) {
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let key = &rest[start + 1..start + end];
+        if !key.is_empty() {
+            keys.insert(key.to_string());
+        }
+        rest = &rest[start + end + 1..];
+    }
+}
+
+/// Read every style (see [`read_style_for_source`]) and merge them with
+/// [`MapboxStyle::union`].
+pub fn read_styles(paths: &[PathBuf], source: Option<&str>) -> Result<MapboxStyle> {
+    let styles = paths
+        .iter()
+        .map(|path| read_style_for_source(path, source))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(MapboxStyle::union(styles))
+}
+
+/// The vector source layers are collected from: `requested` if the style
+/// declares it, otherwise the only vector source. `None` when the style
+/// declares no vector source (every layer is used).
+pub(crate) fn resolve_vector_source(
+    style: &Value,
+    requested: Option<&str>,
+) -> Result<Option<String>> {
+    let vector_sources = style
+        .get("sources")
+        .and_then(|sources| sources.as_object())
+        .map(|sources| {
+            sources
+                .iter()
+                .filter(|(_, source)| {
+                    source.get("type").and_then(|kind| kind.as_str()) == Some("vector")
+                })
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    match requested {
+        Some(id) if vector_sources.iter().any(|source| source == id) => Ok(Some(id.to_string())),
+        Some(id) => anyhow::bail!(
+            "style has no vector source `{id}` (vector sources: {})",
+            vector_sources.join(", ")
+        ),
+        None if vector_sources.len() > 1 => anyhow::bail!(
+            "style has several vector sources ({}); choose one with --source",
+            vector_sources.join(", ")
+        ),
+        None => Ok(vector_sources.into_iter().next()),
+    }
+}
+
 pub fn read_style_source_layers(path: &Path) -> Result<HashSet<String>> {
     Ok(read_style(path)?.source_layers())
 }
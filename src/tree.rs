@@ -0,0 +1,198 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::mbtiles::{
+    DEFAULT_GZIP_LEVEL, MbtilesFile, create_tiles_output_schema, decode_tile_payload,
+    encode_tile_payload, ensure_mbtiles_path, flip_y,
+};
+use crate::pmtiles::{PmtilesFile, decode_tile_payload_pmtiles};
+use crate::source::TileSource;
+
+/// Metadata file written next to the `z/x/y` directories by explode.
+pub const TREE_METADATA_FILE: &str = "metadata.json";
+
+/// Tiles written or read by explode/implode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TileTreeStats {
+    pub tiles: u64,
+    pub min_zoom: Option<u8>,
+    pub max_zoom: Option<u8>,
+}
+
+impl TileTreeStats {
+    fn record(&mut self, zoom: u8) {
+        self.tiles += 1;
+        self.min_zoom = Some(self.min_zoom.map_or(zoom, |min| min.min(zoom)));
+        self.max_zoom = Some(self.max_zoom.map_or(zoom, |max| max.max(zoom)));
+    }
+}
+
+/// Write every tile of an MBTiles file to `out_dir/z/x/y.mvt` as raw protobuf,
+/// or as `y.mvt.gz` when `gzip` is set. Tree rows are XYZ, so TMS
+/// `tile_row`s are flipped.
+pub fn explode_mbtiles(input: &Path, out_dir: &Path, gzip: bool) -> Result<TileTreeStats> {
+    let source = MbtilesFile::open(input)?;
+    explode_tile_source(&source, out_dir, gzip, decode_tile_payload)
+}
+
+/// PMTiles counterpart of [`explode_mbtiles`]; tiles are decoded using the
+/// header's `tile_compression`.
+pub fn explode_pmtiles(input: &Path, out_dir: &Path, gzip: bool) -> Result<TileTreeStats> {
+    let source = PmtilesFile::open(input)?;
+    let tile_compression = source.header().tile_compression;
+    explode_tile_source(&source, out_dir, gzip, |data| {
+        decode_tile_payload_pmtiles(data, tile_compression)
+    })
+}
+
+fn explode_tile_source<S: TileSource + ?Sized>(
+    source: &S,
+    out_dir: &Path,
+    gzip: bool,
+    decode: impl Fn(&[u8]) -> Result<Vec<u8>>,
+) -> Result<TileTreeStats> {
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create output dir: {}", out_dir.display()))?;
+    let metadata = source.metadata()?;
+    let json = serde_json::to_vec_pretty(&metadata).context("serialize metadata")?;
+    fs::write(out_dir.join(TREE_METADATA_FILE), json).context("write metadata.json")?;
+
+    let extension = if gzip { "mvt.gz" } else { "mvt" };
+    let scheme = source.row_scheme();
+    let mut stats = TileTreeStats::default();
+    for zoom in source.zoom_counts()?.into_keys() {
+        source.scan_tiles(zoom, None, true, &mut |row| {
+            let data = row.data.unwrap_or_default();
+            let payload = encode_tile_payload(&decode(&data)?, gzip, DEFAULT_GZIP_LEVEL)?;
+            let dir = out_dir.join(row.zoom.to_string()).join(row.x.to_string());
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("failed to create dir: {}", dir.display()))?;
+            let y = scheme.convert_row(row.zoom, row.y);
+            let path = dir.join(format!("{y}.{extension}"));
+            fs::write(&path, payload)
+                .with_context(|| format!("failed to write tile: {}", path.display()))?;
+            stats.record(row.zoom);
+            Ok(true)
+        })?;
+    }
+    Ok(stats)
+}
+
+/// Build an MBTiles file from a `z/x/y.mvt` tree written by explode. Raw
+/// `.mvt`/`.pbf` tiles are gzip-compressed; `.mvt.gz` tiles are stored as is.
+/// `minzoom`/`maxzoom` are inferred from the tree, whose XYZ rows are
+/// stored as TMS `tile_row`s.
+pub fn implode_to_mbtiles(in_dir: &Path, output: &Path) -> Result<TileTreeStats> {
+    ensure_mbtiles_path(output)?;
+    let tiles = collect_tree_tiles(in_dir)?;
+    if tiles.is_empty() {
+        anyhow::bail!("no z/x/y tiles found in {}", in_dir.display());
+    }
+    let mut metadata = read_tree_metadata(in_dir)?;
+
+    let mut conn = Connection::open(output)
+        .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
+    create_tiles_output_schema(&conn)?;
+    let tx = conn.transaction().context("begin output transaction")?;
+    let mut stats = TileTreeStats::default();
+    for tile in tiles {
+        let data = fs::read(&tile.path)
+            .with_context(|| format!("failed to read {}", tile.path.display()))?;
+        let data = if tile.gzipped {
+            data
+        } else {
+            encode_tile_payload(&data, true, DEFAULT_GZIP_LEVEL)?
+        };
+        tx.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            params![tile.zoom, tile.x, flip_y(tile.zoom, tile.y), data],
+        )
+        .context("insert tile")?;
+        stats.record(tile.zoom);
+    }
+
+    if let (Some(min_zoom), Some(max_zoom)) = (stats.min_zoom, stats.max_zoom) {
+        metadata.insert("minzoom".to_string(), min_zoom.to_string());
+        metadata.insert("maxzoom".to_string(), max_zoom.to_string());
+    }
+    metadata
+        .entry("format".to_string())
+        .or_insert_with(|| "pbf".to_string());
+    for (name, value) in &metadata {
+        tx.execute(
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+            params![name, value],
+        )
+        .context("insert metadata")?;
+    }
+    tx.commit().context("commit output")?;
+    Ok(stats)
+}
+
+fn read_tree_metadata(in_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let path = in_dir.join(TREE_METADATA_FILE);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let data = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&data).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+struct TreeTile {
+    zoom: u8,
+    x: u32,
+    y: u32,
+    path: PathBuf,
+    gzipped: bool,
+}
+
+/// Every tile file under `in_dir`, sorted by z/x/y.
+fn collect_tree_tiles(in_dir: &Path) -> Result<Vec<TreeTile>> {
+    let mut tiles = Vec::new();
+    for (zoom, zoom_dir) in numeric_entries(in_dir, true)? {
+        let zoom = u8::try_from(zoom).context("zoom directory out of range")?;
+        for (x, x_dir) in numeric_entries(&zoom_dir, true)? {
+            for (y, path) in numeric_entries(&x_dir, false)? {
+                let gzipped = path.to_string_lossy().ends_with(".gz");
+                tiles.push(TreeTile {
+                    zoom,
+                    x,
+                    y,
+                    path,
+                    gzipped,
+                });
+            }
+        }
+    }
+    tiles.sort_by_key(|tile| (tile.zoom, tile.x, tile.y));
+    Ok(tiles)
+}
+
+/// Directory entries whose name (minus a tile extension for files) is a number.
+fn numeric_entries(dir: &Path, dirs: bool) -> Result<Vec<(u32, PathBuf)>> {
+    let mut entries = Vec::new();
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("failed to read dir: {}", dir.display()))?
+    {
+        let entry = entry.context("read dir entry")?;
+        let path = entry.path();
+        if path.is_dir() != dirs {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let stem = if dirs {
+            Some(name.as_str())
+        } else {
+            [".mvt.gz", ".mvt", ".pbf"]
+                .iter()
+                .find_map(|ext| name.strip_suffix(ext))
+        };
+        if let Some(value) = stem.and_then(|stem| stem.parse::<u32>().ok()) {
+            entries.push((value, path));
+        }
+    }
+    Ok(entries)
+}
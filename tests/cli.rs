@@ -1,7 +1,9 @@
+use std::path::Path;
+
 use clap::{CommandFactory, Parser};
 
-use vt_optimizer::cli::ReportFormat;
 use vt_optimizer::cli::{Cli, Command, StyleMode, TileInfoFormat, UnknownFilterMode};
+use vt_optimizer::cli::{InspectReportFormat, ReportFormat};
 
 #[test]
 fn parse_optimize_minimal() {
@@ -13,7 +15,7 @@ fn parse_optimize_minimal() {
             assert_eq!(args.input_format, None);
             assert_eq!(args.output_format, None);
             assert_eq!(args.report_format, ReportFormat::Text);
-            assert_eq!(args.style, None);
+            assert!(args.style.is_empty());
             assert_eq!(args.style_mode, StyleMode::LayerFilter);
             assert_eq!(args.unknown_filter, UnknownFilterMode::Keep);
             assert_eq!(args.max_tile_bytes, 1_280_000);
@@ -21,6 +23,7 @@ fn parse_optimize_minimal() {
             assert_eq!(args.io_batch, 1_000);
             assert_eq!(args.checkpoint, None);
             assert!(!args.resume);
+            assert!(!args.no_progress);
         }
         _ => panic!("expected optimize command"),
     }
@@ -55,6 +58,7 @@ fn parse_optimize_options() {
         "--checkpoint",
         "state.json",
         "--resume",
+        "--no-progress",
     ]);
 
     match cli.command {
@@ -64,7 +68,7 @@ fn parse_optimize_options() {
             assert_eq!(args.input_format.unwrap(), "mbtiles");
             assert_eq!(args.output_format.unwrap(), "pmtiles");
             assert_eq!(args.report_format, ReportFormat::Json);
-            assert_eq!(args.style.unwrap().as_os_str(), "style.json");
+            assert_eq!(args.style, vec![std::path::PathBuf::from("style.json")]);
             assert_eq!(args.style_mode, StyleMode::LayerFilter);
             assert_eq!(args.unknown_filter, UnknownFilterMode::Drop);
             assert_eq!(args.max_tile_bytes, 2048);
@@ -72,6 +76,7 @@ fn parse_optimize_options() {
             assert_eq!(args.io_batch, 200);
             assert_eq!(args.checkpoint.unwrap().as_os_str(), "state.json");
             assert!(args.resume);
+            assert!(args.no_progress);
         }
         _ => panic!("expected optimize command"),
     }
@@ -116,6 +121,8 @@ fn parse_inspect_options() {
         "input.mbtiles",
         "--sample",
         "0.1",
+        "--sample-seed",
+        "42",
         "--topn",
         "5",
         "--histogram-buckets",
@@ -135,6 +142,10 @@ fn parse_inspect_options() {
         "--layers",
         "roads,buildings",
         "--recommend",
+        "--recommend-max-layer-bytes",
+        "50000",
+        "--recommend-max-layer-features",
+        "200",
         "--fast",
         "--list-tiles",
         "--limit",
@@ -144,6 +155,7 @@ fn parse_inspect_options() {
         "--ndjson-lite",
         "--ndjson-compact",
         "--include-layer-list",
+        "--percentiles",
         "--tile-info-format",
         "compact",
     ]);
@@ -152,22 +164,26 @@ fn parse_inspect_options() {
         Some(Command::Inspect(args)) => {
             assert_eq!(args.input.as_os_str(), "input.mbtiles");
             assert_eq!(args.sample.as_deref(), Some("0.1"));
+            assert_eq!(args.sample_seed, Some(42));
+            assert!(args.percentiles);
             assert_eq!(args.topn, Some(5));
             assert_eq!(args.histogram_buckets, 12);
-            assert_eq!(args.output, ReportFormat::Json);
+            assert_eq!(args.output, InspectReportFormat::Json);
             assert_eq!(args.stats.as_deref(), Some("summary,zoom"));
             assert!(args.no_progress);
             assert_eq!(args.zoom, Some(3));
             assert_eq!(args.x, None);
             assert_eq!(args.y, None);
             assert_eq!(args.bucket, Some(2));
-            assert_eq!(args.tile.as_deref(), Some("3/4/5"));
+            assert_eq!(args.tile, vec!["3/4/5".to_string()]);
             assert!(args.summary);
             assert_eq!(
                 args.layers,
                 vec!["roads".to_string(), "buildings".to_string()]
             );
             assert!(args.recommend);
+            assert_eq!(args.recommend_max_layer_bytes, Some(50_000));
+            assert_eq!(args.recommend_max_layer_features, Some(200));
             assert!(args.fast);
             assert!(args.list_tiles);
             assert_eq!(args.limit, 20);
@@ -181,6 +197,112 @@ fn parse_inspect_options() {
     }
 }
 
+#[test]
+fn parse_inspect_csv_section_input_format_and_sparkline() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "input.mbtiles",
+        "--report-format",
+        "csv",
+        "--csv-section",
+        "top_tiles",
+        "--input-format",
+        "mbtiles",
+        "--sparkline",
+    ]);
+
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert_eq!(args.output, InspectReportFormat::Csv);
+            assert_eq!(
+                args.csv_section,
+                Some(vt_optimizer::cli::CsvSection::TopTiles)
+            );
+            assert_eq!(args.input_format.as_deref(), Some("mbtiles"));
+            assert!(args.sparkline);
+        }
+        _ => panic!("expected inspect command"),
+    }
+}
+
+#[test]
+fn parse_report_format_csv_only_for_inspect() {
+    for args in [
+        ["optimize", "input.mbtiles"],
+        ["style-check", "--style=style.json"],
+    ] {
+        let err = Cli::try_parse_from(
+            ["vt-optimizer"]
+                .into_iter()
+                .chain(args)
+                .chain(["--report-format", "csv"]),
+        )
+        .expect_err("csv report format");
+        assert_eq!(err.kind(), clap::error::ErrorKind::InvalidValue, "{args:?}");
+    }
+    let help = Cli::command()
+        .find_subcommand_mut("optimize")
+        .expect("optimize command")
+        .render_help()
+        .to_string();
+    assert!(!help.contains("csv"), "{help}");
+}
+
+#[test]
+fn parse_simplify_batch_options() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "simplify",
+        "input.mbtiles",
+        "--zoom",
+        "14",
+        "--layer",
+        "water",
+        "--tolerance",
+        "2",
+        "--over-limit-only",
+        "--max-tile-bytes",
+        "500000",
+    ]);
+
+    match cli.command {
+        Some(Command::Simplify(args)) => {
+            assert_eq!(args.z, Some(14));
+            assert_eq!(args.x, None);
+            assert_eq!(args.layer, vec!["water".to_string()]);
+            assert_eq!(args.tolerance, Some(2.0));
+            assert!(args.over_limit_only);
+            assert!(!args.all_zooms);
+            assert_eq!(args.max_tile_bytes, 500_000);
+        }
+        _ => panic!("expected simplify command"),
+    }
+}
+
+#[test]
+fn parse_style_check_options() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "style-check",
+        "--style",
+        "style.json",
+        "--strict",
+        "--report-format",
+        "json",
+    ]);
+
+    match cli.command {
+        Some(Command::StyleCheck(args)) => {
+            assert_eq!(args.style.as_os_str(), "style.json");
+            assert!(args.strict);
+            assert_eq!(args.max_zoom, 22);
+            assert_eq!(args.report_format, ReportFormat::Json);
+        }
+        _ => panic!("expected style-check command"),
+    }
+}
+
 #[test]
 fn parse_inspect_tile_coords_short_flags() {
     let cli = Cli::parse_from([
@@ -204,6 +326,25 @@ fn parse_inspect_tile_coords_short_flags() {
     }
 }
 
+#[test]
+fn parse_inspect_count_only() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "input.mbtiles",
+        "--count-only",
+        "--zoom",
+        "3",
+    ]);
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert!(args.count_only);
+            assert_eq!(args.zoom, Some(3));
+        }
+        _ => panic!("expected inspect command"),
+    }
+}
+
 #[test]
 fn parse_inspect_output_ndjson() {
     let cli = Cli::parse_from([
@@ -215,12 +356,74 @@ fn parse_inspect_output_ndjson() {
     ]);
     match cli.command {
         Some(Command::Inspect(args)) => {
-            assert_eq!(args.output, ReportFormat::Ndjson);
+            assert_eq!(args.output, InspectReportFormat::Ndjson);
         }
         _ => panic!("expected inspect command"),
     }
 }
 
+#[test]
+fn parse_json_compact_and_pretty_toggle() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "input.mbtiles",
+        "--report-format",
+        "json",
+        "--compact",
+    ]);
+    match cli.command {
+        Some(Command::Inspect(args)) => assert!(args.compact),
+        _ => panic!("expected inspect command"),
+    }
+
+    // The last of --compact/--pretty wins.
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "optimize",
+        "input.mbtiles",
+        "--compact",
+        "--pretty",
+    ]);
+    match cli.command {
+        Some(Command::Optimize(args)) => assert!(!args.compact),
+        _ => panic!("expected optimize command"),
+    }
+}
+
+#[test]
+fn parse_inspect_list_tiles_size_range() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "input.mbtiles",
+        "--list-tiles",
+        "--min-bytes",
+        "1000",
+        "--max-bytes",
+        "5000",
+    ]);
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert_eq!(args.min_bytes, Some(1000));
+            assert_eq!(args.max_bytes, Some(5000));
+        }
+        _ => panic!("expected inspect command"),
+    }
+
+    let err = Cli::try_parse_from([
+        "vt-optimizer",
+        "inspect",
+        "input.mbtiles",
+        "--list-tiles",
+        "--bucket",
+        "2",
+        "--min-bytes",
+        "1000",
+    ]);
+    assert!(err.is_err());
+}
+
 #[test]
 fn parse_inspect_layers_deprecated_alias() {
     let cli = Cli::parse_from([
@@ -255,3 +458,212 @@ fn inspect_help_describes_fields() {
     assert!(help.contains("Include per-layer stats"));
     assert!(help.contains("Tile summary detail level"));
 }
+
+#[test]
+fn parse_optimize_multiple_styles() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "optimize",
+        "planet.mbtiles",
+        "--style",
+        "light.json",
+        "--style",
+        "dark.json",
+    ]);
+    match cli.command {
+        Some(Command::Optimize(args)) => {
+            assert_eq!(
+                args.style,
+                vec![
+                    std::path::PathBuf::from("light.json"),
+                    std::path::PathBuf::from("dark.json")
+                ]
+            );
+        }
+        _ => panic!("expected optimize command"),
+    }
+}
+
+#[test]
+fn parse_optimize_style_source() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "optimize",
+        "overlay.mbtiles",
+        "--style",
+        "style.json",
+        "--source",
+        "overlay",
+    ]);
+    match cli.command {
+        Some(Command::Optimize(args)) => {
+            assert_eq!(args.source.as_deref(), Some("overlay"));
+        }
+        _ => panic!("expected optimize command"),
+    }
+}
+
+#[test]
+fn parse_optimize_keep_and_drop_layers() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "optimize",
+        "planet.mbtiles",
+        "--keep-layer",
+        "roads",
+        "--keep-layer",
+        "water",
+        "--drop-layer",
+        "poi",
+    ]);
+    match cli.command {
+        Some(Command::Optimize(args)) => {
+            assert!(args.style.is_empty());
+            assert_eq!(args.keep_layer, vec!["roads", "water"]);
+            assert_eq!(args.drop_layer, vec!["poi"]);
+        }
+        _ => panic!("expected optimize command"),
+    }
+    assert!(
+        Cli::try_parse_from([
+            "vt-optimizer",
+            "optimize",
+            "planet.mbtiles",
+            "--style",
+            "style.json",
+            "--drop-layer",
+            "poi",
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn parse_copy_brotli_quality_and_window() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "copy",
+        "planet.mbtiles",
+        "--output",
+        "planet.pmtiles",
+        "--brotli-quality",
+        "11",
+        "--brotli-window",
+        "24",
+    ]);
+    match cli.command {
+        Some(Command::Copy(args)) => {
+            assert_eq!(args.brotli_quality, 11);
+            assert_eq!(args.brotli_window, 24);
+        }
+        _ => panic!("expected copy command"),
+    }
+}
+
+#[test]
+fn parse_inspect_attributes_size() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "planet.mbtiles",
+        "--include-layer-list",
+        "--attributes-size",
+    ]);
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert!(args.include_layer_list);
+            assert!(args.attributes_size);
+        }
+        _ => panic!("expected inspect command"),
+    }
+}
+
+#[test]
+fn parse_inspect_empty_exact() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "planet.mbtiles",
+        "--empty-threshold",
+        "80",
+        "--empty-exact",
+    ]);
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert_eq!(args.empty_threshold, 80);
+            assert!(args.empty_exact);
+        }
+        _ => panic!("expected inspect command"),
+    }
+}
+
+#[test]
+fn parse_inspect_immutable() {
+    let cli = Cli::parse_from(["vt-optimizer", "inspect", "live.mbtiles", "--immutable"]);
+    match cli.command {
+        Some(Command::Inspect(args)) => assert!(args.immutable),
+        _ => panic!("expected inspect command"),
+    }
+}
+
+#[test]
+fn parse_inspect_style_preview() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "planet.mbtiles",
+        "--style",
+        "style.json",
+        "--source",
+        "osm",
+    ]);
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert_eq!(args.style.as_deref(), Some(Path::new("style.json")));
+            assert_eq!(args.source.as_deref(), Some("osm"));
+        }
+        _ => panic!("expected inspect command"),
+    }
+    assert!(
+        Cli::try_parse_from([
+            "vt-optimizer",
+            "inspect",
+            "planet.mbtiles",
+            "--source",
+            "osm"
+        ])
+        .is_err()
+    );
+    assert!(
+        Cli::try_parse_from([
+            "vt-optimizer",
+            "inspect",
+            "planet.mbtiles",
+            "--style",
+            "style.json",
+            "--layers-only",
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn parse_get_options() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "get",
+        "planet.pmtiles",
+        "14/8723/5473",
+        "--raw",
+        "--output",
+        "tile.mvt",
+    ]);
+    match cli.command {
+        Some(Command::Get(args)) => {
+            assert_eq!(args.tile, "14/8723/5473");
+            assert!(args.raw);
+            assert_eq!(args.output, Some(std::path::PathBuf::from("tile.mvt")));
+        }
+        _ => panic!("expected get command"),
+    }
+}
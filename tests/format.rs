@@ -2,7 +2,8 @@ use std::path::Path;
 
 use vt_optimizer::format::validate_output_format_matches_path;
 use vt_optimizer::format::{
-    TileFormat, decide_formats, default_output_path_pruned, plan_copy, plan_optimize,
+    RasterFormat, TileFormat, TileMediaType, decide_formats, default_output_path_pruned,
+    detect_tile_media_type, is_remote_input, plan_copy, plan_optimize, resolve_input_format,
     resolve_output_path,
 };
 
@@ -74,6 +75,20 @@ fn decide_formats_errors_when_input_unknown_and_no_override() {
     assert!(msg.contains("cannot infer input format"));
 }
 
+#[test]
+fn resolve_input_format_uses_override_for_unknown_extension() {
+    assert_eq!(
+        resolve_input_format(Path::new("tiles.db"), Some("PMTiles")).expect("override"),
+        TileFormat::Pmtiles
+    );
+    assert_eq!(
+        resolve_input_format(Path::new("tiles.mbtiles"), None).expect("extension"),
+        TileFormat::Mbtiles
+    );
+    let err = resolve_input_format(Path::new("tiles.db"), None).expect_err("should error");
+    assert!(err.to_string().contains("--input-format"));
+}
+
 #[test]
 fn decide_formats_errors_on_unknown_override() {
     let err = decide_formats(Path::new("input.mbtiles"), None, Some("tilejson"), None)
@@ -169,6 +184,26 @@ fn plan_optimize_rejects_output_conflict() {
     assert!(msg.contains("conflicts"));
 }
 
+#[test]
+fn from_extension_detects_remote_pmtiles_urls() {
+    let url = Path::new("https://example.com/tiles.pmtiles?token=abc");
+    assert!(is_remote_input(url));
+    assert_eq!(TileFormat::from_extension(url), Some(TileFormat::Pmtiles));
+    assert!(!is_remote_input(Path::new("tiles.pmtiles")));
+}
+
+#[test]
+fn plan_optimize_rejects_remote_input() {
+    let err = plan_optimize(
+        Path::new("https://example.com/tiles.pmtiles"),
+        None,
+        None,
+        None,
+    )
+    .expect_err("remote input");
+    assert!(err.to_string().contains("remote inputs"));
+}
+
 #[test]
 fn default_output_path_pruned_changes_extension() {
     let path = default_output_path_pruned(Path::new("planet.mbtiles"), TileFormat::Pmtiles);
@@ -208,3 +243,38 @@ fn resolve_output_path_defaults_to_pruned() {
     let path = resolve_output_path(Path::new("input.mbtiles"), None, TileFormat::Mbtiles);
     assert_eq!(path.as_os_str(), "input.pruned.mbtiles");
 }
+
+#[test]
+fn detect_tile_media_type_reads_magic_bytes() {
+    let cases: [(&[u8], TileMediaType); 9] = [
+        (&[0x1f, 0x8b, 0x08, 0x00], TileMediaType::Mvt),
+        (&[0x1a, 0x05, 0x78, 0x02], TileMediaType::Mvt),
+        (&[], TileMediaType::Mvt),
+        (b"\x89PNG\r\n\x1a\n\0\0", TileMediaType::Png),
+        (&[0xff, 0xd8, 0xff, 0xe0], TileMediaType::Jpeg),
+        (b"RIFF\x10\0\0\0WEBPVP8 ", TileMediaType::Webp),
+        (b"\0\0\0\x1cftypavif\0\0\0\0", TileMediaType::Avif),
+        (b"\0\0\0\x1cftypmif1\0\0\0\0", TileMediaType::Unknown),
+        (&[0x8b, 0x02, 0x80], TileMediaType::Unknown),
+    ];
+    for (bytes, expected) in cases {
+        assert_eq!(detect_tile_media_type(bytes), expected, "{bytes:?}");
+    }
+    assert_eq!(
+        RasterFormat::from_magic(b"\0\0\0\x1cftypavis\0\0\0\0"),
+        Some(RasterFormat::Avif)
+    );
+    assert_eq!(
+        RasterFormat::from_metadata("AVIF"),
+        Some(RasterFormat::Avif)
+    );
+    assert_eq!(
+        TileMediaType::from_pmtiles_tile_type(5),
+        TileMediaType::Avif
+    );
+    assert_eq!(TileMediaType::from_pmtiles_tile_type(1), TileMediaType::Mvt);
+    assert_eq!(
+        TileMediaType::from_pmtiles_tile_type(0),
+        TileMediaType::Unknown
+    );
+}
@@ -0,0 +1,267 @@
+use std::fs;
+use std::path::Path;
+
+use mvt::{GeomEncoder, GeomType, Tile};
+use serde_json::Value;
+
+use vt_optimizer::geojson::{
+    GeojsonImportOptions, OutsideTileMode, export_mbtiles_geojson, export_pmtiles_geojson,
+    import_mbtiles_geojson, import_pmtiles_geojson,
+};
+use vt_optimizer::mbtiles::TileCoord;
+use vt_optimizer::pmtiles::{PmtilesFile, mbtiles_to_pmtiles};
+use vt_optimizer::source::TileSource;
+
+/// One square covering the north-west quarter of the tile, plus a point.
+fn create_export_mbtiles(path: &Path) {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("water");
+    let mut encoder = GeomEncoder::new(GeomType::Polygon);
+    for (x, y) in [(0.0, 0.0), (2048.0, 0.0), (2048.0, 2048.0), (0.0, 2048.0)] {
+        encoder.add_point(x, y).expect("point");
+    }
+    let mut feature = layer.into_feature(encoder.encode().expect("encode"));
+    feature.set_id(42);
+    feature.add_tag_string("class", "lake");
+    tile.add_layer(feature.into_layer()).expect("add water");
+
+    let layer = tile.create_layer("pois");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(4096.0, 4096.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    tile.add_layer(layer.into_feature(geom).into_layer())
+        .expect("add pois");
+
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        ",
+    )
+    .expect("schema");
+    // XYZ 1/0/0 is TMS row 1
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 0, 1, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("tile insert");
+}
+
+fn read_geojson(path: &Path) -> Value {
+    serde_json::from_slice(&fs::read(path).expect("read geojson")).expect("parse geojson")
+}
+
+fn assert_close(actual: &Value, expected: f64) {
+    let actual = actual.as_f64().expect("number");
+    assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+}
+
+#[test]
+fn export_mbtiles_geojson_maps_square_to_lon_lat() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_export_mbtiles(&input);
+    let output = dir.path().join("tile.geojson");
+    let coord = TileCoord {
+        zoom: 1,
+        x: 0,
+        y: 0,
+    };
+
+    let count = export_mbtiles_geojson(&input, coord, &[], &output).expect("export");
+    assert_eq!(count, 2);
+    let json = read_geojson(&output);
+    assert_eq!(json["type"], "FeatureCollection");
+    let water = &json["features"][0];
+    assert_eq!(water["id"], 42);
+    assert_eq!(water["properties"]["class"], "lake");
+    assert_eq!(water["properties"]["layer"], "water");
+    assert_eq!(water["geometry"]["type"], "Polygon");
+
+    let ring = water["geometry"]["coordinates"][0]
+        .as_array()
+        .expect("ring");
+    let lons: Vec<f64> = ring.iter().map(|c| c[0].as_f64().unwrap()).collect();
+    let lats: Vec<f64> = ring.iter().map(|c| c[1].as_f64().unwrap()).collect();
+    let fold = |values: &[f64], f: fn(f64, f64) -> f64| values.iter().copied().reduce(f).unwrap();
+    assert_close(&fold(&lons, f64::min).into(), -180.0);
+    assert_close(&fold(&lons, f64::max).into(), -90.0);
+    assert_close(&fold(&lats, f64::min).into(), 66.51326044311186);
+    assert_close(&fold(&lats, f64::max).into(), 85.0511287798066);
+    // RFC 7946 exterior rings are counterclockwise
+    let area: f64 = ring
+        .windows(2)
+        .map(|pair| {
+            pair[0][0].as_f64().unwrap() * pair[1][1].as_f64().unwrap()
+                - pair[1][0].as_f64().unwrap() * pair[0][1].as_f64().unwrap()
+        })
+        .sum();
+    assert!(area > 0.0, "exterior ring is clockwise");
+
+    let poi = &json["features"][1];
+    assert_eq!(poi["geometry"]["type"], "Point");
+    assert_close(&poi["geometry"]["coordinates"][0], 0.0);
+    assert_close(&poi["geometry"]["coordinates"][1], 0.0);
+}
+
+#[test]
+fn export_pmtiles_geojson_filters_layers() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_export_mbtiles(&input);
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("convert");
+    let output = dir.path().join("tile.geojson");
+    let coord = TileCoord {
+        zoom: 1,
+        x: 0,
+        y: 0,
+    };
+
+    let count =
+        export_pmtiles_geojson(&pmtiles, coord, &["pois".to_string()], &output).expect("export");
+    assert_eq!(count, 1);
+    assert_eq!(
+        read_geojson(&output)["features"][0]["properties"]["layer"],
+        "pois"
+    );
+
+    let missing = TileCoord {
+        zoom: 1,
+        x: 1,
+        y: 1,
+    };
+    let err = export_pmtiles_geojson(&pmtiles, missing, &[], &output).expect_err("missing");
+    assert!(err.to_string().contains("tile 1/1/1 not found"), "{err}");
+}
+
+fn decoded_features(data: Vec<u8>) -> Vec<(String, mvt_reader::feature::Feature)> {
+    let reader = mvt_reader::Reader::new(data).expect("decode");
+    let mut features = Vec::new();
+    for layer in reader.get_layer_metadata().expect("layers") {
+        for feature in reader.get_features(layer.layer_index).expect("features") {
+            features.push((layer.name.clone(), feature));
+        }
+    }
+    features
+}
+
+#[test]
+fn import_mbtiles_geojson_round_trips_export() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_export_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 1, 1, x'00')",
+        [],
+    )
+    .expect("other tile");
+    drop(conn);
+    let coord = TileCoord {
+        zoom: 1,
+        x: 0,
+        y: 0,
+    };
+    let geojson = dir.path().join("tile.geojson");
+    export_mbtiles_geojson(&input, coord, &[], &geojson).expect("export");
+    let mut json = read_geojson(&geojson);
+    json["features"][0]["properties"]["class"] = "reservoir".into();
+    fs::write(&geojson, json.to_string()).expect("write geojson");
+
+    let output = dir.path().join("output.mbtiles");
+    let count = import_mbtiles_geojson(
+        &input,
+        coord,
+        &geojson,
+        &output,
+        &GeojsonImportOptions::default(),
+    )
+    .expect("import");
+    assert_eq!(count, 2);
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let read = |row: u32, column: u32| -> Vec<u8> {
+        conn.query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = ?1 AND tile_row = ?2",
+            (column, row),
+            |row| row.get(0),
+        )
+        .expect("read tile")
+    };
+    assert_eq!(read(1, 1), vec![0u8]);
+    let original = rusqlite::Connection::open(&input)
+        .expect("open input")
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE tile_column = 0",
+            [],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+        .expect("read original");
+    let before = decoded_features(original);
+    let after = decoded_features(read(1, 0));
+    assert_eq!(after.len(), before.len());
+    for ((layer_before, before), (layer_after, after)) in before.iter().zip(&after) {
+        assert_eq!(layer_before, layer_after);
+        assert_eq!(before.geometry, after.geometry, "{layer_after}");
+        assert_eq!(before.id, after.id);
+    }
+    let class = after[0].1.properties.as_ref().expect("properties")["class"].clone();
+    assert_eq!(
+        class,
+        mvt_reader::feature::Value::String("reservoir".into())
+    );
+}
+
+#[test]
+fn import_pmtiles_geojson_clips_or_rejects_outside_features() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_export_mbtiles(&input);
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("convert");
+    let geojson = dir.path().join("edit.geojson");
+    fs::write(
+        &geojson,
+        r#"{"type":"FeatureCollection","features":[
+  {"type":"Feature","id":5,"geometry":{"type":"Point","coordinates":[-90,45]},"properties":{"name":"inside","rank":-2,"tags":["a"]}},
+  {"type":"Feature","geometry":{"type":"Point","coordinates":[90,-45]},"properties":{"name":"outside"}}
+]}"#,
+    )
+    .expect("write geojson");
+    let coord = TileCoord {
+        zoom: 1,
+        x: 0,
+        y: 0,
+    };
+    let output = dir.path().join("output.pmtiles");
+
+    let mut options = GeojsonImportOptions {
+        layer: Some("edits".to_string()),
+        ..GeojsonImportOptions::default()
+    };
+    let count =
+        import_pmtiles_geojson(&pmtiles, coord, &geojson, &output, &options).expect("import");
+    assert_eq!(count, 1);
+    let source = PmtilesFile::open(&output).expect("open output");
+    let data = source.tile_data(coord).expect("read").expect("tile");
+    let features = decoded_features(source.decode_tile(&data).expect("decode"));
+    assert_eq!(features.len(), 1);
+    let (layer, feature) = &features[0];
+    assert_eq!(layer, "edits");
+    assert_eq!(feature.id, Some(5));
+    let properties = feature.properties.as_ref().expect("properties");
+    assert_eq!(properties["rank"], mvt_reader::feature::Value::SInt(-2));
+    assert_eq!(
+        properties["tags"],
+        mvt_reader::feature::Value::String(r#"["a"]"#.to_string())
+    );
+
+    options.outside = OutsideTileMode::Reject;
+    let err = import_pmtiles_geojson(&pmtiles, coord, &geojson, &output, &options)
+        .expect_err("outside feature");
+    assert!(format!("{err:#}").contains("feature 1"), "{err:#}");
+}
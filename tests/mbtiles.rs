@@ -1,9 +1,17 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
+use vt_optimizer::error::VtError;
+use vt_optimizer::format::{RasterFormat, TileMediaType};
 use vt_optimizer::mbtiles::{
-    InspectOptions, MbtilesStats, MbtilesZoomStats, SampleSpec, copy_mbtiles, inspect_mbtiles,
-    inspect_mbtiles_with_options, parse_sample_spec,
+    DEFAULT_SAMPLE_SEED, DedupStats, InspectOptions, MbtilesConnection, MbtilesFile,
+    MbtilesReadOptions, MbtilesStats, MbtilesZoomStats, RecommendThresholds, SampleSpec,
+    TileCompression, TileCoord, TileTableNames, copy_mbtiles, include_sample, include_zoom_sample,
+    inspect_mbtiles, inspect_mbtiles_with_options, inspect_tile_source_with_options,
+    parse_sample_spec, parse_tile_compression, quick_stats, sample_zoom_offsets, tile_count,
+    tiles_iter,
 };
+use vt_optimizer::pmtiles::{mbtiles_to_pmtiles, pmtiles_to_mbtiles};
 
 fn create_sample_mbtiles(path: &Path) {
     let conn = rusqlite::Connection::open(path).expect("open");
@@ -91,6 +99,7 @@ fn inspect_mbtiles_reports_minimal_stats() {
             total_bytes: 40,
             max_bytes: 30,
             avg_bytes: 20,
+            percentiles: None,
         }
     );
     assert_eq!(
@@ -102,6 +111,7 @@ fn inspect_mbtiles_reports_minimal_stats() {
                 total_bytes: 40,
                 max_bytes: 30,
                 avg_bytes: 20,
+                percentiles: None,
             },
         }]
     );
@@ -117,6 +127,49 @@ fn inspect_mbtiles_reports_minimal_stats() {
     assert!(report.top_tiles.is_empty());
 }
 
+#[test]
+fn inspect_tile_source_reads_in_memory_connection() {
+    let conn = rusqlite::Connection::open_in_memory().expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        INSERT INTO metadata (name, value) VALUES ('name', 'memory');
+        ",
+    )
+    .expect("schema");
+    for (zoom, size) in [(0u8, 10usize), (1, 30), (1, 50)] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, 0, ?2, ?3)",
+            (zoom, size as i64, vec![0u8; size]),
+        )
+        .expect("tile");
+    }
+
+    let source = MbtilesConnection::new(conn);
+    let options = InspectOptions {
+        no_progress: true,
+        topn: 1,
+        histogram_buckets: 2,
+        ..InspectOptions::default()
+    };
+    let report = inspect_tile_source_with_options(&source, options).expect("inspect");
+
+    assert_eq!(report.metadata.get("name"), Some(&"memory".to_string()));
+    assert_eq!(report.overall.tile_count, 3);
+    assert_eq!(report.overall.total_bytes, 90);
+    assert_eq!(report.by_zoom.len(), 2);
+    assert_eq!(report.top_tiles.len(), 1);
+    assert_eq!(report.top_tiles[0].bytes, 50);
+    assert_eq!(report.histogram.len(), 2);
+    assert_eq!(report.histograms_by_zoom.len(), 2);
+}
+
 #[test]
 fn inspect_mbtiles_reports_metadata() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -159,6 +212,184 @@ fn inspect_mbtiles_counts_over_limit_tiles() {
     assert_eq!(report.over_limit_tiles, 1);
 }
 
+#[test]
+fn inspect_mbtiles_reports_exact_and_sampled_over_limit_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    // Every fourth tile of z3 is over the 100 byte limit
+    for x in 0..8u32 {
+        for y in 0..8u32 {
+            let size = if (x * 8 + y) % 4 == 0 { 200 } else { 10 };
+            conn.execute(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, ?1, ?2, ?3)",
+                (x, y, vec![0u8; size]),
+            )
+            .expect("tile");
+        }
+    }
+
+    let full = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            max_tile_bytes: 100,
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+    assert_eq!(full.over_limit_tiles, 16);
+    assert_eq!(full.over_limit_tiles_exact, Some(16));
+    assert_eq!(full.over_limit_tiles_sampled, None);
+
+    for sample in [SampleSpec::Ratio(0.25), SampleSpec::Count(10)] {
+        let report = inspect_mbtiles_with_options(
+            &path,
+            InspectOptions {
+                max_tile_bytes: 100,
+                sample: Some(sample.clone()),
+                no_progress: true,
+                ..InspectOptions::default()
+            },
+        )
+        .expect("inspect");
+        assert!(report.sampled);
+        assert_eq!(report.over_limit_tiles, 16, "{sample:?}");
+        assert_eq!(report.over_limit_tiles_exact, Some(16), "{sample:?}");
+        let sampled = report.over_limit_tiles_sampled.expect("sampled estimate");
+        assert_eq!(sampled.sample_size, report.sample_used_tiles);
+        assert!(sampled.sample_count <= sampled.sample_size);
+        let expected = (sampled.sample_count as f64 * 64.0 / sampled.sample_size as f64).round();
+        assert_eq!(sampled.estimate, expected as u64, "{sample:?}");
+        if let SampleSpec::Count(count) = sample {
+            assert_eq!(sampled.sample_size, count);
+        }
+    }
+}
+
+#[test]
+fn inspect_mbtiles_per_zoom_sample_covers_every_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (vec![0u8; 200],),
+    )
+    .expect("tile");
+    for x in 0..8u32 {
+        for y in 0..8u32 {
+            let size = if (x * 8 + y) % 4 == 0 { 200 } else { 10 };
+            conn.execute(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, ?1, ?2, ?3)",
+                (x, y, vec![0u8; size]),
+            )
+            .expect("tile");
+        }
+    }
+
+    let report = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            max_tile_bytes: 100,
+            sample: Some(SampleSpec::PerZoom(0.25)),
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+    // The single z0 tile plus a quarter of z3
+    assert_eq!(report.sample_used_tiles, 17);
+    assert_eq!(report.over_limit_tiles_exact, Some(17));
+    // Each zoom is scaled by its own sampling rate
+    let sampled = report.over_limit_tiles_sampled.expect("sampled estimate");
+    assert_eq!(sampled.sample_size, 17);
+    assert!(sampled.sample_count >= 1);
+    assert_eq!(sampled.estimate, 1 + (sampled.sample_count - 1) * 4);
+}
+
+#[test]
+fn inspect_mbtiles_uses_empty_tile_threshold() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&path);
+
+    let report = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            empty_tile_max_bytes: 20,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+
+    assert_eq!(report.empty_tiles, 1);
+    assert_eq!(report.empty_ratio, 0.5);
+    assert_eq!(report.empty_tile_max_bytes, 20);
+}
+
+#[test]
+fn inspect_mbtiles_histogram_excludes_empty_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&path);
+
+    // Sizes cached by the stats scan, then a histogram rescan
+    for histogram_cache_max_tiles in [16_000_000, 0] {
+        let report = inspect_mbtiles_with_options(
+            &path,
+            InspectOptions {
+                histogram_buckets: 2,
+                empty_tile_max_bytes: 20,
+                histogram_exclude_empty: true,
+                strict: false,
+                histogram_cache_max_tiles,
+                no_progress: true,
+                ..InspectOptions::default()
+            },
+        )
+        .expect("inspect");
+
+        assert_eq!(report.empty_tiles, 1);
+        assert_eq!(report.histogram.len(), 2);
+        assert_eq!(report.histogram[0].min_bytes, 30);
+        assert_eq!(report.histogram.iter().map(|b| b.count).sum::<u64>(), 1);
+        assert_eq!(report.histogram[0].count, 1);
+        assert!((report.histogram[0].pct_tiles - 1.0).abs() < 1e-6);
+        assert!((report.histogram[0].pct_level_bytes - 1.0).abs() < 1e-6);
+        let zoom_counts: Vec<u64> = report.histograms_by_zoom[0]
+            .buckets
+            .iter()
+            .map(|b| b.count)
+            .collect();
+        assert_eq!(zoom_counts, vec![1, 0]);
+    }
+}
+
 #[test]
 fn copy_mbtiles_copies_tiles_and_metadata() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -214,6 +445,127 @@ fn copy_mbtiles_supports_map_images_schema() {
     assert_eq!(report.overall.avg_bytes, 15);
 }
 
+/// Compact schema without the `tiles` view: (0,0,0) and (1,0,0) share a blob.
+fn create_sample_mbtiles_shallow(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles_shallow (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data_id INTEGER,
+            PRIMARY KEY (zoom_level, tile_column, tile_row)
+        ) WITHOUT ROWID;
+        CREATE TABLE tiles_data (tile_data_id INTEGER PRIMARY KEY, tile_data BLOB);
+        INSERT INTO metadata (name, value) VALUES ('name', 'shallow');
+        INSERT INTO tiles_shallow VALUES (0, 0, 0, 1), (1, 0, 0, 1), (1, 1, 1, 2);
+        ",
+    )
+    .expect("schema");
+    for (id, data) in [(1, vec![1u8; 10]), (2, vec![2u8; 20])] {
+        conn.execute(
+            "INSERT INTO tiles_data (tile_data_id, tile_data) VALUES (?1, ?2)",
+            (id, data),
+        )
+        .expect("tiles_data");
+    }
+}
+
+#[test]
+fn tile_count_counts_every_schema() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let tiles = dir.path().join("tiles.mbtiles");
+    create_sample_mbtiles(&tiles);
+    assert_eq!(tile_count(&tiles, None).expect("count"), 2);
+    assert_eq!(tile_count(&tiles, Some(0)).expect("count z0"), 2);
+    assert_eq!(tile_count(&tiles, Some(1)).expect("count z1"), 0);
+
+    let map_images = dir.path().join("map_images.mbtiles");
+    create_sample_mbtiles_map_images(&map_images);
+    assert_eq!(tile_count(&map_images, None).expect("count"), 2);
+
+    let shallow = dir.path().join("shallow.mbtiles");
+    create_sample_mbtiles_shallow(&shallow);
+    assert_eq!(tile_count(&shallow, None).expect("count"), 3);
+    assert_eq!(tile_count(&shallow, Some(1)).expect("count z1"), 2);
+}
+
+#[test]
+fn inspect_mbtiles_supports_shallow_schema() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_sample_mbtiles_shallow(&input);
+
+    let options = InspectOptions {
+        no_progress: true,
+        dedup_stats: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&input, options).expect("inspect");
+    assert_eq!(report.overall.tile_count, 3);
+    assert_eq!(report.overall.total_bytes, 40);
+    assert_eq!(report.overall.max_bytes, 20);
+    // The tiles sharing a tile_data_id are already stored once
+    assert_eq!(
+        report.dedup.expect("dedup report").overall,
+        DedupStats {
+            unique_tiles: 2,
+            duplicate_tiles: 1,
+            reclaimable_bytes: 0,
+        }
+    );
+}
+
+#[test]
+fn copy_mbtiles_preserves_shallow_dedup() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_sample_mbtiles_shallow(&input);
+
+    copy_mbtiles(&input, &output).expect("copy");
+
+    let report = inspect_mbtiles(&output).expect("inspect output");
+    assert_eq!(report.overall.tile_count, 3);
+    assert_eq!(report.overall.total_bytes, 40);
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let blobs: i64 = conn
+        .query_row("SELECT COUNT(*) FROM tiles_data", [], |row| row.get(0))
+        .expect("count tiles_data");
+    assert_eq!(blobs, 2);
+    let view_rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM tiles", [], |row| row.get(0))
+        .expect("count tiles view");
+    assert_eq!(view_rows, 3);
+}
+
+#[test]
+fn mbtiles_to_pmtiles_reads_shallow_schema_without_view() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    let roundtrip = dir.path().join("roundtrip.mbtiles");
+    create_sample_mbtiles_shallow(&input);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    pmtiles_to_mbtiles(&pmtiles, &roundtrip).expect("pmtiles->mbtiles");
+
+    let report = inspect_mbtiles(&roundtrip).expect("inspect roundtrip");
+    assert_eq!(report.overall.tile_count, 3);
+    assert_eq!(report.overall.total_bytes, 40);
+    let conn = rusqlite::Connection::open(&roundtrip).expect("open roundtrip");
+    let tile: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 1 AND tile_row = 1",
+            [],
+            |row| row.get(0),
+        )
+        .expect("z1 tile");
+    assert_eq!(tile, vec![2u8; 20]);
+}
+
 #[test]
 fn inspect_mbtiles_rejects_non_mbtiles_path() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -221,10 +573,149 @@ fn inspect_mbtiles_rejects_non_mbtiles_path() {
     create_sample_mbtiles(&path);
 
     let err = inspect_mbtiles(&path).expect_err("should error");
+    assert!(matches!(err, VtError::NotMbtiles { .. }), "{err:?}");
     let msg = err.to_string();
     assert!(msg.contains("mbtiles"));
 }
 
+#[test]
+fn inspect_mbtiles_reports_missing_file_as_not_found() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("missing.mbtiles");
+
+    let err = inspect_mbtiles(&path).expect_err("should error");
+    assert!(
+        matches!(&err, VtError::Io { source, .. } if source.kind() == std::io::ErrorKind::NotFound),
+        "{err:?}"
+    );
+    assert!(err.to_string().contains("mbtiles not found"));
+}
+
+#[test]
+fn inspect_mbtiles_waits_for_a_lock_to_be_released() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&path);
+
+    let writer = rusqlite::Connection::open(&path).expect("open writer");
+    writer.execute_batch("BEGIN EXCLUSIVE").expect("lock");
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        writer.execute_batch("COMMIT").expect("unlock");
+    });
+
+    let report = inspect_mbtiles(&path).expect("inspect after the lock is released");
+    assert_eq!(report.overall.tile_count, 2);
+    release.join().expect("writer thread");
+}
+
+#[test]
+fn inspect_reads_a_custom_tile_table() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tile_store (z INTEGER, x INTEGER, y INTEGER, data BLOB);
+        INSERT INTO tile_store VALUES (0, 0, 0, x'00000000000000000000');
+        INSERT INTO tile_store VALUES (1, 1, 0, x'0000000000000000000000000000000000000000');
+        ",
+    )
+    .expect("schema");
+    drop(conn);
+
+    let names = TileTableNames {
+        table: "tile_store".to_string(),
+        zoom: "z".to_string(),
+        column: "x".to_string(),
+        row: "y".to_string(),
+        data: "data".to_string(),
+    };
+    let source = MbtilesFile::open_with_options(
+        &path,
+        MbtilesReadOptions {
+            tile_table: Some(names.clone()),
+            ..MbtilesReadOptions::default()
+        },
+    )
+    .expect("open custom table");
+    let report = inspect_tile_source_with_options(
+        &source,
+        InspectOptions {
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+    assert_eq!(report.overall.tile_count, 2);
+    assert_eq!(report.overall.total_bytes, 30);
+    assert_eq!(
+        report.by_zoom.iter().map(|z| z.zoom).collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+
+    let err = MbtilesFile::open_with_options(
+        &path,
+        MbtilesReadOptions {
+            tile_table: Some(TileTableNames {
+                data: "blob".to_string(),
+                ..names
+            }),
+            ..MbtilesReadOptions::default()
+        },
+    )
+    .expect_err("missing column");
+    let msg = format!("{err:#}");
+    assert!(msg.contains("blob"), "{msg}");
+    assert!(msg.contains("available columns: z, x, y, data"), "{msg}");
+
+    let err = MbtilesFile::open_with_options(
+        &path,
+        MbtilesReadOptions {
+            tile_table: Some(TileTableNames {
+                table: "tiles_v2".to_string(),
+                ..TileTableNames::default()
+            }),
+            ..MbtilesReadOptions::default()
+        },
+    )
+    .expect_err("missing table");
+    assert!(format!("{err:#}").contains("available tables: metadata, tile_store"));
+}
+
+#[test]
+fn immutable_source_reads_a_locked_database() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&path);
+
+    let writer = rusqlite::Connection::open(&path).expect("open writer");
+    writer.execute_batch("BEGIN EXCLUSIVE").expect("lock");
+
+    let source = MbtilesFile::open_immutable(&path).expect("open immutable");
+    let report = inspect_tile_source_with_options(
+        &source,
+        InspectOptions {
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+    assert_eq!(report.overall.tile_count, 2);
+    drop(writer);
+}
+
+#[test]
+fn inspect_mbtiles_accepts_path_without_tile_extension() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("tiles.db");
+    create_sample_mbtiles(&path);
+
+    let report = inspect_mbtiles(&path).expect("inspect");
+    assert_eq!(report.overall.tile_count, 2);
+}
+
 #[test]
 fn copy_mbtiles_rejects_non_mbtiles_paths() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -245,18 +736,40 @@ fn inspect_mbtiles_topn_and_histogram() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 1,
         histogram_buckets: 2,
         no_progress: true,
         max_tile_bytes: 100,
         zoom: None,
         bucket: None,
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
     assert_eq!(report.top_tiles.len(), 1);
@@ -285,22 +798,287 @@ fn inspect_mbtiles_sample_count() {
 
     let options = InspectOptions {
         sample: Some(SampleSpec::Count(1)),
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 0,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: None,
         bucket: None,
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
     assert_eq!(report.sample_used_tiles, 1);
     assert_eq!(report.overall.tile_count, 1);
+    assert_eq!(report.sample_seed, Some(DEFAULT_SAMPLE_SEED));
+}
+
+#[test]
+fn inspect_sample_count_spreads_across_zooms() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    for zoom in 0..4u32 {
+        for column in 0..10u32 {
+            conn.execute(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, 0, ?3)",
+                (zoom, column, vec![1u8; 10]),
+            )
+            .expect("tile");
+        }
+    }
+    drop(conn);
+
+    let options = InspectOptions {
+        sample: Some(SampleSpec::Count(8)),
+        sample_seed: Some(42),
+        topn: 0,
+        histogram_buckets: 0,
+        no_progress: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(report.sample_used_tiles, 8);
+    assert_eq!(report.sample_seed, Some(42));
+    let per_zoom = report
+        .by_zoom
+        .iter()
+        .map(|zoom| (zoom.zoom, zoom.stats.tile_count))
+        .collect::<Vec<_>>();
+    assert_eq!(per_zoom, vec![(0, 2), (1, 2), (2, 2), (3, 2)]);
+}
+
+#[test]
+fn inspect_dedup_stats_hashes_tile_payloads() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    for (zoom, x, y, byte, size) in [
+        (0u8, 0u32, 0u32, 7u8, 10usize),
+        (1, 0, 0, 7, 10),
+        (1, 0, 1, 7, 10),
+        (1, 1, 0, 8, 20),
+    ] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            (zoom, x, y, vec![byte; size]),
+        )
+        .expect("tile");
+    }
+    drop(conn);
+
+    let options = InspectOptions {
+        no_progress: true,
+        dedup_stats: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    let dedup = report.dedup.expect("dedup report");
+    assert!(!dedup.estimated);
+    assert_eq!(
+        dedup.overall,
+        DedupStats {
+            unique_tiles: 2,
+            duplicate_tiles: 2,
+            reclaimable_bytes: 20,
+        }
+    );
+    assert_eq!(dedup.by_zoom.len(), 2);
+    assert_eq!(dedup.by_zoom[0].stats.duplicate_tiles, 0);
+    assert_eq!(
+        dedup.by_zoom[1].stats,
+        DedupStats {
+            unique_tiles: 2,
+            duplicate_tiles: 1,
+            reclaimable_bytes: 10,
+        }
+    );
+
+    let report = inspect_mbtiles(&path).expect("inspect");
+    assert!(report.dedup.is_none());
+}
+
+#[test]
+fn inspect_dedup_stats_counts_shared_tile_ids_without_hashing() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE map (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_id TEXT);
+        CREATE TABLE images (tile_id TEXT, tile_data BLOB);
+        CREATE VIEW tiles AS
+            SELECT map.zoom_level AS zoom_level, map.tile_column AS tile_column,
+                map.tile_row AS tile_row, images.tile_data AS tile_data
+            FROM map JOIN images ON images.tile_id = map.tile_id;
+        INSERT INTO map VALUES (0, 0, 0, 'a'), (1, 0, 0, 'a'), (1, 1, 1, 'b'), (1, 1, 0, 'c');
+        ",
+    )
+    .expect("schema");
+    // 'b' holds the same bytes as 'a' but is only compared by id
+    for (id, byte) in [("a", 1u8), ("b", 1), ("c", 2)] {
+        conn.execute(
+            "INSERT INTO images (tile_id, tile_data) VALUES (?1, ?2)",
+            (id, vec![byte; 10]),
+        )
+        .expect("image");
+    }
+    drop(conn);
+
+    let options = InspectOptions {
+        no_progress: true,
+        dedup_stats: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    // The tile reusing 'a' is a duplicate already stored once
+    assert_eq!(
+        report.dedup.expect("dedup report").overall,
+        DedupStats {
+            unique_tiles: 3,
+            duplicate_tiles: 1,
+            reclaimable_bytes: 0,
+        }
+    );
+}
+
+#[test]
+fn tiles_iter_streams_every_tile() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&path);
+
+    let mut tiles = tiles_iter(&path)
+        .expect("tiles iter")
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("read tiles");
+    tiles.sort_by_key(|(coord, _)| (coord.zoom, coord.x, coord.y));
+    assert_eq!(
+        tiles,
+        vec![
+            (
+                TileCoord {
+                    zoom: 0,
+                    x: 0,
+                    y: 0
+                },
+                vec![0u8; 10]
+            ),
+            (
+                TileCoord {
+                    zoom: 0,
+                    x: 0,
+                    y: 1
+                },
+                vec![0u8; 30]
+            ),
+        ]
+    );
+
+    // Stopping early must not block on the reader thread.
+    assert_eq!(tiles_iter(&path).expect("tiles iter").take(1).count(), 1);
+
+    let map_images = dir.path().join("map_images.mbtiles");
+    create_sample_mbtiles_map_images(&map_images);
+    let sizes = tiles_iter(&map_images)
+        .expect("tiles iter")
+        .map(|tile| tile.map(|(_, data)| data.len()))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("read tiles");
+    assert_eq!(sizes.len(), 2);
+    assert!(sizes.contains(&10) && sizes.contains(&20));
+}
+
+#[test]
+fn include_sample_seed_is_independent_of_total() {
+    let spec = SampleSpec::Ratio(0.5);
+    let with_small_total = (1..=200)
+        .map(|index| include_sample(index, 200, Some(&spec), Some(7)))
+        .collect::<Vec<_>>();
+    let with_large_total = (1..=200)
+        .map(|index| include_sample(index, 10_000, Some(&spec), Some(7)))
+        .collect::<Vec<_>>();
+    assert_eq!(with_small_total, with_large_total);
+    assert!(with_small_total.iter().any(|included| *included));
+    assert!(with_small_total.iter().any(|included| !*included));
+}
+
+#[test]
+fn include_sample_count_picks_evenly_spaced_tiles() {
+    let spec = SampleSpec::Count(10);
+    let picked = |seed| {
+        (1..=1000)
+            .filter(|index| include_sample(*index, 1000, Some(&spec), seed))
+            .collect::<Vec<_>>()
+    };
+    let default_seed = picked(None);
+    assert_eq!(default_seed, picked(Some(DEFAULT_SAMPLE_SEED)));
+    assert_eq!(default_seed.len(), 10);
+    assert!(default_seed.windows(2).all(|pair| pair[1] - pair[0] == 100));
+    assert_eq!(picked(Some(3)).len(), 10);
+    assert_ne!(picked(Some(3)), default_seed);
+
+    let counts = [(0, 4), (1, 16)].into_iter().collect();
+    let (offsets, total) = sample_zoom_offsets(&counts, None);
+    assert_eq!(
+        offsets.into_iter().collect::<Vec<_>>(),
+        vec![(0, 0), (1, 4)]
+    );
+    assert_eq!(total, 20);
+    let (offsets, total) = sample_zoom_offsets(&counts, Some(1));
+    assert_eq!(offsets.into_iter().collect::<Vec<_>>(), vec![(1, 0)]);
+    assert_eq!(total, 16);
 }
 
 #[test]
@@ -309,4 +1087,200 @@ fn parse_sample_spec_ratio_and_count() {
     assert_eq!(ratio, SampleSpec::Ratio(0.25));
     let count = parse_sample_spec("10").expect("count");
     assert_eq!(count, SampleSpec::Count(10));
+    let per_zoom = parse_sample_spec("z:0.1").expect("per-zoom");
+    assert_eq!(per_zoom, SampleSpec::PerZoom(0.1));
+    assert!(parse_sample_spec("z:5").is_err());
+}
+
+#[test]
+fn include_zoom_sample_applies_ratio_within_each_zoom() {
+    let counts = [(0, 1), (1, 4), (2, 16)].into_iter().collect();
+    let (offsets, total) = sample_zoom_offsets(&counts, None);
+    let picked = |spec: &SampleSpec| {
+        let mut index = 0;
+        let mut picked = BTreeMap::new();
+        for (&zoom, &count) in &counts {
+            for _ in 0..count {
+                index += 1;
+                if include_zoom_sample(index, total, zoom, &counts, &offsets, Some(spec), None) {
+                    *picked.entry(zoom).or_insert(0u64) += 1;
+                }
+            }
+        }
+        picked
+    };
+    let per_zoom = picked(&SampleSpec::PerZoom(0.25));
+    assert_eq!(
+        per_zoom.into_iter().collect::<Vec<_>>(),
+        vec![(0, 1), (1, 1), (2, 4)]
+    );
+    // Other specs keep counting over the whole scan
+    assert_eq!(picked(&SampleSpec::Count(21)).values().sum::<u64>(), 21);
+}
+
+#[test]
+fn parse_tile_compression_codecs_and_levels() {
+    assert_eq!(
+        parse_tile_compression("none").expect("none"),
+        TileCompression::None
+    );
+    assert_eq!(
+        parse_tile_compression("gzip:9").expect("gzip"),
+        TileCompression::Gzip(9)
+    );
+    assert_eq!(
+        parse_tile_compression("brotli").expect("brotli"),
+        TileCompression::Brotli(5)
+    );
+    assert!(parse_tile_compression("gzip:10").is_err());
+    assert!(parse_tile_compression("brotli:12").is_err());
+    assert!(parse_tile_compression("zstd").is_err());
+}
+
+fn create_raster_mbtiles(path: &Path, format: Option<&str>, magic: &[u8]) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    if let Some(format) = format {
+        conn.execute(
+            "INSERT INTO metadata (name, value) VALUES ('format', ?1)",
+            (format,),
+        )
+        .expect("metadata");
+    }
+    for (zoom, len) in [(0u8, 200usize), (1, 500)] {
+        let mut data = magic.to_vec();
+        data.resize(len, 0);
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, 0, 0, ?2)",
+            (zoom, data),
+        )
+        .expect("tile");
+    }
+}
+
+#[test]
+fn inspect_mbtiles_reports_raster_tileset() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("raster.mbtiles");
+    create_raster_mbtiles(&path, Some("png"), b"\x89PNG\r\n\x1a\n");
+
+    let options = InspectOptions {
+        no_progress: true,
+        topn: 1,
+        histogram_buckets: 2,
+        include_layer_list: true,
+        layer_zoom_matrix: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(report.raster_format, Some(RasterFormat::Png));
+    assert_eq!(report.overall.tile_count, 2);
+    assert_eq!(report.overall.total_bytes, 700);
+    assert_eq!(report.top_tiles[0].bytes, 500);
+    assert_eq!(report.histogram.len(), 2);
+    assert!(report.file_layers.is_empty());
+    assert!(report.layer_zoom_matrix.is_empty());
+    let json = serde_json::to_value(&report).expect("json");
+    assert_eq!(json["raster_format"], "png");
+}
+
+#[test]
+fn inspect_mbtiles_detects_raster_from_tile_bytes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("raster.mbtiles");
+    create_raster_mbtiles(&path, None, &[0xff, 0xd8, 0xff, 0xe0]);
+
+    let report = inspect_mbtiles(&path).expect("inspect");
+    assert_eq!(report.raster_format, Some(RasterFormat::Jpeg));
+
+    let vector = dir.path().join("vector.mbtiles");
+    create_sample_mbtiles(&vector);
+    assert_eq!(
+        inspect_mbtiles(&vector).expect("inspect").raster_format,
+        None
+    );
+}
+
+#[test]
+fn inspect_mbtiles_counts_media_types() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("mixed.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        INSERT INTO metadata (name, value) VALUES ('format', 'pbf');
+        ",
+    )
+    .expect("schema");
+    let tiles: [&[u8]; 4] = [
+        &[0x1f, 0x8b, 0x08, 0x00],
+        &[0x1a, 0x00],
+        b"RIFF\x10\0\0\0WEBPVP8 ",
+        b"\0\0\0\x1cftypavif\0\0\0\0",
+    ];
+    for (x, data) in tiles.into_iter().enumerate() {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (2, ?1, 0, ?2)",
+            (x as u32, data),
+        )
+        .expect("tile");
+    }
+
+    let options = InspectOptions {
+        no_progress: true,
+        media_types: true,
+        histogram_exclude_empty: false,
+        strict: false,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(report.raster_format, None);
+    assert_eq!(
+        report.media_types.into_iter().collect::<Vec<_>>(),
+        vec![
+            (TileMediaType::Mvt, 2),
+            (TileMediaType::Webp, 1),
+            (TileMediaType::Avif, 1),
+        ]
+    );
+
+    let report = inspect_mbtiles(&path).expect("inspect");
+    assert!(report.media_types.is_empty());
+}
+
+#[test]
+fn quick_stats_matches_inspect_totals() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&path);
+    let map_images = dir.path().join("map-images.mbtiles");
+    create_sample_mbtiles_map_images(&map_images);
+
+    for path in [&path, &map_images] {
+        let report = inspect_mbtiles(path).expect("inspect");
+        assert_eq!(
+            quick_stats(path, None).expect("quick stats"),
+            report.overall
+        );
+    }
+    let zoom0 = quick_stats(&path, Some(0)).expect("zoom 0");
+    assert_eq!(
+        (zoom0.tile_count, zoom0.total_bytes, zoom0.max_bytes),
+        (2, 40, 30)
+    );
+    let zoom5 = quick_stats(&path, Some(5)).expect("zoom 5");
+    assert_eq!((zoom5.tile_count, zoom5.avg_bytes), (0, 0));
 }
@@ -1,8 +1,15 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use mvt::{GeomEncoder, GeomType, Tile};
 
-use vt_optimizer::mbtiles::{FileLayerSummary, InspectOptions, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{
+    CardinalityOptions, DistinctValueSketch, FileLayerSummary, InspectOptions, MetadataMismatch,
+    MetadataMismatchKind, PROPERTY_HISTOGRAM_TOP, PropertyValueCount, RecommendThresholds,
+    SampleSpec, StyleLayerStatus, TopValueCounter, inspect_mbtiles_with_options,
+    layer_encoded_sizes,
+};
+use vt_optimizer::pmtiles::{inspect_pmtiles_with_options, mbtiles_to_pmtiles};
 
 fn create_layer_tile() -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -64,18 +71,40 @@ fn inspect_collects_file_layer_list() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 0,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: None,
         bucket: None,
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: true,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -90,6 +119,17 @@ fn inspect_collects_file_layer_list() {
                 feature_count: 1,
                 property_key_count: 1,
                 property_value_count: 1,
+                invalid_geometry_count: None,
+                tiles_with_layer: None,
+                min_features_per_tile: None,
+                max_features_per_tile: None,
+                avg_features_per_tile: None,
+                top_keys: Vec::new(),
+                geometry_bytes: None,
+                attribute_bytes: None,
+                approx_bytes_field_length: None,
+                approx_bytes_field_length_by_zoom: BTreeMap::new(),
+                pct_of_total: None,
             },
             FileLayerSummary {
                 name: "roads".to_string(),
@@ -97,6 +137,17 @@ fn inspect_collects_file_layer_list() {
                 feature_count: 1,
                 property_key_count: 2,
                 property_value_count: 2,
+                invalid_geometry_count: None,
+                tiles_with_layer: None,
+                min_features_per_tile: None,
+                max_features_per_tile: None,
+                avg_features_per_tile: None,
+                top_keys: Vec::new(),
+                geometry_bytes: None,
+                attribute_bytes: None,
+                approx_bytes_field_length: None,
+                approx_bytes_field_length_by_zoom: BTreeMap::new(),
+                pct_of_total: None,
             },
         ]
     );
@@ -110,18 +161,40 @@ fn inspect_filters_file_layer_list() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 0,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: None,
         bucket: None,
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: vec!["roads".to_string()],
         recommend: false,
         include_layer_list: true,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -133,6 +206,698 @@ fn inspect_filters_file_layer_list() {
             feature_count: 1,
             property_key_count: 2,
             property_value_count: 2,
+            invalid_geometry_count: None,
+            tiles_with_layer: None,
+            min_features_per_tile: None,
+            max_features_per_tile: None,
+            avg_features_per_tile: None,
+            top_keys: Vec::new(),
+            geometry_bytes: None,
+            attribute_bytes: None,
+            approx_bytes_field_length: None,
+            approx_bytes_field_length_by_zoom: BTreeMap::new(),
+            pct_of_total: None,
         }]
     );
 }
+
+fn polygon_geom(points: &[(f32, f32)]) -> mvt::GeomData {
+    let mut encoder = GeomEncoder::new(GeomType::Polygon);
+    for (x, y) in points {
+        encoder.add_point(*x, *y).expect("point");
+    }
+    encoder.encode().expect("encode")
+}
+
+#[test]
+fn inspect_check_geometry_counts_invalid_polygons() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("water");
+    let square = polygon_geom(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+    let layer = layer.into_feature(square).into_layer();
+    let bowtie = polygon_geom(&[(0.0, 0.0), (10.0, 10.0), (10.0, 0.0), (0.0, 10.0)]);
+    let layer = layer.into_feature(bowtie).into_layer();
+    tile.add_layer(layer).expect("add water");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 0, 0, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("tile insert");
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        check_geometry: true,
+        attributes_size: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        strict: false,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    let invalid = report
+        .file_layers
+        .iter()
+        .map(|layer| (layer.name.as_str(), layer.invalid_geometry_count))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        invalid,
+        vec![
+            ("buildings", Some(0)),
+            ("roads", Some(0)),
+            ("water", Some(1)),
+        ]
+    );
+}
+
+#[test]
+fn inspect_attributes_size_splits_layer_bytes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("mbtiles->pmtiles");
+
+    let sizes = |layers: &[FileLayerSummary]| {
+        layers
+            .iter()
+            .map(|layer| {
+                (
+                    layer.name.clone(),
+                    layer.geometry_bytes,
+                    layer.attribute_bytes,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        attributes_size: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    let expected = sizes(&report.file_layers);
+    for (name, geometry, attributes) in &expected {
+        assert!(geometry.expect("geometry bytes") > 0, "{name}");
+        assert!(attributes.expect("attribute bytes") > 0, "{name}");
+    }
+    // Two string properties on roads, one on buildings
+    assert!(expected[1].2 > expected[0].2, "{expected:?}");
+
+    let sampled = InspectOptions {
+        sample: Some(SampleSpec::Count(1)),
+        ..options.clone()
+    };
+    let report = inspect_mbtiles_with_options(&path, sampled).expect("inspect sampled");
+    assert_eq!(sizes(&report.file_layers), expected);
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+    assert_eq!(sizes(&report.file_layers), expected);
+
+    let report = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            attributes_size: false,
+            ..options
+        },
+    )
+    .expect("inspect");
+    assert!(
+        report
+            .file_layers
+            .iter()
+            .all(|layer| layer.attribute_bytes.is_none())
+    );
+}
+
+#[test]
+fn inspect_layer_bytes_sums_layer_field_lengths() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("mbtiles->pmtiles");
+
+    let bytes = |layers: &[FileLayerSummary]| {
+        layers
+            .iter()
+            .map(|layer| {
+                (
+                    layer.name.clone(),
+                    layer.approx_bytes_field_length,
+                    layer.approx_bytes_field_length_by_zoom.clone(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        layer_bytes: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    // create_layer_tile writes roads, then buildings
+    let sizes = layer_encoded_sizes(&create_layer_tile()).expect("layer sizes");
+    assert_eq!(
+        bytes(&report.file_layers),
+        vec![
+            (
+                "buildings".to_string(),
+                Some(sizes[1]),
+                BTreeMap::from([(0, sizes[1])])
+            ),
+            (
+                "roads".to_string(),
+                Some(sizes[0]),
+                BTreeMap::from([(0, sizes[0])])
+            ),
+        ]
+    );
+    let pct: f64 = report
+        .file_layers
+        .iter()
+        .map(|layer| layer.pct_of_total.expect("pct"))
+        .sum();
+    assert!((pct - 100.0).abs() < 1e-9, "{pct}");
+
+    let sampled = InspectOptions {
+        sample: Some(SampleSpec::Count(1)),
+        ..options.clone()
+    };
+    let expected = bytes(&report.file_layers);
+    let report = inspect_mbtiles_with_options(&path, sampled).expect("inspect sampled");
+    assert_eq!(bytes(&report.file_layers), expected);
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+    assert_eq!(bytes(&report.file_layers), expected);
+}
+
+#[test]
+fn inspect_builds_layer_zoom_matrix() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    for x in 0..2 {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (2, ?1, 0, ?2)",
+            (x, create_layer_tile()),
+        )
+        .expect("tile insert");
+    }
+    drop(conn);
+
+    let options = InspectOptions {
+        no_progress: true,
+        layer_zoom_matrix: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(
+        report.layer_zoom_matrix,
+        vec![
+            ("buildings".to_string(), vec![(0, 1), (2, 2)]),
+            ("roads".to_string(), vec![(0, 1), (2, 2)]),
+        ]
+    );
+
+    let options = InspectOptions {
+        no_progress: true,
+        layer_zoom_matrix: true,
+        layers: vec!["roads".to_string()],
+        zoom: Some(2),
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(
+        report.layer_zoom_matrix,
+        vec![("roads".to_string(), vec![(2, 2)])]
+    );
+}
+
+#[test]
+fn inspect_previews_style_layer_coverage() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    for x in 0..2 {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (2, ?1, 0, ?2)",
+            (x, create_layer_tile()),
+        )
+        .expect("tile insert");
+    }
+    drop(conn);
+    let style = dir.path().join("style.json");
+    std::fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","minzoom":2,"paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        layer_bytes: true,
+        layer_zoom_matrix: true,
+        style: Some(style),
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    let preview = report.style_preview.expect("style preview");
+    let sizes = layer_encoded_sizes(&create_layer_tile()).expect("layer sizes");
+    let layers = preview
+        .layers
+        .iter()
+        .map(|layer| {
+            (
+                layer.name.as_str(),
+                layer.status,
+                layer.kept_zooms.clone(),
+                layer.dropped_zooms.clone(),
+                layer.removed_features,
+                layer.removed_bytes,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        layers,
+        vec![
+            (
+                "buildings",
+                StyleLayerStatus::Dropped,
+                vec![],
+                vec![0, 2],
+                Some(3),
+                Some(3 * sizes[1])
+            ),
+            (
+                "roads",
+                StyleLayerStatus::Partial,
+                vec![2],
+                vec![0],
+                Some(1),
+                Some(sizes[0])
+            ),
+        ]
+    );
+    assert_eq!(preview.total_features, 6);
+    assert_eq!(preview.removed_features, Some(4));
+    assert_eq!(preview.total_bytes, Some(3 * (sizes[0] + sizes[1])));
+    assert_eq!(preview.removed_bytes, Some(3 * sizes[1] + sizes[0]));
+
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("mbtiles->pmtiles");
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+    assert_eq!(report.style_preview, Some(preview));
+}
+
+#[test]
+fn inspect_builds_property_histogram() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    for x in 0..2 {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, ?1, 0, ?2)",
+            (x, create_layer_tile()),
+        )
+        .expect("tile insert");
+    }
+    drop(conn);
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        property_histogram: Some("class".to_string()),
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    assert_eq!(report.property_histograms.len(), 1);
+    let histogram = &report.property_histograms[0];
+    assert_eq!(
+        (histogram.layer.as_str(), histogram.key.as_str()),
+        ("roads", "class")
+    );
+    assert_eq!(
+        histogram.values,
+        vec![PropertyValueCount {
+            value: "primary".to_string(),
+            count: 3,
+        }]
+    );
+    assert!(!histogram.truncated);
+
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("convert");
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+    assert_eq!(report.property_histograms.len(), 1);
+    assert_eq!(report.property_histograms[0].values[0].count, 3);
+}
+
+/// Two zoom 1 tiles of 150 `poi` points each; `ref` is unique per feature,
+/// `class` alternates between two values.
+fn create_unique_ref_mbtiles(path: &Path) {
+    create_layer_mbtiles(path);
+    let conn = rusqlite::Connection::open(path).expect("open");
+    for x in 0..2u32 {
+        let mut tile = Tile::new(4096);
+        let mut layer = tile.create_layer("poi");
+        for idx in 0..150u32 {
+            let geom = GeomEncoder::new(GeomType::Point)
+                .point(f64::from(idx), 1.0)
+                .expect("point")
+                .encode()
+                .expect("encode");
+            let mut feature = layer.into_feature(geom);
+            feature.add_tag_string("ref", &format!("osm-{x}-{idx:05}"));
+            feature.add_tag_string("class", if idx % 2 == 0 { "shop" } else { "cafe" });
+            layer = feature.into_layer();
+        }
+        tile.add_layer(layer).expect("add poi");
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, ?1, 0, ?2)",
+            (x, tile.to_bytes().expect("tile bytes")),
+        )
+        .expect("tile insert");
+    }
+}
+
+#[test]
+fn inspect_detects_high_cardinality_keys() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_unique_ref_mbtiles(&path);
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        cardinality: Some(CardinalityOptions::default()),
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    assert_eq!(report.high_cardinality_keys.len(), 1);
+    let key = &report.high_cardinality_keys[0];
+    assert_eq!((key.layer.as_str(), key.key.as_str()), ("poi", "ref"));
+    assert_eq!(key.feature_count, 300);
+    assert_eq!(key.distinct_values, 300);
+    assert!(!key.estimated);
+    assert_eq!(key.distinct_ratio, 1.0);
+    // "osm-0-00000": 11 bytes string, 1 + 1 + 11 inside a 1 + 1 byte
+    // value entry, plus 2 bytes of tags per feature.
+    assert_eq!(key.estimated_bytes, 300 * (2 + 13 + 2));
+
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("convert");
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+    assert_eq!(report.high_cardinality_keys, vec![key.clone()]);
+
+    // A small cap estimates the distinct count instead.
+    let capped = InspectOptions {
+        cardinality: Some(CardinalityOptions {
+            max_tracked_values: 128,
+            min_distinct_ratio: 0.5,
+            ..CardinalityOptions::default()
+        }),
+        ..options.clone()
+    };
+    let report = inspect_mbtiles_with_options(&path, capped).expect("inspect capped");
+    assert_eq!(report.high_cardinality_keys.len(), 1);
+    assert!(report.high_cardinality_keys[0].estimated);
+    let estimate = report.high_cardinality_keys[0].distinct_values;
+    assert!((200..=300).contains(&estimate), "{estimate}");
+
+    let without = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            cardinality: None,
+            ..options
+        },
+    )
+    .expect("inspect without");
+    assert!(without.high_cardinality_keys.is_empty());
+}
+
+#[test]
+fn distinct_value_sketch_estimates_beyond_capacity() {
+    let mut sketch = DistinctValueSketch::new(1024);
+    let mut other = DistinctValueSketch::new(1024);
+    for idx in 0..50_000 {
+        let value = format!("value-{idx}");
+        if idx % 2 == 0 {
+            sketch.insert(&value);
+        } else {
+            other.insert(&value);
+        }
+        // Repeats do not count twice.
+        sketch.insert("value-0");
+    }
+    sketch.merge(other);
+    assert!(sketch.is_estimated());
+    let estimate = sketch.estimate() as f64;
+    assert!((estimate / 50_000.0 - 1.0).abs() < 0.1, "{estimate}");
+
+    let mut exact = DistinctValueSketch::new(1024);
+    for idx in 0..500 {
+        exact.insert(&format!("value-{}", idx % 100));
+    }
+    assert!(!exact.is_estimated());
+    assert_eq!(exact.estimate(), 100);
+}
+
+#[test]
+fn inspect_checks_gzipped_metadata_vector_layers() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let json = r#"{"vector_layers":[
+        {"id":"roads","description":"Road lines","minzoom":0,"maxzoom":14,
+         "fields":{"class":"String","name":"String"}},
+        {"id":"water","minzoom":0,"maxzoom":14,"fields":{}},
+        {"id":"labels","minzoom":10,"maxzoom":14,"fields":{}}
+    ]}"#;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, json.as_bytes()).expect("gzip");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute(
+        "INSERT INTO metadata (name, value) VALUES ('json', ?1)",
+        (encoder.finish().expect("gzip"),),
+    )
+    .expect("metadata insert");
+    drop(conn);
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        zoom: Some(0),
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    let ids = report
+        .vector_layers
+        .iter()
+        .map(|layer| layer.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["roads", "water", "labels"]);
+    assert_eq!(
+        report.vector_layers[0].description.as_deref(),
+        Some("Road lines")
+    );
+    assert_eq!(report.vector_layers[0].fields.len(), 2);
+    // labels starts at z10, so it is not expected in a z0 scan.
+    assert_eq!(
+        report.metadata_mismatches,
+        vec![
+            MetadataMismatch {
+                layer: "water".to_string(),
+                kind: MetadataMismatchKind::DeclaredNotInTiles,
+            },
+            MetadataMismatch {
+                layer: "buildings".to_string(),
+                kind: MetadataMismatchKind::InTilesNotDeclared,
+            },
+        ]
+    );
+
+    // Without a layer list the declaration is reported but not checked.
+    let report = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            include_layer_list: false,
+            ..options.clone()
+        },
+    )
+    .expect("inspect without layer list");
+    assert_eq!(report.vector_layers.len(), 3);
+    assert!(report.metadata_mismatches.is_empty());
+
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("convert");
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+    assert_eq!(report.vector_layers.len(), 3);
+    assert_eq!(report.metadata_mismatches.len(), 2);
+}
+
+#[test]
+fn top_value_counter_keeps_most_frequent_values() {
+    let mut counter = TopValueCounter::new();
+    for idx in 0..(PROPERTY_HISTOGRAM_TOP * 10) {
+        counter.add(format!("rare-{idx}"), 1);
+    }
+    let mut other = TopValueCounter::new();
+    other.add("common".to_string(), 50);
+    other.add("frequent".to_string(), 20);
+    counter.merge(other);
+
+    let histogram = counter.into_histogram("roads".to_string(), "class".to_string());
+    assert!(histogram.truncated);
+    assert_eq!(histogram.values.len(), PROPERTY_HISTOGRAM_TOP);
+    assert_eq!(histogram.values[0].value, "common");
+    assert_eq!(histogram.values[0].count, 50);
+    assert_eq!(histogram.values[1].value, "frequent");
+}
+
+fn create_roads_tile(features: usize) -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let mut layer = tile.create_layer("roads");
+    for idx in 0..features {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(idx as f64, 1.0)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let mut feature = layer.into_feature(geom);
+        feature.add_tag_string("name", &format!("road {idx}"));
+        if idx == 0 {
+            feature.add_tag_string("class", "minor");
+        }
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer).expect("add roads");
+    tile.to_bytes().expect("tile bytes")
+}
+
+#[test]
+fn inspect_zoom_layer_list_reports_per_tile_stats() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 0, 0, ?1)",
+        (create_layer_tile(),),
+    )
+    .expect("tile insert");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 1, 0, ?1)",
+        (create_roads_tile(3),),
+    )
+    .expect("tile insert");
+    drop(conn);
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("convert");
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        zoom: Some(1),
+        ..InspectOptions::default()
+    };
+    let mbtiles_report = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    let pmtiles_report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect");
+    for layers in [&mbtiles_report.file_layers, &pmtiles_report.file_layers] {
+        let roads = layers.iter().find(|l| l.name == "roads").expect("roads");
+        assert_eq!(roads.feature_count, 4);
+        assert_eq!(roads.tiles_with_layer, Some(2));
+        assert_eq!(roads.min_features_per_tile, Some(1));
+        assert_eq!(roads.max_features_per_tile, Some(3));
+        assert_eq!(roads.avg_features_per_tile, Some(2.0));
+        assert_eq!(
+            roads.top_keys,
+            vec![("name".to_string(), 4), ("class".to_string(), 2)]
+        );
+        let buildings = layers
+            .iter()
+            .find(|l| l.name == "buildings")
+            .expect("buildings");
+        assert_eq!(buildings.tiles_with_layer, Some(1));
+        assert_eq!(buildings.top_keys, vec![("height".to_string(), 1)]);
+    }
+
+    let report = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            zoom: None,
+            ..options
+        },
+    )
+    .expect("inspect");
+    assert!(
+        report
+            .file_layers
+            .iter()
+            .all(|l| l.tiles_with_layer.is_none())
+    );
+    let json = serde_json::to_value(&report.file_layers[0]).expect("json");
+    assert!(json.get("top_keys").is_none());
+}
+
+#[test]
+fn inspect_layers_only_skips_size_scan() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&path);
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&path, &pmtiles).expect("convert");
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        histogram_buckets: 10,
+        topn: 5,
+        ..InspectOptions::default()
+    };
+    let full = inspect_mbtiles_with_options(&path, options.clone()).expect("inspect");
+    let layers_only = InspectOptions {
+        layers_only: true,
+        ..options
+    };
+    for report in [
+        inspect_mbtiles_with_options(&path, layers_only.clone()).expect("inspect"),
+        inspect_pmtiles_with_options(&pmtiles, &layers_only).expect("inspect pmtiles"),
+    ] {
+        let mut layers = report.file_layers.clone();
+        layers.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut expected = full.file_layers.clone();
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(layers, expected);
+        assert_eq!(report.overall.tile_count, 0);
+        assert!(report.by_zoom.is_empty());
+        assert!(report.histogram.is_empty());
+        assert!(report.top_tiles.is_empty());
+
+        let json = serde_json::to_value(&report).expect("serialize");
+        assert_eq!(json["histogram"], serde_json::json!([]));
+        assert_eq!(json["by_zoom"], serde_json::json!([]));
+        assert_eq!(json["overall"]["avg_bytes"], 0);
+        assert_eq!(json["file_layers"].as_array().map(Vec::len), Some(2));
+    }
+}
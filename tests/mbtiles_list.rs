@@ -1,7 +1,10 @@
 use std::path::Path;
 
+use std::sync::Mutex;
+
 use vt_optimizer::mbtiles::{
-    InspectOptions, TileListOptions, TileSort, inspect_mbtiles_with_options,
+    InspectOptions, RecommendThresholds, TileListOptions, TileRecord, TileScheme, TileSizeRange,
+    TileSort, inspect_mbtiles_streaming, inspect_mbtiles_with_options, parse_tile_url_template,
 };
 
 fn create_list_mbtiles(path: &Path) {
@@ -48,13 +51,14 @@ fn list_tiles_sorted_by_size_with_limit() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 2,
         no_progress: true,
         max_tile_bytes: 100,
         zoom: Some(2),
         bucket: Some(0),
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
@@ -62,7 +66,29 @@ fn list_tiles_sorted_by_size_with_limit() {
         list_tiles: Some(TileListOptions {
             limit: 2,
             sort: TileSort::Size,
+            size_range: None,
         }),
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -79,13 +105,14 @@ fn list_tiles_sorted_by_zxy() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 2,
         no_progress: true,
         max_tile_bytes: 100,
         zoom: Some(2),
         bucket: Some(1),
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
@@ -93,7 +120,29 @@ fn list_tiles_sorted_by_zxy() {
         list_tiles: Some(TileListOptions {
             limit: 10,
             sort: TileSort::Zxy,
+            size_range: None,
         }),
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -101,3 +150,157 @@ fn list_tiles_sorted_by_zxy() {
     assert_eq!(report.bucket_tiles[0].x, 0);
     assert_eq!(report.bucket_tiles[0].y, 0);
 }
+
+#[test]
+fn list_tiles_streaming_emits_every_tile_without_bucket() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_list_mbtiles(&path);
+
+    let options = InspectOptions {
+        no_progress: true,
+        max_tile_bytes: 50,
+        list_tiles: Some(TileListOptions {
+            limit: 0,
+            sort: TileSort::Size,
+            size_range: None,
+        }),
+        ..InspectOptions::default()
+    };
+    let records = Mutex::new(Vec::new());
+    let sink = |record: TileRecord| {
+        records.lock().unwrap().push(record);
+        Ok(())
+    };
+    let report = inspect_mbtiles_streaming(&path, options, &sink).expect("inspect");
+
+    let mut records = records.into_inner().unwrap();
+    records.sort_by_key(|record| record.bytes);
+    assert_eq!(
+        records
+            .iter()
+            .map(|record| (record.x, record.bytes, record.over_limit))
+            .collect::<Vec<_>>(),
+        vec![(2, 10, false), (1, 50, false), (0, 100, true)]
+    );
+    assert!(report.bucket_tiles.is_empty());
+    assert_eq!(report.overall.tile_count, 3);
+}
+
+#[test]
+fn list_tiles_streaming_respects_bucket_and_limit() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_list_mbtiles(&path);
+
+    let options = InspectOptions {
+        histogram_buckets: 2,
+        no_progress: true,
+        zoom: Some(2),
+        bucket: Some(0),
+        list_tiles: Some(TileListOptions {
+            limit: 1,
+            sort: TileSort::Size,
+            size_range: None,
+        }),
+        ..InspectOptions::default()
+    };
+    let records = Mutex::new(Vec::new());
+    let sink = |record: TileRecord| {
+        records.lock().unwrap().push(record);
+        Ok(())
+    };
+    inspect_mbtiles_streaming(&path, options, &sink).expect("inspect");
+
+    let records = records.into_inner().unwrap();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].bytes <= 50);
+}
+
+#[test]
+fn list_tiles_by_size_range_without_histogram() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_list_mbtiles(&path);
+
+    let options = |min_bytes, max_bytes| InspectOptions {
+        no_progress: true,
+        list_tiles: Some(TileListOptions {
+            limit: 10,
+            sort: TileSort::Zxy,
+            size_range: Some(TileSizeRange::new(min_bytes, max_bytes).expect("range")),
+        }),
+        ..InspectOptions::default()
+    };
+    let listed = |options| {
+        let mut tiles = inspect_mbtiles_with_options(&path, options)
+            .expect("inspect")
+            .bucket_tiles
+            .iter()
+            .map(|tile| (tile.x, tile.bytes))
+            .collect::<Vec<_>>();
+        tiles.sort_unstable();
+        tiles
+    };
+    assert_eq!(listed(options(Some(20), Some(60))), vec![(1, 50)]);
+    assert_eq!(listed(options(Some(50), None)), vec![(0, 100), (1, 50)]);
+    assert_eq!(listed(options(None, Some(10))), vec![(2, 10)]);
+    assert!(TileSizeRange::new(Some(60), Some(20)).is_err());
+
+    let records = Mutex::new(Vec::new());
+    let sink = |record: TileRecord| {
+        records.lock().unwrap().push(record.bytes);
+        Ok(())
+    };
+    inspect_mbtiles_streaming(&path, options(Some(11), Some(100)), &sink).expect("inspect");
+    let mut records = records.into_inner().unwrap();
+    records.sort_unstable();
+    assert_eq!(records, vec![50, 100]);
+}
+
+#[test]
+fn tile_url_template_fills_top_and_bucket_tile_urls() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_list_mbtiles(&path);
+
+    let options = InspectOptions {
+        topn: 1,
+        histogram_buckets: 2,
+        no_progress: true,
+        zoom: Some(2),
+        bucket: Some(1),
+        list_tiles: Some(TileListOptions {
+            limit: 10,
+            sort: TileSort::Size,
+            size_range: None,
+        }),
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    let template = parse_tile_url_template("https://tiles.example.com/{z}/{x}/{y}.pbf?tms={-y}")
+        .expect("template");
+    // The fixture tile is stored at TMS row 0, which is XYZ row 3 at zoom 2
+    let report = vt_optimizer::output::apply_tile_url_template(report, &template, TileScheme::Tms);
+
+    assert_eq!(
+        report.top_tiles[0].url.as_deref(),
+        Some("https://tiles.example.com/2/0/3.pbf?tms=0")
+    );
+    assert_eq!(
+        report.bucket_tiles[0].url.as_deref(),
+        Some("https://tiles.example.com/2/0/3.pbf?tms=0")
+    );
+    let lines = vt_optimizer::output::format_top_tiles_lines(&report.top_tiles);
+    assert!(lines[0].ends_with(" https://tiles.example.com/2/0/3.pbf?tms=0"));
+    let json = serde_json::to_value(&report.top_tiles[0]).expect("json");
+    assert_eq!(json["url"], "https://tiles.example.com/2/0/3.pbf?tms=0");
+}
+
+#[test]
+fn tile_url_template_rejects_unknown_placeholders() {
+    let template = parse_tile_url_template("maplibre://tiles/{z}/{x}/{-y}").expect("template");
+    assert_eq!(template.expand(1, 1, 0), "maplibre://tiles/1/1/1");
+    assert!(parse_tile_url_template("https://example.com/{z}/{x}/{row}").is_err());
+    assert!(parse_tile_url_template("https://example.com/{z}/{x}/{y").is_err());
+}
@@ -2,7 +2,12 @@ use std::path::Path;
 
 use mvt::{GeomEncoder, GeomType, Tile};
 
-use vt_optimizer::mbtiles::{InspectOptions, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{
+    InspectOptions, LayerSummary, MbtilesStats, MbtilesZoomStats, RecommendReason,
+    RecommendThresholds, RecommendedAction, TileSummary, build_recommendations,
+    build_recommendations_with_thresholds, decode_tile_payload, inspect_mbtiles_with_options,
+    layer_encoded_sizes,
+};
 
 fn create_tile_with_points(count: usize) -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -63,18 +68,40 @@ fn inspect_recommend_selects_over_limit_bucket_and_summaries() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 2,
         histogram_buckets: 2,
         no_progress: true,
         max_tile_bytes: 50,
         zoom: Some(0),
         bucket: None,
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: true,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -85,3 +112,279 @@ fn inspect_recommend_selects_over_limit_bucket_and_summaries() {
     assert_eq!(report.top_tile_summaries[0].x, report.top_tiles[0].x);
     assert_eq!(report.top_tile_summaries[0].y, report.top_tiles[0].y);
 }
+
+/// `buildings`: polygons with many vertices; `pois`: a couple of points.
+fn create_tile_dominated_by(buildings: usize, pois: usize) -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let mut layer = tile.create_layer("buildings");
+    for i in 0..buildings {
+        let mut encoder = GeomEncoder::new(GeomType::Polygon);
+        for step in 0..32 {
+            let angle = step as f64 / 32.0 * std::f64::consts::TAU;
+            let cx = 200.0 + (i * 40) as f64;
+            encoder
+                .add_point(cx + 15.0 * angle.cos(), 200.0 + 15.0 * angle.sin())
+                .expect("point");
+        }
+        layer = layer
+            .into_feature(encoder.encode().expect("encode"))
+            .into_layer();
+    }
+    tile.add_layer(layer).expect("add buildings");
+    let mut layer = tile.create_layer("pois");
+    for i in 0..pois {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(i as f64 * 10.0, 5.0)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        layer = layer.into_feature(geom).into_layer();
+    }
+    tile.add_layer(layer).expect("add pois");
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn create_mbtiles_with_tiles(path: &Path, tiles: &[(u8, u32, u32, Vec<u8>)]) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    for (zoom, x, y, data) in tiles {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            (zoom, x, y, data),
+        )
+        .expect("tile");
+    }
+}
+
+fn recommend_options(zoom: u8) -> InspectOptions {
+    InspectOptions {
+        topn: 2,
+        histogram_buckets: 2,
+        no_progress: true,
+        max_tile_bytes: 50,
+        zoom: Some(zoom),
+        recommend: true,
+        ..InspectOptions::default()
+    }
+}
+
+#[test]
+fn layer_encoded_sizes_cover_every_layer() {
+    let tile = create_tile_dominated_by(3, 2);
+    let sizes = layer_encoded_sizes(&decode_tile_payload(&tile).expect("payload")).expect("sizes");
+    assert_eq!(sizes.len(), 2);
+    assert!(sizes[0] > sizes[1]);
+    // Each layer adds a one-byte key and a varint length prefix.
+    let overhead: u64 = sizes
+        .iter()
+        .map(|size| if *size < 128 { 2 } else { 3 })
+        .sum();
+    assert_eq!(sizes.iter().sum::<u64>() + overhead, tile.len() as u64);
+}
+
+#[test]
+fn recommend_suggests_simplify_for_dominant_polygon_layer() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_mbtiles_with_tiles(
+        &path,
+        &[
+            (14, 0, 0, create_tile_dominated_by(6, 1)),
+            (14, 1, 0, create_tile_dominated_by(4, 1)),
+        ],
+    );
+
+    let report = inspect_mbtiles_with_options(&path, recommend_options(14)).expect("inspect");
+    assert_eq!(report.recommendations.len(), 1);
+    let recommendation = &report.recommendations[0];
+    assert_eq!(recommendation.zoom, 14);
+    assert_eq!(recommendation.layer, "buildings");
+    assert!(recommendation.bytes_share > 0.9);
+    assert!(matches!(
+        recommendation.action,
+        RecommendedAction::Simplify { tolerance } if tolerance == 1.0
+    ));
+    assert!(recommendation.estimated_savings_bytes > 0);
+    assert!(recommendation.estimated_savings_bytes < report.overall.total_bytes);
+}
+
+#[test]
+fn recommend_suggests_minzoom_for_dominant_point_layer() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_mbtiles_with_tiles(&path, &[(5, 0, 0, create_tile_dominated_by(0, 60))]);
+
+    let report = inspect_mbtiles_with_options(&path, recommend_options(5)).expect("inspect");
+    let recommendation = report
+        .recommendations
+        .iter()
+        .find(|item| item.layer == "pois")
+        .expect("pois recommendation");
+    assert_eq!(
+        recommendation.action,
+        RecommendedAction::RaiseMinzoom { minzoom: 6 }
+    );
+    assert!(recommendation.bytes_share > 0.9);
+    assert_eq!(recommendation.vertices_share, 1.0);
+}
+
+fn layer(name: &str, bytes: u64, vertex_count: u64, feature_count: usize) -> LayerSummary {
+    LayerSummary {
+        name: name.to_string(),
+        bytes,
+        feature_count,
+        vertex_count,
+        property_key_count: 0,
+        property_value_count: 0,
+        property_keys: Vec::new(),
+        invalid_geometry_count: None,
+        duplicate_feature_count: 0,
+    }
+}
+
+#[test]
+fn build_recommendations_ranks_layers_and_scales_to_zoom_bytes() {
+    let summary = |x: u32, layers: Vec<LayerSummary>| TileSummary {
+        zoom: 12,
+        x,
+        y: 0,
+        tile_bytes: 1_000,
+        layer_count: layers.len(),
+        total_features: 0,
+        vertex_count: 0,
+        property_key_count: 0,
+        property_value_count: 0,
+        layers,
+    };
+    let summaries = vec![
+        summary(
+            0,
+            vec![
+                layer("roads", 300, 3_000, 10),
+                layer("landuse", 500, 40_000, 100),
+                layer("labels", 50, 20, 20),
+            ],
+        ),
+        summary(
+            1,
+            vec![layer("roads", 100, 1_000, 10), layer("landuse", 50, 5, 1)],
+        ),
+    ];
+    let by_zoom = vec![MbtilesZoomStats {
+        zoom: 12,
+        stats: MbtilesStats {
+            tile_count: 10,
+            total_bytes: 10_000,
+            max_bytes: 1_000,
+            avg_bytes: 1_000,
+            percentiles: None,
+        },
+    }];
+
+    let recommendations = build_recommendations(&by_zoom, &summaries);
+    assert_eq!(
+        recommendations
+            .iter()
+            .map(|item| item.layer.as_str())
+            .collect::<Vec<_>>(),
+        vec!["landuse", "roads"]
+    );
+    // landuse: 550 of 1000 layer bytes, 400 vertices per feature.
+    assert_eq!(
+        recommendations[0].action,
+        RecommendedAction::Simplify { tolerance: 2.0 }
+    );
+    assert_eq!(recommendations[0].estimated_savings_bytes, 2_200);
+    // roads: 400 of 1000 layer bytes, 200 vertices per feature.
+    assert_eq!(
+        recommendations[1].action,
+        RecommendedAction::Simplify { tolerance: 2.0 }
+    );
+    assert_eq!(recommendations[1].estimated_savings_bytes, 1_600);
+}
+
+#[test]
+fn build_recommendations_applies_layer_thresholds_and_flags_most_vertices() {
+    let summary = |x: u32, layers: Vec<LayerSummary>| TileSummary {
+        zoom: 8,
+        x,
+        y: 0,
+        tile_bytes: 2_000,
+        layer_count: layers.len(),
+        total_features: 0,
+        vertex_count: 0,
+        property_key_count: 0,
+        property_value_count: 0,
+        layers,
+    };
+    let summaries = vec![
+        summary(
+            0,
+            vec![
+                layer("water", 1_600, 200, 20),
+                layer("pois", 150, 300, 300),
+                layer("boundaries", 250, 4_000, 10),
+            ],
+        ),
+        summary(
+            1,
+            vec![
+                layer("water", 1_600, 200, 20),
+                layer("pois", 150, 300, 300),
+                layer("boundaries", 250, 4_000, 10),
+            ],
+        ),
+    ];
+
+    // Defaults: only the byte share threshold, plus the vertex-heavy layer.
+    let recommendations = build_recommendations(&[], &summaries);
+    assert_eq!(
+        recommendations
+            .iter()
+            .map(|item| (item.layer.as_str(), item.reason))
+            .collect::<Vec<_>>(),
+        vec![
+            ("water", RecommendReason::BytesShare),
+            ("boundaries", RecommendReason::MostVertices),
+        ]
+    );
+    assert_eq!(
+        recommendations[1].action,
+        RecommendedAction::Simplify { tolerance: 2.0 }
+    );
+    assert_eq!(recommendations[1].avg_bytes, 250);
+
+    let thresholds = RecommendThresholds {
+        max_layer_bytes: Some(200),
+        max_layer_features: Some(100),
+        ..RecommendThresholds::default()
+    };
+    let recommendations = build_recommendations_with_thresholds(&[], &summaries, &thresholds);
+    assert_eq!(
+        recommendations
+            .iter()
+            .map(|item| (item.layer.as_str(), item.reason))
+            .collect::<Vec<_>>(),
+        vec![
+            ("water", RecommendReason::BytesShare),
+            ("boundaries", RecommendReason::LayerBytes),
+            ("pois", RecommendReason::FeatureCount),
+        ]
+    );
+    assert_eq!(recommendations[2].avg_features, 300);
+    assert_eq!(
+        recommendations[2].action,
+        RecommendedAction::RaiseMinzoom { minzoom: 9 }
+    );
+}
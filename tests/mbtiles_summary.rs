@@ -4,7 +4,10 @@ use std::path::Path;
 use flate2::{Compression, write::GzEncoder};
 use mvt::{GeomEncoder, GeomType, Tile};
 
-use vt_optimizer::mbtiles::{InspectOptions, TileCoord, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{
+    InspectOptions, RecommendThresholds, TileCoord, inspect_mbtiles_with_options, parse_tile_list,
+    parse_tile_range, parse_tile_spec, summarize_mbtiles_tiles,
+};
 
 fn create_vector_tile() -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -74,22 +77,44 @@ fn inspect_tile_summary_reports_layer_counts() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 0,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: None,
         bucket: None,
-        tile: Some(TileCoord {
+        tiles: vec![TileCoord {
             zoom: 3,
             x: 4,
             y: 5,
-        }),
+        }],
         summary: true,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -132,22 +157,44 @@ fn inspect_tile_summary_decodes_gzip_tiles() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 0,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: None,
         bucket: None,
-        tile: Some(TileCoord {
+        tiles: vec![TileCoord {
             zoom: 3,
             x: 4,
             y: 5,
-        }),
+        }],
         summary: true,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -156,6 +203,39 @@ fn inspect_tile_summary_decodes_gzip_tiles() {
     assert_eq!(summary.layers[1].name, "buildings");
 }
 
+#[test]
+fn inspect_measure_uncompressed_reports_decoded_sizes() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    let data = create_vector_tile();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&data).expect("gzip write");
+    let compressed = encoder.finish().expect("gzip finish");
+    create_summary_mbtiles(&path, compressed.clone());
+
+    let options = InspectOptions {
+        no_progress: true,
+        histogram_buckets: 2,
+        topn: 1,
+        uncompressed_sizes: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(report.overall.total_bytes, data.len() as u64);
+    assert_eq!(report.stored_bytes, Some(compressed.len() as u64));
+    assert_eq!(report.top_tiles[0].bytes, data.len() as u64);
+    let histogram_bytes: u64 = report.histogram.iter().map(|b| b.total_bytes).sum();
+    assert_eq!(histogram_bytes, data.len() as u64);
+
+    let options = InspectOptions {
+        no_progress: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(report.overall.total_bytes, compressed.len() as u64);
+    assert_eq!(report.stored_bytes, None);
+}
+
 #[test]
 fn inspect_tile_summary_filters_layer() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -165,22 +245,44 @@ fn inspect_tile_summary_filters_layer() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 0,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: None,
         bucket: None,
-        tile: Some(TileCoord {
+        tiles: vec![TileCoord {
             zoom: 3,
             x: 4,
             y: 5,
-        }),
+        }],
         summary: true,
         layers: vec!["roads".to_string()],
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -200,3 +302,212 @@ fn inspect_tile_summary_filters_layer() {
         vec!["class".to_string(), "name".to_string()]
     );
 }
+
+#[test]
+fn summarize_mbtiles_tiles_visits_listed_tiles_in_order() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_summary_mbtiles(&path, create_vector_tile());
+
+    let coords = parse_tile_list("# problem tiles\n3/4/5\n\n3/0/0\n3/4/5\n").expect("parse");
+    assert_eq!(coords.len(), 3);
+    let mut summaries = Vec::new();
+    let count = summarize_mbtiles_tiles(&path, &coords, &["roads".to_string()], false, &mut |s| {
+        summaries.push(s);
+        Ok(())
+    })
+    .expect("summarize");
+
+    assert_eq!(count, 2);
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(
+        (summaries[0].zoom, summaries[0].x, summaries[0].y),
+        (3, 4, 5)
+    );
+    assert_eq!(summaries[0].layers.len(), 1);
+    assert_eq!(summaries[0].layers[0].name, "roads");
+}
+
+#[test]
+fn tile_summary_counts_duplicate_features() {
+    let mut tile = Tile::new(4096);
+    let mut layer = tile.create_layer("poi");
+    // The first two differ only in id and tag order; the others differ in
+    // geometry or a value.
+    for (id, point, tags) in [
+        (1, (1.0, 2.0), [("name", "Cafe"), ("class", "food")]),
+        (2, (1.0, 2.0), [("class", "food"), ("name", "Cafe")]),
+        (3, (1.0, 3.0), [("name", "Cafe"), ("class", "food")]),
+        (4, (1.0, 2.0), [("name", "Bar"), ("class", "food")]),
+    ] {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(point.0, point.1)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let mut feature = layer.into_feature(geom);
+        feature.set_id(id);
+        for (key, value) in tags {
+            feature.add_tag_string(key, value);
+        }
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer).expect("add poi layer");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_summary_mbtiles(&path, tile.to_bytes().expect("tile bytes"));
+
+    let mut summaries = Vec::new();
+    summarize_mbtiles_tiles(
+        &path,
+        &[parse_tile_spec("3/4/5").expect("tile")],
+        &[],
+        false,
+        &mut |s| {
+            summaries.push(s);
+            Ok(())
+        },
+    )
+    .expect("summarize");
+    assert_eq!(summaries[0].layers[0].feature_count, 4);
+    assert_eq!(summaries[0].layers[0].duplicate_feature_count, 1);
+
+    create_summary_mbtiles(&dir.path().join("plain.mbtiles"), create_vector_tile());
+    let mut summaries = Vec::new();
+    summarize_mbtiles_tiles(
+        &dir.path().join("plain.mbtiles"),
+        &[parse_tile_spec("3/4/5").expect("tile")],
+        &[],
+        false,
+        &mut |s| {
+            summaries.push(s);
+            Ok(())
+        },
+    )
+    .expect("summarize");
+    assert!(
+        summaries[0]
+            .layers
+            .iter()
+            .all(|layer| layer.duplicate_feature_count == 0)
+    );
+}
+
+#[test]
+fn parse_tile_list_reports_bad_line() {
+    let err = parse_tile_list("3/4/5\n3/x/5\n").expect_err("bad line");
+    assert!(format!("{err:#}").contains("line 2"));
+}
+
+#[test]
+fn parse_tile_range_expands_rows() {
+    let coords = parse_tile_range("14/8700-8701/5450-5452").expect("range");
+    assert_eq!(coords.len(), 6);
+    assert_eq!(
+        coords[0],
+        TileCoord {
+            zoom: 14,
+            x: 8700,
+            y: 5450
+        }
+    );
+    assert_eq!(
+        coords[1],
+        TileCoord {
+            zoom: 14,
+            x: 8701,
+            y: 5450
+        }
+    );
+    assert_eq!(parse_tile_range("3/4/5").expect("single").len(), 1);
+
+    let err = parse_tile_spec("3/8/0").expect_err("x out of range");
+    assert!(err.to_string().contains("out of range for zoom 3"), "{err}");
+    assert!(parse_tile_range("3/5-4/0").is_err());
+    assert!(parse_tile_range("3/0-8/0").is_err());
+    assert!(parse_tile_range("16/0-200/0-200").is_err());
+}
+
+#[test]
+fn inspect_summarizes_each_requested_tile() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_summary_mbtiles(&path, create_vector_tile());
+    rusqlite::Connection::open(&path)
+        .expect("open")
+        .execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, 5, 5, ?1)",
+            (create_vector_tile(),),
+        )
+        .expect("tile insert");
+
+    let options = InspectOptions {
+        no_progress: true,
+        tiles: parse_tile_range("3/4-6/5").expect("range"),
+        summary: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    // 3/6/5 is not stored and is skipped
+    let coords: Vec<_> = report
+        .tile_summaries
+        .iter()
+        .map(|summary| (summary.zoom, summary.x, summary.y))
+        .collect();
+    assert_eq!(coords, vec![(3, 4, 5), (3, 5, 5)]);
+    assert_eq!(report.tile_summary.expect("first").x, 4);
+}
+
+fn create_tile_with_unknown_command() -> Vec<u8> {
+    // type=POINT, packed geometry [Command(id=5, count=1), 2, 2]
+    let feature = [0x18, 0x01, 0x22, 0x03, 13, 2, 2];
+    let mut layer = vec![0x78, 0x02, 0x0a, 0x05];
+    layer.extend_from_slice(b"roads");
+    layer.extend_from_slice(&[0x12, feature.len() as u8]);
+    layer.extend_from_slice(&feature);
+    layer.extend_from_slice(&[0x28, 0x80, 0x20]);
+    let mut tile = vec![0x1a, layer.len() as u8];
+    tile.extend_from_slice(&layer);
+    tile
+}
+
+#[test]
+fn inspect_validate_mvt_lists_invalid_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_summary_mbtiles(&path, create_vector_tile());
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, 1, 2, ?1)",
+        (create_tile_with_unknown_command(),),
+    )
+    .expect("tile insert");
+    drop(conn);
+
+    let options = InspectOptions {
+        no_progress: true,
+        validate_mvt: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    let validation = report.mvt_validation.expect("validation");
+    assert_eq!(validation.invalid_tiles, 1);
+    assert_eq!(validation.errors.len(), 1);
+    let error = &validation.errors[0];
+    assert_eq!((error.zoom, error.x, error.y), (3, 1, 2));
+    assert!(
+        error.error.contains("unknown geometry command 5"),
+        "{}",
+        error.error
+    );
+
+    let report = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+    assert!(report.mvt_validation.is_none());
+}
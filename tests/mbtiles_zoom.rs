@@ -1,6 +1,9 @@
 use std::path::Path;
 
-use vt_optimizer::mbtiles::{InspectOptions, MbtilesStats, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{
+    InspectOptions, MbtilesStats, QuantileSketch, RecommendThresholds, SampleSpec, SizeQuantiles,
+    TilePercentiles, inspect_mbtiles_with_options,
+};
 
 fn create_zoom_sample_mbtiles(path: &Path) {
     let conn = rusqlite::Connection::open(path).expect("open");
@@ -52,18 +55,40 @@ fn inspect_zoom_limits_stats_and_histogram() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 2,
         no_progress: true,
         max_tile_bytes: 100,
         zoom: Some(1),
         bucket: Some(0),
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -74,6 +99,7 @@ fn inspect_zoom_limits_stats_and_histogram() {
             total_bytes: 120,
             max_bytes: 70,
             avg_bytes: 60,
+            percentiles: None,
         }
     );
     assert_eq!(report.by_zoom.len(), 1);
@@ -95,18 +121,40 @@ fn inspect_zoom_sampling_uses_zoom_tile_count() {
 
     let options = InspectOptions {
         sample: Some(vt_optimizer::mbtiles::SampleSpec::Count(1)),
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 0,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: Some(1),
         bucket: None,
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -123,18 +171,40 @@ fn inspect_histograms_by_zoom_includes_each_zoom() {
 
     let options = InspectOptions {
         sample: None,
+        sample_seed: None,
         topn: 0,
         histogram_buckets: 2,
         no_progress: true,
         max_tile_bytes: 0,
         zoom: None,
         bucket: None,
-        tile: None,
+        tiles: Vec::new(),
         summary: false,
         layers: Vec::new(),
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        check_geometry: false,
+        attributes_size: false,
+        layer_bytes: false,
+        percentiles: false,
+        dedup_stats: false,
+        recommend_thresholds: RecommendThresholds::default(),
+        layer_zoom_matrix: false,
+        property_histogram: None,
+        cardinality: None,
+        uncompressed_sizes: false,
+        hash: None,
+        validate_mvt: false,
+        empty_tile_max_bytes: 50,
+        histogram_cache_max_tiles: 16_000_000,
+        layers_only: false,
+        media_types: false,
+        histogram_exclude_empty: false,
+        empty_exact: false,
+        strict: false,
+        style: None,
+        style_source: None,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -144,3 +214,136 @@ fn inspect_histograms_by_zoom_includes_each_zoom() {
     assert_eq!(report.histograms_by_zoom[0].buckets.len(), 2);
     assert_eq!(report.histograms_by_zoom[1].buckets.len(), 2);
 }
+
+#[test]
+fn inspect_histograms_from_cached_sizes_match_rescans() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_zoom_sample_mbtiles(&path);
+
+    let inspect = |histogram_cache_max_tiles| {
+        inspect_mbtiles_with_options(
+            &path,
+            InspectOptions {
+                histogram_buckets: 3,
+                no_progress: true,
+                max_tile_bytes: 40,
+                histogram_cache_max_tiles,
+                ..InspectOptions::default()
+            },
+        )
+        .expect("inspect")
+    };
+    let cached = inspect(u64::MAX);
+    let rescanned = inspect(0);
+
+    assert_eq!(cached.histogram.len(), 3);
+    assert_eq!(cached.histograms_by_zoom.len(), 2);
+    assert_eq!(cached, rescanned);
+}
+
+#[test]
+fn inspect_percentiles_are_exact_on_full_scan() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_zoom_sample_mbtiles(&path);
+
+    let options = InspectOptions {
+        no_progress: true,
+        percentiles: true,
+        ..InspectOptions::default()
+    };
+
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    assert_eq!(
+        report.overall.percentiles,
+        Some(TilePercentiles {
+            p50: 30,
+            p90: 70,
+            p95: 70,
+            p99: 70,
+            p999: 70,
+        })
+    );
+    assert_eq!(
+        report.by_zoom[0].stats.percentiles,
+        Some(TilePercentiles {
+            p50: 10,
+            p90: 30,
+            p95: 30,
+            p99: 30,
+            p999: 30,
+        })
+    );
+    assert_eq!(
+        report.by_zoom[1].stats.percentiles,
+        Some(TilePercentiles {
+            p50: 50,
+            p90: 70,
+            p95: 70,
+            p99: 70,
+            p999: 70,
+        })
+    );
+
+    let report = inspect_mbtiles_with_options(
+        &path,
+        InspectOptions {
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+    assert_eq!(report.overall.percentiles, None);
+    assert!(report.by_zoom.iter().all(|z| z.stats.percentiles.is_none()));
+}
+
+#[test]
+fn inspect_percentiles_use_sketch_when_sampling() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_zoom_sample_mbtiles(&path);
+
+    let options = InspectOptions {
+        sample: Some(SampleSpec::Ratio(1.0)),
+        no_progress: true,
+        percentiles: true,
+        ..InspectOptions::default()
+    };
+
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    let percentiles = report.overall.percentiles.expect("percentiles");
+    assert!(percentiles.p50.abs_diff(30) <= 1);
+    assert!(percentiles.p99.abs_diff(70) <= 1);
+}
+
+#[test]
+fn quantile_sketch_stays_within_relative_accuracy() {
+    let mut sketch = QuantileSketch::new();
+    for value in 1..=10_000u64 {
+        sketch.insert(value);
+    }
+    assert_eq!(sketch.count(), 10_000);
+    for (pct, expected) in [(50u8, 5_000u64), (90, 9_000), (99, 9_900)] {
+        let estimate = sketch.percentile(pct).expect("percentile");
+        assert!(
+            estimate.abs_diff(expected) as f64 <= expected as f64 * 0.011,
+            "p{pct}: {estimate} vs {expected}"
+        );
+    }
+    assert_eq!(QuantileSketch::new().percentile(50), None);
+}
+
+#[test]
+fn size_quantiles_report_p999() {
+    let mut exact = SizeQuantiles::new(false);
+    let mut sketch = SizeQuantiles::new(true);
+    for value in 1..=1_000u64 {
+        exact.insert(value);
+        sketch.insert(value);
+    }
+    let exact = exact.percentiles().expect("exact");
+    assert_eq!((exact.p99, exact.p999), (990, 999));
+    let sketch = sketch.percentiles().expect("sketch");
+    assert!(sketch.p999.abs_diff(999) <= 10, "p99.9: {}", sketch.p999);
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use vt_optimizer::cli::CsvSection;
+use vt_optimizer::mbtiles::{
+    FileLayerSummary, HistogramBucket, MbtilesReport, MbtilesStats, MbtilesZoomStats,
+    TilePercentiles, TopTile, ZoomHistogram,
+};
+use vt_optimizer::output::{csv_field, csv_lines};
+
+fn stats(tile_count: u64, total_bytes: u64) -> MbtilesStats {
+    MbtilesStats {
+        tile_count,
+        total_bytes,
+        max_bytes: total_bytes,
+        avg_bytes: total_bytes / tile_count.max(1),
+        percentiles: None,
+    }
+}
+
+fn bucket(min_bytes: u64, max_bytes: u64, count: u64) -> HistogramBucket {
+    HistogramBucket {
+        min_bytes,
+        max_bytes,
+        count,
+        total_bytes: max_bytes * count,
+        running_avg_bytes: max_bytes,
+        pct_tiles: 0.5,
+        pct_level_bytes: 0.25,
+        accum_pct_tiles: 0.5,
+        accum_pct_level_bytes: 0.25,
+        avg_near_limit: false,
+        avg_over_limit: true,
+    }
+}
+
+fn sample_report() -> MbtilesReport {
+    MbtilesReport {
+        metadata: BTreeMap::new(),
+        overall: stats(3, 3_000_000),
+        by_zoom: vec![
+            MbtilesZoomStats {
+                zoom: 0,
+                stats: stats(1, 1_000_000),
+            },
+            MbtilesZoomStats {
+                zoom: 1,
+                stats: MbtilesStats {
+                    percentiles: Some(TilePercentiles {
+                        p50: 10,
+                        p90: 20,
+                        p95: 30,
+                        p99: 40,
+                        p999: 50,
+                    }),
+                    ..stats(2, 2_000_000)
+                },
+            },
+        ],
+        empty_tiles: 0,
+        empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
+        over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
+        sampled: false,
+        sample_total_tiles: 3,
+        sample_used_tiles: 3,
+        sample_seed: None,
+        histogram: vec![bucket(1, 10, 2)],
+        histograms_by_zoom: vec![ZoomHistogram {
+            zoom: 1,
+            buckets: vec![bucket(5, 10, 1)],
+        }],
+        file_layers: vec![FileLayerSummary {
+            name: "roads, major".to_string(),
+            vertex_count: 12,
+            feature_count: 3,
+            property_key_count: 2,
+            property_value_count: 4,
+            invalid_geometry_count: None,
+            tiles_with_layer: None,
+            min_features_per_tile: None,
+            max_features_per_tile: None,
+            avg_features_per_tile: None,
+            top_keys: Vec::new(),
+            geometry_bytes: None,
+            attribute_bytes: None,
+            approx_bytes_field_length: None,
+            approx_bytes_field_length_by_zoom: BTreeMap::new(),
+            pct_of_total: None,
+        }],
+        top_tiles: vec![TopTile {
+            zoom: 1,
+            x: 0,
+            y: 1,
+            bytes: 2_000_000,
+            url: None,
+            hash: None,
+        }],
+        bucket_count: None,
+        bucket_tiles: vec![],
+        tile_summary: None,
+        tile_summaries: Vec::new(),
+        recommended_buckets: vec![],
+        recommendations: vec![],
+        top_tile_summaries: vec![],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
+    }
+}
+
+#[test]
+fn csv_field_quotes_delimiters_and_quotes() {
+    assert_eq!(csv_field("roads"), "roads");
+    assert_eq!(csv_field("roads, major"), "\"roads, major\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+}
+
+#[test]
+fn csv_section_writes_single_table_with_raw_numbers() {
+    let lines = csv_lines(&sample_report(), Some(CsvSection::Zoom));
+    assert_eq!(
+        lines,
+        vec![
+            "zoom,tile_count,total_bytes,max_bytes,avg_bytes,p50,p90,p95,p99,p999",
+            "0,1,1000000,1000000,1000000,,,,,",
+            "1,2,2000000,2000000,1000000,10,20,30,40,50",
+        ]
+    );
+}
+
+#[test]
+fn csv_section_keeps_header_when_table_is_empty() {
+    let mut report = sample_report();
+    report.top_tiles.clear();
+    let lines = csv_lines(&report, Some(CsvSection::TopTiles));
+    assert_eq!(lines, vec!["zoom,x,y,bytes"]);
+}
+
+#[test]
+fn csv_without_section_prefixes_each_table() {
+    let lines = csv_lines(&sample_report(), None);
+    assert_eq!(
+        lines[0],
+        "section,zoom,tile_count,total_bytes,max_bytes,avg_bytes,p50,p90,p95,p99,p999"
+    );
+    assert!(lines.contains(&"section,zoom,bucket,min_bytes,max_bytes,count,total_bytes,running_avg_bytes,pct_tiles,pct_level_bytes,accum_pct_tiles,accum_pct_level_bytes,avg_near_limit,avg_over_limit".to_string()));
+    assert!(lines.contains(&"histogram,,0,1,10,2,20,10,0.5,0.25,0.5,0.25,false,true".to_string()));
+    assert!(lines.contains(&"histogram,1,0,5,10,1,10,10,0.5,0.25,0.5,0.25,false,true".to_string()));
+    assert!(lines.contains(&"layers,\"roads, major\",12,3,2,4,,,,,".to_string()));
+    assert_eq!(
+        lines.last().map(String::as_str),
+        Some("top_tiles,1,0,1,2000000")
+    );
+    assert_eq!(lines.iter().filter(|line| line.is_empty()).count(), 3);
+}
@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use vt_optimizer::format::RasterFormat;
+use vt_optimizer::mbtiles::{
+    ContentHash, DedupReport, DedupStats, FileLayerSummary, HighCardinalityKey, HistogramBucket,
+    LayerSummary, MbtilesReport, MbtilesStats, MbtilesZoomStats, MetadataMismatch,
+    MetadataMismatchKind, MvtValidation, OverLimitEstimate, PropertyHistogram, PropertyValueCount,
+    RecommendReason, Recommendation, RecommendedAction, StyleLayerPreview, StyleLayerStatus,
+    StylePreview, TileDecodeError, TilePercentiles, TileSummary, TopTile, VectorLayerInfo,
+    ZoomDedupStats, ZoomHistogram,
+};
+use vt_optimizer::output::{
+    INSPECT_REPORT_VERSION, ReportGenerator, VersionedReport, format_utc_timestamp,
+    json_report_string, report_json_schema,
+};
+
+const SCHEMA_SNAPSHOT: &str = "tests/snapshots/inspect_report_schema.json";
+
+fn stats(tile_count: u64, total_bytes: u64) -> MbtilesStats {
+    MbtilesStats {
+        tile_count,
+        total_bytes,
+        max_bytes: total_bytes,
+        avg_bytes: total_bytes / tile_count.max(1),
+        percentiles: Some(TilePercentiles {
+            p50: 10,
+            p90: 20,
+            p95: 30,
+            p99: 40,
+            p999: 50,
+        }),
+    }
+}
+
+fn bucket() -> HistogramBucket {
+    HistogramBucket {
+        min_bytes: 1,
+        max_bytes: 10,
+        count: 2,
+        total_bytes: 20,
+        running_avg_bytes: 10,
+        pct_tiles: 0.5,
+        pct_level_bytes: 0.25,
+        accum_pct_tiles: 0.5,
+        accum_pct_level_bytes: 0.25,
+        avg_near_limit: false,
+        avg_over_limit: true,
+    }
+}
+
+fn top_tile() -> TopTile {
+    TopTile {
+        zoom: 1,
+        x: 0,
+        y: 1,
+        bytes: 2_000,
+        url: Some("https://example.com/1/0/1".to_string()),
+        hash: Some("00ff".to_string()),
+    }
+}
+
+fn tile_summary() -> TileSummary {
+    TileSummary {
+        zoom: 1,
+        x: 0,
+        y: 1,
+        tile_bytes: 2_000,
+        layer_count: 1,
+        total_features: 3,
+        vertex_count: 12,
+        property_key_count: 2,
+        property_value_count: 4,
+        layers: vec![LayerSummary {
+            name: "roads".to_string(),
+            bytes: 1_500,
+            feature_count: 3,
+            vertex_count: 12,
+            property_key_count: 2,
+            property_value_count: 4,
+            property_keys: vec!["class".to_string()],
+            invalid_geometry_count: Some(0),
+            duplicate_feature_count: 0,
+        }],
+    }
+}
+
+fn recommendation(action: RecommendedAction) -> Recommendation {
+    Recommendation {
+        zoom: 1,
+        layer: "roads".to_string(),
+        reason: RecommendReason::MostVertices,
+        bytes_share: 0.75,
+        vertices_share: 0.5,
+        avg_bytes: 1_500,
+        avg_features: 3,
+        action,
+        estimated_savings_bytes: 100,
+    }
+}
+
+/// A report with every optional section filled in.
+fn full_report() -> MbtilesReport {
+    let dedup_stats = DedupStats {
+        unique_tiles: 2,
+        duplicate_tiles: 1,
+        reclaimable_bytes: 10,
+    };
+    MbtilesReport {
+        metadata: BTreeMap::from([("name".to_string(), "sample".to_string())]),
+        overall: stats(3, 3_000),
+        by_zoom: vec![MbtilesZoomStats {
+            zoom: 1,
+            stats: stats(3, 3_000),
+        }],
+        empty_tiles: 1,
+        empty_ratio: 0.33,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
+        over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: Some(OverLimitEstimate::new(0, 3, 3)),
+        corrupt_rows: 0,
+        sampled: true,
+        sample_total_tiles: 3,
+        sample_used_tiles: 3,
+        sample_seed: None,
+        histogram: vec![bucket()],
+        histograms_by_zoom: vec![ZoomHistogram {
+            zoom: 1,
+            buckets: vec![bucket()],
+        }],
+        file_layers: vec![FileLayerSummary {
+            name: "roads".to_string(),
+            vertex_count: 12,
+            feature_count: 3,
+            property_key_count: 2,
+            property_value_count: 4,
+            invalid_geometry_count: Some(1),
+            tiles_with_layer: Some(1),
+            min_features_per_tile: Some(3),
+            max_features_per_tile: Some(3),
+            avg_features_per_tile: Some(3.0),
+            top_keys: vec![("class".to_string(), 3)],
+            geometry_bytes: Some(40),
+            attribute_bytes: Some(24),
+            approx_bytes_field_length: None,
+            approx_bytes_field_length_by_zoom: BTreeMap::new(),
+            pct_of_total: None,
+        }],
+        top_tiles: vec![top_tile()],
+        bucket_count: Some(1),
+        bucket_tiles: vec![top_tile()],
+        tile_summary: Some(tile_summary()),
+        tile_summaries: vec![tile_summary()],
+        recommended_buckets: vec![0],
+        recommendations: vec![
+            recommendation(RecommendedAction::Simplify { tolerance: 4.0 }),
+            recommendation(RecommendedAction::RaiseMinzoom { minzoom: 2 }),
+        ],
+        top_tile_summaries: vec![tile_summary()],
+        dedup: Some(DedupReport {
+            estimated: false,
+            overall: dedup_stats,
+            by_zoom: vec![ZoomDedupStats {
+                zoom: 1,
+                stats: dedup_stats,
+            }],
+        }),
+        content_hash: Some(ContentHash {
+            algorithm: "xxh3-128".to_string(),
+            tiles: 3,
+            digest: "00ff".to_string(),
+        }),
+        layer_zoom_matrix: vec![("roads".to_string(), vec![(1, 3)])],
+        property_histograms: vec![PropertyHistogram {
+            layer: "roads".to_string(),
+            key: "class".to_string(),
+            values: vec![PropertyValueCount {
+                value: "primary".to_string(),
+                count: 3,
+            }],
+            truncated: false,
+        }],
+        stored_bytes: Some(1_000),
+        mvt_validation: Some(MvtValidation {
+            invalid_tiles: 1,
+            errors: vec![TileDecodeError {
+                zoom: 1,
+                x: 0,
+                y: 1,
+                error: "truncated".to_string(),
+            }],
+        }),
+        raster_format: Some(RasterFormat::Png),
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: vec![HighCardinalityKey {
+            layer: "roads".to_string(),
+            key: "ref".to_string(),
+            feature_count: 3,
+            distinct_values: 3,
+            distinct_ratio: 1.0,
+            estimated: false,
+            estimated_bytes: 42,
+        }],
+        vector_layers: vec![VectorLayerInfo {
+            id: "roads".to_string(),
+            description: None,
+            minzoom: Some(0),
+            maxzoom: Some(14),
+            fields: BTreeMap::from([("class".to_string(), "String".to_string())]),
+        }],
+        metadata_mismatches: vec![MetadataMismatch {
+            layer: "water".to_string(),
+            kind: MetadataMismatchKind::DeclaredNotInTiles,
+        }],
+        style_preview: Some(StylePreview {
+            layers: vec![StyleLayerPreview {
+                name: "roads".to_string(),
+                status: StyleLayerStatus::Partial,
+                kept_zooms: vec![1],
+                dropped_zooms: vec![0],
+                removed_features: Some(1),
+                removed_bytes: Some(10),
+            }],
+            total_features: 3,
+            removed_features: Some(1),
+            removed_features_pct: Some(33.3),
+            total_bytes: Some(30),
+            removed_bytes: Some(10),
+            removed_bytes_pct: Some(33.3),
+        }),
+    }
+}
+
+/// Minimal JSON Schema check covering the keywords `report_json_schema` uses.
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Vec<String> {
+    let Some(schema) = schema.as_object() else {
+        return if schema == &Value::Bool(false) {
+            vec![format!("{path}: not allowed")]
+        } else {
+            Vec::new()
+        };
+    };
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/$defs/");
+        return validate(root, &root["$defs"][name], value, path);
+    }
+    let mut errors = Vec::new();
+    for (keyword, any) in [("anyOf", true), ("oneOf", false)] {
+        if let Some(options) = schema.get(keyword).and_then(Value::as_array) {
+            let matches = options
+                .iter()
+                .filter(|option| validate(root, option, value, path).is_empty())
+                .count();
+            if (any && matches == 0) || (!any && matches != 1) {
+                errors.push(format!("{path}: {keyword} matched {matches} schemas"));
+            }
+        }
+    }
+    if let Some(expected) = schema.get("const")
+        && expected != value
+    {
+        errors.push(format!("{path}: expected {expected}"));
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array)
+        && !values.contains(value)
+    {
+        errors.push(format!("{path}: {value} not in enum"));
+    }
+    if let Some(kind) = schema.get("type").and_then(Value::as_str) {
+        let ok = match kind {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "integer" => value.is_u64() || value.is_i64(),
+            "number" => value.is_number(),
+            "string" => value.is_string(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => false,
+        };
+        if !ok {
+            errors.push(format!("{path}: expected {kind}, got {value}"));
+            return errors;
+        }
+    }
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64)
+        && value.as_f64().is_some_and(|number| number < minimum)
+    {
+        errors.push(format!("{path}: below minimum {minimum}"));
+    }
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(name) {
+                errors.push(format!("{path}: missing required `{name}`"));
+            }
+        }
+        for (name, field) in object {
+            let field_path = format!("{path}.{name}");
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(field_schema) => {
+                    errors.extend(validate(root, field_schema, field, &field_path))
+                }
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{field_path}: not declared in the schema"))
+                    }
+                    Some(extra) => errors.extend(validate(root, extra, field, &field_path)),
+                    None => {}
+                },
+            }
+        }
+    }
+    if let Some(items) = value.as_array() {
+        let prefix = schema
+            .get("prefixItems")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        for (index, item) in items.iter().enumerate() {
+            let item_schema = prefix.get(index).or_else(|| schema.get("items"));
+            if let Some(item_schema) = item_schema {
+                errors.extend(validate(
+                    root,
+                    item_schema,
+                    item,
+                    &format!("{path}[{index}]"),
+                ));
+            }
+        }
+    }
+    errors
+}
+
+fn generator() -> ReportGenerator {
+    ReportGenerator {
+        name: "vt-optimizer".to_string(),
+        version: "0.0.0".to_string(),
+        command_line: vec!["vt-optimizer".to_string(), "inspect".to_string()],
+        timestamp: "2024-01-01T00:00:00Z".to_string(),
+    }
+}
+
+#[test]
+fn report_json_schema_matches_snapshot() {
+    let expected: Value = serde_json::from_str(
+        &std::fs::read_to_string(SCHEMA_SNAPSHOT).expect("read schema snapshot"),
+    )
+    .expect("parse schema snapshot");
+    let schema = report_json_schema();
+    assert!(
+        schema == expected,
+        "inspect report schema changed; review the change, bump INSPECT_REPORT_VERSION when a \
+         field was renamed or removed, and update {SCHEMA_SNAPSHOT} with:\n{}",
+        serde_json::to_string_pretty(&schema).unwrap()
+    );
+}
+
+#[test]
+fn versioned_report_conforms_to_schema() {
+    let schema = report_json_schema();
+    let report = full_report();
+    let value = serde_json::to_value(VersionedReport::new(&report, generator())).unwrap();
+    assert_eq!(value["report_version"], INSPECT_REPORT_VERSION);
+    assert_eq!(value["generator"]["command_line"][1], "inspect");
+    assert_eq!(
+        validate(&schema, &schema, &value, "$"),
+        Vec::<String>::new()
+    );
+
+    // Optional sections are omitted rather than null
+    let mut report = full_report();
+    report.file_layers[0].top_keys.clear();
+    report.dedup = None;
+    report.raster_format = None;
+    report.tile_summary = None;
+    report.over_limit_tiles_exact = None;
+    let value = serde_json::to_value(VersionedReport::new(&report, generator())).unwrap();
+    assert!(value.get("dedup").is_none());
+    assert!(value.get("over_limit_tiles_exact").is_none());
+    assert_eq!(
+        validate(&schema, &schema, &value, "$"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn schema_rejects_undeclared_fields() {
+    let schema = report_json_schema();
+    let report = full_report();
+    let mut value = serde_json::to_value(VersionedReport::new(&report, generator())).unwrap();
+    value["overall"]["unexpected"] = Value::from(1);
+    value.as_object_mut().unwrap().remove("raster_tiles");
+    let errors = validate(&schema, &schema, &value, "$");
+    assert_eq!(
+        errors,
+        vec![
+            "$: missing required `raster_tiles`".to_string(),
+            "$.overall.unexpected: not declared in the schema".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn generator_reports_crate_version_and_utc_timestamp() {
+    let generator = ReportGenerator::current();
+    assert_eq!(generator.version, env!("CARGO_PKG_VERSION"));
+    assert!(!generator.command_line.is_empty());
+    assert_eq!(generator.timestamp.len(), "2024-01-01T00:00:00Z".len());
+    assert_eq!(format_utc_timestamp(0), "1970-01-01T00:00:00Z");
+    assert_eq!(format_utc_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+    assert_eq!(format_utc_timestamp(1_704_067_199), "2023-12-31T23:59:59Z");
+}
+
+#[test]
+fn json_report_string_compacts_to_one_line() {
+    let report = full_report();
+    let report = VersionedReport::new(&report, generator());
+    let pretty = json_report_string(&report, false).expect("pretty");
+    let compact = json_report_string(&report, true).expect("compact");
+    assert!(pretty.lines().count() > 1);
+    assert_eq!(compact.lines().count(), 1);
+    assert!(compact.len() < pretty.len());
+    assert_eq!(
+        serde_json::from_str::<Value>(&compact).unwrap(),
+        serde_json::from_str::<Value>(&pretty).unwrap()
+    );
+}
@@ -2,11 +2,12 @@ use std::collections::BTreeMap;
 
 use vt_optimizer::cli::TileInfoFormat;
 use vt_optimizer::mbtiles::{
-    HistogramBucket, MbtilesReport, MbtilesStats, MbtilesZoomStats, TileSummary, TopTile,
-    ZoomHistogram,
+    HistogramBucket, MbtilesReport, MbtilesStats, MbtilesZoomStats, TileRecord, TileSummary,
+    TopTile, ZoomHistogram,
 };
 use vt_optimizer::output::{
-    NdjsonOptions, apply_tile_info_format, ndjson_lines, resolve_output_format,
+    NdjsonOptions, apply_tile_info_format, ndjson_lines, ndjson_tile_line,
+    ndjson_tile_line_with_url, resolve_output_format,
 };
 
 #[test]
@@ -18,6 +19,7 @@ fn ndjson_splits_histograms_and_top_tile_summaries() {
             total_bytes: 10,
             max_bytes: 10,
             avg_bytes: 10,
+            percentiles: None,
         },
         by_zoom: vec![MbtilesZoomStats {
             zoom: 0,
@@ -26,14 +28,21 @@ fn ndjson_splits_histograms_and_top_tile_summaries() {
                 total_bytes: 10,
                 max_bytes: 10,
                 avg_bytes: 10,
+                percentiles: None,
             },
         }],
         empty_tiles: 0,
         empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 1,
         sample_used_tiles: 1,
+        sample_seed: None,
         histogram: vec![HistogramBucket {
             min_bytes: 1,
             max_bytes: 10,
@@ -63,6 +72,8 @@ fn ndjson_splits_histograms_and_top_tile_summaries() {
             x: 0,
             y: 0,
             bytes: 10,
+            url: None,
+            hash: None,
         }],
         bucket_count: None,
         bucket_tiles: vec![TopTile {
@@ -70,9 +81,13 @@ fn ndjson_splits_histograms_and_top_tile_summaries() {
             x: 1,
             y: 1,
             bytes: 5,
+            url: None,
+            hash: None,
         }],
         tile_summary: None,
+        tile_summaries: Vec::new(),
         recommended_buckets: vec![0],
+        recommendations: vec![],
         top_tile_summaries: vec![
             TileSummary {
                 zoom: 0,
@@ -99,6 +114,19 @@ fn ndjson_splits_histograms_and_top_tile_summaries() {
                 layers: vec![],
             },
         ],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let lines = ndjson_lines(
@@ -152,14 +180,21 @@ fn ndjson_lite_omits_summary() {
             total_bytes: 10,
             max_bytes: 10,
             avg_bytes: 10,
+            percentiles: None,
         },
         by_zoom: vec![],
         empty_tiles: 0,
         empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 1,
         sample_used_tiles: 1,
+        sample_seed: None,
         histogram: vec![],
         histograms_by_zoom: vec![],
         file_layers: vec![],
@@ -167,8 +202,23 @@ fn ndjson_lite_omits_summary() {
         bucket_count: None,
         bucket_tiles: vec![],
         tile_summary: None,
+        tile_summaries: Vec::new(),
         recommended_buckets: vec![],
+        recommendations: vec![],
         top_tile_summaries: vec![],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let lines = ndjson_lines(
@@ -196,14 +246,21 @@ fn ndjson_sorts_zoom_histograms_and_recommendations() {
             total_bytes: 10,
             max_bytes: 10,
             avg_bytes: 10,
+            percentiles: None,
         },
         by_zoom: vec![],
         empty_tiles: 0,
         empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 1,
         sample_used_tiles: 1,
+        sample_seed: None,
         histogram: vec![],
         histograms_by_zoom: vec![
             ZoomHistogram {
@@ -220,8 +277,23 @@ fn ndjson_sorts_zoom_histograms_and_recommendations() {
         bucket_count: None,
         bucket_tiles: vec![],
         tile_summary: None,
+        tile_summaries: Vec::new(),
         recommended_buckets: vec![2, 0, 1],
+        recommendations: vec![],
         top_tile_summaries: vec![],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let lines = ndjson_lines(
@@ -270,14 +342,21 @@ fn ndjson_compact_minimizes_payloads() {
             total_bytes: 10,
             max_bytes: 10,
             avg_bytes: 10,
+            percentiles: None,
         },
         by_zoom: vec![],
         empty_tiles: 0,
         empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 1,
         sample_used_tiles: 1,
+        sample_seed: None,
         histogram: vec![HistogramBucket {
             min_bytes: 1,
             max_bytes: 10,
@@ -301,6 +380,8 @@ fn ndjson_compact_minimizes_payloads() {
             x: 0,
             y: 0,
             bytes: 10,
+            url: None,
+            hash: None,
         }],
         bucket_count: None,
         bucket_tiles: vec![TopTile {
@@ -308,8 +389,11 @@ fn ndjson_compact_minimizes_payloads() {
             x: 1,
             y: 1,
             bytes: 5,
+            url: None,
+            hash: None,
         }],
-        tile_summary: Some(TileSummary {
+        tile_summary: None,
+        tile_summaries: vec![TileSummary {
             zoom: 2,
             x: 2,
             y: 2,
@@ -320,8 +404,9 @@ fn ndjson_compact_minimizes_payloads() {
             property_key_count: 0,
             property_value_count: 0,
             layers: vec![],
-        }),
+        }],
         recommended_buckets: vec![],
+        recommendations: vec![],
         top_tile_summaries: vec![TileSummary {
             zoom: 3,
             x: 3,
@@ -334,6 +419,19 @@ fn ndjson_compact_minimizes_payloads() {
             property_value_count: 0,
             layers: vec![],
         }],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let lines = ndjson_lines(
@@ -362,14 +460,21 @@ fn ndjson_compact_omits_summary_even_when_requested() {
             total_bytes: 10,
             max_bytes: 10,
             avg_bytes: 10,
+            percentiles: None,
         },
         by_zoom: vec![],
         empty_tiles: 0,
         empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 1,
         sample_used_tiles: 1,
+        sample_seed: None,
         histogram: vec![],
         histograms_by_zoom: vec![],
         file_layers: vec![],
@@ -377,8 +482,23 @@ fn ndjson_compact_omits_summary_even_when_requested() {
         bucket_count: None,
         bucket_tiles: vec![],
         tile_summary: None,
+        tile_summaries: Vec::new(),
         recommended_buckets: vec![],
+        recommendations: vec![],
         top_tile_summaries: vec![],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let lines = ndjson_lines(
@@ -406,21 +526,29 @@ fn ndjson_tile_info_format_compact_omits_property_keys() {
             total_bytes: 10,
             max_bytes: 10,
             avg_bytes: 10,
+            percentiles: None,
         },
         by_zoom: vec![],
         empty_tiles: 0,
         empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 1,
         sample_used_tiles: 1,
+        sample_seed: None,
         histogram: vec![],
         histograms_by_zoom: vec![],
         file_layers: vec![],
         top_tiles: vec![],
         bucket_count: None,
         bucket_tiles: vec![],
-        tile_summary: Some(TileSummary {
+        tile_summary: None,
+        tile_summaries: vec![TileSummary {
             zoom: 2,
             x: 2,
             y: 2,
@@ -432,15 +560,32 @@ fn ndjson_tile_info_format_compact_omits_property_keys() {
             property_value_count: 1,
             layers: vec![vt_optimizer::mbtiles::LayerSummary {
                 name: "roads".to_string(),
+                bytes: 12,
                 feature_count: 1,
                 vertex_count: 1,
                 property_key_count: 1,
                 property_value_count: 1,
                 property_keys: vec!["name".to_string()],
+                invalid_geometry_count: None,
+                duplicate_feature_count: 0,
             }],
-        }),
+        }],
         recommended_buckets: vec![],
+        recommendations: vec![],
         top_tile_summaries: vec![],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let report = apply_tile_info_format(report, TileInfoFormat::Compact);
@@ -458,8 +603,43 @@ fn ndjson_tile_info_format_compact_omits_property_keys() {
 
 #[test]
 fn ndjson_compact_forces_output_format() {
-    let output = resolve_output_format(vt_optimizer::cli::ReportFormat::Text, true);
-    assert_eq!(output, vt_optimizer::cli::ReportFormat::Ndjson);
-    let output = resolve_output_format(vt_optimizer::cli::ReportFormat::Json, false);
-    assert_eq!(output, vt_optimizer::cli::ReportFormat::Json);
+    let output = resolve_output_format(vt_optimizer::cli::InspectReportFormat::Text, true);
+    assert_eq!(output, vt_optimizer::cli::InspectReportFormat::Ndjson);
+    let output = resolve_output_format(vt_optimizer::cli::InspectReportFormat::Json, false);
+    assert_eq!(output, vt_optimizer::cli::InspectReportFormat::Json);
+}
+
+#[test]
+fn ndjson_tile_line_is_flat_record() {
+    let line = ndjson_tile_line(&TileRecord {
+        zoom: 3,
+        x: 1,
+        y: 2,
+        bytes: 40,
+        over_limit: true,
+        hash: None,
+    })
+    .expect("line");
+    assert_eq!(
+        line,
+        r#"{"bytes":40,"over_limit":true,"type":"tile","x":1,"y":2,"z":3}"#
+    );
+}
+
+#[test]
+fn ndjson_tile_line_with_url_adds_url() {
+    let record = TileRecord {
+        zoom: 3,
+        x: 1,
+        y: 2,
+        bytes: 40,
+        over_limit: false,
+        hash: None,
+    };
+    let line = ndjson_tile_line_with_url(&record, Some("https://t.example/3/1/2".to_string()))
+        .expect("line");
+    assert_eq!(
+        line,
+        r#"{"bytes":40,"over_limit":false,"type":"tile","url":"https://t.example/3/1/2","x":1,"y":2,"z":3}"#
+    );
 }
@@ -0,0 +1,164 @@
+#![cfg(feature = "parquet")]
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+use arrow_array::{
+    Array, BooleanArray, Float64Array, RecordBatch, StringArray, UInt8Array, UInt32Array,
+    UInt64Array,
+};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use vt_optimizer::mbtiles::{FileLayerSummary, TileRecord};
+use vt_optimizer::parquet_report::{
+    ParquetTileWriter, layer_schema, tile_schema, write_layers_parquet,
+};
+
+fn read_batches(path: &Path) -> Vec<RecordBatch> {
+    let file = File::open(path).expect("open parquet");
+    ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("parquet reader")
+        .build()
+        .expect("build reader")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("read batches")
+}
+
+fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+    batch
+        .column_by_name(name)
+        .expect("column")
+        .as_any()
+        .downcast_ref::<T>()
+        .expect("column type")
+}
+
+#[test]
+fn parquet_tile_writer_streams_records() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("tiles.parquet");
+    let writer = ParquetTileWriter::create(&path).expect("create writer");
+    let records = (0..3u32).map(|x| TileRecord {
+        zoom: 2,
+        x,
+        y: 1,
+        bytes: 100 * u64::from(x + 1),
+        over_limit: x == 2,
+        hash: (x == 0).then(|| "abc".to_string()),
+    });
+    for record in records {
+        writer.write(record).expect("write record");
+    }
+    assert_eq!(writer.finish().expect("finish"), 3);
+
+    let batches = read_batches(&path);
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.schema(), tile_schema());
+    assert_eq!(column::<UInt8Array>(batch, "zoom").values(), &[2, 2, 2]);
+    assert_eq!(column::<UInt32Array>(batch, "x").values(), &[0, 1, 2]);
+    assert_eq!(column::<UInt32Array>(batch, "y").values(), &[1, 1, 1]);
+    assert_eq!(
+        column::<UInt64Array>(batch, "bytes").values(),
+        &[100, 200, 300]
+    );
+    let over_limit = column::<BooleanArray>(batch, "over_limit");
+    assert_eq!(
+        over_limit.iter().collect::<Vec<_>>(),
+        vec![Some(false), Some(false), Some(true)]
+    );
+    let hash = column::<StringArray>(batch, "hash");
+    assert_eq!(
+        hash.iter().collect::<Vec<_>>(),
+        vec![Some("abc"), None, None]
+    );
+}
+
+fn layer(
+    name: &str,
+    features: u64,
+    vertices: u64,
+    keys: usize,
+    values: usize,
+    bytes: u64,
+    pct: f64,
+) -> FileLayerSummary {
+    FileLayerSummary {
+        name: name.to_string(),
+        vertex_count: vertices,
+        feature_count: features,
+        property_key_count: keys,
+        property_value_count: values,
+        invalid_geometry_count: None,
+        tiles_with_layer: None,
+        min_features_per_tile: None,
+        max_features_per_tile: None,
+        avg_features_per_tile: None,
+        top_keys: Vec::new(),
+        geometry_bytes: None,
+        attribute_bytes: None,
+        approx_bytes_field_length: Some(bytes),
+        approx_bytes_field_length_by_zoom: BTreeMap::new(),
+        pct_of_total: Some(pct),
+    }
+}
+
+#[test]
+fn parquet_layers_keep_unset_columns_null() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("layers.parquet");
+    let layers = vec![
+        layer("roads", 10, 40, 2, 5, 300, 75.0),
+        layer("water", 1, 8, 0, 0, 100, 25.0),
+    ];
+    assert_eq!(write_layers_parquet(&path, &layers).expect("write"), 2);
+
+    let batches = read_batches(&path);
+    let batch = &batches[0];
+    assert_eq!(batch.schema(), layer_schema());
+    let names = column::<StringArray>(batch, "layer");
+    assert_eq!(
+        names.iter().collect::<Vec<_>>(),
+        vec![Some("roads"), Some("water")]
+    );
+    assert_eq!(column::<UInt64Array>(batch, "features").values(), &[10, 1]);
+    assert_eq!(column::<UInt64Array>(batch, "vertices").values(), &[40, 8]);
+    assert_eq!(
+        column::<UInt64Array>(batch, "approx_bytes").values(),
+        &[300, 100]
+    );
+    assert_eq!(
+        column::<Float64Array>(batch, "pct_of_total").values(),
+        &[75.0, 25.0]
+    );
+    let invalid = column::<UInt64Array>(batch, "invalid_geometries");
+    assert_eq!(invalid.null_count(), 2);
+}
+
+#[test]
+fn parquet_report_format_is_only_parsed_for_inspect() {
+    use clap::Parser;
+    use vt_optimizer::cli::{Cli, Command, InspectReportFormat};
+
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "inspect",
+        "input.mbtiles",
+        "--report-format",
+        "parquet",
+    ]);
+    match cli.command {
+        Some(Command::Inspect(args)) => assert_eq!(args.output, InspectReportFormat::Parquet),
+        _ => panic!("expected inspect command"),
+    }
+    let err = Cli::try_parse_from([
+        "vt-optimizer",
+        "optimize",
+        "input.mbtiles",
+        "--report-format",
+        "parquet",
+    ])
+    .expect_err("parquet optimize report");
+    assert_eq!(err.kind(), clap::error::ErrorKind::InvalidValue);
+}
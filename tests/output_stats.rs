@@ -20,14 +20,21 @@ fn apply_stats_filter_removes_unlisted_sections() {
             total_bytes: 10,
             max_bytes: 10,
             avg_bytes: 10,
+            percentiles: None,
         },
         by_zoom: vec![],
         empty_tiles: 1,
         empty_ratio: 1.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: true,
         sample_total_tiles: 10,
         sample_used_tiles: 1,
+        sample_seed: None,
         histogram: vec![HistogramBucket {
             min_bytes: 1,
             max_bytes: 2,
@@ -47,8 +54,23 @@ fn apply_stats_filter_removes_unlisted_sections() {
         bucket_count: Some(1),
         bucket_tiles: vec![],
         tile_summary: None,
+        tile_summaries: Vec::new(),
         recommended_buckets: vec![1],
+        recommendations: vec![],
         top_tile_summaries: vec![],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let filter = parse_stats_filter(Some("summary")).expect("filter");
@@ -71,3 +93,20 @@ fn parse_stats_filter_rejects_unknown_section() {
     let err = parse_stats_filter(Some("unknown")).expect_err("unknown should error");
     assert!(err.to_string().contains("possible values"));
 }
+
+#[test]
+fn stats_filter_layers_only_allows_metadata() {
+    assert!(parse_stats_filter(Some("layers")).unwrap().layers_only());
+    assert!(
+        parse_stats_filter(Some("metadata,layers"))
+            .unwrap()
+            .layers_only()
+    );
+    assert!(
+        !parse_stats_filter(Some("layers,zoom"))
+            .unwrap()
+            .layers_only()
+    );
+    assert!(!parse_stats_filter(Some("metadata")).unwrap().layers_only());
+    assert!(!parse_stats_filter(None).unwrap().layers_only());
+}
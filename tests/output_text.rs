@@ -2,12 +2,14 @@ use std::collections::BTreeMap;
 
 use nu_ansi_term::Color;
 use vt_optimizer::mbtiles::{
-    HistogramBucket, MbtilesStats, MbtilesZoomStats, TileSummary, TopTile, ZoomHistogram,
+    HistogramBucket, MbtilesStats, MbtilesZoomStats, RecommendReason, Recommendation,
+    RecommendedAction, TilePercentiles, TileSummary, TopTile, ZoomHistogram,
 };
 use vt_optimizer::output::{
-    LayerTotals, format_histogram_table, format_histograms_by_zoom_section,
-    format_metadata_section, format_tile_summary_text, format_top_tiles_lines, format_zoom_table,
-    summarize_file_layers,
+    LayerTotals, format_bytes, format_histogram_table, format_histograms_by_zoom_section,
+    format_layer_zoom_matrix, format_metadata_section, format_recommendation,
+    format_tile_summary_text, format_top_tiles_lines, format_zoom_ranges, format_zoom_sparkline,
+    format_zoom_table, summarize_file_layers,
 };
 
 #[allow(clippy::too_many_arguments)]
@@ -47,6 +49,7 @@ fn zoom_stats(zoom: u8, tile_count: u64, total: u64, max: u64, avg: u64) -> Mbti
             total_bytes: total,
             max_bytes: max,
             avg_bytes: avg,
+            percentiles: None,
         },
     }
 }
@@ -86,6 +89,29 @@ fn format_zoom_table_sorts_and_labels() {
     assert!(z2_index < z5_index);
 }
 
+#[test]
+fn format_zoom_table_adds_percentile_columns() {
+    let mut with_percentiles = zoom_stats(3, 4, 4_000, 2_000, 1_000);
+    with_percentiles.stats.percentiles = Some(TilePercentiles {
+        p50: 900,
+        p90: 1_500,
+        p95: 1_800,
+        p99: 1_900,
+        p999: 2_000,
+    });
+    let lines = format_zoom_table(
+        &[with_percentiles, zoom_stats(4, 1, 500, 500, 500)],
+        5,
+        4_500,
+    );
+    assert!(lines[0].contains("p50") && lines[0].contains("p99.9"));
+    assert!(lines[1].ends_with(&format_bytes(2_000)));
+    assert!(lines[2].trim_end().ends_with('-'));
+
+    let lines = format_zoom_table(&[zoom_stats(4, 1, 500, 500, 500)], 1, 500);
+    assert!(!lines[0].contains("p50"));
+}
+
 #[test]
 fn format_top_tiles_lines_includes_size() {
     let tiles = vec![TopTile {
@@ -93,6 +119,8 @@ fn format_top_tiles_lines_includes_size() {
         x: 2,
         y: 3,
         bytes: 2048,
+        url: None,
+        hash: None,
     }];
     let lines = format_top_tiles_lines(&tiles);
     assert_eq!(lines.len(), 1);
@@ -224,6 +252,17 @@ fn summarize_file_layers_accumulates_counts() {
             feature_count: 2,
             property_key_count: 3,
             property_value_count: 4,
+            invalid_geometry_count: None,
+            tiles_with_layer: None,
+            min_features_per_tile: None,
+            max_features_per_tile: None,
+            avg_features_per_tile: None,
+            top_keys: Vec::new(),
+            geometry_bytes: None,
+            attribute_bytes: None,
+            approx_bytes_field_length: None,
+            approx_bytes_field_length_by_zoom: BTreeMap::new(),
+            pct_of_total: None,
         },
         vt_optimizer::mbtiles::FileLayerSummary {
             name: "b".to_string(),
@@ -231,6 +270,17 @@ fn summarize_file_layers_accumulates_counts() {
             feature_count: 5,
             property_key_count: 7,
             property_value_count: 11,
+            invalid_geometry_count: None,
+            tiles_with_layer: None,
+            min_features_per_tile: None,
+            max_features_per_tile: None,
+            avg_features_per_tile: None,
+            top_keys: Vec::new(),
+            geometry_bytes: None,
+            attribute_bytes: None,
+            approx_bytes_field_length: None,
+            approx_bytes_field_length_by_zoom: BTreeMap::new(),
+            pct_of_total: None,
         },
     ];
 
@@ -247,3 +297,96 @@ fn summarize_file_layers_accumulates_counts() {
         })
     );
 }
+
+#[test]
+fn format_zoom_sparkline_scales_to_largest_zoom() {
+    let zoom = |zoom: u8, total_bytes: u64| MbtilesZoomStats {
+        zoom,
+        stats: MbtilesStats {
+            tile_count: 1,
+            total_bytes,
+            max_bytes: total_bytes,
+            avg_bytes: total_bytes,
+            percentiles: None,
+        },
+    };
+    let stats = vec![zoom(2, 100), zoom(0, 800), zoom(1, 400), zoom(4, 1)];
+
+    assert_eq!(format_zoom_sparkline(&stats), "█▄▁ ▁");
+    assert_eq!(format_zoom_sparkline(&[]), "");
+}
+
+#[test]
+fn format_recommendation_describes_action_and_savings() {
+    let recommendation = Recommendation {
+        zoom: 14,
+        layer: "buildings".to_string(),
+        reason: RecommendReason::BytesShare,
+        bytes_share: 0.62,
+        vertices_share: 0.8,
+        avg_bytes: 4096,
+        avg_features: 120,
+        action: RecommendedAction::Simplify { tolerance: 1.0 },
+        estimated_savings_bytes: 2048,
+    };
+    assert_eq!(
+        format_recommendation(&recommendation),
+        "layer `buildings` contributes 62% of bytes (80% of vertices) at z14 — consider simplify tolerance 1.0 (est. -2.00KB)"
+    );
+    let recommendation = Recommendation {
+        action: RecommendedAction::RaiseMinzoom { minzoom: 15 },
+        ..recommendation
+    };
+    assert!(format_recommendation(&recommendation).contains("consider raising its minzoom to 15"));
+}
+
+#[test]
+fn format_recommendation_describes_threshold_reason() {
+    let recommendation = Recommendation {
+        zoom: 10,
+        layer: "pois".to_string(),
+        reason: RecommendReason::FeatureCount,
+        bytes_share: 0.05,
+        vertices_share: 0.02,
+        avg_bytes: 1024,
+        avg_features: 350,
+        action: RecommendedAction::RaiseMinzoom { minzoom: 11 },
+        estimated_savings_bytes: 512,
+    };
+    assert_eq!(
+        format_recommendation(&recommendation),
+        "layer `pois` averages 350 features per tile at z10 — consider raising its minzoom to 11 (est. -512B)"
+    );
+    let recommendation = Recommendation {
+        reason: RecommendReason::MostVertices,
+        vertices_share: 0.45,
+        ..recommendation
+    };
+    assert!(
+        format_recommendation(&recommendation)
+            .starts_with("layer `pois` has the most vertices (45%) at z10")
+    );
+}
+
+#[test]
+fn format_zoom_ranges_collapses_consecutive_zooms() {
+    assert_eq!(format_zoom_ranges(&[0, 1, 2, 5, 7, 8]), "z0-z2, z5, z7-z8");
+    assert_eq!(format_zoom_ranges(&[]), "");
+}
+
+#[test]
+fn format_layer_zoom_matrix_marks_missing_zooms() {
+    let matrix = vec![
+        ("roads".to_string(), vec![(0, 3), (2, 1200)]),
+        ("water".to_string(), vec![(1, 7)]),
+    ];
+    assert_eq!(
+        format_layer_zoom_matrix(&matrix),
+        vec![
+            "  name  z0 z1   z2".to_string(),
+            "  roads  3  - 1200".to_string(),
+            "  water  -  7    -".to_string(),
+        ]
+    );
+    assert!(format_layer_zoom_matrix(&[]).is_empty());
+}
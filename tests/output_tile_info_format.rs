@@ -8,11 +8,14 @@ use vt_optimizer::output::apply_tile_info_format;
 fn tile_info_format_compact_clears_property_keys() {
     let layer = LayerSummary {
         name: "roads".to_string(),
+        bytes: 12,
         feature_count: 1,
         vertex_count: 2,
         property_key_count: 2,
         property_value_count: 2,
         property_keys: vec!["class".to_string(), "name".to_string()],
+        invalid_geometry_count: None,
+        duplicate_feature_count: 0,
     };
     let summary = TileSummary {
         zoom: 1,
@@ -33,22 +36,31 @@ fn tile_info_format_compact_clears_property_keys() {
             total_bytes: 0,
             max_bytes: 0,
             avg_bytes: 0,
+            percentiles: None,
         },
         by_zoom: vec![],
         empty_tiles: 0,
         empty_ratio: 0.0,
+        empty_tile_max_bytes: 50,
+        small_tiles: None,
         over_limit_tiles: 0,
+        over_limit_tiles_exact: Some(0),
+        over_limit_tiles_sampled: None,
+        corrupt_rows: 0,
         sampled: false,
         sample_total_tiles: 0,
         sample_used_tiles: 0,
+        sample_seed: None,
         histogram: vec![],
         histograms_by_zoom: vec![],
         file_layers: vec![],
         top_tiles: vec![],
         bucket_count: None,
         bucket_tiles: vec![],
-        tile_summary: Some(summary),
+        tile_summary: Some(summary.clone()),
+        tile_summaries: vec![summary],
         recommended_buckets: vec![],
+        recommendations: vec![],
         top_tile_summaries: vec![TileSummary {
             zoom: 4,
             x: 5,
@@ -61,9 +73,23 @@ fn tile_info_format_compact_clears_property_keys() {
             property_value_count: 2,
             layers: vec![layer],
         }],
+        dedup: None,
+        content_hash: None,
+        layer_zoom_matrix: Vec::new(),
+        property_histograms: Vec::new(),
+        stored_bytes: None,
+        mvt_validation: None,
+        raster_format: None,
+        raster_tiles: 0,
+        media_types: BTreeMap::new(),
+        high_cardinality_keys: Vec::new(),
+        vector_layers: Vec::new(),
+        metadata_mismatches: Vec::new(),
+        style_preview: None,
     };
 
     let report = apply_tile_info_format(report, TileInfoFormat::Compact);
+    assert!(report.tile_summaries[0].layers[0].property_keys.is_empty());
     let summary = report.tile_summary.expect("tile summary");
     assert!(summary.layers[0].property_keys.is_empty());
     assert!(
@@ -0,0 +1,266 @@
+use std::io::Write;
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use geo_types::{Coord, Geometry, LineString, MultiPoint, Point, Polygon};
+use mvt::{GeomEncoder, GeomType, Tile};
+use mvt_reader::Reader;
+use vt_optimizer::mbtiles::{
+    DEFAULT_SIMPLIFY_BUFFER, OverzoomOptions, decode_tile_payload, overzoom_geometry,
+    overzoom_mbtiles, overzoom_tile_payload,
+};
+
+const ORIGIN: Coord<f32> = Coord { x: 0.0, y: 0.0 };
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).expect("gzip");
+    encoder.finish().expect("gzip finish")
+}
+
+/// A point in the north-west quadrant and a road crossing the whole tile
+/// from west to east along its northern half.
+fn parent_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("pois");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(1000.0, 1000.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.set_id(7);
+    feature.add_tag_string("name", "station");
+    tile.add_layer(feature.into_layer()).expect("add pois");
+
+    let layer = tile.create_layer("roads");
+    let geom = GeomEncoder::new(GeomType::Linestring)
+        .point(0.0, 1000.0)
+        .expect("point")
+        .point(4096.0, 1000.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    tile.add_layer(layer.into_feature(geom).into_layer())
+        .expect("add roads");
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn create_parent_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        INSERT INTO metadata (name, value) VALUES ('minzoom', '0');
+        INSERT INTO metadata (name, value) VALUES ('maxzoom', '0');
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (gzip(&parent_tile()),),
+    )
+    .expect("tile insert");
+}
+
+fn options(target_zoom: u8) -> OverzoomOptions {
+    OverzoomOptions {
+        target_zoom,
+        buffer: DEFAULT_SIMPLIFY_BUFFER,
+        threads: 2,
+        readers: 1,
+        io_batch: 10,
+        no_progress: true,
+    }
+}
+
+fn layer_names(payload: &[u8]) -> Vec<String> {
+    let reader = Reader::new(payload.to_vec()).expect("reader");
+    reader
+        .get_layer_metadata()
+        .expect("layers")
+        .into_iter()
+        .map(|layer| layer.name)
+        .collect()
+}
+
+#[test]
+fn overzoom_geometry_scales_and_clips_lines_at_the_child_boundary() {
+    let line = Geometry::LineString(LineString::from(vec![(0.0, 100.0), (4096.0, 100.0)]));
+    // East child of a z+1 split: x offset is one extent
+    let clipped = overzoom_geometry(&line, 2.0, Coord { x: 4096.0, y: 0.0 }, -80.0, 4176.0)
+        .expect("clipped line");
+    assert_eq!(
+        clipped,
+        Geometry::LineString(LineString::from(vec![(-80.0, 200.0), (4096.0, 200.0)]))
+    );
+
+    // A line leaving and re-entering the tile is cut into two parts
+    let zigzag = Geometry::LineString(LineString::from(vec![
+        (10.0, 10.0),
+        (10.0, 5000.0),
+        (20.0, 5000.0),
+        (20.0, 10.0),
+    ]));
+    match overzoom_geometry(&zigzag, 1.0, ORIGIN, 0.0, 4096.0).expect("clipped") {
+        Geometry::MultiLineString(lines) => assert_eq!(lines.0.len(), 2),
+        other => panic!("expected two parts, got {other:?}"),
+    }
+
+    let outside = Geometry::LineString(LineString::from(vec![(3000.0, 0.0), (3000.0, 4096.0)]));
+    assert_eq!(overzoom_geometry(&outside, 2.0, ORIGIN, 0.0, 4096.0), None);
+}
+
+#[test]
+fn overzoom_geometry_drops_points_and_polygons_outside_the_child() {
+    let points = Geometry::MultiPoint(MultiPoint(vec![
+        Point::new(100.0, 100.0),
+        Point::new(3000.0, 100.0),
+    ]));
+    assert_eq!(
+        overzoom_geometry(&points, 2.0, ORIGIN, 0.0, 4096.0),
+        Some(Geometry::MultiPoint(MultiPoint(vec![Point::new(
+            200.0, 200.0
+        )])))
+    );
+    assert_eq!(
+        overzoom_geometry(&points, 2.0, Coord { x: 0.0, y: 4096.0 }, 0.0, 4096.0),
+        None
+    );
+
+    // A square straddling the child boundary is cut at the buffer edge
+    let square = Geometry::Polygon(Polygon::new(
+        LineString::from(vec![
+            (1000.0, 1000.0),
+            (3000.0, 1000.0),
+            (3000.0, 1500.0),
+            (1000.0, 1500.0),
+            (1000.0, 1000.0),
+        ]),
+        Vec::new(),
+    ));
+    let Some(Geometry::Polygon(clipped)) = overzoom_geometry(&square, 2.0, ORIGIN, -80.0, 4176.0)
+    else {
+        panic!("expected a clipped polygon");
+    };
+    assert_eq!(
+        clipped.exterior(),
+        &LineString::from(vec![
+            (2000.0, 2000.0),
+            (4176.0, 2000.0),
+            (4176.0, 3000.0),
+            (2000.0, 3000.0),
+            (2000.0, 2000.0),
+        ])
+    );
+
+    // Only a sliver thinner than a tile unit reaches the child: collapsed
+    let sliver = Geometry::Polygon(Polygon::new(
+        LineString::from(vec![
+            (0.0, 0.0),
+            (2048.1, 0.0),
+            (2048.1, 100.0),
+            (0.0, 100.0),
+            (0.0, 0.0),
+        ]),
+        Vec::new(),
+    ));
+    assert_eq!(
+        overzoom_geometry(&sliver, 2.0, Coord { x: 4096.0, y: 0.0 }, 0.0, 4096.0),
+        None
+    );
+}
+
+#[test]
+fn overzoom_tile_payload_omits_children_without_features() {
+    let children = overzoom_tile_payload(&parent_tile(), 1, 0).expect("overzoom");
+    // Only the northern children hold the road; the point is north-west
+    let coords: Vec<(u32, u32)> = children.iter().map(|child| (child.dx, child.dy)).collect();
+    assert_eq!(coords, vec![(0, 0), (1, 0)]);
+    assert_eq!(layer_names(&children[0].bytes), vec!["pois", "roads"]);
+    assert_eq!(layer_names(&children[1].bytes), vec!["roads"]);
+
+    let reader = Reader::new(children[0].bytes.clone()).expect("reader");
+    let features = reader.get_features(0).expect("features");
+    assert_eq!(features[0].id, Some(7));
+    assert_eq!(
+        features[0].get_geometry(),
+        &Geometry::MultiPoint(MultiPoint(vec![Point::new(2000.0, 2000.0)]))
+    );
+}
+
+#[test]
+fn overzoom_mbtiles_adds_children_alongside_the_originals() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_parent_mbtiles(&input);
+
+    let stats = overzoom_mbtiles(&input, &output, &options(2)).expect("overzoom");
+    assert_eq!(stats.parent_tiles, 1);
+    // The road only reaches the northern row at z1 and z2 (it stays more
+    // than the buffer away from the row below)
+    assert_eq!(stats.generated_by_zoom.get(&1), Some(&2));
+    assert_eq!(stats.generated_by_zoom.get(&2), Some(&4));
+    assert_eq!(stats.empty_children, 2 + 12);
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let mut stmt = conn
+        .prepare(
+            "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles ORDER BY zoom_level, tile_column, tile_row",
+        )
+        .expect("prepare");
+    let rows: Vec<(u8, u32, u32, Vec<u8>)> = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .expect("query")
+        .map(|row| row.expect("row"))
+        .collect();
+    let coords: Vec<(u8, u32, u32)> = rows.iter().map(|(z, x, y, _)| (*z, *x, *y)).collect();
+    // TMS rows: XYZ y=0 is row 1 at z1 and row 3 at z2
+    assert_eq!(
+        coords,
+        vec![
+            (0, 0, 0),
+            (1, 0, 1),
+            (1, 1, 1),
+            (2, 0, 3),
+            (2, 1, 3),
+            (2, 2, 3),
+            (2, 3, 3),
+        ]
+    );
+    assert_eq!(rows[0].3, gzip(&parent_tile()));
+    for (_, _, _, data) in rows.iter().skip(1) {
+        assert!(data.starts_with(&[0x1f, 0x8b]), "children stay gzipped");
+        let payload = decode_tile_payload(data).expect("gunzip");
+        assert!(layer_names(&payload).contains(&"roads".to_string()));
+    }
+
+    let maxzoom: String = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE name = 'maxzoom'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("maxzoom");
+    assert_eq!(maxzoom, "2");
+}
+
+#[test]
+fn overzoom_mbtiles_requires_a_target_above_the_max_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_parent_mbtiles(&input);
+
+    let err = overzoom_mbtiles(&input, &output, &options(0)).expect_err("same zoom");
+    assert!(
+        err.to_string()
+            .contains("must be above the tileset max zoom 0"),
+        "{err}"
+    );
+}
@@ -7,10 +7,26 @@ use brotli::CompressorWriter;
 use flate2::read::GzDecoder;
 use mvt::{GeomEncoder, GeomType, Tile};
 use mvt_reader::Reader;
-use vt_optimizer::mbtiles::{InspectOptions, inspect_mbtiles};
+use proptest::prelude::*;
+use vt_optimizer::error::VtError;
+use vt_optimizer::mbtiles::{
+    DEFAULT_GZIP_LEVEL, DedupStats, FeatureCleanup, InspectOptions, TileCompression, TileCoord,
+    TileHashAlgorithm, TileListOptions, TileRecord, TileScheme, TileSizeRange, TileSort,
+    decode_tile_payload, encode_tile_payload, inspect_mbtiles, inspect_mbtiles_with_options,
+    inspect_tile_source_with_options,
+};
 use vt_optimizer::pmtiles::{
-    inspect_pmtiles_with_options, mbtiles_to_pmtiles, pmtiles_to_mbtiles, prune_pmtiles_layer_only,
+    DirectoryIndex, Entry, InternalCompression, LocalReader, PmtilesFile, PmtilesWriteOptions,
+    PmtilesWriter, RangeReader,
+    algo::tile_id_to_xyz,
+    algo::{encode_directory, run_zoom, tile_id_zoom},
+    build_header, encode_internal_bytes, inspect_pmtiles_streaming, inspect_pmtiles_with_options,
+    mbtiles_to_pmtiles, mbtiles_to_pmtiles_with_options, pmtiles_to_mbtiles,
+    pmtiles_to_mbtiles_with_options, prune_pmtiles_layer_only,
+    prune_pmtiles_layer_only_with_threads, quick_stats_pmtiles, read_directory_section,
+    read_header, tile_count_pmtiles, tile_id_from_xyz, tiles_iter, write_header,
 };
+use vt_optimizer::source::{TileSource, read_tile};
 use vt_optimizer::style::read_style;
 
 fn create_sample_mbtiles(path: &Path) {
@@ -195,6 +211,230 @@ fn mbtiles_to_pmtiles_and_back_preserves_counts() {
     assert_eq!(report.overall.avg_bytes, 15);
 }
 
+/// One gzip tile with a single tiny feature and one with three featureless
+/// layers; both are at most 80 stored bytes.
+fn create_small_tiles_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("poi");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(1.0, 2.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    tile.add_layer(layer.into_feature(geom).into_layer())
+        .expect("add poi");
+    let small = encode_tile_payload(&tile.to_bytes().expect("tile bytes"), true, 9).expect("gzip");
+
+    let mut tile = Tile::new(4096);
+    for name in ["water", "landuse", "buildings"] {
+        tile.add_layer(tile.create_layer(name)).expect("add layer");
+    }
+    let empty = encode_tile_payload(&tile.to_bytes().expect("tile bytes"), true, 9).expect("gzip");
+    assert!(small.len() <= 80 && empty.len() <= 80);
+
+    for (column, data) in [(0, small), (1, empty)] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, ?1, 0, ?2)",
+            (column, data),
+        )
+        .expect("tile insert");
+    }
+}
+
+#[test]
+fn inspect_empty_exact_separates_empty_from_small_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_small_tiles_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let by_size = InspectOptions {
+        empty_tile_max_bytes: 80,
+        no_progress: true,
+        ..InspectOptions::default()
+    };
+    let exact = InspectOptions {
+        empty_exact: true,
+        ..by_size.clone()
+    };
+    for report in [
+        inspect_mbtiles_with_options(&input, by_size.clone()).expect("inspect mbtiles"),
+        inspect_pmtiles_with_options(&pmtiles, &by_size).expect("inspect pmtiles"),
+    ] {
+        assert_eq!(report.empty_tiles, 2);
+        assert_eq!(report.small_tiles, None);
+    }
+    for report in [
+        inspect_mbtiles_with_options(&input, exact.clone()).expect("inspect mbtiles"),
+        inspect_pmtiles_with_options(&pmtiles, &exact).expect("inspect pmtiles"),
+    ] {
+        assert_eq!(report.empty_tiles, 1);
+        assert_eq!(report.small_tiles, Some(1));
+        assert_eq!(report.empty_tile_max_bytes, 80);
+    }
+}
+
+#[test]
+fn inspect_pmtiles_histogram_excludes_empty_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let report = inspect_pmtiles_with_options(
+        &pmtiles,
+        &InspectOptions {
+            histogram_buckets: 2,
+            empty_tile_max_bytes: 15,
+            histogram_exclude_empty: true,
+            strict: false,
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect pmtiles");
+
+    assert_eq!(report.empty_tiles, 1);
+    assert_eq!(report.histogram[0].min_bytes, 20);
+    assert_eq!(report.histogram.iter().map(|b| b.count).sum::<u64>(), 1);
+    assert!((report.histogram[0].pct_tiles - 1.0).abs() < 1e-6);
+    let zooms: Vec<u8> = report.histograms_by_zoom.iter().map(|h| h.zoom).collect();
+    assert_eq!(zooms, vec![1]);
+}
+
+#[test]
+fn mbtiles_to_pmtiles_compresses_directory_and_metadata() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&input);
+
+    for (compression, code) in [
+        (InternalCompression::Brotli, 2u8),
+        (InternalCompression::Gzip, 1),
+        (InternalCompression::None, 0),
+    ] {
+        let pmtiles = dir.path().join(format!("output-{code}.pmtiles"));
+        mbtiles_to_pmtiles_with_options(
+            &input,
+            &pmtiles,
+            compression,
+            None,
+            TileScheme::Tms,
+            PmtilesWriteOptions::default(),
+        )
+        .expect("mbtiles->pmtiles");
+
+        let mut file = File::open(&pmtiles).expect("open pmtiles");
+        let header = read_header(&file).expect("header");
+        assert_eq!(header.internal_compression, code);
+        let mut root = vec![0u8; header.root_length as usize];
+        file.seek(SeekFrom::Start(header.root_offset))
+            .expect("seek root");
+        file.read_exact(&mut root).expect("read root");
+        assert_eq!(root.starts_with(&[0x1f, 0x8b]), code == 1);
+
+        let report = inspect_pmtiles_with_options(&pmtiles, &InspectOptions::default())
+            .expect("inspect pmtiles");
+        assert_eq!(report.overall.tile_count, 2);
+        assert_eq!(
+            report.metadata.get("name").map(String::as_str),
+            Some("sample")
+        );
+    }
+}
+
+fn read_mbtiles_tiles(path: &Path) -> Vec<(i64, i64, i64, Vec<u8>)> {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    let mut stmt = conn
+        .prepare(
+            "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles ORDER BY zoom_level, tile_column, tile_row",
+        )
+        .expect("prepare");
+    stmt.query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })
+    .expect("query")
+    .map(|row| row.expect("row"))
+    .collect()
+}
+
+#[test]
+fn mbtiles_pmtiles_roundtrip_preserves_tiles_for_each_internal_compression() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&input);
+    {
+        let conn = rusqlite::Connection::open(&input).expect("open");
+        for (z, x, y, byte) in [(2, 1, 3, 3u8), (2, 2, 3, 3), (3, 5, 4, 4)] {
+            conn.execute(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                (z, x, y, vec![byte; 12]),
+            )
+            .expect("tile");
+        }
+    }
+    let expected = read_mbtiles_tiles(&input);
+
+    for compression in [
+        InternalCompression::None,
+        InternalCompression::Gzip,
+        InternalCompression::Brotli,
+    ] {
+        let pmtiles = dir.path().join(format!("{compression:?}.pmtiles"));
+        let output = dir.path().join(format!("{compression:?}.mbtiles"));
+        mbtiles_to_pmtiles_with_options(
+            &input,
+            &pmtiles,
+            compression,
+            None,
+            TileScheme::Tms,
+            PmtilesWriteOptions::default(),
+        )
+        .expect("mbtiles->pmtiles");
+        pmtiles_to_mbtiles(&pmtiles, &output).expect("pmtiles->mbtiles");
+
+        assert_eq!(read_mbtiles_tiles(&output), expected, "{compression:?}");
+        let conn = rusqlite::Connection::open(&output).expect("open output");
+        let name: String = conn
+            .query_row(
+                "SELECT value FROM metadata WHERE name = 'name'",
+                [],
+                |row| row.get(0),
+            )
+            .expect("metadata");
+        assert_eq!(name, "sample");
+    }
+}
+
+#[test]
+fn mbtiles_to_pmtiles_defaults_to_brotli_internal_compression() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    let file = File::open(&pmtiles).expect("open pmtiles");
+    let header = read_header(&file).expect("header");
+    assert_eq!(header.internal_compression, 2);
+}
+
 #[test]
 fn inspect_pmtiles_reads_metadata() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -261,8 +501,17 @@ fn prune_pmtiles_removes_unlisted_layers() {
     .expect("write style");
     let style = read_style(&style_path).expect("read style");
 
-    prune_pmtiles_layer_only(&input_pmtiles, &output_pmtiles, &style, false, true)
-        .expect("prune pmtiles");
+    prune_pmtiles_layer_only(
+        &input_pmtiles,
+        &output_pmtiles,
+        &style,
+        false,
+        true,
+        false,
+        DEFAULT_GZIP_LEVEL,
+        &FeatureCleanup::default(),
+    )
+    .expect("prune pmtiles");
 
     pmtiles_to_mbtiles(&output_pmtiles, &output_mbtiles).expect("pmtiles->mbtiles");
     let conn = rusqlite::Connection::open(&output_mbtiles).expect("open output");
@@ -287,6 +536,76 @@ fn prune_pmtiles_removes_unlisted_layers() {
     assert_eq!(layers[0].name, "roads");
 }
 
+#[test]
+fn prune_pmtiles_threads_produce_identical_output() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let style_path = dir.path().join("style.json");
+
+    create_layer_mbtiles(&input_mbtiles);
+    let conn = rusqlite::Connection::open(&input_mbtiles).expect("open");
+    for (z, x, y) in [(1, 0, 1), (1, 1, 0), (2, 3, 2), (3, 5, 6)] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            (z, x, y, create_layer_tile()),
+        )
+        .expect("tile insert");
+    }
+    drop(conn);
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+
+    let mut outputs = Vec::new();
+    for threads in [1, 4] {
+        let output = dir.path().join(format!("output-{threads}.pmtiles"));
+        let stats = prune_pmtiles_layer_only_with_threads(
+            &input_pmtiles,
+            &output,
+            &style,
+            false,
+            true,
+            false,
+            DEFAULT_GZIP_LEVEL,
+            None,
+            &FeatureCleanup::default(),
+            threads,
+            true,
+            false,
+            None,
+            &Default::default(),
+            false,
+            PmtilesWriteOptions::default(),
+        )
+        .expect("prune pmtiles");
+        assert_eq!(stats.removed_features_by_zoom.values().sum::<u64>(), 5);
+        let tiles = tiles_iter(&output)
+            .expect("tiles iter")
+            .map(|tile| {
+                let (coord, data) = tile.expect("read tile");
+                let payload = decode_tile_payload(&data).expect("decode payload");
+                let reader = Reader::new(payload).expect("decode");
+                let layers = reader
+                    .get_layer_metadata()
+                    .expect("layers")
+                    .into_iter()
+                    .map(|layer| layer.name)
+                    .collect::<Vec<_>>();
+                (coord.zoom, coord.x, coord.y, layers)
+            })
+            .collect::<Vec<_>>();
+        outputs.push(tiles);
+    }
+    assert_eq!(outputs[0].len(), 5);
+    assert_eq!(outputs[0], outputs[1]);
+}
+
 #[test]
 fn prune_pmtiles_preserves_tile_compression() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -305,8 +624,17 @@ fn prune_pmtiles_preserves_tile_compression() {
     .expect("write style");
     let style = read_style(&style_path).expect("read style");
 
-    prune_pmtiles_layer_only(&input_pmtiles, &output_pmtiles, &style, false, true)
-        .expect("prune pmtiles");
+    prune_pmtiles_layer_only(
+        &input_pmtiles,
+        &output_pmtiles,
+        &style,
+        false,
+        true,
+        false,
+        DEFAULT_GZIP_LEVEL,
+        &FeatureCleanup::default(),
+    )
+    .expect("prune pmtiles");
 
     let input_tile_compression =
         read_tile_compression(&input_pmtiles).expect("read input compression");
@@ -315,6 +643,282 @@ fn prune_pmtiles_preserves_tile_compression() {
     assert_eq!(input_tile_compression, output_tile_compression);
 }
 
+#[test]
+fn prune_pmtiles_recompress_sets_header_tile_compression() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let output_pmtiles = dir.path().join("output.pmtiles");
+    let style_path = dir.path().join("style.json");
+
+    create_layer_mbtiles(&input_mbtiles);
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+
+    let stats = prune_pmtiles_layer_only_with_threads(
+        &input_pmtiles,
+        &output_pmtiles,
+        &style,
+        false,
+        true,
+        false,
+        DEFAULT_GZIP_LEVEL,
+        Some(TileCompression::Brotli(11)),
+        &FeatureCleanup::default(),
+        2,
+        true,
+        false,
+        None,
+        &Default::default(),
+        false,
+        PmtilesWriteOptions::default(),
+    )
+    .expect("prune pmtiles");
+    assert_eq!(stats.recompress.expect("recompress stats").tiles, 1);
+    assert_eq!(
+        read_tile_compression(&output_pmtiles).expect("compression"),
+        2
+    );
+
+    let options = InspectOptions {
+        include_layer_list: true,
+        no_progress: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&output_pmtiles, &options).expect("inspect");
+    let layers = report
+        .file_layers
+        .iter()
+        .map(|layer| layer.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(layers, vec!["roads"]);
+}
+
+#[test]
+fn copy_transcodes_gzip_mbtiles_to_brotli_pmtiles_and_back() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&input);
+    let raw = create_layer_tile();
+    let gzipped = encode_tile_payload(&raw, true, DEFAULT_GZIP_LEVEL).expect("gzip");
+    rusqlite::Connection::open(&input)
+        .expect("open")
+        .execute("UPDATE tiles SET tile_data = ?1", (gzipped.clone(),))
+        .expect("gzip tiles");
+
+    let pmtiles = dir.path().join("output.pmtiles");
+    let stats = mbtiles_to_pmtiles_with_options(
+        &input,
+        &pmtiles,
+        InternalCompression::default(),
+        Some(TileCompression::Brotli(9)),
+        TileScheme::Tms,
+        PmtilesWriteOptions::default(),
+    )
+    .expect("mbtiles->pmtiles");
+    assert_eq!(stats.tiles, 1);
+    assert_eq!(stats.input_bytes, gzipped.len() as u64);
+    assert_eq!(read_tile_compression(&pmtiles).expect("compression"), 2);
+
+    let output = dir.path().join("output.mbtiles");
+    let stats = pmtiles_to_mbtiles_with_options(
+        &pmtiles,
+        &output,
+        Some(TileCompression::Gzip(9)),
+        TileScheme::Tms,
+    )
+    .expect("pmtiles->mbtiles");
+    assert_eq!(stats.tiles, 1);
+    let tiles = read_mbtiles_tiles(&output);
+    assert!(tiles[0].3.starts_with(&[0x1f, 0x8b]));
+    assert_eq!(decode_tile_payload(&tiles[0].3).expect("decode"), raw);
+}
+
+#[test]
+fn copy_applies_brotli_quality_and_window() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&input);
+    let raw = create_layer_tile();
+
+    let pmtiles = dir.path().join("output.pmtiles");
+    let write_options = PmtilesWriteOptions {
+        brotli_quality: 11,
+        brotli_window: 24,
+    };
+    mbtiles_to_pmtiles_with_options(
+        &input,
+        &pmtiles,
+        InternalCompression::Brotli,
+        Some(TileCompression::Brotli(11)),
+        TileScheme::Tms,
+        write_options,
+    )
+    .expect("mbtiles->pmtiles");
+    let output = dir.path().join("output.mbtiles");
+    pmtiles_to_mbtiles_with_options(
+        &pmtiles,
+        &output,
+        Some(TileCompression::None),
+        TileScheme::Tms,
+    )
+    .expect("pmtiles->mbtiles");
+    assert_eq!(read_mbtiles_tiles(&output)[0].3, raw);
+
+    for (brotli_quality, brotli_window, message) in [
+        (5, 9, "brotli window"),
+        (5, 25, "brotli window"),
+        (12, 22, "brotli quality"),
+    ] {
+        let err = mbtiles_to_pmtiles_with_options(
+            &input,
+            &dir.path().join("invalid.pmtiles"),
+            InternalCompression::Brotli,
+            None,
+            TileScheme::Tms,
+            PmtilesWriteOptions {
+                brotli_quality,
+                brotli_window,
+            },
+        )
+        .expect_err("invalid brotli options");
+        assert!(err.to_string().contains(message), "{err}");
+    }
+}
+
+#[test]
+fn inspect_pmtiles_measures_uncompressed_brotli_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&input);
+    let raw = create_layer_tile();
+    let pmtiles = dir.path().join("output.pmtiles");
+    let stats = mbtiles_to_pmtiles_with_options(
+        &input,
+        &pmtiles,
+        InternalCompression::default(),
+        Some(TileCompression::Brotli(9)),
+        TileScheme::Tms,
+        PmtilesWriteOptions::default(),
+    )
+    .expect("mbtiles->pmtiles");
+
+    let options = InspectOptions {
+        no_progress: true,
+        uncompressed_sizes: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect");
+    assert_eq!(report.overall.total_bytes, raw.len() as u64);
+    assert_eq!(report.stored_bytes, Some(stats.output_bytes));
+}
+
+#[test]
+fn inspect_hash_matches_between_mbtiles_and_recompressed_pmtiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    let gzipped =
+        encode_tile_payload(&create_layer_tile(), true, DEFAULT_GZIP_LEVEL).expect("gzip");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 0, 0, ?1)",
+        (gzipped,),
+    )
+    .expect("tile insert");
+    drop(conn);
+    let pmtiles = dir.path().join("output.pmtiles");
+    mbtiles_to_pmtiles_with_options(
+        &input,
+        &pmtiles,
+        InternalCompression::default(),
+        Some(TileCompression::Brotli(9)),
+        TileScheme::Tms,
+        PmtilesWriteOptions::default(),
+    )
+    .expect("mbtiles->pmtiles");
+
+    let options = InspectOptions {
+        no_progress: true,
+        topn: 10,
+        hash: Some(TileHashAlgorithm::Sha256),
+        ..InspectOptions::default()
+    };
+    let mbtiles_report = inspect_mbtiles_with_options(&input, options.clone()).expect("mbtiles");
+    let pmtiles_report = inspect_pmtiles_with_options(&pmtiles, &options).expect("pmtiles");
+    let mbtiles_hash = mbtiles_report.content_hash.expect("mbtiles hash");
+    let pmtiles_hash = pmtiles_report.content_hash.expect("pmtiles hash");
+    assert_eq!(mbtiles_hash, pmtiles_hash);
+    assert_eq!(mbtiles_hash.algorithm, "sha256");
+    assert_eq!(mbtiles_hash.tiles, mbtiles_report.overall.tile_count);
+    assert_eq!(mbtiles_hash.digest.len(), 64);
+    let tile_hash = mbtiles_report.top_tiles[0]
+        .hash
+        .as_deref()
+        .expect("tile hash");
+    assert_eq!(
+        tile_hash,
+        TileHashAlgorithm::Sha256.hex_digest(&create_layer_tile())
+    );
+    assert_eq!(pmtiles_report.top_tiles[0].hash.as_deref(), Some(tile_hash));
+
+    let options = InspectOptions {
+        no_progress: true,
+        hash: Some(TileHashAlgorithm::Xxh3),
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&input, options).expect("xxh3");
+    let xxh3 = report.content_hash.expect("xxh3 hash");
+    assert_eq!(xxh3.algorithm, "xxh3");
+    assert_eq!(xxh3.digest.len(), 32);
+}
+
+#[test]
+fn mbtiles_pmtiles_conversion_flips_tms_rows() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&input);
+
+    let pmtiles = dir.path().join("output.pmtiles");
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    let source = PmtilesFile::open(&pmtiles).expect("open pmtiles");
+    let xyz = TileCoord {
+        zoom: 1,
+        x: 1,
+        y: 0,
+    };
+    assert_eq!(source.tile_data(xyz).expect("read"), Some(vec![2u8; 20]));
+
+    let output = dir.path().join("output.mbtiles");
+    pmtiles_to_mbtiles(&pmtiles, &output).expect("pmtiles->mbtiles");
+    let tiles = read_mbtiles_tiles(&output);
+    assert!(tiles.contains(&(1, 1, 1, vec![2u8; 20])));
+    assert!(tiles.contains(&(0, 0, 0, vec![1u8; 10])));
+
+    let xyz_pmtiles = dir.path().join("xyz.pmtiles");
+    mbtiles_to_pmtiles_with_options(
+        &input,
+        &xyz_pmtiles,
+        InternalCompression::default(),
+        None,
+        TileScheme::Xyz,
+        PmtilesWriteOptions::default(),
+    )
+    .expect("mbtiles->pmtiles xyz");
+    let source = PmtilesFile::open(&xyz_pmtiles).expect("open pmtiles");
+    let coord = TileCoord {
+        zoom: 1,
+        x: 1,
+        y: 1,
+    };
+    assert_eq!(source.tile_data(coord).expect("read"), Some(vec![2u8; 20]));
+}
+
 fn read_tile_compression(path: &Path) -> std::io::Result<u8> {
     const HEADER_SIZE: usize = 127;
     const MAGIC: &[u8; 7] = b"PMTiles";
@@ -398,46 +1002,304 @@ fn inspect_pmtiles_counts_tiles_by_zoom() {
 }
 
 #[test]
-fn inspect_pmtiles_builds_histograms_by_zoom() {
+fn pmtiles_conversion_accepts_paths_without_tile_extension() {
     let dir = tempfile::tempdir().expect("tempdir");
     let input = dir.path().join("input.mbtiles");
-    let pmtiles = dir.path().join("output.pmtiles");
+    let pmtiles = dir.path().join("archive.bin");
     create_sample_mbtiles(&input);
 
     mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
-    let options = InspectOptions {
-        histogram_buckets: 3,
-        ..Default::default()
-    };
-    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
-
-    assert_eq!(report.histograms_by_zoom.len(), 2);
-    let z0 = report
-        .histograms_by_zoom
-        .iter()
-        .find(|entry| entry.zoom == 0)
-        .expect("z0 histogram");
-    assert_eq!(z0.buckets.len(), 3);
-    assert_eq!(z0.buckets[0].count, 1);
-    assert_eq!(z0.buckets[0].total_bytes, 10);
+    let report = inspect_pmtiles_with_options(&pmtiles, &InspectOptions::default())
+        .expect("inspect pmtiles");
+    assert_eq!(report.overall.tile_count, 2);
 
-    let z1 = report
-        .histograms_by_zoom
-        .iter()
-        .find(|entry| entry.zoom == 1)
-        .expect("z1 histogram");
-    assert_eq!(z1.buckets.len(), 3);
-    assert_eq!(z1.buckets[0].count, 1);
-    assert_eq!(z1.buckets[0].total_bytes, 20);
+    let err = inspect_pmtiles_with_options(&input, &InspectOptions::default())
+        .expect_err("mbtiles extension");
+    assert!(err.to_string().contains("pmtiles"));
 }
 
 #[test]
-fn inspect_pmtiles_collects_top_tiles() {
+fn inspect_pmtiles_recommend_summarizes_top_tiles() {
     let dir = tempfile::tempdir().expect("tempdir");
     let input = dir.path().join("input.mbtiles");
     let pmtiles = dir.path().join("output.pmtiles");
-    create_sample_mbtiles(&input);
-
+    create_layer_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let options = InspectOptions {
+        topn: 1,
+        histogram_buckets: 2,
+        no_progress: true,
+        zoom: Some(0),
+        recommend: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+
+    assert_eq!(report.top_tile_summaries.len(), 1);
+    let summary = &report.top_tile_summaries[0];
+    assert_eq!(summary.layer_count, 2);
+    assert!(summary.layers.iter().all(|layer| layer.bytes > 0));
+    assert!(!report.recommendations.is_empty());
+    assert!(report.recommendations.iter().all(|item| item.zoom == 0));
+}
+
+#[test]
+fn inspect_pmtiles_streaming_emits_tiles_from_directory() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let options = InspectOptions {
+        no_progress: true,
+        max_tile_bytes: 15,
+        list_tiles: Some(TileListOptions {
+            limit: 0,
+            sort: TileSort::Size,
+            size_range: None,
+        }),
+        ..InspectOptions::default()
+    };
+    let records = std::sync::Mutex::new(Vec::new());
+    let sink = |record: TileRecord| {
+        records.lock().unwrap().push(record);
+        Ok(())
+    };
+    let report = inspect_pmtiles_streaming(&pmtiles, &options, &sink).expect("inspect");
+
+    let records = records.into_inner().unwrap();
+    assert_eq!(
+        records
+            .iter()
+            .map(|record| (record.zoom, record.bytes, record.over_limit))
+            .collect::<Vec<_>>(),
+        vec![(0, 10, false), (1, 20, true)]
+    );
+    assert!(report.bucket_tiles.is_empty());
+}
+
+#[test]
+fn inspect_pmtiles_lists_tiles_in_size_range() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let options = InspectOptions {
+        no_progress: true,
+        list_tiles: Some(TileListOptions {
+            limit: 10,
+            sort: TileSort::Size,
+            size_range: Some(TileSizeRange::new(Some(15), None).expect("range")),
+        }),
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect");
+    assert_eq!(
+        report
+            .bucket_tiles
+            .iter()
+            .map(|tile| (tile.zoom, tile.bytes))
+            .collect::<Vec<_>>(),
+        vec![(1, 20)]
+    );
+}
+
+#[test]
+fn inspect_pmtiles_percentiles_from_directory_lengths() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    let options = InspectOptions {
+        no_progress: true,
+        percentiles: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+
+    let overall = report.overall.percentiles.expect("overall percentiles");
+    assert_eq!((overall.p50, overall.p99), (10, 20));
+    let z1 = report
+        .by_zoom
+        .iter()
+        .find(|entry| entry.zoom == 1)
+        .and_then(|entry| entry.stats.percentiles)
+        .expect("z1 percentiles");
+    assert_eq!((z1.p50, z1.p90, z1.p95, z1.p99), (20, 20, 20, 20));
+}
+
+#[test]
+fn inspect_pmtiles_dedup_stats_from_entry_offsets() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    let options = InspectOptions {
+        no_progress: true,
+        dedup_stats: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+
+    let dedup = report.dedup.expect("dedup report");
+    assert_eq!(dedup.overall.unique_tiles, 2);
+    assert_eq!(dedup.overall.duplicate_tiles, 0);
+    assert_eq!(dedup.by_zoom.len(), 2);
+}
+
+#[test]
+fn inspect_pmtiles_dedup_stats_counts_shared_offsets_without_hashing() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("shared.pmtiles");
+    // Tiles 0-1 share offset 0 and tiles 2, 4 and 5 offset 10; tile 3 holds
+    // the same bytes as tile 0 but is only compared by offset
+    let entries = [
+        (0, 0, 10, 1),
+        (1, 0, 10, 1),
+        (2, 10, 20, 1),
+        (3, 30, 10, 1),
+        (4, 10, 20, 2),
+    ]
+    .map(|(tile_id, offset, length, run_length)| Entry {
+        tile_id,
+        offset,
+        length,
+        run_length,
+    });
+    let data = [vec![1u8; 10], vec![2u8; 20], vec![1u8; 10]].concat();
+    let root = encode_directory(&entries).expect("encode root");
+    let header = build_header(root.len() as u64, data.len() as u64, 6, 0, 2);
+    let mut file = File::create(&path).expect("create pmtiles");
+    write_header(&file, &header).expect("write header");
+    file.seek(SeekFrom::Start(header.root_offset))
+        .expect("seek root");
+    file.write_all(&root).expect("write root");
+    file.write_all(&data).expect("write data");
+    drop(file);
+
+    let options = InspectOptions {
+        no_progress: true,
+        dedup_stats: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&path, &options).expect("inspect pmtiles");
+    assert_eq!(
+        report.dedup.expect("dedup report").overall,
+        DedupStats {
+            unique_tiles: 3,
+            duplicate_tiles: 3,
+            reclaimable_bytes: 0,
+        }
+    );
+}
+
+#[test]
+fn tiles_iter_walks_pmtiles_directory() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let tiles = tiles_iter(&pmtiles)
+        .expect("tiles iter")
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("read tiles");
+    assert_eq!(tiles.len(), 2);
+    assert_eq!(
+        tiles[0].0,
+        TileCoord {
+            zoom: 0,
+            x: 0,
+            y: 0
+        }
+    );
+    assert_eq!(tiles[0].1, vec![1u8; 10]);
+    assert_eq!(tiles[1].0.zoom, 1);
+    assert_eq!(tiles[1].1, vec![2u8; 20]);
+}
+
+#[test]
+fn pmtiles_file_implements_tile_source() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let source = PmtilesFile::open(&pmtiles).expect("open pmtiles");
+    let counts = source.zoom_counts().expect("zoom counts");
+    assert_eq!(counts.get(&0), Some(&1));
+    assert_eq!(counts.get(&1), Some(&1));
+
+    let data = source
+        .tile_data(TileCoord {
+            zoom: 0,
+            x: 0,
+            y: 0,
+        })
+        .expect("tile data");
+    assert_eq!(data.map(|data| data.len()), Some(10));
+
+    let options = InspectOptions {
+        no_progress: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_tile_source_with_options(&source, options).expect("inspect source");
+    assert_eq!(report.overall.tile_count, 2);
+    assert_eq!(report.overall.total_bytes, 30);
+    assert_eq!(report.by_zoom.len(), 2);
+}
+
+#[test]
+fn inspect_pmtiles_builds_histograms_by_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    let options = InspectOptions {
+        histogram_buckets: 3,
+        ..Default::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+
+    assert_eq!(report.histograms_by_zoom.len(), 2);
+    let z0 = report
+        .histograms_by_zoom
+        .iter()
+        .find(|entry| entry.zoom == 0)
+        .expect("z0 histogram");
+    assert_eq!(z0.buckets.len(), 3);
+    assert_eq!(z0.buckets[0].count, 1);
+    assert_eq!(z0.buckets[0].total_bytes, 10);
+
+    let z1 = report
+        .histograms_by_zoom
+        .iter()
+        .find(|entry| entry.zoom == 1)
+        .expect("z1 histogram");
+    assert_eq!(z1.buckets.len(), 3);
+    assert_eq!(z1.buckets[0].count, 1);
+    assert_eq!(z1.buckets[0].total_bytes, 20);
+}
+
+#[test]
+fn inspect_pmtiles_collects_top_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+
     mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
     let options = InspectOptions {
         topn: 1,
@@ -449,7 +1311,8 @@ fn inspect_pmtiles_collects_top_tiles() {
     let tile = &report.top_tiles[0];
     assert_eq!(tile.zoom, 1);
     assert_eq!(tile.x, 1);
-    assert_eq!(tile.y, 1);
+    // TMS row 1 at zoom 1 is XYZ row 0
+    assert_eq!(tile.y, 0);
     assert_eq!(tile.bytes, 20);
 }
 
@@ -482,3 +1345,595 @@ fn inspect_pmtiles_collects_layer_list() {
     assert_eq!(layers[1].property_key_count, 2);
     assert_eq!(layers[1].property_value_count, 2);
 }
+
+#[test]
+fn inspect_pmtiles_builds_layer_zoom_matrix() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_layer_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 1, 1, ?1)",
+        (create_layer_tile(),),
+    )
+    .expect("tile insert");
+    drop(conn);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let options = InspectOptions {
+        no_progress: true,
+        layer_zoom_matrix: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect pmtiles");
+    assert_eq!(
+        report.layer_zoom_matrix,
+        vec![
+            ("buildings".to_string(), vec![(0, 1), (1, 1)]),
+            ("roads".to_string(), vec![(0, 1), (1, 1)]),
+        ]
+    );
+    assert!(report.file_layers.is_empty());
+}
+
+#[test]
+fn tile_count_pmtiles_uses_header_or_zoom_walk() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let output = dir.path().join("counted.pmtiles");
+    let mut writer = PmtilesWriter::create(&output).expect("create writer");
+    for (z, x, y) in [(0, 0, 0), (1, 0, 0), (1, 1, 1), (2, 3, 1)] {
+        writer
+            .add_tile(tile_id_from_xyz(z, x, y).expect("tile id"), &[z; 8])
+            .expect("add tile");
+    }
+    writer
+        .finish(serde_json::Map::new(), 0, 0, 1)
+        .expect("finish");
+
+    assert_eq!(tile_count_pmtiles(&output, None).expect("count"), 4);
+    assert_eq!(tile_count_pmtiles(&output, Some(1)).expect("count z1"), 2);
+    assert_eq!(tile_count_pmtiles(&output, Some(5)).expect("count z5"), 0);
+}
+
+#[test]
+fn pmtiles_writer_streams_tiles_added_out_of_order() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let output = dir.path().join("streamed.pmtiles");
+
+    let mut writer = PmtilesWriter::create(&output).expect("create writer");
+    for (z, x, y, byte) in [(2, 3, 1, 3u8), (0, 0, 0, 1u8), (1, 1, 0, 2u8)] {
+        writer
+            .add_tile(
+                tile_id_from_xyz(z, x, y).expect("tile id"),
+                &vec![byte; 4 + byte as usize],
+            )
+            .expect("add tile");
+    }
+    assert_eq!(writer.tile_count(), 3);
+    let mut metadata = serde_json::Map::new();
+    metadata.insert("name".to_string(), serde_json::Value::from("streamed"));
+    writer.finish(metadata, 0, 0, 1).expect("finish");
+
+    let header = read_header(&File::open(&output).expect("open")).expect("header");
+    assert_eq!((header.min_zoom, header.max_zoom), (0, 2));
+    assert_eq!(header.n_addressed_tiles, 3);
+    let source = PmtilesFile::open(&output).expect("open pmtiles");
+    assert_eq!(
+        source
+            .metadata()
+            .expect("metadata")
+            .get("name")
+            .map(String::as_str),
+        Some("streamed")
+    );
+    assert_eq!(
+        source
+            .tile_data(TileCoord {
+                zoom: 2,
+                x: 3,
+                y: 1
+            })
+            .expect("read")
+            .expect("tile"),
+        vec![3u8; 7]
+    );
+    assert_eq!(
+        source
+            .tile_data(TileCoord {
+                zoom: 0,
+                x: 0,
+                y: 0
+            })
+            .expect("read")
+            .expect("tile"),
+        vec![1u8; 5]
+    );
+}
+
+#[test]
+fn inspect_pmtiles_reports_raster_tileset() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("raster.mbtiles");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        INSERT INTO metadata (name, value) VALUES ('format', 'png');
+        ",
+    )
+    .expect("schema");
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.resize(64, 0);
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (png.clone(),),
+    )
+    .expect("tile");
+    drop(conn);
+    let pmtiles = dir.path().join("raster.pmtiles");
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("convert");
+
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_pmtiles_with_options(&pmtiles, &options).expect("inspect");
+    assert_eq!(
+        report.raster_format.map(|format| format.as_str()),
+        Some("png")
+    );
+    assert_eq!(report.overall.tile_count, 1);
+    assert_eq!(report.raster_tiles, 1);
+    assert!(report.file_layers.is_empty());
+
+    let output = dir.path().join("output.pmtiles");
+    let style = dir.path().join("style.json");
+    fs::write(&style, r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"}]}"#).expect("write style");
+    let style = read_style(&style).expect("read style");
+    let stats = prune_pmtiles_layer_only(
+        &pmtiles,
+        &output,
+        &style,
+        false,
+        true,
+        false,
+        DEFAULT_GZIP_LEVEL,
+        &FeatureCleanup::default(),
+    )
+    .expect("prune raster");
+    assert_eq!(stats.raster_tiles, 1);
+    let pruned = PmtilesFile::open(&output).expect("open output");
+    let tile = pruned
+        .tile_data(TileCoord {
+            zoom: 0,
+            x: 0,
+            y: 0,
+        })
+        .expect("read")
+        .expect("tile");
+    assert_eq!(tile, png);
+}
+
+#[test]
+fn pmtiles_open_explains_wrong_magic() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("tiles.pmtiles");
+    create_sample_mbtiles(&mbtiles);
+    let Err(err) = PmtilesFile::open(&mbtiles) else {
+        panic!("sqlite file is not pmtiles");
+    };
+    let message = format!("{err:#}");
+    assert!(
+        message.contains("first bytes [53 51 4c 69 74 65 20]"),
+        "{message}"
+    );
+    assert!(message.contains("--input-format mbtiles"), "{message}");
+
+    let gzipped = dir.path().join("gzipped.pmtiles");
+    fs::write(&gzipped, [0x1f, 0x8b, 0x08, 0x00]).expect("write");
+    let Err(err) = PmtilesFile::open(&gzipped) else {
+        panic!("gzip file is not pmtiles");
+    };
+    let message = format!("{err:#}");
+    assert!(message.contains("file size 4 bytes"), "{message}");
+    assert!(message.contains("gzip-compressed"), "{message}");
+
+    let err = inspect_pmtiles_with_options(&gzipped, &InspectOptions::default())
+        .expect_err("not pmtiles");
+    assert!(matches!(err, VtError::NotPmtiles { .. }), "{err:?}");
+}
+
+#[test]
+fn local_reader_rejects_offsets_beyond_the_file() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("input.pmtiles");
+    create_layer_mbtiles(&mbtiles);
+    mbtiles_to_pmtiles(&mbtiles, &pmtiles).expect("mbtiles->pmtiles");
+
+    let reader = LocalReader::open(&pmtiles).expect("open");
+    let size = fs::metadata(&pmtiles).expect("metadata").len();
+    assert_eq!(reader.is_mapped(), cfg!(feature = "mmap"));
+    assert_eq!(reader.read_range(0, 7).expect("magic"), b"PMTiles");
+    assert!(reader.read_range(size - 1, 2).is_err());
+    assert!(reader.read_range(u64::MAX, 1).is_err());
+
+    // Root directory length far past the end of the file
+    let corrupt_root = dir.path().join("corrupt-root.pmtiles");
+    let mut bytes = fs::read(&pmtiles).expect("read");
+    bytes[16..24].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    fs::write(&corrupt_root, &bytes).expect("write");
+    let err = inspect_pmtiles_with_options(&corrupt_root, &InspectOptions::default())
+        .expect_err("corrupt root length");
+    assert!(
+        format!("{err:#}").contains("beyond the end of the archive"),
+        "{err:#}"
+    );
+
+    // Tile data offset past the end of the file
+    let corrupt_data = dir.path().join("corrupt-data.pmtiles");
+    let mut bytes = fs::read(&pmtiles).expect("read");
+    bytes[56..64].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    fs::write(&corrupt_data, &bytes).expect("write");
+    let options = InspectOptions {
+        include_layer_list: true,
+        no_progress: true,
+        ..InspectOptions::default()
+    };
+    let err = inspect_pmtiles_with_options(&corrupt_data, &options).expect_err("corrupt data");
+    assert!(
+        format!("{err:#}").contains("beyond the end of the archive"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn quick_stats_pmtiles_sums_directory_entries() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &output).expect("convert");
+
+    let stats = quick_stats_pmtiles(&output, None).expect("quick stats");
+    assert_eq!(
+        (
+            stats.tile_count,
+            stats.total_bytes,
+            stats.max_bytes,
+            stats.avg_bytes
+        ),
+        (2, 30, 20, 15)
+    );
+    let report =
+        inspect_pmtiles_with_options(&output, &InspectOptions::default()).expect("inspect");
+    assert_eq!(stats, report.overall);
+    let zoom1 = quick_stats_pmtiles(&output, Some(1)).expect("zoom 1");
+    assert_eq!((zoom1.tile_count, zoom1.total_bytes), (1, 20));
+}
+
+#[test]
+fn pmtiles_writer_moves_large_directories_into_leaves() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let output = dir.path().join("leaves.pmtiles");
+    let mut writer = PmtilesWriter::create(&output).expect("create writer");
+    // Uncompressed directories with gaps between ids and varying lengths
+    // give a root far beyond 16 KiB
+    let base = tile_id_from_xyz(8, 0, 0).expect("base id");
+    let tiles: Vec<(u64, Vec<u8>)> = (0..20_000u64)
+        .map(|idx| {
+            (
+                base + 3 * idx,
+                vec![(idx % 251) as u8; 1 + (idx % 7) as usize],
+            )
+        })
+        .collect();
+    for (tile_id, data) in &tiles {
+        writer.add_tile(*tile_id, data).expect("add tile");
+    }
+    writer
+        .finish(serde_json::Map::new(), 0, 0, 1)
+        .expect("finish");
+
+    let file = File::open(&output).expect("open output");
+    let header = read_header(&file).expect("header");
+    assert!(header.leaf_length > 0);
+    assert!(header.root_offset + header.root_length <= 16 * 1024);
+    let index = DirectoryIndex::new(&file, &header).expect("index");
+    let mut count = 0;
+    index
+        .walk(|entry| {
+            count += entry.run_length;
+            Ok(())
+        })
+        .expect("walk");
+    assert_eq!(count, 20_000);
+    assert!(index.leaf_reads() > 1);
+    for (tile_id, data) in tiles.iter().step_by(97) {
+        assert_eq!(
+            index.read_tile(*tile_id).expect("read tile"),
+            Some(data.clone())
+        );
+    }
+}
+
+#[test]
+fn tile_id_to_xyz_rejects_ids_beyond_zoom_30() {
+    let deepest = tile_id_from_xyz(30, (1 << 30) - 1, 0).expect("z30 id");
+    assert_eq!(
+        tile_id_to_xyz(deepest).expect("z30"),
+        (30, (1 << 30) - 1, 0)
+    );
+    let err = tile_id_to_xyz(u64::MAX).expect_err("beyond z30");
+    assert!(err.to_string().contains("beyond zoom 30"), "{err}");
+    assert!(tile_id_from_xyz(31, 0, 0).is_err());
+    assert!(tile_id_from_xyz(2, 4, 0).is_err());
+}
+
+#[test]
+fn tile_id_zoom_switches_at_each_base_id() {
+    for z in 1..=30u8 {
+        let base = tile_id_from_xyz(z, 0, 0).expect("base id");
+        assert_eq!(tile_id_zoom(base).expect("base"), z);
+        assert_eq!(tile_id_zoom(base - 1).expect("previous"), z - 1);
+    }
+    assert!(tile_id_zoom(tile_id_from_xyz(30, 0, 0).expect("z30 id") * 4 + 1).is_err());
+    assert_eq!(run_zoom(1, 4).expect("z1 run"), Some(1));
+    assert_eq!(run_zoom(1, 5).expect("crossing run"), None);
+    assert_eq!(run_zoom(0, 0).expect("zero run"), Some(0));
+}
+
+proptest! {
+    #[test]
+    fn tile_id_round_trips_up_to_z22(
+        (z, x, y) in (0u8..=22).prop_flat_map(|z| (Just(z), 0..1u32 << z, 0..1u32 << z))
+    ) {
+        let tile_id = tile_id_from_xyz(z, x, y).expect("tile id");
+        prop_assert_eq!(tile_id_zoom(tile_id).expect("zoom"), z);
+        prop_assert_eq!(tile_id_to_xyz(tile_id).expect("xyz"), (z, x, y));
+    }
+}
+
+/// Tiles at zooms 0-2 with one to three features each, so sizes repeat.
+fn create_pyramid_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    for zoom in 0u32..=2 {
+        for x in 0..(1u32 << zoom) {
+            for y in 0..(1u32 << zoom) {
+                let mut tile = Tile::new(4096);
+                let mut layer = tile.create_layer("roads");
+                for idx in 0..=((x + y) % 3) {
+                    let geom = GeomEncoder::new(GeomType::Point)
+                        .point(f64::from(idx), 2.0)
+                        .expect("point")
+                        .encode()
+                        .expect("encode");
+                    let mut feature = layer.into_feature(geom);
+                    feature.add_tag_string("class", ["primary", "secondary"][idx as usize % 2]);
+                    layer = feature.into_layer();
+                }
+                tile.add_layer(layer).expect("add roads");
+                conn.execute(
+                    "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                    (zoom, x, y, tile.to_bytes().expect("tile bytes")),
+                )
+                .expect("tile insert");
+            }
+        }
+    }
+}
+
+/// Copy `input` with all but the first two root entries moved into leaf
+/// directories of `leaf_size` entries.
+fn write_pmtiles_with_leaves(input: &Path, output: &Path, leaf_size: usize) -> usize {
+    let file = File::open(input).expect("open input");
+    let mut header = read_header(&file).expect("header");
+    let entries = read_directory_section(&file, &header, header.root_offset, header.root_length)
+        .expect("root directory");
+    let metadata = file
+        .read_range(header.metadata_offset, header.metadata_length)
+        .expect("metadata");
+    let data = file
+        .read_range(header.data_offset, header.data_length)
+        .expect("data");
+
+    let (direct, rest) = entries.split_at(2);
+    let mut root = direct.to_vec();
+    let mut leaves = Vec::new();
+    for chunk in rest.chunks(leaf_size) {
+        let leaf = encode_internal_bytes(
+            &encode_directory(chunk).expect("encode leaf"),
+            header.internal_compression,
+        )
+        .expect("compress leaf");
+        root.push(Entry {
+            tile_id: chunk[0].tile_id,
+            offset: leaves.len() as u64,
+            length: leaf.len() as u32,
+            run_length: 0,
+        });
+        leaves.extend_from_slice(&leaf);
+    }
+    let root_bytes = encode_internal_bytes(
+        &encode_directory(&root).expect("encode root"),
+        header.internal_compression,
+    )
+    .expect("compress root");
+
+    header.root_offset = 127;
+    header.root_length = root_bytes.len() as u64;
+    header.metadata_offset = header.root_offset + header.root_length;
+    header.leaf_offset = header.metadata_offset + header.metadata_length;
+    header.leaf_length = leaves.len() as u64;
+    header.data_offset = header.leaf_offset + header.leaf_length;
+    let mut out = File::create(output).expect("create output");
+    write_header(&out, &header).expect("write header");
+    out.seek(SeekFrom::Start(header.root_offset))
+        .expect("seek root");
+    for section in [&root_bytes, &metadata, &leaves, &data] {
+        out.write_all(section).expect("write section");
+    }
+    root.len() - direct.len()
+}
+
+#[test]
+fn inspect_pmtiles_with_leaf_directories_matches_root_only_archive() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let flat = dir.path().join("flat.pmtiles");
+    let leafed = dir.path().join("leafed.pmtiles");
+    create_pyramid_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &flat).expect("mbtiles->pmtiles");
+    let leaf_count = write_pmtiles_with_leaves(&flat, &leafed, 3);
+    assert!(leaf_count > 2);
+
+    let options = InspectOptions {
+        no_progress: true,
+        histogram_buckets: 4,
+        topn: 5,
+        bucket: Some(0),
+        list_tiles: Some(TileListOptions {
+            limit: 100,
+            sort: TileSort::Zxy,
+            size_range: None,
+        }),
+        include_layer_list: true,
+        layer_zoom_matrix: true,
+        percentiles: true,
+        dedup_stats: true,
+        hash: Some(TileHashAlgorithm::Xxh3),
+        recommend: true,
+        ..InspectOptions::default()
+    };
+    let expected = inspect_pmtiles_with_options(&flat, &options).expect("inspect flat");
+    let report = inspect_pmtiles_with_options(&leafed, &options).expect("inspect leafed");
+    assert_eq!(report.overall.tile_count, 21);
+    assert!(!report.bucket_tiles.is_empty());
+    assert_eq!(report, expected);
+
+    let zoom_options = InspectOptions {
+        zoom: Some(2),
+        ..options
+    };
+    let expected = inspect_pmtiles_with_options(&flat, &zoom_options).expect("inspect flat");
+    let report = inspect_pmtiles_with_options(&leafed, &zoom_options).expect("inspect leafed");
+    assert_eq!(report.overall.tile_count, 16);
+    assert_eq!(report, expected);
+}
+
+#[test]
+fn directory_index_reads_each_leaf_once_within_cache_limit() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let flat = dir.path().join("flat.pmtiles");
+    let leafed = dir.path().join("leafed.pmtiles");
+    create_pyramid_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &flat).expect("mbtiles->pmtiles");
+    let leaf_count = write_pmtiles_with_leaves(&flat, &leafed, 3) as u64;
+
+    let file = File::open(&leafed).expect("open");
+    let header = read_header(&file).expect("header");
+    let count_tiles = |index: &DirectoryIndex<'_>| {
+        index
+            .fold(
+                || 0u64,
+                |count, entry| {
+                    *count += u64::from(entry.run_length);
+                    Ok(0)
+                },
+                |count, other| *count += other,
+                None,
+            )
+            .expect("fold")
+    };
+
+    let index = DirectoryIndex::new(&file, &header).expect("index");
+    assert_eq!(count_tiles(&index), 21);
+    assert_eq!(count_tiles(&index), 21);
+    assert_eq!(index.leaf_reads(), leaf_count);
+
+    // A cap below the total leaf size evicts leaves between walks
+    let index = DirectoryIndex::new(&file, &header)
+        .expect("index")
+        .with_cache_limit(3);
+    index.walk(|_| Ok(())).expect("walk");
+    assert_eq!(count_tiles(&index), 21);
+    assert!(index.leaf_reads() > leaf_count);
+}
+
+#[test]
+fn read_tile_returns_stored_bytes_by_xyz_coordinate() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let flat = dir.path().join("flat.pmtiles");
+    let leafed = dir.path().join("leafed.pmtiles");
+    create_pyramid_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &flat).expect("mbtiles->pmtiles");
+    write_pmtiles_with_leaves(&flat, &leafed, 3);
+
+    // XYZ 2/1/0 is stored at TMS row 3 in the MBTiles file
+    let coord = TileCoord {
+        zoom: 2,
+        x: 1,
+        y: 0,
+    };
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    let stored: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 2 AND tile_column = 1 AND tile_row = 3",
+            [],
+            |row| row.get(0),
+        )
+        .expect("stored tile");
+    assert_eq!(
+        read_tile(&input, coord).expect("read mbtiles"),
+        Some(stored.clone())
+    );
+
+    let source = PmtilesFile::open(&flat).expect("open pmtiles");
+    for path in [&flat, &leafed] {
+        let data = read_tile(path, coord)
+            .expect("read pmtiles")
+            .expect("tile stored");
+        assert_eq!(source.decode_tile(&data).expect("decode"), stored);
+    }
+
+    let missing = TileCoord {
+        zoom: 3,
+        x: 0,
+        y: 0,
+    };
+    assert_eq!(read_tile(&input, missing).expect("read"), None);
+    assert_eq!(read_tile(&leafed, missing).expect("read"), None);
+    assert_eq!(
+        PmtilesFile::open(&leafed)
+            .expect("open leafed")
+            .tile_data(coord)
+            .expect("tile data")
+            .map(|data| source.decode_tile(&data).expect("decode")),
+        Some(stored)
+    );
+
+    let too_deep = TileCoord {
+        zoom: 31,
+        x: 0,
+        y: 0,
+    };
+    let err = read_tile(&leafed, too_deep).expect_err("beyond z30");
+    assert!(err.to_string().contains("beyond zoom 30"), "{err}");
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "http")]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::thread;
+
+use vt_optimizer::mbtiles::InspectOptions;
+use vt_optimizer::pmtiles::{
+    inspect_pmtiles_url_with_options, inspect_pmtiles_with_options, mbtiles_to_pmtiles,
+};
+
+fn create_sample_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        INSERT INTO metadata (name, value) VALUES ('name', 'remote');
+        ",
+    )
+    .expect("schema");
+    for (zoom, x, size) in [(0u8, 0u32, 10usize), (1, 0, 20), (1, 1, 40)] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, 0, ?3)",
+            (zoom, x, vec![1u8; size]),
+        )
+        .expect("tile");
+    }
+}
+
+/// Serve `data` over HTTP, answering `Range: bytes=a-b` requests with 206.
+fn serve_ranges(data: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut reader = BufReader::new(stream.try_clone().expect("clone"));
+            let mut range = None;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                let lower = line.to_ascii_lowercase();
+                if let Some(value) = lower.strip_prefix("range: bytes=") {
+                    let (start, end) = value.trim().split_once('-').expect("range");
+                    range = Some((
+                        start.parse::<usize>().expect("start"),
+                        end.parse::<usize>().expect("end"),
+                    ));
+                }
+            }
+            let (start, end) = range.expect("range header");
+            let end = end.min(data.len() - 1);
+            let body = &data[start..=end];
+            let head = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                body.len(),
+                start,
+                end,
+                data.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(body);
+        }
+    });
+    format!("http://{addr}/tiles.pmtiles")
+}
+
+#[test]
+fn inspect_pmtiles_url_matches_local_inspect() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let options = InspectOptions {
+        no_progress: true,
+        topn: 2,
+        histogram_buckets: 2,
+        include_layer_list: true,
+        ..InspectOptions::default()
+    };
+    let url = serve_ranges(std::fs::read(&pmtiles).expect("read pmtiles"));
+    let remote = inspect_pmtiles_url_with_options(&url, &options).expect("inspect url");
+    let local = inspect_pmtiles_with_options(
+        &pmtiles,
+        &InspectOptions {
+            include_layer_list: false,
+            ..options.clone()
+        },
+    )
+    .expect("inspect local");
+
+    assert_eq!(remote.metadata, local.metadata);
+    assert_eq!(remote.overall, local.overall);
+    assert_eq!(remote.by_zoom, local.by_zoom);
+    assert_eq!(remote.top_tiles, local.top_tiles);
+    assert_eq!(remote.histogram.len(), 2);
+    assert!(remote.file_layers.is_empty());
+}
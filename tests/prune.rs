@@ -1,11 +1,20 @@
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 
 use mvt::{GeomEncoder, GeomType, Tile};
 use mvt_reader::Reader;
 
-use vt_optimizer::mbtiles::{PruneOptions, inspect_mbtiles, prune_mbtiles_layer_only};
-use vt_optimizer::style::read_style;
+use vt_optimizer::mbtiles::{
+    CheckpointOptions, FeatureCleanup, FeatureIdMode, InspectOptions, MbtilesFile, OutputOrder,
+    PruneCheckpoint, PruneOptions, SampleSpec, TileCompression, copy_mbtiles, decode_tile_payload,
+    encode_tile_payload, estimate_prune, inspect_mbtiles, inspect_mbtiles_with_options,
+    is_geometry_valid, parse_layer_renames, prune_mbtiles_layer_only,
+    prune_mbtiles_layer_only_with_checkpoint,
+};
+use vt_optimizer::pmtiles::{inspect_pmtiles_with_options, mbtiles_to_pmtiles};
+use vt_optimizer::report::OptimizeReport;
+use vt_optimizer::style::{LayerSelection, read_style};
 
 fn create_layer_tile() -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -130,13 +139,9 @@ fn prune_mbtiles_removes_unlisted_layers() {
         &style,
         false,
         PruneOptions {
-            threads: 1,
             io_batch: 10,
-            readers: 1,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
@@ -155,6 +160,127 @@ fn prune_mbtiles_removes_unlisted_layers() {
     assert_eq!(layers[0].name, "roads");
 }
 
+#[test]
+fn prune_mbtiles_with_explicit_layer_lists() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&input);
+
+    let output_layers = |name: &str, selection: LayerSelection| {
+        let output = dir.path().join(name);
+        let stats =
+            prune_mbtiles_layer_only(&input, &output, selection, true, checkpoint_prune_options())
+                .expect("prune mbtiles");
+        let conn = rusqlite::Connection::open(&output).expect("open output");
+        let data: Vec<u8> = conn
+            .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+            .expect("read tile");
+        let reader = Reader::new(data).expect("decode");
+        let layers = reader
+            .get_layer_metadata()
+            .expect("layers")
+            .into_iter()
+            .map(|layer| layer.name)
+            .collect::<Vec<_>>();
+        let removed = stats
+            .removed_layers_by_zoom
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        (layers, removed)
+    };
+
+    let keep = LayerSelection::ExplicitList {
+        keep: Some(["buildings".to_string()].into()),
+        drop: Default::default(),
+    };
+    assert_eq!(
+        output_layers("keep.mbtiles", keep),
+        (vec!["buildings".to_string()], vec!["roads".to_string()])
+    );
+    let drop = LayerSelection::ExplicitList {
+        keep: None,
+        drop: ["buildings".to_string()].into(),
+    };
+    assert_eq!(
+        output_layers("drop.mbtiles", drop),
+        (vec!["roads".to_string()], vec!["buildings".to_string()])
+    );
+}
+
+#[test]
+fn prune_mbtiles_keeps_per_layer_extent() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+
+    // `mvt::Tile` has one extent, so the fixture concatenates two one-layer tiles.
+    let mut data = Vec::new();
+    for (name, extent, x, y) in [
+        ("roads", 4096, 100.0, 200.0),
+        ("buildings", 8192, 6000.0, 7000.0),
+    ] {
+        let mut tile = Tile::new(extent);
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(x, y)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let layer = tile.create_layer(name).into_feature(geom).into_layer();
+        tile.add_layer(layer).expect("add layer");
+        data.extend(tile.to_bytes().expect("tile bytes"));
+    }
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (data,),
+    )
+    .expect("tile insert");
+    drop(conn);
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"},{"id":"buildings","type":"fill","source":"osm","source-layer":"buildings"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    prune_mbtiles_layer_only(&input, &output, &style, false, checkpoint_prune_options())
+        .expect("prune");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    let layers = reader.get_layer_metadata().expect("layers");
+    let extents: Vec<(String, u32)> = layers
+        .iter()
+        .map(|layer| (layer.name.clone(), layer.extent))
+        .collect();
+    assert_eq!(
+        extents,
+        vec![("roads".to_string(), 4096), ("buildings".to_string(), 8192)]
+    );
+    let features = reader.get_features(1).expect("features");
+    let geo_types::Geometry::MultiPoint(points) = features[0].get_geometry() else {
+        panic!("expected point geometry");
+    };
+    assert_eq!((points.0[0].x(), points.0[0].y()), (6000.0, 7000.0));
+}
+
 #[test]
 fn prune_mbtiles_supports_map_images_schema() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -176,13 +302,9 @@ fn prune_mbtiles_supports_map_images_schema() {
         &style,
         false,
         PruneOptions {
-            threads: 1,
             io_batch: 10,
-            readers: 1,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
@@ -191,6 +313,63 @@ fn prune_mbtiles_supports_map_images_schema() {
     assert_eq!(report.overall.tile_count, 1);
 }
 
+#[test]
+fn prune_mbtiles_keeps_shallow_schema_deduplicated() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles_shallow (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data_id INTEGER,
+            PRIMARY KEY (zoom_level, tile_column, tile_row)
+        ) WITHOUT ROWID;
+        CREATE TABLE tiles_data (tile_data_id INTEGER PRIMARY KEY, tile_data BLOB);
+        INSERT INTO tiles_shallow VALUES (1, 0, 0, 1), (1, 0, 1, 1), (1, 1, 0, 1);
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles_data (tile_data_id, tile_data) VALUES (1, ?1)",
+        (create_layer_tile(),),
+    )
+    .expect("tiles_data");
+    drop(conn);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let stats =
+        prune_mbtiles_layer_only(&input, &output, &style, false, checkpoint_prune_options())
+            .expect("prune");
+    assert!(stats.removed_layers_by_zoom["buildings"].contains(&1));
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let count = |query: &str| -> i64 { conn.query_row(query, [], |row| row.get(0)).expect(query) };
+    assert_eq!(count("SELECT COUNT(*) FROM tiles_shallow"), 3);
+    assert_eq!(count("SELECT COUNT(*) FROM tiles_data"), 1);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM sqlite_master WHERE name = 'tiles_shallow_index'"),
+        1
+    );
+    let data: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 1 AND tile_row = 0",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read tile through view");
+    let reader = Reader::new(data).expect("decode");
+    let layers = reader.get_layer_metadata().expect("layers");
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "roads");
+}
+
 #[test]
 fn prune_mbtiles_handles_multiple_tiles() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -216,10 +395,8 @@ fn prune_mbtiles_handles_multiple_tiles() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
@@ -291,10 +468,8 @@ fn prune_mbtiles_filters_features_by_style() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
@@ -346,10 +521,8 @@ fn prune_mbtiles_keeps_features_on_unknown_filter() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
@@ -393,10 +566,9 @@ fn prune_mbtiles_drops_features_on_unknown_filter_when_configured() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
             keep_unknown_filters: false,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
@@ -439,10 +611,8 @@ fn prune_mbtiles_handles_multiple_readers() {
             threads: 4,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
@@ -467,7 +637,7 @@ fn prune_mbtiles_drop_empty_tiles() {
     .expect("write style");
     let style = read_style(&style).expect("read style");
 
-    prune_mbtiles_layer_only(
+    let stats = prune_mbtiles_layer_only(
         &input,
         &output,
         &style,
@@ -475,15 +645,1649 @@ fn prune_mbtiles_drop_empty_tiles() {
         PruneOptions {
             threads: 2,
             io_batch: 10,
-            readers: 1,
-            read_cache_mb: None,
-            write_cache_mb: None,
             drop_empty_tiles: true,
-            keep_unknown_filters: true,
+            no_progress: true,
+            ..Default::default()
         },
     )
     .expect("prune mbtiles");
 
     let report = inspect_mbtiles(&output).expect("inspect output");
     assert_eq!(report.overall.tile_count, 0);
+    let zoom0 = stats.tiles_by_zoom.get(&0).expect("zoom 0 counts");
+    assert_eq!((zoom0.tiles_before, zoom0.tiles_after), (2, 0));
+    assert_eq!(zoom0.bytes_before, 2 * create_layer_tile().len() as u64);
+    assert_eq!(zoom0.bytes_after, 0);
+}
+
+#[test]
+fn prune_mbtiles_drop_empty_threshold_drops_small_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+
+    create_layer_mbtiles_multiple(&input);
+
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    for (threshold, expected) in [(1, 2), (10_000, 0)] {
+        let output = dir.path().join(format!("output-{threshold}.mbtiles"));
+        prune_mbtiles_layer_only(
+            &input,
+            &output,
+            &style,
+            false,
+            PruneOptions {
+                threads: 2,
+                io_batch: 10,
+                drop_empty_tiles: true,
+                no_progress: true,
+                drop_empty_max_bytes: Some(threshold),
+                ..Default::default()
+            },
+        )
+        .expect("prune mbtiles");
+
+        let report = inspect_mbtiles(&output).expect("inspect output");
+        assert_eq!(report.overall.tile_count, expected);
+    }
+}
+
+#[test]
+fn estimate_prune_projects_sampled_tiles_onto_the_tileset() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+
+    create_layer_mbtiles_multiple(&input);
+
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+    let options = PruneOptions {
+        io_batch: 10,
+        no_progress: true,
+        ..Default::default()
+    };
+    let source = MbtilesFile::open(&input).expect("open input");
+
+    let full =
+        estimate_prune(&source, &style, false, &options, None, None, None).expect("estimate");
+    let sampled = estimate_prune(
+        &source,
+        &style,
+        false,
+        &options,
+        None,
+        Some(&SampleSpec::Count(1)),
+        None,
+    )
+    .expect("estimate sample");
+    assert!(!output.exists());
+
+    prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune mbtiles");
+    let written = inspect_mbtiles(&output).expect("inspect output").overall;
+
+    assert_eq!(full.sampled_tiles(), 2);
+    assert_eq!(full.projected_totals().tiles_after, written.tile_count);
+    assert_eq!(full.projected_totals().bytes_after, written.total_bytes);
+    assert_eq!(full.projected_removed_features(), 2);
+    assert!(full.sample.removed_layers_by_zoom.contains_key("buildings"));
+
+    assert_eq!(sampled.sampled_tiles(), 1);
+    assert_eq!(sampled.projected_totals(), full.projected_totals());
+    assert_eq!(sampled.projected_removed_features(), 2);
+}
+
+fn read_metadata_value(path: &Path, name: &str) -> String {
+    rusqlite::Connection::open(path)
+        .expect("open output")
+        .query_row(
+            "SELECT value FROM metadata WHERE name = ?1",
+            (name,),
+            |row| row.get(0),
+        )
+        .expect("metadata value")
+}
+
+#[test]
+fn prune_mbtiles_drop_empty_tiles_refreshes_zoom_and_bounds_metadata() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        INSERT INTO metadata (name, value) VALUES
+            ('minzoom', '13'), ('maxzoom', '14'),
+            ('bounds', '-180,-85,180,85'), ('center', '10,10,14');
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (13, 4096, 4096, ?1)",
+        (create_layer_tile(),),
+    )
+    .expect("z13 tile");
+    // z14 only has buildings, which the style drops
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("buildings");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(3.0, 4.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    tile.add_layer(layer.into_feature(geom).into_layer())
+        .expect("add buildings");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (14, 8192, 8192, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("z14 tile");
+    drop(conn);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let mut options = checkpoint_prune_options();
+    options.drop_empty_tiles = true;
+    let output = dir.path().join("output.mbtiles");
+    prune_mbtiles_layer_only(&input, &output, &style, false, options.clone()).expect("prune");
+    assert_eq!(read_metadata_value(&output, "minzoom"), "13");
+    assert_eq!(read_metadata_value(&output, "maxzoom"), "13");
+    // TMS row 4096 at z13 is the tile just north of the equator
+    assert_eq!(
+        read_metadata_value(&output, "bounds"),
+        "0,0,0.043945,0.043945"
+    );
+    assert_eq!(
+        read_metadata_value(&output, "center"),
+        "0.021973,0.021973,13"
+    );
+
+    options.keep_original_metadata = true;
+    let output = dir.path().join("output-original.mbtiles");
+    prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+    assert_eq!(read_metadata_value(&output, "maxzoom"), "14");
+    assert_eq!(read_metadata_value(&output, "bounds"), "-180,-85,180,85");
+}
+
+#[test]
+fn optimize_report_serializes_stable_fields() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles_multiple(&input);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let stats =
+        prune_mbtiles_layer_only(&input, &output, &style, false, checkpoint_prune_options())
+            .expect("prune mbtiles");
+    let report_path = dir.path().join("report.json");
+    OptimizeReport::for_prune(
+        &input,
+        &output,
+        Duration::from_millis(1500),
+        serde_json::json!({ "threads": 1 }),
+        stats,
+    )
+    .expect("report")
+    .write(&report_path)
+    .expect("write report");
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&fs::read(&report_path).expect("read report")).expect("json");
+    assert_eq!(report["version"], 1);
+    assert_eq!(report["command"], "optimize");
+    assert_eq!(report["input"]["path"], input.display().to_string());
+    assert_eq!(
+        report["output"]["size_bytes"],
+        fs::metadata(&output).expect("stat").len()
+    );
+    assert_eq!(report["wall_time_secs"], 1.5);
+    assert_eq!(report["options"]["threads"], 1);
+    assert_eq!(report["totals"]["tiles_before"], 2);
+    assert_eq!(report["totals"]["tiles_after"], 2);
+    assert_eq!(report["zooms"]["0"]["tiles_after"], 2);
+    assert!(report["prune"]["removed_layers_by_zoom"]["buildings"].is_array());
+    assert!(report.get("simplify").is_none());
+}
+
+#[test]
+fn prune_mbtiles_fix_winding_reverses_rings() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("water");
+    let mut encoder = GeomEncoder::new(GeomType::Polygon);
+    for (x, y) in [(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)] {
+        encoder.add_point(x, y).expect("point");
+    }
+    let layer = layer
+        .into_feature(encoder.encode().expect("encode"))
+        .into_layer();
+    tile.add_layer(layer).expect("add water");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("tile insert");
+
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"water","type":"fill","source":"osm","source-layer":"water"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    let stats = prune_mbtiles_layer_only(
+        &input,
+        &output,
+        &style,
+        false,
+        PruneOptions {
+            io_batch: 10,
+            fix_winding: true,
+            no_progress: true,
+            ..Default::default()
+        },
+    )
+    .expect("prune mbtiles");
+    assert_eq!(stats.corrected_rings, 1);
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    let features = reader.get_features(0).expect("features");
+    assert_eq!(features.len(), 1);
+    assert!(is_geometry_valid(&features[0].geometry));
+}
+
+#[test]
+fn prune_mbtiles_clamp_zoom_skips_zooms_outside_style() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    for row in 0..2 {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, 0, ?1, ?2)",
+            (row, create_layer_tile()),
+        )
+        .expect("tile insert");
+    }
+    drop(conn);
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","maxzoom":2}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    let stats = prune_mbtiles_layer_only(
+        &input,
+        &output,
+        &style,
+        false,
+        PruneOptions {
+            clamp_zoom: true,
+            ..checkpoint_prune_options()
+        },
+    )
+    .expect("prune mbtiles");
+    assert_eq!(stats.clamped_tiles, 2);
+    assert_eq!(stats.tiles_by_zoom[&3].tiles_after, 0);
+
+    let report = inspect_mbtiles(&output).expect("inspect output");
+    assert_eq!(report.overall.tile_count, 1);
+    assert_eq!(report.by_zoom[0].zoom, 0);
+}
+
+fn create_layer_mbtiles_grid(path: &Path, count: u32) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        INSERT INTO metadata (name, value) VALUES ('name', 'grid');
+        ",
+    )
+    .expect("schema");
+    let data = create_layer_tile();
+    for x in 0..count {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (5, ?1, 0, ?2)",
+            (x, data.clone()),
+        )
+        .expect("tile insert");
+    }
+}
+
+fn checkpoint_prune_options() -> PruneOptions {
+    PruneOptions {
+        threads: 2,
+        io_batch: 4,
+        no_progress: true,
+        ..Default::default()
+    }
+}
+
+fn write_roads_style(path: &Path) {
+    fs::write(
+        path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+}
+
+#[test]
+fn prune_mbtiles_batched_output_has_tile_index() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles_grid(&input, 10);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    prune_mbtiles_layer_only(&input, &output, &style, false, checkpoint_prune_options())
+        .expect("prune");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let index_sql: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'index' AND name = 'tile_index'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("tile_index");
+    assert!(index_sql.contains("UNIQUE"));
+    let tiles: i64 = conn
+        .query_row("SELECT COUNT(*) FROM tiles", [], |row| row.get(0))
+        .expect("count");
+    assert_eq!(tiles, 10);
+    let analyzed: i64 = conn
+        .query_row("SELECT COUNT(*) FROM sqlite_stat1", [], |row| row.get(0))
+        .expect("sqlite_stat1");
+    assert!(analyzed > 0);
+}
+
+#[test]
+fn prune_mbtiles_checkpoint_records_completion() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    let checkpoint_path = dir.path().join("optimize.checkpoint.json");
+    create_layer_mbtiles_grid(&input, 20);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let mut options = checkpoint_prune_options();
+    options.readers = 3;
+    let checkpoint = CheckpointOptions {
+        path: checkpoint_path.clone(),
+        resume: false,
+        interval: 3,
+    };
+    prune_mbtiles_layer_only_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        options,
+        Some(&checkpoint),
+    )
+    .expect("prune");
+
+    let state = PruneCheckpoint::read(&checkpoint_path).expect("checkpoint");
+    assert!(state.complete);
+    assert_eq!(state.tiles_done, 20);
+    assert_eq!(state.ranges.len(), 3);
+    assert!(
+        state
+            .ranges
+            .iter()
+            .all(|range| range.next_rowid == range.end_rowid + 1)
+    );
+    let report = inspect_mbtiles(&output).expect("inspect output");
+    assert_eq!(report.overall.tile_count, 20);
+}
+
+#[test]
+fn prune_mbtiles_resume_continues_after_last_committed_rowid() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    let checkpoint_path = dir.path().join("optimize.checkpoint.json");
+    create_layer_mbtiles_grid(&input, 20);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let mut checkpoint = CheckpointOptions {
+        path: checkpoint_path.clone(),
+        resume: false,
+        interval: 5,
+    };
+    prune_mbtiles_layer_only_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        checkpoint_prune_options(),
+        Some(&checkpoint),
+    )
+    .expect("prune");
+
+    // Simulate a run killed after the first 7 tiles were committed.
+    {
+        let conn = rusqlite::Connection::open(&output).expect("open output");
+        conn.execute("DELETE FROM tiles WHERE rowid > 7", [])
+            .expect("truncate output");
+    }
+    let mut state = PruneCheckpoint::read(&checkpoint_path).expect("checkpoint");
+    state.ranges[0].next_rowid = state.ranges[0].start_rowid + 7;
+    state.tiles_done = 7;
+    state.complete = false;
+    state.write(&checkpoint_path).expect("rewrite checkpoint");
+
+    checkpoint.resume = true;
+    let stats = prune_mbtiles_layer_only_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        checkpoint_prune_options(),
+        Some(&checkpoint),
+    )
+    .expect("resume");
+    assert_eq!(
+        stats
+            .removed_layers_by_zoom
+            .get("buildings")
+            .map(|z| z.len()),
+        Some(1)
+    );
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let (total, distinct): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT tile_column) FROM tiles",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("count");
+    assert_eq!((total, distinct), (20, 20));
+    let metadata_rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM metadata", [], |row| row.get(0))
+        .expect("metadata count");
+    assert_eq!(metadata_rows, 1);
+    let state = PruneCheckpoint::read(&checkpoint_path).expect("checkpoint");
+    assert!(state.complete);
+    assert_eq!(state.tiles_done, 20);
+}
+
+#[test]
+fn prune_mbtiles_resume_replaces_rows_committed_after_the_checkpoint() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    let checkpoint_path = dir.path().join("optimize.checkpoint.json");
+    create_layer_mbtiles_grid(&input, 20);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let mut checkpoint = CheckpointOptions {
+        path: checkpoint_path.clone(),
+        resume: false,
+        interval: 5,
+    };
+    prune_mbtiles_layer_only_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        checkpoint_prune_options(),
+        Some(&checkpoint),
+    )
+    .expect("prune");
+
+    // Simulate a run killed after committing 10 tiles but before saving the
+    // checkpoint, which still records the first 5.
+    {
+        let conn = rusqlite::Connection::open(&output).expect("open output");
+        conn.execute_batch("DELETE FROM tiles WHERE rowid > 10; DROP INDEX tile_index;")
+            .expect("truncate output");
+    }
+    let mut state = PruneCheckpoint::read(&checkpoint_path).expect("checkpoint");
+    state.ranges[0].next_rowid = state.ranges[0].start_rowid + 5;
+    state.tiles_done = 5;
+    state.complete = false;
+    state.write(&checkpoint_path).expect("rewrite checkpoint");
+
+    checkpoint.resume = true;
+    prune_mbtiles_layer_only_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        checkpoint_prune_options(),
+        Some(&checkpoint),
+    )
+    .expect("resume");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let (total, distinct): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT tile_column) FROM tiles",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("count");
+    assert_eq!((total, distinct), (20, 20));
+}
+
+#[test]
+fn prune_mbtiles_resume_rejects_checkpoint_for_other_input() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let other = dir.path().join("other.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    let checkpoint_path = dir.path().join("optimize.checkpoint.json");
+    create_layer_mbtiles_grid(&input, 4);
+    create_layer_mbtiles_grid(&other, 4);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let mut checkpoint = CheckpointOptions {
+        path: checkpoint_path,
+        resume: false,
+        interval: 2,
+    };
+    prune_mbtiles_layer_only_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        checkpoint_prune_options(),
+        Some(&checkpoint),
+    )
+    .expect("prune");
+
+    checkpoint.resume = true;
+    let err = prune_mbtiles_layer_only_with_checkpoint(
+        &other,
+        &output,
+        &style,
+        false,
+        checkpoint_prune_options(),
+        Some(&checkpoint),
+    )
+    .expect_err("mismatched input");
+    assert!(err.to_string().contains("different input"));
+}
+
+fn create_gzip_roads_mbtiles(path: &Path) {
+    let mut tile = Tile::new(4096);
+    let mut layer = tile.create_layer("roads");
+    for i in 0..200 {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point((i % 20) as f64, (i / 20) as f64)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let mut feature = layer.into_feature(geom);
+        feature.add_tag_string("class", "primary");
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer).expect("add roads");
+    // Dropped by the roads style, so pruning rewrites the tile.
+    let layer = tile.create_layer("water");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(5.0, 5.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    tile.add_layer(layer.into_feature(geom).into_layer())
+        .expect("add water");
+    let data = encode_tile_payload(&tile.to_bytes().expect("tile bytes"), true, 9).expect("gzip");
+
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (data,),
+    )
+    .expect("tile insert");
+}
+
+#[test]
+fn prune_mbtiles_honors_gzip_level() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    create_gzip_roads_mbtiles(&input);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let mut outputs = Vec::new();
+    for level in [0, 9] {
+        let output = dir.path().join(format!("output-{level}.mbtiles"));
+        let mut options = checkpoint_prune_options();
+        options.compression_level = level;
+        prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+        let conn = rusqlite::Connection::open(&output).expect("open output");
+        let data: Vec<u8> = conn
+            .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+            .expect("tile");
+        assert!(data.starts_with(&[0x1f, 0x8b]));
+        outputs.push(data);
+    }
+
+    assert!(outputs[0].len() > outputs[1].len());
+    assert_eq!(
+        decode_tile_payload(&outputs[0]).expect("decode level 0"),
+        decode_tile_payload(&outputs[1]).expect("decode level 9")
+    );
+}
+
+#[test]
+fn prune_mbtiles_recompress_overrides_input_compression() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    create_gzip_roads_mbtiles(&input);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let output = dir.path().join("output.mbtiles");
+    let mut options = checkpoint_prune_options();
+    options.recompress = Some(TileCompression::None);
+    let stats = prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("tile");
+    assert!(!data.starts_with(&[0x1f, 0x8b]));
+    Reader::new(data.clone()).expect("raw tile");
+    let recompress = stats.recompress.expect("recompress stats");
+    assert_eq!(recompress.tiles, 1);
+    assert_eq!(recompress.output_bytes, data.len() as u64);
+    assert!(recompress.input_bytes < recompress.output_bytes);
+
+    let mut options = checkpoint_prune_options();
+    options.recompress = Some(TileCompression::Brotli(11));
+    let output = dir.path().join("brotli.mbtiles");
+    let err = prune_mbtiles_layer_only(&input, &output, &style, false, options)
+        .expect_err("brotli mbtiles");
+    assert!(err.to_string().contains("only supported for PMTiles"));
+}
+
+#[test]
+fn prune_mbtiles_passes_untouched_tiles_through() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_gzip_roads_mbtiles(&input);
+    let input_data: Vec<u8> = rusqlite::Connection::open(&input)
+        .expect("open input")
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("input tile");
+    let style = dir.path().join("style.json");
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}},{"id":"water","type":"circle","source":"osm","source-layer":"water"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    let output = dir.path().join("output.mbtiles");
+    let mut options = checkpoint_prune_options();
+    options.compression_level = 1;
+    let stats = prune_mbtiles_layer_only(&input, &output, &style, true, options).expect("prune");
+    let data: Vec<u8> = rusqlite::Connection::open(&output)
+        .expect("open output")
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("output tile");
+    assert_eq!(data, input_data);
+    assert_eq!(stats.unchanged_tiles, 1);
+
+    // Dropping the water layer changes the tile, so it is re-encoded.
+    let style = dir.path().join("roads.json");
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+    let output = dir.path().join("roads.mbtiles");
+    let stats = prune_mbtiles_layer_only(&input, &output, &style, true, checkpoint_prune_options())
+        .expect("prune roads");
+    let data: Vec<u8> = rusqlite::Connection::open(&output)
+        .expect("open output")
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("output tile");
+    assert_ne!(data, input_data);
+    assert_eq!(stats.unchanged_tiles, 0);
+}
+
+fn square(size: f64) -> mvt::GeomData {
+    let mut encoder = GeomEncoder::new(GeomType::Polygon);
+    for (x, y) in [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)] {
+        encoder.add_point(100.0 + x, 100.0 + y).expect("point");
+    }
+    encoder.encode().expect("encode")
+}
+
+fn create_cleanup_mbtiles(path: &Path) {
+    let mut tile = Tile::new(4096);
+    for name in ["buildings", "landmarks"] {
+        let layer = tile.create_layer(name);
+        // 8x8 units is 0.25 square pixels at extent 4096.
+        let layer = layer.into_feature(square(8.0)).into_layer();
+        let layer = layer.into_feature(square(400.0)).into_layer();
+        tile.add_layer(layer).expect("add polygons");
+    }
+    let mut layer = tile.create_layer("pois");
+    for (x, y) in [(100.0, 100.0), (105.0, 100.0), (1000.0, 1000.0)] {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(x, y)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        layer = layer.into_feature(geom).into_layer();
+    }
+    tile.add_layer(layer).expect("add pois");
+
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, 0, 0, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("tile insert");
+}
+
+fn output_feature_counts(path: &Path) -> std::collections::BTreeMap<String, usize> {
+    let conn = rusqlite::Connection::open(path).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    reader
+        .get_layer_metadata()
+        .expect("layers")
+        .into_iter()
+        .map(|layer| {
+            let count = reader
+                .get_features(layer.layer_index)
+                .expect("features")
+                .len();
+            (layer.name, count)
+        })
+        .collect()
+}
+
+fn output_feature_ids(path: &Path) -> Vec<Option<u64>> {
+    let conn = rusqlite::Connection::open(path).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let data = decode_tile_payload(&data).expect("decode payload");
+    let reader = Reader::new(data).expect("decode");
+    reader
+        .get_features(0)
+        .expect("features")
+        .into_iter()
+        .map(|feature| feature.id)
+        .collect()
+}
+
+#[test]
+fn prune_mbtiles_feature_ids_keep_drop_or_reindex() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    let mut tile = Tile::new(4096);
+    let mut layer = tile.create_layer("roads");
+    for (index, id) in [Some(7), Some(7), None].into_iter().enumerate() {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(index as f64, 1.0)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let mut feature = layer.into_feature(geom);
+        if let Some(id) = id {
+            feature.set_id(id);
+        }
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer).expect("add roads");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "CREATE TABLE metadata (name TEXT, value TEXT);
+         CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("tile insert");
+    drop(conn);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    for (mode, expected) in [
+        (FeatureIdMode::Keep, vec![Some(7), Some(7), None]),
+        (FeatureIdMode::Drop, vec![None, None, None]),
+        (FeatureIdMode::Reindex, vec![Some(1), Some(2), Some(3)]),
+    ] {
+        let output = dir.path().join(format!("{mode:?}.mbtiles"));
+        let mut options = checkpoint_prune_options();
+        options.cleanup.feature_ids = mode;
+        prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+        assert_eq!(output_feature_ids(&output), expected, "{mode:?}");
+    }
+}
+
+#[test]
+fn prune_mbtiles_keep_keys_drops_unreferenced_properties() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles(&input);
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"},{"id":"buildings","type":"fill","source":"osm","source-layer":"buildings"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    let mut options = checkpoint_prune_options();
+    // Only roads is whitelisted; buildings keeps every key
+    options.keep_keys = Some(
+        [("roads".to_string(), std::collections::HashSet::new())]
+            .into_iter()
+            .collect(),
+    );
+    let stats = prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+    assert_eq!(stats.removed_keys_by_layer["roads"]["class"], 1);
+    assert!(!stats.removed_keys_by_layer.contains_key("buildings"));
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(decode_tile_payload(&data).expect("decode payload")).expect("decode");
+    for layer in reader.get_layer_metadata().expect("layers") {
+        let features = reader.get_features(layer.layer_index).expect("features");
+        let properties = features[0].properties.clone().unwrap_or_default();
+        match layer.name.as_str() {
+            "roads" => assert!(properties.is_empty(), "{properties:?}"),
+            "buildings" => assert!(properties.contains_key("height")),
+            other => panic!("unexpected layer {other}"),
+        }
+    }
+}
+
+#[test]
+fn prune_mbtiles_renames_and_merges_layers() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&input);
+    let keep_all = || LayerSelection::ExplicitList {
+        keep: None,
+        drop: Default::default(),
+    };
+
+    let output = dir.path().join("renamed.mbtiles");
+    let mut options = checkpoint_prune_options();
+    options.layer_renames =
+        parse_layer_renames(&["roads=streets".to_string()]).expect("parse renames");
+    prune_mbtiles_layer_only(&input, &output, keep_all(), false, options).expect("prune");
+    assert_eq!(
+        output_feature_counts(&output),
+        [("buildings".to_string(), 1), ("streets".to_string(), 1)].into()
+    );
+
+    let output = dir.path().join("merged.mbtiles");
+    let mut options = checkpoint_prune_options();
+    options.cleanup.feature_ids = FeatureIdMode::Reindex;
+    options.layer_renames = parse_layer_renames(&[
+        "roads=features".to_string(),
+        "buildings=features".to_string(),
+    ])
+    .expect("parse renames");
+    options.keep_keys = Some(
+        [("roads".to_string(), std::collections::HashSet::new())]
+            .into_iter()
+            .collect(),
+    );
+    let stats =
+        prune_mbtiles_layer_only(&input, &output, keep_all(), false, options).expect("prune");
+    assert_eq!(stats.removed_keys_by_layer["features"]["class"], 1);
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    let layers = reader.get_layer_metadata().expect("layers");
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "features");
+    let features = reader.get_features(0).expect("features");
+    assert_eq!(
+        features
+            .iter()
+            .map(|feature| feature.id)
+            .collect::<Vec<_>>(),
+        vec![Some(1), Some(2)]
+    );
+    assert!(
+        features[0]
+            .properties
+            .clone()
+            .unwrap_or_default()
+            .is_empty()
+    );
+    assert!(
+        features[1]
+            .properties
+            .clone()
+            .unwrap_or_default()
+            .contains_key("height")
+    );
+
+    assert!(parse_layer_renames(&["roads".to_string()]).is_err());
+    assert!(parse_layer_renames(&["roads=a".to_string(), "roads=b".to_string()]).is_err());
+}
+
+#[test]
+fn prune_mbtiles_drops_tiny_features_and_merges_points() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    create_cleanup_mbtiles(&input);
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"buildings","type":"fill","source":"osm","source-layer":"buildings"},{"id":"landmarks","type":"fill","source":"osm","source-layer":"landmarks","interactive":true},{"id":"pois","type":"circle","source":"osm","source-layer":"pois"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    let output = dir.path().join("output.mbtiles");
+    let mut options = checkpoint_prune_options();
+    options.cleanup = FeatureCleanup {
+        drop_tiny_features: Some(1.0),
+        merge_nearby_points: Some(1.0),
+        ..FeatureCleanup::default()
+    };
+    let stats =
+        prune_mbtiles_layer_only(&input, &output, &style, false, options.clone()).expect("prune");
+    let counts = output_feature_counts(&output);
+    assert_eq!(counts["buildings"], 1);
+    assert_eq!(counts["landmarks"], 2);
+    assert_eq!(counts["pois"], 2);
+    assert_eq!(stats.tiny_features_by_layer["buildings"][&3], 1);
+    assert_eq!(stats.merged_points_by_layer["pois"][&3], 1);
+    assert!(!stats.tiny_features_by_layer.contains_key("landmarks"));
+    assert_eq!(stats.removed_features_by_zoom[&3], 2);
+
+    let output = dir.path().join("output-excluded.mbtiles");
+    options.cleanup.exclude_layers = ["buildings".to_string()].into_iter().collect();
+    let stats = prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+    let counts = output_feature_counts(&output);
+    assert_eq!(counts["buildings"], 2);
+    assert_eq!(counts["pois"], 2);
+    assert!(stats.tiny_features_by_layer.is_empty());
+}
+
+#[test]
+fn prune_mbtiles_dedup_stores_identical_tiles_once() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open input");
+    for (x, y) in [(0, 0), (0, 1), (1, 0)] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, ?1, ?2, ?3)",
+            (x, y, create_layer_tile()),
+        )
+        .expect("tile insert");
+    }
+    drop(conn);
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+
+    let output = dir.path().join("output.mbtiles");
+    let mut options = checkpoint_prune_options();
+    options.dedup = true;
+    let stats = prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+    let dedup = stats.dedup.expect("dedup stats");
+    assert_eq!(dedup.tiles, 4);
+    assert_eq!(dedup.unique_blobs, 1);
+    assert_eq!(dedup.ratio(), 4.0);
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let map_rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM map", [], |row| row.get(0))
+        .expect("map count");
+    let image_rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM images", [], |row| row.get(0))
+        .expect("images count");
+    assert_eq!((map_rows, image_rows), (4, 1));
+    let view_rows: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM tiles WHERE LENGTH(tile_data) > 0",
+            [],
+            |row| row.get(0),
+        )
+        .expect("tiles view count");
+    assert_eq!(view_rows, 4);
+    let report = inspect_mbtiles(&output).expect("inspect output");
+    assert_eq!(report.overall.tile_count, 4);
+
+    let pmtiles = dir.path().join("output.pmtiles");
+    mbtiles_to_pmtiles(&output, &pmtiles).expect("copy dedup output to pmtiles");
+    let report = inspect_pmtiles_with_options(&pmtiles, &InspectOptions::default())
+        .expect("inspect pmtiles");
+    assert_eq!(report.overall.tile_count, 4);
+}
+
+#[test]
+fn prune_mbtiles_copies_raster_tiles_unchanged() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles(&input);
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.resize(32, 7);
+    rusqlite::Connection::open(&input)
+        .expect("open")
+        .execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 1, 0, ?1)",
+            (png.clone(),),
+        )
+        .expect("png tile");
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let stats =
+        prune_mbtiles_layer_only(&input, &output, &style, false, checkpoint_prune_options())
+            .expect("prune");
+    assert_eq!(stats.raster_tiles, 1);
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 1 AND tile_row = 0",
+            [],
+            |row| row.get(0),
+        )
+        .expect("raster tile");
+    assert_eq!(data, png);
+    drop(conn);
+
+    // Raster tiles are counted whenever tile bytes are read, and are not
+    // reported as invalid MVT
+    let options = InspectOptions {
+        no_progress: true,
+        include_layer_list: true,
+        validate_mvt: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&input, options).expect("inspect");
+    assert_eq!(report.raster_format, None);
+    assert_eq!(report.raster_tiles, 1);
+    assert_eq!(report.file_layers.len(), 2);
+    assert_eq!(report.mvt_validation.expect("validation").invalid_tiles, 0);
+}
+
+#[test]
+fn prune_mbtiles_append_merges_into_existing_output() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let first = dir.path().join("first.mbtiles");
+    let second = dir.path().join("second.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles(&first);
+    create_layer_mbtiles_multiple(&second);
+    for (path, name) in [(&first, "first"), (&second, "second")] {
+        rusqlite::Connection::open(path)
+            .expect("open")
+            .execute(
+                "INSERT INTO metadata (name, value) VALUES ('name', ?1)",
+                (name,),
+            )
+            .expect("metadata");
+    }
+    fs::write(
+        &style,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style).expect("read style");
+    let append = PruneOptions {
+        append: true,
+        ..checkpoint_prune_options()
+    };
+
+    prune_mbtiles_layer_only(&first, &output, &style, false, append.clone()).expect("first");
+    prune_mbtiles_layer_only(&second, &output, &style, false, append.clone()).expect("second");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM tiles", [], |row| row.get(0))
+        .expect("count");
+    assert_eq!(count, 2);
+    let names: Vec<String> = conn
+        .prepare("SELECT value FROM metadata WHERE name = 'name'")
+        .expect("prepare")
+        .query_map([], |row| row.get(0))
+        .expect("query")
+        .collect::<Result<_, _>>()
+        .expect("rows");
+    assert_eq!(names, vec!["first".to_string()]);
+    drop(conn);
+
+    let err = prune_mbtiles_layer_only(
+        &first,
+        &output,
+        &style,
+        false,
+        PruneOptions {
+            dedup: true,
+            ..append
+        },
+    )
+    .expect_err("schema mismatch");
+    assert!(err.to_string().contains("map/images"), "{err}");
+}
+
+/// One tile at 0/0/0 stored in the map/images or shallow schema.
+fn create_single_tile_mbtiles(path: &Path, shallow: bool, data: Vec<u8>) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    let (schema, insert) = if shallow {
+        (
+            "
+            CREATE TABLE metadata (name TEXT, value TEXT);
+            CREATE TABLE tiles_shallow (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data_id INTEGER);
+            CREATE TABLE tiles_data (tile_data_id INTEGER PRIMARY KEY, tile_data BLOB);
+            INSERT INTO tiles_shallow VALUES (0, 0, 0, 1);
+            ",
+            "INSERT INTO tiles_data (tile_data_id, tile_data) VALUES (1, ?1)",
+        )
+    } else {
+        (
+            "
+            CREATE TABLE metadata (name TEXT, value TEXT);
+            CREATE TABLE map (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_id TEXT);
+            CREATE TABLE images (tile_id TEXT, tile_data BLOB);
+            INSERT INTO map VALUES (0, 0, 0, 't1');
+            ",
+            "INSERT INTO images (tile_id, tile_data) VALUES ('t1', ?1)",
+        )
+    };
+    conn.execute_batch(schema).expect("schema");
+    conn.execute(insert, (data,)).expect("tile insert");
+}
+
+fn create_moved_roads_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("roads");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(5.0, 6.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.add_tag_string("class", "secondary");
+    tile.add_layer(feature.into_layer()).expect("add roads");
+    tile.to_bytes().expect("tile bytes")
+}
+
+#[test]
+fn prune_mbtiles_append_replaces_tiles_in_blob_schemas() {
+    for shallow in [false, true] {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let first = dir.path().join("first.mbtiles");
+        let second = dir.path().join("second.mbtiles");
+        let output = dir.path().join("output.mbtiles");
+        let fresh = dir.path().join("fresh.mbtiles");
+        let style = dir.path().join("style.json");
+        create_single_tile_mbtiles(&first, shallow, create_layer_tile());
+        create_single_tile_mbtiles(&second, shallow, create_moved_roads_tile());
+        write_roads_style(&style);
+        let style = read_style(&style).expect("read style");
+        let append = PruneOptions {
+            append: true,
+            ..checkpoint_prune_options()
+        };
+
+        prune_mbtiles_layer_only(&first, &output, &style, false, append.clone()).expect("first");
+        prune_mbtiles_layer_only(&second, &output, &style, false, append.clone()).expect("second");
+        prune_mbtiles_layer_only(&second, &fresh, &style, false, append).expect("fresh");
+
+        let query = if shallow {
+            "SELECT tile_data FROM tiles_shallow JOIN tiles_data USING (tile_data_id)"
+        } else {
+            "SELECT tile_data FROM map JOIN images USING (tile_id)"
+        };
+        let tile = |path: &Path| -> Vec<u8> {
+            rusqlite::Connection::open(path)
+                .expect("open output")
+                .query_row(query, [], |row| row.get(0))
+                .expect("tile")
+        };
+        assert_eq!(tile(&output), tile(&fresh), "shallow: {shallow}");
+        // The replaced blob is not left behind
+        let blobs = if shallow { "tiles_data" } else { "images" };
+        let count: i64 = rusqlite::Connection::open(&output)
+            .expect("open output")
+            .query_row(&format!("SELECT COUNT(*) FROM {blobs}"), [], |row| {
+                row.get(0)
+            })
+            .expect("count");
+        assert_eq!(count, 1, "shallow: {shallow}");
+    }
+}
+
+fn file_sha256(path: &Path) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(fs::read(path).expect("read output"));
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Like `create_layer_tile`, but the road carries several properties, so a
+/// re-encoded tile depends on the order its tags are written in.
+fn create_multi_property_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("roads");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(1.0, 2.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    for (key, value) in [
+        ("class", "primary"),
+        ("name", "Main Street"),
+        ("surface", "asphalt"),
+        ("ref", "A1"),
+        ("oneway", "yes"),
+        ("layer", "0"),
+        ("lanes", "2"),
+        ("maxspeed", "50"),
+    ] {
+        feature.add_tag_string(key, value);
+    }
+    let layer = feature.into_layer();
+    tile.add_layer(layer).expect("add roads");
+
+    let layer = tile.create_layer("buildings");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(3.0, 4.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.add_tag_string("height", "10");
+    let layer = feature.into_layer();
+    tile.add_layer(layer).expect("add buildings");
+
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn create_layer_mbtiles_shuffled(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        ",
+    )
+    .expect("schema");
+    let data = create_multi_property_tile();
+    for zoom in (1..=3u8).rev() {
+        let size = 1u32 << zoom;
+        for x in (0..size).rev() {
+            for y in 0..size {
+                conn.execute(
+                    "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                    (zoom, x, y, data.clone()),
+                )
+                .expect("tile insert");
+            }
+        }
+    }
+}
+
+fn output_coords(path: &Path) -> Vec<(u8, u32, u32)> {
+    let conn = rusqlite::Connection::open(path).expect("open output");
+    let mut stmt = conn
+        .prepare("SELECT zoom_level, tile_column, tile_row FROM tiles ORDER BY rowid")
+        .expect("prepare");
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .expect("query")
+        .map(|row| row.expect("row"))
+        .collect()
+}
+
+#[test]
+fn prune_mbtiles_output_order_is_reproducible() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles_shuffled(&input);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+    let run = |name: &str, order: OutputOrder, threads: usize| {
+        let output = dir.path().join(name);
+        let options = PruneOptions {
+            threads,
+            io_batch: 1,
+            output_order: order,
+            ..checkpoint_prune_options()
+        };
+        prune_mbtiles_layer_only(&input, &output, &style, false, options).expect("prune");
+        output
+    };
+
+    let first = run("zxy-1.mbtiles", OutputOrder::Zxy, 4);
+    let hash = file_sha256(&first);
+    for (run_index, threads) in [(2, 4), (3, 1), (4, 4)] {
+        let again = run(
+            &format!("zxy-{run_index}.mbtiles"),
+            OutputOrder::Zxy,
+            threads,
+        );
+        assert_eq!(file_sha256(&again), hash, "run {run_index}");
+    }
+    let coords = output_coords(&first);
+    assert_eq!(coords.len(), 4 + 16 + 64);
+    let mut sorted = coords.clone();
+    sorted.sort();
+    assert_eq!(coords, sorted);
+
+    let first = run("hilbert-1.mbtiles", OutputOrder::Hilbert, 4);
+    let hash = file_sha256(&first);
+    for (run_index, threads) in [(2, 4), (3, 1), (4, 4)] {
+        let again = run(
+            &format!("hilbert-{run_index}.mbtiles"),
+            OutputOrder::Hilbert,
+            threads,
+        );
+        assert_eq!(file_sha256(&again), hash, "run {run_index}");
+    }
+    let ids: Vec<u64> = output_coords(&first)
+        .into_iter()
+        .map(|(z, x, y)| {
+            vt_optimizer::pmtiles::tile_id_from_xyz(z, x, (1 << z) - 1 - y).expect("tile id")
+        })
+        .collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn prune_mbtiles_output_order_reorders_checkpointed_output() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let style = dir.path().join("style.json");
+    create_layer_mbtiles_shuffled(&input);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+    let run = |name: &str, options: PruneOptions| {
+        let output = dir.path().join(name);
+        let checkpoint = CheckpointOptions {
+            path: dir.path().join(format!("{name}.checkpoint")),
+            resume: false,
+            interval: 10,
+        };
+        prune_mbtiles_layer_only_with_checkpoint(
+            &input,
+            &output,
+            &style,
+            false,
+            options,
+            Some(&checkpoint),
+        )
+        .map(|_| output)
+    };
+
+    let zxy = PruneOptions {
+        output_order: OutputOrder::Zxy,
+        ..checkpoint_prune_options()
+    };
+    let output = run("zxy.mbtiles", zxy.clone()).expect("prune zxy");
+    let coords = output_coords(&output);
+    assert_eq!(coords.len(), 4 + 16 + 64);
+    let mut sorted = coords.clone();
+    sorted.sort();
+    assert_eq!(coords, sorted);
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let indexes: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'tile_index'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("index");
+    assert_eq!(indexes, 1);
+
+    let hilbert = PruneOptions {
+        output_order: OutputOrder::Hilbert,
+        ..zxy.clone()
+    };
+    let output = run("hilbert.mbtiles", hilbert).expect("prune hilbert");
+    let ids: Vec<u64> = output_coords(&output)
+        .into_iter()
+        .map(|(z, x, y)| {
+            vt_optimizer::pmtiles::tile_id_from_xyz(z, x, (1 << z) - 1 - y).expect("tile id")
+        })
+        .collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let dedup = PruneOptions { dedup: true, ..zxy };
+    let err = run("dedup.mbtiles", dedup).expect_err("order with checkpoint and dedup");
+    assert!(err.to_string().contains("--output-order"), "{err}");
+}
+
+/// Coordinates stored as TEXT, as in some legacy exports; z12 columns 20 and
+/// 100 sort the other way round as strings.
+fn create_text_coords_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level TEXT, tile_column TEXT, tile_row TEXT, tile_data BLOB);
+        ",
+    )
+    .expect("schema");
+    let data = create_layer_tile();
+    for (z, x, y) in [("0", "0", "0"), ("12", "20", "7"), ("12", "100", "7")] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            (z, x, y, data.clone()),
+        )
+        .expect("tile insert");
+    }
+}
+
+#[test]
+fn text_tile_coordinates_pass_inspect_optimize_and_copy() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_text_coords_mbtiles(&input);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let report = inspect_mbtiles_with_options(
+        &input,
+        InspectOptions {
+            histogram_buckets: 2,
+            histogram_cache_max_tiles: 0,
+            topn: 3,
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect");
+    assert_eq!(report.overall.tile_count, 3);
+    assert_eq!(report.histogram.iter().map(|b| b.count).sum::<u64>(), 3);
+    let mut top: Vec<(u8, u32, u32)> = report
+        .top_tiles
+        .iter()
+        .map(|tile| (tile.zoom, tile.x, tile.y))
+        .collect();
+    top.sort();
+    assert_eq!(top, vec![(0, 0, 0), (12, 20, 7), (12, 100, 7)]);
+
+    prune_mbtiles_layer_only(&input, &output, &style, false, checkpoint_prune_options())
+        .expect("prune");
+    let mut coords = output_coords(&output);
+    coords.sort();
+    assert_eq!(coords, vec![(0, 0, 0), (12, 20, 7), (12, 100, 7)]);
+
+    let copied = dir.path().join("copy.mbtiles");
+    copy_mbtiles(&input, &copied).expect("copy");
+    let mut coords = output_coords(&copied);
+    coords.sort();
+    assert_eq!(coords, vec![(0, 0, 0), (12, 20, 7), (12, 100, 7)]);
+}
+
+/// Two valid tiles and three rows whose address no tile can have.
+fn create_corrupt_rows_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        ",
+    )
+    .expect("schema");
+    let data = create_layer_tile();
+    for (z, x, y) in [(0, 0, 0), (1, 1, 0), (1, 0, -1), (200, 0, 0), (-1, 0, 0)] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            (z, x, y, data.clone()),
+        )
+        .expect("tile insert");
+    }
+}
+
+#[test]
+fn corrupt_rows_are_skipped_and_counted_unless_strict() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style = dir.path().join("style.json");
+    create_corrupt_rows_mbtiles(&input);
+    write_roads_style(&style);
+    let style = read_style(&style).expect("read style");
+
+    let options = InspectOptions {
+        no_progress: true,
+        ..InspectOptions::default()
+    };
+    let report = inspect_mbtiles_with_options(&input, options.clone()).expect("inspect");
+    assert_eq!(report.overall.tile_count, 2);
+    assert_eq!(report.corrupt_rows, 3);
+    let err = inspect_mbtiles_with_options(
+        &input,
+        InspectOptions {
+            strict: true,
+            ..options
+        },
+    )
+    .expect_err("strict inspect");
+    assert!(err.to_string().contains("invalid tile address"), "{err}");
+
+    let stats =
+        prune_mbtiles_layer_only(&input, &output, &style, false, checkpoint_prune_options())
+            .expect("prune");
+    assert_eq!(stats.corrupt_rows, 3);
+    let mut coords = output_coords(&output);
+    coords.sort();
+    assert_eq!(coords, vec![(0, 0, 0), (1, 1, 0)]);
+
+    let strict_output = dir.path().join("strict.mbtiles");
+    let err = prune_mbtiles_layer_only(
+        &input,
+        &strict_output,
+        &style,
+        false,
+        PruneOptions {
+            strict: true,
+            ..checkpoint_prune_options()
+        },
+    )
+    .expect_err("strict prune");
+    assert!(err.to_string().contains("invalid tile address"), "{err}");
+    assert!(!strict_output.exists());
 }
@@ -0,0 +1,266 @@
+#![cfg(feature = "serve")]
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::thread;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use mvt::{GeomEncoder, GeomType, Tile};
+use serde_json::Value;
+
+use vt_optimizer::pmtiles::mbtiles_to_pmtiles;
+use vt_optimizer::serve::{ServeOptions, TileServer};
+
+const ORIGIN: &str = "http://127.0.0.1:8080";
+
+fn roads_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("roads");
+    let geom = GeomEncoder::new(GeomType::Linestring)
+        .point(0.0, 0.0)
+        .expect("point")
+        .point(100.0, 100.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    tile.add_layer(layer.into_feature(geom).into_layer())
+        .expect("add roads");
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).expect("gzip");
+    encoder.finish().expect("gzip finish")
+}
+
+/// One gzipped tile at XYZ 1/0/0 (TMS row 1).
+fn create_serve_mbtiles(path: &Path, with_vector_layers: bool) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        INSERT INTO metadata (name, value) VALUES ('center', '139.7,35.6,1');
+        ",
+    )
+    .expect("schema");
+    if with_vector_layers {
+        conn.execute(
+            "INSERT INTO metadata (name, value) VALUES ('json', ?1)",
+            (r#"{"vector_layers":[{"id":"roads"},{"id":"water"}]}"#,),
+        )
+        .expect("metadata json");
+    }
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 0, 1, ?1)",
+        (gzip(&roads_tile()),),
+    )
+    .expect("tile insert");
+}
+
+fn layer_ids(style: &Value) -> Vec<&str> {
+    style["layers"]
+        .as_array()
+        .expect("layers")
+        .iter()
+        .map(|layer| layer["id"].as_str().expect("id"))
+        .collect()
+}
+
+#[test]
+fn serve_mbtiles_tile_flips_rows_and_keeps_gzip_encoding() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_serve_mbtiles(&input, true);
+    let server = TileServer::mbtiles(&input, &ServeOptions::default()).expect("server");
+
+    let response = server.handle("/tiles/1/0/0.pbf", ORIGIN);
+    assert_eq!(response.status, 200);
+    assert_eq!(response.content_type, "application/x-protobuf");
+    assert_eq!(response.content_encoding, Some("gzip"));
+    assert_eq!(response.body, gzip(&roads_tile()));
+
+    assert_eq!(server.handle("/tiles/1/0/1.pbf", ORIGIN).status, 204);
+    assert_eq!(server.handle("/tiles/1/2/0.pbf", ORIGIN).status, 404);
+    assert_eq!(server.handle("/tiles/1/0.pbf", ORIGIN).status, 404);
+    assert_eq!(server.handle("/tiles/32/0/0.pbf", ORIGIN).status, 404);
+    assert_eq!(server.handle("/missing", ORIGIN).status, 404);
+}
+
+#[test]
+fn serve_pmtiles_tile_is_sent_decodable() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    create_serve_mbtiles(&mbtiles, true);
+    let input = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&mbtiles, &input).expect("convert");
+    let server = TileServer::pmtiles(&input, &ServeOptions::default()).expect("server");
+
+    let response = server.handle("/tiles/1/0/0.pbf", ORIGIN);
+    assert_eq!(response.status, 200);
+    let payload = match response.content_encoding {
+        Some("gzip") => vt_optimizer::mbtiles::decode_tile_payload(&response.body).expect("gunzip"),
+        None => response.body,
+        Some(other) => panic!("unexpected encoding {other}"),
+    };
+    assert_eq!(payload, roads_tile());
+    assert_eq!(server.handle("/tiles/1/1/1.pbf", ORIGIN).status, 204);
+    assert_eq!(server.handle("/tiles/31/0/0.pbf", ORIGIN).status, 404);
+}
+
+#[test]
+fn serve_default_style_draws_every_vector_layer() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_serve_mbtiles(&input, true);
+    let server = TileServer::mbtiles(&input, &ServeOptions::default()).expect("server");
+
+    let response = server.handle("/style.json", ORIGIN);
+    assert_eq!(response.status, 200);
+    let style: Value = serde_json::from_slice(&response.body).expect("style json");
+    assert_eq!(
+        style["sources"]["tiles"]["tiles"][0],
+        "http://127.0.0.1:8080/tiles/{z}/{x}/{y}.pbf"
+    );
+    assert_eq!(style["center"], serde_json::json!([139.7, 35.6]));
+    assert_eq!(
+        layer_ids(&style),
+        vec![
+            "background",
+            "roads-fill",
+            "roads-line",
+            "roads-circle",
+            "water-fill",
+            "water-line",
+            "water-circle",
+        ]
+    );
+
+    // Without vector_layers metadata the layers of a stored tile are used
+    let bare = dir.path().join("bare.mbtiles");
+    create_serve_mbtiles(&bare, false);
+    let server = TileServer::mbtiles(&bare, &ServeOptions::default()).expect("server");
+    let style = server.style_json(ORIGIN).expect("style");
+    assert_eq!(
+        layer_ids(&style),
+        vec!["background", "roads-fill", "roads-line", "roads-circle"]
+    );
+
+    let page = server.handle("/", ORIGIN);
+    assert_eq!(page.content_type, "text/html; charset=utf-8");
+    assert!(
+        String::from_utf8(page.body)
+            .unwrap()
+            .contains("/style.json")
+    );
+}
+
+#[test]
+fn serve_style_points_the_chosen_source_at_local_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_serve_mbtiles(&input, true);
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+            "version": 8,
+            "sources": {
+                "base": { "type": "vector", "url": "https://example.com/base.json" },
+                "extra": { "type": "vector", "url": "https://example.com/extra.json" }
+            },
+            "layers": [
+                { "id": "roads", "type": "line", "source": "base", "source-layer": "roads" }
+            ]
+        }"#,
+    )
+    .expect("write style");
+
+    let options = ServeOptions {
+        style: Some(style_path.clone()),
+        source: None,
+    };
+    let err = TileServer::mbtiles(&input, &options)
+        .err()
+        .expect("ambiguous source");
+    assert!(err.to_string().contains("choose one with --source"));
+
+    let options = ServeOptions {
+        style: Some(style_path),
+        source: Some("base".to_string()),
+    };
+    let server = TileServer::mbtiles(&input, &options).expect("server");
+    let style = server.style_json(ORIGIN).expect("style");
+    assert_eq!(
+        style["sources"]["base"],
+        serde_json::json!({
+            "type": "vector",
+            "tiles": ["http://127.0.0.1:8080/tiles/{z}/{x}/{y}.pbf"],
+        })
+    );
+    assert_eq!(
+        style["sources"]["extra"]["url"],
+        "https://example.com/extra.json"
+    );
+    assert_eq!(layer_ids(&style), vec!["roads"]);
+}
+
+#[test]
+fn serve_answers_http_requests_over_tcp() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_serve_mbtiles(&input, true);
+    let server = TileServer::mbtiles(&input, &ServeOptions::default()).expect("server");
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let addr = listener.local_addr().expect("addr");
+    thread::spawn(move || server.serve(listener));
+
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    write!(
+        stream,
+        "GET /tiles/1/0/0.pbf HTTP/1.1\r\nHost: tiles.test\r\nConnection: close\r\n\r\n"
+    )
+    .expect("request");
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).expect("response");
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .expect("header end");
+    let head = String::from_utf8_lossy(&response[..split]);
+    assert!(head.starts_with("HTTP/1.1 200 OK"), "{head}");
+    assert!(head.contains("Content-Encoding: gzip"), "{head}");
+    assert_eq!(&response[split + 4..], gzip(&roads_tile()).as_slice());
+
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    write!(
+        stream,
+        "GET /style.json HTTP/1.1\r\nHost: tiles.test\r\nConnection: close\r\n\r\n"
+    )
+    .expect("request");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("response");
+    assert!(response.contains("http://tiles.test/tiles/{z}/{x}/{y}.pbf"));
+
+    for (request, status) in [("HEAD", "200 OK"), ("POST", "405 Method Not Allowed")] {
+        let mut stream = TcpStream::connect(addr).expect("connect");
+        write!(
+            stream,
+            "{request} /tiles/1/0/0.pbf HTTP/1.1\r\nHost: tiles.test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )
+        .expect("request");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("response");
+        assert!(
+            response.starts_with(&format!("HTTP/1.1 {status}")),
+            "{response}"
+        );
+        if request == "HEAD" {
+            assert!(response.ends_with("\r\n\r\n"), "{response}");
+        }
+    }
+}
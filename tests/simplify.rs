@@ -2,9 +2,14 @@ use std::path::Path;
 
 use mvt::{GeomEncoder, GeomType, Tile};
 use mvt_reader::Reader;
-use vt_optimizer::mbtiles::{InspectOptions, TileCoord, simplify_mbtiles_tile};
+use vt_optimizer::error::VtError;
+use vt_optimizer::mbtiles::{
+    DEFAULT_SIMPLIFY_BUFFER, InspectOptions, SimplifyOptions, SimplifyStats, TileCoord,
+    fix_polygon_winding, is_geometry_valid, simplify_geometry, simplify_mbtiles_all,
+    simplify_mbtiles_batch, simplify_mbtiles_tile, simplify_tile_payload,
+};
 use vt_optimizer::pmtiles::{
-    inspect_pmtiles_with_options, mbtiles_to_pmtiles, simplify_pmtiles_tile,
+    inspect_pmtiles_with_options, mbtiles_to_pmtiles, simplify_pmtiles_batch, simplify_pmtiles_tile,
 };
 
 fn create_layer_tile() -> Vec<u8> {
@@ -116,7 +121,16 @@ fn simplify_mbtiles_tile_filters_layers() {
         x: 0,
         y: 0,
     };
-    simplify_mbtiles_tile(&input, &output, coord, &["roads".to_string()], None).expect("simplify");
+    simplify_mbtiles_tile(
+        &input,
+        &output,
+        coord,
+        &["roads".to_string()],
+        None,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect("simplify");
 
     let conn = rusqlite::Connection::open(&output).expect("open output");
     let data: Vec<u8> = conn
@@ -132,6 +146,67 @@ fn simplify_mbtiles_tile_filters_layers() {
     assert_eq!(layers[0].name, "roads");
 }
 
+#[test]
+fn simplify_mbtiles_tile_reports_typed_errors() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_layer_mbtiles(&input);
+
+    let missing = TileCoord {
+        zoom: 1,
+        x: 1,
+        y: 0,
+    };
+    let err = simplify_mbtiles_tile(
+        &input,
+        &dir.path().join("missing.mbtiles"),
+        missing,
+        &[],
+        None,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect_err("missing tile");
+    assert!(
+        matches!(err, VtError::TileNotFound(coord) if coord == missing),
+        "{err:?}"
+    );
+
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 1, 0, ?1)",
+        (vec![0xffu8; 8],),
+    )
+    .expect("tile insert");
+    let err = simplify_mbtiles_tile(
+        &input,
+        &dir.path().join("broken.mbtiles"),
+        missing,
+        &[],
+        None,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect_err("broken tile");
+    assert!(
+        matches!(err, VtError::DecodeTile { coord, .. } if coord == missing),
+        "{err:?}"
+    );
+    assert!(err.to_string().starts_with("failed to decode tile 1/1/0"));
+
+    let err = simplify_mbtiles_tile(
+        &dir.path().join("input.pmtiles"),
+        &dir.path().join("other.mbtiles"),
+        missing,
+        &[],
+        None,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect_err("wrong extension");
+    assert!(matches!(err, VtError::NotMbtiles { .. }), "{err:?}");
+}
+
 #[test]
 fn simplify_mbtiles_tile_keeps_all_layers_when_empty() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -144,7 +219,16 @@ fn simplify_mbtiles_tile_keeps_all_layers_when_empty() {
         x: 0,
         y: 0,
     };
-    simplify_mbtiles_tile(&input, &output, coord, &[], None).expect("simplify");
+    simplify_mbtiles_tile(
+        &input,
+        &output,
+        coord,
+        &[],
+        None,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect("simplify");
 
     let conn = rusqlite::Connection::open(&output).expect("open output");
     let data: Vec<u8> = conn
@@ -174,7 +258,16 @@ fn simplify_mbtiles_tile_applies_tolerance() {
         x: 0,
         y: 0,
     };
-    simplify_mbtiles_tile(&input, &output, coord, &[], Some(0.5)).expect("simplify");
+    simplify_mbtiles_tile(
+        &input,
+        &output,
+        coord,
+        &[],
+        Some(0.5),
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect("simplify");
 
     let conn = rusqlite::Connection::open(&output).expect("open output");
     let data: Vec<u8> = conn
@@ -208,9 +301,453 @@ fn simplify_pmtiles_tile_outputs_single_tile() {
         x: 0,
         y: 0,
     };
-    simplify_pmtiles_tile(&pmtiles, &output, coord, &[], None).expect("simplify");
+    simplify_pmtiles_tile(
+        &pmtiles,
+        &output,
+        coord,
+        &[],
+        None,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect("simplify");
 
     let report =
         inspect_pmtiles_with_options(&output, &InspectOptions::default()).expect("inspect");
     assert_eq!(report.overall.tile_count, 1);
 }
+
+fn create_mixed_zoom_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        INSERT INTO metadata (name, value) VALUES ('name', 'mixed');
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (create_line_tile(),),
+    )
+    .expect("tile z0");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 0, 0, ?1)",
+        (create_line_tile(),),
+    )
+    .expect("tile z1");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 1, 0, ?1)",
+        (create_layer_tile(),),
+    )
+    .expect("tile z1 layers");
+}
+
+fn batch_options(zoom: Option<u8>) -> SimplifyOptions {
+    SimplifyOptions {
+        layers: vec!["roads".to_string()],
+        tolerance: Some(0.5),
+        buffer: DEFAULT_SIMPLIFY_BUFFER,
+        preserve_topology: false,
+        zoom,
+        over_limit_only: false,
+        max_tile_bytes: 0,
+        threads: 2,
+        readers: 1,
+        io_batch: 16,
+    }
+}
+
+#[test]
+fn simplify_mbtiles_batch_rewrites_selected_zoom_and_copies_rest() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_mixed_zoom_mbtiles(&input);
+
+    let stats = simplify_mbtiles_batch(&input, &output, &batch_options(Some(1))).expect("batch");
+    assert_eq!(stats.tiles_read, 3);
+    assert_eq!(stats.tiles_simplified(), 2);
+    assert_eq!(stats.by_zoom.keys().copied().collect::<Vec<_>>(), vec![1]);
+    let totals = stats.totals();
+    assert_eq!(totals.feature_count, 2);
+    assert!(totals.vertices_after < totals.vertices_before);
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM tiles", [], |row| row.get(0))
+        .expect("count");
+    assert_eq!(count, 3);
+    let name: String = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE name = 'name'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("metadata");
+    assert_eq!(name, "mixed");
+    let untouched: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 0",
+            [],
+            |row| row.get(0),
+        )
+        .expect("z0 tile");
+    assert_eq!(untouched, create_line_tile());
+
+    let layered: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 1",
+            [],
+            |row| row.get(0),
+        )
+        .expect("z1 layered tile");
+    let reader = Reader::new(layered).expect("decode");
+    let names: Vec<_> = reader
+        .get_layer_metadata()
+        .expect("layers")
+        .into_iter()
+        .map(|layer| layer.name)
+        .collect();
+    assert!(names.contains(&"roads".to_string()));
+    assert!(names.contains(&"buildings".to_string()));
+}
+
+#[test]
+fn simplify_mbtiles_batch_over_limit_only_skips_small_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_mixed_zoom_mbtiles(&input);
+
+    let mut options = batch_options(None);
+    options.over_limit_only = true;
+    options.max_tile_bytes = 1_000_000;
+    let stats = simplify_mbtiles_batch(&input, &output, &options).expect("batch");
+    assert_eq!(stats.tiles_read, 3);
+    assert_eq!(stats.tiles_simplified(), 0);
+
+    options.max_tile_bytes = 0;
+    let output_all = dir.path().join("output-all.mbtiles");
+    let stats = simplify_mbtiles_batch(&input, &output_all, &options).expect("batch");
+    assert_eq!(stats.tiles_simplified(), 3);
+}
+
+#[test]
+fn simplify_mbtiles_all_rewrites_every_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_mixed_zoom_mbtiles(&input);
+
+    // The zoom and size selectors are ignored.
+    let mut options = batch_options(Some(1));
+    options.over_limit_only = true;
+    options.max_tile_bytes = 1_000_000;
+    let stats = simplify_mbtiles_all(&input, &output, &options).expect("simplify all");
+    assert_eq!(stats.tiles_read, 3);
+    assert_eq!(stats.tiles_simplified(), 3);
+    assert_eq!(
+        stats.by_zoom.keys().copied().collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    let totals = stats.totals();
+    assert!(totals.vertices_after < totals.vertices_before);
+}
+
+#[test]
+fn simplify_pmtiles_batch_aggregates_per_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("input.pmtiles");
+    let output = dir.path().join("output.pmtiles");
+    create_mixed_zoom_mbtiles(&mbtiles);
+    mbtiles_to_pmtiles(&mbtiles, &pmtiles).expect("to pmtiles");
+
+    let stats = simplify_pmtiles_batch(&pmtiles, &output, &batch_options(None)).expect("batch");
+    assert_eq!(stats.tiles_read, 3);
+    assert_eq!(stats.by_zoom.get(&0).map(|zoom| zoom.tiles), Some(1));
+    assert_eq!(stats.by_zoom.get(&1).map(|zoom| zoom.tiles), Some(2));
+
+    let report =
+        inspect_pmtiles_with_options(&output, &InspectOptions::default()).expect("inspect");
+    assert_eq!(report.overall.tile_count, 3);
+}
+
+fn polygon(points: Vec<(f32, f32)>) -> geo_types::Geometry<f32> {
+    geo_types::Geometry::Polygon(geo_types::Polygon::new(
+        geo_types::LineString::from(points),
+        vec![],
+    ))
+}
+
+#[test]
+fn is_geometry_valid_checks_rings() {
+    let square = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+    assert!(is_geometry_valid(&polygon(square.clone())));
+    let reversed = square.into_iter().rev().collect::<Vec<_>>();
+    assert!(!is_geometry_valid(&polygon(reversed)));
+    let bowtie = vec![(0.0, 0.0), (10.0, 10.0), (10.0, 0.0), (0.0, 10.0)];
+    assert!(!is_geometry_valid(&polygon(bowtie)));
+    let sliver = vec![(0.0, 0.0), (10.0, 0.0)];
+    assert!(!is_geometry_valid(&polygon(sliver)));
+}
+
+#[test]
+fn fix_polygon_winding_regroups_reversed_rings() {
+    let square = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+    assert!(fix_polygon_winding(&polygon(square.clone())).is_none());
+    assert!(fix_polygon_winding(&geo_types::Geometry::Point((1.0, 2.0).into())).is_none());
+
+    // Written with the opposite convention, the hole decodes as a second exterior.
+    let outer = square.iter().rev().copied().collect::<Vec<_>>();
+    let hole = vec![(2.0, 2.0), (8.0, 2.0), (8.0, 8.0), (2.0, 8.0)];
+    let decoded = geo_types::Geometry::MultiPolygon(geo_types::MultiPolygon(vec![
+        geo_types::Polygon::new(geo_types::LineString::from(outer), vec![]),
+        geo_types::Polygon::new(geo_types::LineString::from(hole), vec![]),
+    ]));
+    assert!(!is_geometry_valid(&decoded));
+
+    let (fixed, corrected) = fix_polygon_winding(&decoded).expect("fixed");
+    assert_eq!(corrected, 2);
+    assert!(is_geometry_valid(&fixed));
+    let geo_types::Geometry::MultiPolygon(polygons) = fixed else {
+        panic!("expected multipolygon");
+    };
+    assert_eq!(polygons.0.len(), 1);
+    assert_eq!(polygons.0[0].interiors().len(), 1);
+}
+
+fn star_ring(cx: f32, cy: f32, outer: f32, inner: f32) -> Vec<(f32, f32)> {
+    let mut points = (0..10)
+        .map(|i| {
+            let radius = if i % 2 == 0 { outer } else { inner };
+            let angle = std::f32::consts::PI * i as f32 / 5.0;
+            (cx + radius * angle.cos(), cy + radius * angle.sin())
+        })
+        .collect::<Vec<_>>();
+    points.push(points[0]);
+    points
+}
+
+#[test]
+fn simplify_geometry_drops_collapsed_star_polygon() {
+    let star = polygon(star_ring(200.0, 200.0, 100.0, 40.0));
+    let mut stats = SimplifyStats::default();
+
+    assert!(
+        simplify_geometry(
+            &star,
+            500.0,
+            4096,
+            DEFAULT_SIMPLIFY_BUFFER,
+            false,
+            &mut stats
+        )
+        .is_none()
+    );
+    assert_eq!(stats.dropped_polygons, 1);
+
+    let mut stats = SimplifyStats::default();
+    let kept = simplify_geometry(
+        &star,
+        30.0,
+        4096,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+        &mut stats,
+    )
+    .expect("star kept");
+    assert_eq!(stats.dropped_polygons, 0);
+    assert!(is_geometry_valid(&kept));
+}
+
+#[test]
+fn simplify_geometry_drops_collapsed_holes_only() {
+    let exterior = geo_types::LineString::from(vec![
+        (0.0, 0.0),
+        (1000.0, 0.0),
+        (1000.0, 1000.0),
+        (0.0, 1000.0),
+        (0.0, 0.0),
+    ]);
+    let mut hole = star_ring(500.0, 500.0, 3.0, 1.0);
+    hole.reverse();
+    let geometry = geo_types::Geometry::Polygon(geo_types::Polygon::new(
+        exterior,
+        vec![geo_types::LineString::from(hole)],
+    ));
+    let mut stats = SimplifyStats::default();
+
+    let simplified = simplify_geometry(
+        &geometry,
+        20.0,
+        4096,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+        &mut stats,
+    )
+    .expect("kept");
+    assert_eq!(stats.dropped_holes, 1);
+    assert_eq!(stats.dropped_polygons, 0);
+    match simplified {
+        geo_types::Geometry::Polygon(polygon) => assert!(polygon.interiors().is_empty()),
+        other => panic!("expected polygon, got {other:?}"),
+    }
+}
+
+#[test]
+fn simplify_geometry_preserve_topology_restores_self_intersecting_ring() {
+    // The bump on the top edge is within tolerance, but flattening it would
+    // cut through the spike that rises into it from the bottom edge.
+    let ring = vec![
+        (0.0, 0.0),
+        (48.0, 0.0),
+        (50.0, 103.0),
+        (52.0, 0.0),
+        (100.0, 0.0),
+        (100.0, 100.0),
+        (60.0, 100.0),
+        (50.0, 106.0),
+        (40.0, 100.0),
+        (0.0, 100.0),
+        (0.0, 0.0),
+    ];
+    let geometry = polygon(ring.clone());
+
+    assert!(is_geometry_valid(&geometry));
+
+    let mut stats = SimplifyStats::default();
+    let simplified = simplify_geometry(
+        &geometry,
+        8.0,
+        4096,
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+        &mut stats,
+    )
+    .expect("kept");
+    assert!(!is_geometry_valid(&simplified));
+
+    let mut stats = SimplifyStats::default();
+    let preserved = simplify_geometry(
+        &geometry,
+        8.0,
+        4096,
+        DEFAULT_SIMPLIFY_BUFFER,
+        true,
+        &mut stats,
+    )
+    .expect("kept");
+    assert_eq!(stats.restored_rings, 1);
+    assert_eq!(preserved, geometry);
+}
+
+/// A zigzag line with peaks of increasing height, scaled down by `divisor`.
+fn create_zigzag_tile(extent: u32, divisor: f64) -> Vec<u8> {
+    let mut tile = Tile::new(extent);
+    let layer = tile.create_layer("roads");
+    let mut encoder = GeomEncoder::new(GeomType::Linestring);
+    for i in 0..40 {
+        let peak = if i % 2 == 0 {
+            0.0
+        } else {
+            8.0 * (i / 2 + 1) as f64
+        };
+        encoder = encoder
+            .point((i * 96) as f64 / divisor, (1024.0 + peak) / divisor)
+            .expect("point");
+    }
+    let layer = layer
+        .into_feature(encoder.encode().expect("encode"))
+        .into_layer();
+    tile.add_layer(layer).expect("add layer");
+    tile.to_bytes().expect("tile bytes")
+}
+
+#[test]
+fn simplify_tolerance_scales_with_layer_extent() {
+    let keep = std::collections::HashSet::new();
+    let (_, full) = simplify_tile_payload(
+        &create_zigzag_tile(4096, 1.0),
+        &keep,
+        Some(40.0),
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect("simplify 4096");
+    let (_, small) = simplify_tile_payload(
+        &create_zigzag_tile(512, 8.0),
+        &keep,
+        Some(40.0),
+        DEFAULT_SIMPLIFY_BUFFER,
+        false,
+    )
+    .expect("simplify 512");
+
+    assert_eq!(full.vertices_before, 40);
+    assert_eq!(small.vertices_before, 40);
+    assert!(full.vertices_after > 2 && full.vertices_after < 40);
+    assert_eq!(small.vertices_after, full.vertices_after);
+}
+
+#[test]
+fn simplify_geometry_clamps_to_extent_plus_buffer() {
+    let line = geo_types::Geometry::LineString(geo_types::LineString::from(vec![
+        (-1000.0, 100.0),
+        (200.0, 100.0),
+        (300.0, 600.0),
+        (900.0, 100.0),
+    ]));
+    let mut stats = SimplifyStats::default();
+
+    // Extent 512 scales the 80-unit buffer to 10.
+    let simplified = simplify_geometry(&line, 1.0, 512, 80, false, &mut stats).expect("kept");
+    let geo_types::Geometry::LineString(simplified) = simplified else {
+        panic!("expected linestring");
+    };
+    assert_eq!(
+        simplified.0,
+        vec![
+            geo_types::coord! { x: -10.0, y: 100.0 },
+            geo_types::coord! { x: 200.0, y: 100.0 },
+            geo_types::coord! { x: 300.0, y: 522.0 },
+            geo_types::coord! { x: 522.0, y: 100.0 },
+        ]
+    );
+}
+
+#[test]
+fn simplify_mbtiles_batch_rejects_raster_tileset() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("raster.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (b"RIFF\0\0\0\0WEBPVP8 ".to_vec(),),
+    )
+    .expect("tile");
+    drop(conn);
+
+    let err = simplify_mbtiles_batch(&input, &output, &batch_options(None))
+        .expect_err("raster input must be rejected");
+    assert!(
+        err.to_string().contains("raster tileset (webp)"),
+        "unexpected error: {err}"
+    );
+    assert!(!output.exists());
+}
@@ -1,6 +1,8 @@
 use std::fs;
 
-use vt_optimizer::style::read_style;
+use vt_optimizer::style::{
+    FilterResult, FilterSupport, read_style, read_style_for_source, read_styles,
+};
 
 #[test]
 fn style_visibility_checks_zoom_and_paint() {
@@ -405,3 +407,476 @@ fn style_filter_supports_legacy_not_syntax() {
         vt_optimizer::style::FilterResult::True
     );
 }
+
+#[test]
+fn style_check_reports_unknown_operators_and_zoom_coverage() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "background", "type": "background" },
+    { "id": "roads", "type": "line", "source": "osm", "source-layer": "roads", "filter": ["all", ["==", "class", "primary"], [">=", "rank", 3], ["any", ["within", { "type": "Polygon", "coordinates": [] }], ["<", "rank", 9]]] },
+    { "id": "pois", "type": "symbol", "source": "osm", "source-layer": "pois", "minzoom": 12, "maxzoom": 15, "filter": ["in", "kind", "a", "b"] },
+    { "id": "water", "type": "fill", "source": "osm", "source-layer": "water", "filter": ["==", ["length", ["get", "name"]], 3] },
+    { "id": "hidden", "type": "fill", "source": "osm", "source-layer": "hidden", "layout": { "visibility": "none" } }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    let report = style.check(16);
+
+    let layers = report
+        .layers
+        .iter()
+        .map(|layer| (layer.id.as_str(), layer.has_filter, &layer.filter))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        layers,
+        vec![
+            (
+                "roads",
+                true,
+                &FilterSupport {
+                    supported: false,
+                    unknown_ops: vec!["within".to_string()],
+                }
+            ),
+            (
+                "pois",
+                true,
+                &FilterSupport {
+                    supported: true,
+                    unknown_ops: Vec::new(),
+                }
+            ),
+            (
+                "water",
+                true,
+                &FilterSupport {
+                    supported: false,
+                    unknown_ops: vec!["length".to_string()],
+                }
+            ),
+            (
+                "hidden",
+                false,
+                &FilterSupport {
+                    supported: true,
+                    unknown_ops: Vec::new(),
+                }
+            ),
+        ]
+    );
+    assert_eq!(report.unsupported_layers(), 2);
+
+    let coverage = report
+        .source_layers
+        .iter()
+        .map(|item| (item.source_layer.as_str(), item.kept_zooms.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        coverage,
+        vec![
+            ("hidden", Vec::new()),
+            ("pois", vec![12, 13, 14]),
+            ("roads", (0..=16).collect()),
+            ("water", (0..=16).collect()),
+        ]
+    );
+}
+
+fn feature_with(props: &[(&str, mvt_reader::feature::Value)]) -> mvt_reader::feature::Feature {
+    mvt_reader::feature::Feature {
+        geometry: geo_types::Geometry::Point(geo_types::Point::new(0.0, 0.0)),
+        id: None,
+        properties: Some(
+            props
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn style_filter_evaluates_expression_operators() {
+    use mvt_reader::feature::Value as V;
+    use vt_optimizer::style::FilterResult::{False, True, Unknown};
+
+    let filters = [
+        ("eq", r#"["==", ["get", "class"], "primary"]"#),
+        ("neq", r#"["!=", ["get", "class"], "primary"]"#),
+        ("eq_number", r#"["==", ["get", "height"], 10]"#),
+        ("lt", r#"["<", ["get", "rank"], 5]"#),
+        ("le", r#"["<=", ["get", "rank"], 5]"#),
+        ("gt", r#"[">", ["get", "rank"], 5]"#),
+        ("ge", r#"[">=", "rank", 5]"#),
+        ("zoom", r#"["<", ["zoom"], 8]"#),
+        ("in", r#"["in", ["get", "class"], ["literal", ["a", "b"]]]"#),
+        ("not_in", r#"["!in", "class", "a", "b"]"#),
+        ("in_substring", r#"["in", "foo", ["get", "name"]]"#),
+        (
+            "match",
+            r#"["match", ["get", "class"], ["a", "b"], true, false]"#,
+        ),
+        (
+            "all",
+            r#"["all", [">=", ["zoom"], 10], ["!", ["has", "tunnel"]]]"#,
+        ),
+        (
+            "any",
+            r#"["any", ["==", ["get", "class"], "a"], ["within", {}]]"#,
+        ),
+        ("has", r#"["has", "name"]"#),
+        ("not_has", r#"["!has", "name"]"#),
+        (
+            "unknown_operand",
+            r#"[">", ["length", ["get", "name"]], 3]"#,
+        ),
+    ];
+    let layers = filters
+        .iter()
+        .map(|(id, filter)| {
+            format!(
+                r#"{{ "id": "{id}", "type": "line", "source": "osm", "source-layer": "{id}", "filter": {filter} }}"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        format!(r#"{{ "version": 8, "sources": {{ "osm": {{ "type": "vector" }} }}, "layers": [{layers}] }}"#),
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+
+    let class = |value: &str| vec![("class", V::String(value.to_string()))];
+    type Case<'a> = (&'a str, u8, Vec<(&'a str, V)>, FilterResult);
+    let cases: Vec<Case> = vec![
+        ("eq", 10, class("primary"), True),
+        ("eq", 10, class("secondary"), False),
+        ("eq", 10, Vec::new(), False),
+        ("neq", 10, class("secondary"), True),
+        ("neq", 10, Vec::new(), True),
+        (
+            "eq_number",
+            10,
+            vec![("height", V::String("10".to_string()))],
+            True,
+        ),
+        ("eq_number", 10, vec![("height", V::Double(10.0))], True),
+        (
+            "eq_number",
+            10,
+            vec![("height", V::String("tall".to_string()))],
+            False,
+        ),
+        ("lt", 10, vec![("rank", V::Int(3))], True),
+        ("lt", 10, vec![("rank", V::String("3".to_string()))], True),
+        ("lt", 10, vec![("rank", V::UInt(5))], False),
+        ("lt", 10, Vec::new(), False),
+        ("le", 10, vec![("rank", V::UInt(5))], True),
+        ("le", 10, vec![("rank", V::Float(5.5))], False),
+        ("gt", 10, vec![("rank", V::SInt(6))], True),
+        ("gt", 10, vec![("rank", V::UInt(5))], False),
+        ("gt", 10, vec![("rank", V::Bool(true))], False),
+        ("ge", 10, vec![("rank", V::UInt(5))], True),
+        ("ge", 10, vec![("rank", V::Int(4))], False),
+        ("zoom", 7, Vec::new(), True),
+        ("zoom", 8, Vec::new(), False),
+        ("in", 10, class("b"), True),
+        ("in", 10, class("c"), False),
+        ("not_in", 10, class("c"), True),
+        ("not_in", 10, class("a"), False),
+        (
+            "in_substring",
+            10,
+            vec![("name", V::String("foobar".to_string()))],
+            Unknown,
+        ),
+        ("match", 10, class("a"), True),
+        ("match", 10, class("c"), False),
+        ("all", 10, Vec::new(), True),
+        ("all", 9, Vec::new(), False),
+        ("all", 10, vec![("tunnel", V::Bool(true))], False),
+        ("any", 10, class("a"), True),
+        ("any", 10, class("b"), Unknown),
+        ("has", 10, vec![("name", V::String("x".to_string()))], True),
+        ("has", 10, Vec::new(), False),
+        ("not_has", 10, Vec::new(), True),
+        (
+            "unknown_operand",
+            10,
+            vec![("name", V::String("long".to_string()))],
+            Unknown,
+        ),
+    ];
+    for (layer, zoom, props, expected) in cases {
+        let mut unknown = 0usize;
+        assert_eq!(
+            style.should_keep_feature(layer, zoom, &feature_with(&props), &mut unknown),
+            expected,
+            "{layer} at z{zoom} with {props:?}"
+        );
+    }
+}
+
+#[test]
+fn style_visibility_keeps_layers_with_fractional_zoom_bounds() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "poi", "type": "symbol", "source": "osm", "source-layer": "poi", "minzoom": 13.5 },
+    { "id": "roads", "type": "line", "source": "osm", "source-layer": "roads", "maxzoom": 14.5 },
+    { "id": "water", "type": "fill", "source": "osm", "source-layer": "water", "minzoom": 2, "maxzoom": 5 },
+    { "id": "housenumbers", "type": "symbol", "source": "osm", "source-layer": "housenumbers", "minzoom": 16 }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    assert!(!style.is_layer_visible_on_zoom("poi", 12));
+    assert!(style.is_layer_visible_on_zoom("poi", 13));
+    assert!(style.is_layer_visible_on_zoom("roads", 14));
+    assert!(!style.is_layer_visible_on_zoom("roads", 15));
+    assert!(!style.is_layer_visible_on_zoom("water", 1));
+    assert!(style.is_layer_visible_on_zoom("water", 4));
+    assert!(!style.is_layer_visible_on_zoom("water", 5));
+    assert!(!style.is_layer_visible_on_zoom("housenumbers", 14));
+
+    let style = style.with_tileset_max_zoom(Some(14));
+    assert!(style.is_layer_visible_on_zoom("housenumbers", 14));
+    assert!(!style.is_layer_visible_on_zoom("housenumbers", 13));
+    assert!(!style.is_layer_visible_on_zoom("roads", 15));
+}
+
+#[test]
+fn style_covers_zoom_is_union_of_layer_ranges() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "water", "type": "fill", "source": "osm", "source-layer": "water", "minzoom": 2, "maxzoom": 5 },
+    { "id": "poi", "type": "symbol", "source": "osm", "source-layer": "poi", "minzoom": 8, "maxzoom": 10 },
+    { "id": "hidden", "type": "line", "source": "osm", "source-layer": "roads", "layout": { "visibility": "none" } }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    let covered = (0..=12)
+        .filter(|zoom| style.covers_zoom(*zoom))
+        .collect::<Vec<_>>();
+    assert_eq!(covered, vec![2, 3, 4, 8, 9]);
+
+    let style = style.with_tileset_max_zoom(Some(1));
+    assert!(style.covers_zoom(1));
+}
+
+#[test]
+fn styles_union_keeps_layers_and_features_any_style_needs() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let light = dir.path().join("light.json");
+    let dark = dir.path().join("dark.json");
+    fs::write(
+        &light,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "roads", "type": "line", "source": "osm", "source-layer": "roads", "filter": ["==", "class", "primary"] },
+    { "id": "water", "type": "fill", "source": "osm", "source-layer": "water", "minzoom": 5 }
+  ]
+}"#,
+    )
+    .expect("write light");
+    fs::write(
+        &dark,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "roads", "type": "line", "source": "osm", "source-layer": "roads", "filter": ["==", "class", "secondary"] },
+    { "id": "water", "type": "fill", "source": "osm", "source-layer": "water", "maxzoom": 3 },
+    { "id": "poi", "type": "symbol", "source": "osm", "source-layer": "poi" }
+  ]
+}"#,
+    )
+    .expect("write dark");
+
+    let style = read_styles(&[light.clone(), dark.clone()], None).expect("read styles");
+    assert_eq!(style.source_layers().len(), 3);
+    assert!(style.is_layer_visible_on_zoom("water", 2));
+    assert!(!style.is_layer_visible_on_zoom("water", 4));
+    assert!(style.is_layer_visible_on_zoom("water", 6));
+
+    let feature = |class: &str| mvt_reader::feature::Feature {
+        geometry: geo_types::Geometry::Point(geo_types::Point::new(0.0, 0.0)),
+        id: None,
+        properties: Some(
+            [(
+                "class".to_string(),
+                mvt_reader::feature::Value::String(class.to_string()),
+            )]
+            .into_iter()
+            .collect(),
+        ),
+    };
+    let mut unknown = 0usize;
+    for (class, expected) in [
+        ("primary", FilterResult::True),
+        ("secondary", FilterResult::True),
+        ("service", FilterResult::False),
+    ] {
+        assert_eq!(
+            style.should_keep_feature("roads", 3, &feature(class), &mut unknown),
+            expected,
+            "class {class}"
+        );
+    }
+
+    let retained = style.retaining_styles(22);
+    let light_name = light.display().to_string();
+    let dark_name = dark.display().to_string();
+    assert_eq!(
+        retained["roads"],
+        vec![light_name.clone(), dark_name.clone()]
+    );
+    assert_eq!(retained["poi"], vec![dark_name]);
+    assert_eq!(style.style_names().len(), 2);
+    assert_eq!(style.style_names()[0], light_name);
+}
+
+#[test]
+fn style_merge_unions_zoom_ranges_of_shared_source_layers() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let base = dir.path().join("base.json");
+    let labels = dir.path().join("labels.json");
+    fs::write(
+        &base,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "roads", "type": "line", "source": "osm", "source-layer": "roads", "minzoom": 5, "maxzoom": 10 }
+  ]
+}"#,
+    )
+    .expect("write base");
+    fs::write(
+        &labels,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "road-labels", "type": "symbol", "source": "osm", "source-layer": "roads", "minzoom": 8, "maxzoom": 14 },
+    { "id": "places", "type": "symbol", "source": "osm", "source-layer": "places", "minzoom": 3, "maxzoom": 4 }
+  ]
+}"#,
+    )
+    .expect("write labels");
+
+    let mut style = read_style(&base).expect("read base");
+    style.merge(read_style(&labels).expect("read labels"));
+    let zooms = style.source_layer_zooms(16);
+    assert_eq!(zooms["roads"], (5..14).collect::<Vec<u8>>());
+    assert_eq!(zooms["places"], vec![3]);
+    assert_eq!(style.style_names().len(), 2);
+    assert_eq!(style.sources(), ["osm".to_string()]);
+}
+
+#[test]
+fn style_source_selects_layers_of_one_vector_source() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": {
+    "basemap": { "type": "vector" },
+    "overlay": { "type": "vector" },
+    "hillshade": { "type": "raster-dem" }
+  },
+  "layers": [
+    { "id": "roads", "type": "line", "source": "basemap", "source-layer": "roads" },
+    { "id": "water", "type": "fill", "source": "basemap", "source-layer": "water" },
+    { "id": "trails", "type": "line", "source": "overlay", "source-layer": "trails", "filter": ["==", "kind", "hiking"] },
+    { "id": "trail-water", "type": "fill", "source": "overlay", "source-layer": "water", "minzoom": 10 }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let err = read_style(&style_path).expect_err("ambiguous source");
+    assert!(err.to_string().contains("basemap, overlay"), "{err}");
+    let err = read_style_for_source(&style_path, Some("hillshade")).expect_err("raster source");
+    assert!(
+        err.to_string().contains("no vector source `hillshade`"),
+        "{err}"
+    );
+
+    let overlay = read_style_for_source(&style_path, Some("overlay")).expect("overlay");
+    let mut layers = overlay.source_layers().into_iter().collect::<Vec<_>>();
+    layers.sort();
+    assert_eq!(layers, vec!["trails", "water"]);
+    assert_eq!(overlay.sources(), ["overlay".to_string()]);
+    assert!(!overlay.is_layer_visible_on_zoom("water", 5));
+    assert!(overlay.is_layer_visible_on_zoom("water", 10));
+
+    let basemap = read_style_for_source(&style_path, Some("basemap")).expect("basemap");
+    assert!(basemap.is_layer_visible_on_zoom("water", 5));
+    assert!(!basemap.source_layers().contains("trails"));
+}
+
+#[test]
+fn style_lists_referenced_property_keys() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "roads", "type": "line", "source": "osm", "source-layer": "roads", "filter": ["all", ["==", "$type", "LineString"], ["==", "class", "primary"]] },
+    { "id": "roads-label", "type": "symbol", "source": "osm", "source-layer": "roads", "layout": { "text-field": "{ref} {name}" } },
+    { "id": "buildings", "type": "fill-extrusion", "source": "osm", "source-layer": "buildings", "filter": ["has", "height"], "paint": { "fill-extrusion-height": ["get", "height"], "fill-extrusion-color": { "property": "colour", "type": "identity" } } },
+    { "id": "pois", "type": "symbol", "source": "osm", "source-layer": "pois", "layout": { "text-field": ["get", "name", ["properties"]] } },
+    { "id": "places", "type": "circle", "source": "osm", "source-layer": "places", "interactive": true }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    let keys = style.referenced_property_keys();
+    let sorted = |layer: &str| {
+        let mut keys = keys[layer].iter().cloned().collect::<Vec<_>>();
+        keys.sort();
+        keys
+    };
+    assert_eq!(sorted("roads"), vec!["class", "name", "ref"]);
+    assert_eq!(sorted("buildings"), vec!["colour", "height"]);
+    assert!(!keys.contains_key("pois"));
+    assert!(!keys.contains_key("places"));
+}
@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+
+use vt_optimizer::mbtiles::{
+    DEFAULT_GZIP_LEVEL, MbtilesFile, TileCoord, decode_tile_payload, encode_tile_payload,
+};
+use vt_optimizer::pmtiles::mbtiles_to_pmtiles;
+use vt_optimizer::source::TileSource;
+use vt_optimizer::tree::{explode_mbtiles, explode_pmtiles, implode_to_mbtiles};
+
+fn create_sample_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+        ("name", "sample"),
+    )
+    .expect("metadata");
+    let gzipped = encode_tile_payload(&[1u8; 10], true, DEFAULT_GZIP_LEVEL).expect("gzip");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (2, 1, 3, ?1)",
+        (gzipped,),
+    )
+    .expect("tile1");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (4, 5, 6, ?1)",
+        (vec![2u8; 20],),
+    )
+    .expect("tile2");
+}
+
+#[test]
+fn explode_and_implode_round_trip_mbtiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&input);
+
+    let tree = dir.path().join("tree");
+    let stats = explode_mbtiles(&input, &tree, false).expect("explode");
+    assert_eq!(stats.tiles, 2);
+    // Tree rows are XYZ: TMS row 3 at zoom 2 becomes y 0, row 6 at zoom 4 y 9
+    assert_eq!(
+        fs::read(tree.join("2/1/0.mvt")).expect("tile1"),
+        vec![1u8; 10]
+    );
+    assert_eq!(
+        fs::read(tree.join("4/5/9.mvt")).expect("tile2"),
+        vec![2u8; 20]
+    );
+    assert!(tree.join("metadata.json").exists());
+
+    let output = dir.path().join("output.mbtiles");
+    let stats = implode_to_mbtiles(&tree, &output).expect("implode");
+    assert_eq!(stats.tiles, 2);
+    assert_eq!((stats.min_zoom, stats.max_zoom), (Some(2), Some(4)));
+
+    let source = MbtilesFile::open(&output).expect("open output");
+    let metadata = source.metadata().expect("metadata");
+    assert_eq!(metadata.get("name").map(String::as_str), Some("sample"));
+    assert_eq!(metadata.get("minzoom").map(String::as_str), Some("2"));
+    assert_eq!(metadata.get("maxzoom").map(String::as_str), Some("4"));
+    let tile = source
+        .tile_data(TileCoord {
+            zoom: 4,
+            x: 5,
+            y: 6,
+        })
+        .expect("read")
+        .expect("tile");
+    assert!(tile.starts_with(&[0x1f, 0x8b]));
+    assert_eq!(decode_tile_payload(&tile).expect("decode"), vec![2u8; 20]);
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let rows = conn
+        .prepare("SELECT zoom_level, tile_column, tile_row FROM tiles ORDER BY zoom_level")
+        .expect("prepare")
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, u8>(0)?,
+                row.get::<_, u32>(1)?,
+                row.get::<_, u32>(2)?,
+            ))
+        })
+        .expect("query")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("rows");
+    assert_eq!(rows, vec![(2, 1, 3), (4, 5, 6)]);
+}
+
+#[test]
+fn explode_gzip_keeps_tiles_compressed() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&input);
+
+    let tree = dir.path().join("tree");
+    explode_mbtiles(&input, &tree, true).expect("explode");
+    let tile = fs::read(tree.join("4/5/9.mvt.gz")).expect("tile");
+    assert!(tile.starts_with(&[0x1f, 0x8b]));
+    assert_eq!(decode_tile_payload(&tile).expect("decode"), vec![2u8; 20]);
+
+    let output = dir.path().join("output.mbtiles");
+    let stats = implode_to_mbtiles(&tree, &output).expect("implode");
+    assert_eq!(stats.tiles, 2);
+}
+
+#[test]
+fn explode_pmtiles_decodes_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_sample_mbtiles(&input);
+    let pmtiles = dir.path().join("input.pmtiles");
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("convert");
+
+    let tree = dir.path().join("tree");
+    let stats = explode_pmtiles(&pmtiles, &tree, false).expect("explode");
+    assert_eq!(stats.tiles, 2);
+    // PMTiles rows are XYZ: TMS row 3 at zoom 2 becomes y 0
+    assert_eq!(
+        fs::read(tree.join("2/1/0.mvt")).expect("tile1"),
+        vec![1u8; 10]
+    );
+}